@@ -94,8 +94,8 @@ impl ClientBuilder {
 
     /// Sets whether the client should automatically trust servers. If this is not set then
     /// the client will reject the server upon first connect and the server's certificate
-    /// must be manually moved from pki's `/rejected` folder to the `/trusted` folder. If it is
-    /// set, then the server cert will automatically be stored in the `/trusted` folder.
+    /// must be manually moved from pki's `/rejected` folder to the `/trusted/certs` folder. If it is
+    /// set, then the server cert will automatically be stored in the `/trusted/certs` folder.
     pub fn trust_server_certs(mut self, trust_server_certs: bool) -> Self {
         self.config.trust_server_certs = trust_server_certs;
         self
@@ -284,6 +284,23 @@ impl ClientBuilder {
         self
     }
 
+    /// Read the operation limits from the server after connecting, and automatically
+    /// split calls to `read`, `write`, `call`, `browse`, `history_read` and
+    /// `create_monitored_items` on the session that exceed them.
+    ///
+    /// Defaults to `true`.
+    pub fn read_operation_limits(mut self, read_operation_limits: bool) -> Self {
+        self.config.performance.read_operation_limits = read_operation_limits;
+        self
+    }
+
+    /// Maximum number of requests sent at the same time when a call is split
+    /// because it exceeds the server operation limits. Defaults to 4.
+    pub fn max_concurrent_chunks(mut self, max_concurrent_chunks: usize) -> Self {
+        self.config.performance.max_concurrent_chunks = max_concurrent_chunks;
+        self
+    }
+
     /// Automatically recreate subscriptions on reconnect, by first calling
     /// [`crate::Session::transfer_subscriptions`], then attempting to recreate
     /// subscriptions if that fails.
@@ -207,6 +207,14 @@ pub(crate) struct Performance {
     /// Maximum number of monitored items per request when recreating subscriptions on session recreation.
     #[serde(default = "defaults::recreate_monitored_items_chunk")]
     pub(crate) recreate_monitored_items_chunk: usize,
+    /// Read the operation limits from the server after connecting, and split
+    /// requests that exceed them.
+    #[serde(default = "defaults::read_operation_limits")]
+    pub(crate) read_operation_limits: bool,
+    /// Maximum number of requests sent at the same time when a request is split
+    /// because it exceeds the server operation limits.
+    #[serde(default = "defaults::max_concurrent_chunks")]
+    pub(crate) max_concurrent_chunks: usize,
 }
 
 impl Default for Performance {
@@ -214,6 +222,8 @@ impl Default for Performance {
         Self {
             ignore_clock_skew: false,
             recreate_monitored_items_chunk: defaults::recreate_monitored_items_chunk(),
+            read_operation_limits: defaults::read_operation_limits(),
+            max_concurrent_chunks: defaults::max_concurrent_chunks(),
         }
     }
 }
@@ -560,6 +570,14 @@ mod defaults {
         1000
     }
 
+    pub(super) fn read_operation_limits() -> bool {
+        true
+    }
+
+    pub(super) fn max_concurrent_chunks() -> usize {
+        4
+    }
+
     pub(super) fn recreate_subscriptions() -> bool {
         true
     }
@@ -0,0 +1,553 @@
+//! This module contains a client for the certificate management functionality of a
+//! Global Discovery Server (GDS), using the pull model defined in OPC UA Part 12.
+//!
+//! The [`GdsClient`] wraps a session connected to the GDS. A typical application will:
+//!
+//!  1. Register itself with [`GdsClient::register_application`], or use an application ID
+//!     it already knows.
+//!  2. Request a certificate with [`GdsClient::request_certificate`], which signs a
+//!     certificate signing request with the existing private key, or
+//!     [`GdsClient::request_new_key_pair`], which lets the GDS generate a new key.
+//!  3. Download the trust list with [`GdsClient::update_trust_list`].
+//!
+//! The certificates and trust list are written to the [`CertificateStore`], and are used
+//! by any secure channel created after that.
+//!
+//! Note that a GDS will normally only accept these calls on an encrypted session,
+//! from a user with the necessary permissions.
+
+use std::{sync::Arc, time::Duration};
+
+use opcua_core::{sync::RwLock, trace_read_lock, trace_write_lock};
+use opcua_crypto::{CertificateStore, PrivateKey, X509};
+use opcua_types::{
+    gds::{
+        ApplicationRecordDataType, GdsMethodId, GdsObjectId, GDS_NAMESPACE_URI,
+        PRIVATE_KEY_FORMAT_PEM,
+    },
+    BinaryDecodable, BrowsePath, ByteString, CallMethodRequest, ExtensionObject, MethodId, NodeId,
+    QualifiedName, RelativePath, StatusCode, TrustListDataType, TryFromVariant, UAString, Variant,
+};
+
+use crate::{
+    session::{session_debug, session_error},
+    Session,
+};
+
+/// Number of bytes requested in each call to `Read` on the trust list file.
+const TRUST_LIST_CHUNK_SIZE: i32 = 65536;
+/// Default maximum size in bytes of a trust list read from the GDS.
+const DEFAULT_MAX_TRUST_LIST_SIZE: usize = 16 * 1024 * 1024;
+
+/// A certificate issued by the GDS.
+#[derive(Debug, Clone)]
+pub struct IssuedCertificate {
+    /// The new application instance certificate.
+    pub certificate: X509,
+    /// The private key, if the GDS generated a new key pair.
+    pub private_key: Option<PrivateKey>,
+    /// Certificates of the issuers of `certificate`, needed to build its chain.
+    pub issuer_certificates: Vec<X509>,
+}
+
+/// Client for the certificate management methods of a GDS.
+pub struct GdsClient {
+    session: Arc<Session>,
+    namespace: u16,
+    poll_interval: Duration,
+    request_timeout: Duration,
+    max_trust_list_size: usize,
+}
+
+impl GdsClient {
+    /// Create a new GDS client using `session`, which must be connected to the GDS.
+    /// This looks up the index of the GDS namespace on the server.
+    pub async fn new(session: Arc<Session>) -> Result<Self, StatusCode> {
+        let namespace = session
+            .get_namespace_index(GDS_NAMESPACE_URI)
+            .await
+            .map_err(|e| {
+                session_error!(session, "Server does not have the GDS namespace: {e}");
+                e.status()
+            })?;
+        Ok(Self {
+            session,
+            namespace,
+            poll_interval: Duration::from_secs(1),
+            request_timeout: Duration::from_secs(60),
+            max_trust_list_size: DEFAULT_MAX_TRUST_LIST_SIZE,
+        })
+    }
+
+    /// Set the interval between each call to `FinishRequest` while waiting for
+    /// a certificate request to be approved. Default is one second.
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Set how long to wait for a certificate request to be approved before giving up.
+    /// Default is one minute.
+    pub fn request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+
+    /// Set the maximum size in bytes of a trust list read from the GDS. Reading a larger
+    /// trust list fails with `BadEncodingLimitsExceeded`. Default is 16 MiB.
+    pub fn max_trust_list_size(mut self, max_trust_list_size: usize) -> Self {
+        self.max_trust_list_size = max_trust_list_size;
+        self
+    }
+
+    /// Get the session used by this client.
+    pub fn session(&self) -> &Arc<Session> {
+        &self.session
+    }
+
+    /// Get the index of the GDS namespace on the server.
+    pub fn namespace(&self) -> u16 {
+        self.namespace
+    }
+
+    async fn call(
+        &self,
+        object_id: NodeId,
+        method_id: NodeId,
+        args: Vec<Variant>,
+    ) -> Result<Vec<Variant>, StatusCode> {
+        let result = self
+            .session
+            .call_one(CallMethodRequest {
+                object_id,
+                method_id,
+                input_arguments: Some(args),
+            })
+            .await?;
+        if result.status_code.is_bad() {
+            return Err(result.status_code);
+        }
+        Ok(result.output_arguments.unwrap_or_default())
+    }
+
+    async fn call_directory(
+        &self,
+        method: GdsMethodId,
+        args: Vec<Variant>,
+    ) -> Result<Vec<Variant>, StatusCode> {
+        self.call(
+            GdsObjectId::Directory.node_id(self.namespace),
+            method.node_id(self.namespace),
+            args,
+        )
+        .await
+    }
+
+    fn output<T: TryFromVariant>(
+        &self,
+        outputs: &mut [Variant],
+        idx: usize,
+    ) -> Result<T, StatusCode> {
+        let Some(value) = outputs.get_mut(idx) else {
+            session_error!(
+                self.session,
+                "Missing output argument {idx} from GDS method"
+            );
+            return Err(StatusCode::BadUnexpectedError);
+        };
+        T::try_from_variant(std::mem::take(value)).map_err(|e| {
+            session_error!(
+                self.session,
+                "Invalid output argument {idx} from GDS method: {e}"
+            );
+            StatusCode::BadUnexpectedError
+        })
+    }
+
+    /// Register an application with the GDS, returning the application ID assigned to it.
+    ///
+    /// See OPC UA Part 12 7.9.4 for a complete description of the method.
+    pub async fn register_application(
+        &self,
+        application: ApplicationRecordDataType,
+    ) -> Result<NodeId, StatusCode> {
+        let mut outputs = self
+            .call_directory(
+                GdsMethodId::Directory_RegisterApplication,
+                vec![ExtensionObject::from_message(application).into()],
+            )
+            .await?;
+        self.output(&mut outputs, 0)
+    }
+
+    /// Start a request for a certificate signed by the GDS, using a DER encoded PKCS #10
+    /// certificate signing request. Returns the ID of the request, which is passed
+    /// to [`GdsClient::finish_request`].
+    ///
+    /// `certificate_group_id` and `certificate_type_id` may be null to use the defaults of the GDS.
+    ///
+    /// See OPC UA Part 12 7.9.7 for a complete description of the method.
+    pub async fn start_signing_request(
+        &self,
+        application_id: &NodeId,
+        certificate_group_id: &NodeId,
+        certificate_type_id: &NodeId,
+        certificate_request: ByteString,
+    ) -> Result<NodeId, StatusCode> {
+        let mut outputs = self
+            .call_directory(
+                GdsMethodId::Directory_StartSigningRequest,
+                vec![
+                    application_id.clone().into(),
+                    certificate_group_id.clone().into(),
+                    certificate_type_id.clone().into(),
+                    certificate_request.into(),
+                ],
+            )
+            .await?;
+        self.output(&mut outputs, 0)
+    }
+
+    /// Start a request for a new key pair and certificate generated by the GDS.
+    /// The private key is requested in PEM format, without a password. Returns the
+    /// ID of the request, which is passed to [`GdsClient::finish_request`].
+    ///
+    /// `certificate_group_id` and `certificate_type_id` may be null to use the defaults of the GDS.
+    ///
+    /// See OPC UA Part 12 7.9.6 for a complete description of the method.
+    pub async fn start_new_key_pair_request(
+        &self,
+        application_id: &NodeId,
+        certificate_group_id: &NodeId,
+        certificate_type_id: &NodeId,
+        subject_name: &str,
+        domain_names: Vec<UAString>,
+    ) -> Result<NodeId, StatusCode> {
+        let mut outputs = self
+            .call_directory(
+                GdsMethodId::Directory_StartNewKeyPairRequest,
+                vec![
+                    application_id.clone().into(),
+                    certificate_group_id.clone().into(),
+                    certificate_type_id.clone().into(),
+                    UAString::from(subject_name).into(),
+                    domain_names.into(),
+                    UAString::from(PRIVATE_KEY_FORMAT_PEM).into(),
+                    UAString::null().into(),
+                ],
+            )
+            .await?;
+        self.output(&mut outputs, 0)
+    }
+
+    /// Check whether the request with ID `request_id` has completed. Returns `None` if
+    /// the request has not been approved yet.
+    ///
+    /// See OPC UA Part 12 7.9.8 for a complete description of the method.
+    pub async fn finish_request(
+        &self,
+        application_id: &NodeId,
+        request_id: &NodeId,
+    ) -> Result<Option<IssuedCertificate>, StatusCode> {
+        let mut outputs = match self
+            .call_directory(
+                GdsMethodId::Directory_FinishRequest,
+                vec![application_id.clone().into(), request_id.clone().into()],
+            )
+            .await
+        {
+            Ok(r) => r,
+            Err(StatusCode::BadNothingToDo) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let certificate: ByteString = self.output(&mut outputs, 0)?;
+        let private_key: Option<ByteString> = self.output(&mut outputs, 1)?;
+        let issuer_certificates: Option<Vec<ByteString>> = self.output(&mut outputs, 2)?;
+
+        let certificate = X509::from_byte_string(&certificate).map_err(|e| {
+            session_error!(self.session, "GDS returned an invalid certificate: {e}");
+            StatusCode::BadCertificateInvalid
+        })?;
+        let private_key = match private_key {
+            Some(key) if !key.is_null_or_empty() => {
+                Some(PrivateKey::from_pem(key.as_ref()).map_err(|_| {
+                    session_error!(self.session, "GDS returned an invalid private key");
+                    StatusCode::BadDecodingError
+                })?)
+            }
+            _ => None,
+        };
+        let issuer_certificates = issuer_certificates
+            .into_iter()
+            .flatten()
+            .map(|c| {
+                X509::from_byte_string(&c).map_err(|e| {
+                    session_error!(
+                        self.session,
+                        "GDS returned an invalid issuer certificate: {e}"
+                    );
+                    StatusCode::BadCertificateInvalid
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Some(IssuedCertificate {
+            certificate,
+            private_key,
+            issuer_certificates,
+        }))
+    }
+
+    /// Call `FinishRequest` until the request completes, or the request timeout expires,
+    /// in which case this returns `BadTimeout`.
+    pub async fn wait_for_request(
+        &self,
+        application_id: &NodeId,
+        request_id: &NodeId,
+    ) -> Result<IssuedCertificate, StatusCode> {
+        let deadline = tokio::time::Instant::now() + self.request_timeout;
+        loop {
+            if let Some(issued) = self.finish_request(application_id, request_id).await? {
+                return Ok(issued);
+            }
+            if tokio::time::Instant::now() + self.poll_interval > deadline {
+                session_error!(
+                    self.session,
+                    "Certificate request {request_id} was not approved in time"
+                );
+                return Err(StatusCode::BadTimeout);
+            }
+            session_debug!(self.session, "Certificate request {request_id} is pending");
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Get the ID of the trust list object for the application in the given certificate group.
+    /// `certificate_group_id` may be null to use the default group.
+    ///
+    /// See OPC UA Part 12 7.9.10 for a complete description of the method.
+    pub async fn get_trust_list(
+        &self,
+        application_id: &NodeId,
+        certificate_group_id: &NodeId,
+    ) -> Result<NodeId, StatusCode> {
+        let mut outputs = self
+            .call_directory(
+                GdsMethodId::Directory_GetTrustList,
+                vec![
+                    application_id.clone().into(),
+                    certificate_group_id.clone().into(),
+                ],
+            )
+            .await?;
+        self.output(&mut outputs, 0)
+    }
+
+    /// Read the contents of the trust list file object `trust_list_id`.
+    pub async fn read_trust_list(
+        &self,
+        trust_list_id: &NodeId,
+    ) -> Result<TrustListDataType, StatusCode> {
+        // The methods are components of the trust list object, so look them up
+        // instead of assuming that the server accepts the method IDs from FileType.
+        let paths: Vec<_> = ["Open", "Read", "Close"]
+            .into_iter()
+            .map(|name| BrowsePath {
+                starting_node: trust_list_id.clone(),
+                relative_path: RelativePath::from(&[QualifiedName::new(0, name)][..]),
+            })
+            .collect();
+        let results = self
+            .session
+            .translate_browse_paths_to_node_ids(&paths)
+            .await?;
+        let fallback = [
+            MethodId::FileType_Open,
+            MethodId::FileType_Read,
+            MethodId::FileType_Close,
+        ];
+        let methods: Vec<NodeId> = results
+            .into_iter()
+            .zip(fallback)
+            .map(|(r, fallback)| {
+                r.targets
+                    .and_then(|t| t.into_iter().next())
+                    .map(|t| t.target_id.node_id)
+                    .unwrap_or_else(|| fallback.into())
+            })
+            .collect();
+        let Ok([open, read, close]) = <[NodeId; 3]>::try_from(methods) else {
+            session_error!(
+                self.session,
+                "Too few results from TranslateBrowsePathsToNodeIds for trust list methods"
+            );
+            return Err(StatusCode::BadUnknownResponse);
+        };
+
+        // Mode 1 is read.
+        let mut outputs = self
+            .call(trust_list_id.clone(), open, vec![1u8.into()])
+            .await?;
+        let handle: u32 = self.output(&mut outputs, 0)?;
+
+        let mut data = Vec::new();
+        let res = loop {
+            let mut outputs = match self
+                .call(
+                    trust_list_id.clone(),
+                    read.clone(),
+                    vec![handle.into(), TRUST_LIST_CHUNK_SIZE.into()],
+                )
+                .await
+            {
+                Ok(r) => r,
+                Err(e) => break Err(e),
+            };
+            let chunk: ByteString = match self.output(&mut outputs, 0) {
+                Ok(c) => c,
+                Err(e) => break Err(e),
+            };
+            // The server may return less than requested before the end of the file,
+            // so keep reading until it returns nothing.
+            if chunk.is_null_or_empty() {
+                break Ok(());
+            }
+            if data.len() + chunk.as_ref().len() > self.max_trust_list_size {
+                session_error!(
+                    self.session,
+                    "Trust list is larger than the limit of {} bytes",
+                    self.max_trust_list_size
+                );
+                break Err(StatusCode::BadEncodingLimitsExceeded);
+            }
+            data.extend_from_slice(chunk.as_ref());
+        };
+        if let Err(e) = self
+            .call(trust_list_id.clone(), close, vec![handle.into()])
+            .await
+        {
+            session_error!(self.session, "Failed to close trust list file: {e}");
+        }
+        res?;
+
+        let ctx = self.session.encoding_context().read();
+        TrustListDataType::decode(&mut data.as_slice(), &ctx.context()).map_err(|e| {
+            session_error!(self.session, "Failed to decode trust list: {e}");
+            StatusCode::BadDecodingError
+        })
+    }
+
+    /// Request a new certificate for the application, signed by the GDS, and store it in
+    /// `certificate_store` along with its issuer chain.
+    ///
+    /// The signing request is created from the certificate and private key already in the
+    /// store, so the private key never leaves the application.
+    pub async fn request_certificate(
+        &self,
+        certificate_store: &RwLock<CertificateStore>,
+        application_id: &NodeId,
+    ) -> Result<IssuedCertificate, StatusCode> {
+        let (cert, pkey) = {
+            let store = trace_read_lock!(certificate_store);
+            let cert = store.read_own_cert().map_err(|e| {
+                session_error!(self.session, "Failed to read own certificate: {e}");
+                StatusCode::BadConfigurationError
+            })?;
+            let pkey = store.read_own_pkey().map_err(|e| {
+                session_error!(self.session, "Failed to read own private key: {e}");
+                StatusCode::BadConfigurationError
+            })?;
+            (cert, pkey)
+        };
+        let request = cert.create_signing_request(&pkey, None).map_err(|e| {
+            session_error!(
+                self.session,
+                "Failed to create certificate signing request: {e}"
+            );
+            StatusCode::BadInternalError
+        })?;
+
+        let request_id = self
+            .start_signing_request(
+                application_id,
+                &NodeId::null(),
+                &NodeId::null(),
+                request.into(),
+            )
+            .await?;
+        let issued = self.wait_for_request(application_id, &request_id).await?;
+        if !issued.certificate.is_key_pair(&pkey) {
+            session_error!(
+                self.session,
+                "Certificate issued by the GDS does not match the private key"
+            );
+            return Err(StatusCode::BadCertificateInvalid);
+        }
+        self.store_issued_certificate(certificate_store, &issued)?;
+        Ok(issued)
+    }
+
+    /// Request a new key pair and certificate for the application, generated by the GDS,
+    /// and store them in `certificate_store` along with the issuer chain.
+    pub async fn request_new_key_pair(
+        &self,
+        certificate_store: &RwLock<CertificateStore>,
+        application_id: &NodeId,
+        subject_name: &str,
+        domain_names: Vec<UAString>,
+    ) -> Result<IssuedCertificate, StatusCode> {
+        let request_id = self
+            .start_new_key_pair_request(
+                application_id,
+                &NodeId::null(),
+                &NodeId::null(),
+                subject_name,
+                domain_names,
+            )
+            .await?;
+        let issued = self.wait_for_request(application_id, &request_id).await?;
+        match &issued.private_key {
+            Some(pkey) if issued.certificate.is_key_pair(pkey) => (),
+            _ => {
+                session_error!(
+                    self.session,
+                    "GDS did not return a private key matching the new certificate"
+                );
+                return Err(StatusCode::BadCertificateInvalid);
+            }
+        }
+        self.store_issued_certificate(certificate_store, &issued)?;
+        Ok(issued)
+    }
+
+    fn store_issued_certificate(
+        &self,
+        certificate_store: &RwLock<CertificateStore>,
+        issued: &IssuedCertificate,
+    ) -> Result<(), StatusCode> {
+        let store = trace_write_lock!(certificate_store);
+        let store_err = |e: String| {
+            session_error!(self.session, "Failed to store issued certificate: {e}");
+            StatusCode::BadInternalError
+        };
+        for issuer in &issued.issuer_certificates {
+            store.add_certificate(issuer, false).map_err(store_err)?;
+        }
+        store
+            .store_own_cert_and_pkey(&issued.certificate, issued.private_key.as_ref())
+            .map_err(store_err)
+    }
+
+    /// Download the trust list for the application in the default certificate group, and
+    /// replace the trust list in `certificate_store` with it.
+    pub async fn update_trust_list(
+        &self,
+        certificate_store: &RwLock<CertificateStore>,
+        application_id: &NodeId,
+    ) -> Result<TrustListDataType, StatusCode> {
+        let trust_list_id = self.get_trust_list(application_id, &NodeId::null()).await?;
+        let trust_list = self.read_trust_list(&trust_list_id).await?;
+        trace_write_lock!(certificate_store).write_trust_list(&trust_list)?;
+        Ok(trust_list)
+    }
+}
@@ -115,7 +115,9 @@ pub mod browser;
 mod builder;
 mod config;
 pub mod custom_types;
+pub mod gds;
 mod identity_token;
+pub mod node_cache;
 mod retry;
 mod session;
 pub mod transport;
@@ -124,10 +126,11 @@ pub use builder::ClientBuilder;
 pub use config::{ClientConfig, ClientEndpoint, ClientUserToken, ANONYMOUS_USER_TOKEN_ID};
 pub use retry::{ExponentialBackoff, SessionRetryPolicy};
 pub use session::{
-    Client, DataChangeCallback, DefaultRetryPolicy, EventCallback, HistoryReadAction,
-    HistoryUpdateAction, MonitoredItem, OnSubscriptionNotification, RequestRetryPolicy, Session,
-    SessionActivity, SessionBuilder, SessionConnectMode, SessionEventLoop, SessionPollResult,
-    Subscription, SubscriptionActivity, SubscriptionCallbacks, UARequest,
+    ArgumentError, CallError, CallInputs, CallOutputs, Client, DataChangeCallback,
+    DefaultRetryPolicy, EventCallback, HistoryReadAction, HistoryUpdateAction, MethodArguments,
+    MonitoredItem, OnSubscriptionNotification, OperationLimits, QueryError, RequestRetryPolicy,
+    Session, SessionActivity, SessionBuilder, SessionConnectMode, SessionEventLoop,
+    SessionPollResult, Subscription, SubscriptionActivity, SubscriptionCallbacks, UARequest,
 };
 pub use transport::AsyncSecureChannel;
 
@@ -142,9 +145,9 @@ pub mod services {
         ActivateSession, AddNodes, AddReferences, Browse, BrowseNext, Call, Cancel, CloseSession,
         CreateMonitoredItems, CreateSession, CreateSubscription, DeleteMonitoredItems, DeleteNodes,
         DeleteReferences, DeleteSubscriptions, HistoryRead, HistoryUpdate, ModifyMonitoredItems,
-        ModifySubscription, Publish, Read, RegisterNodes, Republish, SetMonitoringMode,
-        SetPublishingMode, SetTriggering, TransferSubscriptions, TranslateBrowsePaths,
-        UnregisterNodes, Write,
+        ModifySubscription, Publish, QueryFirst, QueryNext, Read, RegisterNodes, Republish,
+        SetMonitoringMode, SetPublishingMode, SetTriggering, TransferSubscriptions,
+        TranslateBrowsePaths, UnregisterNodes, Write,
     };
 }
 
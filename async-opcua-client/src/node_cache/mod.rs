@@ -0,0 +1,702 @@
+//! Contains the [NodeCache], a lazily populated client-side cache of
+//! node attributes and references.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use futures::TryStreamExt;
+use opcua_core::sync::{Mutex, RwLock};
+use opcua_types::{
+    read_u32, write_u32, AttributeId, BinaryDecodable, BinaryEncodable, BrowseDescription,
+    BrowseDirection, BrowseResultMaskFlags, ContentFilter, ContentFilterElement, DataValue, Error,
+    EventFilter, ExtensionObject, FilterOperator, ModelChangeStructureDataType,
+    MonitoredItemCreateRequest, MonitoringMode, MonitoringParameters, NodeId, ObjectId,
+    ObjectTypeId, Operand, ReadValueId, ReferenceDescription, ReferenceTypeId, RelativePath,
+    SemanticChangeStructureDataType, SimpleAttributeOperand, StatusCode, TimestampsToReturn,
+    UAString, VariableId, Variant,
+};
+use tracing::{debug, warn};
+
+use crate::{
+    browser::{BrowserConfig, NoneBrowserPolicy},
+    MonitoredItem, OnSubscriptionNotification, Session,
+};
+
+/// Version of the format written by [NodeCache::save].
+const CACHE_FILE_VERSION: u32 = 1;
+
+#[derive(Default)]
+struct CachedNode {
+    attributes: HashMap<u32, DataValue>,
+    references: Option<Vec<ReferenceDescription>>,
+}
+
+#[derive(Default)]
+struct CacheInner {
+    nodes: HashMap<NodeId, CachedNode>,
+    /// The namespace array of the server the cache was populated from, if known.
+    namespaces: Option<Vec<UAString>>,
+    /// Incremented each time the cache is invalidated, used to avoid storing
+    /// results of requests that were sent before an invalidation.
+    generation: u64,
+}
+
+impl CacheInner {
+    fn invalidate(&mut self, node_id: &NodeId) {
+        self.generation += 1;
+        let Some(node) = self.nodes.remove(node_id) else {
+            return;
+        };
+        // Nodes referencing this node have it in their list of references,
+        // which may no longer be accurate.
+        for rf in node.references.into_iter().flatten() {
+            if rf.node_id.server_index != 0 {
+                continue;
+            }
+            if let Some(target) = self.nodes.get_mut(&rf.node_id.node_id) {
+                target.references = None;
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.generation += 1;
+        self.nodes.clear();
+    }
+
+    fn set_namespaces(&mut self, namespaces: Vec<UAString>) {
+        if self.namespaces.as_ref().is_some_and(|n| n != &namespaces) {
+            debug!("Namespace array changed, clearing node cache");
+            self.clear();
+        }
+        self.namespaces = Some(namespaces);
+    }
+}
+
+/// Whether a read result describes the node, and should be cached.
+fn is_cacheable(value: &DataValue) -> bool {
+    let status = value.status();
+    status.is_good() || status == StatusCode::BadAttributeIdInvalid
+}
+
+fn namespaces_from_variant(value: Option<Variant>) -> Option<Vec<UAString>> {
+    let Some(Variant::Array(arr)) = value else {
+        return None;
+    };
+    arr.values
+        .into_iter()
+        .map(|v| match v {
+            Variant::String(s) => Some(s),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Subscription callback invalidating the cache on model changes.
+struct CacheInvalidator {
+    inner: Arc<RwLock<CacheInner>>,
+}
+
+impl CacheInvalidator {
+    fn affected_nodes(field: &Variant, nodes: &mut Vec<NodeId>) -> bool {
+        let items = match field {
+            Variant::ExtensionObject(o) => std::slice::from_ref(o),
+            Variant::Array(a) => {
+                for v in &a.values {
+                    if !Self::affected_nodes(v, nodes) {
+                        return false;
+                    }
+                }
+                return true;
+            }
+            _ => return false,
+        };
+        for obj in items {
+            if let Some(c) = obj.inner_as::<ModelChangeStructureDataType>() {
+                nodes.push(c.affected.clone());
+            } else if let Some(c) = obj.inner_as::<SemanticChangeStructureDataType>() {
+                nodes.push(c.affected.clone());
+            } else {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl OnSubscriptionNotification for CacheInvalidator {
+    fn on_data_value(&mut self, notification: DataValue, _item: &MonitoredItem) {
+        if let Some(namespaces) = namespaces_from_variant(notification.value) {
+            self.inner.write().set_namespaces(namespaces);
+        }
+    }
+
+    fn on_event(&mut self, event_fields: Option<Vec<Variant>>, _item: &MonitoredItem) {
+        // The event fields are the changes of a GeneralModelChangeEvent and of a SemanticChangeEvent,
+        // only one will be set. If the server does not tell us what changed, clear everything.
+        let mut affected = Vec::new();
+        let known = event_fields.iter().flatten().any(|f| !f.is_empty())
+            && event_fields
+                .iter()
+                .flatten()
+                .filter(|f| !f.is_empty())
+                .all(|f| Self::affected_nodes(f, &mut affected));
+
+        let mut inner = self.inner.write();
+        if known {
+            for node_id in &affected {
+                inner.invalidate(node_id);
+            }
+        } else {
+            debug!("Received model change event without a list of changes, clearing node cache");
+            inner.clear();
+        }
+    }
+}
+
+/// A client-side cache of node attributes and references, populated lazily
+/// as nodes are read and browsed.
+///
+/// The `Value` attribute is never cached, reading it through the cache always
+/// reads from the server.
+///
+/// By default, the cache is never invalidated. Call [NodeCache::subscribe_to_changes]
+/// to create a subscription that invalidates affected nodes when the server reports
+/// changes through `GeneralModelChangeEventType` or `SemanticChangeEventType` events,
+/// and clears the cache when the `NamespaceArray` changes.
+///
+/// # Example
+///
+/// ```ignore
+/// use opcua::client::node_cache::NodeCache;
+///
+/// let cache = NodeCache::new(session.clone());
+/// cache.subscribe_to_changes(Duration::from_secs(1)).await?;
+/// let name = cache.read_attribute(&node_id, AttributeId::BrowseName).await?;
+/// let targets = cache
+///     .resolve_browse_path(&ObjectId::ObjectsFolder.into(), &RelativePath::try_from("/1:MyObject")?)
+///     .await?;
+/// ```
+pub struct NodeCache {
+    session: Arc<Session>,
+    inner: Arc<RwLock<CacheInner>>,
+    config: BrowserConfig,
+    subscription_id: Mutex<Option<u32>>,
+}
+
+impl NodeCache {
+    /// Create a new, empty node cache using the given session.
+    pub fn new(session: Arc<Session>) -> Self {
+        Self {
+            session,
+            inner: Default::default(),
+            config: BrowserConfig::default(),
+            subscription_id: Mutex::new(None),
+        }
+    }
+
+    /// Set the configuration for the internal browser.
+    pub fn config(mut self, config: BrowserConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Read a list of attributes from the given node, using cached values where possible.
+    ///
+    /// The results are returned in the same order as `attributes`. Attributes that
+    /// do not exist on the node are cached as well, with status `BadAttributeIdInvalid`.
+    pub async fn read_attributes(
+        &self,
+        node_id: &NodeId,
+        attributes: &[AttributeId],
+    ) -> Result<Vec<DataValue>, Error> {
+        let (mut results, generation) = {
+            let inner = self.inner.read();
+            let node = inner.nodes.get(node_id);
+            let results: Vec<_> = attributes
+                .iter()
+                .map(|a| {
+                    if *a == AttributeId::Value {
+                        return None;
+                    }
+                    node.and_then(|n| n.attributes.get(&(*a as u32))).cloned()
+                })
+                .collect();
+            (results, inner.generation)
+        };
+
+        let to_read: Vec<_> = attributes
+            .iter()
+            .zip(results.iter())
+            .filter(|(_, r)| r.is_none())
+            .map(|(a, _)| ReadValueId::new(node_id.clone(), *a))
+            .collect();
+        if to_read.is_empty() {
+            return Ok(results.into_iter().flatten().collect());
+        }
+
+        let values = self
+            .session
+            .read(&to_read, TimestampsToReturn::Neither, 0.0)
+            .await
+            .map_err(|e| Error::new(e, format!("Failed to read attributes of node {node_id}")))?;
+        if values.len() != to_read.len() {
+            return Err(Error::new(
+                StatusCode::BadUnexpectedError,
+                "Server returned an unexpected number of results",
+            ));
+        }
+
+        let mut inner = self.inner.write();
+        // If the cache was invalidated while we were reading, the values may be outdated,
+        // so we just return them without caching them.
+        let store = inner.generation == generation;
+        let node = inner.nodes.entry(node_id.clone()).or_default();
+        let mut values = values.into_iter();
+        for (attribute, result) in attributes.iter().zip(results.iter_mut()) {
+            if result.is_some() {
+                continue;
+            }
+            let value = values.next().unwrap_or_default();
+            if store && *attribute != AttributeId::Value && is_cacheable(&value) {
+                node.attributes.insert(*attribute as u32, value.clone());
+            }
+            *result = Some(value);
+        }
+
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// Read a single attribute from the given node, using the cached value if possible.
+    pub async fn read_attribute(
+        &self,
+        node_id: &NodeId,
+        attribute: AttributeId,
+    ) -> Result<DataValue, Error> {
+        let mut res = self.read_attributes(node_id, &[attribute]).await?;
+        Ok(res.pop().unwrap_or_default())
+    }
+
+    /// Get all references to and from the given node, browsing the node
+    /// if it is not already in the cache.
+    pub async fn references(&self, node_id: &NodeId) -> Result<Vec<ReferenceDescription>, Error> {
+        let generation = {
+            let inner = self.inner.read();
+            if let Some(refs) = inner.nodes.get(node_id).and_then(|n| n.references.as_ref()) {
+                return Ok(refs.clone());
+            }
+            inner.generation
+        };
+
+        let stream = self
+            .session
+            .browser()
+            .config(self.config.clone())
+            .handler(NoneBrowserPolicy)
+            .run(vec![BrowseDescription {
+                node_id: node_id.clone(),
+                browse_direction: BrowseDirection::Both,
+                reference_type_id: ReferenceTypeId::References.into(),
+                include_subtypes: true,
+                node_class_mask: 0,
+                result_mask: BrowseResultMaskFlags::all().bits(),
+            }]);
+        futures::pin_mut!(stream);
+
+        let mut references = Vec::new();
+        while let Some(r) = stream.try_next().await? {
+            if r.status().is_bad() {
+                return Err(Error::new(
+                    r.status(),
+                    format!("Failed to browse node {node_id}"),
+                ));
+            }
+            references.extend(r.into_results().1);
+        }
+
+        let mut inner = self.inner.write();
+        if inner.generation == generation {
+            inner.nodes.entry(node_id.clone()).or_default().references = Some(references.clone());
+        }
+        Ok(references)
+    }
+
+    /// Get references from the given node in the given direction,
+    /// with the given reference type, optionally including subtypes.
+    pub async fn find_references(
+        &self,
+        node_id: &NodeId,
+        direction: BrowseDirection,
+        reference_type_id: impl Into<NodeId>,
+        include_subtypes: bool,
+    ) -> Result<Vec<ReferenceDescription>, Error> {
+        let reference_type_id = reference_type_id.into();
+        let mut res = Vec::new();
+        for rf in self.references(node_id).await? {
+            if !matches_direction(&rf, direction) {
+                continue;
+            }
+            if self
+                .matches_reference_type(&rf.reference_type_id, &reference_type_id, include_subtypes)
+                .await?
+            {
+                res.push(rf);
+            }
+        }
+        Ok(res)
+    }
+
+    /// Check whether `type_id` is equal to or a subtype of `base_type_id`,
+    /// by following `HasSubtype` references.
+    pub async fn is_subtype_of(
+        &self,
+        type_id: &NodeId,
+        base_type_id: &NodeId,
+    ) -> Result<bool, Error> {
+        let mut current = type_id.clone();
+        let mut visited = HashSet::new();
+        loop {
+            if &current == base_type_id {
+                return Ok(true);
+            }
+            if !visited.insert(current.clone()) {
+                return Ok(false);
+            }
+            let parent = self.references(&current).await?.into_iter().find(|r| {
+                !r.is_forward
+                    && r.reference_type_id == ReferenceTypeId::HasSubtype
+                    && r.node_id.server_index == 0
+            });
+            match parent {
+                Some(p) => current = p.node_id.node_id,
+                None => return Ok(false),
+            }
+        }
+    }
+
+    async fn matches_reference_type(
+        &self,
+        reference_type_id: &NodeId,
+        filter: &NodeId,
+        include_subtypes: bool,
+    ) -> Result<bool, Error> {
+        if filter.is_null() || reference_type_id == filter {
+            Ok(true)
+        } else if include_subtypes {
+            self.is_subtype_of(reference_type_id, filter).await
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Resolve a browse path from `start` using cached references, like the
+    /// `TranslateBrowsePathsToNodeIds` service. Returns all nodes matching the path.
+    pub async fn resolve_browse_path(
+        &self,
+        start: &NodeId,
+        path: &RelativePath,
+    ) -> Result<Vec<NodeId>, Error> {
+        let mut current = vec![start.clone()];
+        for element in path.elements.iter().flatten() {
+            let mut next = Vec::new();
+            let direction = if element.is_inverse {
+                BrowseDirection::Inverse
+            } else {
+                BrowseDirection::Forward
+            };
+            for node_id in &current {
+                for rf in self.references(node_id).await? {
+                    if rf.node_id.server_index != 0
+                        || !matches_direction(&rf, direction)
+                        || rf.browse_name != element.target_name
+                        || next.contains(&rf.node_id.node_id)
+                    {
+                        continue;
+                    }
+                    if self
+                        .matches_reference_type(
+                            &rf.reference_type_id,
+                            &element.reference_type_id,
+                            element.include_subtypes,
+                        )
+                        .await?
+                    {
+                        next.push(rf.node_id.node_id);
+                    }
+                }
+            }
+            if next.is_empty() {
+                return Ok(next);
+            }
+            current = next;
+        }
+        Ok(current)
+    }
+
+    /// Remove the given node from the cache. This also invalidates the cached references
+    /// of any cached node this node references.
+    pub fn invalidate(&self, node_id: &NodeId) {
+        self.inner.write().invalidate(node_id);
+    }
+
+    /// Remove all nodes from the cache.
+    pub fn clear(&self) {
+        self.inner.write().clear();
+    }
+
+    /// Create a subscription that invalidates the cache when the server reports
+    /// changes to the address space, or when the namespace array changes.
+    ///
+    /// Affected nodes are removed from the cache when the server emits a
+    /// `GeneralModelChangeEventType` or `SemanticChangeEventType` event. If the event
+    /// does not say which nodes changed, the entire cache is cleared.
+    ///
+    /// Does nothing if the cache is already subscribed.
+    pub async fn subscribe_to_changes(&self, publishing_interval: Duration) -> Result<(), Error> {
+        if self.subscription_id.lock().is_some() {
+            return Ok(());
+        }
+
+        let subscription_id = self
+            .session
+            .create_subscription(
+                publishing_interval,
+                60,
+                20,
+                0,
+                0,
+                true,
+                CacheInvalidator {
+                    inner: self.inner.clone(),
+                },
+            )
+            .await
+            .map_err(|e| Error::new(e, "Failed to create node cache subscription"))?;
+
+        let changes_field = |type_id: ObjectTypeId| SimpleAttributeOperand {
+            type_definition_id: type_id.into(),
+            browse_path: Some(vec!["Changes".into()]),
+            attribute_id: AttributeId::Value as u32,
+            index_range: Default::default(),
+        };
+        let filter = EventFilter {
+            select_clauses: Some(vec![
+                changes_field(ObjectTypeId::GeneralModelChangeEventType),
+                changes_field(ObjectTypeId::SemanticChangeEventType),
+            ]),
+            where_clause: ContentFilter {
+                elements: Some(vec![
+                    ContentFilterElement::from((
+                        FilterOperator::Or,
+                        vec![Operand::element(1), Operand::element(2)],
+                    )),
+                    ContentFilterElement::from((
+                        FilterOperator::OfType,
+                        vec![Operand::literal(NodeId::from(
+                            ObjectTypeId::GeneralModelChangeEventType,
+                        ))],
+                    )),
+                    ContentFilterElement::from((
+                        FilterOperator::OfType,
+                        vec![Operand::literal(NodeId::from(
+                            ObjectTypeId::SemanticChangeEventType,
+                        ))],
+                    )),
+                ]),
+            },
+        };
+
+        let items = vec![
+            MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: ObjectId::Server.into(),
+                    attribute_id: AttributeId::EventNotifier as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    queue_size: 100,
+                    discard_oldest: true,
+                    filter: ExtensionObject::from_message(filter),
+                    ..Default::default()
+                },
+            },
+            MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId::new_value(VariableId::Server_NamespaceArray.into()),
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    queue_size: 1,
+                    ..Default::default()
+                },
+            },
+        ];
+
+        let result = self
+            .session
+            .create_monitored_items(subscription_id, TimestampsToReturn::Neither, items)
+            .await
+            .map_err(|e| Error::new(e, "Failed to create node cache monitored items"));
+        let status = match &result {
+            Ok(r) => r
+                .iter()
+                .map(|r| r.result.status_code)
+                .find(|s| s.is_bad())
+                .map(|s| Error::new(s, "Failed to create node cache monitored items")),
+            Err(_) => None,
+        };
+        if let Some(e) = result.err().or(status) {
+            if let Err(e) = self.session.delete_subscription(subscription_id).await {
+                warn!("Failed to delete node cache subscription: {e}");
+            }
+            return Err(e);
+        }
+
+        *self.subscription_id.lock() = Some(subscription_id);
+        Ok(())
+    }
+
+    /// Delete the subscription created by [NodeCache::subscribe_to_changes], if any.
+    /// The cache will no longer be invalidated automatically.
+    pub async fn unsubscribe(&self) -> Result<(), Error> {
+        let Some(subscription_id) = self.subscription_id.lock().take() else {
+            return Ok(());
+        };
+        self.session
+            .delete_subscription(subscription_id)
+            .await
+            .map_err(|e| Error::new(e, "Failed to delete node cache subscription"))?;
+        Ok(())
+    }
+
+    async fn read_namespace_array(&self) -> Result<Vec<UAString>, Error> {
+        let value = self
+            .session
+            .read(
+                &[ReadValueId::new_value(
+                    VariableId::Server_NamespaceArray.into(),
+                )],
+                TimestampsToReturn::Neither,
+                0.0,
+            )
+            .await
+            .map_err(|e| Error::new(e, "Failed to read namespace array"))?
+            .into_iter()
+            .next()
+            .and_then(|v| v.value);
+        namespaces_from_variant(value).ok_or_else(|| {
+            Error::new(
+                StatusCode::BadUnexpectedError,
+                "Namespace array is not an array of strings",
+            )
+        })
+    }
+
+    /// Write the contents of the cache to a file, so that it can be
+    /// restored with [NodeCache::load] later.
+    pub async fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let known_namespaces = self.inner.read().namespaces.clone();
+        let namespaces = match known_namespaces {
+            Some(n) => n,
+            None => self.read_namespace_array().await?,
+        };
+
+        let file =
+            std::fs::File::create(path).map_err(|e| Error::new(StatusCode::BadInternalError, e))?;
+        let mut stream = BufWriter::new(file);
+        let ctx_r = self.session.encoding_context().read();
+        let ctx = ctx_r.context();
+        let inner = self.inner.read();
+
+        write_u32(&mut stream, CACHE_FILE_VERSION)?;
+        Some(namespaces).encode(&mut stream, &ctx)?;
+        write_u32(&mut stream, inner.nodes.len() as u32)?;
+        for (node_id, node) in &inner.nodes {
+            node_id.encode(&mut stream, &ctx)?;
+            write_u32(&mut stream, node.attributes.len() as u32)?;
+            for (attribute, value) in &node.attributes {
+                write_u32(&mut stream, *attribute)?;
+                value.encode(&mut stream, &ctx)?;
+            }
+            node.references.encode(&mut stream, &ctx)?;
+        }
+        stream
+            .flush()
+            .map_err(|e| Error::new(StatusCode::BadInternalError, e))?;
+        Ok(())
+    }
+
+    /// Load the contents of the cache from a file written by [NodeCache::save],
+    /// adding them to the cache.
+    ///
+    /// If the namespace array of the server has changed since the file was written,
+    /// the file is ignored, and this returns `false`.
+    pub async fn load(&self, path: impl AsRef<Path>) -> Result<bool, Error> {
+        let namespaces = self.read_namespace_array().await?;
+
+        let file =
+            std::fs::File::open(path).map_err(|e| Error::new(StatusCode::BadInternalError, e))?;
+        let mut stream = BufReader::new(file);
+        let ctx_r = self.session.encoding_context().read();
+        let ctx = ctx_r.context();
+
+        let version = read_u32(&mut stream)?;
+        if version != CACHE_FILE_VERSION {
+            return Err(Error::decoding(format!(
+                "Unsupported node cache file version {version}"
+            )));
+        }
+        let saved_namespaces = <Option<Vec<UAString>>>::decode(&mut stream, &ctx)?;
+        if saved_namespaces.as_ref() != Some(&namespaces) {
+            debug!("Namespace array has changed, ignoring saved node cache");
+            self.inner.write().set_namespaces(namespaces);
+            return Ok(false);
+        }
+
+        let mut nodes = HashMap::new();
+        let num_nodes = read_u32(&mut stream)?;
+        for _ in 0..num_nodes {
+            let node_id = NodeId::decode(&mut stream, &ctx)?;
+            let num_attributes = read_u32(&mut stream)?;
+            let mut attributes = HashMap::new();
+            for _ in 0..num_attributes {
+                let attribute = read_u32(&mut stream)?;
+                attributes.insert(attribute, DataValue::decode(&mut stream, &ctx)?);
+            }
+            let references = <Option<Vec<ReferenceDescription>>>::decode(&mut stream, &ctx)?;
+            nodes.insert(
+                node_id,
+                CachedNode {
+                    attributes,
+                    references,
+                },
+            );
+        }
+        // Make sure we are at the end of the file.
+        if stream
+            .read(&mut [0u8])
+            .map_err(|e| Error::new(StatusCode::BadInternalError, e))?
+            != 0
+        {
+            return Err(Error::decoding("Unexpected data at end of node cache file"));
+        }
+
+        let mut inner = self.inner.write();
+        inner.set_namespaces(namespaces);
+        inner.generation += 1;
+        inner.nodes.extend(nodes);
+        Ok(true)
+    }
+}
+
+fn matches_direction(rf: &ReferenceDescription, direction: BrowseDirection) -> bool {
+    match direction {
+        BrowseDirection::Forward => rf.is_forward,
+        BrowseDirection::Inverse => !rf.is_forward,
+        _ => true,
+    }
+}
@@ -5,10 +5,7 @@ use tokio::{pin, select};
 use tracing::{debug, error};
 
 use crate::{
-    transport::{
-        tcp::{TcpConnector, TransportConfiguration},
-        TransportPollResult,
-    },
+    transport::{tcp::TransportConfiguration, TransportPollResult, UrlConnector},
     AsyncSecureChannel, ClientConfig, ClientEndpoint, IdentityToken,
 };
 use opcua_core::{
@@ -234,7 +231,7 @@ impl Client {
                 max_message_size: self.config.decoding_options.max_message_size,
                 max_chunk_count: self.config.decoding_options.max_chunk_count,
             },
-            Box::new(TcpConnector),
+            Box::new(UrlConnector::new(self.certificate_store.clone())),
             channel_lifetime,
             // We should only ever need the default decoding context for temporary connections.
             Arc::new(RwLock::new(ContextOwned::new_default(
@@ -641,7 +638,7 @@ impl Client {
     pub fn is_supported_endpoint(&self, endpoint: &EndpointDescription) -> bool {
         if let Ok(security_policy) = SecurityPolicy::from_str(endpoint.security_policy_uri.as_ref())
         {
-            !matches!(security_policy, SecurityPolicy::Unknown)
+            security_policy.is_supported()
         } else {
             false
         }
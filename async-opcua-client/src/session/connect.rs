@@ -101,6 +101,12 @@ impl SessionConnector {
             }
         };
 
+        // Read the limits before recreating subscriptions, so that monitored items
+        // are created in chunks the server accepts.
+        if self.inner.read_operation_limits {
+            self.inner.update_operation_limits().await;
+        }
+
         if self.inner.recreate_subscriptions {
             self.inner.transfer_subscriptions_from_old_session().await;
         }
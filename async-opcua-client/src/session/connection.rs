@@ -9,10 +9,7 @@ use opcua_types::{
 use tracing::error;
 
 use crate::{
-    transport::{
-        tcp::{TcpConnector, TransportConfiguration},
-        Connector,
-    },
+    transport::{tcp::TransportConfiguration, Connector, UrlConnector},
     AsyncSecureChannel, ClientConfig, IdentityToken,
 };
 
@@ -21,7 +18,7 @@ use super::{Client, EndpointInfo, Session, SessionEventLoop};
 struct SessionBuilderInner {
     session_id: Option<NodeId>,
     user_identity_token: IdentityToken,
-    connector: Box<dyn Connector>,
+    connector: Option<Box<dyn Connector>>,
     type_loaders: Vec<Arc<dyn TypeLoader>>,
 }
 
@@ -46,7 +43,7 @@ impl<'a> SessionBuilder<'a, (), ()> {
             inner: SessionBuilderInner {
                 session_id: None,
                 user_identity_token: IdentityToken::Anonymous,
-                connector: Box::new(TcpConnector),
+                connector: None,
                 type_loaders: Vec::new(),
             },
         }
@@ -85,6 +82,17 @@ impl<T, R> SessionBuilder<'_, T, R> {
         self
     }
 
+    /// Set the connector used to open connections to the server. The default
+    /// is [`UrlConnector`], which connects to the endpoint URL using the transport
+    /// given by its scheme.
+    ///
+    /// Use [`ReverseTcpConnector`](crate::transport::ReverseTcpConnector) to instead
+    /// wait for the server to connect to the client using reverse connect.
+    pub fn connector(mut self, connector: impl Connector + 'static) -> Self {
+        self.inner.connector = Some(Box::new(connector));
+        self
+    }
+
     /// Add an initial type loader to the session. You can add more of these later.
     /// Note that custom type loaders will likely not work until namespaces
     /// are fetched from the server.
@@ -308,9 +316,11 @@ impl<R> SessionBuilder<'_, EndpointDescription, R> {
         identity_token: IdentityToken,
         endpoint: EndpointDescription,
         config: &ClientConfig,
-        connector: Box<dyn Connector>,
+        connector: Option<Box<dyn Connector>>,
         ctx: ContextOwned,
     ) -> AsyncSecureChannel {
+        let connector =
+            connector.unwrap_or_else(|| Box::new(UrlConnector::new(certificate_store.clone())));
         AsyncSecureChannel::new(
             certificate_store,
             EndpointInfo {
@@ -2,6 +2,7 @@ mod client;
 mod connect;
 mod connection;
 mod event_loop;
+mod operation_limits;
 mod request_builder;
 mod retry;
 mod services;
@@ -49,6 +50,7 @@ pub use connection::SessionBuilder;
 pub use event_loop::{SessionActivity, SessionEventLoop, SessionPollResult};
 use opcua_core::handle::AtomicHandle;
 use opcua_core::sync::{Mutex, RwLock};
+pub use operation_limits::OperationLimits;
 pub use request_builder::UARequest;
 pub use retry::{DefaultRetryPolicy, RequestRetryPolicy};
 pub use services::attributes::{
@@ -56,6 +58,7 @@ pub use services::attributes::{
 };
 pub use services::method::Call;
 pub use services::node_management::{AddNodes, AddReferences, DeleteNodes, DeleteReferences};
+pub use services::query::{QueryError, QueryFirst, QueryNext};
 pub use services::session::{ActivateSession, Cancel, CloseSession, CreateSession};
 use services::subscriptions::state::SubscriptionState;
 use services::subscriptions::PublishLimits;
@@ -66,6 +69,9 @@ pub use services::subscriptions::{
     SetTriggering, Subscription, SubscriptionActivity, SubscriptionCallbacks,
     TransferSubscriptions,
 };
+pub use services::typed_method::{
+    ArgumentError, CallError, CallInputs, CallOutputs, MethodArguments,
+};
 pub use services::view::{
     Browse, BrowseNext, RegisterNodes, TranslateBrowsePaths, UnregisterNodes,
 };
@@ -176,6 +182,9 @@ pub struct Session {
     pub(super) publish_timeout: Duration,
     pub(super) recreate_monitored_items_chunk: usize,
     pub(super) recreate_subscriptions: bool,
+    pub(super) read_operation_limits: bool,
+    pub(super) operation_limits: ArcSwap<OperationLimits>,
+    pub(super) max_concurrent_chunks: usize,
     pub(super) should_reconnect: AtomicBool,
     pub(super) session_timeout: f64,
     /// Reference to the subscription cache for the client.
@@ -217,6 +226,9 @@ impl Session {
             publish_timeout: config.publish_timeout,
             recreate_monitored_items_chunk: config.performance.recreate_monitored_items_chunk,
             recreate_subscriptions: config.recreate_subscriptions,
+            read_operation_limits: config.performance.read_operation_limits,
+            operation_limits: ArcSwap::default(),
+            max_concurrent_chunks: config.performance.max_concurrent_chunks,
             should_reconnect: AtomicBool::new(true),
             subscription_state: Mutex::new(SubscriptionState::new(
                 config.min_publish_interval,
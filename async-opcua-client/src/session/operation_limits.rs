@@ -0,0 +1,273 @@
+use std::future::Future;
+
+use futures::{Stream, StreamExt, TryStreamExt};
+use opcua_types::{
+    DataValue, NodeId, ReadValueId, StatusCode, TimestampsToReturn, VariableId, Variant,
+};
+
+use crate::session::{session_error, session_warn, Read, UARequest};
+
+use super::Session;
+
+/// Limits on the number of operations in a single service call, as reported by the server
+/// in `Server/ServerCapabilities/OperationLimits`.
+///
+/// The session reads these after connecting, and splits calls to [`Session::read`],
+/// [`Session::write`], [`Session::call`], [`Session::browse`], [`Session::history_read`] and
+/// [`Session::create_monitored_items`] that exceed them into several requests.
+///
+/// A limit of `0` means that the server does not define a limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OperationLimits {
+    /// Maximum number of nodes per `Read` call.
+    pub max_nodes_per_read: u32,
+    /// Maximum number of nodes per `HistoryRead` call for data values.
+    pub max_nodes_per_history_read_data: u32,
+    /// Maximum number of nodes per `HistoryRead` call for events.
+    pub max_nodes_per_history_read_events: u32,
+    /// Maximum number of nodes per `Write` call.
+    pub max_nodes_per_write: u32,
+    /// Maximum number of methods per `Call` call.
+    pub max_nodes_per_method_call: u32,
+    /// Maximum number of nodes per `Browse` call.
+    pub max_nodes_per_browse: u32,
+    /// Maximum number of monitored items per `CreateMonitoredItems` call.
+    pub max_monitored_items_per_call: u32,
+}
+
+impl OperationLimits {
+    const NODES: [VariableId; 7] = [
+        VariableId::Server_ServerCapabilities_OperationLimits_MaxNodesPerRead,
+        VariableId::Server_ServerCapabilities_OperationLimits_MaxNodesPerHistoryReadData,
+        VariableId::Server_ServerCapabilities_OperationLimits_MaxNodesPerHistoryReadEvents,
+        VariableId::Server_ServerCapabilities_OperationLimits_MaxNodesPerWrite,
+        VariableId::Server_ServerCapabilities_OperationLimits_MaxNodesPerMethodCall,
+        VariableId::Server_ServerCapabilities_OperationLimits_MaxNodesPerBrowse,
+        VariableId::Server_ServerCapabilities_OperationLimits_MaxMonitoredItemsPerCall,
+    ];
+
+    fn from_values(values: &[DataValue]) -> Self {
+        // Limits the server does not expose are treated as unlimited.
+        let limit = |idx: usize| match values.get(idx).and_then(|v| v.value.as_ref()) {
+            Some(Variant::UInt32(v)) => *v,
+            _ => 0,
+        };
+        Self {
+            max_nodes_per_read: limit(0),
+            max_nodes_per_history_read_data: limit(1),
+            max_nodes_per_history_read_events: limit(2),
+            max_nodes_per_write: limit(3),
+            max_nodes_per_method_call: limit(4),
+            max_nodes_per_browse: limit(5),
+            max_monitored_items_per_call: limit(6),
+        }
+    }
+}
+
+impl Session {
+    /// Get the operation limits currently used to split requests.
+    pub fn operation_limits(&self) -> OperationLimits {
+        **self.operation_limits.load()
+    }
+
+    /// Set the operation limits used to split requests, overriding the
+    /// limits read from the server. Note that the limits are read again each time
+    /// the session connects, unless this is disabled in the client configuration.
+    pub fn set_operation_limits(&self, limits: OperationLimits) {
+        self.operation_limits.store(std::sync::Arc::new(limits));
+    }
+
+    /// Read the operation limits from the server, and store them on the session.
+    ///
+    /// This is called automatically when the session connects, unless disabled with
+    /// [`ClientBuilder::read_operation_limits`](crate::ClientBuilder::read_operation_limits).
+    pub async fn read_operation_limits(&self) -> Result<OperationLimits, StatusCode> {
+        let response = Read::new(self)
+            .nodes_to_read(
+                OperationLimits::NODES
+                    .iter()
+                    .map(|id| ReadValueId::from(NodeId::from(*id)))
+                    .collect(),
+            )
+            .timestamps_to_return(TimestampsToReturn::Neither)
+            .send(&self.channel)
+            .await?;
+        let limits = OperationLimits::from_values(&response.results.unwrap_or_default());
+        self.set_operation_limits(limits);
+        Ok(limits)
+    }
+
+    /// Read the operation limits after connecting, keeping the previous limits if this fails.
+    pub(super) async fn update_operation_limits(&self) {
+        if let Err(e) = self.read_operation_limits().await {
+            session_warn!(self, "Failed to read operation limits from the server: {e}");
+        }
+    }
+
+    /// Send `items` using `send`, split into chunks of at most `limit` items.
+    /// Chunks are sent concurrently, up to the configured maximum, and the
+    /// results are returned in the same order as `items`.
+    ///
+    /// If any chunk fails, the whole call fails, even though some operations
+    /// may have been carried out by the server.
+    pub(crate) async fn send_chunked<T, R, Fut>(
+        &self,
+        items: Vec<T>,
+        limit: u32,
+        send: impl Fn(Vec<T>) -> Fut,
+    ) -> Result<Vec<R>, StatusCode>
+    where
+        Fut: Future<Output = Result<Vec<R>, StatusCode>>,
+    {
+        let limit = limit as usize;
+        if limit == 0 || items.len() <= limit {
+            return send(items).await;
+        }
+
+        let results: Vec<Vec<R>> = self
+            .send_chunks(Self::split_chunks(items, limit), &send)
+            .try_collect()
+            .await?;
+
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// Like [`Session::send_chunked`], but if some chunks fail, `release` is called
+    /// with the operations and results of the chunks that succeeded before the call
+    /// fails. This is used for operations that return continuation points, which
+    /// would otherwise stay allocated on the server.
+    pub(crate) async fn send_chunked_or_release<T, R, Fut, RFut>(
+        &self,
+        items: Vec<T>,
+        limit: u32,
+        send: impl Fn(Vec<T>) -> Fut,
+        release: impl FnOnce(Vec<(T, R)>) -> RFut,
+    ) -> Result<Vec<R>, StatusCode>
+    where
+        T: Clone,
+        Fut: Future<Output = Result<Vec<R>, StatusCode>>,
+        RFut: Future<Output = Result<(), StatusCode>>,
+    {
+        let limit = limit as usize;
+        if limit == 0 || items.len() <= limit {
+            return send(items).await;
+        }
+
+        let chunks = Self::split_chunks(items, limit);
+        let results: Vec<Result<Vec<R>, StatusCode>> =
+            self.send_chunks(chunks.clone(), &send).collect().await;
+        let Some(error) = results.iter().find_map(|r| r.as_ref().err().copied()) else {
+            return Ok(results.into_iter().flatten().flatten().collect());
+        };
+
+        let succeeded: Vec<_> = chunks
+            .into_iter()
+            .zip(results)
+            .filter_map(|(chunk, result)| Some(chunk.into_iter().zip(result.ok()?)))
+            .flatten()
+            .collect();
+        if !succeeded.is_empty() {
+            if let Err(e) = release(succeeded).await {
+                session_warn!(
+                    self,
+                    "Failed to release the continuation points of a failed chunked request: {e}"
+                );
+            }
+        }
+        Err(error)
+    }
+
+    /// Like [`Session::send_chunked`], but if some chunks fail, the results of the
+    /// operations in those chunks are produced by `failed`, so that the results of
+    /// the chunks that succeeded are not lost. This is used for operations that
+    /// create state on the server, which the caller must be told about.
+    ///
+    /// The call only fails if every chunk fails.
+    pub(crate) async fn send_chunked_per_item<T, R, Fut>(
+        &self,
+        items: Vec<T>,
+        limit: u32,
+        send: impl Fn(Vec<T>) -> Fut,
+        failed: impl Fn(T, StatusCode) -> R,
+    ) -> Result<Vec<R>, StatusCode>
+    where
+        T: Clone,
+        Fut: Future<Output = Result<Vec<R>, StatusCode>>,
+    {
+        let limit = limit as usize;
+        if limit == 0 || items.len() <= limit {
+            return send(items).await;
+        }
+
+        let chunks = Self::split_chunks(items, limit);
+        let results: Vec<Result<Vec<R>, StatusCode>> =
+            self.send_chunks(chunks.clone(), &send).collect().await;
+        if let Some(Err(e)) = results.first() {
+            if results.iter().all(Result::is_err) {
+                return Err(*e);
+            }
+        }
+
+        let mut out = Vec::new();
+        for (chunk, result) in chunks.into_iter().zip(results) {
+            match result {
+                Ok(r) => out.extend(r),
+                Err(e) => {
+                    session_warn!(
+                        self,
+                        "Chunk of {} operations failed: {e}, other chunks succeeded",
+                        chunk.len()
+                    );
+                    out.extend(chunk.into_iter().map(|item| failed(item, e)));
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    fn split_chunks<T>(items: Vec<T>, limit: usize) -> Vec<Vec<T>> {
+        let mut chunks = Vec::with_capacity(items.len().div_ceil(limit));
+        let mut iter = items.into_iter();
+        loop {
+            let chunk: Vec<_> = (&mut iter).take(limit).collect();
+            if chunk.is_empty() {
+                break;
+            }
+            chunks.push(chunk);
+        }
+        chunks
+    }
+
+    /// Send each chunk using `send`, returning a stream of the results in order.
+    fn send_chunks<'a, T, R, Fut>(
+        &'a self,
+        chunks: Vec<Vec<T>>,
+        send: &'a impl Fn(Vec<T>) -> Fut,
+    ) -> impl Stream<Item = Result<Vec<R>, StatusCode>> + 'a
+    where
+        T: 'a,
+        R: 'a,
+        Fut: Future<Output = Result<Vec<R>, StatusCode>> + 'a,
+    {
+        futures::stream::iter(chunks)
+            .map(move |chunk| {
+                let len = chunk.len();
+                let fut = send(chunk);
+                async move {
+                    let results = fut.await?;
+                    // Results are matched to operations by position, so a chunk with the
+                    // wrong number of results would misalign everything after it.
+                    if results.len() != len {
+                        session_error!(
+                            self,
+                            "Expected {len} results for chunked request, got {}",
+                            results.len()
+                        );
+                        return Err(StatusCode::BadUnknownResponse);
+                    }
+                    Ok(results)
+                }
+            })
+            .buffered(self.max_concurrent_chunks.max(1))
+    }
+}
@@ -504,14 +504,18 @@ impl Session {
         timestamps_to_return: TimestampsToReturn,
         max_age: f64,
     ) -> Result<Vec<DataValue>, StatusCode> {
-        Ok(Read::new(self)
-            .nodes_to_read(nodes_to_read.to_vec())
-            .timestamps_to_return(timestamps_to_return)
-            .max_age(max_age)
-            .send(&self.channel)
-            .await?
-            .results
-            .unwrap_or_default())
+        let limit = self.operation_limits().max_nodes_per_read;
+        self.send_chunked(nodes_to_read.to_vec(), limit, |chunk| async move {
+            Ok(Read::new(self)
+                .nodes_to_read(chunk)
+                .timestamps_to_return(timestamps_to_return)
+                .max_age(max_age)
+                .send(&self.channel)
+                .await?
+                .results
+                .unwrap_or_default())
+        })
+        .await
     }
 
     /// Reads historical values or events of one or more nodes. The caller is expected to provide
@@ -543,14 +547,50 @@ impl Session {
         release_continuation_points: bool,
         nodes_to_read: &[HistoryReadValueId],
     ) -> Result<Vec<HistoryReadResult>, StatusCode> {
-        Ok(HistoryRead::new(history_read_details, self)
-            .timestamps_to_return(timestamps_to_return)
-            .release_continuation_points(release_continuation_points)
-            .nodes_to_read(nodes_to_read.to_vec())
-            .send(&self.channel)
-            .await?
-            .results
-            .unwrap_or_default())
+        let limits = self.operation_limits();
+        let limit = match history_read_details {
+            HistoryReadAction::ReadEventDetails(_) => limits.max_nodes_per_history_read_events,
+            _ => limits.max_nodes_per_history_read_data,
+        };
+        self.send_chunked_or_release(
+            nodes_to_read.to_vec(),
+            limit,
+            |chunk| {
+                let history_read_details = history_read_details.clone();
+                async move {
+                    Ok(HistoryRead::new(history_read_details, self)
+                        .timestamps_to_return(timestamps_to_return)
+                        .release_continuation_points(release_continuation_points)
+                        .nodes_to_read(chunk)
+                        .send(&self.channel)
+                        .await?
+                        .results
+                        .unwrap_or_default())
+                }
+            },
+            |succeeded| {
+                let history_read_details = history_read_details.clone();
+                async move {
+                    let nodes_to_read: Vec<_> = succeeded
+                        .into_iter()
+                        .filter(|(_, r)| !r.continuation_point.is_null())
+                        .map(|(node, r)| HistoryReadValueId {
+                            continuation_point: r.continuation_point,
+                            ..node
+                        })
+                        .collect();
+                    if !nodes_to_read.is_empty() {
+                        HistoryRead::new(history_read_details, self)
+                            .release_continuation_points(true)
+                            .nodes_to_read(nodes_to_read)
+                            .send(&self.channel)
+                            .await?;
+                    }
+                    Ok(())
+                }
+            },
+        )
+        .await
     }
 
     /// Writes values to nodes by sending a [`WriteRequest`] to the server. Note that some servers may reject DataValues
@@ -571,12 +611,16 @@ impl Session {
         &self,
         nodes_to_write: &[WriteValue],
     ) -> Result<Vec<StatusCode>, StatusCode> {
-        Ok(Write::new(self)
-            .nodes_to_write(nodes_to_write.to_vec())
-            .send(&self.channel)
-            .await?
-            .results
-            .unwrap_or_default())
+        let limit = self.operation_limits().max_nodes_per_write;
+        self.send_chunked(nodes_to_write.to_vec(), limit, |chunk| async move {
+            Ok(Write::new(self)
+                .nodes_to_write(chunk)
+                .send(&self.channel)
+                .await?
+                .results
+                .unwrap_or_default())
+        })
+        .await
     }
 
     /// Updates historical values. The caller is expected to provide one or more history update operations
@@ -123,12 +123,16 @@ impl Session {
         &self,
         methods: Vec<CallMethodRequest>,
     ) -> Result<Vec<CallMethodResult>, StatusCode> {
-        Ok(Call::new(self)
-            .methods_to_call(methods)
-            .send(&self.channel)
-            .await?
-            .results
-            .unwrap_or_default())
+        let limit = self.operation_limits().max_nodes_per_method_call;
+        self.send_chunked(methods, limit, |chunk| async move {
+            Ok(Call::new(self)
+                .methods_to_call(chunk)
+                .send(&self.channel)
+                .await?
+                .results
+                .unwrap_or_default())
+        })
+        .await
     }
 
     /// Calls a single method on an object on the server by sending a [`CallRequest`] to the server.
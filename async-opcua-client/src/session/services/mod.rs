@@ -1,6 +1,8 @@
 pub(super) mod attributes;
 pub(super) mod method;
 pub(super) mod node_management;
+pub(super) mod query;
 pub(super) mod session;
 pub(super) mod subscriptions;
+pub(super) mod typed_method;
 pub(super) mod view;
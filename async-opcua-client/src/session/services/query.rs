@@ -0,0 +1,424 @@
+use std::time::Duration;
+
+use crate::{
+    session::{
+        process_service_result, process_unexpected_response,
+        request_builder::{builder_base, builder_debug, builder_error, RequestHeaderBuilder},
+        session_debug,
+    },
+    Session, UARequest,
+};
+use opcua_core::ResponseMessage;
+use opcua_types::{
+    ByteString, ContentFilter, ContentFilterResult, IntegerId, NodeId, NodeTypeDescription,
+    ParsingResult, QueryDataSet, QueryFirstRequest, QueryFirstResponse, QueryNextRequest,
+    QueryNextResponse, StatusCode, ViewDescription,
+};
+
+/// Error returned from [`Session::query`].
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum QueryError {
+    /// The request failed with the given status.
+    #[error("Query failed: {0}")]
+    Status(StatusCode),
+    /// The server rejected the node types or the filter. The status is
+    /// `BadContentFilterInvalid` if any element of the filter is invalid.
+    #[error("Query is invalid: {status}")]
+    Invalid {
+        /// The reason the query is invalid.
+        status: StatusCode,
+        /// The result of parsing each node type, with diagnostics if requested.
+        parsing_results: Vec<ParsingResult>,
+        /// The result of parsing the filter, with diagnostics if requested.
+        filter_result: ContentFilterResult,
+    },
+}
+
+impl From<StatusCode> for QueryError {
+    fn from(value: StatusCode) -> Self {
+        Self::Status(value)
+    }
+}
+
+impl QueryError {
+    /// Get the status code of the error.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            QueryError::Status(s) => *s,
+            QueryError::Invalid { status, .. } => *status,
+        }
+    }
+
+    fn from_response(response: QueryFirstResponse) -> Self {
+        let status = response.response_header.service_result;
+        let parsing_results = response.parsing_results.unwrap_or_default();
+        let filter_result = response.filter_result;
+        let filter_invalid = filter_result
+            .element_results
+            .iter()
+            .flatten()
+            .any(|r| r.status_code.is_bad());
+        if !filter_invalid && parsing_results.iter().all(|r| r.status_code.is_good()) {
+            return QueryError::Status(status);
+        }
+        QueryError::Invalid {
+            status: if filter_invalid {
+                StatusCode::BadContentFilterInvalid
+            } else {
+                status
+            },
+            parsing_results,
+            filter_result,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Find nodes matching a set of type descriptions and a filter, by sending a
+/// [`QueryFirstRequest`] to the server.
+///
+/// See OPC UA Part 4 - Services 5.9.3 for complete description of the service and error responses.
+pub struct QueryFirst {
+    view: ViewDescription,
+    node_types: Vec<NodeTypeDescription>,
+    filter: ContentFilter,
+    max_data_sets_to_return: u32,
+    max_references_to_return: u32,
+
+    header: RequestHeaderBuilder,
+}
+
+builder_base!(QueryFirst);
+
+impl QueryFirst {
+    /// Construct a new call to the `QueryFirst` service.
+    pub fn new(session: &Session) -> Self {
+        Self {
+            view: ViewDescription::default(),
+            node_types: Vec::new(),
+            filter: ContentFilter::default(),
+            max_data_sets_to_return: 0,
+            max_references_to_return: 0,
+
+            header: RequestHeaderBuilder::new_from_session(session),
+        }
+    }
+
+    /// Construct a new call to the `QueryFirst` service, setting header parameters manually.
+    pub fn new_manual(
+        session_id: u32,
+        timeout: Duration,
+        auth_token: NodeId,
+        request_handle: IntegerId,
+    ) -> Self {
+        Self {
+            view: ViewDescription::default(),
+            node_types: Vec::new(),
+            filter: ContentFilter::default(),
+            max_data_sets_to_return: 0,
+            max_references_to_return: 0,
+
+            header: RequestHeaderBuilder::new(session_id, timeout, auth_token, request_handle),
+        }
+    }
+
+    /// Set the view to query.
+    pub fn view(mut self, view: ViewDescription) -> Self {
+        self.view = view;
+        self
+    }
+
+    /// Set node types to query, overwriting any that were set previously.
+    pub fn node_types(mut self, node_types: Vec<NodeTypeDescription>) -> Self {
+        self.node_types = node_types;
+        self
+    }
+
+    /// Add a node type to query.
+    pub fn node_type(mut self, node_type: NodeTypeDescription) -> Self {
+        self.node_types.push(node_type);
+        self
+    }
+
+    /// Set the filter nodes must match. This can be built using
+    /// [`ContentFilterBuilder`](opcua_types::operand::ContentFilterBuilder).
+    pub fn filter(mut self, filter: impl Into<ContentFilter>) -> Self {
+        self.filter = filter.into();
+        self
+    }
+
+    /// Set the maximum number of data sets to return. The default is zero, meaning server-defined.
+    pub fn max_data_sets_to_return(mut self, max_data_sets_to_return: u32) -> Self {
+        self.max_data_sets_to_return = max_data_sets_to_return;
+        self
+    }
+
+    /// Set the maximum number of references to return. The default is zero, meaning server-defined.
+    pub fn max_references_to_return(mut self, max_references_to_return: u32) -> Self {
+        self.max_references_to_return = max_references_to_return;
+        self
+    }
+}
+
+impl QueryFirst {
+    /// Send the request, returning the response even if the service result is bad,
+    /// since it may contain the results of parsing the request.
+    async fn send_unchecked(
+        self,
+        channel: &crate::AsyncSecureChannel,
+    ) -> Result<QueryFirstResponse, StatusCode> {
+        if self.node_types.is_empty() {
+            builder_error!(self, "query_first was not supplied with any node types");
+            return Err(StatusCode::BadNothingToDo);
+        }
+        let request = QueryFirstRequest {
+            request_header: self.header.header,
+            view: self.view,
+            node_types: Some(self.node_types),
+            filter: self.filter,
+            max_data_sets_to_return: self.max_data_sets_to_return,
+            max_references_to_return: self.max_references_to_return,
+        };
+        let response = channel.send(request, self.header.timeout).await?;
+        if let ResponseMessage::QueryFirst(response) = response {
+            builder_debug!(self, "query_first, success");
+            Ok(*response)
+        } else {
+            builder_error!(self, "query_first failed");
+            Err(process_unexpected_response(response))
+        }
+    }
+}
+
+impl UARequest for QueryFirst {
+    type Out = QueryFirstResponse;
+
+    async fn send<'a>(self, channel: &'a crate::AsyncSecureChannel) -> Result<Self::Out, StatusCode>
+    where
+        Self: 'a,
+    {
+        let response = self.send_unchecked(channel).await?;
+        process_service_result(&response.response_header)?;
+        Ok(response)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Continue a query by sending a continuation point in a [`QueryNextRequest`] to the server.
+///
+/// See OPC UA Part 4 - Services 5.9.4 for complete description of the service and error responses.
+pub struct QueryNext {
+    continuation_point: ByteString,
+    release_continuation_point: bool,
+
+    header: RequestHeaderBuilder,
+}
+
+builder_base!(QueryNext);
+
+impl QueryNext {
+    /// Construct a new call to the `QueryNext` service.
+    pub fn new(session: &Session) -> Self {
+        Self {
+            continuation_point: ByteString::null(),
+            release_continuation_point: false,
+
+            header: RequestHeaderBuilder::new_from_session(session),
+        }
+    }
+
+    /// Construct a new call to the `QueryNext` service, setting header parameters manually.
+    pub fn new_manual(
+        session_id: u32,
+        timeout: Duration,
+        auth_token: NodeId,
+        request_handle: IntegerId,
+    ) -> Self {
+        Self {
+            continuation_point: ByteString::null(),
+            release_continuation_point: false,
+
+            header: RequestHeaderBuilder::new(session_id, timeout, auth_token, request_handle),
+        }
+    }
+
+    /// Set the continuation point returned by a previous call to `QueryFirst` or `QueryNext`.
+    pub fn continuation_point(mut self, continuation_point: ByteString) -> Self {
+        self.continuation_point = continuation_point;
+        self
+    }
+
+    /// Set `release_continuation_point`. If this is `true`, the server releases
+    /// the continuation point without returning any more data.
+    pub fn release_continuation_point(mut self, release_continuation_point: bool) -> Self {
+        self.release_continuation_point = release_continuation_point;
+        self
+    }
+}
+
+impl UARequest for QueryNext {
+    type Out = QueryNextResponse;
+
+    async fn send<'a>(self, channel: &'a crate::AsyncSecureChannel) -> Result<Self::Out, StatusCode>
+    where
+        Self: 'a,
+    {
+        if self.continuation_point.is_null() {
+            builder_error!(
+                self,
+                "query_next was not supplied with a continuation point"
+            );
+            return Err(StatusCode::BadNothingToDo);
+        }
+        let request = QueryNextRequest {
+            request_header: self.header.header,
+            release_continuation_point: self.release_continuation_point,
+            continuation_point: self.continuation_point,
+        };
+        let response = channel.send(request, self.header.timeout).await?;
+        if let ResponseMessage::QueryNext(response) = response {
+            builder_debug!(self, "query_next, success");
+            process_service_result(&response.response_header)?;
+            Ok(*response)
+        } else {
+            builder_error!(self, "query_next failed");
+            Err(process_unexpected_response(response))
+        }
+    }
+}
+
+impl Session {
+    /// Find nodes matching a set of type descriptions and a filter by sending a [`QueryFirstRequest`]
+    /// to the server. If the server has more results than it returns, the response contains a
+    /// continuation point, for use with `query_next()`.
+    ///
+    /// See OPC UA Part 4 - Services 5.9.3 for complete description of the service and error responses.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_types` - A list of [`NodeTypeDescription`] giving the types of nodes to return,
+    ///   and which values to return for each node.
+    /// * `filter` - A [`ContentFilter`] the returned nodes must match.
+    /// * `view` - Optional [`ViewDescription`] to query in.
+    /// * `max_data_sets_to_return` - Maximum number of data sets to return, or zero for server-defined.
+    /// * `max_references_to_return` - Maximum number of references to return, or zero for server-defined.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(QueryFirstResponse)` - The response from the server, containing a list of [`QueryDataSet`]
+    ///   and possibly a continuation point.
+    /// * `Err(StatusCode)` - Request failed, [Status code](StatusCode) is the reason for failure.
+    ///
+    pub async fn query_first(
+        &self,
+        node_types: &[NodeTypeDescription],
+        filter: impl Into<ContentFilter>,
+        view: Option<ViewDescription>,
+        max_data_sets_to_return: u32,
+        max_references_to_return: u32,
+    ) -> Result<QueryFirstResponse, StatusCode> {
+        QueryFirst::new(self)
+            .node_types(node_types.to_vec())
+            .filter(filter)
+            .view(view.unwrap_or_default())
+            .max_data_sets_to_return(max_data_sets_to_return)
+            .max_references_to_return(max_references_to_return)
+            .send(&self.channel)
+            .await
+    }
+
+    /// Continue a query by sending a continuation point in a [`QueryNextRequest`] to the server.
+    /// This function may have to be called repeatedly to get all the results of the query.
+    ///
+    /// See OPC UA Part 4 - Services 5.9.4 for complete description of the service and error responses.
+    ///
+    /// # Arguments
+    ///
+    /// * `release_continuation_point` - Flag indicating if the continuation point should be released by the server
+    /// * `continuation_point` - The continuation point returned by `query_first()` or `query_next()`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((Vec<QueryDataSet>, ByteString))` - The next list of [`QueryDataSet`], and the revised
+    ///   continuation point, which is null if there are no more results.
+    /// * `Err(StatusCode)` - Request failed, [Status code](StatusCode) is the reason for failure.
+    ///
+    pub async fn query_next(
+        &self,
+        release_continuation_point: bool,
+        continuation_point: ByteString,
+    ) -> Result<(Vec<QueryDataSet>, ByteString), StatusCode> {
+        let response = QueryNext::new(self)
+            .continuation_point(continuation_point)
+            .release_continuation_point(release_continuation_point)
+            .send(&self.channel)
+            .await?;
+        Ok((
+            response.query_data_sets.unwrap_or_default(),
+            response.revised_continuation_point,
+        ))
+    }
+
+    /// Find all nodes matching a set of type descriptions and a filter, calling `QueryFirst`
+    /// and then `QueryNext` until the server has returned every result.
+    ///
+    /// The filter can be built using [`ContentFilterBuilder`](opcua_types::operand::ContentFilterBuilder),
+    /// for example `ContentFilterBuilder::new().of_type(type_id)`.
+    ///
+    /// See OPC UA Part 4 - Services 5.9 for complete description of the services and error responses.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_types` - A list of [`NodeTypeDescription`] giving the types of nodes to return,
+    ///   and which values to return for each node.
+    /// * `filter` - A [`ContentFilter`] the returned nodes must match.
+    /// * `view` - Optional [`ViewDescription`] to query in.
+    /// * `max_data_sets_per_call` - Maximum number of data sets to return in each call,
+    ///   or zero for server-defined.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<QueryDataSet>)` - Every [`QueryDataSet`] returned by the server.
+    /// * `Err(QueryError)` - Request failed. If the server rejected the node types or the filter,
+    ///   this contains the parsing results and diagnostics returned by the server.
+    ///
+    pub async fn query(
+        &self,
+        node_types: &[NodeTypeDescription],
+        filter: impl Into<ContentFilter>,
+        view: Option<ViewDescription>,
+        max_data_sets_per_call: u32,
+    ) -> Result<Vec<QueryDataSet>, QueryError> {
+        let response = QueryFirst::new(self)
+            .node_types(node_types.to_vec())
+            .filter(filter)
+            .view(view.unwrap_or_default())
+            .max_data_sets_to_return(max_data_sets_per_call)
+            .send_unchecked(&self.channel)
+            .await?;
+        if response.response_header.service_result.is_bad() {
+            return Err(QueryError::from_response(response));
+        }
+        let mut data_sets = response.query_data_sets.unwrap_or_default();
+        let mut continuation_point = response.continuation_point;
+        while !continuation_point.is_null() {
+            match self.query_next(false, continuation_point.clone()).await {
+                Ok((next, revised)) => {
+                    data_sets.extend(next);
+                    continuation_point = revised;
+                }
+                Err(e) => {
+                    // Free the continuation point on the server, in case it is still held.
+                    if let Err(release_err) = self.query_next(true, continuation_point).await {
+                        session_debug!(
+                            self,
+                            "Failed to release query continuation point: {release_err}"
+                        );
+                    }
+                    return Err(e.into());
+                }
+            }
+        }
+        Ok(data_sets)
+    }
+}
@@ -4,18 +4,19 @@ use opcua_core::{
     comms::url::hostname_from_url, sync::RwLock, trace_read_lock, trace_write_lock, ResponseMessage,
 };
 use opcua_crypto::{
-    self, certificate_store::CertificateStore, legacy_encrypt_secret, PKey, SecurityPolicy, X509,
+    self, certificate_store::CertificateStore, ecc_encrypt_secret, legacy_encrypt_secret,
+    rsa_encrypt_secret, user_identity, LegacyEncryptedSecret, PrivateKey, SecurityPolicy, X509,
 };
 use opcua_types::{
-    ActivateSessionRequest, ActivateSessionResponse, AnonymousIdentityToken,
-    ApplicationDescription, ByteString, CancelRequest, CancelResponse, CloseSessionRequest,
-    CloseSessionResponse, CreateSessionRequest, CreateSessionResponse, EndpointDescription, Error,
-    ExtensionObject, IntegerId, IssuedIdentityToken, MessageSecurityMode, NodeId, SignatureData,
-    SignedSoftwareCertificate, StatusCode, UAString, UserNameIdentityToken, UserTokenType,
+    ActivateSessionRequest, ActivateSessionResponse, AdditionalParametersType,
+    AnonymousIdentityToken, ApplicationDescription, ByteString, CancelRequest, CancelResponse,
+    CloseSessionRequest, CloseSessionResponse, CreateSessionRequest, CreateSessionResponse,
+    EndpointDescription, EphemeralKeyType, Error, ExtensionObject, IntegerId, IssuedIdentityToken,
+    MessageSecurityMode, NodeId, ResponseHeader, SignatureData, SignedSoftwareCertificate,
+    StatusCode, UAString, UserNameIdentityToken, UserTokenPolicy, UserTokenType, Variant,
     X509IdentityToken,
 };
-use rsa::RsaPrivateKey;
-use tracing::error;
+use tracing::{error, warn};
 
 use crate::{
     session::{
@@ -25,6 +26,71 @@ use crate::{
     AsyncSecureChannel, IdentityToken, Session, UARequest,
 };
 
+/// Get the security policy used to encrypt the secret of the identity token, if it is an
+/// ECC security policy. Secrets are encrypted with the security policy of the user token policy,
+/// or with the security policy of the endpoint if the user token policy does not have one.
+fn ecdh_security_policy(
+    identity_token: &IdentityToken,
+    endpoint: &EndpointDescription,
+) -> Option<SecurityPolicy> {
+    let token_type = match identity_token {
+        IdentityToken::UserName(_, _) => UserTokenType::UserName,
+        IdentityToken::IssuedToken(_) => UserTokenType::IssuedToken,
+        IdentityToken::Anonymous | IdentityToken::X509(_, _) => return None,
+    };
+    let policy = endpoint.find_policy(token_type)?;
+    let security_policy = if policy.security_policy_uri.is_empty() {
+        SecurityPolicy::from_uri(endpoint.security_policy_uri.as_ref())
+    } else {
+        SecurityPolicy::from_uri(policy.security_policy_uri.as_ref())
+    };
+    security_policy.is_ecc().then_some(security_policy)
+}
+
+/// Create the additional request header used to ask the server for an ephemeral key for
+/// encrypting secrets with the given ECC security policy.
+fn ecdh_request_header(security_policy: Option<SecurityPolicy>) -> ExtensionObject {
+    match security_policy {
+        Some(security_policy) => ExtensionObject::from_message(AdditionalParametersType::new([(
+            user_identity::ECDH_POLICY_URI_PARAMETER,
+            Variant::from(security_policy.to_uri()),
+        )])),
+        None => ExtensionObject::null(),
+    }
+}
+
+/// Read the ephemeral key issued by the server from the additional header of a response,
+/// verifying that it was signed by the server.
+fn read_ecdh_key(
+    response_header: &ResponseHeader,
+    security_policy: SecurityPolicy,
+    server_cert: &ByteString,
+) -> Result<ByteString, Error> {
+    let value = response_header
+        .additional_header
+        .inner_as::<AdditionalParametersType>()
+        .and_then(|p| p.get(user_identity::ECDH_KEY_PARAMETER));
+    let key = match value {
+        Some(Variant::ExtensionObject(o)) => o.inner_as::<EphemeralKeyType>(),
+        Some(Variant::StatusCode(status)) => {
+            return Err(Error::new(
+                *status,
+                "Server rejected the request for an ephemeral key",
+            ))
+        }
+        _ => None,
+    };
+    let Some(key) = key else {
+        return Err(Error::new(
+            StatusCode::BadSecurityPolicyRejected,
+            "Server did not issue an ephemeral key",
+        ));
+    };
+    let server_cert = X509::from_byte_string(server_cert)?;
+    user_identity::verify_ephemeral_key_type(key, security_policy, &server_cert)?;
+    Ok(key.public_key.clone())
+}
+
 #[derive(Clone)]
 /// Sends a [`CreateSessionRequest`] to the server, returning the session id of the created
 /// session. Internally, the session will store the authentication token which is used for requests
@@ -47,6 +113,7 @@ pub struct CreateSession<'a> {
     max_response_message_size: u32,
     certificate_store: &'a RwLock<CertificateStore>,
     endpoint: &'a EndpointDescription,
+    ecdh_security_policy: Option<SecurityPolicy>,
 
     header: RequestHeaderBuilder,
 }
@@ -69,6 +136,10 @@ impl<'a> CreateSession<'a> {
                 .map(|r| r.as_byte_string())
                 .unwrap_or_default(),
             endpoint: &session.endpoint_info().endpoint,
+            ecdh_security_policy: ecdh_security_policy(
+                &session.endpoint_info().user_identity_token,
+                &session.endpoint_info().endpoint,
+            ),
             certificate_store: session.channel.certificate_store(),
             session_timeout: session.session_timeout,
             max_response_message_size: 0,
@@ -95,10 +166,18 @@ impl<'a> CreateSession<'a> {
             max_response_message_size: 0,
             certificate_store,
             endpoint,
+            ecdh_security_policy: None,
             header: RequestHeaderBuilder::new(session_id, timeout, auth_token, request_handle),
         }
     }
 
+    /// Request an ephemeral key from the server, used to encrypt user identity secrets
+    /// with the given ECC security policy when activating the session.
+    pub fn request_ecdh_key(mut self, security_policy: SecurityPolicy) -> Self {
+        self.ecdh_security_policy = Some(security_policy);
+        self
+    }
+
     /// Set the client description.
     pub fn client_description(mut self, desc: impl Into<ApplicationDescription>) -> Self {
         self.client_description = desc.into();
@@ -160,8 +239,12 @@ impl UARequest for CreateSession<'_> {
     where
         Self: 'a,
     {
+        let mut request_header = self.header.header;
+        if self.ecdh_security_policy.is_some() {
+            request_header.additional_header = ecdh_request_header(self.ecdh_security_policy);
+        }
         let request = CreateSessionRequest {
-            request_header: self.header.header,
+            request_header,
             client_description: self.client_description,
             server_uri: self.server_uri,
             endpoint_url: self.endpoint_url,
@@ -206,6 +289,19 @@ impl UARequest for CreateSession<'_> {
                 &response.authentication_token,
             )?;
 
+            if let Some(ecdh_security_policy) = self.ecdh_security_policy {
+                // Failing to get a key is not fatal here, activating the session will fail
+                // later if the key is actually needed.
+                let key = read_ecdh_key(
+                    &response.response_header,
+                    ecdh_security_policy,
+                    &response.server_certificate,
+                )
+                .inspect_err(|e| warn!("Failed to get an ephemeral key from the server: {e}"))
+                .ok();
+                channel.set_ecdh_key(key);
+            }
+
             Ok(*response)
         } else {
             tracing::error!("create_session failed");
@@ -224,7 +320,9 @@ impl UARequest for CreateSession<'_> {
 /// is handled automatically as part of connect/reconnect logic.
 pub struct ActivateSession {
     identity_token: IdentityToken,
-    private_key: Option<PKey<RsaPrivateKey>>,
+    private_key: Option<PrivateKey>,
+    certificate: Option<X509>,
+    rsa_encrypted_secret: bool,
     locale_ids: Vec<UAString>,
     client_software_certificates: Vec<SignedSoftwareCertificate>,
     endpoint: EndpointDescription,
@@ -242,6 +340,8 @@ impl ActivateSession {
         Self {
             identity_token: session.endpoint_info().user_identity_token.clone(),
             private_key: session.channel.read_own_private_key(),
+            certificate: session.channel.read_own_certificate(),
+            rsa_encrypted_secret: false,
             locale_ids: session
                 .endpoint_info()
                 .preferred_locales
@@ -265,6 +365,8 @@ impl ActivateSession {
         Self {
             identity_token: IdentityToken::Anonymous,
             private_key: None,
+            certificate: None,
+            rsa_encrypted_secret: false,
             locale_ids: Vec::new(),
             client_software_certificates: Vec::new(),
             endpoint,
@@ -279,11 +381,26 @@ impl ActivateSession {
     }
 
     /// Set the client private key.
-    pub fn private_key(mut self, private_key: PKey<RsaPrivateKey>) -> Self {
+    pub fn private_key(mut self, private_key: PrivateKey) -> Self {
         self.private_key = Some(private_key);
         self
     }
 
+    /// Set the client certificate. This is used to sign user identity secrets encrypted
+    /// with an ECC security policy, and must be the certificate the session was created with.
+    pub fn certificate(mut self, certificate: X509) -> Self {
+        self.certificate = Some(certificate);
+        self
+    }
+
+    /// Encrypt user identity secrets with an RSA security policy using the
+    /// `RsaEncryptedSecret` format, instead of the legacy format. The server must support
+    /// this format.
+    pub fn rsa_encrypted_secret(mut self, rsa_encrypted_secret: bool) -> Self {
+        self.rsa_encrypted_secret = rsa_encrypted_secret;
+        self
+    }
+
     /// Set the requested list of locales.
     pub fn locale_ids(mut self, locale_ids: Vec<UAString>) -> Self {
         self.locale_ids = locale_ids;
@@ -311,10 +428,88 @@ impl ActivateSession {
         self
     }
 
+    /// Encrypt a user identity secret, selecting the format from the security policy of the
+    /// user token policy, or from the security policy of the channel if the user token policy
+    /// does not have one.
+    #[allow(clippy::too_many_arguments)]
+    fn encrypt_secret(
+        &self,
+        policy: &UserTokenPolicy,
+        remote_nonce: &ByteString,
+        remote_cert: &Option<X509>,
+        ecdh_key: Option<&ByteString>,
+        security_mode: MessageSecurityMode,
+        channel_security_policy: SecurityPolicy,
+        secret: &[u8],
+    ) -> Result<LegacyEncryptedSecret, Error> {
+        let token_security_policy = if policy.security_policy_uri.is_empty() {
+            channel_security_policy
+        } else {
+            SecurityPolicy::from_uri(policy.security_policy_uri.as_ref())
+        };
+
+        let secret = if token_security_policy.is_ecc() {
+            let Some(ecdh_key) = ecdh_key else {
+                return Err(Error::new(
+                    StatusCode::BadIdentityTokenRejected,
+                    "Cannot encrypt secret, the server has not issued an ephemeral key",
+                ));
+            };
+            let (Some(certificate), Some(private_key)) = (&self.certificate, &self.private_key)
+            else {
+                return Err(Error::new(
+                    StatusCode::BadIdentityTokenRejected,
+                    "Cannot encrypt secret, no client certificate to sign it with",
+                ));
+            };
+            ecc_encrypt_secret(
+                token_security_policy,
+                certificate,
+                private_key,
+                ecdh_key.as_ref(),
+                remote_nonce.as_ref(),
+                secret,
+            )?
+        } else if self.rsa_encrypted_secret
+            && token_security_policy
+                .asymmetric_encryption_padding()
+                .is_some()
+        {
+            let Some(remote_cert) = remote_cert else {
+                return Err(Error::new(
+                    StatusCode::BadCertificateInvalid,
+                    "Cannot encrypt secret, the server has no certificate",
+                ));
+            };
+            rsa_encrypt_secret(
+                token_security_policy,
+                remote_cert,
+                remote_nonce.as_ref(),
+                secret,
+            )?
+        } else {
+            return legacy_encrypt_secret(
+                channel_security_policy,
+                security_mode,
+                policy,
+                remote_nonce.as_ref(),
+                remote_cert,
+                secret,
+            );
+        };
+
+        Ok(LegacyEncryptedSecret {
+            policy: policy.policy_id.clone(),
+            secret,
+            encryption_algorithm: UAString::null(),
+        })
+    }
+
     async fn user_identity_token(
         &self,
         remote_nonce: &ByteString,
         remote_cert: &Option<X509>,
+        ecdh_key: Option<&ByteString>,
         security_mode: MessageSecurityMode,
         channel_security_policy: SecurityPolicy,
     ) -> Result<(ExtensionObject, SignatureData), Error> {
@@ -362,14 +557,13 @@ impl ActivateSession {
                 Ok((identity_token, SignatureData::null()))
             }
             IdentityToken::UserName(user, pass) => {
-                let nonce = remote_nonce.as_ref();
-                let cert = remote_cert;
-                let secret = legacy_encrypt_secret(
-                    channel_security_policy,
-                    security_mode,
+                let secret = self.encrypt_secret(
                     policy,
-                    nonce,
-                    cert,
+                    remote_nonce,
+                    remote_cert,
+                    ecdh_key,
+                    security_mode,
+                    channel_security_policy,
                     pass.0.as_bytes(),
                 )?;
                 let identity_token = UserNameIdentityToken {
@@ -412,14 +606,13 @@ impl ActivateSession {
             }
             IdentityToken::IssuedToken(source) => {
                 let token = source.0.get_issued_token().await?;
-                let nonce = remote_nonce.as_ref();
-                let cert = remote_cert;
-                let secret = legacy_encrypt_secret(
-                    channel_security_policy,
-                    security_mode,
+                let secret = self.encrypt_secret(
                     policy,
-                    nonce,
-                    cert,
+                    remote_nonce,
+                    remote_cert,
+                    ecdh_key,
+                    security_mode,
+                    channel_security_policy,
                     token.as_ref(),
                 )?;
                 let identity_token = IssuedIdentityToken {
@@ -448,10 +641,13 @@ impl ActivateSession {
                 secure_channel.security_mode(),
             )
         };
+        let ecdh_key = channel.ecdh_key();
+        let ecdh_security_policy = ecdh_security_policy(&self.identity_token, &self.endpoint);
         let (user_identity_token, user_token_signature) = self
             .user_identity_token(
                 &remote_nonce,
                 &remote_cert,
+                ecdh_key.as_deref(),
                 message_security_mode,
                 security_policy,
             )
@@ -485,8 +681,14 @@ impl ActivateSession {
             }
         };
 
+        let mut request_header = self.header.header;
+        if ecdh_security_policy.is_some() {
+            // Ask for a new key, since each key may only be used once.
+            request_header.additional_header = ecdh_request_header(ecdh_security_policy);
+        }
+
         Ok(ActivateSessionRequest {
-            request_header: self.header.header,
+            request_header,
             client_signature,
             client_software_certificates: if self.client_software_certificates.is_empty() {
                 None
@@ -512,6 +714,7 @@ impl UARequest for ActivateSession {
         Self: 'a,
     {
         let timeout = self.header.timeout;
+        let ecdh_security_policy = ecdh_security_policy(&self.identity_token, &self.endpoint);
         let request = self.build_request(channel).await?;
 
         let response = channel.send(request, timeout).await?;
@@ -520,6 +723,23 @@ impl UARequest for ActivateSession {
             tracing::debug!("activate_session success");
             // trace!("ActivateSessionResponse = {:#?}", response);
             process_service_result(&response.response_header)?;
+            if let Some(ecdh_security_policy) = ecdh_security_policy {
+                let server_cert = {
+                    let secure_channel = trace_read_lock!(channel.secure_channel);
+                    secure_channel
+                        .remote_cert()
+                        .map(|c| c.as_byte_string())
+                        .unwrap_or_default()
+                };
+                let key = read_ecdh_key(
+                    &response.response_header,
+                    ecdh_security_policy,
+                    &server_cert,
+                )
+                .inspect_err(|e| warn!("Failed to get an ephemeral key from the server: {e}"))
+                .ok();
+                channel.set_ecdh_key(key);
+            }
             Ok(*response)
         } else {
             tracing::error!("activate_session failed");
@@ -1767,6 +1767,8 @@ impl Session {
     ///
     /// * `Ok(Vec<MonitoredItemCreateResult>)` - A list of [`MonitoredItemCreateResult`] corresponding to the items to create.
     ///   The size and order of the list matches the size and order of the `items_to_create` request parameter.
+    ///   If the items are split into several requests because of the server operation limits, and some
+    ///   of those requests fail, the items sent in the failed requests have the error as their status code.
     /// * `Err(StatusCode)` - Request failed, [Status code](StatusCode) is the reason for failure.
     ///
     pub async fn create_monitored_items(
@@ -1786,32 +1788,51 @@ impl Session {
                 return Err(StatusCode::BadSubscriptionIdInvalid);
             }
         }
-        let result = CreateMonitoredItems::new(subscription_id, self)
-            .items_to_create(items_to_create)
-            .timestamps_to_return(timestamps_to_return)
-            .send(&self.channel)
-            .await?;
-        // Set the items in our internal state
-        let items_to_create = result
-            .results
-            .iter()
-            .map(|item| CreateMonitoredItem {
-                id: item.result.monitored_item_id,
-                client_handle: item.requested_parameters.client_handle,
-                discard_oldest: item.requested_parameters.discard_oldest,
-                item_to_monitor: item.item_to_monitor.clone(),
-                monitoring_mode: item.monitoring_mode,
-                queue_size: item.result.revised_queue_size,
-                sampling_interval: item.result.revised_sampling_interval,
-                filter: item.requested_parameters.filter.clone(),
-            })
-            .collect::<Vec<CreateMonitoredItem>>();
-        {
-            let mut subscription_state = trace_lock!(self.subscription_state);
-            subscription_state.insert_monitored_items(subscription_id, items_to_create);
-        }
+        let limit = self.operation_limits().max_monitored_items_per_call;
+        self.send_chunked_per_item(
+            items_to_create,
+            limit,
+            |chunk| async move {
+                let result = CreateMonitoredItems::new(subscription_id, self)
+                    .items_to_create(chunk)
+                    .timestamps_to_return(timestamps_to_return)
+                    .send(&self.channel)
+                    .await?;
+                // Set the items in our internal state
+                let items_to_create = result
+                    .results
+                    .iter()
+                    .map(|item| CreateMonitoredItem {
+                        id: item.result.monitored_item_id,
+                        client_handle: item.requested_parameters.client_handle,
+                        discard_oldest: item.requested_parameters.discard_oldest,
+                        item_to_monitor: item.item_to_monitor.clone(),
+                        monitoring_mode: item.monitoring_mode,
+                        queue_size: item.result.revised_queue_size,
+                        sampling_interval: item.result.revised_sampling_interval,
+                        filter: item.requested_parameters.filter.clone(),
+                    })
+                    .collect::<Vec<CreateMonitoredItem>>();
+                {
+                    let mut subscription_state = trace_lock!(self.subscription_state);
+                    subscription_state.insert_monitored_items(subscription_id, items_to_create);
+                }
 
-        Ok(result.results)
+                Ok(result.results)
+            },
+            // Items in chunks that failed were not created, but items in other chunks were,
+            // so report the failure for each item instead of failing the whole call.
+            |item, status_code| CreatedMonitoredItem {
+                result: MonitoredItemCreateResult {
+                    status_code,
+                    ..Default::default()
+                },
+                requested_parameters: item.requested_parameters,
+                monitoring_mode: item.monitoring_mode,
+                item_to_monitor: item.item_to_monitor,
+            },
+        )
+        .await
     }
 
     /// Modifies monitored items on a subscription by sending a [`ModifyMonitoredItemsRequest`] to the server.
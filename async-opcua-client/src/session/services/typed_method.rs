@@ -0,0 +1,736 @@
+//! Typed method calls, where input arguments are encoded from Rust values and
+//! outputs decoded into a tuple of types implementing [TryFromVariant].
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use opcua_types::{
+    Argument, AttributeId, BrowseDescription, BrowseDirection, BrowsePath, BrowseResultMask,
+    CallMethodRequest, DataTypeId, DiagnosticInfo, NodeClass, NodeClassMask, NodeId, QualifiedName,
+    ReadValueId, ReferenceTypeId, StatusCode, TimestampsToReturn, TryFromVariant, UAString,
+    Variant, VariantScalarTypeId,
+};
+
+use crate::Session;
+
+/// A single invalid argument in a method call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArgumentError {
+    /// Index of the argument.
+    pub index: usize,
+    /// Name of the argument, if known.
+    pub name: Option<UAString>,
+    /// Reason the argument is invalid.
+    pub status: StatusCode,
+    /// Diagnostic info returned by the server, if any.
+    pub diagnostic_info: Option<DiagnosticInfo>,
+}
+
+impl ArgumentError {
+    fn new(index: usize, argument: Option<&Argument>, status: StatusCode) -> Self {
+        Self {
+            index,
+            name: argument.map(|a| a.name.clone()),
+            status,
+            diagnostic_info: None,
+        }
+    }
+}
+
+impl Display for ArgumentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "argument {} ({}): {}", self.index, name, self.status),
+            None => write!(f, "argument {}: {}", self.index, self.status),
+        }
+    }
+}
+
+fn fmt_argument_errors(errors: &[ArgumentError]) -> String {
+    errors
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Error returned from typed method calls.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum CallError {
+    /// The request failed, or the method call failed with the given status.
+    #[error("Method call failed: {0}")]
+    Status(StatusCode),
+    /// One or more input arguments were rejected, either before sending the
+    /// request, or by the server.
+    #[error("Invalid input arguments: {}", fmt_argument_errors(.0))]
+    InputArguments(Vec<ArgumentError>),
+    /// One or more output arguments were missing or could not be decoded.
+    #[error("Invalid output arguments: {}", fmt_argument_errors(.0))]
+    OutputArguments(Vec<ArgumentError>),
+}
+
+impl CallError {
+    /// Get a status code representing this error.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            CallError::Status(s) => *s,
+            CallError::InputArguments(_) => StatusCode::BadInvalidArgument,
+            CallError::OutputArguments(_) => StatusCode::BadUnexpectedError,
+        }
+    }
+}
+
+impl From<StatusCode> for CallError {
+    fn from(value: StatusCode) -> Self {
+        Self::Status(value)
+    }
+}
+
+/// Trait for lists of method input arguments. This is implemented for tuples of up to
+/// 8 types implementing `Into<Variant>`, and for `Vec<Variant>`.
+pub trait CallInputs {
+    /// Convert the inputs into a list of variants.
+    fn into_variants(self) -> Vec<Variant>;
+}
+
+/// Trait for lists of method outputs. This is implemented for tuples of up to
+/// 8 types implementing [TryFromVariant], and for `Vec<Variant>`.
+pub trait CallOutputs: Sized {
+    /// The expected number of outputs, or `None` if any number of outputs is accepted.
+    fn count() -> Option<usize>;
+
+    /// Decode the outputs. On failure, the result for each output is returned.
+    fn decode(outputs: Vec<Variant>) -> Result<Self, Vec<StatusCode>>;
+}
+
+impl CallInputs for Vec<Variant> {
+    fn into_variants(self) -> Vec<Variant> {
+        self
+    }
+}
+
+impl CallOutputs for Vec<Variant> {
+    fn count() -> Option<usize> {
+        None
+    }
+
+    fn decode(outputs: Vec<Variant>) -> Result<Self, Vec<StatusCode>> {
+        Ok(outputs)
+    }
+}
+
+macro_rules! impl_call_tuple {
+    ($($t:ident $v:ident),*) => {
+        impl<$($t),*> CallInputs for ($($t,)*)
+        where
+            $($t: Into<Variant>),*
+        {
+            fn into_variants(self) -> Vec<Variant> {
+                let ($($v,)*) = self;
+                vec![$($v.into()),*]
+            }
+        }
+
+        impl<$($t),*> CallOutputs for ($($t,)*)
+        where
+            $($t: TryFromVariant),*
+        {
+            fn count() -> Option<usize> {
+                let names: &[&str] = &[$(stringify!($t)),*];
+                Some(names.len())
+            }
+
+            #[allow(unused)]
+            fn decode(outputs: Vec<Variant>) -> Result<Self, Vec<StatusCode>> {
+                let mut outputs = outputs.into_iter();
+                let mut results = Vec::new();
+                $(
+                    let $v = $t::try_from_variant(outputs.next().unwrap_or_default()).ok();
+                    results.push(if $v.is_some() {
+                        StatusCode::Good
+                    } else {
+                        StatusCode::BadTypeMismatch
+                    });
+                )*
+                match ($($v,)*) {
+                    ($(Some($v),)*) => Ok(($($v,)*)),
+                    #[allow(unreachable_patterns)]
+                    _ => Err(results),
+                }
+            }
+        }
+    };
+}
+
+impl_call_tuple!();
+impl_call_tuple!(T1 v1);
+impl_call_tuple!(T1 v1, T2 v2);
+impl_call_tuple!(T1 v1, T2 v2, T3 v3);
+impl_call_tuple!(T1 v1, T2 v2, T3 v3, T4 v4);
+impl_call_tuple!(T1 v1, T2 v2, T3 v3, T4 v4, T5 v5);
+impl_call_tuple!(T1 v1, T2 v2, T3 v3, T4 v4, T5 v5, T6 v6);
+impl_call_tuple!(T1 v1, T2 v2, T3 v3, T4 v4, T5 v5, T6 v6, T7 v7);
+impl_call_tuple!(T1 v1, T2 v2, T3 v3, T4 v4, T5 v5, T6 v6, T7 v7, T8 v8);
+
+/// The input and output arguments of a method, read from the
+/// `InputArguments` and `OutputArguments` properties.
+#[derive(Debug, Clone, Default)]
+pub struct MethodArguments {
+    /// Input arguments of the method.
+    pub inputs: Vec<Argument>,
+    /// Output arguments of the method.
+    pub outputs: Vec<Argument>,
+    /// The supertype of each argument data type that is not a built-in type, and of
+    /// their supertypes in turn, up to a built-in type. Values of these data types are
+    /// validated against the built-in type they derive from.
+    pub supertypes: HashMap<NodeId, NodeId>,
+}
+
+impl MethodArguments {
+    /// Validate a list of input arguments against the method input arguments.
+    pub fn validate_inputs(&self, inputs: &[Variant]) -> Result<(), CallError> {
+        self.validate(&self.inputs, inputs)
+            .map_err(CallError::InputArguments)
+    }
+
+    /// Validate a list of outputs against the method output arguments.
+    pub fn validate_outputs(&self, outputs: &[Variant]) -> Result<(), CallError> {
+        self.validate(&self.outputs, outputs)
+            .map_err(CallError::OutputArguments)
+    }
+
+    fn validate(
+        &self,
+        arguments: &[Argument],
+        values: &[Variant],
+    ) -> Result<(), Vec<ArgumentError>> {
+        let mut errors = Vec::new();
+        for (index, arg) in arguments.iter().enumerate() {
+            let status = match values.get(index) {
+                None => StatusCode::BadArgumentsMissing,
+                Some(v) if !self.data_type_matches(&arg.data_type, v) => {
+                    StatusCode::BadTypeMismatch
+                }
+                Some(v) if !dimensions_match(arg, v) => StatusCode::BadOutOfRange,
+                _ => continue,
+            };
+            errors.push(ArgumentError::new(index, Some(arg), status));
+        }
+        for index in arguments.len()..values.len() {
+            errors.push(ArgumentError::new(
+                index,
+                None,
+                StatusCode::BadTooManyArguments,
+            ));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Check whether `value` is valid for the data type `data_type`.
+    ///
+    /// Other data types are checked against the built-in type they derive from, found
+    /// through `supertypes`. Data types with no known supertype are not checked.
+    fn data_type_matches(&self, data_type: &NodeId, value: &Variant) -> bool {
+        let mut data_type = data_type;
+        // Guard against loops in a broken type hierarchy.
+        for _ in 0..=self.supertypes.len() {
+            if let Some(matches) = builtin_type_matches(data_type, value) {
+                return matches;
+            }
+            match self.supertypes.get(data_type) {
+                Some(parent) => data_type = parent,
+                None => return true,
+            }
+        }
+        true
+    }
+}
+
+/// Check whether `value` is valid for the built-in data type `data_type`, or one of
+/// the abstract supertypes of the built-in types.
+///
+/// Returns `None` if `data_type` is not one of these types.
+fn builtin_type_matches(data_type: &NodeId, value: &Variant) -> Option<bool> {
+    let data_type = data_type.as_data_type_id().ok()?;
+    // Null values only match BaseDataType.
+    let scalar = value.scalar_type_id();
+    let is = |t| scalar == Some(t);
+    use VariantScalarTypeId as V;
+    Some(match data_type {
+        DataTypeId::BaseDataType => true,
+        DataTypeId::Number => matches!(
+            scalar,
+            Some(
+                V::SByte
+                    | V::Byte
+                    | V::Int16
+                    | V::UInt16
+                    | V::Int32
+                    | V::UInt32
+                    | V::Int64
+                    | V::UInt64
+                    | V::Float
+                    | V::Double
+            )
+        ),
+        DataTypeId::Integer => matches!(scalar, Some(V::SByte | V::Int16 | V::Int32 | V::Int64)),
+        DataTypeId::UInteger => {
+            matches!(scalar, Some(V::Byte | V::UInt16 | V::UInt32 | V::UInt64))
+        }
+        DataTypeId::Enumeration => is(V::Int32),
+        DataTypeId::Structure => is(V::ExtensionObject),
+        DataTypeId::Boolean => is(V::Boolean),
+        DataTypeId::SByte => is(V::SByte),
+        DataTypeId::Byte => is(V::Byte),
+        DataTypeId::Int16 => is(V::Int16),
+        DataTypeId::UInt16 => is(V::UInt16),
+        DataTypeId::Int32 => is(V::Int32),
+        DataTypeId::UInt32 => is(V::UInt32),
+        DataTypeId::Int64 => is(V::Int64),
+        DataTypeId::UInt64 => is(V::UInt64),
+        DataTypeId::Float => is(V::Float),
+        DataTypeId::Double => is(V::Double),
+        DataTypeId::String => is(V::String),
+        DataTypeId::DateTime => is(V::DateTime),
+        DataTypeId::Guid => is(V::Guid),
+        DataTypeId::ByteString => is(V::ByteString),
+        DataTypeId::XmlElement => is(V::XmlElement),
+        DataTypeId::NodeId => is(V::NodeId),
+        DataTypeId::ExpandedNodeId => is(V::ExpandedNodeId),
+        DataTypeId::StatusCode => is(V::StatusCode),
+        DataTypeId::QualifiedName => is(V::QualifiedName),
+        DataTypeId::LocalizedText => is(V::LocalizedText),
+        DataTypeId::DataValue => is(V::DataValue),
+        DataTypeId::DiagnosticInfo => is(V::DiagnosticInfo),
+        _ => return None,
+    })
+}
+
+/// Check whether `value` matches the value rank and array dimensions of `arg`.
+fn dimensions_match(arg: &Argument, value: &Variant) -> bool {
+    let dims = match value {
+        // Null values are allowed for any value rank.
+        Variant::Empty => return true,
+        Variant::Array(a) => match &a.dimensions {
+            Some(d) => d.clone(),
+            None => vec![a.values.len() as u32],
+        },
+        _ => Vec::new(),
+    };
+    let rank_ok = match arg.value_rank {
+        -3 => dims.len() <= 1,
+        -2 => true,
+        -1 => dims.is_empty(),
+        0 => !dims.is_empty(),
+        n => dims.len() == n as usize,
+    };
+    if !rank_ok {
+        return false;
+    }
+    // An array dimension of 0 means the length of that dimension is unknown.
+    match &arg.array_dimensions {
+        Some(max) if max.len() == dims.len() => {
+            dims.iter().zip(max).all(|(d, m)| *m == 0 || d <= m)
+        }
+        _ => true,
+    }
+}
+
+impl Session {
+    /// Call a method with typed inputs and outputs.
+    ///
+    /// `inputs` is typically a tuple of values convertible to [Variant], and the
+    /// outputs are decoded into a tuple of types implementing [TryFromVariant].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let (sum,): (i32,) = session.call_typed(object_id, method_id, (1, 2)).await?;
+    /// ```
+    ///
+    /// # Arguments
+    ///
+    /// * `object_id` - The object or object type the method is called on.
+    /// * `method_id` - The method to call.
+    /// * `inputs` - Input arguments to the method.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(O)` - The decoded outputs of the method.
+    /// * `Err(CallError)` - The call failed, or the server rejected one or more input arguments,
+    ///   or the outputs could not be decoded.
+    pub async fn call_typed<I: CallInputs, O: CallOutputs>(
+        &self,
+        object_id: impl Into<NodeId>,
+        method_id: impl Into<NodeId>,
+        inputs: I,
+    ) -> Result<O, CallError> {
+        self.call_typed_inner(
+            object_id.into(),
+            method_id.into(),
+            inputs.into_variants(),
+            None,
+        )
+        .await
+    }
+
+    /// Call a method with typed inputs and outputs, like [Session::call_typed], but
+    /// first read the `InputArguments` and `OutputArguments` properties of the method.
+    ///
+    /// The inputs are validated against the data types, value ranks, and array dimensions
+    /// of the input arguments before the request is sent, and the outputs are validated
+    /// against the output arguments. Data types that are not built-in types are checked
+    /// against the built-in type they derive from.
+    ///
+    /// If you call the same method repeatedly, consider reading the arguments once with
+    /// [Session::read_method_arguments] and calling
+    /// [Session::call_typed_with_arguments] instead.
+    pub async fn call_typed_validated<I: CallInputs, O: CallOutputs>(
+        &self,
+        object_id: impl Into<NodeId>,
+        method_id: impl Into<NodeId>,
+        inputs: I,
+    ) -> Result<O, CallError> {
+        let method_id = method_id.into();
+        let arguments = self.read_method_arguments(&method_id).await?;
+        self.call_typed_inner(
+            object_id.into(),
+            method_id,
+            inputs.into_variants(),
+            Some(&arguments),
+        )
+        .await
+    }
+
+    /// Call a method with typed inputs and outputs, validating inputs and outputs
+    /// against the given method arguments.
+    ///
+    /// See [Session::call_typed_validated].
+    pub async fn call_typed_with_arguments<I: CallInputs, O: CallOutputs>(
+        &self,
+        object_id: impl Into<NodeId>,
+        method_id: impl Into<NodeId>,
+        inputs: I,
+        arguments: &MethodArguments,
+    ) -> Result<O, CallError> {
+        self.call_typed_inner(
+            object_id.into(),
+            method_id.into(),
+            inputs.into_variants(),
+            Some(arguments),
+        )
+        .await
+    }
+
+    /// Read the `InputArguments` and `OutputArguments` properties of a method, and the
+    /// supertypes of any argument data types that are not built-in types.
+    ///
+    /// A method without an `InputArguments` or `OutputArguments` property has no
+    /// input or output arguments.
+    ///
+    /// # Arguments
+    ///
+    /// * `method_id` - The method to read arguments for.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(MethodArguments)` - The input and output arguments of the method.
+    /// * `Err(StatusCode)` - Request failed, or the method or one of its argument properties
+    ///   could not be read. [Status code](StatusCode) is the reason for failure.
+    pub async fn read_method_arguments(
+        &self,
+        method_id: &NodeId,
+    ) -> Result<MethodArguments, StatusCode> {
+        let paths: Vec<_> = ["InputArguments", "OutputArguments"]
+            .into_iter()
+            .map(|name| BrowsePath {
+                starting_node: method_id.clone(),
+                relative_path: (&[QualifiedName::new(0, name)] as &[QualifiedName]).into(),
+            })
+            .collect();
+        let results = self.translate_browse_paths_to_node_ids(&paths).await?;
+        let ids = results
+            .into_iter()
+            .map(|r| {
+                // BadNoMatch means the property does not exist, any other error means
+                // the method itself could not be browsed.
+                if r.status_code == StatusCode::BadNoMatch {
+                    return Ok(None);
+                }
+                if r.status_code.is_bad() {
+                    return Err(r.status_code);
+                }
+                Ok(r.targets
+                    .and_then(|t| t.into_iter().next())
+                    .map(|t| t.target_id.node_id))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // The node class of the method is read as well, since a method that does not
+        // exist also has no argument properties.
+        let to_read: Vec<_> = [ReadValueId {
+            node_id: method_id.clone(),
+            attribute_id: AttributeId::NodeClass as u32,
+            ..Default::default()
+        }]
+        .into_iter()
+        .chain(ids.iter().flatten().map(|id| ReadValueId {
+            node_id: id.clone(),
+            attribute_id: AttributeId::Value as u32,
+            ..Default::default()
+        }))
+        .collect();
+        let mut values = self
+            .read(&to_read, TimestampsToReturn::Neither, 0.0)
+            .await?
+            .into_iter();
+        let node_class = values.next().ok_or(StatusCode::BadUnexpectedError)?;
+        if let Some(status) = node_class.status.filter(|s| s.is_bad()) {
+            return Err(status);
+        }
+        if node_class.value != Some(Variant::Int32(NodeClass::Method as i32)) {
+            return Err(StatusCode::BadMethodInvalid);
+        }
+
+        let mut arguments = ids.iter().map(|id| -> Result<Vec<Argument>, StatusCode> {
+            if id.is_none() {
+                return Ok(Vec::new());
+            }
+            let value = values.next().ok_or(StatusCode::BadUnexpectedError)?;
+            if let Some(status) = value.status.filter(|s| s.is_bad()) {
+                return Err(status);
+            }
+            match value.value {
+                None | Some(Variant::Empty) => Ok(Vec::new()),
+                Some(v) => v.try_cast_to().map_err(|_| StatusCode::BadTypeMismatch),
+            }
+        });
+        let mut arguments = MethodArguments {
+            inputs: arguments.next().unwrap_or_else(|| Ok(Vec::new()))?,
+            outputs: arguments.next().unwrap_or_else(|| Ok(Vec::new()))?,
+            supertypes: HashMap::new(),
+        };
+        arguments.supertypes = self.read_supertypes(&arguments).await?;
+        Ok(arguments)
+    }
+
+    /// Browse the supertypes of the argument data types that are not built-in types,
+    /// until a built-in type is reached.
+    async fn read_supertypes(
+        &self,
+        arguments: &MethodArguments,
+    ) -> Result<HashMap<NodeId, NodeId>, StatusCode> {
+        let is_unresolved = |id: &NodeId| builtin_type_matches(id, &Variant::Empty).is_none();
+        let mut seen = HashSet::new();
+        let mut to_browse: Vec<_> = arguments
+            .inputs
+            .iter()
+            .chain(arguments.outputs.iter())
+            .map(|a| a.data_type.clone())
+            .filter(|id| is_unresolved(id) && seen.insert(id.clone()))
+            .collect();
+        let mut supertypes = HashMap::new();
+        while !to_browse.is_empty() {
+            let descriptions: Vec<_> = to_browse
+                .iter()
+                .map(|id| BrowseDescription {
+                    node_id: id.clone(),
+                    browse_direction: BrowseDirection::Inverse,
+                    reference_type_id: ReferenceTypeId::HasSubtype.into(),
+                    include_subtypes: false,
+                    node_class_mask: NodeClassMask::DATA_TYPE.bits(),
+                    result_mask: BrowseResultMask::None as u32,
+                })
+                .collect();
+            let results = self.browse(&descriptions, 1, None).await?;
+            let mut next = Vec::new();
+            for (id, result) in to_browse.into_iter().zip(results) {
+                if result.status_code.is_bad() {
+                    return Err(result.status_code);
+                }
+                let Some(parent) = result
+                    .references
+                    .and_then(|r| r.into_iter().next())
+                    .map(|r| r.node_id.node_id)
+                else {
+                    continue;
+                };
+                if is_unresolved(&parent) && seen.insert(parent.clone()) {
+                    next.push(parent.clone());
+                }
+                supertypes.insert(id, parent);
+            }
+            to_browse = next;
+        }
+        Ok(supertypes)
+    }
+
+    async fn call_typed_inner<O: CallOutputs>(
+        &self,
+        object_id: NodeId,
+        method_id: NodeId,
+        inputs: Vec<Variant>,
+        arguments: Option<&MethodArguments>,
+    ) -> Result<O, CallError> {
+        if let Some(arguments) = arguments {
+            arguments.validate_inputs(&inputs)?;
+        }
+        let input_names: Vec<_> = arguments
+            .map(|a| a.inputs.iter().map(|a| a.name.clone()).collect())
+            .unwrap_or_default();
+
+        let result = self
+            .call_one(CallMethodRequest {
+                object_id,
+                method_id,
+                input_arguments: Some(inputs),
+            })
+            .await?;
+
+        if let Some(results) = &result.input_argument_results {
+            let diagnostics = result.input_argument_diagnostic_infos.as_deref();
+            let errors: Vec<_> = results
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| s.is_bad())
+                .map(|(index, status)| ArgumentError {
+                    index,
+                    name: input_names.get(index).cloned(),
+                    status: *status,
+                    diagnostic_info: diagnostics.and_then(|d| d.get(index)).cloned(),
+                })
+                .collect();
+            if !errors.is_empty() {
+                return Err(CallError::InputArguments(errors));
+            }
+        }
+        if result.status_code.is_bad() {
+            return Err(CallError::Status(result.status_code));
+        }
+
+        let outputs = result.output_arguments.unwrap_or_default();
+        if let Some(arguments) = arguments {
+            arguments.validate_outputs(&outputs)?;
+        }
+        if let Some(count) = O::count() {
+            if outputs.len() != count {
+                let status = if outputs.len() < count {
+                    StatusCode::BadArgumentsMissing
+                } else {
+                    StatusCode::BadTooManyArguments
+                };
+                return Err(CallError::OutputArguments(vec![ArgumentError::new(
+                    outputs.len().min(count),
+                    None,
+                    status,
+                )]));
+            }
+        }
+        O::decode(outputs).map_err(|results| {
+            let output_args = arguments.map(|a| &a.outputs);
+            CallError::OutputArguments(
+                results
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, s)| s.is_bad())
+                    .map(|(index, status)| {
+                        ArgumentError::new(index, output_args.and_then(|a| a.get(index)), status)
+                    })
+                    .collect(),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opcua_types::{Argument, DataTypeId, NodeId, StatusCode, Variant};
+
+    use super::{CallError, CallOutputs, MethodArguments};
+
+    #[test]
+    fn decode_outputs() {
+        let out = <(i32, String)>::decode(vec![5.into(), "hello".into()]).unwrap();
+        assert_eq!(out, (5, "hello".to_owned()));
+
+        let err = <(i32, bool)>::decode(vec!["hello".into(), true.into()]).unwrap_err();
+        assert_eq!(err, vec![StatusCode::BadTypeMismatch, StatusCode::Good]);
+    }
+
+    #[test]
+    fn validate_arguments() {
+        let mut arr: Argument = ("Values", DataTypeId::Int32).into();
+        arr.value_rank = 1;
+        arr.array_dimensions = Some(vec![2]);
+        let args = MethodArguments {
+            inputs: vec![("Name", DataTypeId::String).into(), arr],
+            outputs: vec![("Result", DataTypeId::Number).into()],
+            ..Default::default()
+        };
+
+        args.validate_inputs(&["a".into(), vec![1i32, 2].into()])
+            .unwrap();
+        args.validate_outputs(&[1.5f64.into()]).unwrap();
+
+        let Err(CallError::InputArguments(errs)) =
+            args.validate_inputs(&[1i32.into(), vec![1i32, 2, 3].into()])
+        else {
+            panic!("Expected argument errors");
+        };
+        assert_eq!(errs.len(), 2);
+        assert_eq!(errs[0].status, StatusCode::BadTypeMismatch);
+        assert_eq!(errs[0].name.as_ref().unwrap().as_ref(), "Name");
+        assert_eq!(errs[1].status, StatusCode::BadOutOfRange);
+
+        let Err(CallError::InputArguments(errs)) = args.validate_inputs(&["a".into()]) else {
+            panic!("Expected argument errors");
+        };
+        assert_eq!(errs[0].index, 1);
+        assert_eq!(errs[0].status, StatusCode::BadArgumentsMissing);
+
+        let Err(CallError::OutputArguments(errs)) =
+            args.validate_outputs(&[1.5f64.into(), Variant::Empty])
+        else {
+            panic!("Expected argument errors");
+        };
+        assert_eq!(errs[0].index, 1);
+        assert_eq!(errs[0].status, StatusCode::BadTooManyArguments);
+    }
+
+    #[test]
+    fn validate_subtypes() {
+        let custom_enum = NodeId::new(1, 1);
+        let mut state: Argument = ("State", DataTypeId::Int32).into();
+        state.data_type = custom_enum.clone();
+        let mut unknown: Argument = ("Unknown", DataTypeId::Int32).into();
+        unknown.data_type = NodeId::new(1, 2);
+        let args = MethodArguments {
+            inputs: vec![("Duration", DataTypeId::Duration).into(), state, unknown],
+            outputs: Vec::new(),
+            supertypes: [
+                (DataTypeId::Duration.into(), DataTypeId::Double.into()),
+                (custom_enum, DataTypeId::Enumeration.into()),
+            ]
+            .into_iter()
+            .collect(),
+        };
+
+        args.validate_inputs(&[1.5f64.into(), 2i32.into(), "a".into()])
+            .unwrap();
+
+        let Err(CallError::InputArguments(errs)) =
+            args.validate_inputs(&[1i32.into(), "a".into(), "a".into()])
+        else {
+            panic!("Expected argument errors");
+        };
+        assert_eq!(errs.len(), 2);
+        assert_eq!(errs[0].index, 0);
+        assert_eq!(errs[1].index, 1);
+        assert!(errs.iter().all(|e| e.status == StatusCode::BadTypeMismatch));
+    }
+}
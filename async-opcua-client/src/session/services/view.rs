@@ -462,14 +462,37 @@ impl Session {
         max_references_per_node: u32,
         view: Option<ViewDescription>,
     ) -> Result<Vec<BrowseResult>, StatusCode> {
-        Ok(Browse::new(self)
-            .nodes_to_browse(nodes_to_browse.to_vec())
-            .view(view.unwrap_or_default())
-            .max_references_per_node(max_references_per_node)
-            .send(&self.channel)
-            .await?
-            .results
-            .unwrap_or_default())
+        let limit = self.operation_limits().max_nodes_per_browse;
+        let view = view.unwrap_or_default();
+        self.send_chunked_or_release(
+            nodes_to_browse.to_vec(),
+            limit,
+            |chunk| {
+                let view = view.clone();
+                async move {
+                    Ok(Browse::new(self)
+                        .nodes_to_browse(chunk)
+                        .view(view)
+                        .max_references_per_node(max_references_per_node)
+                        .send(&self.channel)
+                        .await?
+                        .results
+                        .unwrap_or_default())
+                }
+            },
+            |succeeded| async move {
+                let continuation_points: Vec<_> = succeeded
+                    .into_iter()
+                    .map(|(_, r)| r.continuation_point)
+                    .filter(|c| !c.is_null())
+                    .collect();
+                if !continuation_points.is_empty() {
+                    self.browse_next(true, &continuation_points).await?;
+                }
+                Ok(())
+            },
+        )
+        .await
     }
 
     /// Continue to discover references to nodes by sending continuation points in a [`BrowseNextRequest`]
@@ -44,6 +44,8 @@ pub struct AsyncSecureChannel {
 
     request_send: ArcSwapOption<RequestSend>,
     encoding_context: Arc<RwLock<ContextOwned>>,
+    /// Ephemeral key last issued by the server for encrypting user identity secrets.
+    ecdh_key: ArcSwapOption<ByteString>,
 }
 
 /// Event loop for a secure channel. This must be polled to make progress.
@@ -87,6 +89,17 @@ impl AsyncSecureChannel {
         Ok(())
     }
 
+    /// Get the ephemeral key last issued by the server for encrypting user identity secrets
+    /// with an ECC security policy.
+    pub(crate) fn ecdh_key(&self) -> Option<Arc<ByteString>> {
+        self.ecdh_key.load_full()
+    }
+
+    /// Set the ephemeral key issued by the server.
+    pub(crate) fn set_ecdh_key(&self, key: Option<ByteString>) {
+        self.ecdh_key.store(key.map(Arc::new));
+    }
+
     pub(crate) fn security_policy(&self) -> SecurityPolicy {
         let secure_channel = trace_read_lock!(self.secure_channel);
         secure_channel.security_policy()
@@ -107,14 +120,22 @@ impl AsyncSecureChannel {
         self.state.set_auth_token(token);
     }
 
+    /// Read our private key for the security policy of the channel.
     pub(crate) fn read_own_private_key(&self) -> Option<PrivateKey> {
         let cert_store = trace_read_lock!(self.certificate_store);
-        cert_store.read_own_pkey().ok()
+        match self.security_policy().ecc_curve() {
+            Some(curve) => cert_store.read_own_ecc_pkey(curve).ok(),
+            None => cert_store.read_own_pkey().ok(),
+        }
     }
 
+    /// Read our certificate for the security policy of the channel.
     pub(crate) fn read_own_certificate(&self) -> Option<X509> {
         let cert_store = trace_read_lock!(self.certificate_store);
-        cert_store.read_own_cert().ok()
+        match self.security_policy().ecc_curve() {
+            Some(curve) => cert_store.read_own_ecc_cert(curve).ok(),
+            None => cert_store.read_own_cert().ok(),
+        }
     }
 
     pub(crate) fn certificate_store(&self) -> &RwLock<CertificateStore> {
@@ -154,6 +175,7 @@ impl AsyncSecureChannel {
             connector,
             channel_lifetime,
             encoding_context,
+            ecdh_key: Default::default(),
         }
     }
 
@@ -289,6 +311,19 @@ impl AsyncSecureChannel {
                 let mut secure_channel = trace_write_lock!(self.secure_channel);
                 secure_channel.set_private_key(key);
                 secure_channel.set_cert(cert);
+                if let Some(curve) = security_policy.ecc_curve() {
+                    let certificate_store = trace_read_lock!(self.certificate_store);
+                    let ecc_cert = certificate_store
+                        .read_own_ecc_cert(curve)
+                        .and_then(|cert| Ok((cert, certificate_store.read_own_ecc_pkey(curve)?)));
+                    match ecc_cert {
+                        Ok(ecc_cert) => secure_channel.set_ecc_cert_and_key(curve, Some(ecc_cert)),
+                        Err(e) => {
+                            error!("Cannot use security policy {security_policy}, there is no application certificate for curve {curve}: {e}");
+                            return Err(StatusCode::BadCertificateInvalid);
+                        }
+                    }
+                }
                 secure_channel.set_security_policy(security_policy);
                 secure_channel.set_security_mode(self.endpoint_info.endpoint.security_mode);
                 let _ = secure_channel.set_remote_cert_from_byte_string(
@@ -1,11 +1,16 @@
 use std::{future::Future, sync::Arc};
 
 use async_trait::async_trait;
-use opcua_core::{comms::secure_channel::SecureChannel, sync::RwLock};
+use opcua_core::{
+    comms::{secure_channel::SecureChannel, url::is_websocket_url},
+    sync::RwLock,
+};
+use opcua_crypto::CertificateStore;
 use opcua_types::StatusCode;
 
 use super::{
-    tcp::{TcpTransport, TransportConfiguration},
+    tcp::{TcpConnector, TcpTransport, TransportConfiguration},
+    websocket::WebSocketConnector,
     OutgoingMessage, TransportPollResult,
 };
 
@@ -30,6 +35,43 @@ pub trait Connector: Send + Sync {
     ) -> Result<TcpTransport, StatusCode>;
 }
 
+/// Connector selecting the transport from the scheme of the endpoint URL,
+/// using [`TcpConnector`] for `opc.tcp`, and [`WebSocketConnector`] for
+/// `opc.ws` and `opc.wss`. This is the default connector.
+pub struct UrlConnector {
+    websocket: WebSocketConnector,
+}
+
+impl UrlConnector {
+    /// Create a new URL connector, validating TLS certificates using `certificate_store`.
+    pub fn new(certificate_store: Arc<RwLock<CertificateStore>>) -> Self {
+        Self {
+            websocket: WebSocketConnector::new(certificate_store),
+        }
+    }
+}
+
+#[async_trait]
+impl Connector for UrlConnector {
+    async fn connect(
+        &self,
+        channel: Arc<RwLock<SecureChannel>>,
+        outgoing_recv: tokio::sync::mpsc::Receiver<OutgoingMessage>,
+        config: TransportConfiguration,
+        endpoint_url: &str,
+    ) -> Result<TcpTransport, StatusCode> {
+        if is_websocket_url(endpoint_url) {
+            self.websocket
+                .connect(channel, outgoing_recv, config, endpoint_url)
+                .await
+        } else {
+            TcpConnector
+                .connect(channel, outgoing_recv, config, endpoint_url)
+                .await
+        }
+    }
+}
+
 /// Trait for client transport channels.
 ///
 /// Note for implementors:
@@ -3,11 +3,17 @@
 mod channel;
 mod connect;
 mod core;
+mod reverse;
 mod state;
+mod stream;
 pub(super) mod tcp;
+mod websocket;
 
 pub use channel::{AsyncSecureChannel, SecureChannelEventLoop};
-pub use connect::{Connector, Transport};
+pub use connect::{Connector, Transport, UrlConnector};
 pub(crate) use core::OutgoingMessage;
 pub use core::TransportPollResult;
+pub use reverse::ReverseTcpConnector;
+pub use stream::StreamConnector;
 pub use tcp::TcpConnector;
+pub use websocket::WebSocketConnector;
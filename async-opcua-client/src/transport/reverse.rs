@@ -0,0 +1,146 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use opcua_core::comms::{secure_channel::SecureChannel, tcp_codec::Message};
+use opcua_types::StatusCode;
+use parking_lot::RwLock;
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tracing::{debug, error, warn};
+
+use super::{
+    connect::Connector,
+    core::OutgoingMessage,
+    tcp::{TcpConnector, TcpTransport, TransportConfiguration},
+};
+
+/// Connector for `opc.tcp` using reverse connect, as defined in OPC UA Part 6, 7.1.3.
+///
+/// Instead of connecting to the server, the client listens for incoming connections,
+/// and waits for the server to send a `ReverseHello` message. After that, the connection
+/// is established as normal, starting with the client sending `HELLO`.
+///
+/// Each call to `connect` waits for a new connection from the server, so reconnecting
+/// works the same way as for normal connections, as long as the server keeps
+/// connecting to the client.
+///
+/// The listener may be shared between several connectors, in which case each
+/// connection goes to whichever connector accepts it first. Use
+/// [`ReverseTcpConnector::server_uri`] to only accept connections from a specific server.
+pub struct ReverseTcpConnector {
+    listener: Arc<TcpListener>,
+    server_uri: Option<String>,
+    reverse_hello_timeout: Duration,
+}
+
+impl ReverseTcpConnector {
+    /// Create a new reverse connector listening on the given TCP listener.
+    pub fn new(listener: Arc<TcpListener>) -> Self {
+        Self {
+            listener,
+            server_uri: None,
+            reverse_hello_timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Create a new reverse connector listening on `addr`.
+    pub async fn bind(addr: impl ToSocketAddrs) -> std::io::Result<Self> {
+        Ok(Self::new(Arc::new(TcpListener::bind(addr).await?)))
+    }
+
+    /// Only accept connections from the server with this application URI.
+    /// Connections from other servers are closed.
+    pub fn server_uri(mut self, server_uri: impl Into<String>) -> Self {
+        self.server_uri = Some(server_uri.into());
+        self
+    }
+
+    /// Time to wait for the server to send `ReverseHello` after a connection
+    /// is accepted. The default is 5 seconds.
+    pub fn reverse_hello_timeout(mut self, timeout: Duration) -> Self {
+        self.reverse_hello_timeout = timeout;
+        self
+    }
+
+    /// Get the local address the connector is listening on.
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+}
+
+#[async_trait]
+impl Connector for ReverseTcpConnector {
+    async fn connect(
+        &self,
+        channel: Arc<RwLock<SecureChannel>>,
+        outgoing_recv: tokio::sync::mpsc::Receiver<OutgoingMessage>,
+        config: TransportConfiguration,
+        endpoint_url: &str,
+    ) -> Result<TcpTransport, StatusCode> {
+        loop {
+            let (socket, addr) = self.listener.accept().await.map_err(|e| {
+                error!("Failed to accept reverse connection: {e}");
+                StatusCode::BadCommunicationError
+            })?;
+            debug!("Accepted reverse connection from {addr}");
+
+            let (mut framed_read, writer, policy) = TcpConnector::split_socket(socket, &channel);
+            let reverse_hello =
+                match tokio::time::timeout(self.reverse_hello_timeout, framed_read.next()).await {
+                    Ok(Some(Ok(Message::ReverseHello(msg)))) if msg.is_valid() => msg,
+                    Ok(other) => {
+                        warn!(
+                            "Expected ReverseHello from {addr}, got {:?}, closing connection",
+                            other
+                        );
+                        continue;
+                    }
+                    Err(_) => {
+                        warn!("Timeout waiting for ReverseHello from {addr}, closing connection");
+                        continue;
+                    }
+                };
+            tracing::trace!("Received reverse hello: {reverse_hello:?}");
+
+            if let Some(server_uri) = &self.server_uri {
+                if reverse_hello.server_uri.as_ref() != server_uri {
+                    warn!(
+                        "Rejecting reverse connection from unexpected server {}",
+                        reverse_hello.server_uri
+                    );
+                    continue;
+                }
+            }
+
+            // The server tells us which endpoint URL to use, since the
+            // client may not be able to know it in advance.
+            let endpoint_url = reverse_hello
+                .endpoint_url
+                .value()
+                .as_deref()
+                .unwrap_or(endpoint_url);
+            // A failed handshake only closes this connection, the server may connect again.
+            let (framed_read, writer, ack, policy) =
+                match TcpConnector::hello(framed_read, writer, policy, &config, endpoint_url).await
+                {
+                    Ok(r) => r,
+                    Err(e) => {
+                        warn!(
+                        "Hello to reverse connection from {addr} failed: {e}, closing connection"
+                    );
+                        continue;
+                    }
+                };
+
+            return Ok(TcpTransport::new(
+                channel,
+                outgoing_recv,
+                &config,
+                framed_read,
+                writer,
+                ack,
+                policy,
+            ));
+        }
+    }
+}
@@ -117,8 +117,15 @@ impl SecureChannelState {
 
         let (security_mode, security_policy, client_nonce) = {
             let mut secure_channel = trace_write_lock!(self.secure_channel);
-            let client_nonce = secure_channel.security_policy().random_nonce();
-            secure_channel.set_local_nonce(client_nonce.as_ref());
+            let client_nonce = if secure_channel.security_policy().is_ecc() {
+                // The nonce is the public part of a new ephemeral key
+                secure_channel.create_random_nonce();
+                secure_channel.local_nonce_as_byte_string()
+            } else {
+                let client_nonce = secure_channel.security_policy().random_nonce();
+                secure_channel.set_local_nonce(client_nonce.as_ref());
+                client_nonce
+            };
             (
                 secure_channel.security_mode(),
                 secure_channel.security_policy(),
@@ -183,7 +190,7 @@ impl SecureChannelState {
                         || secure_channel.security_mode() == MessageSecurityMode::SignAndEncrypt)
                 {
                     secure_channel.set_remote_nonce_from_byte_string(&response.server_nonce)?;
-                    secure_channel.derive_keys();
+                    secure_channel.derive_keys()?;
                 }
             }
             Ok(())
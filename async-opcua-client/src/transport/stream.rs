@@ -0,0 +1,83 @@
+use std::{future::Future, sync::Arc};
+
+use async_trait::async_trait;
+use futures::{future::BoxFuture, FutureExt};
+use opcua_core::comms::{
+    secure_channel::SecureChannel,
+    stream::{AsyncStream, BoxedStream},
+};
+use opcua_types::StatusCode;
+use parking_lot::RwLock;
+use tracing::error;
+
+use super::{
+    connect::Connector,
+    core::OutgoingMessage,
+    tcp::{TcpConnector, TcpTransport, TransportConfiguration},
+};
+
+type OpenStream = dyn Fn() -> BoxFuture<'static, std::io::Result<BoxedStream>> + Send + Sync;
+
+/// Connector for the `opc.tcp` binary protocol over any byte stream, such as a
+/// Unix domain socket, or an in-memory pipe to a server in the same process.
+///
+/// The stream is opened by calling the given function each time the client connects,
+/// the endpoint URL is only sent to the server as part of `HELLO`, so it should
+/// still be the `opc.tcp` URL of an endpoint on the server.
+pub struct StreamConnector {
+    open: Box<OpenStream>,
+}
+
+impl StreamConnector {
+    /// Create a new stream connector, calling `open` to open a new stream to the server
+    /// each time the client connects.
+    pub fn new<F, Fut, S>(open: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::io::Result<S>> + Send + 'static,
+        S: AsyncStream + 'static,
+    {
+        Self {
+            open: Box::new(move || {
+                open()
+                    .map(|r| r.map(|s| Box::new(s) as BoxedStream))
+                    .boxed()
+            }),
+        }
+    }
+
+    /// Create a new stream connector connecting to a Unix domain socket at `path`.
+    #[cfg(unix)]
+    pub fn unix(path: impl Into<std::path::PathBuf>) -> Self {
+        let path = path.into();
+        Self::new(move || tokio::net::UnixStream::connect(path.clone()))
+    }
+}
+
+#[async_trait]
+impl Connector for StreamConnector {
+    async fn connect(
+        &self,
+        channel: Arc<RwLock<SecureChannel>>,
+        outgoing_recv: tokio::sync::mpsc::Receiver<OutgoingMessage>,
+        config: TransportConfiguration,
+        endpoint_url: &str,
+    ) -> Result<TcpTransport, StatusCode> {
+        let stream = (self.open)().await.map_err(|e| {
+            error!("Could not open stream to server: {e}");
+            StatusCode::BadCommunicationError
+        })?;
+        let (framed_read, writer, policy) = TcpConnector::split_socket(stream, &channel);
+        let (framed_read, writer, ack, policy) =
+            TcpConnector::hello(framed_read, writer, policy, &config, endpoint_url).await?;
+        Ok(TcpTransport::new(
+            channel,
+            outgoing_recv,
+            &config,
+            framed_read,
+            writer,
+            ack,
+            policy,
+        ))
+    }
+}
@@ -10,6 +10,7 @@ use opcua_core::{
     comms::{
         buffer::SendBuffer,
         secure_channel::SecureChannel,
+        stream::{AsyncStream, BoxedStream},
         tcp_codec::{Message, TcpCodec},
         tcp_types::HelloMessage,
         url::hostname_port_from_url,
@@ -33,8 +34,8 @@ enum TransportCloseState {
 
 pub struct TcpTransport {
     state: TransportState,
-    read: FramedRead<ReadHalf<TcpStream>, TcpCodec>,
-    write: WriteHalf<TcpStream>,
+    read: FramedRead<ReadHalf<BoxedStream>, TcpCodec>,
+    write: WriteHalf<BoxedStream>,
     send_buffer: SendBuffer,
     should_close: bool,
     closed: TransportCloseState,
@@ -59,13 +60,20 @@ impl TcpConnector {
         endpoint_url: &str,
     ) -> Result<
         (
-            FramedRead<ReadHalf<TcpStream>, TcpCodec>,
-            WriteHalf<TcpStream>,
+            FramedRead<ReadHalf<BoxedStream>, TcpCodec>,
+            WriteHalf<BoxedStream>,
             AcknowledgeMessage,
             SecurityPolicy,
         ),
         StatusCode,
     > {
+        let socket = Self::connect_socket(endpoint_url).await?;
+        let (framed_read, writer, policy) = Self::split_socket(socket, secure_channel);
+        Self::hello(framed_read, writer, policy, config, endpoint_url).await
+    }
+
+    /// Open a TCP connection to the host and port given by `endpoint_url`.
+    pub(super) async fn connect_socket(endpoint_url: &str) -> Result<TcpStream, StatusCode> {
         let (host, port) = hostname_port_from_url(
             endpoint_url,
             opcua_core::constants::DEFAULT_OPC_UA_SERVER_PORT,
@@ -94,13 +102,46 @@ impl TcpConnector {
 
         debug!("Connecting to {} with url {}", addr, endpoint_url);
 
-        let socket = TcpStream::connect(&addr).await.map_err(|err| {
+        TcpStream::connect(&addr).await.map_err(|err| {
             error!("Could not connect to host {}, {:?}", addr, err);
             StatusCode::BadCommunicationError
-        })?;
+        })
+    }
 
-        let (reader, mut writer) = tokio::io::split(socket);
+    /// Split a connected socket into a framed reader and a writer.
+    pub(super) fn split_socket(
+        socket: impl AsyncStream + 'static,
+        secure_channel: &RwLock<SecureChannel>,
+    ) -> (
+        FramedRead<ReadHalf<BoxedStream>, TcpCodec>,
+        WriteHalf<BoxedStream>,
+        SecurityPolicy,
+    ) {
+        let (reader, writer) = tokio::io::split(Box::new(socket) as BoxedStream);
+        let secure_channel = trace_read_lock!(secure_channel);
+        (
+            FramedRead::new(reader, TcpCodec::new(secure_channel.decoding_options())),
+            writer,
+            secure_channel.security_policy(),
+        )
+    }
 
+    /// Send HELLO on a connected socket and wait for the server to acknowledge it.
+    pub(super) async fn hello(
+        mut framed_read: FramedRead<ReadHalf<BoxedStream>, TcpCodec>,
+        mut writer: WriteHalf<BoxedStream>,
+        policy: SecurityPolicy,
+        config: &TransportConfiguration,
+        endpoint_url: &str,
+    ) -> Result<
+        (
+            FramedRead<ReadHalf<BoxedStream>, TcpCodec>,
+            WriteHalf<BoxedStream>,
+            AcknowledgeMessage,
+            SecurityPolicy,
+        ),
+        StatusCode,
+    > {
         let hello = HelloMessage::new(
             endpoint_url,
             config.send_buffer_size,
@@ -109,13 +150,6 @@ impl TcpConnector {
             config.max_chunk_count,
         );
         tracing::trace!("Send hello message: {hello:?}");
-        let (mut framed_read, policy) = {
-            let secure_channel = trace_read_lock!(secure_channel);
-            (
-                FramedRead::new(reader, TcpCodec::new(secure_channel.decoding_options())),
-                secure_channel.security_policy(),
-            )
-        };
 
         writer
             .write_all(&opcua_types::SimpleBinaryEncodable::encode_to_vec(&hello))
@@ -162,6 +196,28 @@ impl Connector for TcpConnector {
                 Ok(k) => k,
                 Err(status) => return Err(status),
             };
+        Ok(TcpTransport::new(
+            channel,
+            outgoing_recv,
+            &config,
+            framed_read,
+            writer,
+            ack,
+            policy,
+        ))
+    }
+}
+
+impl TcpTransport {
+    pub(super) fn new(
+        channel: Arc<RwLock<SecureChannel>>,
+        outgoing_recv: tokio::sync::mpsc::Receiver<OutgoingMessage>,
+        config: &TransportConfiguration,
+        framed_read: FramedRead<ReadHalf<BoxedStream>, TcpCodec>,
+        writer: WriteHalf<BoxedStream>,
+        ack: AcknowledgeMessage,
+        policy: SecurityPolicy,
+    ) -> Self {
         let mut buffer = SendBuffer::new(
             config.send_buffer_size,
             config.max_message_size,
@@ -174,7 +230,7 @@ impl Connector for TcpConnector {
             ack.max_chunk_count as usize,
         );
 
-        Ok(TcpTransport {
+        TcpTransport {
             state: TransportState::new(
                 channel,
                 outgoing_recv,
@@ -186,7 +242,7 @@ impl Connector for TcpConnector {
             send_buffer: buffer,
             should_close: false,
             closed: TransportCloseState::Open,
-        })
+        }
     }
 }
 
@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use opcua_core::{
+    comms::{secure_channel::SecureChannel, url::OPC_WSS_SCHEME, websocket},
+    sync::RwLock,
+};
+use opcua_crypto::CertificateStore;
+use opcua_types::StatusCode;
+use tracing::error;
+
+use super::{
+    connect::Connector,
+    core::OutgoingMessage,
+    tcp::{TcpConnector, TcpTransport, TransportConfiguration},
+};
+
+/// Connector for the `opc.ws` and `opc.wss` transports, carrying OPC UA binary
+/// messages over WebSockets using the `opcua+uacp` subprotocol.
+///
+/// `opc.wss` requires the `tls` feature. The server TLS certificate is validated
+/// using the client certificate store, the same way as server application
+/// instance certificates.
+pub struct WebSocketConnector {
+    #[cfg(feature = "tls")]
+    tls: opcua_core::comms::tls::TlsConnector,
+}
+
+impl WebSocketConnector {
+    /// Create a new WebSocket connector, validating TLS certificates using
+    /// `certificate_store`.
+    pub fn new(certificate_store: Arc<RwLock<CertificateStore>>) -> Self {
+        #[cfg(not(feature = "tls"))]
+        let _ = certificate_store;
+        Self {
+            #[cfg(feature = "tls")]
+            tls: opcua_core::comms::tls::connector(certificate_store),
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    async fn connect_tls(
+        &self,
+        socket: tokio::net::TcpStream,
+        endpoint_url: &str,
+    ) -> Result<opcua_core::comms::tls::ClientTlsStream<tokio::net::TcpStream>, StatusCode> {
+        let (host, _) = opcua_core::comms::url::hostname_port_from_url(
+            endpoint_url,
+            opcua_core::constants::DEFAULT_OPC_UA_SERVER_PORT,
+        )?;
+        let server_name = opcua_core::comms::tls::server_name(&host)?;
+        self.tls.connect(server_name, socket).await.map_err(|e| {
+            error!("TLS handshake with {endpoint_url} failed: {e}");
+            StatusCode::BadSecurityChecksFailed
+        })
+    }
+}
+
+#[async_trait]
+impl Connector for WebSocketConnector {
+    async fn connect(
+        &self,
+        channel: Arc<RwLock<SecureChannel>>,
+        outgoing_recv: tokio::sync::mpsc::Receiver<OutgoingMessage>,
+        config: TransportConfiguration,
+        endpoint_url: &str,
+    ) -> Result<TcpTransport, StatusCode> {
+        let socket = TcpConnector::connect_socket(endpoint_url).await?;
+        let map_err = |e: std::io::Error| {
+            error!("WebSocket handshake with {endpoint_url} failed: {e}");
+            StatusCode::BadCommunicationError
+        };
+
+        let (framed_read, writer, policy) = if endpoint_url.starts_with(OPC_WSS_SCHEME) {
+            #[cfg(feature = "tls")]
+            {
+                let socket = self.connect_tls(socket, endpoint_url).await?;
+                let stream =
+                    websocket::client_handshake(socket, endpoint_url, config.recv_buffer_size)
+                        .await
+                        .map_err(map_err)?;
+                TcpConnector::split_socket(stream, &channel)
+            }
+            #[cfg(not(feature = "tls"))]
+            {
+                error!("Cannot connect to {endpoint_url}, the tls feature is not enabled");
+                return Err(StatusCode::BadTcpEndpointUrlInvalid);
+            }
+        } else {
+            let stream = websocket::client_handshake(socket, endpoint_url, config.recv_buffer_size)
+                .await
+                .map_err(map_err)?;
+            TcpConnector::split_socket(stream, &channel)
+        };
+
+        let (framed_read, writer, ack, policy) =
+            TcpConnector::hello(framed_read, writer, policy, &config, endpoint_url).await?;
+        Ok(TcpTransport::new(
+            channel,
+            outgoing_recv,
+            &config,
+            framed_read,
+            writer,
+            ack,
+            policy,
+        ))
+    }
+}
@@ -15,6 +15,7 @@ pub enum FieldKind<'a> {
 pub struct CollectedField<'a> {
     pub type_id: FieldKind<'a>,
     pub data_type_id: Option<&'a str>,
+    pub value_rank: i32,
     pub placeholder: bool,
 }
 
@@ -206,6 +207,7 @@ impl<'a> TypeCollector<'a> {
                     let mut is_placeholder = false;
                     let mut type_def: Option<&'a str> = None;
                     let mut data_type_id: Option<&'a str> = None;
+                    let mut value_rank = -1;
                     let target = node.lookup_node_id(rf.target);
                     for crf in self
                         .references
@@ -252,6 +254,7 @@ impl<'a> TypeCollector<'a> {
                                 .with_context(format!("collecting type {type_id}")));
                             };
                             data_type_id = Some(target_node.lookup_node_id(v.data_type.0.as_str()));
+                            value_rank = v.value_rank.0;
                             FieldKind::Variable(type_def)
                         }
                         UANode::Method(_) => FieldKind::Method,
@@ -273,6 +276,7 @@ impl<'a> TypeCollector<'a> {
                             placeholder: is_placeholder,
                             type_id: kind,
                             data_type_id,
+                            value_rank,
                         },
                     );
                 }
@@ -109,7 +109,13 @@ impl SendBuffer {
     /// Set whether we are using legacy sequence numbers or not.
     /// This depends on the active security policy.
     pub fn set_sequence_number_legacy(&mut self, is_legacy: bool) {
+        let unused = self.sequence_numbers.current() == self.sequence_numbers.min_value();
         self.sequence_numbers.set_is_legacy(is_legacy);
+        // If nothing has been sent yet, start at the first sequence number of the new mode.
+        if unused {
+            let min_value = self.sequence_numbers.min_value();
+            self.sequence_numbers.set(min_value);
+        }
     }
 
     /// Clear the list of pending messages, then
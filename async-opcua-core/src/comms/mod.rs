@@ -12,6 +12,10 @@ pub mod message_chunk_info;
 pub mod secure_channel;
 pub mod security_header;
 pub mod sequence_number;
+pub mod stream;
 pub mod tcp_codec;
 pub mod tcp_types;
+#[cfg(feature = "tls")]
+pub mod tls;
 pub mod url;
+pub mod websocket;
@@ -17,7 +17,8 @@ use chrono::Duration;
 use tracing::{error, trace};
 
 use opcua_crypto::{
-    aeskey::AesKey,
+    aeskey::{AesKey, POLY1305_TAG_SIZE},
+    ecc::{EccCurve, EphemeralKey},
     pkey::{KeySize, PrivateKey, PublicKey},
     random,
     x509::X509,
@@ -73,12 +74,16 @@ pub struct SecureChannel {
     cert: Option<X509>,
     /// Our private key
     private_key: Option<PrivateKey>,
+    /// Our certificates and private keys for the ECC security policies, by curve
+    ecc_certs: HashMap<EccCurve, (X509, PrivateKey)>,
     /// Their certificate
     remote_cert: Option<X509>,
     /// Their nonce provided by open secure channel
     remote_nonce: Vec<u8>,
     /// Our nonce generated while handling open secure channel
     local_nonce: Vec<u8>,
+    /// Our ephemeral key for ECC security policies, the public key is the local nonce
+    ephemeral_key: Option<EphemeralKey>,
     /// Client (i.e. other end's set of keys) Symmetric Signing Key, Encrypt Key, IV
     ///
     /// This is a map of channel token ids and their respective keys. We need to keep
@@ -107,9 +112,11 @@ impl SecureChannel {
             token_created_at: DateTime::now(),
             token_lifetime: 0,
             local_nonce: Vec::new(),
+            ephemeral_key: None,
             remote_nonce: Vec::new(),
             cert: None,
             private_key: None,
+            ecc_certs: HashMap::new(),
             remote_cert: None,
             local_keys: None,
             encoding_context: Default::default(),
@@ -142,6 +149,19 @@ impl SecureChannel {
             };
             (cert, pkey)
         };
+        // The ECC certificates are optional, they are only needed for the ECC security policies.
+        let ecc_certs = {
+            let certificate_store = certificate_store.read();
+            EccCurve::ALL
+                .iter()
+                .copied()
+                .filter_map(|curve| {
+                    let cert = certificate_store.read_own_ecc_cert(curve).ok()?;
+                    let pkey = certificate_store.read_own_ecc_pkey(curve).ok()?;
+                    Some((curve, (cert, pkey)))
+                })
+                .collect()
+        };
         SecureChannel {
             role,
             security_mode: MessageSecurityMode::None,
@@ -151,9 +171,11 @@ impl SecureChannel {
             token_created_at: DateTime::now(),
             token_lifetime: 0,
             local_nonce: Vec::new(),
+            ephemeral_key: None,
             remote_nonce: Vec::new(),
             cert,
             private_key,
+            ecc_certs,
             remote_cert: None,
             local_keys: None,
             encoding_context,
@@ -171,9 +193,44 @@ impl SecureChannel {
         self.cert = cert;
     }
 
-    /// Get the application certificate.
+    /// Get the application certificate used with the current security policy.
     pub fn cert(&self) -> Option<X509> {
-        self.cert.clone()
+        self.own_cert().cloned()
+    }
+
+    /// Set the application certificate and private key used with the ECC security
+    /// policies on `curve`.
+    pub fn set_ecc_cert_and_key(&mut self, curve: EccCurve, cert: Option<(X509, PrivateKey)>) {
+        match cert {
+            Some(cert) => {
+                self.ecc_certs.insert(curve, cert);
+            }
+            None => {
+                self.ecc_certs.remove(&curve);
+            }
+        }
+    }
+
+    /// Our certificate for the current security policy. The ECC policies use a
+    /// certificate on the curve of the policy instead of the RSA certificate.
+    fn own_cert(&self) -> Option<&X509> {
+        match self.security_policy.ecc_curve() {
+            Some(curve) => self.ecc_certs.get(&curve).map(|(cert, _)| cert),
+            None => self.cert.as_ref(),
+        }
+    }
+
+    /// Our private key for the current security policy.
+    fn own_private_key(&self) -> Option<&PrivateKey> {
+        match self.security_policy.ecc_curve() {
+            Some(curve) => self.ecc_certs.get(&curve).map(|(_, pkey)| pkey),
+            None => self.private_key.as_ref(),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn set_role(&mut self, role: Role) {
+        self.role = role;
     }
 
     /// Set the remote certificate.
@@ -322,7 +379,7 @@ impl SecureChannel {
                         };
                     AsymmetricSecurityHeader::new(
                         self.security_policy,
-                        self.cert.as_ref().unwrap(),
+                        self.own_cert().unwrap(),
                         receiver_certificate_thumbprint,
                     )
                 };
@@ -338,11 +395,21 @@ impl SecureChannel {
         }
     }
 
-    /// Creates a nonce for the connection. The nonce should be the same size as the symmetric key
+    /// Creates a nonce for the connection. The nonce should be the same size as the symmetric key.
+    ///
+    /// For the ECC security policies, this creates a new ephemeral key, and the nonce is
+    /// its public key.
     pub fn create_random_nonce(&mut self) {
-        self.local_nonce
-            .resize(self.security_policy.secure_channel_nonce_length(), 0);
-        random::bytes(&mut self.local_nonce);
+        if let Some(curve) = self.security_policy.ecc_curve() {
+            let ephemeral_key = EphemeralKey::new(curve);
+            self.local_nonce = ephemeral_key.public_key_bytes();
+            self.ephemeral_key = Some(ephemeral_key);
+        } else {
+            self.local_nonce
+                .resize(self.security_policy.secure_channel_nonce_length(), 0);
+            random::bytes(&mut self.local_nonce);
+            self.ephemeral_key = None;
+        }
     }
 
     /// Sets the remote certificate
@@ -428,15 +495,44 @@ impl SecureChannel {
     /// The Client keys are used to secure Messages sent by the Client. The Server keys
     /// are used to secure Messages sent by the Server.
     ///
-    pub fn derive_keys(&mut self) {
-        self.insert_remote_keys(
-            self.security_policy
-                .make_secure_channel_keys(&self.local_nonce, &self.remote_nonce),
-        );
-        self.local_keys = Some(
-            self.security_policy
-                .make_secure_channel_keys(&self.remote_nonce, &self.local_nonce),
-        );
+    /// The ECC security policies instead derive the keys from the secret shared through the
+    /// ephemeral keys exchanged as nonces, see [`SecurityPolicy::make_ecc_secure_channel_keys`].
+    pub fn derive_keys(&mut self) -> Result<(), Error> {
+        if self.security_policy.is_ecc() {
+            let Some(ephemeral_key) = &self.ephemeral_key else {
+                return Err(Error::new(
+                    StatusCode::BadInternalError,
+                    "Cannot derive keys, no ephemeral key has been created",
+                ));
+            };
+            let shared_secret = ephemeral_key.shared_secret(&self.remote_nonce)?;
+            if self.is_client_role() {
+                let (client_keys, server_keys) = self.security_policy.make_ecc_secure_channel_keys(
+                    &shared_secret,
+                    &self.local_nonce,
+                    &self.remote_nonce,
+                );
+                self.insert_remote_keys(server_keys);
+                self.local_keys = Some(client_keys);
+            } else {
+                let (client_keys, server_keys) = self.security_policy.make_ecc_secure_channel_keys(
+                    &shared_secret,
+                    &self.remote_nonce,
+                    &self.local_nonce,
+                );
+                self.insert_remote_keys(client_keys);
+                self.local_keys = Some(server_keys);
+            }
+        } else {
+            self.insert_remote_keys(
+                self.security_policy
+                    .make_secure_channel_keys(&self.local_nonce, &self.remote_nonce),
+            );
+            self.local_keys = Some(
+                self.security_policy
+                    .make_secure_channel_keys(&self.remote_nonce, &self.local_nonce),
+            );
+        }
         trace!("Remote nonce = {:?}", self.remote_nonce);
         trace!("Local nonce = {:?}", self.local_nonce);
         trace!(
@@ -444,6 +540,7 @@ impl SecureChannel {
             self.get_remote_keys(self.token_id)
         );
         trace!("Derived local keys = {:?}", self.local_keys);
+        Ok(())
     }
 
     /// Get the deadline as an [`Instant`] for token renewal, used
@@ -467,7 +564,7 @@ impl SecureChannel {
             SecurityHeader::Asymmetric(security_header) => {
                 if !security_header.sender_certificate.is_null() {
                     let x509 = X509::from_byte_string(&security_header.sender_certificate).unwrap();
-                    x509.public_key().unwrap().size()
+                    x509.public_key().unwrap().signature_size()
                 } else {
                     trace!("No certificate / public key was supplied in the asymmetric security header");
                     0
@@ -504,6 +601,13 @@ impl SecureChannel {
         {
             return (0, 0);
         }
+        // OpenSecureChannel is only signed with the ECC policies, and authenticated
+        // encryption does not need padding.
+        if self.security_policy.is_ecc() && message_type.is_open_secure_channel()
+            || self.security_policy.is_aead()
+        {
+            return (0, 0);
+        }
 
         match security_header {
             SecurityHeader::Asymmetric(security_header) => {
@@ -874,8 +978,21 @@ impl SecureChannel {
     ) -> Result<usize, StatusCode> {
         let header_size = encrypted_range.start;
 
-        let signing_key = self.private_key.as_ref().unwrap();
-        let signing_key_size = signing_key.size();
+        let Some(signing_key) = self.own_private_key() else {
+            error!("Cannot sign message, no private key for security policy {security_policy}");
+            return Err(StatusCode::BadNoValidCertificates);
+        };
+        let signing_key_size = signing_key.signature_size();
+
+        if security_policy.is_ecc() {
+            // The ECC policies only sign the OpenSecureChannel messages, the keys for the
+            // channel are agreed on through the ephemeral keys in the nonces.
+            let signed_range = 0..(encrypted_range.end - signing_key_size);
+            let (l, r) = src.split_at_mut(signed_range.end);
+            security_policy.asymmetric_sign(signing_key, l, &mut r[0..signing_key_size])?;
+            dst[..encrypted_range.end].copy_from_slice(&src[..encrypted_range.end]);
+            return Ok(encrypted_range.end);
+        }
 
         let signed_range = 0..(encrypted_range.end - signing_key_size);
         let signature_range = signed_range.end..encrypted_range.end;
@@ -1033,8 +1150,44 @@ impl SecureChannel {
         // The receiver certificate thumbprint identifies which of our certs was used by the client
         // to encrypt the message. We have to work out from the thumbprint which cert to use
 
-        let our_cert = self.cert.as_ref().unwrap();
+        let Some(our_cert) = self.own_cert() else {
+            return Err(Error::new(
+                StatusCode::BadNoValidCertificates,
+                format!(
+                    "There is no application certificate for security policy {security_policy}"
+                ),
+            ));
+        };
         let our_thumbprint = our_cert.thumbprint();
+
+        if security_policy.is_ecc() {
+            // Messages are only signed, so the thumbprint is optional
+            if !receiver_thumbprint.is_null()
+                && our_thumbprint.value() != receiver_thumbprint.as_ref()
+            {
+                return Err(Error::new(
+                    StatusCode::BadNoValidCertificates,
+                    "Supplied thumbprint does not match application certificate's thumbprint",
+                ));
+            }
+            let signature_size = verification_key.signature_size();
+            if encrypted_range.end - encrypted_range.start < signature_size {
+                return Err(Error::new(
+                    StatusCode::BadSecurityChecksFailed,
+                    "Message is too short to contain a signature",
+                ));
+            }
+            let signature_start = encrypted_range.end - signature_size;
+            dst[..encrypted_range.end].copy_from_slice(&src[..encrypted_range.end]);
+            security_policy.asymmetric_verify_signature(
+                verification_key,
+                &src[..signature_start],
+                &src[signature_start..encrypted_range.end],
+                their_key,
+            )?;
+            return Ok(signature_start);
+        }
+
         if our_thumbprint.value() != receiver_thumbprint.as_ref() {
             Err(Error::new(
                 StatusCode::BadNoValidCertificates,
@@ -1051,7 +1204,12 @@ impl SecureChannel {
             trace!("Decrypting message range {:?}", encrypted_range);
             let mut decrypted_tmp = vec![0u8; encrypted_size];
 
-            let private_key = self.private_key.as_ref().unwrap();
+            let Some(private_key) = self.own_private_key() else {
+                return Err(Error::new(
+                    StatusCode::BadNoValidCertificates,
+                    "There is no private key for the application certificate",
+                ));
+            };
             let decrypted_size = security_policy.asymmetric_decrypt(
                 private_key,
                 &src[encrypted_range.clone()],
@@ -1092,7 +1250,7 @@ impl SecureChannel {
                 signature_range_dst
             );
             // Keysize for padding is publickey length if avaiable
-            let key_size = if let Some(rem) = &self.cert {
+            let key_size = if let Some(rem) = self.own_cert() {
                 if let Ok(cert) = rem.public_key() {
                     cert.size()
                 } else {
@@ -1219,6 +1377,10 @@ impl SecureChannel {
         encrypted_range: Range<usize>,
         dst: &mut [u8],
     ) -> Result<usize, StatusCode> {
+        if self.security_policy.is_aead() && self.security_mode != MessageSecurityMode::None {
+            return self.aead_sign_and_encrypt(src, encrypted_range, dst);
+        }
+
         let encrypted_size = match self.security_mode {
             MessageSecurityMode::None => {
                 trace!("encrypt_and_sign is doing nothing because security mode == None");
@@ -1261,6 +1423,93 @@ impl SecureChannel {
         Ok(encrypted_size)
     }
 
+    /// The nonce of an authenticated encryption algorithm is the initialization vector with
+    /// the token id and the sequence number of the message mixed into the first 8 bytes.
+    fn aead_nonce(iv: &[u8], token_id: u32, sequence_number: u32) -> Vec<u8> {
+        let mut nonce = iv.to_vec();
+        for (n, b) in nonce[..4].iter_mut().zip(token_id.to_le_bytes()) {
+            *n ^= b;
+        }
+        for (n, b) in nonce[4..8].iter_mut().zip(sequence_number.to_le_bytes()) {
+            *n ^= b;
+        }
+        nonce
+    }
+
+    fn read_sequence_number(src: &[u8], offset: usize) -> Result<u32, Error> {
+        src.get(offset..(offset + 4))
+            .and_then(|b| b.try_into().ok())
+            .map(u32::from_le_bytes)
+            .ok_or_else(|| Error::new(StatusCode::BadDecodingError, "Message is too short"))
+    }
+
+    /// Find where the associated data, which is only authenticated, ends and where the
+    /// authentication tag starts. The data in between is encrypted.
+    ///
+    /// With authenticated encryption, the sequence header stays in the clear since it is
+    /// part of the nonce. In `Sign` mode everything up to the tag is associated data.
+    fn aead_boundaries(&self, encrypted_range: &Range<usize>) -> Result<(usize, usize), Error> {
+        if encrypted_range.end < encrypted_range.start + 8 + POLY1305_TAG_SIZE {
+            return Err(Error::new(
+                StatusCode::BadSecurityChecksFailed,
+                "Message is too short to contain an authentication tag",
+            ));
+        }
+        let tag_start = encrypted_range.end - POLY1305_TAG_SIZE;
+        let aad_end = if self.security_mode == MessageSecurityMode::SignAndEncrypt {
+            encrypted_range.start + 8
+        } else {
+            tag_start
+        };
+        Ok((aad_end, tag_start))
+    }
+
+    /// Secure a message with authenticated encryption. The authentication tag takes the
+    /// place of the signature, and there is no padding.
+    fn aead_sign_and_encrypt(
+        &self,
+        src: &mut [u8],
+        encrypted_range: Range<usize>,
+        dst: &mut [u8],
+    ) -> Result<usize, StatusCode> {
+        let (aad_end, tag_start) = self.aead_boundaries(&encrypted_range)?;
+        let sequence_number = Self::read_sequence_number(src, encrypted_range.start)?;
+        let (key, iv) = self.encryption_keys();
+        let nonce = Self::aead_nonce(iv, self.token_id, sequence_number);
+
+        let (aad, rest) = src[..encrypted_range.end].split_at_mut(aad_end);
+        let (plain, tag) = rest.split_at_mut(tag_start - aad_end);
+        key.aead_encrypt(&nonce, aad, plain, tag)?;
+
+        dst[..encrypted_range.end].copy_from_slice(&src[..encrypted_range.end]);
+        Ok(encrypted_range.end)
+    }
+
+    /// Decrypt and verify a message secured with authenticated encryption. Returns the
+    /// size of the message without the authentication tag.
+    fn aead_decrypt_and_verify(
+        &self,
+        src: &[u8],
+        encrypted_range: Range<usize>,
+        token_id: u32,
+        dst: &mut [u8],
+    ) -> Result<usize, Error> {
+        let (aad_end, tag_start) = self.aead_boundaries(&encrypted_range)?;
+        let sequence_number = Self::read_sequence_number(src, encrypted_range.start)?;
+        let (key, iv) = self.decryption_keys(token_id).ok_or_else(|| {
+            Error::new(
+                StatusCode::BadSecureChannelClosed,
+                "Missing decryption keys",
+            )
+        })?;
+        let nonce = Self::aead_nonce(iv, token_id, sequence_number);
+
+        dst[..tag_start].copy_from_slice(&src[..tag_start]);
+        let (aad, rest) = dst[..tag_start].split_at_mut(aad_end);
+        key.aead_decrypt(&nonce, aad, rest, &src[tag_start..encrypted_range.end])?;
+        Ok(tag_start)
+    }
+
     fn symmetric_sign_in_place(
         &self,
         buf: &mut [u8],
@@ -1300,6 +1549,10 @@ impl SecureChannel {
         token_id: u32,
         dst: &mut [u8],
     ) -> Result<usize, Error> {
+        if self.security_policy.is_aead() && self.security_mode != MessageSecurityMode::None {
+            return self.aead_decrypt_and_verify(src, encrypted_range, token_id, dst);
+        }
+
         match self.security_mode {
             MessageSecurityMode::None => {
                 // Just copy everything from src to dst
@@ -1413,7 +1666,11 @@ impl SecureChannel {
             | SecurityPolicy::Basic256
             | SecurityPolicy::Basic256Sha256
             | SecurityPolicy::Aes128Sha256RsaOaep
-            | SecurityPolicy::Aes256Sha256RsaPss => {}
+            | SecurityPolicy::Aes256Sha256RsaPss
+            | SecurityPolicy::EccNistP256
+            | SecurityPolicy::EccNistP384
+            | SecurityPolicy::EccBrainpoolP256r1
+            | SecurityPolicy::EccCurve25519 => {}
             _ => {
                 panic!("Unsupported security policy");
             }
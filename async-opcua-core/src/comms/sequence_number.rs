@@ -42,7 +42,8 @@ impl SequenceNumberHandle {
         self.is_legacy
     }
 
-    pub(crate) fn set_is_legacy(&mut self, is_legacy: bool) {
+    /// Set whether the sequence number handle uses legacy sequence numbers or not.
+    pub fn set_is_legacy(&mut self, is_legacy: bool) {
         self.is_legacy = is_legacy;
         if self.current_value > self.max_value() {
             // If the current value is greater than the max value, wrap around to the min value
@@ -0,0 +1,16 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2024 Adam Lock
+
+//! Type erased byte streams used by the client and server transports.
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// Trait for a bidirectional byte stream that can carry OPC UA binary messages,
+/// such as a TCP socket, or a WebSocket connection.
+pub trait AsyncStream: AsyncRead + AsyncWrite + Send + Sync + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Sync + Unpin> AsyncStream for T {}
+
+/// A boxed [`AsyncStream`].
+pub type BoxedStream = Box<dyn AsyncStream>;
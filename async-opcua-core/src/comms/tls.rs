@@ -0,0 +1,100 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2024 Adam Lock
+
+//! TLS support for the `opc.wss` transport.
+//!
+//! The server presents its application instance certificate, and the client validates it
+//! against its own certificate store, the same way as certificates used for OPC UA security.
+
+use std::{sync::Arc, time::SystemTime};
+
+use opcua_crypto::{CertificateStore, PrivateKey, SecurityPolicy, X509};
+use opcua_types::StatusCode;
+use parking_lot::RwLock;
+use rustls::{
+    client::{ServerCertVerified, ServerCertVerifier},
+    Certificate, CertificateError, ServerName,
+};
+use tracing::error;
+
+pub use tokio_rustls::{
+    client::TlsStream as ClientTlsStream, server::TlsStream as ServerTlsStream,
+};
+pub use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+/// Create a TLS acceptor using the given certificate and private key.
+pub fn acceptor(cert: &X509, key: &PrivateKey) -> Result<TlsAcceptor, StatusCode> {
+    let cert = cert.to_der().map_err(|e| {
+        error!("Failed to encode TLS certificate: {e}");
+        StatusCode::BadCertificateInvalid
+    })?;
+    let key = key.to_der().map_err(|e| {
+        error!("Failed to encode TLS private key: {e}");
+        StatusCode::BadCertificateInvalid
+    })?;
+    let config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![Certificate(cert)],
+            rustls::PrivateKey(key.as_bytes().to_vec()),
+        )
+        .map_err(|e| {
+            error!("Invalid TLS certificate or private key: {e}");
+            StatusCode::BadCertificateInvalid
+        })?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Create a TLS connector that validates server certificates using `certificate_store`.
+pub fn connector(certificate_store: Arc<RwLock<CertificateStore>>) -> TlsConnector {
+    let config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(CertificateStoreVerifier { certificate_store }))
+        .with_no_client_auth();
+    TlsConnector::from(Arc::new(config))
+}
+
+/// Get the TLS server name for `host`, which may be a DNS name or an IP address.
+pub fn server_name(host: &str) -> Result<ServerName, StatusCode> {
+    ServerName::try_from(host).map_err(|_| {
+        error!("Invalid TLS server name {host}");
+        StatusCode::BadTcpEndpointUrlInvalid
+    })
+}
+
+/// Certificate verifier using an OPC UA certificate store, so that the server
+/// certificate is trusted according to the same rules as certificates used
+/// for OPC UA security.
+struct CertificateStoreVerifier {
+    certificate_store: Arc<RwLock<CertificateStore>>,
+}
+
+impl ServerCertVerifier for CertificateStoreVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let cert = X509::from_der(&end_entity.0)
+            .map_err(|_| rustls::Error::InvalidCertificate(CertificateError::BadEncoding))?;
+        let hostname = match server_name {
+            ServerName::DnsName(name) => name.as_ref().to_owned(),
+            ServerName::IpAddress(addr) => addr.to_string(),
+            _ => return Err(rustls::Error::UnsupportedNameType),
+        };
+        self.certificate_store
+            .read()
+            .validate_application_instance_cert(&cert, SecurityPolicy::None, Some(&hostname), None)
+            .map_err(|e| {
+                error!("TLS server certificate is not trusted: {e}");
+                rustls::Error::InvalidCertificate(CertificateError::ApplicationVerificationFailure)
+            })?;
+        Ok(ServerCertVerified::assertion())
+    }
+}
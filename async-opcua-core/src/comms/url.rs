@@ -11,6 +11,10 @@ use opcua_types::status_code::StatusCode;
 
 /// Scheme for OPC-UA TCP.
 pub const OPC_TCP_SCHEME: &str = "opc.tcp";
+/// Scheme for OPC-UA binary over WebSockets.
+pub const OPC_WS_SCHEME: &str = "opc.ws";
+/// Scheme for OPC-UA binary over WebSockets with TLS.
+pub const OPC_WSS_SCHEME: &str = "opc.wss";
 
 /// Creates a `Url` from the input string, supplying a default port if necessary.
 fn opc_url_from_str(s: &str) -> Result<Url, url::ParseError> {
@@ -73,10 +77,22 @@ pub fn is_valid_opc_ua_url(url: &str) -> bool {
     is_opc_ua_binary_url(url)
 }
 
-/// Check if this is an OPC-UA TCP URL.
+/// Check if this is an OPC-UA binary URL, using either TCP or WebSockets.
 pub fn is_opc_ua_binary_url(url: &str) -> bool {
     if let Ok(url) = opc_url_from_str(url) {
-        url.scheme() == OPC_TCP_SCHEME
+        matches!(
+            url.scheme(),
+            OPC_TCP_SCHEME | OPC_WS_SCHEME | OPC_WSS_SCHEME
+        )
+    } else {
+        false
+    }
+}
+
+/// Check if this is an OPC-UA WebSocket URL, with or without TLS.
+pub fn is_websocket_url(url: &str) -> bool {
+    if let Ok(url) = opc_url_from_str(url) {
+        matches!(url.scheme(), OPC_WS_SCHEME | OPC_WSS_SCHEME)
     } else {
         false
     }
@@ -112,7 +128,11 @@ pub fn hostname_port_from_url(url: &str, default_port: u16) -> Result<(String, u
     // Validate and split out the endpoint we have
     let url = Url::parse(url).map_err(|_| StatusCode::BadTcpEndpointUrlInvalid)?;
 
-    if url.scheme() != OPC_TCP_SCHEME || !url.has_host() {
+    if !matches!(
+        url.scheme(),
+        OPC_TCP_SCHEME | OPC_WS_SCHEME | OPC_WSS_SCHEME
+    ) || !url.has_host()
+    {
         Err(StatusCode::BadTcpEndpointUrlInvalid)
     } else {
         let host = url.host_str().unwrap();
@@ -131,7 +151,24 @@ mod tests {
         assert!(is_opc_ua_binary_url(
             "opc.tcp://[FEDC:BA98:7654:3210:FEDC:BA98:7654:3210]:80/xyz"
         ));
+        assert!(is_opc_ua_binary_url("opc.ws://foo/xyz"));
+        assert!(is_opc_ua_binary_url("opc.wss://foo:443/xyz"));
         assert!(!is_opc_ua_binary_url("http://foo/xyz"));
+        assert!(!is_websocket_url("opc.tcp://foo/xyz"));
+        assert!(is_websocket_url("opc.wss://foo/xyz"));
+    }
+
+    #[test]
+    fn hostname_port_test() {
+        assert_eq!(
+            hostname_port_from_url("opc.tcp://foo:123/xyz", 4840).unwrap(),
+            ("foo".to_owned(), 123)
+        );
+        assert_eq!(
+            hostname_port_from_url("opc.wss://foo/xyz", 4840).unwrap(),
+            ("foo".to_owned(), 4840)
+        );
+        assert!(hostname_port_from_url("http://foo/xyz", 4840).is_err());
     }
 
     #[test]
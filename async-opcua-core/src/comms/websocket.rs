@@ -0,0 +1,637 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2024 Adam Lock
+
+//! Minimal implementation of the WebSocket protocol, RFC 6455, used to carry
+//! OPC UA binary messages as described in OPC UA Part 6, 7.5.
+//!
+//! The WebSocket connection uses the `opcua+uacp` subprotocol, meaning that the
+//! stream carries exactly the same data as a plain `opc.tcp` connection, so the
+//! [`TcpCodec`](super::tcp_codec::TcpCodec) can be used unchanged on top of a
+//! [`WebSocketStream`].
+
+use std::{
+    io::{self, ErrorKind},
+    pin::Pin,
+    task::{ready, Context, Poll, Waker},
+};
+
+use base64::Engine;
+use bytes::{Buf, BufMut, BytesMut};
+use sha1::{Digest, Sha1};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tracing::{debug, error};
+use url::Url;
+
+/// WebSocket subprotocol for OPC UA binary messages using the UA Connection Protocol.
+pub const WEBSOCKET_SUBPROTOCOL: &str = "opcua+uacp";
+
+/// GUID appended to the key in the WebSocket handshake, defined in RFC 6455.
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Maximum size of the HTTP headers in the WebSocket handshake.
+const MAX_HANDSHAKE_SIZE: usize = 8192;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+/// Maximum payload size of a control frame, RFC 6455, 5.5.
+const MAX_CONTROL_FRAME_SIZE: u64 = 125;
+
+/// Stream implementing `AsyncRead` and `AsyncWrite` over a WebSocket connection.
+/// Each write is sent as a single binary frame, and the payload of incoming binary frames
+/// is returned from reads.
+///
+/// Note that like other buffered streams, a write that returns `Pending` must be
+/// retried with the same data.
+pub struct WebSocketStream<S> {
+    inner: S,
+    /// Whether outgoing frames should be masked, which is required for clients.
+    /// Incoming frames must be masked if and only if this is `false`.
+    mask: bool,
+    /// Maximum payload size of a received frame.
+    max_frame_size: usize,
+    /// Raw data read from the inner stream, not yet decoded.
+    read_buf: BytesMut,
+    /// Decoded payload data, not yet returned to the reader.
+    payload: BytesMut,
+    /// Encoded frames, not yet written to the inner stream.
+    write_buf: BytesMut,
+    /// Length of the data frame in `write_buf`, returned once it has been written.
+    pending_write: Option<usize>,
+    /// A control frame, such as a pong, is waiting in `write_buf`. No more frames are
+    /// decoded until it has been written, so a peer sending pings without reading the
+    /// responses cannot make `write_buf` grow without limit.
+    control_pending: bool,
+    /// Waker of a reader waiting for a pending write to send the queued control frames.
+    read_waker: Option<Waker>,
+    /// The connection has been closed, by either side.
+    closed: bool,
+}
+
+fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(WEBSOCKET_GUID.as_bytes());
+    base64::engine::general_purpose::STANDARD.encode(hasher.finalize())
+}
+
+fn handshake_error(msg: impl Into<String>) -> io::Error {
+    let msg = msg.into();
+    error!("WebSocket handshake failed: {msg}");
+    io::Error::new(ErrorKind::InvalidData, msg)
+}
+
+/// Read the HTTP header part of a handshake message. Returns the header lines and
+/// any data following the header.
+async fn read_http_header<S: AsyncRead + Unpin>(
+    stream: &mut S,
+) -> io::Result<(Vec<String>, BytesMut)> {
+    let mut buf = BytesMut::with_capacity(1024);
+    loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            let header = buf.split_to(pos + 4);
+            let header = std::str::from_utf8(&header)
+                .map_err(|_| handshake_error("HTTP header is not valid UTF-8"))?;
+            let lines = header
+                .split("\r\n")
+                .filter(|l| !l.is_empty())
+                .map(|l| l.to_owned())
+                .collect();
+            return Ok((lines, buf));
+        }
+        if buf.len() > MAX_HANDSHAKE_SIZE {
+            return Err(handshake_error("HTTP header is too large"));
+        }
+        if stream.read_buf(&mut buf).await? == 0 {
+            return Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "Stream closed during WebSocket handshake",
+            ));
+        }
+    }
+}
+
+/// Find the value of the header with the given name, ignoring case.
+fn header_value<'a>(lines: &'a [String], name: &str) -> Option<&'a str> {
+    lines.iter().skip(1).find_map(|l| {
+        let (key, value) = l.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then_some(value.trim())
+    })
+}
+
+/// Check if a comma separated header value contains the given token, ignoring case.
+fn header_contains(lines: &[String], name: &str, token: &str) -> bool {
+    header_value(lines, name)
+        .is_some_and(|v| v.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)))
+}
+
+/// Perform the client side of the WebSocket handshake on `stream`, requesting the
+/// `opcua+uacp` subprotocol. The host and path of `url` are sent in the HTTP request.
+///
+/// Frames received from the server larger than `max_frame_size` are rejected, this should
+/// be the receive buffer size, which is the largest chunk the server may send.
+pub async fn client_handshake<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    url: &str,
+    max_frame_size: usize,
+) -> io::Result<WebSocketStream<S>> {
+    let url = Url::parse(url).map_err(|e| handshake_error(format!("Invalid url {url}: {e}")))?;
+    let host = match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{host}:{port}"),
+        (Some(host), None) => host.to_owned(),
+        (None, _) => return Err(handshake_error(format!("Missing host in url {url}"))),
+    };
+    let path = match url.query() {
+        Some(query) => format!("{}?{query}", url.path()),
+        None => url.path().to_owned(),
+    };
+    let path = if path.is_empty() { "/" } else { &path };
+
+    let mut key = [0u8; 16];
+    opcua_crypto::random::bytes(&mut key);
+    let key = base64::engine::general_purpose::STANDARD.encode(key);
+    let request = format!(
+        "GET {path} HTTP/1.1\r\n\
+         Host: {host}\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Key: {key}\r\n\
+         Sec-WebSocket-Version: 13\r\n\
+         Sec-WebSocket-Protocol: {WEBSOCKET_SUBPROTOCOL}\r\n\r\n"
+    );
+    stream.write_all(request.as_bytes()).await?;
+    stream.flush().await?;
+
+    let (lines, rest) = read_http_header(&mut stream).await?;
+    let status = lines.first().map(|l| l.as_str()).unwrap_or_default();
+    if status.split_whitespace().nth(1) != Some("101") {
+        return Err(handshake_error(format!(
+            "Server did not accept the WebSocket upgrade: {status}"
+        )));
+    }
+    if header_value(&lines, "Sec-WebSocket-Accept") != Some(accept_key(&key).as_str()) {
+        return Err(handshake_error("Invalid Sec-WebSocket-Accept header"));
+    }
+    if header_value(&lines, "Sec-WebSocket-Protocol") != Some(WEBSOCKET_SUBPROTOCOL) {
+        return Err(handshake_error(format!(
+            "Server did not accept the {WEBSOCKET_SUBPROTOCOL} subprotocol"
+        )));
+    }
+
+    let mut stream = WebSocketStream::new(stream, true, max_frame_size);
+    stream.read_buf = rest;
+    Ok(stream)
+}
+
+/// Perform the server side of the WebSocket handshake on `stream`. The client must request
+/// the `opcua+uacp` subprotocol. Returns the stream and the path requested by the client.
+///
+/// Frames received from the client larger than `max_frame_size` are rejected, this should
+/// be the receive buffer size, which is the largest chunk the client may send.
+pub async fn server_handshake<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    max_frame_size: usize,
+) -> io::Result<(WebSocketStream<S>, String)> {
+    let (lines, rest) = read_http_header(&mut stream).await?;
+    let request = lines.first().map(|l| l.as_str()).unwrap_or_default();
+    let mut parts = request.split_whitespace();
+    let (Some("GET"), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(handshake_error(format!("Invalid request: {request}")));
+    };
+    let path = path.to_owned();
+
+    let reject = |reason: &str| {
+        format!(
+            "HTTP/1.1 400 Bad Request\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{reason}",
+            reason.len()
+        )
+    };
+
+    let key = header_value(&lines, "Sec-WebSocket-Key");
+    let error = if !header_contains(&lines, "Upgrade", "websocket")
+        || !header_contains(&lines, "Connection", "upgrade")
+    {
+        Some("Not a WebSocket upgrade request")
+    } else if header_value(&lines, "Sec-WebSocket-Version") != Some("13") {
+        Some("Unsupported WebSocket version")
+    } else if key.is_none() {
+        Some("Missing Sec-WebSocket-Key")
+    } else if !header_contains(&lines, "Sec-WebSocket-Protocol", WEBSOCKET_SUBPROTOCOL) {
+        Some("The opcua+uacp subprotocol is required")
+    } else {
+        None
+    };
+    if let Some(error) = error {
+        let _ = stream.write_all(reject(error).as_bytes()).await;
+        let _ = stream.shutdown().await;
+        return Err(handshake_error(error));
+    }
+
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\
+         Sec-WebSocket-Protocol: {WEBSOCKET_SUBPROTOCOL}\r\n\r\n",
+        accept_key(key.unwrap_or_default())
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await?;
+    debug!("Accepted WebSocket connection on path {path}");
+
+    let mut stream = WebSocketStream::new(stream, false, max_frame_size);
+    stream.read_buf = rest;
+    Ok((stream, path))
+}
+
+impl<S> WebSocketStream<S> {
+    fn new(inner: S, mask: bool, max_frame_size: usize) -> Self {
+        Self {
+            inner,
+            mask,
+            max_frame_size,
+            read_buf: BytesMut::new(),
+            payload: BytesMut::new(),
+            write_buf: BytesMut::new(),
+            pending_write: None,
+            control_pending: false,
+            read_waker: None,
+            closed: false,
+        }
+    }
+
+    /// Get a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    fn encode_frame(&mut self, opcode: u8, payload: &[u8]) {
+        let buf = &mut self.write_buf;
+        buf.reserve(payload.len() + 14);
+        buf.put_u8(0x80 | opcode);
+        let mask_bit = if self.mask { 0x80 } else { 0 };
+        if payload.len() < 126 {
+            buf.put_u8(mask_bit | payload.len() as u8);
+        } else if payload.len() <= u16::MAX as usize {
+            buf.put_u8(mask_bit | 126);
+            buf.put_u16(payload.len() as u16);
+        } else {
+            buf.put_u8(mask_bit | 127);
+            buf.put_u64(payload.len() as u64);
+        }
+        if self.mask {
+            let mut key = [0u8; 4];
+            opcua_crypto::random::bytes(&mut key);
+            buf.put_slice(&key);
+            buf.extend(payload.iter().enumerate().map(|(i, b)| b ^ key[i % 4]));
+        } else {
+            buf.put_slice(payload);
+        }
+    }
+
+    /// Try to decode a single frame from the read buffer, returning `false` if
+    /// more data is needed.
+    fn decode_frame(&mut self) -> io::Result<bool> {
+        let buf = &self.read_buf[..];
+        if buf.len() < 2 {
+            return Ok(false);
+        }
+        let fin = buf[0] & 0x80 != 0;
+        let opcode = buf[0] & 0x0F;
+        let masked = buf[1] & 0x80 != 0;
+        // RFC 6455, 5.1: clients must mask all frames, servers must not mask any.
+        if masked == self.mask {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                if masked {
+                    "Received a masked WebSocket frame from the server"
+                } else {
+                    "Received an unmasked WebSocket frame from the client"
+                },
+            ));
+        }
+        let mut header_len = 2;
+        let len = match buf[1] & 0x7F {
+            126 => {
+                header_len += 2;
+                if buf.len() < header_len {
+                    return Ok(false);
+                }
+                u16::from_be_bytes([buf[2], buf[3]]) as u64
+            }
+            127 => {
+                header_len += 8;
+                if buf.len() < header_len {
+                    return Ok(false);
+                }
+                u64::from_be_bytes(buf[2..10].try_into().unwrap())
+            }
+            l => l as u64,
+        };
+        // RFC 6455, 5.5: control frames must not be fragmented, and have a payload of
+        // at most 125 bytes.
+        if opcode & 0x8 != 0 && (!fin || len > MAX_CONTROL_FRAME_SIZE) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Invalid WebSocket control frame with opcode {opcode} and size {len}"),
+            ));
+        }
+        if len > self.max_frame_size as u64 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "WebSocket frame of size {len} exceeds the maximum frame size {}",
+                    self.max_frame_size
+                ),
+            ));
+        }
+        let mask_start = header_len;
+        if masked {
+            header_len += 4;
+        }
+        let len = len as usize;
+        if buf.len() < header_len + len {
+            self.read_buf.reserve(header_len + len - buf.len());
+            return Ok(false);
+        }
+
+        let mut frame = self.read_buf.split_to(header_len + len);
+        if masked {
+            let key: [u8; 4] = frame[mask_start..mask_start + 4].try_into().unwrap();
+            for (i, b) in frame[header_len..].iter_mut().enumerate() {
+                *b ^= key[i % 4];
+            }
+        }
+        frame.advance(header_len);
+
+        match opcode {
+            OPCODE_BINARY | OPCODE_CONTINUATION => self.payload.extend_from_slice(&frame),
+            OPCODE_PING => {
+                self.encode_frame(OPCODE_PONG, &frame);
+                self.control_pending = true;
+            }
+            OPCODE_PONG => {}
+            OPCODE_CLOSE => {
+                debug!("Received WebSocket close frame");
+                if !self.closed {
+                    // Echo the status code back, as required by RFC 6455.
+                    let status = frame.get(0..2).unwrap_or_default().to_vec();
+                    self.encode_frame(OPCODE_CLOSE, &status);
+                    self.control_pending = true;
+                    self.closed = true;
+                }
+            }
+            OPCODE_TEXT => {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "Text frames are not allowed with the opcua+uacp subprotocol",
+                ))
+            }
+            _ => {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Unknown WebSocket opcode {opcode}"),
+                ))
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> WebSocketStream<S> {
+    /// Write all encoded frames to the inner stream.
+    fn poll_write_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.write_buf.is_empty() {
+            let written = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.write_buf))?;
+            if written == 0 {
+                return Poll::Ready(Err(ErrorKind::WriteZero.into()));
+            }
+            self.write_buf.advance(written);
+        }
+        if self.control_pending {
+            self.control_pending = false;
+            if let Some(waker) = self.read_waker.take() {
+                waker.wake();
+            }
+        }
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for WebSocketStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if !this.payload.is_empty() {
+                let len = this.payload.len().min(buf.remaining());
+                buf.put_slice(&this.payload.split_to(len));
+                return Poll::Ready(Ok(()));
+            }
+            // Send any pending control frames, such as pong, before decoding more frames.
+            if this.control_pending {
+                if this.pending_write.is_some() {
+                    // The control frames are queued after a data frame, and are sent
+                    // by the pending write.
+                    this.read_waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+                match this.poll_write_buf(cx) {
+                    Poll::Ready(Ok(())) => (),
+                    // The peer may close the connection right after sending a close frame.
+                    Poll::Ready(Err(_)) if this.closed => {
+                        this.write_buf.clear();
+                        this.control_pending = false;
+                    }
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+            if this.decode_frame()? {
+                continue;
+            }
+            if this.closed {
+                return Poll::Ready(Ok(()));
+            }
+
+            let mut chunk = [0u8; 8192];
+            let mut read = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut read))?;
+            if read.filled().is_empty() {
+                this.closed = true;
+                if !this.read_buf.is_empty() {
+                    return Poll::Ready(Err(ErrorKind::UnexpectedEof.into()));
+                }
+                return Poll::Ready(Ok(()));
+            }
+            this.read_buf.extend_from_slice(read.filled());
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for WebSocketStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.pending_write.is_none() {
+            // Write out any pending control frames first.
+            ready!(this.poll_write_buf(cx))?;
+            if this.closed {
+                return Poll::Ready(Err(ErrorKind::BrokenPipe.into()));
+            }
+            this.encode_frame(OPCODE_BINARY, buf);
+            this.pending_write = Some(buf.len());
+        }
+        ready!(this.poll_write_buf(cx))?;
+        Poll::Ready(Ok(this.pending_write.take().unwrap_or_default()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_write_buf(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.closed {
+            this.closed = true;
+            // Status code 1000, normal closure.
+            this.encode_frame(OPCODE_CLOSE, &1000u16.to_be_bytes());
+        }
+        ready!(this.poll_write_buf(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::{accept_key, client_handshake, server_handshake};
+
+    #[test]
+    fn handshake_accept_key() {
+        // Example from RFC 6455, section 1.3.
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[tokio::test]
+    async fn websocket_roundtrip() {
+        let (client, server) = tokio::io::duplex(64);
+        let server = tokio::spawn(async move {
+            let (mut stream, path) = server_handshake(server, 100_000).await.unwrap();
+            assert_eq!(path, "/test");
+            let mut buf = vec![0u8; 100_000];
+            stream.read_exact(&mut buf).await.unwrap();
+            stream.write_all(&buf).await.unwrap();
+            stream.shutdown().await.unwrap();
+        });
+
+        let mut stream = client_handshake(client, "opc.ws://localhost:4840/test", 100_000)
+            .await
+            .unwrap();
+        let data: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+        stream.write_all(&data).await.unwrap();
+        let mut echo = Vec::new();
+        stream.read_to_end(&mut echo).await.unwrap();
+        assert_eq!(echo, data);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn websocket_requires_subprotocol() {
+        let (mut client, server) = tokio::io::duplex(1024);
+        let server = tokio::spawn(async move { server_handshake(server, 1024).await.map(|_| ()) });
+        client
+            .write_all(
+                b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                  Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        assert!(server.await.unwrap().is_err());
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 400"));
+    }
+
+    async fn server_read_frame(frame: &[u8]) -> std::io::Result<Vec<u8>> {
+        let (mut client, server) = tokio::io::duplex(1024);
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = server_handshake(server, 1024).await?;
+            let mut buf = Vec::new();
+            stream.read_to_end(&mut buf).await?;
+            Ok(buf)
+        });
+        client
+            .write_all(
+                b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                  Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\
+                  Sec-WebSocket-Protocol: opcua+uacp\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        client.write_all(frame).await.unwrap();
+        client.shutdown().await.unwrap();
+        server.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn websocket_server_requires_masked_frames() {
+        // Masked binary frame with payload "abc" and a zero mask.
+        let data = server_read_frame(&[0x82, 0x83, 0, 0, 0, 0, b'a', b'b', b'c'])
+            .await
+            .unwrap();
+        assert_eq!(data, b"abc");
+        // The same frame without a mask is rejected.
+        let err = server_read_frame(&[0x82, 0x03, b'a', b'b', b'c'])
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn websocket_rejects_invalid_control_frames() {
+        // Masked ping with a 126 byte payload.
+        let mut frame = vec![0x89, 0xFE, 0, 126, 0, 0, 0, 0];
+        frame.extend_from_slice(&[0; 126]);
+        let err = server_read_frame(&frame).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        // Masked ping without FIN set.
+        let err = server_read_frame(&[0x09, 0x80, 0, 0, 0, 0])
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        // A valid ping is answered, and does not interrupt the data.
+        let data = server_read_frame(&[
+            0x89, 0x81, 0, 0, 0, 0, b'x', 0x82, 0x83, 0, 0, 0, 0, b'a', b'b', b'c',
+        ])
+        .await
+        .unwrap();
+        assert_eq!(data, b"abc");
+    }
+
+    #[tokio::test]
+    async fn websocket_rejects_oversized_frame() {
+        // Header of a masked binary frame claiming a 64 KiB payload, the payload is never sent.
+        let err = server_read_frame(&[0x82, 0xFE, 0xFF, 0xFF, 0, 0, 0, 0])
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
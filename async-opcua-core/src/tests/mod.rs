@@ -3,6 +3,7 @@ use std::fmt::Debug;
 use std::io::Cursor;
 
 use opcua_crypto::{
+    ecc::EccCurve,
     pkey::PrivateKey,
     security_policy::SecurityPolicy,
     x509::{X509Data, X509},
@@ -64,7 +65,7 @@ fn make_secure_channel(
     secure_channel.set_security_policy(security_policy);
     secure_channel.set_local_nonce(&local_nonce);
     secure_channel.set_remote_nonce(&remote_nonce);
-    secure_channel.derive_keys();
+    secure_channel.derive_keys().unwrap();
     secure_channel
 }
 
@@ -156,6 +157,20 @@ fn make_test_cert(key_size: u32) -> (X509, PrivateKey) {
     cert.unwrap()
 }
 
+fn make_test_ecc_cert(curve: EccCurve) -> (X509, PrivateKey) {
+    let args = X509Data {
+        key_size: 0,
+        common_name: "x".to_string(),
+        organization: "x.org".to_string(),
+        organizational_unit: "x.org ops".to_string(),
+        country: "EN".to_string(),
+        state: "London".to_string(),
+        alt_host_names: vec!["urn:testapplication".to_string(), "testhost".to_string()].into(),
+        certificate_duration_days: 60,
+    };
+    X509::ecc_cert_and_pkey(&args, curve).unwrap()
+}
+
 fn make_test_cert_2048() -> (X509, PrivateKey) {
     make_test_cert(2048)
 }
@@ -15,9 +15,48 @@ fn test_symmetric_encrypt_decrypt(
     security_mode: MessageSecurityMode,
     security_policy: SecurityPolicy,
 ) {
-    let (secure_channel1, mut secure_channel2) =
-        make_secure_channels(security_mode, security_policy);
+    let (secure_channel1, secure_channel2) = make_secure_channels(security_mode, security_policy);
+    test_symmetric_encrypt_decrypt_channels(message, secure_channel1, secure_channel2);
+}
 
+/// Makes a client and a server channel that have agreed on keys through an exchange
+/// of ephemeral keys, as done with the ECC security policies.
+fn make_ecc_secure_channels(
+    security_mode: MessageSecurityMode,
+    security_policy: SecurityPolicy,
+) -> (SecureChannel, SecureChannel) {
+    let make_channel = |role| {
+        let mut secure_channel = SecureChannel::new_no_certificate_store();
+        secure_channel.set_role(role);
+        secure_channel.set_security_mode(security_mode);
+        secure_channel.set_security_policy(security_policy);
+        secure_channel.create_random_nonce();
+        secure_channel
+    };
+    let mut client = make_channel(Role::Client);
+    let mut server = make_channel(Role::Server);
+    client.set_remote_nonce(server.local_nonce());
+    server.set_remote_nonce(client.local_nonce());
+    client.derive_keys().unwrap();
+    server.derive_keys().unwrap();
+    (client, server)
+}
+
+fn test_ecc_symmetric_encrypt_decrypt(
+    security_mode: MessageSecurityMode,
+    security_policy: SecurityPolicy,
+) {
+    let (client, server) = make_ecc_secure_channels(security_mode, security_policy);
+    test_symmetric_encrypt_decrypt_channels(make_sample_message(), client, server);
+    let (client, server) = make_ecc_secure_channels(security_mode, security_policy);
+    test_symmetric_encrypt_decrypt_channels(make_sample_message(), server, client);
+}
+
+fn test_symmetric_encrypt_decrypt_channels(
+    message: impl Message + PartialEq + Debug,
+    secure_channel1: SecureChannel,
+    mut secure_channel2: SecureChannel,
+) {
     let mut chunks = Chunker::encode(
         SequenceNumberHandle::new(true),
         1,
@@ -233,3 +272,134 @@ fn symmetric_sign_and_encrypt_message_chunk_basic256sha256() {
         SecurityPolicy::Basic256Sha256,
     );
 }
+
+/// Sign an OpenSecureChannel chunk with an ECC key and verify it on the other side. The ECC
+/// policies do not encrypt these messages.
+fn test_ecc_asymmetric_sign_verify(security_policy: SecurityPolicy) {
+    use crate::ResponseMessage;
+
+    let curve = security_policy.ecc_curve().unwrap();
+    let (our_cert, our_key) = make_test_ecc_cert(curve);
+    let (their_cert, their_key) = make_test_ecc_cert(curve);
+
+    let mut secure_channel = SecureChannel::new_no_certificate_store();
+    secure_channel.set_security_mode(MessageSecurityMode::SignAndEncrypt);
+    secure_channel.set_security_policy(security_policy);
+    secure_channel.set_ecc_cert_and_key(curve, Some((our_cert.clone(), our_key)));
+    secure_channel.set_remote_cert(Some(their_cert.clone()));
+
+    let message: ResponseMessage = make_open_secure_channel_response().into();
+    let mut chunks = Chunker::encode(
+        SequenceNumberHandle::new(false),
+        1,
+        0,
+        0,
+        &secure_channel,
+        &message,
+    )
+    .unwrap();
+    assert_eq!(chunks.len(), 1);
+    let chunk = &mut chunks[0];
+
+    let mut signed_data = vec![0u8; chunk.data.len() + 4096];
+    let signed_size = secure_channel
+        .apply_security(chunk, &mut signed_data[..])
+        .unwrap();
+    // Only a signature is appended to the message
+    assert_eq!(
+        signed_size,
+        chunk.data.len()
+            + secure_channel
+                .cert()
+                .unwrap()
+                .public_key()
+                .unwrap()
+                .signature_size()
+    );
+
+    secure_channel.set_ecc_cert_and_key(curve, Some((their_cert, their_key)));
+    secure_channel.set_remote_cert(Some(our_cert));
+
+    let chunk2 = secure_channel
+        .verify_and_remove_security(&signed_data[..signed_size])
+        .unwrap();
+    assert_eq!(&chunk.data, &chunk2.data);
+
+    // A modified message fails verification
+    signed_data[signed_size - 100] ^= 0xff;
+    assert!(secure_channel
+        .verify_and_remove_security(&signed_data[..signed_size])
+        .is_err());
+}
+
+/// The ECC security policies, brainpoolP256r1 is only supported if `async-opcua-crypto`
+/// is built with the `brainpool` feature.
+fn ecc_policies() -> impl Iterator<Item = SecurityPolicy> {
+    [
+        SecurityPolicy::EccNistP256,
+        SecurityPolicy::EccNistP384,
+        SecurityPolicy::EccBrainpoolP256r1,
+        SecurityPolicy::EccCurve25519,
+    ]
+    .into_iter()
+    .filter(SecurityPolicy::is_supported)
+}
+
+#[test]
+fn asymmetric_sign_message_chunk_ecc() {
+    let _ = Test::setup();
+    for policy in ecc_policies() {
+        test_ecc_asymmetric_sign_verify(policy);
+    }
+}
+
+#[test]
+fn symmetric_sign_message_chunk_ecc() {
+    let _ = Test::setup();
+    for policy in ecc_policies() {
+        test_ecc_symmetric_encrypt_decrypt(MessageSecurityMode::Sign, policy);
+    }
+}
+
+#[test]
+fn symmetric_sign_and_encrypt_message_chunk_ecc() {
+    let _ = Test::setup();
+    for policy in ecc_policies() {
+        test_ecc_symmetric_encrypt_decrypt(MessageSecurityMode::SignAndEncrypt, policy);
+    }
+}
+
+#[test]
+fn symmetric_aead_rejects_tampering() {
+    let _ = Test::setup();
+    let (client, mut server) = make_ecc_secure_channels(
+        MessageSecurityMode::SignAndEncrypt,
+        SecurityPolicy::EccCurve25519,
+    );
+    let mut chunks = Chunker::encode(
+        SequenceNumberHandle::new(false),
+        1,
+        0,
+        0,
+        &client,
+        &make_sample_message(),
+    )
+    .unwrap();
+    let chunk = &mut chunks[0];
+    let mut encrypted_data = vec![0u8; chunk.data.len() + 4096];
+    let encrypted_size = client
+        .apply_security(chunk, &mut encrypted_data[..])
+        .unwrap();
+    // The message body is encrypted
+    assert_ne!(
+        &encrypted_data[encrypted_size - 40..encrypted_size - 16],
+        &chunk.data[chunk.data.len() - 24..]
+    );
+    assert!(server
+        .verify_and_remove_security(&encrypted_data[..encrypted_size])
+        .is_ok());
+    encrypted_data[encrypted_size - 20] ^= 1;
+    assert!(server
+        .verify_and_remove_security(&encrypted_data[..encrypted_size])
+        .is_err());
+}
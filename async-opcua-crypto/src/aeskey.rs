@@ -3,6 +3,9 @@
 // Copyright (C) 2017-2024 Adam Lock
 
 //! Symmetric encryption / decryption wrapper.
+//!
+//! Despite the name, [`AesKey`] also holds the ChaCha20-Poly1305 key used by the `ECC_curve25519`
+//! security policy.
 
 use std::result::Result;
 
@@ -12,6 +15,7 @@ use aes::cipher::{
     KeyIvInit,
 };
 use cbc;
+use chacha20poly1305::{aead::AeadInPlace, ChaCha20Poly1305, KeyInit};
 
 use opcua_types::status_code::StatusCode;
 use opcua_types::Error;
@@ -26,6 +30,10 @@ type Aes256CbcDec = cbc::Decryptor<aes::Aes256>;
 const AES_BLOCK_SIZE: usize = 16;
 const AES128_KEY_SIZE: usize = 16;
 const AES256_KEY_SIZE: usize = 32;
+const CHACHA20_KEY_SIZE: usize = 32;
+const CHACHA20_NONCE_SIZE: usize = 12;
+/// Size of the Poly1305 authentication tag in bytes.
+pub const POLY1305_TAG_SIZE: usize = 16;
 
 type AesArray128 = GenericArray<u8, <aes::Aes128 as aes::cipher::BlockSizeUser>::BlockSize>;
 type AesArray256 = GenericArray<u8, <aes::Aes256 as aes::cipher::KeySizeUser>::KeySize>;
@@ -128,7 +136,10 @@ impl AesKey {
             | SecurityPolicy::Aes128Sha256RsaOaep
             | SecurityPolicy::Basic256
             | SecurityPolicy::Basic256Sha256
-            | SecurityPolicy::Aes256Sha256RsaPss => AES_BLOCK_SIZE,
+            | SecurityPolicy::Aes256Sha256RsaPss
+            | SecurityPolicy::EccNistP256
+            | SecurityPolicy::EccNistP384
+            | SecurityPolicy::EccBrainpoolP256r1 => AES_BLOCK_SIZE,
             _ => 0,
         }
     }
@@ -140,7 +151,11 @@ impl AesKey {
             | SecurityPolicy::Aes128Sha256RsaOaep
             | SecurityPolicy::Basic256
             | SecurityPolicy::Basic256Sha256
-            | SecurityPolicy::Aes256Sha256RsaPss => AES_BLOCK_SIZE,
+            | SecurityPolicy::Aes256Sha256RsaPss
+            | SecurityPolicy::EccNistP256
+            | SecurityPolicy::EccNistP384
+            | SecurityPolicy::EccBrainpoolP256r1 => AES_BLOCK_SIZE,
+            SecurityPolicy::EccCurve25519 => CHACHA20_NONCE_SIZE,
             _ => 0,
         }
     }
@@ -148,11 +163,16 @@ impl AesKey {
     /// Get the AES key length.
     pub fn key_length(&self) -> usize {
         match self.security_policy {
-            SecurityPolicy::Basic128Rsa15 | SecurityPolicy::Aes128Sha256RsaOaep => AES128_KEY_SIZE,
+            SecurityPolicy::Basic128Rsa15
+            | SecurityPolicy::Aes128Sha256RsaOaep
+            | SecurityPolicy::EccNistP256
+            | SecurityPolicy::EccBrainpoolP256r1 => AES128_KEY_SIZE,
 
             SecurityPolicy::Basic256
             | SecurityPolicy::Basic256Sha256
-            | SecurityPolicy::Aes256Sha256RsaPss => AES256_KEY_SIZE,
+            | SecurityPolicy::Aes256Sha256RsaPss
+            | SecurityPolicy::EccNistP384 => AES256_KEY_SIZE,
+            SecurityPolicy::EccCurve25519 => CHACHA20_KEY_SIZE,
             _ => 0,
         }
     }
@@ -160,13 +180,15 @@ impl AesKey {
     /// Encrypt data in `src` into `dst`.
     pub fn encrypt(&self, src: &[u8], iv: &[u8], dst: &mut [u8]) -> EncryptResult {
         match self.security_policy {
-            SecurityPolicy::Basic128Rsa15 | SecurityPolicy::Aes128Sha256RsaOaep => {
-                self.encrypt_aes128_cbc(src, iv, dst)
-            }
+            SecurityPolicy::Basic128Rsa15
+            | SecurityPolicy::Aes128Sha256RsaOaep
+            | SecurityPolicy::EccNistP256
+            | SecurityPolicy::EccBrainpoolP256r1 => self.encrypt_aes128_cbc(src, iv, dst),
 
             SecurityPolicy::Basic256
             | SecurityPolicy::Basic256Sha256
-            | SecurityPolicy::Aes256Sha256RsaPss => self.encrypt_aes256_cbc(src, iv, dst),
+            | SecurityPolicy::Aes256Sha256RsaPss
+            | SecurityPolicy::EccNistP384 => self.encrypt_aes256_cbc(src, iv, dst),
 
             _ => Err(Error::new(
                 StatusCode::BadUnexpectedError,
@@ -178,13 +200,15 @@ impl AesKey {
     /// Decrypts data using AES. The initialization vector is the nonce generated for the secure channel
     pub fn decrypt(&self, src: &[u8], iv: &[u8], dst: &mut [u8]) -> EncryptResult {
         match self.security_policy {
-            SecurityPolicy::Basic128Rsa15 | SecurityPolicy::Aes128Sha256RsaOaep => {
-                self.decrypt_aes128_cbc(src, iv, dst)
-            }
+            SecurityPolicy::Basic128Rsa15
+            | SecurityPolicy::Aes128Sha256RsaOaep
+            | SecurityPolicy::EccNistP256
+            | SecurityPolicy::EccBrainpoolP256r1 => self.decrypt_aes128_cbc(src, iv, dst),
 
             SecurityPolicy::Basic256
             | SecurityPolicy::Basic256Sha256
-            | SecurityPolicy::Aes256Sha256RsaPss => self.decrypt_aes256_cbc(src, iv, dst),
+            | SecurityPolicy::Aes256Sha256RsaPss
+            | SecurityPolicy::EccNistP384 => self.decrypt_aes256_cbc(src, iv, dst),
 
             _ => Err(Error::new(
                 StatusCode::BadUnexpectedError,
@@ -192,6 +216,66 @@ impl AesKey {
             )),
         }
     }
+
+    fn chacha20poly1305(&self, nonce: &[u8]) -> Result<ChaCha20Poly1305, Error> {
+        if self.security_policy != SecurityPolicy::EccCurve25519 {
+            return Err(Error::new(
+                StatusCode::BadUnexpectedError,
+                "Unsupported security policy",
+            ));
+        }
+        if nonce.len() != CHACHA20_NONCE_SIZE {
+            return Err(Error::new(
+                StatusCode::BadUnexpectedError,
+                format!("Nonce is not an expected size, len = {}", nonce.len()),
+            ));
+        }
+        ChaCha20Poly1305::new_from_slice(&self.value)
+            .map_err(|e| Error::new(StatusCode::BadUnexpectedError, e.to_string()))
+    }
+
+    /// Encrypt `buffer` in place using authenticated encryption, also authenticating
+    /// `associated_data`. The authentication tag is written to `tag`.
+    pub fn aead_encrypt(
+        &self,
+        nonce: &[u8],
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &mut [u8],
+    ) -> Result<(), Error> {
+        let cipher = self.chacha20poly1305(nonce)?;
+        let computed = cipher
+            .encrypt_in_place_detached(nonce.into(), associated_data, buffer)
+            .map_err(|e| Error::new(StatusCode::BadUnexpectedError, e.to_string()))?;
+        tag.copy_from_slice(&computed);
+        Ok(())
+    }
+
+    /// Decrypt `buffer` in place using authenticated encryption, verifying `tag` over the
+    /// cipher text and `associated_data`.
+    pub fn aead_decrypt(
+        &self,
+        nonce: &[u8],
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8],
+    ) -> Result<(), Error> {
+        let cipher = self.chacha20poly1305(nonce)?;
+        if tag.len() != POLY1305_TAG_SIZE {
+            return Err(Error::new(
+                StatusCode::BadSecurityChecksFailed,
+                "Authentication tag has the wrong length",
+            ));
+        }
+        cipher
+            .decrypt_in_place_detached(nonce.into(), associated_data, buffer, tag.into())
+            .map_err(|_| {
+                Error::new(
+                    StatusCode::BadSecurityChecksFailed,
+                    "Authentication tag is invalid",
+                )
+            })
+    }
 }
 
 #[cfg(test)]
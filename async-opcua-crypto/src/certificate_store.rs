@@ -9,11 +9,13 @@ use std::fs::File;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 
-use tracing::{debug, error, info, trace, warn};
+use tracing::{debug, error, info, warn};
 
-use opcua_types::status_code::StatusCode;
+use opcua_types::{status_code::StatusCode, ByteString, TrustListDataType, TrustListMasks};
 
 use super::{
+    crl::X509Crl,
+    ecc::EccCurve,
     pkey::PrivateKey,
     security_policy::SecurityPolicy,
     x509::{X509Data, X509},
@@ -27,9 +29,21 @@ const OWN_CERTIFICATE_PATH: &str = "own/cert.der";
 /// Default path to the applications own private key
 const OWN_PRIVATE_KEY_PATH: &str = "private/private.pem";
 /// The directory holding trusted certificates
-const TRUSTED_CERTS_DIR: &str = "trusted";
+const TRUSTED_CERTS_DIR: &str = "trusted/certs";
+/// The directory holding revocation lists for trusted certificate authorities
+const TRUSTED_CRL_DIR: &str = "trusted/crl";
+/// The directory holding certificate authorities that are not trusted themselves, but
+/// are needed to build certificate chains
+const ISSUER_CERTS_DIR: &str = "issuer/certs";
+/// The directory holding revocation lists for issuer certificate authorities
+const ISSUER_CRL_DIR: &str = "issuer/crl";
+/// The directory trusted certificates were stored in before the trust list was split
+/// into certificates and revocation lists
+const LEGACY_TRUSTED_CERTS_DIR: &str = "trusted";
 /// The directory holding rejected certificates
 const REJECTED_CERTS_DIR: &str = "rejected";
+/// Maximum number of certificates in a certificate chain
+const MAX_CHAIN_LENGTH: usize = 10;
 
 /// The certificate store manages the storage of a server/client's own certificate & private key
 /// and the trust / rejection of certificates from the other end.
@@ -51,6 +65,10 @@ pub struct CertificateStore {
     /// into the trusted folder if this flag is set. Certs in the trusted folder must still pass
     /// validity checks.
     trust_unknown_certs: bool,
+    /// Certificates whose revocation status is unknown, because an issuer has no current
+    /// revocation list, are normally accepted with a warning, but they are rejected if this
+    /// flag is set.
+    reject_unknown_revocation_status: bool,
 }
 
 impl CertificateStore {
@@ -65,6 +83,7 @@ impl CertificateStore {
             check_time: true,
             skip_verify_certs: false,
             trust_unknown_certs: false,
+            reject_unknown_revocation_status: false,
         }
     }
 
@@ -80,6 +99,7 @@ impl CertificateStore {
     where
         X: Into<X509Data>,
     {
+        let x509_data: Option<X509Data> = x509_data.map(Into::into);
         let mut certificate_store = CertificateStore::new(pki_path);
         if let (Some(cert_path), Some(pkey_path)) = (cert_path, pkey_path) {
             certificate_store.own_certificate_path = cert_path.to_path_buf();
@@ -91,13 +111,12 @@ impl CertificateStore {
         } else {
             let cert = certificate_store.read_own_cert();
             let pkey = certificate_store.read_own_pkey();
-            match (cert, pkey, x509_data) {
+            match (cert, pkey, &x509_data) {
                 (Ok(cert), Ok(pkey), _) => (Some(cert), Some(pkey)),
                 (_, _, Some(x509_data)) => {
                     info!("Creating sample application instance certificate and private key");
-                    let x509_data = x509_data.into();
                     let result = certificate_store
-                        .create_and_store_application_instance_cert(&x509_data, overwrite);
+                        .create_and_store_application_instance_cert(x509_data, overwrite);
                     match result {
                         Ok((cert, pkey)) => (Some(cert), Some(pkey)),
                         Err(err) => {
@@ -116,6 +135,22 @@ impl CertificateStore {
                 }
             }
         };
+        if let Some(x509_data) = &x509_data {
+            // The certificates for the ECC security policies are created along with the RSA one
+            for &curve in EccCurve::ALL {
+                if certificate_store.read_own_ecc_cert(curve).is_ok()
+                    && certificate_store.read_own_ecc_pkey(curve).is_ok()
+                {
+                    continue;
+                }
+                info!("Creating sample application instance certificate and private key for curve {curve}");
+                if let Err(err) = certificate_store
+                    .create_and_store_ecc_application_instance_cert(x509_data, curve, true)
+                {
+                    error!("Certificate creation for curve {curve} failed, error = {err}");
+                }
+            }
+        }
         (certificate_store, cert, pkey)
     }
 
@@ -130,11 +165,18 @@ impl CertificateStore {
         self.trust_unknown_certs = trust_unknown_certs;
     }
 
-    /// Check expiration time of incoming certificates.
+    /// Check expiration time of incoming certificates, and the update times
+    /// of revocation lists.
     pub fn set_check_time(&mut self, check_time: bool) {
         self.check_time = check_time;
     }
 
+    /// Set `reject_unknown_revocation_status` to reject certificates issued by a certificate
+    /// authority without a current revocation list, instead of accepting them with a warning.
+    pub fn set_reject_unknown_revocation_status(&mut self, reject_unknown_revocation_status: bool) {
+        self.reject_unknown_revocation_status = reject_unknown_revocation_status;
+    }
+
     /// Reads a private key from a path on disk.
     pub fn read_pkey(path: &Path) -> Result<PrivateKey, String> {
         if let Ok(pkey) = PrivateKey::read_pem_file(path) {
@@ -164,6 +206,20 @@ impl CertificateStore {
         })
     }
 
+    /// Reads the store's own certificate for the ECC security policies on `curve`.
+    pub fn read_own_ecc_cert(&self, curve: EccCurve) -> Result<X509, String> {
+        let path = self.own_ecc_certificate_path(curve);
+        CertificateStore::read_cert(&path)
+            .map_err(|e| format!("Cannot read cert from path {:?}: {e}", path))
+    }
+
+    /// Read own private key for the ECC security policies on `curve` from file.
+    pub fn read_own_ecc_pkey(&self, curve: EccCurve) -> Result<PrivateKey, String> {
+        let path = self.own_ecc_private_key_path(curve);
+        CertificateStore::read_pkey(&path)
+            .map_err(|e| format!("Cannot read pkey from path {:?}: {e}", path))
+    }
+
     /// Create a certificate and key pair to the specified locations
     pub fn create_certificate_and_key(
         args: &X509Data,
@@ -177,13 +233,7 @@ impl CertificateStore {
         let _ = CertificateStore::store_cert(&cert, cert_path, overwrite)?;
 
         // Write the private key
-        use rsa::pkcs8;
-        use x509_cert::der::pem::PemLabel;
-        let doc = pkey.to_der().unwrap();
-        let pem = doc
-            .to_pem(rsa::pkcs8::PrivateKeyInfo::PEM_LABEL, pkcs8::LineEnding::CR)
-            .unwrap();
-        let _ = CertificateStore::write_to_file(pem.as_bytes(), pkey_path, overwrite)?;
+        let _ = CertificateStore::store_pkey(&pkey, pkey_path, overwrite)?;
         Ok((cert, pkey))
     }
 
@@ -203,6 +253,23 @@ impl CertificateStore {
         )
     }
 
+    /// Create an Application Instance Certificate with a key on the given elliptic curve, for use
+    /// with the ECC security policies, and write it to disk next to the RSA certificate,
+    /// see [`CertificateStore::own_ecc_certificate_path`].
+    pub fn create_and_store_ecc_application_instance_cert(
+        &self,
+        args: &X509Data,
+        curve: EccCurve,
+        overwrite: bool,
+    ) -> Result<(X509, PrivateKey), String> {
+        let (cert, pkey) = X509::ecc_cert_and_pkey(args, curve)?;
+        let _ =
+            CertificateStore::store_cert(&cert, &self.own_ecc_certificate_path(curve), overwrite)?;
+        let _ =
+            CertificateStore::store_pkey(&pkey, &self.own_ecc_private_key_path(curve), overwrite)?;
+        Ok((cert, pkey))
+    }
+
     /// Validates the cert as trusted and valid. If the cert is unknown, it will be written to
     /// the rejected folder so that the administrator can manually move it to the trusted folder.
     ///
@@ -221,52 +288,17 @@ impl CertificateStore {
         self.validate_application_instance_cert(cert, security_policy, hostname, application_uri)
     }
 
-    /// Ensures that the cert provided is the same as the one specified by a path. This is a
-    /// security check to stop someone from renaming a cert on disk to match another cert and
-    /// somehow bypassing or subverting a check. The disk cert must exactly match the memory cert
-    /// or the test is assumed to fail.
-    fn ensure_cert_and_file_are_the_same(cert: &X509, cert_path: &Path) -> bool {
-        if !cert_path.exists() {
-            trace!("Cannot find cert on disk");
-            false
-        } else {
-            match CertificateStore::read_cert(cert_path) {
-                Ok(file_der) => {
-                    // Compare the buffers
-                    trace!("Comparing cert on disk to memory");
-                    let der;
-                    {
-                        let r = cert.to_der();
-                        match r {
-                            Err(_) => return false,
-                            Ok(val) => der = val,
-                        }
-                    }
-
-                    let target_der;
-                    {
-                        let r = file_der.to_der();
-                        match r {
-                            Err(_) => return false,
-                            Ok(val) => target_der = val,
-                        }
-                    }
-
-                    der == target_der
-                }
-                Err(err) => {
-                    trace!("Cannot read cert from disk {:?} - {}", cert_path, err);
-                    // No cert2 to compare to
-                    false
-                }
-            }
-        }
-    }
-
     /// Validates the certificate according to the strictness set in the CertificateStore itself.
-    /// Validation might include checking the issue time, expiration time, revocation, trust chain
-    /// etc. In the first instance this function will only check if the cert is recognized
-    /// and is already contained in the trusted or rejected folder.
+    ///
+    /// The certificate chain is built from the certificates in the trusted and issuer
+    /// directories, and the certificate is trusted if it, or any certificate authority in its
+    /// chain, is in the trusted directory. Every certificate in the chain is checked against
+    /// the revocation lists of its issuer, found in the trusted and issuer CRL directories.
+    /// If an issuer has no current revocation list, the revocation status is unknown, which is
+    /// only an error if `reject_unknown_revocation_status` is set.
+    /// Depending on configuration, the issue time, expiration time, hostname and application
+    /// URI are checked as well. The key length is checked against `security_policy`, unless
+    /// it is `SecurityPolicy::None`.
     ///
     /// # Errors
     ///
@@ -283,112 +315,312 @@ impl CertificateStore {
         let cert_file_name = CertificateStore::cert_file_name(cert);
         debug!("Validating cert with name on disk {}", cert_file_name);
 
-        // Look for the cert in the rejected folder. If it's rejected there is no purpose going
-        // any further
-        {
-            let mut cert_path = self.rejected_certs_dir();
-            if !cert_path.exists() {
-                error!(
-                    "Path for rejected certificates {} does not exist",
-                    cert_path.display()
+        let trusted_dir = self.trusted_certs_dir();
+        if !trusted_dir.exists() {
+            error!(
+                "Path for trusted certificates {} does not exist",
+                trusted_dir.display()
+            );
+            return Err(StatusCode::BadUnexpectedError);
+        }
+        let trusted_certs = CertificateStore::read_certs_in_dir(&trusted_dir);
+        let issuer_certs = CertificateStore::read_certs_in_dir(&self.issuer_certs_dir());
+
+        // Build the chain from the certificate to a self-signed root
+        let chain = match CertificateStore::build_chain(cert, &trusted_certs, &issuer_certs) {
+            Ok(chain) => chain,
+            Err(e) => {
+                warn!(
+                    "Certificate {} could not be validated: {}, so it will be stored in rejected directory",
+                    cert_file_name, e
                 );
-                return Err(StatusCode::BadUnexpectedError);
+                let _ = self.store_rejected_cert(cert);
+                return Err(e);
             }
-            cert_path.push(&cert_file_name);
-            if cert_path.exists() {
+        };
+
+        // The certificate is trusted if any certificate in its chain is trusted.
+        // Otherwise, look for the cert in the rejected folder. If it's rejected there is
+        // no purpose going any further. A certificate may have been rejected before its
+        // issuer was trusted, so the rejected folder is only consulted here.
+        if !chain.iter().any(|c| trusted_certs.contains(c)) {
+            if self.is_rejected(&cert_file_name)? {
                 warn!(
                     "Certificate {} is untrusted because it resides in the rejected directory",
                     cert_file_name
                 );
                 return Err(StatusCode::BadSecurityChecksFailed);
             }
-        }
-
-        // Check the trusted folder. These checks are more strict to ensure the cert is genuinely
-        // trusted
-        {
-            // Check the trusted folder
-            let mut cert_path = self.trusted_certs_dir();
-            if !cert_path.exists() {
-                error!(
-                    "Path for rejected certificates {} does not exist",
-                    cert_path.display()
+            if self.trust_unknown_certs {
+                // Put the unknown cert into the trusted folder
+                warn!(
+                    "Certificate {} is unknown but policy will store it into the trusted directory",
+                    cert_file_name
                 );
-                return Err(StatusCode::BadUnexpectedError);
-            }
-            cert_path.push(&cert_file_name);
-
-            // Check if cert is in the trusted folder
-            if !cert_path.exists() {
-                // ... trust checks based on ca could be added here to add cert straight to trust folder
-                if self.trust_unknown_certs {
-                    // Put the unknown cert into the trusted folder
-                    warn!("Certificate {} is unknown but policy will store it into the trusted directory", cert_file_name);
-                    let _ = self.store_trusted_cert(cert);
-                // Note that we drop through and still check the cert for validity
-                } else {
-                    warn!("Certificate {} is unknown and untrusted so it will be stored in rejected directory", cert_file_name);
-                    let _ = self.store_rejected_cert(cert);
-                    return Err(StatusCode::BadCertificateUntrusted);
-                }
+                let _ = self.store_trusted_cert(cert);
+            // Note that we drop through and still check the cert for validity
+            } else {
+                warn!("Certificate {} is unknown and untrusted so it will be stored in rejected directory", cert_file_name);
+                let _ = self.store_rejected_cert(cert);
+                return Err(StatusCode::BadCertificateUntrusted);
             }
+        }
 
-            // Read the cert from the trusted folder to make sure it matches the one supplied
-            if !CertificateStore::ensure_cert_and_file_are_the_same(cert, &cert_path) {
-                error!("Certificate in memory does not match the one on disk {} so cert will automatically be treated as untrusted", cert_path.display());
-                return Err(StatusCode::BadUnexpectedError);
+        // Check that the certificate is the right length for the security policy.
+        // There is no requirement when the certificate is not used for OPC UA security,
+        // such as when validating TLS certificates.
+        match cert.key_length() {
+            Err(_) => {
+                error!("Cannot read key length from certificate {}", cert_file_name);
+                return Err(StatusCode::BadSecurityChecksFailed);
             }
-
-            // Check that the certificate is the right length for the security policy
-            match cert.key_length() {
-                Err(_) => {
-                    error!("Cannot read key length from certificate {}", cert_file_name);
+            Ok(key_length) => {
+                if security_policy != SecurityPolicy::None
+                    && !security_policy.is_valid_keylength(key_length)
+                {
+                    warn!(
+                        "Certificate {} has an invalid key length {} for the policy {}",
+                        cert_file_name, key_length, security_policy
+                    );
                     return Err(StatusCode::BadSecurityChecksFailed);
                 }
-                Ok(key_length) => {
-                    if !security_policy.is_valid_keylength(key_length) {
-                        warn!(
-                            "Certificate {} has an invalid key length {} for the policy {}",
-                            cert_file_name, key_length, security_policy
-                        );
-                        return Err(StatusCode::BadSecurityChecksFailed);
-                    }
-                }
             }
-
-            if self.skip_verify_certs {
-                debug!(
-                    "Skipping additional verifications for certificate {}",
-                    cert_file_name
+        }
+        // Curves with the same key length, such as P-256 and Curve25519, must not be mixed up.
+        if security_policy != SecurityPolicy::None {
+            let curve = cert.public_key().ok().and_then(|k| k.ecc_curve());
+            if curve != security_policy.ecc_curve() {
+                warn!(
+                    "Certificate {} has the wrong key type for the policy {}",
+                    cert_file_name, security_policy
                 );
-                return Ok(());
+                return Err(StatusCode::BadSecurityChecksFailed);
             }
+        }
 
-            // Now inspect the cert not before / after values to ensure its validity
-            if self.check_time {
-                use chrono::Utc;
-                let now = Utc::now();
-                cert.is_time_valid(&now)?;
+        // Revocation is part of trust, so this is checked even if other verifications are skipped
+        self.check_revocation(&chain)?;
+
+        if self.skip_verify_certs {
+            debug!(
+                "Skipping additional verifications for certificate {}",
+                cert_file_name
+            );
+            return Ok(());
+        }
+
+        // Now inspect the cert not before / after values to ensure its validity, and that of its issuers
+        if self.check_time {
+            use chrono::Utc;
+            let now = Utc::now();
+            cert.is_time_valid(&now)?;
+            for issuer in chain.iter().skip(1) {
+                issuer
+                    .is_time_valid(&now)
+                    .map_err(|_| StatusCode::BadCertificateIssuerTimeInvalid)?;
             }
+        }
 
-            // Compare the hostname of the cert against the cert supplied
-            if let Some(hostname) = hostname {
-                cert.is_hostname_valid(hostname)?;
+        // Compare the hostname of the cert against the cert supplied
+        if let Some(hostname) = hostname {
+            cert.is_hostname_valid(hostname)?;
+        }
+
+        // Compare the application / product uri to the supplied application description
+        if let Some(application_uri) = application_uri {
+            cert.is_application_uri_valid(application_uri)?;
+        }
+
+        Ok(())
+    }
+
+    /// Check that `cert` chains to a self-signed root, through `issuer_certs` or the trusted
+    /// and issuer certificates in the store. Each of `issuer_certs` must be a certificate
+    /// authority that chains to a root in the same way. Unless time checks are disabled, every
+    /// certificate in the chain must be valid at the current time.
+    ///
+    /// This is used to check a new application instance certificate for this application,
+    /// so the certificate itself does not need to be trusted.
+    ///
+    /// # Errors
+    ///
+    /// `BadCertificateInvalid` if an issuer is not a certificate authority or a signature is
+    /// invalid, `BadCertificateChainIncomplete` if an issuer cannot be found, or the status
+    /// of the failed time check.
+    ///
+    pub fn validate_certificate_chain(
+        &self,
+        cert: &X509,
+        issuer_certs: &[X509],
+    ) -> Result<(), StatusCode> {
+        if let Some(issuer) = issuer_certs.iter().find(|c| !c.is_ca()) {
+            warn!(
+                "Issuer certificate {} is not a certificate authority",
+                issuer.subject_name()
+            );
+            return Err(StatusCode::BadCertificateInvalid);
+        }
+        let trusted_certs = CertificateStore::read_certs_in_dir(&self.trusted_certs_dir());
+        let mut all_issuers = CertificateStore::read_certs_in_dir(&self.issuer_certs_dir());
+        all_issuers.extend(issuer_certs.iter().cloned());
+
+        let now = chrono::Utc::now();
+        for (idx, c) in std::iter::once(cert).chain(issuer_certs).enumerate() {
+            let chain = CertificateStore::build_chain(c, &trusted_certs, &all_issuers)?;
+            if self.check_time {
+                for (chain_idx, c) in chain.iter().enumerate() {
+                    c.is_time_valid(&now).map_err(|e| {
+                        if idx == 0 && chain_idx == 0 {
+                            e
+                        } else {
+                            StatusCode::BadCertificateIssuerTimeInvalid
+                        }
+                    })?;
+                }
             }
+        }
+        Ok(())
+    }
 
-            // Compare the application / product uri to the supplied application description
-            if let Some(application_uri) = application_uri {
-                cert.is_application_uri_valid(application_uri)?;
+    /// Build the certificate chain for `cert`, starting with `cert` itself and ending with
+    /// a self-signed root certificate. Issuers are looked up among the trusted and issuer
+    /// certificates.
+    fn build_chain(
+        cert: &X509,
+        trusted_certs: &[X509],
+        issuer_certs: &[X509],
+    ) -> Result<Vec<X509>, StatusCode> {
+        let mut chain = vec![cert.clone()];
+        loop {
+            let last = chain.last().unwrap();
+            if last.is_self_issued() {
+                if !last.is_issued_by(last) {
+                    warn!(
+                        "Signature of self-signed certificate {} is invalid",
+                        last.subject_name()
+                    );
+                    return Err(StatusCode::BadCertificateInvalid);
+                }
+                return Ok(chain);
             }
+            if chain.len() > MAX_CHAIN_LENGTH {
+                warn!("Certificate chain is longer than {}", MAX_CHAIN_LENGTH);
+                return Err(StatusCode::BadCertificateChainIncomplete);
+            }
+            let Some(issuer) = trusted_certs
+                .iter()
+                .chain(issuer_certs.iter())
+                .find(|c| c.is_ca() && !chain.contains(c) && last.is_issued_by(c))
+            else {
+                warn!(
+                    "Cannot find issuer {} of certificate {}",
+                    last.issuer_name(),
+                    last.subject_name()
+                );
+                return Err(StatusCode::BadCertificateChainIncomplete);
+            };
+            chain.push(issuer.clone());
+        }
+    }
+
+    /// Check each certificate in the chain against the revocation lists of its issuer.
+    ///
+    /// Revocation lists that are not current are ignored, unless time checks are disabled.
+    /// If a certificate authority in the chain has no revocation list, the revocation status
+    /// of the certificate it issued is unknown. This is reported as
+    /// `BadCertificateRevocationUnknown` or `BadCertificateIssuerRevocationUnknown` if
+    /// `reject_unknown_revocation_status` is set, and suppressed with a warning otherwise,
+    /// as Part 4 allows.
+    fn check_revocation(&self, chain: &[X509]) -> Result<(), StatusCode> {
+        let now = chrono::Utc::now();
+        let crls: Vec<_> = CertificateStore::read_crls_in_dir(&self.trusted_crl_dir())
+            .into_iter()
+            .chain(CertificateStore::read_crls_in_dir(&self.issuer_crl_dir()))
+            .filter(|c| {
+                if self.check_time && !c.is_time_valid(&now) {
+                    warn!(
+                        "Revocation list from {} is not valid at the current time, ignoring it",
+                        c.issuer_name()
+                    );
+                    return false;
+                }
+                true
+            })
+            .collect();
 
-            // Other tests that we might do with trust lists
-            // ... issuer
-            // ... trust (self-signed, ca etc.)
-            // ... revocation
+        for (idx, pair) in chain.windows(2).enumerate() {
+            let (cert, issuer) = (&pair[0], &pair[1]);
+            let mut issuer_crls = crls.iter().filter(|c| c.is_issued_by(issuer)).peekable();
+            if issuer_crls.peek().is_none() {
+                let status = if idx == 0 {
+                    StatusCode::BadCertificateRevocationUnknown
+                } else {
+                    StatusCode::BadCertificateIssuerRevocationUnknown
+                };
+                if self.reject_unknown_revocation_status {
+                    warn!(
+                        "No valid revocation list found for certificate authority {}",
+                        issuer.subject_name()
+                    );
+                    return Err(status);
+                }
+                warn!(
+                    "No valid revocation list found for certificate authority {}, ignoring {}",
+                    issuer.subject_name(),
+                    status
+                );
+                continue;
+            }
+            if issuer_crls.any(|c| c.is_revoked(cert)) {
+                warn!("Certificate {} has been revoked", cert.subject_name());
+                return Err(if idx == 0 {
+                    StatusCode::BadCertificateRevoked
+                } else {
+                    StatusCode::BadCertificateIssuerRevoked
+                });
+            }
         }
         Ok(())
     }
 
+    /// Read all certificates in a directory, skipping any files that are not valid certificates.
+    fn read_certs_in_dir(path: &Path) -> Vec<X509> {
+        CertificateStore::files_in_dir(path)
+            .filter(|p| matches!(p.extension(), Some(e) if e == "der" || e == "pem"))
+            .filter_map(|p| match CertificateStore::read_cert(&p) {
+                Ok(cert) => Some(cert),
+                Err(e) => {
+                    warn!("{}", e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Read all certificate revocation lists in a directory, skipping any files that are not
+    /// valid revocation lists.
+    fn read_crls_in_dir(path: &Path) -> Vec<X509Crl> {
+        CertificateStore::files_in_dir(path)
+            .filter(|p| matches!(p.extension(), Some(e) if e == "der" || e == "pem" || e == "crl"))
+            .filter_map(|p| match CertificateStore::read_crl(&p) {
+                Ok(crl) => Some(crl),
+                Err(e) => {
+                    warn!("{}", e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn files_in_dir(path: &Path) -> impl Iterator<Item = PathBuf> {
+        std::fs::read_dir(path)
+            .into_iter()
+            .flatten()
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_file())
+    }
+
     /// Returns a certificate file name from the cert's issuer and thumbprint fields.
     /// File name is either "prefix - \[thumbprint\].der" or "thumbprint.der" depending on
     /// the cert's common name being empty or not
@@ -414,16 +646,47 @@ impl CertificateStore {
     /// A string description of any failure
     ///
     pub fn ensure_pki_path(&self) -> Result<(), String> {
-        let mut path = self.pki_path.clone();
-        let subdirs = [TRUSTED_CERTS_DIR, REJECTED_CERTS_DIR];
+        let subdirs = [
+            TRUSTED_CERTS_DIR,
+            TRUSTED_CRL_DIR,
+            ISSUER_CERTS_DIR,
+            ISSUER_CRL_DIR,
+            REJECTED_CERTS_DIR,
+        ];
         for subdir in &subdirs {
-            path.push(subdir);
-            CertificateStore::ensure_dir(&path)?;
-            path.pop();
+            CertificateStore::ensure_dir(&self.pki_path.join(subdir))?;
         }
+        self.migrate_legacy_trusted_certs();
         Ok(())
     }
 
+    /// Trusted certificates used to be stored directly in the `trusted` directory.
+    /// Move any such certificates into the `trusted/certs` directory.
+    fn migrate_legacy_trusted_certs(&self) {
+        let legacy_dir = self.pki_path.join(LEGACY_TRUSTED_CERTS_DIR);
+        let trusted_dir = self.trusted_certs_dir();
+        for path in CertificateStore::files_in_dir(&legacy_dir)
+            .filter(|p| matches!(p.extension(), Some(e) if e == "der" || e == "pem"))
+        {
+            let Some(file_name) = path.file_name() else {
+                continue;
+            };
+            let target = trusted_dir.join(file_name);
+            info!(
+                "Moving trusted certificate {} to {}",
+                path.display(),
+                target.display()
+            );
+            if let Err(e) = std::fs::rename(&path, &target) {
+                warn!(
+                    "Failed to move trusted certificate {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+    }
+
     /// Ensure the directory exists, creating it if necessary
     ///
     /// # Errors
@@ -457,6 +720,30 @@ impl CertificateStore {
         path
     }
 
+    /// Get path to the application instance certificate used with the ECC security policies
+    /// on `curve`. This is the path of the RSA certificate with the curve name appended to the
+    /// file name, e.g. `own/cert_nistP256.der`.
+    pub fn own_ecc_certificate_path(&self, curve: EccCurve) -> PathBuf {
+        Self::path_for_curve(&self.own_certificate_path(), curve)
+    }
+
+    /// Get path to the private key used with the ECC security policies on `curve`, e.g.
+    /// `private/private_nistP256.pem`.
+    pub fn own_ecc_private_key_path(&self, curve: EccCurve) -> PathBuf {
+        Self::path_for_curve(&self.own_private_key_path(), curve)
+    }
+
+    fn path_for_curve(path: &Path, curve: EccCurve) -> PathBuf {
+        let mut file_name = path.file_stem().unwrap_or_default().to_os_string();
+        file_name.push("_");
+        file_name.push(curve.name());
+        if let Some(extension) = path.extension() {
+            file_name.push(".");
+            file_name.push(extension);
+        }
+        path.with_file_name(file_name)
+    }
+
     /// Get the path to the rejected certs dir
     pub fn rejected_certs_dir(&self) -> PathBuf {
         let mut path = PathBuf::from(&self.pki_path);
@@ -471,6 +758,28 @@ impl CertificateStore {
         path
     }
 
+    /// Get the path to the dir containing revocation lists for trusted certificate authorities
+    pub fn trusted_crl_dir(&self) -> PathBuf {
+        let mut path = PathBuf::from(&self.pki_path);
+        path.push(TRUSTED_CRL_DIR);
+        path
+    }
+
+    /// Get the path to the issuer certs dir. Certificate authorities in this directory
+    /// are used to build certificate chains, but are not trusted themselves.
+    pub fn issuer_certs_dir(&self) -> PathBuf {
+        let mut path = PathBuf::from(&self.pki_path);
+        path.push(ISSUER_CERTS_DIR);
+        path
+    }
+
+    /// Get the path to the dir containing revocation lists for issuer certificate authorities
+    pub fn issuer_crl_dir(&self) -> PathBuf {
+        let mut path = PathBuf::from(&self.pki_path);
+        path.push(ISSUER_CRL_DIR);
+        path
+    }
+
     /// Write a cert to the rejected directory. If the write succeeds, the function
     /// returns a path to the written file.
     ///
@@ -487,6 +796,19 @@ impl CertificateStore {
         Ok(cert_path)
     }
 
+    /// Check whether a certificate with the given file name is in the rejected directory.
+    fn is_rejected(&self, cert_file_name: &str) -> Result<bool, StatusCode> {
+        let cert_path = self.rejected_certs_dir();
+        if !cert_path.exists() {
+            error!(
+                "Path for rejected certificates {} does not exist",
+                cert_path.display()
+            );
+            return Err(StatusCode::BadUnexpectedError);
+        }
+        Ok(cert_path.join(cert_file_name).exists())
+    }
+
     /// Writes a cert to the trusted directory. If the write succeeds, the function
     /// returns a path to the written file.
     ///
@@ -503,6 +825,307 @@ impl CertificateStore {
         Ok(cert_path)
     }
 
+    /// Read all certificates in the rejected directory.
+    pub fn read_rejected_certs(&self) -> Vec<X509> {
+        CertificateStore::read_certs_in_dir(&self.rejected_certs_dir())
+    }
+
+    /// Read the trust list from disk. `masks` is a combination of [`TrustListMasks`] flags
+    /// selecting which of the four lists to include.
+    pub fn read_trust_list(&self, masks: u32) -> TrustListDataType {
+        let certs = |dir: PathBuf, mask: TrustListMasks| {
+            (masks & mask as u32 != 0).then(|| {
+                CertificateStore::read_certs_in_dir(&dir)
+                    .iter()
+                    .map(|c| c.as_byte_string())
+                    .collect()
+            })
+        };
+        let crls = |dir: PathBuf, mask: TrustListMasks| {
+            (masks & mask as u32 != 0).then(|| {
+                CertificateStore::read_crls_in_dir(&dir)
+                    .iter()
+                    .filter_map(|c| c.to_der().ok())
+                    .map(ByteString::from)
+                    .collect()
+            })
+        };
+        TrustListDataType {
+            specified_lists: masks & TrustListMasks::All as u32,
+            trusted_certificates: certs(
+                self.trusted_certs_dir(),
+                TrustListMasks::TrustedCertificates,
+            ),
+            trusted_crls: crls(self.trusted_crl_dir(), TrustListMasks::TrustedCrls),
+            issuer_certificates: certs(self.issuer_certs_dir(), TrustListMasks::IssuerCertificates),
+            issuer_crls: crls(self.issuer_crl_dir(), TrustListMasks::IssuerCrls),
+        }
+    }
+
+    /// Replace the lists given by `trust_list.specified_lists` with the contents of
+    /// `trust_list`. Lists that are not specified are left as they are.
+    ///
+    /// Every entry is parsed before anything is written, so if this fails the store is
+    /// left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// `BadCertificateInvalid` if any of the certificates or revocation lists are invalid,
+    /// `BadInternalError` if the store could not be updated.
+    ///
+    pub fn write_trust_list(&self, trust_list: &TrustListDataType) -> Result<(), StatusCode> {
+        let masks = trust_list.specified_lists;
+        let parse_certs = |list: &Option<Vec<ByteString>>| {
+            list.iter()
+                .flatten()
+                .map(|c| X509::from_byte_string(c).map_err(|_| StatusCode::BadCertificateInvalid))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let parse_crls = |list: &Option<Vec<ByteString>>| {
+            list.iter()
+                .flatten()
+                .map(|c| {
+                    X509Crl::from_der(c.as_ref()).map_err(|_| StatusCode::BadCertificateInvalid)
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let trusted_certs = parse_certs(&trust_list.trusted_certificates)?;
+        let trusted_crls = parse_crls(&trust_list.trusted_crls)?;
+        let issuer_certs = parse_certs(&trust_list.issuer_certificates)?;
+        let issuer_crls = parse_crls(&trust_list.issuer_crls)?;
+
+        let write = || -> Result<(), String> {
+            if masks & TrustListMasks::TrustedCertificates as u32 != 0 {
+                self.replace_certs_in_dir(&self.trusted_certs_dir(), &trusted_certs)?;
+            }
+            if masks & TrustListMasks::TrustedCrls as u32 != 0 {
+                self.replace_crls_in_dir(&self.trusted_crl_dir(), &trusted_crls)?;
+            }
+            if masks & TrustListMasks::IssuerCertificates as u32 != 0 {
+                self.replace_certs_in_dir(&self.issuer_certs_dir(), &issuer_certs)?;
+            }
+            if masks & TrustListMasks::IssuerCrls as u32 != 0 {
+                self.replace_crls_in_dir(&self.issuer_crl_dir(), &issuer_crls)?;
+            }
+            Ok(())
+        };
+        write().map_err(|e| {
+            error!("Failed to update trust list: {e}");
+            StatusCode::BadInternalError
+        })
+    }
+
+    /// Add a certificate to the trusted certificates if `is_trusted` is true,
+    /// or to the issuer certificates otherwise.
+    ///
+    /// # Errors
+    ///
+    /// A string description of any failure
+    ///
+    pub fn add_certificate(&self, cert: &X509, is_trusted: bool) -> Result<PathBuf, String> {
+        let dir = if is_trusted {
+            self.trusted_certs_dir()
+        } else {
+            self.issuer_certs_dir()
+        };
+        let cert_path = dir.join(CertificateStore::cert_file_name(cert));
+        let _ = CertificateStore::store_cert(cert, &cert_path, true)?;
+        Ok(cert_path)
+    }
+
+    /// Remove the certificate with the given hex encoded thumbprint from the trusted
+    /// certificates if `is_trusted` is true, or from the issuer certificates otherwise.
+    /// Any revocation lists issued by the certificate are removed as well.
+    ///
+    /// # Errors
+    ///
+    /// `BadInvalidArgument` if the certificate is not in the list, `BadInternalError` if
+    /// it could not be removed.
+    ///
+    pub fn remove_certificate(&self, thumbprint: &str, is_trusted: bool) -> Result<(), StatusCode> {
+        let (cert_dir, crl_dir) = if is_trusted {
+            (self.trusted_certs_dir(), self.trusted_crl_dir())
+        } else {
+            (self.issuer_certs_dir(), self.issuer_crl_dir())
+        };
+        let mut found = None;
+        for path in CertificateStore::files_in_dir(&cert_dir) {
+            if let Ok(cert) = CertificateStore::read_cert(&path) {
+                if cert
+                    .thumbprint()
+                    .as_hex_string()
+                    .eq_ignore_ascii_case(thumbprint)
+                {
+                    found = Some((path, cert));
+                    break;
+                }
+            }
+        }
+        let Some((path, cert)) = found else {
+            return Err(StatusCode::BadInvalidArgument);
+        };
+        info!("Removing certificate {}", path.display());
+        std::fs::remove_file(&path).map_err(|e| {
+            error!("Failed to remove certificate {}: {e}", path.display());
+            StatusCode::BadInternalError
+        })?;
+        for path in CertificateStore::files_in_dir(&crl_dir) {
+            if matches!(CertificateStore::read_crl(&path), Ok(crl) if crl.is_issued_by(&cert)) {
+                info!("Removing revocation list {}", path.display());
+                if let Err(e) = std::fs::remove_file(&path) {
+                    warn!("Failed to remove revocation list {}: {e}", path.display());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the store's own certificate, and its private key if `pkey` is given.
+    ///
+    /// Both files are written to temporary files first, and only renamed into place once
+    /// everything has been written, so a failure never leaves a new certificate next to
+    /// the old private key.
+    ///
+    /// # Errors
+    ///
+    /// A string description of any failure
+    ///
+    pub fn store_own_cert_and_pkey(
+        &self,
+        cert: &X509,
+        pkey: Option<&PrivateKey>,
+    ) -> Result<(), String> {
+        let cert_path = self.own_certificate_path();
+        let pkey_path = self.own_private_key_path();
+        let der = cert
+            .to_der()
+            .map_err(|_| "Could not encode certificate".to_string())?;
+
+        let pkey_tmp = match pkey {
+            Some(pkey) => {
+                let pem = CertificateStore::pkey_to_pem(pkey)?;
+                Some(CertificateStore::write_temp_file(
+                    pem.as_bytes(),
+                    &pkey_path,
+                )?)
+            }
+            None => None,
+        };
+        let cert_tmp = match CertificateStore::write_temp_file(&der, &cert_path) {
+            Ok(p) => p,
+            Err(e) => {
+                if let Some(pkey_tmp) = pkey_tmp {
+                    let _ = std::fs::remove_file(pkey_tmp);
+                }
+                return Err(e);
+            }
+        };
+
+        info!("Writing X509 cert to {}", cert_path.display());
+        if let Some(pkey_tmp) = pkey_tmp {
+            CertificateStore::rename_file(&pkey_tmp, &pkey_path)?;
+        }
+        CertificateStore::rename_file(&cert_tmp, &cert_path)
+    }
+
+    /// Replace the contents of `dir` with `certs`.
+    fn replace_certs_in_dir(&self, dir: &Path, certs: &[X509]) -> Result<(), String> {
+        let files = certs
+            .iter()
+            .map(|cert| {
+                let der = cert
+                    .to_der()
+                    .map_err(|_| "Could not encode certificate".to_string())?;
+                Ok((CertificateStore::cert_file_name(cert), der))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        CertificateStore::replace_dir(dir, &files)
+    }
+
+    /// Replace the contents of `dir` with `crls`.
+    fn replace_crls_in_dir(&self, dir: &Path, crls: &[X509Crl]) -> Result<(), String> {
+        let files = crls
+            .iter()
+            .map(|crl| {
+                let der = crl
+                    .to_der()
+                    .map_err(|_| "Could not encode revocation list".to_string())?;
+                let thumbprint = crl
+                    .thumbprint()
+                    .map_err(|_| "Could not encode revocation list".to_string())?;
+                Ok((format!("{}.crl", thumbprint.as_hex_string()), der))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        CertificateStore::replace_dir(dir, &files)
+    }
+
+    /// Replace the contents of `dir` with `files`, given as file names and contents.
+    ///
+    /// The files are written to a staging directory next to `dir`, which is then swapped
+    /// in, so `dir` never holds a partially written list.
+    fn replace_dir(dir: &Path, files: &[(String, Vec<u8>)]) -> Result<(), String> {
+        let staging = CertificateStore::sibling_path(dir, "new");
+        let old = CertificateStore::sibling_path(dir, "old");
+        for path in [&staging, &old] {
+            if path.exists() {
+                std::fs::remove_dir_all(path)
+                    .map_err(|e| format!("Could not remove {}: {e}", path.display()))?;
+            }
+        }
+
+        CertificateStore::ensure_dir(&staging)?;
+        for (name, bytes) in files {
+            let _ = CertificateStore::write_to_file(bytes, &staging.join(name), true)?;
+        }
+
+        if dir.exists() {
+            CertificateStore::rename_file(dir, &old)?;
+        }
+        if let Err(e) = CertificateStore::rename_file(&staging, dir) {
+            // Put the old directory back, so the store keeps working.
+            let _ = std::fs::rename(&old, dir);
+            return Err(e);
+        }
+        if old.exists() {
+            if let Err(e) = std::fs::remove_dir_all(&old) {
+                warn!("Could not remove {}: {e}", old.display());
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the path `path` with `suffix` appended to its file name.
+    fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".");
+        name.push(suffix);
+        path.with_file_name(name)
+    }
+
+    /// Encode a private key in pem format
+    fn pkey_to_pem(pkey: &PrivateKey) -> Result<String, String> {
+        use rsa::pkcs8;
+        use x509_cert::der::pem::PemLabel;
+        let doc = pkey
+            .to_der()
+            .map_err(|_| "Could not encode private key".to_string())?;
+        let pem = doc
+            .to_pem(rsa::pkcs8::PrivateKeyInfo::PEM_LABEL, pkcs8::LineEnding::CR)
+            .map_err(|_| "Could not encode private key".to_string())?;
+        Ok(pem.to_string())
+    }
+
+    /// Writes a private key to the specified path in pem format
+    ///
+    /// # Errors
+    ///
+    /// A string description of any failure
+    ///
+    fn store_pkey(pkey: &PrivateKey, path: &Path, overwrite: bool) -> Result<usize, String> {
+        let pem = CertificateStore::pkey_to_pem(pkey)?;
+        CertificateStore::write_to_file(pem.as_bytes(), path, overwrite)
+    }
+
     /// Writes a cert to the specified directory
     ///
     /// # Errors
@@ -552,7 +1175,30 @@ impl CertificateStore {
         }
     }
 
+    /// Reads an X509 certificate revocation list in .der, .crl or .pem format from disk.
+    /// Files with the .crl extension may be either der or pem encoded.
+    ///
+    /// # Errors
+    ///
+    /// A string description of any failure
+    ///
+    pub fn read_crl(path: &Path) -> Result<X509Crl, String> {
+        let data = std::fs::read(path)
+            .map_err(|e| format!("Could not read CRL file {}: {e}", path.display()))?;
+
+        let crl = match path.extension() {
+            Some(v) if v == "der" => X509Crl::from_der(&data),
+            Some(v) if v == "pem" => X509Crl::from_pem(&data),
+            Some(v) if v == "crl" => X509Crl::from_der(&data).or_else(|_| X509Crl::from_pem(&data)),
+            _ => return Err("Only .der, .crl and .pem revocation lists are supported".to_string()),
+        };
+
+        crl.map_err(|_| format!("Could not read CRL from file {}", path.display()))
+    }
+
     /// Writes bytes to file and returns the size written, or an error reason for failure.
+    /// The bytes are written to a temporary file first, which then replaces the file, so
+    /// the file is never left partially written.
     ///
     /// # Errors
     ///
@@ -562,15 +1208,41 @@ impl CertificateStore {
         if !overwrite && file_path.exists() {
             Err(format!("File {} already exists and will not be overwritten. Enable overwrite to disable this safeguard.", file_path.display()))
         } else {
-            if let Some(parent) = file_path.parent() {
-                CertificateStore::ensure_dir(parent)?;
-            }
-            match File::create(file_path) {
-                Ok(mut file) => file
-                    .write(bytes)
-                    .map_err(|_| format!("Could not write bytes to file {}", file_path.display())),
-                Err(_) => Err(format!("Could not create file {}", file_path.display())),
-            }
+            let tmp_path = CertificateStore::write_temp_file(bytes, file_path)?;
+            CertificateStore::rename_file(&tmp_path, file_path)?;
+            Ok(bytes.len())
+        }
+    }
+
+    /// Writes bytes to a temporary file next to `file_path`, and returns its path.
+    /// The file should be renamed to `file_path` once it is needed.
+    fn write_temp_file(bytes: &[u8], file_path: &Path) -> Result<PathBuf, String> {
+        if let Some(parent) = file_path.parent() {
+            CertificateStore::ensure_dir(parent)?;
         }
+        let tmp_path = CertificateStore::sibling_path(file_path, "tmp");
+        let res = File::create(&tmp_path)
+            .map_err(|_| format!("Could not create file {}", tmp_path.display()))
+            .and_then(|mut file| {
+                file.write_all(bytes)
+                    .and_then(|_| file.sync_all())
+                    .map_err(|_| format!("Could not write bytes to file {}", tmp_path.display()))
+            });
+        if let Err(e) = res {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+        Ok(tmp_path)
+    }
+
+    /// Rename `from` to `to`, replacing `to` if it is a file.
+    fn rename_file(from: &Path, to: &Path) -> Result<(), String> {
+        std::fs::rename(from, to).map_err(|e| {
+            format!(
+                "Could not rename {} to {}: {e}",
+                from.display(),
+                to.display()
+            )
+        })
     }
 }
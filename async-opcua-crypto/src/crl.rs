@@ -0,0 +1,138 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2024 Adam Lock
+
+//! Wrapper for X509 certificate revocation lists.
+
+use std::fmt::{self, Debug, Formatter};
+
+use chrono::{DateTime, Utc};
+use x509_cert::{self as x509, crl::CertificateList, time::Time};
+
+use super::{
+    thumbprint::Thumbprint,
+    x509::{X509Error, X509},
+};
+
+#[derive(Clone)]
+/// Wrapper around an X509 certificate revocation list (CRL), as issued by a certificate authority.
+pub struct X509Crl {
+    value: CertificateList,
+}
+
+impl Debug for X509Crl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "[x509 crl]")
+    }
+}
+
+impl X509Crl {
+    /// Load a CRL from a pem file.
+    pub fn from_pem(data: &[u8]) -> Result<Self, X509Error> {
+        use x509::der::{Decode, Reader};
+
+        let mut reader = x509::der::PemReader::new(data)?;
+        if reader.type_label() != "X509 CRL" {
+            return Err(X509Error);
+        }
+        let val = CertificateList::decode(&mut reader)?;
+        let val = reader.finish(val)?;
+        Ok(X509Crl { value: val })
+    }
+
+    /// Load a CRL from a der file.
+    pub fn from_der(data: &[u8]) -> Result<Self, X509Error> {
+        use x509::der::Decode;
+
+        let val = CertificateList::from_der(data)?;
+        Ok(X509Crl { value: val })
+    }
+
+    /// Serialize the CRL to der.
+    pub fn to_der(&self) -> Result<Vec<u8>, X509Error> {
+        use x509::der::Encode;
+
+        Ok(self.value.to_der()?)
+    }
+
+    /// The SHA1 digest of the DER form of the CRL, used to identify it in the certificate store.
+    pub fn thumbprint(&self) -> Result<Thumbprint, X509Error> {
+        use sha1::Digest;
+
+        let der = self.to_der()?;
+        let mut hasher = sha1::Sha1::new();
+        hasher.update(&der);
+        Ok(Thumbprint::new(&hasher.finalize()))
+    }
+
+    /// Produces an issuer name string such as "CN=foo/C=IE"
+    pub fn issuer_name(&self) -> String {
+        self.value
+            .tbs_cert_list
+            .issuer
+            .to_string()
+            .replace(";", "/")
+    }
+
+    /// Whether this CRL was issued by `issuer`. This checks that the issuer name matches
+    /// the subject of `issuer`, and that the signature on the CRL can be verified using the
+    /// public key of `issuer`.
+    pub fn is_issued_by(&self, issuer: &X509) -> bool {
+        use x509::der::Encode;
+
+        if &self.value.tbs_cert_list.issuer != issuer.subject() {
+            return false;
+        }
+        let Ok(tbs) = self.value.tbs_cert_list.to_der() else {
+            return false;
+        };
+        let Some(signature) = self.value.signature.as_bytes() else {
+            return false;
+        };
+        issuer.verify_signed_data(&self.value.signature_algorithm.oid, &tbs, signature)
+    }
+
+    /// Whether the CRL lists `cert` as revoked. This only compares serial numbers,
+    /// so the caller must make sure that the CRL was issued by the issuer of `cert`.
+    pub fn is_revoked(&self, cert: &X509) -> bool {
+        self.value
+            .tbs_cert_list
+            .revoked_certificates
+            .iter()
+            .flatten()
+            .any(|r| &r.serial_number == cert.raw_serial_number())
+    }
+
+    /// The time this CRL was issued.
+    pub fn this_update(&self) -> Option<DateTime<Utc>> {
+        Self::to_chrono(&self.value.tbs_cert_list.this_update)
+    }
+
+    /// The time by which the next CRL will be issued, if set.
+    pub fn next_update(&self) -> Option<DateTime<Utc>> {
+        self.value
+            .tbs_cert_list
+            .next_update
+            .as_ref()
+            .and_then(Self::to_chrono)
+    }
+
+    /// Whether the CRL is current at `now`, that is, it was issued before `now`, and
+    /// its `nextUpdate` time, if set, has not passed.
+    pub fn is_time_valid(&self, now: &DateTime<Utc>) -> bool {
+        let Some(this_update) = self.this_update() else {
+            return false;
+        };
+        if now < &this_update {
+            return false;
+        }
+        match self.value.tbs_cert_list.next_update {
+            Some(_) => self.next_update().is_some_and(|n| now <= &n),
+            None => true,
+        }
+    }
+
+    fn to_chrono(time: &Time) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp_micros(time.to_unix_duration().as_micros() as i64)
+    }
+}
@@ -0,0 +1,228 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2024 Adam Lock
+
+//! Elliptic curve support used by the ECC security policies. This contains the list of
+//! supported curves, and the ephemeral keys exchanged in the OpenSecureChannel nonces.
+
+use std::fmt::{self, Debug, Formatter};
+
+use opcua_types::{status_code::StatusCode, Error};
+
+/// An elliptic curve used by one of the ECC security policies.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EccCurve {
+    /// The NIST P-256 curve, used by `ECC_nistP256`.
+    NistP256,
+    /// The NIST P-384 curve, used by `ECC_nistP384`.
+    NistP384,
+    /// The brainpoolP256r1 curve, used by `ECC_brainpoolP256r1`.
+    /// Requires the `brainpool` feature.
+    #[cfg(feature = "brainpool")]
+    BrainpoolP256r1,
+    /// Curve25519, used by `ECC_curve25519`. Certificates use Ed25519 keys,
+    /// the key exchange uses X25519.
+    Curve25519,
+}
+
+impl EccCurve {
+    /// All supported curves.
+    pub const ALL: &'static [EccCurve] = &[
+        EccCurve::NistP256,
+        EccCurve::NistP384,
+        #[cfg(feature = "brainpool")]
+        EccCurve::BrainpoolP256r1,
+        EccCurve::Curve25519,
+    ];
+
+    /// Short name of the curve, as used in the security policy URIs.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EccCurve::NistP256 => "nistP256",
+            EccCurve::NistP384 => "nistP384",
+            #[cfg(feature = "brainpool")]
+            EccCurve::BrainpoolP256r1 => "brainpoolP256r1",
+            EccCurve::Curve25519 => "curve25519",
+        }
+    }
+
+    /// Size of a private key on this curve in bytes.
+    pub fn key_size(&self) -> usize {
+        match self {
+            EccCurve::NistP256 | EccCurve::Curve25519 => 32,
+            #[cfg(feature = "brainpool")]
+            EccCurve::BrainpoolP256r1 => 32,
+            EccCurve::NistP384 => 48,
+        }
+    }
+
+    /// Length of an encoded ephemeral public key on this curve in bytes. This is the
+    /// length of the nonces exchanged in OpenSecureChannel.
+    ///
+    /// Points on the NIST and Brainpool curves are encoded as the X and Y coordinates
+    /// without the SEC1 prefix byte, Curve25519 points are encoded as defined in RFC 7748.
+    pub fn ephemeral_key_length(&self) -> usize {
+        match self {
+            EccCurve::NistP256 => 64,
+            #[cfg(feature = "brainpool")]
+            EccCurve::BrainpoolP256r1 => 64,
+            EccCurve::NistP384 => 96,
+            EccCurve::Curve25519 => 32,
+        }
+    }
+}
+
+impl fmt::Display for EccCurve {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+enum EphemeralKeyValue {
+    NistP256(p256::ecdh::EphemeralSecret),
+    NistP384(p384::ecdh::EphemeralSecret),
+    #[cfg(feature = "brainpool")]
+    BrainpoolP256r1(bp256::r1::SecretKey),
+    Curve25519(x25519_dalek::StaticSecret),
+}
+
+/// An ephemeral key pair used for the ECDH key exchange when opening a secure channel
+/// with an ECC security policy. The public half of the key is sent in place of the nonce.
+pub struct EphemeralKey {
+    value: EphemeralKeyValue,
+}
+
+impl Debug for EphemeralKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // Never write out the secret
+        write!(f, "[ephemeral key]")
+    }
+}
+
+fn invalid_public_key<T: std::fmt::Display>(e: T) -> Error {
+    Error::new(
+        StatusCode::BadNonceInvalid,
+        format!("Remote ephemeral key is invalid: {e}"),
+    )
+}
+
+impl EphemeralKey {
+    /// Generate a new random ephemeral key on the given curve.
+    pub fn new(curve: EccCurve) -> EphemeralKey {
+        let mut rng = rand::thread_rng();
+        let value = match curve {
+            EccCurve::NistP256 => {
+                EphemeralKeyValue::NistP256(p256::ecdh::EphemeralSecret::random(&mut rng))
+            }
+            EccCurve::NistP384 => {
+                EphemeralKeyValue::NistP384(p384::ecdh::EphemeralSecret::random(&mut rng))
+            }
+            #[cfg(feature = "brainpool")]
+            EccCurve::BrainpoolP256r1 => {
+                // bp256 uses a newer rand_core than the rest of the crate, so let it
+                // draw from the system RNG itself.
+                use bp256::elliptic_curve::Generate;
+                EphemeralKeyValue::BrainpoolP256r1(bp256::r1::SecretKey::generate())
+            }
+            EccCurve::Curve25519 => {
+                EphemeralKeyValue::Curve25519(x25519_dalek::StaticSecret::random_from_rng(rng))
+            }
+        };
+        EphemeralKey { value }
+    }
+
+    /// The curve this key belongs to.
+    pub fn curve(&self) -> EccCurve {
+        match self.value {
+            EphemeralKeyValue::NistP256(_) => EccCurve::NistP256,
+            EphemeralKeyValue::NistP384(_) => EccCurve::NistP384,
+            #[cfg(feature = "brainpool")]
+            EphemeralKeyValue::BrainpoolP256r1(_) => EccCurve::BrainpoolP256r1,
+            EphemeralKeyValue::Curve25519(_) => EccCurve::Curve25519,
+        }
+    }
+
+    /// The encoded public key, which is sent to the other party as the nonce.
+    pub fn public_key_bytes(&self) -> Vec<u8> {
+        use p256::elliptic_curve::sec1::ToEncodedPoint;
+
+        match &self.value {
+            // Skip the 0x04 prefix of the uncompressed SEC1 encoding.
+            EphemeralKeyValue::NistP256(k) => {
+                k.public_key().to_encoded_point(false).as_bytes()[1..].to_vec()
+            }
+            EphemeralKeyValue::NistP384(k) => {
+                k.public_key().to_encoded_point(false).as_bytes()[1..].to_vec()
+            }
+            #[cfg(feature = "brainpool")]
+            EphemeralKeyValue::BrainpoolP256r1(k) => {
+                use bp256::elliptic_curve::sec1::ToSec1Point;
+                k.public_key().to_sec1_point(false).as_bytes()[1..].to_vec()
+            }
+            EphemeralKeyValue::Curve25519(k) => {
+                x25519_dalek::PublicKey::from(k).as_bytes().to_vec()
+            }
+        }
+    }
+
+    /// Compute the shared secret from this key and the public key sent by the other party.
+    pub fn shared_secret(&self, remote_public_key: &[u8]) -> Result<Vec<u8>, Error> {
+        let curve = self.curve();
+        if remote_public_key.len() != curve.ephemeral_key_length() {
+            return Err(Error::new(
+                StatusCode::BadNonceInvalid,
+                format!(
+                    "Remote ephemeral key has length {}, expected {} for {}",
+                    remote_public_key.len(),
+                    curve.ephemeral_key_length(),
+                    curve
+                ),
+            ));
+        }
+
+        match &self.value {
+            EphemeralKeyValue::NistP256(k) => {
+                let remote =
+                    p256::PublicKey::from_sec1_bytes(&sec1_uncompressed(remote_public_key))
+                        .map_err(invalid_public_key)?;
+                Ok(k.diffie_hellman(&remote).raw_secret_bytes().to_vec())
+            }
+            EphemeralKeyValue::NistP384(k) => {
+                let remote =
+                    p384::PublicKey::from_sec1_bytes(&sec1_uncompressed(remote_public_key))
+                        .map_err(invalid_public_key)?;
+                Ok(k.diffie_hellman(&remote).raw_secret_bytes().to_vec())
+            }
+            #[cfg(feature = "brainpool")]
+            EphemeralKeyValue::BrainpoolP256r1(k) => {
+                use bp256::elliptic_curve::point::AffineCoordinates;
+                // bp256 has no ECDH helper, the shared secret is the X coordinate of
+                // the product of our secret scalar and the remote point.
+                let remote =
+                    bp256::elliptic_curve::PublicKey::<bp256::BrainpoolP256r1>::from_sec1_bytes(
+                        &sec1_uncompressed(remote_public_key),
+                    )
+                    .map_err(invalid_public_key)?;
+                let shared = (remote.to_projective() * *k.to_nonzero_scalar()).to_affine();
+                Ok(shared.x().to_vec())
+            }
+            EphemeralKeyValue::Curve25519(k) => {
+                let mut bytes = [0u8; 32];
+                bytes.copy_from_slice(remote_public_key);
+                let shared = k.diffie_hellman(&x25519_dalek::PublicKey::from(bytes));
+                if !shared.was_contributory() {
+                    return Err(invalid_public_key("low order point"));
+                }
+                Ok(shared.as_bytes().to_vec())
+            }
+        }
+    }
+}
+
+fn sec1_uncompressed(coordinates: &[u8]) -> Vec<u8> {
+    let mut point = Vec::with_capacity(coordinates.len() + 1);
+    point.push(0x04);
+    point.extend_from_slice(coordinates);
+    point
+}
@@ -14,7 +14,7 @@ use rsa::pkcs1;
 use rsa::pkcs1v15;
 use rsa::pkcs8;
 use rsa::pss;
-use rsa::signature::{RandomizedSigner, SignatureEncoding, Verifier};
+use rsa::signature::{RandomizedSigner, SignatureEncoding, Signer, Verifier};
 use rsa::{Oaep, Pkcs1v15Encrypt, RsaPrivateKey, RsaPublicKey};
 use sha1;
 use sha2;
@@ -24,6 +24,8 @@ use x509_cert::spki::SubjectPublicKeyInfoOwned;
 
 use opcua_types::{status_code::StatusCode, Error};
 
+use super::ecc::EccCurve;
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 /// RSA padding variant.
 pub enum RsaPadding {
@@ -72,10 +74,44 @@ pub struct PKey<T> {
     pub(crate) value: T,
 }
 
+/// The key material of a private key.
+#[derive(Clone)]
+#[non_exhaustive]
+pub enum PrivateKeyValue {
+    /// An RSA private key.
+    Rsa(RsaPrivateKey),
+    /// An ECC private key on the NIST P-256 curve.
+    NistP256(p256::SecretKey),
+    /// An ECC private key on the NIST P-384 curve.
+    NistP384(p384::SecretKey),
+    /// An ECC private key on the brainpoolP256r1 curve.
+    #[cfg(feature = "brainpool")]
+    BrainpoolP256r1(bp256::r1::SecretKey),
+    /// An Ed25519 private key, used with Curve25519.
+    Curve25519(ed25519_dalek::SigningKey),
+}
+
+/// The key material of a public key.
+#[derive(Clone, PartialEq)]
+#[non_exhaustive]
+pub enum PublicKeyValue {
+    /// An RSA public key.
+    Rsa(RsaPublicKey),
+    /// An ECC public key on the NIST P-256 curve.
+    NistP256(p256::PublicKey),
+    /// An ECC public key on the NIST P-384 curve.
+    NistP384(p384::PublicKey),
+    /// An ECC public key on the brainpoolP256r1 curve.
+    #[cfg(feature = "brainpool")]
+    BrainpoolP256r1(bp256::elliptic_curve::PublicKey<bp256::BrainpoolP256r1>),
+    /// An Ed25519 public key, used with Curve25519.
+    Curve25519(ed25519_dalek::VerifyingKey),
+}
+
 /// A public key
-pub type PublicKey = PKey<RsaPublicKey>;
+pub type PublicKey = PKey<PublicKeyValue>;
 /// A private key
-pub type PrivateKey = PKey<RsaPrivateKey>;
+pub type PrivateKey = PKey<PrivateKeyValue>;
 
 impl<T> Debug for PKey<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -85,6 +121,14 @@ impl<T> Debug for PKey<T> {
     }
 }
 
+fn not_rsa() -> Error {
+    Error::new(StatusCode::BadSecurityChecksFailed, "Key is not an RSA key")
+}
+
+fn not_ecc() -> Error {
+    Error::new(StatusCode::BadSecurityChecksFailed, "Key is not an ECC key")
+}
+
 /// Trait for computing the key size of a private key.
 pub trait KeySize {
     /// Length in bits.
@@ -133,7 +177,10 @@ impl KeySize for PrivateKey {
     /// Length in bits
     fn size(&self) -> usize {
         use rsa::traits::PublicKeyParts;
-        self.value.size()
+        match &self.value {
+            PrivateKeyValue::Rsa(k) => k.size(),
+            _ => self.ecc_curve().map(|c| c.key_size()).unwrap_or_default(),
+        }
     }
 }
 
@@ -143,75 +190,150 @@ impl PrivateKey {
         let mut rng = rand::thread_rng();
 
         let key = RsaPrivateKey::new(&mut rng, bit_length as usize)?;
-        Ok(PKey { value: key })
+        Ok(PKey {
+            value: PrivateKeyValue::Rsa(key),
+        })
     }
 
-    /// Read a private key from the given path.
-    pub fn read_pem_file(path: &std::path::Path) -> Result<PrivateKey, PKeyError> {
-        use pkcs8::DecodePrivateKey;
-        use rsa::pkcs1::DecodeRsaPrivateKey;
+    /// Generate a new ECC private key on the given curve.
+    pub fn new_ecc(curve: EccCurve) -> PrivateKey {
+        let mut rng = rand::thread_rng();
 
-        let r = RsaPrivateKey::read_pkcs8_pem_file(path);
-        match r {
-            Err(_) => {
-                let val = RsaPrivateKey::read_pkcs1_pem_file(path)?;
-                Ok(PKey { value: val })
+        let value = match curve {
+            EccCurve::NistP256 => PrivateKeyValue::NistP256(p256::SecretKey::random(&mut rng)),
+            EccCurve::NistP384 => PrivateKeyValue::NistP384(p384::SecretKey::random(&mut rng)),
+            #[cfg(feature = "brainpool")]
+            EccCurve::BrainpoolP256r1 => {
+                use bp256::elliptic_curve::Generate;
+                PrivateKeyValue::BrainpoolP256r1(bp256::r1::SecretKey::generate())
             }
-            Ok(val) => Ok(PKey { value: val }),
-        }
+            EccCurve::Curve25519 => {
+                PrivateKeyValue::Curve25519(ed25519_dalek::SigningKey::generate(&mut rng))
+            }
+        };
+        PKey { value }
+    }
+
+    /// Read a private key from the given path.
+    pub fn read_pem_file(path: &std::path::Path) -> Result<PrivateKey, PKeyError> {
+        let bytes = std::fs::read(path).map_err(|_| PKeyError)?;
+        Self::from_pem(&bytes)
     }
 
     /// Create a private key from a pem file loaded into a byte array.
+    ///
+    /// RSA keys may be PKCS #8 or PKCS #1 encoded, ECC keys may be PKCS #8 or SEC1 encoded.
     pub fn from_pem(bytes: &[u8]) -> Result<PrivateKey, PKeyError> {
         use pkcs8::DecodePrivateKey;
         use rsa::pkcs1::DecodeRsaPrivateKey;
 
-        let converted = std::str::from_utf8(bytes);
-        match converted {
-            Err(_) => Err(PKeyError),
-            Ok(pem) => {
-                let r = RsaPrivateKey::from_pkcs8_pem(pem);
-                match r {
-                    Err(_) => {
-                        let val = RsaPrivateKey::from_pkcs1_pem(pem)?;
-                        Ok(PKey { value: val })
-                    }
-                    Ok(val) => Ok(PKey { value: val }),
-                }
-            }
-        }
+        let pem = std::str::from_utf8(bytes).map_err(|_| PKeyError)?;
+        let value = if let Ok(k) = RsaPrivateKey::from_pkcs8_pem(pem) {
+            PrivateKeyValue::Rsa(k)
+        } else if let Ok(k) = RsaPrivateKey::from_pkcs1_pem(pem) {
+            PrivateKeyValue::Rsa(k)
+        } else if let Ok(k) = p256::SecretKey::from_pkcs8_pem(pem) {
+            PrivateKeyValue::NistP256(k)
+        } else if let Ok(k) = p384::SecretKey::from_pkcs8_pem(pem) {
+            PrivateKeyValue::NistP384(k)
+        } else if let Ok(k) = ed25519_dalek::SigningKey::from_pkcs8_pem(pem) {
+            PrivateKeyValue::Curve25519(k)
+        } else if let Ok(k) = p256::SecretKey::from_sec1_pem(pem) {
+            PrivateKeyValue::NistP256(k)
+        } else if let Some(value) = Self::brainpool_from_pem(pem) {
+            value
+        } else {
+            PrivateKeyValue::NistP384(p384::SecretKey::from_sec1_pem(pem).map_err(|_| PKeyError)?)
+        };
+        Ok(PKey { value })
+    }
+
+    /// Read a brainpoolP256r1 key, PKCS #8 or SEC1 encoded.
+    #[cfg(feature = "brainpool")]
+    fn brainpool_from_pem(pem: &str) -> Option<PrivateKeyValue> {
+        use bp256::pkcs8::DecodePrivateKey;
+
+        bp256::r1::SecretKey::from_pkcs8_pem(pem)
+            .ok()
+            .or_else(|| bp256::r1::SecretKey::from_sec1_pem(pem).ok())
+            .map(PrivateKeyValue::BrainpoolP256r1)
+    }
+
+    #[cfg(not(feature = "brainpool"))]
+    fn brainpool_from_pem(_pem: &str) -> Option<PrivateKeyValue> {
+        None
     }
 
     /// Serialize the private key to a der file.
     pub fn to_der(&self) -> pkcs8::Result<pkcs8::SecretDocument> {
         use pkcs8::EncodePrivateKey;
 
-        self.value.to_pkcs8_der()
+        match &self.value {
+            PrivateKeyValue::Rsa(k) => k.to_pkcs8_der(),
+            PrivateKeyValue::NistP256(k) => k.to_pkcs8_der(),
+            PrivateKeyValue::NistP384(k) => k.to_pkcs8_der(),
+            #[cfg(feature = "brainpool")]
+            PrivateKeyValue::BrainpoolP256r1(k) => {
+                // bp256 is built on newer pkcs8 and spki releases than the other key types,
+                // so its keys are converted through their DER encoding.
+                let der = bp256::pkcs8::EncodePrivateKey::to_pkcs8_der(k)
+                    .map_err(|_| pkcs8::Error::KeyMalformed)?;
+                Ok(pkcs8::SecretDocument::try_from(der.as_bytes())?)
+            }
+            PrivateKeyValue::Curve25519(k) => k.to_pkcs8_der(),
+        }
     }
 
     /// Get the public key info for this private key.
     pub fn public_key_to_info(&self) -> x509_cert::spki::Result<SubjectPublicKeyInfoOwned> {
-        use rsa::pkcs8::EncodePublicKey;
-        SubjectPublicKeyInfoOwned::try_from(
-            self.value
-                .to_public_key()
-                .to_public_key_der()
-                .unwrap()
-                .as_bytes(),
-        )
+        SubjectPublicKeyInfoOwned::try_from(self.to_public_key().to_der()?.as_bytes())
     }
 
     /// Create a public key based on this private key.
     pub fn to_public_key(&self) -> PublicKey {
-        PublicKey {
-            value: self.value.to_public_key(),
+        let value = match &self.value {
+            PrivateKeyValue::Rsa(k) => PublicKeyValue::Rsa(k.to_public_key()),
+            PrivateKeyValue::NistP256(k) => PublicKeyValue::NistP256(k.public_key()),
+            PrivateKeyValue::NistP384(k) => PublicKeyValue::NistP384(k.public_key()),
+            #[cfg(feature = "brainpool")]
+            PrivateKeyValue::BrainpoolP256r1(k) => PublicKeyValue::BrainpoolP256r1(k.public_key()),
+            PrivateKeyValue::Curve25519(k) => PublicKeyValue::Curve25519(k.verifying_key()),
+        };
+        PublicKey { value }
+    }
+
+    /// The curve of this key, or `None` if this is an RSA key.
+    pub fn ecc_curve(&self) -> Option<EccCurve> {
+        match &self.value {
+            PrivateKeyValue::Rsa(_) => None,
+            PrivateKeyValue::NistP256(_) => Some(EccCurve::NistP256),
+            PrivateKeyValue::NistP384(_) => Some(EccCurve::NistP384),
+            #[cfg(feature = "brainpool")]
+            PrivateKeyValue::BrainpoolP256r1(_) => Some(EccCurve::BrainpoolP256r1),
+            PrivateKeyValue::Curve25519(_) => Some(EccCurve::Curve25519),
+        }
+    }
+
+    /// Size of a signature created with this key in bytes.
+    pub fn signature_size(&self) -> usize {
+        match self.ecc_curve() {
+            // ECC signatures are the concatenated r and s values.
+            Some(curve) => curve.key_size() * 2,
+            None => self.size(),
+        }
+    }
+
+    fn rsa(&self) -> Result<&RsaPrivateKey, Error> {
+        match &self.value {
+            PrivateKeyValue::Rsa(k) => Ok(k),
+            _ => Err(not_rsa()),
         }
     }
 
     /// Signs the data using RSA-SHA1
     pub fn sign_sha1(&self, data: &[u8], signature: &mut [u8]) -> Result<usize, Error> {
         let mut rng = rand::thread_rng();
-        let signing_key = pkcs1v15::SigningKey::<sha1::Sha1>::new(self.value.clone());
+        let signing_key = pkcs1v15::SigningKey::<sha1::Sha1>::new(self.rsa()?.clone());
         match signing_key.try_sign_with_rng(&mut rng, data) {
             Err(e) => Err(Error::new(StatusCode::BadUnexpectedError, e)),
             Ok(signed) => {
@@ -225,7 +347,7 @@ impl PrivateKey {
     /// Signs the data using RSA-SHA256
     pub fn sign_sha256(&self, data: &[u8], signature: &mut [u8]) -> Result<usize, Error> {
         let mut rng = rand::thread_rng();
-        let signing_key = pkcs1v15::SigningKey::<sha2::Sha256>::new(self.value.clone());
+        let signing_key = pkcs1v15::SigningKey::<sha2::Sha256>::new(self.rsa()?.clone());
         match signing_key.try_sign_with_rng(&mut rng, data) {
             Err(e) => Err(Error::new(StatusCode::BadUnexpectedError, e)),
             Ok(signed) => {
@@ -239,7 +361,7 @@ impl PrivateKey {
     /// Signs the data using RSA-SHA256-PSS
     pub fn sign_sha256_pss(&self, data: &[u8], signature: &mut [u8]) -> Result<usize, Error> {
         let mut rng = rand::thread_rng();
-        let signing_key = pss::BlindedSigningKey::<sha2::Sha256>::new(self.value.clone());
+        let signing_key = pss::BlindedSigningKey::<sha2::Sha256>::new(self.rsa()?.clone());
         match signing_key.try_sign_with_rng(&mut rng, data) {
             Err(e) => Err(Error::new(StatusCode::BadUnexpectedError, e)),
             Ok(signed) => {
@@ -250,18 +372,55 @@ impl PrivateKey {
         }
     }
 
-    fn pkcs1_decrypt(&self, src: &[u8]) -> rsa::errors::Result<Vec<u8>> {
-        self.value.decrypt(Pkcs1v15Encrypt, src)
+    /// Signs the data using the signature algorithm of the key's curve, that is ECDSA-SHA256
+    /// for NIST P-256 and brainpoolP256r1, ECDSA-SHA384 for NIST P-384 and Ed25519 for
+    /// Curve25519.
+    pub fn sign_ecc(&self, data: &[u8], signature: &mut [u8]) -> Result<usize, Error> {
+        let val = match &self.value {
+            PrivateKeyValue::NistP256(k) => {
+                let signed: p256::ecdsa::Signature = p256::ecdsa::SigningKey::from(k).sign(data);
+                signed.to_vec()
+            }
+            PrivateKeyValue::NistP384(k) => {
+                let signed: p384::ecdsa::Signature = p384::ecdsa::SigningKey::from(k).sign(data);
+                signed.to_vec()
+            }
+            #[cfg(feature = "brainpool")]
+            PrivateKeyValue::BrainpoolP256r1(k) => {
+                use bp256_ecdsa::signature::Signer;
+                let signed: bp256::r1::ecdsa::Signature =
+                    bp256_ecdsa::SigningKey::from(k).sign(data);
+                signed.to_vec()
+            }
+            PrivateKeyValue::Curve25519(k) => k.sign(data).to_vec(),
+            PrivateKeyValue::Rsa(_) => return Err(not_ecc()),
+        };
+        if signature.len() < val.len() {
+            return Err(Error::new(
+                StatusCode::BadInternalError,
+                format!(
+                    "Signature buffer of {} bytes is too small for {} byte signature",
+                    signature.len(),
+                    val.len()
+                ),
+            ));
+        }
+        signature[..val.len()].copy_from_slice(&val);
+        Ok(val.len())
+    }
+
+    fn pkcs1_decrypt(key: &RsaPrivateKey, src: &[u8]) -> rsa::errors::Result<Vec<u8>> {
+        key.decrypt(Pkcs1v15Encrypt, src)
     }
 
-    fn oaepsha1_decrypt(&self, src: &[u8]) -> rsa::errors::Result<Vec<u8>> {
+    fn oaepsha1_decrypt(key: &RsaPrivateKey, src: &[u8]) -> rsa::errors::Result<Vec<u8>> {
         let padding = Oaep::new::<sha1::Sha1>();
-        self.value.decrypt(padding, src)
+        key.decrypt(padding, src)
     }
 
-    fn oaepsha2_decrypt(&self, src: &[u8]) -> rsa::errors::Result<Vec<u8>> {
+    fn oaepsha2_decrypt(key: &RsaPrivateKey, src: &[u8]) -> rsa::errors::Result<Vec<u8>> {
         let padding = Oaep::new::<sha2::Sha256>();
-        self.value.decrypt(padding, src)
+        key.decrypt(padding, src)
     }
 
     /// Decrypts data in src to dst using the specified padding and returning the size of the decrypted
@@ -272,6 +431,7 @@ impl PrivateKey {
         dst: &mut [u8],
         padding: RsaPadding,
     ) -> Result<usize, PKeyError> {
+        let key = self.rsa().map_err(|_| PKeyError)?;
         let cipher_text_block_size = self.cipher_text_block_size();
 
         // Decrypt the data
@@ -288,9 +448,9 @@ impl PrivateKey {
                 let dst = &mut dst[dst_idx..(dst_idx + cipher_text_block_size)];
 
                 let decrypted = match padding {
-                    RsaPadding::OaepSha256 => self.oaepsha2_decrypt(src)?,
-                    RsaPadding::Pkcs1 => self.pkcs1_decrypt(src)?,
-                    RsaPadding::OaepSha1 => self.oaepsha1_decrypt(src)?,
+                    RsaPadding::OaepSha256 => Self::oaepsha2_decrypt(key, src)?,
+                    RsaPadding::Pkcs1 => Self::pkcs1_decrypt(key, src)?,
+                    RsaPadding::OaepSha1 => Self::oaepsha1_decrypt(key, src)?,
                 };
 
                 let size = decrypted.len();
@@ -311,14 +471,62 @@ impl KeySize for PublicKey {
     /// Length in bits
     fn size(&self) -> usize {
         use rsa::traits::PublicKeyParts;
-        self.value.size()
+        match &self.value {
+            PublicKeyValue::Rsa(k) => k.size(),
+            _ => self.ecc_curve().map(|c| c.key_size()).unwrap_or_default(),
+        }
     }
 }
 
 impl PublicKey {
+    /// Serialize the public key to a DER encoded SubjectPublicKeyInfo.
+    pub fn to_der(&self) -> x509_cert::spki::Result<x509_cert::der::Document> {
+        use pkcs8::EncodePublicKey;
+
+        match &self.value {
+            PublicKeyValue::Rsa(k) => k.to_public_key_der(),
+            PublicKeyValue::NistP256(k) => k.to_public_key_der(),
+            PublicKeyValue::NistP384(k) => k.to_public_key_der(),
+            #[cfg(feature = "brainpool")]
+            PublicKeyValue::BrainpoolP256r1(k) => {
+                let der = bp256::pkcs8::EncodePublicKey::to_public_key_der(k)
+                    .map_err(|_| x509_cert::spki::Error::KeyMalformed)?;
+                Ok(x509_cert::der::Document::try_from(der.as_bytes())?)
+            }
+            PublicKeyValue::Curve25519(k) => k.to_public_key_der(),
+        }
+    }
+
+    /// The curve of this key, or `None` if this is an RSA key.
+    pub fn ecc_curve(&self) -> Option<EccCurve> {
+        match &self.value {
+            PublicKeyValue::Rsa(_) => None,
+            PublicKeyValue::NistP256(_) => Some(EccCurve::NistP256),
+            PublicKeyValue::NistP384(_) => Some(EccCurve::NistP384),
+            #[cfg(feature = "brainpool")]
+            PublicKeyValue::BrainpoolP256r1(_) => Some(EccCurve::BrainpoolP256r1),
+            PublicKeyValue::Curve25519(_) => Some(EccCurve::Curve25519),
+        }
+    }
+
+    /// Size of a signature verified with this key in bytes.
+    pub fn signature_size(&self) -> usize {
+        match self.ecc_curve() {
+            Some(curve) => curve.key_size() * 2,
+            None => self.size(),
+        }
+    }
+
+    fn rsa(&self) -> Result<&RsaPublicKey, Error> {
+        match &self.value {
+            PublicKeyValue::Rsa(k) => Ok(k),
+            _ => Err(not_rsa()),
+        }
+    }
+
     /// Verifies the data using RSA-SHA1
     pub fn verify_sha1(&self, data: &[u8], signature: &[u8]) -> Result<bool, Error> {
-        let verifying_key = pkcs1v15::VerifyingKey::<sha1::Sha1>::new(self.value.clone());
+        let verifying_key = pkcs1v15::VerifyingKey::<sha1::Sha1>::new(self.rsa()?.clone());
         let r = pkcs1v15::Signature::try_from(signature);
         match r {
             Err(e) => Err(Error::new(StatusCode::BadUnexpectedError, e)),
@@ -331,7 +539,7 @@ impl PublicKey {
 
     /// Verifies the data using RSA-SHA256
     pub fn verify_sha256(&self, data: &[u8], signature: &[u8]) -> Result<bool, Error> {
-        let verifying_key = pkcs1v15::VerifyingKey::<sha2::Sha256>::new(self.value.clone());
+        let verifying_key = pkcs1v15::VerifyingKey::<sha2::Sha256>::new(self.rsa()?.clone());
         let r = pkcs1v15::Signature::try_from(signature);
         match r {
             Err(e) => Err(Error::new(StatusCode::BadUnexpectedError, e)),
@@ -344,7 +552,7 @@ impl PublicKey {
 
     /// Verifies the data using RSA-SHA256-PSS
     pub fn verify_sha256_pss(&self, data: &[u8], signature: &[u8]) -> Result<bool, Error> {
-        let verifying_key = pss::VerifyingKey::<sha2::Sha256>::new(self.value.clone());
+        let verifying_key = pss::VerifyingKey::<sha2::Sha256>::new(self.rsa()?.clone());
         let r = pss::Signature::try_from(signature);
         match r {
             Err(e) => Err(Error::new(StatusCode::BadUnexpectedError, e)),
@@ -355,28 +563,55 @@ impl PublicKey {
         }
     }
 
-    fn pkcs1_encrypt(&self, src: &[u8]) -> rsa::errors::Result<Vec<u8>> {
+    /// Verifies the data using the signature algorithm of the key's curve, see
+    /// [`PrivateKey::sign_ecc`].
+    pub fn verify_ecc(&self, data: &[u8], signature: &[u8]) -> Result<bool, Error> {
+        let verified = match &self.value {
+            PublicKeyValue::NistP256(k) => p256::ecdsa::Signature::from_slice(signature)
+                .map(|s| p256::ecdsa::VerifyingKey::from(k).verify(data, &s).is_ok()),
+            PublicKeyValue::NistP384(k) => p384::ecdsa::Signature::from_slice(signature)
+                .map(|s| p384::ecdsa::VerifyingKey::from(k).verify(data, &s).is_ok()),
+            #[cfg(feature = "brainpool")]
+            PublicKeyValue::BrainpoolP256r1(k) => {
+                use bp256_ecdsa::signature::Verifier;
+                bp256::r1::ecdsa::Signature::from_slice(signature)
+                    .map(|s| bp256_ecdsa::VerifyingKey::from(k).verify(data, &s).is_ok())
+                    .map_err(|_| rsa::signature::Error::new())
+            }
+            PublicKeyValue::Curve25519(k) => {
+                ed25519_dalek::Signature::from_slice(signature).map(|s| k.verify(data, &s).is_ok())
+            }
+            PublicKeyValue::Rsa(_) => return Err(not_ecc()),
+        };
+        verified.map_err(|e| Error::new(StatusCode::BadUnexpectedError, e))
+    }
+
+    fn pkcs1_encrypt(key: &RsaPublicKey, src: &[u8]) -> rsa::errors::Result<Vec<u8>> {
         let mut rng = rand::thread_rng();
-        self.value.encrypt(&mut rng, Pkcs1v15Encrypt, src)
+        key.encrypt(&mut rng, Pkcs1v15Encrypt, src)
     }
 
-    fn oaepsha1_encrypt(&self, src: &[u8]) -> rsa::errors::Result<Vec<u8>> {
+    fn oaepsha1_encrypt(key: &RsaPublicKey, src: &[u8]) -> rsa::errors::Result<Vec<u8>> {
         let mut rng = rand::thread_rng();
         let padding = Oaep::new::<sha1::Sha1>();
-        self.value.encrypt(&mut rng, padding, src)
+        key.encrypt(&mut rng, padding, src)
     }
 
-    fn oaepsha2_encrypt(&self, src: &[u8]) -> rsa::errors::Result<Vec<u8>> {
+    fn oaepsha2_encrypt(key: &RsaPublicKey, src: &[u8]) -> rsa::errors::Result<Vec<u8>> {
         let mut rng = rand::thread_rng();
         let padding = Oaep::new::<sha2::Sha256>();
-        self.value.encrypt(&mut rng, padding, src)
+        key.encrypt(&mut rng, padding, src)
     }
 
-    fn encrypt_data_chunk(&self, src: &[u8], padding: RsaPadding) -> Result<Vec<u8>, PKeyError> {
+    fn encrypt_data_chunk(
+        key: &RsaPublicKey,
+        src: &[u8],
+        padding: RsaPadding,
+    ) -> Result<Vec<u8>, PKeyError> {
         let r = match padding {
-            RsaPadding::OaepSha256 => self.oaepsha2_encrypt(src),
-            RsaPadding::Pkcs1 => self.pkcs1_encrypt(src),
-            RsaPadding::OaepSha1 => self.oaepsha1_encrypt(src),
+            RsaPadding::OaepSha256 => Self::oaepsha2_encrypt(key, src),
+            RsaPadding::Pkcs1 => Self::pkcs1_encrypt(key, src),
+            RsaPadding::OaepSha1 => Self::oaepsha1_encrypt(key, src),
         };
 
         match r {
@@ -393,6 +628,7 @@ impl PublicKey {
         dst: &mut [u8],
         padding: RsaPadding,
     ) -> Result<usize, PKeyError> {
+        let key = self.rsa().map_err(|_| PKeyError)?;
         let cipher_text_block_size = self.cipher_text_block_size();
         let plain_text_block_size = self.plain_text_block_size(padding);
 
@@ -415,7 +651,7 @@ impl PublicKey {
             dst_idx += {
                 let src = &src[src_idx..src_end_index];
 
-                let encrypted = self.encrypt_data_chunk(src, padding)?;
+                let encrypted = Self::encrypt_data_chunk(key, src, padding)?;
                 dst[dst_idx..(dst_idx + cipher_text_block_size)].copy_from_slice(&encrypted);
                 encrypted.len()
             };
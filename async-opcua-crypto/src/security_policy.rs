@@ -12,12 +12,17 @@ use tracing::error;
 use opcua_types::{constants, status_code::StatusCode, ByteString, Error};
 
 use super::{
-    aeskey::AesKey,
+    aeskey::{AesKey, POLY1305_TAG_SIZE},
+    ecc::EccCurve,
     hash,
     pkey::{PrivateKey, PublicKey, RsaPadding},
-    random, SHA1_SIZE, SHA256_SIZE,
+    random, SHA1_SIZE, SHA256_SIZE, SHA384_SIZE,
 };
 
+/// Keys for one side of a secure channel: the signing key, the encryption key and
+/// the initialization vector.
+pub type SecureChannelKeys = (Vec<u8>, AesKey, Vec<u8>);
+
 /// Trait for a security policy supported by the library.
 pub trait SecurityPolicyConstants {
     /// The name of the policy.
@@ -171,6 +176,115 @@ impl SecurityPolicyConstants for Basic256 {
     const ASYMMETRIC_KEY_LENGTH: (usize, usize) = (1024, 2048);
 }
 
+/// ECC-nistP256 security policy
+///
+///   AsymmetricSignatureAlgorithm_ECDSA-SHA2-256
+///   CertificateSignatureAlgorithm_ECDSA-SHA2-256
+///   KeyAgreementAlgorithm_ECDH-nistP256
+///   KeyDerivationAlgorithm_HKDF-SHA2-256
+///   SymmetricEncryptionAlgorithm_AES128-CBC
+///   SymmetricSignatureAlgorithm_HMAC-SHA2-256
+///
+/// # Limits
+///
+///   DerivedSignatureKeyLength – 256 bits
+///   AsymmetricKeyLength - 256 bits
+///   SecureChannelNonceLength - 64 bytes
+pub struct EccNistP256;
+impl SecurityPolicyConstants for EccNistP256 {
+    const SECURITY_POLICY: &str = "ECC-nistP256";
+    const SECURITY_POLICY_URI: &str = "http://opcfoundation.org/UA/SecurityPolicy#ECC_nistP256";
+
+    const SYMMETRIC_SIGNATURE_ALGORITHM: &str = crate::algorithms::DSIG_HMAC_SHA256;
+    const ASYMMETRIC_SIGNATURE_ALGORITHM: &str = crate::algorithms::DSIG_ECDSA_SHA256;
+    // ECC policies exchange ephemeral keys instead of encrypting with the certificate key.
+    const ASYMMETRIC_ENCRYPTION_ALGORITHM: &str = "";
+    const DERIVED_SIGNATURE_KEY_LENGTH: usize = 256;
+    const ASYMMETRIC_KEY_LENGTH: (usize, usize) = (256, 256);
+}
+
+/// ECC-brainpoolP256r1 security policy
+///
+///   AsymmetricSignatureAlgorithm_ECDSA-SHA2-256
+///   CertificateSignatureAlgorithm_ECDSA-SHA2-256
+///   KeyAgreementAlgorithm_ECDH-brainpoolP256r1
+///   KeyDerivationAlgorithm_HKDF-SHA2-256
+///   SymmetricEncryptionAlgorithm_AES128-CBC
+///   SymmetricSignatureAlgorithm_HMAC-SHA2-256
+///
+/// # Limits
+///
+///   DerivedSignatureKeyLength – 256 bits
+///   AsymmetricKeyLength - 256 bits
+///   SecureChannelNonceLength - 64 bytes
+pub struct EccBrainpoolP256r1;
+impl SecurityPolicyConstants for EccBrainpoolP256r1 {
+    const SECURITY_POLICY: &str = "ECC-brainpoolP256r1";
+    const SECURITY_POLICY_URI: &str =
+        "http://opcfoundation.org/UA/SecurityPolicy#ECC_brainpoolP256r1";
+
+    const SYMMETRIC_SIGNATURE_ALGORITHM: &str = crate::algorithms::DSIG_HMAC_SHA256;
+    const ASYMMETRIC_SIGNATURE_ALGORITHM: &str = crate::algorithms::DSIG_ECDSA_SHA256;
+    const ASYMMETRIC_ENCRYPTION_ALGORITHM: &str = "";
+    const DERIVED_SIGNATURE_KEY_LENGTH: usize = 256;
+    const ASYMMETRIC_KEY_LENGTH: (usize, usize) = (256, 256);
+}
+
+/// ECC-nistP384 security policy
+///
+///   AsymmetricSignatureAlgorithm_ECDSA-SHA2-384
+///   CertificateSignatureAlgorithm_ECDSA-SHA2-384
+///   KeyAgreementAlgorithm_ECDH-nistP384
+///   KeyDerivationAlgorithm_HKDF-SHA2-384
+///   SymmetricEncryptionAlgorithm_AES256-CBC
+///   SymmetricSignatureAlgorithm_HMAC-SHA2-384
+///
+/// # Limits
+///
+///   DerivedSignatureKeyLength – 384 bits
+///   AsymmetricKeyLength - 384 bits
+///   SecureChannelNonceLength - 96 bytes
+pub struct EccNistP384;
+impl SecurityPolicyConstants for EccNistP384 {
+    const SECURITY_POLICY: &str = "ECC-nistP384";
+    const SECURITY_POLICY_URI: &str = "http://opcfoundation.org/UA/SecurityPolicy#ECC_nistP384";
+
+    const SYMMETRIC_SIGNATURE_ALGORITHM: &str = crate::algorithms::DSIG_HMAC_SHA384;
+    const ASYMMETRIC_SIGNATURE_ALGORITHM: &str = crate::algorithms::DSIG_ECDSA_SHA384;
+    const ASYMMETRIC_ENCRYPTION_ALGORITHM: &str = "";
+    const DERIVED_SIGNATURE_KEY_LENGTH: usize = 384;
+    const ASYMMETRIC_KEY_LENGTH: (usize, usize) = (384, 384);
+}
+
+/// ECC-curve25519 security policy
+///
+///   AsymmetricSignatureAlgorithm_EdDSA-Curve25519
+///   CertificateSignatureAlgorithm_EdDSA-Curve25519
+///   KeyAgreementAlgorithm_ECDH-Curve25519
+///   KeyDerivationAlgorithm_HKDF-SHA2-256
+///   SymmetricEncryptionAlgorithm_ChaCha20Poly1305
+///   SymmetricSignatureAlgorithm_Poly1305
+///
+/// Messages are protected with authenticated encryption, the Poly1305 tag takes the
+/// place of the signature and there is no separate signing key.
+///
+/// # Limits
+///
+///   DerivedSignatureKeyLength – 0 bits
+///   AsymmetricKeyLength - 256 bits
+///   SecureChannelNonceLength - 32 bytes
+pub struct EccCurve25519;
+impl SecurityPolicyConstants for EccCurve25519 {
+    const SECURITY_POLICY: &str = "ECC-curve25519";
+    const SECURITY_POLICY_URI: &str = "http://opcfoundation.org/UA/SecurityPolicy#ECC_curve25519";
+
+    const SYMMETRIC_SIGNATURE_ALGORITHM: &str = crate::algorithms::DSIG_CHACHA20_POLY1305;
+    const ASYMMETRIC_SIGNATURE_ALGORITHM: &str = crate::algorithms::DSIG_EDDSA_ED25519;
+    const ASYMMETRIC_ENCRYPTION_ALGORITHM: &str = "";
+    const DERIVED_SIGNATURE_KEY_LENGTH: usize = 0;
+    const ASYMMETRIC_KEY_LENGTH: (usize, usize) = (256, 256);
+}
+
 /// SecurityPolicy implies what encryption and signing algorithms and their relevant key strengths
 /// are used during an encrypted session.
 #[derive(Debug, Clone, PartialEq, Copy)]
@@ -189,6 +303,14 @@ pub enum SecurityPolicy {
     Basic128Rsa15,
     /// Basic256.
     Basic256,
+    /// ECC with the NIST P-256 curve.
+    EccNistP256,
+    /// ECC with the NIST P-384 curve.
+    EccNistP384,
+    /// ECC with the brainpoolP256r1 curve.
+    EccBrainpoolP256r1,
+    /// ECC with Curve25519.
+    EccCurve25519,
 }
 
 impl fmt::Display for SecurityPolicy {
@@ -218,6 +340,18 @@ impl FromStr for SecurityPolicy {
             Aes256Sha256RsaPss::SECURITY_POLICY | Aes256Sha256RsaPss::SECURITY_POLICY_URI => {
                 SecurityPolicy::Aes256Sha256RsaPss
             }
+            EccNistP256::SECURITY_POLICY | EccNistP256::SECURITY_POLICY_URI => {
+                SecurityPolicy::EccNistP256
+            }
+            EccNistP384::SECURITY_POLICY | EccNistP384::SECURITY_POLICY_URI => {
+                SecurityPolicy::EccNistP384
+            }
+            EccBrainpoolP256r1::SECURITY_POLICY | EccBrainpoolP256r1::SECURITY_POLICY_URI => {
+                SecurityPolicy::EccBrainpoolP256r1
+            }
+            EccCurve25519::SECURITY_POLICY | EccCurve25519::SECURITY_POLICY_URI => {
+                SecurityPolicy::EccCurve25519
+            }
             _ => {
                 error!("Specified security policy \"{}\" is not recognized", s);
                 SecurityPolicy::Unknown
@@ -244,6 +378,10 @@ impl SecurityPolicy {
             SecurityPolicy::Basic256Sha256 => Basic256Sha256::SECURITY_POLICY_URI,
             SecurityPolicy::Aes128Sha256RsaOaep => Aes128Sha256RsaOaep::SECURITY_POLICY_URI,
             SecurityPolicy::Aes256Sha256RsaPss => Aes256Sha256RsaPss::SECURITY_POLICY_URI,
+            SecurityPolicy::EccNistP256 => EccNistP256::SECURITY_POLICY_URI,
+            SecurityPolicy::EccNistP384 => EccNistP384::SECURITY_POLICY_URI,
+            SecurityPolicy::EccBrainpoolP256r1 => EccBrainpoolP256r1::SECURITY_POLICY_URI,
+            SecurityPolicy::EccCurve25519 => EccCurve25519::SECURITY_POLICY_URI,
             _ => {
                 panic!("Shouldn't be turning an unknown policy into a uri");
             }
@@ -251,6 +389,8 @@ impl SecurityPolicy {
     }
 
     /// Returns true if the security policy is supported. It might be recognized but be unsupported by the implementation
+    ///
+    /// `ECC_brainpoolP256r1` is only supported with the `brainpool` feature.
     pub fn is_supported(&self) -> bool {
         matches!(
             self,
@@ -260,7 +400,35 @@ impl SecurityPolicy {
                 | SecurityPolicy::Basic256Sha256
                 | SecurityPolicy::Aes128Sha256RsaOaep
                 | SecurityPolicy::Aes256Sha256RsaPss
-        )
+                | SecurityPolicy::EccNistP256
+                | SecurityPolicy::EccNistP384
+                | SecurityPolicy::EccCurve25519
+        ) || cfg!(feature = "brainpool") && *self == SecurityPolicy::EccBrainpoolP256r1
+    }
+
+    /// Returns true if this is one of the ECC security policies, which use ECC certificates
+    /// and an ephemeral key exchange instead of RSA encryption.
+    pub fn is_ecc(&self) -> bool {
+        self.ecc_curve().is_some()
+    }
+
+    /// The curve used by this security policy, or `None` if this is not a supported ECC policy.
+    pub fn ecc_curve(&self) -> Option<EccCurve> {
+        match self {
+            SecurityPolicy::EccNistP256 => Some(EccCurve::NistP256),
+            SecurityPolicy::EccNistP384 => Some(EccCurve::NistP384),
+            #[cfg(feature = "brainpool")]
+            SecurityPolicy::EccBrainpoolP256r1 => Some(EccCurve::BrainpoolP256r1),
+            SecurityPolicy::EccCurve25519 => Some(EccCurve::Curve25519),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the security policy uses authenticated encryption for symmetric
+    /// messages, in which case the authentication tag replaces the signature and
+    /// messages are not padded.
+    pub fn is_aead(&self) -> bool {
+        matches!(self, SecurityPolicy::EccCurve25519)
     }
 
     /// Returns true if the security policy has been deprecated by the OPC UA specification
@@ -283,6 +451,10 @@ impl SecurityPolicy {
             SecurityPolicy::Basic256Sha256 => Basic256Sha256::SECURITY_POLICY,
             SecurityPolicy::Aes128Sha256RsaOaep => Aes128Sha256RsaOaep::SECURITY_POLICY,
             SecurityPolicy::Aes256Sha256RsaPss => Aes256Sha256RsaPss::SECURITY_POLICY,
+            SecurityPolicy::EccNistP256 => EccNistP256::SECURITY_POLICY,
+            SecurityPolicy::EccNistP384 => EccNistP384::SECURITY_POLICY,
+            SecurityPolicy::EccBrainpoolP256r1 => EccBrainpoolP256r1::SECURITY_POLICY,
+            SecurityPolicy::EccCurve25519 => EccCurve25519::SECURITY_POLICY,
             _ => {
                 panic!("Shouldn't be turning an unknown policy into a string");
             }
@@ -323,6 +495,12 @@ impl SecurityPolicy {
             SecurityPolicy::Aes256Sha256RsaPss => {
                 Aes256Sha256RsaPss::ASYMMETRIC_SIGNATURE_ALGORITHM
             }
+            SecurityPolicy::EccNistP256 => EccNistP256::ASYMMETRIC_SIGNATURE_ALGORITHM,
+            SecurityPolicy::EccNistP384 => EccNistP384::ASYMMETRIC_SIGNATURE_ALGORITHM,
+            SecurityPolicy::EccBrainpoolP256r1 => {
+                EccBrainpoolP256r1::ASYMMETRIC_SIGNATURE_ALGORITHM
+            }
+            SecurityPolicy::EccCurve25519 => EccCurve25519::ASYMMETRIC_SIGNATURE_ALGORITHM,
             _ => {
                 panic!("Invalid policy");
             }
@@ -341,6 +519,10 @@ impl SecurityPolicy {
                 Aes128Sha256RsaOaep::SYMMETRIC_SIGNATURE_ALGORITHM
             }
             SecurityPolicy::Aes256Sha256RsaPss => Aes256Sha256RsaPss::SYMMETRIC_SIGNATURE_ALGORITHM,
+            SecurityPolicy::EccNistP256 => EccNistP256::SYMMETRIC_SIGNATURE_ALGORITHM,
+            SecurityPolicy::EccNistP384 => EccNistP384::SYMMETRIC_SIGNATURE_ALGORITHM,
+            SecurityPolicy::EccBrainpoolP256r1 => EccBrainpoolP256r1::SYMMETRIC_SIGNATURE_ALGORITHM,
+            SecurityPolicy::EccCurve25519 => EccCurve25519::SYMMETRIC_SIGNATURE_ALGORITHM,
             _ => {
                 panic!("Invalid policy");
             }
@@ -356,7 +538,12 @@ impl SecurityPolicy {
             | SecurityPolicy::Basic256
             | SecurityPolicy::Basic256Sha256
             | SecurityPolicy::Aes128Sha256RsaOaep
-            | SecurityPolicy::Aes256Sha256RsaPss => 16,
+            | SecurityPolicy::Aes256Sha256RsaPss
+            | SecurityPolicy::EccNistP256
+            | SecurityPolicy::EccBrainpoolP256r1
+            | SecurityPolicy::EccNistP384 => 16,
+            // ChaCha20 is a stream cipher
+            SecurityPolicy::EccCurve25519 => 1,
             _ => {
                 panic!("Invalid policy");
             }
@@ -372,7 +559,11 @@ impl SecurityPolicy {
             SecurityPolicy::Basic128Rsa15 | SecurityPolicy::Basic256 => SHA1_SIZE,
             SecurityPolicy::Basic256Sha256
             | SecurityPolicy::Aes128Sha256RsaOaep
-            | SecurityPolicy::Aes256Sha256RsaPss => SHA256_SIZE,
+            | SecurityPolicy::Aes256Sha256RsaPss
+            | SecurityPolicy::EccNistP256
+            | SecurityPolicy::EccBrainpoolP256r1 => SHA256_SIZE,
+            SecurityPolicy::EccNistP384 => SHA384_SIZE,
+            SecurityPolicy::EccCurve25519 => POLY1305_TAG_SIZE,
             _ => {
                 panic!("Invalid policy");
             }
@@ -391,6 +582,10 @@ impl SecurityPolicy {
                 Aes128Sha256RsaOaep::DERIVED_SIGNATURE_KEY_LENGTH
             }
             SecurityPolicy::Aes256Sha256RsaPss => Aes256Sha256RsaPss::DERIVED_SIGNATURE_KEY_LENGTH,
+            SecurityPolicy::EccNistP256 => EccNistP256::DERIVED_SIGNATURE_KEY_LENGTH,
+            SecurityPolicy::EccNistP384 => EccNistP384::DERIVED_SIGNATURE_KEY_LENGTH,
+            SecurityPolicy::EccBrainpoolP256r1 => EccBrainpoolP256r1::DERIVED_SIGNATURE_KEY_LENGTH,
+            SecurityPolicy::EccCurve25519 => EccCurve25519::DERIVED_SIGNATURE_KEY_LENGTH,
             _ => {
                 panic!("Invalid policy");
             }
@@ -406,6 +601,10 @@ impl SecurityPolicy {
             SecurityPolicy::Basic256Sha256 => Basic256Sha256::ASYMMETRIC_KEY_LENGTH,
             SecurityPolicy::Aes128Sha256RsaOaep => Aes128Sha256RsaOaep::ASYMMETRIC_KEY_LENGTH,
             SecurityPolicy::Aes256Sha256RsaPss => Aes256Sha256RsaPss::ASYMMETRIC_KEY_LENGTH,
+            SecurityPolicy::EccNistP256 => EccNistP256::ASYMMETRIC_KEY_LENGTH,
+            SecurityPolicy::EccNistP384 => EccNistP384::ASYMMETRIC_KEY_LENGTH,
+            SecurityPolicy::EccBrainpoolP256r1 => EccBrainpoolP256r1::ASYMMETRIC_KEY_LENGTH,
+            SecurityPolicy::EccCurve25519 => EccCurve25519::ASYMMETRIC_KEY_LENGTH,
             _ => {
                 panic!("Invalid policy");
             }
@@ -434,6 +633,14 @@ impl SecurityPolicy {
             | SecurityPolicy::Basic256Sha256
             | SecurityPolicy::Aes128Sha256RsaOaep
             | SecurityPolicy::Aes256Sha256RsaPss => 32,
+            // The nonce of the ECC policies is the ephemeral public key
+            SecurityPolicy::EccNistP256
+            | SecurityPolicy::EccBrainpoolP256r1
+            | SecurityPolicy::EccNistP384
+            | SecurityPolicy::EccCurve25519 => self
+                .ecc_curve()
+                .map(|c| c.ephemeral_key_length())
+                .unwrap_or_default(),
             // The nonce can be used for password or X509 authentication
             // even when the security policy is None.
             // see https://github.com/advisories/GHSA-pq4w-qm9g-qx68
@@ -451,6 +658,10 @@ impl SecurityPolicy {
             Basic256Sha256::SECURITY_POLICY_URI => SecurityPolicy::Basic256Sha256,
             Aes128Sha256RsaOaep::SECURITY_POLICY_URI => SecurityPolicy::Aes128Sha256RsaOaep,
             Aes256Sha256RsaPss::SECURITY_POLICY_URI => SecurityPolicy::Aes256Sha256RsaPss,
+            EccNistP256::SECURITY_POLICY_URI => SecurityPolicy::EccNistP256,
+            EccNistP384::SECURITY_POLICY_URI => SecurityPolicy::EccNistP384,
+            EccBrainpoolP256r1::SECURITY_POLICY_URI => SecurityPolicy::EccBrainpoolP256r1,
+            EccCurve25519::SECURITY_POLICY_URI => SecurityPolicy::EccCurve25519,
             _ => {
                 error!(
                     "Specified security policy uri \"{}\" is not recognized",
@@ -463,8 +674,9 @@ impl SecurityPolicy {
 
     /// Returns whether the security policy uses legacy sequence numbers.
     pub fn legacy_sequence_numbers(&self) -> bool {
-        // All the ones we currently support do...
-        true
+        // The ECC policies were introduced together with the non-legacy sequence numbers
+        // in OPC UA 1.05.
+        !self.is_ecc()
     }
 
     /// Pseudo random function is used as a key derivation algorithm. It creates pseudo random bytes
@@ -485,6 +697,25 @@ impl SecurityPolicy {
         result[offset..(offset + length)].to_vec()
     }
 
+    /// Lengths of the encrypting key and of the initialization vector of the symmetric
+    /// encryption algorithm.
+    fn encrypting_key_and_iv_length(&self) -> (usize, usize) {
+        match self {
+            SecurityPolicy::Basic128Rsa15
+            | SecurityPolicy::Aes128Sha256RsaOaep
+            | SecurityPolicy::EccNistP256
+            | SecurityPolicy::EccBrainpoolP256r1 => (16, 16),
+            SecurityPolicy::Basic256
+            | SecurityPolicy::Basic256Sha256
+            | SecurityPolicy::Aes256Sha256RsaPss
+            | SecurityPolicy::EccNistP384 => (32, 16),
+            SecurityPolicy::EccCurve25519 => (32, 12),
+            _ => {
+                panic!("Invalid policy");
+            }
+        }
+    }
+
     /// Part 6
     /// 6.7.5
     /// Deriving keys Once the SecureChannel is established the Messages are signed and encrypted with
@@ -520,22 +751,10 @@ impl SecurityPolicy {
     /// The Client keys are used to secure Messages sent by the Client. The Server keys
     /// are used to secure Messages sent by the Server.
     ///
-    pub fn make_secure_channel_keys(
-        &self,
-        secret: &[u8],
-        seed: &[u8],
-    ) -> (Vec<u8>, AesKey, Vec<u8>) {
+    pub fn make_secure_channel_keys(&self, secret: &[u8], seed: &[u8]) -> SecureChannelKeys {
         // Work out the length of stuff
         let signing_key_length = self.derived_signature_key_size();
-        let (encrypting_key_length, encrypting_block_size) = match self {
-            SecurityPolicy::Basic128Rsa15 | SecurityPolicy::Aes128Sha256RsaOaep => (16, 16),
-            SecurityPolicy::Basic256
-            | SecurityPolicy::Basic256Sha256
-            | SecurityPolicy::Aes256Sha256RsaPss => (32, 16),
-            _ => {
-                panic!("Invalid policy");
-            }
-        };
+        let (encrypting_key_length, encrypting_block_size) = self.encrypting_key_and_iv_length();
 
         let signing_key = self.prf(secret, seed, signing_key_length, 0);
         let encrypting_key = self.prf(secret, seed, encrypting_key_length, signing_key_length);
@@ -550,6 +769,102 @@ impl SecurityPolicy {
         (signing_key, encrypting_key, iv)
     }
 
+    /// Part 6
+    /// 6.8.1
+    /// Deriving keys for the ECC security policies. Both parties compute a shared secret
+    /// from their own ephemeral key and the ephemeral public key sent as the nonce of the
+    /// other party. The keys are then derived from the shared secret using HKDF, with the
+    /// salt and info made of the total length of the keys, a label and both nonces:
+    ///
+    /// Key | Salt
+    /// Client keys | L \| "opcua-client" \| ClientNonce \| ServerNonce
+    /// Server keys | L \| "opcua-server" \| ServerNonce \| ClientNonce
+    ///
+    /// The output of HKDF is split into the signing key, the encrypting key and the
+    /// initialization vector, in that order.
+    ///
+    /// Returns the client keys followed by the server keys.
+    pub fn make_ecc_secure_channel_keys(
+        &self,
+        shared_secret: &[u8],
+        client_nonce: &[u8],
+        server_nonce: &[u8],
+    ) -> (SecureChannelKeys, SecureChannelKeys) {
+        (
+            self.derive_ecc_keys(shared_secret, b"opcua-client", client_nonce, server_nonce),
+            self.derive_ecc_keys(shared_secret, b"opcua-server", server_nonce, client_nonce),
+        )
+    }
+
+    /// Part 4
+    /// 7.41.2.5
+    /// Deriving keys for an `EccEncryptedSecret`. The sender computes a shared secret from its own
+    /// ephemeral key and the ephemeral key previously issued by the receiver, and the keys are
+    /// derived the same way as the secure channel keys with the salt:
+    ///
+    /// L | "opcua-secret" | SenderPublicKey | ReceiverPublicKey
+    pub fn make_ecc_secret_keys(
+        &self,
+        shared_secret: &[u8],
+        sender_public_key: &[u8],
+        receiver_public_key: &[u8],
+    ) -> SecureChannelKeys {
+        self.derive_ecc_keys(
+            shared_secret,
+            b"opcua-secret",
+            sender_public_key,
+            receiver_public_key,
+        )
+    }
+
+    fn derive_ecc_keys(
+        &self,
+        shared_secret: &[u8],
+        label: &[u8],
+        first_nonce: &[u8],
+        second_nonce: &[u8],
+    ) -> SecureChannelKeys {
+        let signing_key_length = self.derived_signature_key_size();
+        let (encrypting_key_length, iv_length) = self.encrypting_key_and_iv_length();
+        let length = signing_key_length + encrypting_key_length + iv_length;
+
+        let mut salt = Vec::with_capacity(2 + label.len() + first_nonce.len() + second_nonce.len());
+        salt.extend_from_slice(&(length as u16).to_le_bytes());
+        salt.extend_from_slice(label);
+        salt.extend_from_slice(first_nonce);
+        salt.extend_from_slice(second_nonce);
+
+        let keys = match self {
+            SecurityPolicy::EccNistP384 => hash::hkdf_sha384(shared_secret, &salt, &salt, length),
+            SecurityPolicy::EccNistP256
+            | SecurityPolicy::EccBrainpoolP256r1
+            | SecurityPolicy::EccCurve25519 => {
+                hash::hkdf_sha256(shared_secret, &salt, &salt, length)
+            }
+            _ => {
+                panic!("Invalid policy");
+            }
+        };
+        let (signing_key, rest) = keys.split_at(signing_key_length);
+        let (encrypting_key, iv) = rest.split_at(encrypting_key_length);
+        (
+            signing_key.to_vec(),
+            AesKey::new(*self, encrypting_key),
+            iv.to_vec(),
+        )
+    }
+
+    fn check_ecc_curve(&self, curve: Option<EccCurve>) -> Result<(), Error> {
+        if curve == self.ecc_curve() {
+            Ok(())
+        } else {
+            Err(Error::new(
+                StatusCode::BadSecurityChecksFailed,
+                format!("Key type does not match security policy {self}"),
+            ))
+        }
+    }
+
     /// Produce a signature of the data using an asymmetric key. Stores the signature in the supplied
     /// `signature` buffer. Returns the size of the signature within that buffer.
     pub fn asymmetric_sign(
@@ -566,6 +881,13 @@ impl SecurityPolicy {
                 signing_key.sign_sha256(data, signature)?
             }
             SecurityPolicy::Aes256Sha256RsaPss => signing_key.sign_sha256_pss(data, signature)?,
+            SecurityPolicy::EccNistP256
+            | SecurityPolicy::EccBrainpoolP256r1
+            | SecurityPolicy::EccNistP384
+            | SecurityPolicy::EccCurve25519 => {
+                self.check_ecc_curve(signing_key.ecc_curve())?;
+                signing_key.sign_ecc(data, signature)?
+            }
             _ => {
                 panic!("Invalid policy");
             }
@@ -594,6 +916,13 @@ impl SecurityPolicy {
             SecurityPolicy::Aes256Sha256RsaPss => {
                 verification_key.verify_sha256_pss(data, signature)?
             }
+            SecurityPolicy::EccNistP256
+            | SecurityPolicy::EccBrainpoolP256r1
+            | SecurityPolicy::EccNistP384
+            | SecurityPolicy::EccCurve25519 => {
+                self.check_ecc_curve(verification_key.ecc_curve())?;
+                verification_key.verify_ecc(data, signature)?
+            }
             _ => {
                 panic!("Invalid policy");
             }
@@ -604,10 +933,9 @@ impl SecurityPolicy {
             // For debugging / unit testing purposes we might have a their_key to see the source of the error
             #[cfg(debug_assertions)]
             if let Some(their_key) = their_private_key {
-                use crate::pkey::KeySize;
                 use tracing::trace;
                 // Calculate the signature using their key, see what we were expecting versus theirs
-                let mut their_signature = vec![0u8; their_key.size()];
+                let mut their_signature = vec![0u8; their_key.signature_size()];
                 self.asymmetric_sign(&their_key, data, their_signature.as_mut_slice())?;
                 trace!(
                     "Using their_key, signature should be {:?}",
@@ -690,7 +1018,12 @@ impl SecurityPolicy {
             }
             SecurityPolicy::Basic256Sha256
             | SecurityPolicy::Aes128Sha256RsaOaep
-            | SecurityPolicy::Aes256Sha256RsaPss => hash::hmac_sha256(key, data, signature),
+            | SecurityPolicy::Aes256Sha256RsaPss
+            | SecurityPolicy::EccNistP256
+            | SecurityPolicy::EccBrainpoolP256r1 => hash::hmac_sha256(key, data, signature),
+            SecurityPolicy::EccNistP384 => hash::hmac_sha384(key, data, signature),
+            // Messages are authenticated by the AEAD tag, see `AesKey::aead_encrypt`.
+            SecurityPolicy::EccCurve25519 => Err(StatusCode::BadSecurityPolicyRejected),
             _ => {
                 panic!("Unsupported policy")
             }
@@ -711,7 +1044,16 @@ impl SecurityPolicy {
             }
             SecurityPolicy::Basic256Sha256
             | SecurityPolicy::Aes128Sha256RsaOaep
-            | SecurityPolicy::Aes256Sha256RsaPss => hash::verify_hmac_sha256(key, data, signature),
+            | SecurityPolicy::Aes256Sha256RsaPss
+            | SecurityPolicy::EccNistP256
+            | SecurityPolicy::EccBrainpoolP256r1 => hash::verify_hmac_sha256(key, data, signature),
+            SecurityPolicy::EccNistP384 => hash::verify_hmac_sha384(key, data, signature),
+            SecurityPolicy::EccCurve25519 => {
+                return Err(Error::new(
+                    StatusCode::BadSecurityPolicyRejected,
+                    "Messages are authenticated by the AEAD tag for this security policy",
+                ))
+            }
             _ => {
                 panic!("Unsupported policy")
             }
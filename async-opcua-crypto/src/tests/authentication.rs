@@ -3,7 +3,10 @@ use opcua_types::{
 };
 
 use crate::{
-    self as crypto, legacy_decrypt_secret, legacy_encrypt_secret, random, tests::*, SecurityPolicy,
+    self as crypto, decrypt_encrypted_secret, ecc_encrypt_secret, is_encrypted_secret,
+    legacy_decrypt_secret, legacy_encrypt_secret, random, rsa_encrypt_secret,
+    tests::{ecc::make_test_ecc_cert, *},
+    EphemeralKey, SecurityPolicy,
 };
 
 #[test]
@@ -183,3 +186,147 @@ fn user_name_identity_token_encrypted() {
         String::from_utf8(password1.value.unwrap()).unwrap()
     );
 }
+
+#[test]
+fn rsa_encrypted_secret() {
+    let password = "abcdef123456";
+    let nonce = random::byte_string(32);
+    let (cert, pkey) = make_test_cert_2048();
+
+    for policy in [
+        SecurityPolicy::Basic128Rsa15,
+        SecurityPolicy::Basic256,
+        SecurityPolicy::Basic256Sha256,
+        SecurityPolicy::Aes128Sha256RsaOaep,
+        SecurityPolicy::Aes256Sha256RsaPss,
+    ] {
+        let secret =
+            rsa_encrypt_secret(policy, &cert, nonce.as_ref(), password.as_bytes()).unwrap();
+        assert!(is_encrypted_secret(&secret));
+
+        let decrypted =
+            decrypt_encrypted_secret(&secret, policy, nonce.as_ref(), Some(&pkey), None).unwrap();
+        assert_eq!(decrypted.secret.as_ref(), password.as_bytes());
+        assert!(decrypted.signing_certificate.is_none());
+
+        // The nonce must match
+        let other_nonce = random::byte_string(32);
+        assert!(
+            decrypt_encrypted_secret(&secret, policy, other_nonce.as_ref(), Some(&pkey), None)
+                .is_err()
+        );
+
+        // Tampering with the payload must be detected
+        let mut tampered = secret.value.clone().unwrap();
+        let len = tampered.len();
+        tampered[len - policy.symmetric_signature_size() - 1] ^= 0x01;
+        assert!(decrypt_encrypted_secret(
+            &ByteString::from(tampered),
+            policy,
+            nonce.as_ref(),
+            Some(&pkey),
+            None
+        )
+        .is_err());
+    }
+
+    // The security policy must match the one expected by the server
+    let secret = rsa_encrypt_secret(
+        SecurityPolicy::Basic256Sha256,
+        &cert,
+        nonce.as_ref(),
+        password.as_bytes(),
+    )
+    .unwrap();
+    assert!(decrypt_encrypted_secret(
+        &secret,
+        SecurityPolicy::Aes128Sha256RsaOaep,
+        nonce.as_ref(),
+        Some(&pkey),
+        None
+    )
+    .is_err());
+}
+
+#[test]
+fn ecc_encrypted_secret() {
+    let password = "abcdef123456";
+    let nonce = random::byte_string(32);
+
+    for policy in [
+        SecurityPolicy::EccNistP256,
+        SecurityPolicy::EccNistP384,
+        #[cfg(feature = "brainpool")]
+        SecurityPolicy::EccBrainpoolP256r1,
+        SecurityPolicy::EccCurve25519,
+    ] {
+        let curve = policy.ecc_curve().unwrap();
+        let (client_cert, client_pkey) = make_test_ecc_cert(curve);
+        let server_key = EphemeralKey::new(curve);
+
+        let secret = ecc_encrypt_secret(
+            policy,
+            &client_cert,
+            &client_pkey,
+            &server_key.public_key_bytes(),
+            nonce.as_ref(),
+            password.as_bytes(),
+        )
+        .unwrap();
+        assert!(is_encrypted_secret(&secret));
+
+        let decrypted =
+            decrypt_encrypted_secret(&secret, policy, nonce.as_ref(), None, Some(&server_key))
+                .unwrap();
+        assert_eq!(decrypted.secret.as_ref(), password.as_bytes());
+        assert_eq!(
+            decrypted.signing_certificate.unwrap().thumbprint(),
+            client_cert.thumbprint()
+        );
+
+        // Only the ephemeral key the secret was encrypted for can decrypt it
+        let other_key = EphemeralKey::new(curve);
+        assert!(
+            decrypt_encrypted_secret(&secret, policy, nonce.as_ref(), None, Some(&other_key))
+                .is_err()
+        );
+
+        // Tampering with the payload must be detected
+        let mut tampered = secret.value.clone().unwrap();
+        let len = tampered.len();
+        tampered[len - client_pkey.signature_size() - 1] ^= 0x01;
+        assert!(decrypt_encrypted_secret(
+            &ByteString::from(tampered),
+            policy,
+            nonce.as_ref(),
+            None,
+            Some(&server_key)
+        )
+        .is_err());
+    }
+}
+
+#[test]
+fn legacy_secret_is_not_encrypted_secret() {
+    let nonce = random::byte_string(32);
+    let (cert, _) = make_test_cert_2048();
+    let policy = opcua_types::UserTokenPolicy {
+        policy_id: UAString::from("x"),
+        token_type: UserTokenType::UserName,
+        issued_token_type: UAString::null(),
+        issuer_endpoint_url: UAString::null(),
+        security_policy_uri: UAString::null(),
+    };
+    let token = legacy_encrypt_secret(
+        SecurityPolicy::Basic256Sha256,
+        MessageSecurityMode::SignAndEncrypt,
+        &policy,
+        nonce.as_ref(),
+        &Some(cert),
+        b"abcdef123456",
+    )
+    .unwrap();
+    assert!(!is_encrypted_secret(&token.secret));
+    assert!(!is_encrypted_secret(&ByteString::from(b"abcdef123456")));
+    assert!(!is_encrypted_secret(&ByteString::null()));
+}
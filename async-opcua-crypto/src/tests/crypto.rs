@@ -107,6 +107,33 @@ fn create_own_cert_in_pki() {
     drop(tmp_dir)
 }
 
+#[test]
+fn store_own_cert_and_pkey() {
+    let (tmp_dir, cert_store) = make_certificate_store();
+    let (cert, pkey) = make_test_cert_2048();
+    cert_store
+        .store_own_cert_and_pkey(&cert, Some(&pkey))
+        .unwrap();
+    assert_eq!(cert_store.read_own_cert().unwrap(), cert);
+    assert!(cert.is_key_pair(&cert_store.read_own_pkey().unwrap()));
+
+    // Replacing only the certificate keeps the key.
+    let (other, _) = make_test_cert_1024();
+    cert_store.store_own_cert_and_pkey(&other, None).unwrap();
+    assert_eq!(cert_store.read_own_cert().unwrap(), other);
+    assert!(cert.is_key_pair(&cert_store.read_own_pkey().unwrap()));
+
+    // No temporary files are left behind.
+    for path in [
+        cert_store.own_certificate_path(),
+        cert_store.own_private_key_path(),
+    ] {
+        let dir = path.parent().unwrap();
+        assert_eq!(std::fs::read_dir(dir).unwrap().count(), 1);
+    }
+    drop(tmp_dir);
+}
+
 #[test]
 fn create_rejected_cert_in_pki() {
     let (tmp_dir, cert_store) = make_certificate_store();
@@ -0,0 +1,312 @@
+use opcua_types::{ByteString, StatusCode};
+
+use crate::{
+    aeskey::POLY1305_TAG_SIZE,
+    create_ephemeral_key_type,
+    ecc::{EccCurve, EphemeralKey},
+    from_hex, hash,
+    pkey::{PrivateKey, PrivateKeyValue},
+    tests::{make_certificate_store, APPLICATION_HOSTNAME, APPLICATION_URI},
+    verify_ephemeral_key_type,
+    x509::{X509Data, X509},
+    SecurityPolicy,
+};
+
+const ECC_POLICIES: &[SecurityPolicy] = &[
+    SecurityPolicy::EccNistP256,
+    SecurityPolicy::EccNistP384,
+    #[cfg(feature = "brainpool")]
+    SecurityPolicy::EccBrainpoolP256r1,
+    SecurityPolicy::EccCurve25519,
+];
+
+pub(super) fn make_test_ecc_cert(curve: EccCurve) -> (X509, PrivateKey) {
+    let args = X509Data {
+        key_size: 0,
+        common_name: "x".to_string(),
+        organization: "x.org".to_string(),
+        organizational_unit: "x.org ops".to_string(),
+        country: "EN".to_string(),
+        state: "London".to_string(),
+        alt_host_names: vec![
+            APPLICATION_URI.to_string(),
+            APPLICATION_HOSTNAME.to_string(),
+        ]
+        .into(),
+        certificate_duration_days: 60,
+    };
+    X509::ecc_cert_and_pkey(&args, curve).unwrap()
+}
+
+#[test]
+fn create_ecc_cert() {
+    for &policy in ECC_POLICIES {
+        let curve = policy.ecc_curve().unwrap();
+        let (cert, pkey) = make_test_ecc_cert(curve);
+        assert_eq!(pkey.ecc_curve(), Some(curve));
+        assert!(cert.is_key_pair(&pkey));
+        // Self-signed, so the certificate must verify against its own key
+        assert!(cert.is_issued_by(&cert));
+        assert!(cert.is_hostname_valid(APPLICATION_HOSTNAME).is_ok());
+        assert!(cert.is_application_uri_valid(APPLICATION_URI).is_ok());
+
+        // Survives a round trip through DER
+        let cert2 = X509::from_der(&cert.to_der().unwrap()).unwrap();
+        assert_eq!(cert2.public_key().unwrap().ecc_curve(), Some(curve));
+        assert!(policy.is_valid_keylength(cert2.key_length().unwrap()));
+
+        assert!(cert.create_signing_request(&pkey, None).is_ok());
+    }
+}
+
+#[test]
+fn store_and_read_ecc_cert() {
+    let (tmp_dir, cert_store) = make_certificate_store();
+    let args = X509Data::sample_cert();
+    for &curve in EccCurve::ALL {
+        let (cert, _) = cert_store
+            .create_and_store_ecc_application_instance_cert(&args, curve, false)
+            .unwrap();
+        assert!(cert_store
+            .own_ecc_certificate_path(curve)
+            .ends_with(format!("own/cert_{}.der", curve.name())));
+        assert!(cert_store
+            .own_ecc_private_key_path(curve)
+            .ends_with(format!("private/private_{}.pem", curve.name())));
+
+        let cert2 = cert_store.read_own_ecc_cert(curve).unwrap();
+        let pkey2 = cert_store.read_own_ecc_pkey(curve).unwrap();
+        assert_eq!(cert, cert2);
+        assert!(cert2.is_key_pair(&pkey2));
+
+        // Create again with no overwrite
+        assert!(cert_store
+            .create_and_store_ecc_application_instance_cert(&args, curve, false)
+            .is_err());
+    }
+    // The RSA certificate is separate
+    assert!(cert_store.read_own_cert().is_err());
+    drop(tmp_dir);
+}
+
+#[test]
+fn validate_ecc_cert_policy() {
+    let (tmp_dir, mut cert_store) = make_certificate_store();
+    cert_store.set_trust_unknown_certs(true);
+
+    let (cert, _) = make_test_ecc_cert(EccCurve::NistP256);
+    assert!(cert_store
+        .validate_application_instance_cert(&cert, SecurityPolicy::EccNistP256, None, None)
+        .is_ok());
+    // Same key length, but the wrong curve
+    assert_eq!(
+        cert_store.validate_application_instance_cert(
+            &cert,
+            SecurityPolicy::EccCurve25519,
+            None,
+            None
+        ),
+        Err(StatusCode::BadSecurityChecksFailed)
+    );
+    assert_eq!(
+        cert_store.validate_application_instance_cert(
+            &cert,
+            SecurityPolicy::Basic256Sha256,
+            None,
+            None
+        ),
+        Err(StatusCode::BadSecurityChecksFailed)
+    );
+    drop(tmp_dir);
+}
+
+#[test]
+fn sign_verify_ecc() {
+    let msg = b"Mary had a little lamb";
+    let msg2 = b"It's fleece was white as snow";
+    for &policy in ECC_POLICIES {
+        let (cert, pkey) = make_test_ecc_cert(policy.ecc_curve().unwrap());
+        let public_key = cert.public_key().unwrap();
+
+        let mut signature = vec![0u8; pkey.signature_size()];
+        let signed_len = policy.asymmetric_sign(&pkey, msg, &mut signature).unwrap();
+        assert_eq!(signed_len, public_key.signature_size());
+
+        assert!(policy
+            .asymmetric_verify_signature(&public_key, msg, &signature, None)
+            .is_ok());
+        assert!(policy
+            .asymmetric_verify_signature(&public_key, msg2, &signature, None)
+            .is_err());
+        signature[0] = !signature[0];
+        assert!(policy
+            .asymmetric_verify_signature(&public_key, msg, &signature, None)
+            .is_err());
+    }
+
+    // Keys on a different curve are rejected
+    let (_, pkey) = make_test_ecc_cert(EccCurve::NistP384);
+    let mut signature = vec![0u8; pkey.signature_size()];
+    assert!(SecurityPolicy::EccNistP256
+        .asymmetric_sign(&pkey, msg, &mut signature)
+        .is_err());
+}
+
+#[test]
+fn verify_ecdsa_certificate_signature_digest() {
+    use const_oid::db::rfc5912::{ECDSA_WITH_SHA_256, ECDSA_WITH_SHA_384};
+    use p256::ecdsa::signature::hazmat::PrehashSigner;
+    use sha2::Digest;
+
+    // The digest is given by the signature algorithm, not by the curve of the key.
+    let (cert, pkey) = make_test_ecc_cert(EccCurve::NistP256);
+    let PrivateKeyValue::NistP256(k) = &pkey.value else {
+        panic!("Expected a P-256 key");
+    };
+    let msg = b"Mary had a little lamb";
+    let signature: p256::ecdsa::Signature = p256::ecdsa::SigningKey::from(k)
+        .sign_prehash(&sha2::Sha384::digest(msg))
+        .unwrap();
+    let signature = signature.to_der();
+    assert!(cert.verify_signed_data(&ECDSA_WITH_SHA_384, msg, signature.as_bytes()));
+    assert!(!cert.verify_signed_data(&ECDSA_WITH_SHA_256, msg, signature.as_bytes()));
+}
+
+#[test]
+fn ecdh_key_exchange() {
+    for &policy in ECC_POLICIES {
+        let curve = policy.ecc_curve().unwrap();
+        let client_key = EphemeralKey::new(curve);
+        let server_key = EphemeralKey::new(curve);
+        let client_nonce = client_key.public_key_bytes();
+        let server_nonce = server_key.public_key_bytes();
+        assert_eq!(client_nonce.len(), policy.secure_channel_nonce_length());
+        assert_eq!(server_nonce.len(), policy.secure_channel_nonce_length());
+
+        let client_secret = client_key.shared_secret(&server_nonce).unwrap();
+        let server_secret = server_key.shared_secret(&client_nonce).unwrap();
+        assert_eq!(client_secret, server_secret);
+
+        let (client_keys, server_keys) =
+            policy.make_ecc_secure_channel_keys(&client_secret, &client_nonce, &server_nonce);
+        let (other_client_keys, other_server_keys) =
+            policy.make_ecc_secure_channel_keys(&server_secret, &client_nonce, &server_nonce);
+        assert_eq!(client_keys.0, other_client_keys.0);
+        assert_eq!(client_keys.1.value(), other_client_keys.1.value());
+        assert_eq!(client_keys.2, other_client_keys.2);
+        assert_eq!(server_keys.1.value(), other_server_keys.1.value());
+        assert_ne!(client_keys.1.value(), server_keys.1.value());
+
+        assert_eq!(client_keys.0.len(), policy.derived_signature_key_size());
+        assert_eq!(client_keys.1.value().len(), client_keys.1.key_length());
+        assert_eq!(client_keys.2.len(), client_keys.1.iv_length());
+
+        // A nonce of the wrong length is not a valid key
+        assert_eq!(
+            client_key
+                .shared_secret(&server_nonce[1..])
+                .unwrap_err()
+                .status(),
+            StatusCode::BadNonceInvalid
+        );
+    }
+}
+
+#[test]
+fn sign_verify_ephemeral_key_type() {
+    for &policy in ECC_POLICIES {
+        let curve = policy.ecc_curve().unwrap();
+        let (server_cert, server_pkey) = make_test_ecc_cert(curve);
+        let key = EphemeralKey::new(curve);
+
+        let mut key_type = create_ephemeral_key_type(&key, policy, &server_pkey).unwrap();
+        assert_eq!(key_type.public_key.as_ref(), key.public_key_bytes());
+        verify_ephemeral_key_type(&key_type, policy, &server_cert).unwrap();
+
+        // A key signed by someone else is rejected
+        let (other_cert, _) = make_test_ecc_cert(curve);
+        assert!(verify_ephemeral_key_type(&key_type, policy, &other_cert).is_err());
+
+        // As is a modified key
+        key_type.public_key = ByteString::from(EphemeralKey::new(curve).public_key_bytes());
+        assert!(verify_ephemeral_key_type(&key_type, policy, &server_cert).is_err());
+    }
+}
+
+#[test]
+fn hkdf_sha256() {
+    // RFC 5869, test case 1
+    let ikm = [0x0bu8; 22];
+    let salt = from_hex("000102030405060708090a0b0c");
+    let info = from_hex("f0f1f2f3f4f5f6f7f8f9");
+    let okm = hash::hkdf_sha256(&ikm, &salt, &info, 42);
+    let expected = from_hex(
+        "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865",
+    );
+    assert_eq!(okm, expected);
+}
+
+#[test]
+fn sign_hmac_sha384() {
+    let key = b"key";
+    let data = b"The quick brown fox jumps over the lazy dog";
+    let mut signature = [0u8; crate::SHA384_SIZE];
+    assert!(hash::hmac_sha384(key, data, &mut signature).is_ok());
+    assert!(hash::verify_hmac_sha384(key, data, &signature));
+    assert!(!hash::verify_hmac_sha384(key, &data[1..], &signature));
+}
+
+#[test]
+fn chacha20_poly1305() {
+    let policy = SecurityPolicy::EccCurve25519;
+    let key = EphemeralKey::new(EccCurve::Curve25519);
+    let secret = key.shared_secret(&key.public_key_bytes()).unwrap();
+    let (_, aes_key, iv) = policy.make_ecc_secure_channel_keys(&secret, b"a", b"b").0;
+
+    let plain_text = b"The quick brown fox jumps over the lazy dog".to_vec();
+    let mut buffer = plain_text.clone();
+    let mut tag = [0u8; POLY1305_TAG_SIZE];
+    aes_key
+        .aead_encrypt(&iv, b"header", &mut buffer, &mut tag)
+        .unwrap();
+    assert_ne!(buffer, plain_text);
+
+    let mut decrypted = buffer.clone();
+    aes_key
+        .aead_decrypt(&iv, b"header", &mut decrypted, &tag)
+        .unwrap();
+    assert_eq!(decrypted, plain_text);
+
+    // Tampering with the associated data fails authentication
+    let mut decrypted = buffer.clone();
+    assert!(aes_key
+        .aead_decrypt(&iv, b"Header", &mut decrypted, &tag)
+        .is_err());
+}
+
+#[test]
+fn ecc_policy_properties() {
+    for &policy in ECC_POLICIES {
+        assert!(policy.is_supported());
+        assert!(policy.is_ecc());
+        assert!(!policy.legacy_sequence_numbers());
+        assert!(policy.asymmetric_encryption_padding().is_none());
+        assert_eq!(SecurityPolicy::from_uri(policy.to_uri()), policy);
+    }
+    assert!(SecurityPolicy::EccCurve25519.is_aead());
+    assert!(!SecurityPolicy::EccNistP256.is_aead());
+    assert!(!SecurityPolicy::Basic256Sha256.is_ecc());
+    assert_eq!(
+        SecurityPolicy::from_uri("http://opcfoundation.org/UA/SecurityPolicy#ECC_nistP384"),
+        SecurityPolicy::EccNistP384
+    );
+    assert_eq!(
+        SecurityPolicy::from_uri("http://opcfoundation.org/UA/SecurityPolicy#ECC_brainpoolP256r1"),
+        SecurityPolicy::EccBrainpoolP256r1
+    );
+    // brainpoolP256r1 is recognized, but only supported with the `brainpool` feature.
+    assert_eq!(
+        SecurityPolicy::EccBrainpoolP256r1.is_supported(),
+        cfg!(feature = "brainpool")
+    );
+}
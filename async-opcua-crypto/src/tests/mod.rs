@@ -49,4 +49,6 @@ fn make_test_cert_2048() -> (X509, PrivateKey) {
 
 mod authentication;
 mod crypto;
+mod ecc;
 mod security_policy;
+mod trust_list;
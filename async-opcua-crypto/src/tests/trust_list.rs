@@ -0,0 +1,427 @@
+use std::{
+    path::Path,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+use opcua_types::{StatusCode, TrustListDataType, TrustListMasks};
+use rsa::pkcs1v15::SigningKey;
+use x509_cert::{
+    builder::{Builder, CertificateBuilder, Profile},
+    crl::{CertificateList, RevokedCert, TbsCertList},
+    der::Encode,
+    name::Name,
+    serial_number::SerialNumber,
+    spki::{AlgorithmIdentifierOwned, SignatureBitStringEncoding},
+    time::{Time, Validity},
+    Version,
+};
+
+use crate::{
+    pkey::{PrivateKey, PrivateKeyValue},
+    tests::make_certificate_store,
+    x509::X509,
+    CertificateStore, SecurityPolicy, X509Crl,
+};
+
+fn rsa_key(pkey: &PrivateKey) -> rsa::RsaPrivateKey {
+    match &pkey.value {
+        PrivateKeyValue::Rsa(k) => k.clone(),
+        _ => panic!("Expected an RSA key"),
+    }
+}
+
+/// Create a certificate signed by `issuer`, or a self-signed root CA if `issuer` is `None`.
+fn make_cert(
+    name: &str,
+    serial: u32,
+    profile: impl FnOnce(Name) -> Profile,
+    issuer: Option<(&X509, &PrivateKey)>,
+) -> (X509, PrivateKey) {
+    let pkey = PrivateKey::new(1024).unwrap();
+    let subject = Name::from_str(&format!("CN={name},O=x.org")).unwrap();
+    let issuer_name = issuer
+        .map(|(c, _)| c.subject().clone())
+        .unwrap_or_else(|| subject.clone());
+    let signing_key = SigningKey::<sha2::Sha256>::new(
+        issuer
+            .map(|(_, k)| rsa_key(k))
+            .unwrap_or_else(|| rsa_key(&pkey)),
+    );
+    let builder = CertificateBuilder::new(
+        profile(issuer_name),
+        SerialNumber::from(serial),
+        Validity::from_now(Duration::from_secs(86400)).unwrap(),
+        subject,
+        pkey.public_key_to_info().unwrap(),
+        &signing_key,
+    )
+    .unwrap();
+    let cert = builder.build::<rsa::pkcs1v15::Signature>().unwrap();
+    (X509::from_der(&cert.to_der().unwrap()).unwrap(), pkey)
+}
+
+fn make_ca(name: &str) -> (X509, PrivateKey) {
+    make_cert(name, 1, |_| Profile::Root, None)
+}
+
+fn make_sub_ca(name: &str, serial: u32, issuer: (&X509, &PrivateKey)) -> (X509, PrivateKey) {
+    make_cert(
+        name,
+        serial,
+        |issuer| Profile::SubCA {
+            issuer,
+            path_len_constraint: None,
+        },
+        Some(issuer),
+    )
+}
+
+fn make_leaf(name: &str, serial: u32, issuer: (&X509, &PrivateKey)) -> X509 {
+    make_cert(
+        name,
+        serial,
+        |issuer| Profile::Leaf {
+            issuer,
+            enable_key_agreement: false,
+            enable_key_encipherment: true,
+            include_subject_key_identifier: true,
+        },
+        Some(issuer),
+    )
+    .0
+}
+
+/// Create a revocation list issued by `issuer`, revoking the given certificates.
+fn make_crl(issuer: (&X509, &PrivateKey), revoked: &[&X509]) -> X509Crl {
+    make_crl_at(issuer, revoked, SystemTime::now(), None)
+}
+
+/// Create a revocation list with the given `thisUpdate` and `nextUpdate` times.
+fn make_crl_at(
+    issuer: (&X509, &PrivateKey),
+    revoked: &[&X509],
+    this_update: SystemTime,
+    next_update: Option<SystemTime>,
+) -> X509Crl {
+    use rsa::signature::Signer;
+
+    let now = Time::try_from(this_update).unwrap();
+    let algorithm = AlgorithmIdentifierOwned {
+        oid: const_oid::db::rfc5912::SHA_256_WITH_RSA_ENCRYPTION,
+        parameters: Some(x509_cert::der::asn1::Null.into()),
+    };
+    let tbs = TbsCertList {
+        version: Version::V2,
+        signature: algorithm.clone(),
+        issuer: issuer.0.subject().clone(),
+        this_update: now,
+        next_update: next_update.map(|t| Time::try_from(t).unwrap()),
+        revoked_certificates: Some(
+            revoked
+                .iter()
+                .map(|c| RevokedCert {
+                    serial_number: c.raw_serial_number().clone(),
+                    revocation_date: now,
+                    crl_entry_extensions: None,
+                })
+                .collect(),
+        ),
+        crl_extensions: None,
+    };
+    let signing_key = SigningKey::<sha2::Sha256>::new(rsa_key(issuer.1));
+    let signature: rsa::pkcs1v15::Signature = signing_key.sign(&tbs.to_der().unwrap());
+    let crl = CertificateList {
+        tbs_cert_list: tbs,
+        signature_algorithm: algorithm,
+        signature: signature.to_bitstring().unwrap(),
+    };
+    X509Crl::from_der(&crl.to_der().unwrap()).unwrap()
+}
+
+fn write_cert(dir: &Path, cert: &X509) {
+    std::fs::write(
+        dir.join(CertificateStore::cert_file_name(cert)),
+        cert.to_der().unwrap(),
+    )
+    .unwrap();
+}
+
+fn write_crl(dir: &Path, name: &str, crl: &X509Crl) {
+    std::fs::write(dir.join(format!("{name}.crl")), crl.to_der().unwrap()).unwrap();
+}
+
+fn validate(store: &CertificateStore, cert: &X509) -> Result<(), StatusCode> {
+    store.validate_or_reject_application_instance_cert(
+        cert,
+        SecurityPolicy::Basic128Rsa15,
+        None,
+        None,
+    )
+}
+
+#[test]
+fn trusted_ca() {
+    let (_tmp_dir, mut store) = make_certificate_store();
+    let ca = make_ca("CA");
+    let leaf = make_leaf("Leaf", 2, (&ca.0, &ca.1));
+    assert!(leaf.is_issued_by(&ca.0));
+    assert!(!leaf.is_self_issued());
+    assert!(ca.0.is_ca());
+
+    // Without the CA the chain cannot be built.
+    assert_eq!(
+        validate(&store, &leaf),
+        Err(StatusCode::BadCertificateChainIncomplete)
+    );
+    assert!(store
+        .rejected_certs_dir()
+        .join(CertificateStore::cert_file_name(&leaf))
+        .exists());
+
+    // Trusting the CA means the leaf is trusted, without adding it to the trust list,
+    // even though the leaf was rejected before.
+    // The CA has no revocation list, which is only reported if configured.
+    write_cert(&store.trusted_certs_dir(), &ca.0);
+    assert_eq!(validate(&store, &leaf), Ok(()));
+    store.set_reject_unknown_revocation_status(true);
+    assert_eq!(
+        validate(&store, &leaf),
+        Err(StatusCode::BadCertificateRevocationUnknown)
+    );
+    write_crl(
+        &store.trusted_crl_dir(),
+        "ca",
+        &make_crl((&ca.0, &ca.1), &[]),
+    );
+    assert_eq!(validate(&store, &leaf), Ok(()));
+
+    // A certificate with the same name, but not signed by the CA, is not trusted.
+    let other_ca = make_ca("CA");
+    let fake = make_leaf("Leaf", 3, (&other_ca.0, &other_ca.1));
+    assert_eq!(
+        validate(&store, &fake),
+        Err(StatusCode::BadCertificateChainIncomplete)
+    );
+}
+
+#[test]
+fn untrusted_issuer() {
+    let (_tmp_dir, store) = make_certificate_store();
+    let ca = make_ca("CA");
+    let leaf = make_leaf("Leaf", 2, (&ca.0, &ca.1));
+
+    // The CA can be used to build the chain, but it is not trusted.
+    write_cert(&store.issuer_certs_dir(), &ca.0);
+    write_crl(
+        &store.issuer_crl_dir(),
+        "ca",
+        &make_crl((&ca.0, &ca.1), &[]),
+    );
+    assert_eq!(
+        validate(&store, &leaf),
+        Err(StatusCode::BadCertificateUntrusted)
+    );
+
+    // The leaf was rejected, trusting it explicitly makes it valid.
+    std::fs::remove_file(
+        store
+            .rejected_certs_dir()
+            .join(CertificateStore::cert_file_name(&leaf)),
+    )
+    .unwrap();
+    write_cert(&store.trusted_certs_dir(), &leaf);
+    assert_eq!(validate(&store, &leaf), Ok(()));
+}
+
+#[test]
+fn revoked_certificate() {
+    let (_tmp_dir, store) = make_certificate_store();
+    let ca = make_ca("CA");
+    let leaf = make_leaf("Leaf", 2, (&ca.0, &ca.1));
+    let revoked = make_leaf("Revoked", 3, (&ca.0, &ca.1));
+    write_cert(&store.trusted_certs_dir(), &ca.0);
+
+    let crl = make_crl((&ca.0, &ca.1), &[&revoked]);
+    assert!(crl.is_issued_by(&ca.0));
+    assert!(crl.is_revoked(&revoked));
+    assert!(!crl.is_revoked(&leaf));
+    write_crl(&store.trusted_crl_dir(), "ca", &crl);
+
+    assert_eq!(validate(&store, &leaf), Ok(()));
+    assert_eq!(
+        validate(&store, &revoked),
+        Err(StatusCode::BadCertificateRevoked)
+    );
+
+    // A revocation list not signed by the CA is ignored.
+    let other_ca = make_ca("CA");
+    let fake_crl = make_crl((&other_ca.0, &other_ca.1), &[&leaf]);
+    assert!(!fake_crl.is_issued_by(&ca.0));
+    write_crl(&store.trusted_crl_dir(), "fake", &fake_crl);
+    assert_eq!(validate(&store, &leaf), Ok(()));
+}
+
+#[test]
+fn revoked_issuer() {
+    let (_tmp_dir, mut store) = make_certificate_store();
+    store.set_reject_unknown_revocation_status(true);
+    let root = make_ca("Root");
+    let sub = make_sub_ca("Sub", 2, (&root.0, &root.1));
+    let leaf = make_leaf("Leaf", 3, (&sub.0, &sub.1));
+    write_cert(&store.trusted_certs_dir(), &root.0);
+    write_cert(&store.issuer_certs_dir(), &sub.0);
+    write_crl(
+        &store.issuer_crl_dir(),
+        "sub",
+        &make_crl((&sub.0, &sub.1), &[]),
+    );
+
+    // The root has no revocation list, so the status of the sub CA is unknown.
+    assert_eq!(
+        validate(&store, &leaf),
+        Err(StatusCode::BadCertificateIssuerRevocationUnknown)
+    );
+    write_crl(
+        &store.trusted_crl_dir(),
+        "root",
+        &make_crl((&root.0, &root.1), &[]),
+    );
+    assert_eq!(validate(&store, &leaf), Ok(()));
+
+    write_crl(
+        &store.trusted_crl_dir(),
+        "root",
+        &make_crl((&root.0, &root.1), &[&sub.0]),
+    );
+    assert_eq!(
+        validate(&store, &leaf),
+        Err(StatusCode::BadCertificateIssuerRevoked)
+    );
+}
+
+#[test]
+fn outdated_revocation_list() {
+    let (_tmp_dir, mut store) = make_certificate_store();
+    store.set_reject_unknown_revocation_status(true);
+    let ca = make_ca("CA");
+    let leaf = make_leaf("Leaf", 2, (&ca.0, &ca.1));
+    write_cert(&store.trusted_certs_dir(), &ca.0);
+
+    // A revocation list past its next update time is ignored.
+    let day = Duration::from_secs(86400);
+    let expired = make_crl_at(
+        (&ca.0, &ca.1),
+        &[],
+        SystemTime::now() - 2 * day,
+        Some(SystemTime::now() - day),
+    );
+    assert!(!expired.is_time_valid(&chrono::Utc::now()));
+    write_crl(&store.trusted_crl_dir(), "ca", &expired);
+    assert_eq!(
+        validate(&store, &leaf),
+        Err(StatusCode::BadCertificateRevocationUnknown)
+    );
+
+    // So is a revocation list that is not yet valid.
+    let future = make_crl_at((&ca.0, &ca.1), &[], SystemTime::now() + day, None);
+    write_crl(&store.trusted_crl_dir(), "ca", &future);
+    assert_eq!(
+        validate(&store, &leaf),
+        Err(StatusCode::BadCertificateRevocationUnknown)
+    );
+
+    let current = make_crl_at(
+        (&ca.0, &ca.1),
+        &[],
+        SystemTime::now() - day,
+        Some(SystemTime::now() + day),
+    );
+    write_crl(&store.trusted_crl_dir(), "ca", &current);
+    assert_eq!(validate(&store, &leaf), Ok(()));
+}
+
+#[test]
+fn trust_unknown_issuer() {
+    let (_tmp_dir, mut store) = make_certificate_store();
+    store.set_trust_unknown_certs(true);
+    let ca = make_ca("CA");
+    let leaf = make_leaf("Leaf", 2, (&ca.0, &ca.1));
+
+    // Policy trusts unknown certificates, but not certificates whose issuer is not known.
+    assert_eq!(
+        validate(&store, &leaf),
+        Err(StatusCode::BadCertificateChainIncomplete)
+    );
+    assert!(!store
+        .trusted_certs_dir()
+        .join(CertificateStore::cert_file_name(&leaf))
+        .exists());
+    std::fs::remove_dir_all(store.rejected_certs_dir()).unwrap();
+    std::fs::create_dir(store.rejected_certs_dir()).unwrap();
+
+    // Once the issuer is known, the leaf is trusted through policy.
+    write_cert(&store.issuer_certs_dir(), &ca.0);
+    assert_eq!(validate(&store, &leaf), Ok(()));
+    assert!(store
+        .trusted_certs_dir()
+        .join(CertificateStore::cert_file_name(&leaf))
+        .exists());
+}
+
+#[test]
+fn migrate_legacy_trusted_certs() {
+    let (_tmp_dir, store) = make_certificate_store();
+    let ca = make_ca("CA");
+    let legacy_dir = store.trusted_certs_dir().parent().unwrap().to_owned();
+    write_cert(&legacy_dir, &ca.0);
+
+    store.ensure_pki_path().unwrap();
+    let file_name = CertificateStore::cert_file_name(&ca.0);
+    assert!(!legacy_dir.join(&file_name).exists());
+    assert!(store.trusted_certs_dir().join(&file_name).exists());
+}
+
+#[test]
+fn write_trust_list_replaces_dirs() {
+    let (_tmp_dir, store) = make_certificate_store();
+    let ca = make_ca("CA");
+    let other = make_ca("Other");
+    write_cert(&store.trusted_certs_dir(), &other.0);
+    write_cert(&store.issuer_certs_dir(), &other.0);
+
+    store
+        .write_trust_list(&TrustListDataType {
+            specified_lists: TrustListMasks::TrustedCertificates as u32
+                | TrustListMasks::TrustedCrls as u32,
+            trusted_certificates: Some(vec![ca.0.as_byte_string()]),
+            trusted_crls: Some(vec![make_crl((&ca.0, &ca.1), &[]).to_der().unwrap().into()]),
+            issuer_certificates: None,
+            issuer_crls: None,
+        })
+        .unwrap();
+
+    let files = |dir: &Path| {
+        let mut names: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    };
+    assert_eq!(
+        files(&store.trusted_certs_dir()),
+        vec![CertificateStore::cert_file_name(&ca.0)]
+    );
+    assert_eq!(files(&store.trusted_crl_dir()).len(), 1);
+    // Lists that are not specified are left as they are.
+    assert_eq!(
+        files(&store.issuer_certs_dir()),
+        vec![CertificateStore::cert_file_name(&other.0)]
+    );
+    // The staging directories are gone once the new lists are in place.
+    assert_eq!(
+        files(store.trusted_certs_dir().parent().unwrap()),
+        vec!["certs".to_string(), "crl".to_string()]
+    );
+}
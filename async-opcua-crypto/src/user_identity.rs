@@ -7,19 +7,20 @@
 //! The code here determines how or if to encrypt the password depending on the security policy
 //! and user token policy.
 
-use std::io::{Cursor, Write};
+use std::io::{Cursor, Read, Write};
 use std::str::FromStr;
 
 use opcua_types::{
-    encoding::{read_u32, write_u32},
+    encoding::{read_u16, read_u32, read_u8, write_i32, write_u16, write_u32, write_u8},
     status_code::StatusCode,
-    ByteString, UAString,
+    BinaryDecodable, BinaryEncodable, ByteString, ContextOwned, DataTypeId, DateTime,
+    EphemeralKeyType, NodeId, UAString,
     {SignatureData, UserNameIdentityToken, UserTokenPolicy, X509IdentityToken},
 };
 use opcua_types::{Error, IssuedIdentityToken, MessageSecurityMode};
 use tracing::{error, warn};
 
-use super::{KeySize, PrivateKey, RsaPadding, SecurityPolicy, X509};
+use super::{random, AesKey, EphemeralKey, KeySize, PrivateKey, RsaPadding, SecurityPolicy, X509};
 
 /// Trait for a type with a secret encrypted with legacy secret encryption.
 pub trait LegacySecret {
@@ -305,6 +306,515 @@ pub(crate) fn legacy_secret_decrypt(
     }
 }
 
+/// Name of the additional request header parameter used by a client to request an ephemeral
+/// key for encrypting secrets with an ECC security policy. The value is the security policy URI.
+pub const ECDH_POLICY_URI_PARAMETER: &str = "ECDHPolicyUri";
+
+/// Name of the additional response header parameter containing the ephemeral key issued by the
+/// server, as an [`EphemeralKeyType`].
+pub const ECDH_KEY_PARAMETER: &str = "ECDHKey";
+
+/// Create the [`EphemeralKeyType`] sent to a client for an ephemeral key, signed with the
+/// private key of the server certificate for the security policy.
+pub fn create_ephemeral_key_type(
+    ephemeral_key: &EphemeralKey,
+    security_policy: SecurityPolicy,
+    signing_key: &PrivateKey,
+) -> Result<EphemeralKeyType, Error> {
+    let public_key = ephemeral_key.public_key_bytes();
+    let mut signature = vec![0u8; signing_key.signature_size()];
+    security_policy.asymmetric_sign(signing_key, &public_key, &mut signature)?;
+    Ok(EphemeralKeyType {
+        public_key: ByteString::from(public_key),
+        signature: ByteString::from(signature),
+    })
+}
+
+/// Verify that an [`EphemeralKeyType`] received from a server was signed with the server
+/// certificate.
+pub fn verify_ephemeral_key_type(
+    key: &EphemeralKeyType,
+    security_policy: SecurityPolicy,
+    server_cert: &X509,
+) -> Result<(), Error> {
+    let verification_key = server_cert.public_key()?;
+    security_policy.asymmetric_verify_signature(
+        &verification_key,
+        key.public_key.as_ref(),
+        key.signature.as_ref(),
+        None,
+    )
+}
+
+/// Size of the fields preceding the `Length` of an encrypted secret, i.e. the four byte
+/// encoded `TypeId` and the `EncodingMask`.
+const ENCRYPTED_SECRET_PREFIX_SIZE: usize = 5;
+
+fn invalid_secret(msg: impl Into<String>) -> Error {
+    Error::new(StatusCode::BadIdentityTokenInvalid, msg.into())
+}
+
+/// Returns `true` if the secret is encoded in the `RsaEncryptedSecret` or `EccEncryptedSecret`
+/// format described in part 4, 7.41.2.3, rather than the legacy format.
+pub fn is_encrypted_secret(secret: &ByteString) -> bool {
+    let Some(value) = secret.value.as_ref() else {
+        return false;
+    };
+    let ctx_r = ContextOwned::default();
+    NodeId::decode(&mut Cursor::new(value), &ctx_r.context()).is_ok_and(|id| {
+        id == DataTypeId::RsaEncryptedSecret || id == DataTypeId::EccEncryptedSecret
+    })
+}
+
+/// Build the payload of an encrypted secret, which is the part that gets encrypted. It is
+/// padded so the length is a multiple of the block size of the security policy.
+fn encrypted_secret_payload(
+    security_policy: SecurityPolicy,
+    nonce: &[u8],
+    secret: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let block_size = security_policy.plain_block_size();
+    let unpadded_size = 4 + nonce.len() + 4 + secret.len() + 2;
+    let padding_size = (block_size - unpadded_size % block_size) % block_size;
+
+    let ctx_r = ContextOwned::default();
+    let ctx = ctx_r.context();
+    let mut payload = Vec::with_capacity(unpadded_size + padding_size);
+    ByteString::from(nonce).encode(&mut payload, &ctx)?;
+    ByteString::from(secret).encode(&mut payload, &ctx)?;
+    payload.resize(payload.len() + padding_size, padding_size as u8);
+    write_u16(&mut payload, padding_size as u16)?;
+    Ok(payload)
+}
+
+/// Read the nonce and secret from a decrypted payload, checking the padding.
+fn read_encrypted_secret_payload(payload: &[u8]) -> Result<(ByteString, ByteString), Error> {
+    if payload.len() < 2 {
+        return Err(invalid_secret("Encrypted secret payload is too short"));
+    }
+    let (data, padding_size) = payload.split_at(payload.len() - 2);
+    let padding_size = u16::from_le_bytes([padding_size[0], padding_size[1]]) as usize;
+    let Some(data_len) = data.len().checked_sub(padding_size) else {
+        return Err(invalid_secret("Encrypted secret padding is invalid"));
+    };
+
+    let ctx_r = ContextOwned::default();
+    let ctx = ctx_r.context();
+    let mut stream = Cursor::new(&data[..data_len]);
+    let nonce = ByteString::decode(&mut stream, &ctx)?;
+    let secret = ByteString::decode(&mut stream, &ctx)?;
+    if stream.position() as usize != data_len {
+        return Err(invalid_secret(
+            "Encrypted secret payload has trailing data before the padding",
+        ));
+    }
+    Ok((nonce, secret))
+}
+
+/// Encode the header of an encrypted secret, from the `TypeId` up to and including the key data.
+/// The `Length` field is computed from the length of the encrypted payload and the signature
+/// that will follow.
+fn encode_encrypted_secret_header(
+    type_id: DataTypeId,
+    security_policy: SecurityPolicy,
+    signing_certificate: ByteString,
+    key_data: &[u8],
+    payload_size: usize,
+    signature_size: usize,
+) -> Result<Vec<u8>, Error> {
+    let ctx_r = ContextOwned::default();
+    let ctx = ctx_r.context();
+
+    let policy_uri = UAString::from(security_policy.to_uri());
+    let key_data_length = u16::try_from(key_data.len())
+        .map_err(|_| Error::encoding("Encrypted secret key data is too long"))?;
+    let length = policy_uri.byte_len(&ctx)
+        + signing_certificate.byte_len(&ctx)
+        + 8
+        + 2
+        + key_data.len()
+        + payload_size
+        + signature_size;
+
+    let mut header = Vec::with_capacity(ENCRYPTED_SECRET_PREFIX_SIZE + 4 + length);
+    NodeId::from(type_id).encode(&mut header, &ctx)?;
+    // The body of the secret is always binary encoded.
+    write_u8(&mut header, 1u8)?;
+    write_i32(&mut header, length as i32)?;
+    policy_uri.encode(&mut header, &ctx)?;
+    signing_certificate.encode(&mut header, &ctx)?;
+    DateTime::now().encode(&mut header, &ctx)?;
+    write_u16(&mut header, key_data_length)?;
+    header.extend_from_slice(key_data);
+    Ok(header)
+}
+
+/// The fields of an encrypted secret before the encrypted payload.
+struct EncryptedSecretHeader {
+    type_id: NodeId,
+    security_policy: SecurityPolicy,
+    signing_certificate: ByteString,
+    key_data: Vec<u8>,
+    /// Length of the header in bytes, i.e. the offset of the encrypted payload.
+    header_size: usize,
+}
+
+fn decode_encrypted_secret_header(secret: &[u8]) -> Result<EncryptedSecretHeader, Error> {
+    let ctx_r = ContextOwned::default();
+    let ctx = ctx_r.context();
+    let mut stream = Cursor::new(secret);
+
+    let type_id = NodeId::decode(&mut stream, &ctx)?;
+    if read_u8(&mut stream)? != 1 {
+        return Err(invalid_secret(
+            "Encrypted secret has an invalid encoding mask",
+        ));
+    }
+    let length = read_u32(&mut stream)? as usize;
+    if length != secret.len() - stream.position() as usize {
+        return Err(invalid_secret("Encrypted secret has an invalid length"));
+    }
+    let security_policy = SecurityPolicy::from_uri(UAString::decode(&mut stream, &ctx)?.as_ref());
+    let signing_certificate = ByteString::decode(&mut stream, &ctx)?;
+    let _signing_time = DateTime::decode(&mut stream, &ctx)?;
+    let key_data_length = read_u16(&mut stream)? as usize;
+    let mut key_data = vec![0u8; key_data_length];
+    stream.read_exact(&mut key_data).map_err(Error::decoding)?;
+
+    Ok(EncryptedSecretHeader {
+        type_id,
+        security_policy,
+        signing_certificate,
+        key_data,
+        header_size: stream.position() as usize,
+    })
+}
+
+fn encode_byte_strings(values: &[&[u8]]) -> Result<Vec<u8>, Error> {
+    let ctx_r = ContextOwned::default();
+    let ctx = ctx_r.context();
+    let mut buf = Vec::new();
+    for v in values {
+        ByteString::from(*v).encode(&mut buf, &ctx)?;
+    }
+    Ok(buf)
+}
+
+fn decode_byte_strings<const N: usize>(data: &[u8]) -> Result<[ByteString; N], Error> {
+    let ctx_r = ContextOwned::default();
+    let ctx = ctx_r.context();
+    let mut stream = Cursor::new(data);
+    let values: [_; N] = std::array::from_fn(|_| ByteString::decode(&mut stream, &ctx));
+    let mut res: [ByteString; N] = std::array::from_fn(|_| ByteString::null());
+    for (r, v) in res.iter_mut().zip(values) {
+        *r = v?;
+    }
+    if stream.position() as usize != data.len() {
+        return Err(invalid_secret(
+            "Encrypted secret key data has trailing bytes",
+        ));
+    }
+    Ok(res)
+}
+
+/// Encrypt a secret in the `RsaEncryptedSecret` format described in part 4, 7.41.2.4.
+///
+/// The payload is encrypted and signed with random symmetric keys, which are themselves
+/// encrypted with the public key in the server certificate.
+pub fn rsa_encrypt_secret(
+    security_policy: SecurityPolicy,
+    server_cert: &X509,
+    server_nonce: &[u8],
+    secret: &[u8],
+) -> Result<ByteString, Error> {
+    let Some(padding) = security_policy.asymmetric_encryption_padding() else {
+        return Err(Error::new(
+            StatusCode::BadSecurityPolicyRejected,
+            format!("Security policy {security_policy} cannot be used with an RsaEncryptedSecret"),
+        ));
+    };
+
+    // Generate random keys of the right length for the security policy.
+    let (signing_key, encrypting_key, iv) = security_policy.make_secure_channel_keys(
+        random::byte_string(32).as_ref(),
+        random::byte_string(32).as_ref(),
+    );
+
+    let key_data = encode_byte_strings(&[&signing_key, encrypting_key.value(), &iv])?;
+    let public_key = server_cert.public_key()?;
+    let mut encrypted_key_data =
+        vec![0u8; public_key.calculate_cipher_text_size(key_data.len(), padding)];
+    let size = public_key
+        .public_encrypt(&key_data, &mut encrypted_key_data, padding)
+        .map_err(Error::encoding)?;
+    encrypted_key_data.truncate(size);
+
+    let payload = encrypted_secret_payload(security_policy, server_nonce, secret)?;
+    let signature_size = security_policy.symmetric_signature_size();
+    let mut data = encode_encrypted_secret_header(
+        DataTypeId::RsaEncryptedSecret,
+        security_policy,
+        ByteString::null(),
+        &encrypted_key_data,
+        payload.len(),
+        signature_size,
+    )?;
+
+    let header_size = data.len();
+    data.resize(header_size + payload.len() + encrypting_key.block_size(), 0);
+    let size = security_policy.symmetric_encrypt(
+        &encrypting_key,
+        &iv,
+        &payload,
+        &mut data[header_size..],
+    )?;
+    data.truncate(header_size + size);
+
+    let mut signature = vec![0u8; signature_size];
+    security_policy
+        .symmetric_sign(&signing_key, &data, &mut signature)
+        .map_err(|e| Error::new(e, "Failed to sign encrypted secret"))?;
+    data.extend_from_slice(&signature);
+
+    Ok(ByteString::from(data))
+}
+
+/// Encrypt a secret in the `EccEncryptedSecret` format described in part 4, 7.41.2.5.
+///
+/// The payload is encrypted with keys derived from a new ephemeral key and the ephemeral key
+/// previously issued by the server, and signed with the private key of the sender's
+/// application instance certificate.
+pub fn ecc_encrypt_secret(
+    security_policy: SecurityPolicy,
+    signing_cert: &X509,
+    signing_key: &PrivateKey,
+    server_ephemeral_key: &[u8],
+    server_nonce: &[u8],
+    secret: &[u8],
+) -> Result<ByteString, Error> {
+    let Some(curve) = security_policy.ecc_curve() else {
+        return Err(Error::new(
+            StatusCode::BadSecurityPolicyRejected,
+            format!("Security policy {security_policy} cannot be used with an EccEncryptedSecret"),
+        ));
+    };
+
+    let ephemeral_key = EphemeralKey::new(curve);
+    let sender_public_key = ephemeral_key.public_key_bytes();
+    let shared_secret = ephemeral_key.shared_secret(server_ephemeral_key)?;
+    let (_, encrypting_key, iv) = security_policy.make_ecc_secret_keys(
+        &shared_secret,
+        &sender_public_key,
+        server_ephemeral_key,
+    );
+
+    let key_data = encode_byte_strings(&[&sender_public_key, server_ephemeral_key])?;
+    let mut payload = encrypted_secret_payload(security_policy, server_nonce, secret)?;
+    let tag_size = if security_policy.is_aead() {
+        security_policy.symmetric_signature_size()
+    } else {
+        0
+    };
+    let signature_size = signing_key.signature_size();
+    let mut data = encode_encrypted_secret_header(
+        DataTypeId::EccEncryptedSecret,
+        security_policy,
+        signing_cert.as_byte_string(),
+        &key_data,
+        payload.len() + tag_size,
+        signature_size,
+    )?;
+
+    if security_policy.is_aead() {
+        let mut tag = vec![0u8; tag_size];
+        encrypting_key.aead_encrypt(&iv, &data, &mut payload, &mut tag)?;
+        data.extend_from_slice(&payload);
+        data.extend_from_slice(&tag);
+    } else {
+        let header_size = data.len();
+        data.resize(header_size + payload.len() + encrypting_key.block_size(), 0);
+        let size = security_policy.symmetric_encrypt(
+            &encrypting_key,
+            &iv,
+            &payload,
+            &mut data[header_size..],
+        )?;
+        data.truncate(header_size + size);
+    }
+
+    let mut signature = vec![0u8; signature_size];
+    security_policy.asymmetric_sign(signing_key, &data, &mut signature)?;
+    data.extend_from_slice(&signature);
+
+    Ok(ByteString::from(data))
+}
+
+/// A secret decrypted from the `RsaEncryptedSecret` or `EccEncryptedSecret` format.
+#[derive(Debug)]
+pub struct DecryptedSecret {
+    /// The decrypted secret.
+    pub secret: ByteString,
+    /// The certificate that signed the secret. This is only set for an `EccEncryptedSecret`,
+    /// and must be validated by the caller.
+    pub signing_certificate: Option<X509>,
+}
+
+fn decrypt_cbc_payload(
+    security_policy: SecurityPolicy,
+    encrypting_key: &AesKey,
+    iv: &[u8],
+    encrypted: &[u8],
+) -> Result<Vec<u8>, Error> {
+    if encrypting_key.key_length() != encrypting_key.value().len()
+        || encrypted.len().checked_rem(encrypting_key.block_size()) != Some(0)
+    {
+        return Err(invalid_secret(
+            "Encrypted secret has invalid encryption keys",
+        ));
+    }
+    let mut payload = vec![0u8; encrypted.len() + encrypting_key.block_size()];
+    let size = security_policy.symmetric_decrypt(encrypting_key, iv, encrypted, &mut payload)?;
+    payload.truncate(size);
+    Ok(payload)
+}
+
+/// Decrypt a secret in the `RsaEncryptedSecret` or `EccEncryptedSecret` format.
+///
+/// The secret must use `security_policy`, which is the security policy of the user token policy,
+/// and contain the `server_nonce`. The `server_key` is the private key for the server certificate
+/// used with an `RsaEncryptedSecret`, and the `ephemeral_key` is the key most recently issued
+/// to the client, used with an `EccEncryptedSecret`.
+pub fn decrypt_encrypted_secret(
+    secret: &ByteString,
+    security_policy: SecurityPolicy,
+    server_nonce: &[u8],
+    server_key: Option<&PrivateKey>,
+    ephemeral_key: Option<&EphemeralKey>,
+) -> Result<DecryptedSecret, Error> {
+    let Some(data) = secret.value.as_deref() else {
+        return Err(invalid_secret("Missing encrypted secret"));
+    };
+    let header = decode_encrypted_secret_header(data)?;
+    if header.security_policy != security_policy {
+        return Err(Error::new(
+            StatusCode::BadSecurityPolicyRejected,
+            format!(
+                "Encrypted secret uses security policy {}, expected {security_policy}",
+                header.security_policy
+            ),
+        ));
+    }
+
+    let (payload, signing_certificate) = if header.type_id == DataTypeId::RsaEncryptedSecret {
+        let (Some(server_key), Some(padding)) =
+            (server_key, security_policy.asymmetric_encryption_padding())
+        else {
+            return Err(invalid_secret("Cannot decrypt an RsaEncryptedSecret"));
+        };
+
+        let mut key_data = vec![0u8; header.key_data.len()];
+        let size = server_key
+            .private_decrypt(&header.key_data, &mut key_data, padding)
+            .map_err(|e| Error::new(StatusCode::BadIdentityTokenInvalid, e))?;
+        let [signing_key, encrypting_key, iv] = decode_byte_strings::<3>(&key_data[..size])?;
+        let signing_key = signing_key.as_ref();
+
+        let signature_size = security_policy.symmetric_signature_size();
+        let Some(signed_size) = data.len().checked_sub(signature_size) else {
+            return Err(invalid_secret("Encrypted secret is too short"));
+        };
+        if signed_size < header.header_size {
+            return Err(invalid_secret("Encrypted secret is too short"));
+        }
+        if signing_key.len() != security_policy.derived_signature_key_size() {
+            return Err(invalid_secret(
+                "Encrypted secret has an invalid signing key",
+            ));
+        }
+        security_policy.symmetric_verify_signature(
+            signing_key,
+            &data[..signed_size],
+            &data[signed_size..],
+        )?;
+
+        let encrypting_key = AesKey::new(security_policy, encrypting_key.as_ref());
+        let payload = decrypt_cbc_payload(
+            security_policy,
+            &encrypting_key,
+            iv.as_ref(),
+            &data[header.header_size..signed_size],
+        )?;
+        (payload, None)
+    } else if header.type_id == DataTypeId::EccEncryptedSecret {
+        let Some(ephemeral_key) =
+            ephemeral_key.filter(|k| Some(k.curve()) == security_policy.ecc_curve())
+        else {
+            return Err(invalid_secret(
+                "Cannot decrypt an EccEncryptedSecret, no ephemeral key was issued for the security policy",
+            ));
+        };
+
+        let signing_certificate = X509::from_byte_string(&header.signing_certificate)?;
+        let verification_key = signing_certificate.public_key()?;
+        let signature_size = verification_key.signature_size();
+        let Some(signed_size) = data.len().checked_sub(signature_size) else {
+            return Err(invalid_secret("Encrypted secret is too short"));
+        };
+        if signed_size < header.header_size {
+            return Err(invalid_secret("Encrypted secret is too short"));
+        }
+        security_policy.asymmetric_verify_signature(
+            &verification_key,
+            &data[..signed_size],
+            &data[signed_size..],
+            None,
+        )?;
+
+        let [sender_public_key, receiver_public_key] = decode_byte_strings::<2>(&header.key_data)?;
+        if receiver_public_key.as_ref() != ephemeral_key.public_key_bytes().as_slice() {
+            return Err(invalid_secret(
+                "Encrypted secret was not encrypted with the last ephemeral key issued by the server",
+            ));
+        }
+        let shared_secret = ephemeral_key.shared_secret(sender_public_key.as_ref())?;
+        let (_, encrypting_key, iv) = security_policy.make_ecc_secret_keys(
+            &shared_secret,
+            sender_public_key.as_ref(),
+            receiver_public_key.as_ref(),
+        );
+
+        let encrypted = &data[header.header_size..signed_size];
+        let payload = if security_policy.is_aead() {
+            let tag_size = security_policy.symmetric_signature_size();
+            let Some(payload_size) = encrypted.len().checked_sub(tag_size) else {
+                return Err(invalid_secret("Encrypted secret is too short"));
+            };
+            let (encrypted, tag) = encrypted.split_at(payload_size);
+            let mut payload = encrypted.to_vec();
+            encrypting_key.aead_decrypt(&iv, &data[..header.header_size], &mut payload, tag)?;
+            payload
+        } else {
+            decrypt_cbc_payload(security_policy, &encrypting_key, &iv, encrypted)?
+        };
+        (payload, Some(signing_certificate))
+    } else {
+        return Err(invalid_secret(format!(
+            "Unknown encrypted secret type {}",
+            header.type_id
+        )));
+    };
+
+    let (nonce, secret) = read_encrypted_secret_payload(&payload)?;
+    if nonce.as_ref() != server_nonce {
+        return Err(invalid_secret("Encrypted secret has an invalid nonce"));
+    }
+
+    Ok(DecryptedSecret {
+        secret,
+        signing_certificate,
+    })
+}
+
 /// Verify that the X509 identity token supplied to a server contains a valid signature.
 pub fn verify_x509_identity_token(
     token: &X509IdentityToken,
@@ -32,8 +32,9 @@ use x509::ext::pkix::name as xname;
 use opcua_types::{status_code::StatusCode, ApplicationDescription, ByteString, Error};
 
 use super::{
+    ecc::EccCurve,
     hostname,
-    pkey::{PrivateKey, PublicKey},
+    pkey::{PrivateKey, PrivateKeyValue, PublicKey, PublicKeyValue},
     thumbprint::Thumbprint,
 };
 
@@ -393,6 +394,64 @@ impl From<x509::der::Error> for X509Error {
     }
 }
 
+/// Signs certificates and signing requests with a brainpoolP256r1 key. bp256 is built on
+/// newer releases of the RustCrypto traits than x509-cert, so the builders only see the
+/// DER encoded public key here, and the TBS data is signed by hand.
+#[cfg(feature = "brainpool")]
+struct BrainpoolP256r1Signer {
+    key: bp256::r1::SecretKey,
+    public_key: EncodedPublicKey,
+}
+
+#[cfg(feature = "brainpool")]
+#[derive(Clone)]
+struct EncodedPublicKey(x509::der::Document);
+
+#[cfg(feature = "brainpool")]
+impl x509::spki::EncodePublicKey for EncodedPublicKey {
+    fn to_public_key_der(&self) -> x509::spki::Result<x509::der::Document> {
+        Ok(self.0.clone())
+    }
+}
+
+#[cfg(feature = "brainpool")]
+impl BrainpoolP256r1Signer {
+    fn new(pkey: &PrivateKey, key: &bp256::r1::SecretKey) -> x509::spki::Result<Self> {
+        Ok(Self {
+            key: key.clone(),
+            public_key: EncodedPublicKey(pkey.to_public_key().to_der()?),
+        })
+    }
+
+    fn sign(&self, data: &[u8]) -> x509::der::Result<x509::der::asn1::BitString> {
+        use bp256_ecdsa::signature::Signer;
+        let signature: bp256::r1::ecdsa::DerSignature =
+            bp256_ecdsa::SigningKey::from(&self.key).sign(data);
+        x509::der::asn1::BitString::from_bytes(signature.as_bytes())
+    }
+}
+
+#[cfg(feature = "brainpool")]
+impl rsa::signature::Keypair for BrainpoolP256r1Signer {
+    type VerifyingKey = EncodedPublicKey;
+
+    fn verifying_key(&self) -> Self::VerifyingKey {
+        self.public_key.clone()
+    }
+}
+
+#[cfg(feature = "brainpool")]
+impl x509::spki::DynSignatureAlgorithmIdentifier for BrainpoolP256r1Signer {
+    fn signature_algorithm_identifier(
+        &self,
+    ) -> x509::spki::Result<x509::spki::AlgorithmIdentifierOwned> {
+        Ok(x509::spki::AlgorithmIdentifierOwned {
+            oid: const_oid::db::rfc5912::ECDSA_WITH_SHA_256,
+            parameters: None,
+        })
+    }
+}
+
 #[derive(Clone)]
 /// Wrapper around an X509 certificate.
 pub struct X509 {
@@ -470,6 +529,18 @@ impl X509 {
         Ok((cert, pkey))
     }
 
+    /// Creates a self-signed X509v3 certificate and private key on the given elliptic curve.
+    /// This is the application instance certificate used by the ECC security policies,
+    /// the `key_size` of the `x509_data` is ignored.
+    pub fn ecc_cert_and_pkey(
+        x509_data: &X509Data,
+        curve: EccCurve,
+    ) -> Result<(Self, PrivateKey), String> {
+        let pkey = PrivateKey::new_ecc(curve);
+        let cert = Self::from_pkey(&pkey, x509_data)?;
+        Ok((cert, pkey))
+    }
+
     fn append_to_name(name: &mut String, param: &str, data: &str) {
         if !data.is_empty() {
             if !name.is_empty() {
@@ -497,6 +568,54 @@ impl X509 {
     }
 
     fn create_from_pkey(pkey: &PrivateKey, x509_data: &X509Data) -> Result<Self, BuilderError> {
+        use x509::{builder::Builder, der::asn1::BitString};
+
+        let built = match &pkey.value {
+            PrivateKeyValue::Rsa(k) => {
+                let signing_key = pkcs1v15::SigningKey::<sha2::Sha256>::new(k.clone());
+                Self::certificate_builder(pkey, x509_data, &signing_key)?
+                    .build::<pkcs1v15::Signature>()?
+            }
+            PrivateKeyValue::NistP256(k) => {
+                let signing_key = p256::ecdsa::SigningKey::from(k);
+                Self::certificate_builder(pkey, x509_data, &signing_key)?
+                    .build::<p256::ecdsa::DerSignature>()?
+            }
+            PrivateKeyValue::NistP384(k) => {
+                let signing_key = p384::ecdsa::SigningKey::from(k);
+                Self::certificate_builder(pkey, x509_data, &signing_key)?
+                    .build::<p384::ecdsa::DerSignature>()?
+            }
+            #[cfg(feature = "brainpool")]
+            PrivateKeyValue::BrainpoolP256r1(k) => {
+                let signer = BrainpoolP256r1Signer::new(pkey, k)?;
+                let mut builder = Self::certificate_builder(pkey, x509_data, &signer)?;
+                let blob = builder.finalize()?;
+                builder.assemble(signer.sign(&blob)?)?
+            }
+            PrivateKeyValue::Curve25519(k) => {
+                // Ed25519 signatures have no bit string encoding in the signature crate,
+                // so sign the TBS certificate by hand.
+                use ed25519_dalek::Signer;
+                let mut builder = Self::certificate_builder(pkey, x509_data, k)?;
+                let blob = builder.finalize()?;
+                let signature = k.sign(&blob);
+                builder.assemble(BitString::from_bytes(&signature.to_bytes())?)?
+            }
+        };
+
+        Ok(X509 { value: built })
+    }
+
+    fn certificate_builder<'s, S>(
+        pkey: &PrivateKey,
+        x509_data: &X509Data,
+        signing_key: &'s S,
+    ) -> Result<x509::builder::CertificateBuilder<'s, S>, BuilderError>
+    where
+        S: x509::spki::DynSignatureAlgorithmIdentifier + rsa::signature::Keypair,
+        S::VerifyingKey: x509::spki::EncodePublicKey,
+    {
         use std::time::Duration;
         use x509_cert::builder::{CertificateBuilder, Profile};
         use x509_cert::name::Name;
@@ -518,8 +637,6 @@ impl X509 {
         ))
         .unwrap();
 
-        let signing_key = pkcs1v15::SigningKey::<sha2::Sha256>::new(pkey.value.clone());
-
         let serial_number = SerialNumber::from(42u32);
 
         let subject;
@@ -559,7 +676,7 @@ impl X509 {
             validity,
             subject.clone(),
             pub_key,
-            &signing_key,
+            signing_key,
         )?;
 
         builder.add_extension(&x509::ext::pkix::SubjectKeyIdentifier(
@@ -579,11 +696,12 @@ impl X509 {
             use x509::ext::pkix::KeyUsage;
             use x509::ext::pkix::KeyUsages;
 
-            let key_usage = KeyUsages::DigitalSignature
-                | KeyUsages::NonRepudiation
-                | KeyUsages::KeyEncipherment
-                | KeyUsages::DataEncipherment
-                | KeyUsages::KeyCertSign;
+            let mut key_usage =
+                KeyUsages::DigitalSignature | KeyUsages::NonRepudiation | KeyUsages::KeyCertSign;
+            // ECC keys are only used for signing, encryption uses an ephemeral key exchange
+            if pkey.ecc_curve().is_none() {
+                key_usage |= KeyUsages::KeyEncipherment | KeyUsages::DataEncipherment;
+            }
             builder.add_extension(&KeyUsage(key_usage))?;
         }
 
@@ -602,10 +720,7 @@ impl X509 {
             }
         }
 
-        use x509_cert::builder::Builder;
-        let built = builder.build()?;
-
-        Ok(X509 { value: built })
+        Ok(builder)
     }
 
     /// Load a certificate from a der byte string.
@@ -634,12 +749,28 @@ impl X509 {
     pub fn public_key(&self) -> Result<PublicKey, Error> {
         use x509_cert::der::referenced::OwnedToRef;
 
-        let r = RsaPublicKey::try_from(
-            self.value
-                .tbs_certificate
-                .subject_public_key_info
-                .owned_to_ref(),
-        );
+        let info = self
+            .value
+            .tbs_certificate
+            .subject_public_key_info
+            .owned_to_ref();
+        let r = RsaPublicKey::try_from(info.clone())
+            .map(PublicKeyValue::Rsa)
+            .or_else(|_| p256::PublicKey::try_from(info.clone()).map(PublicKeyValue::NistP256))
+            .or_else(|_| p384::PublicKey::try_from(info.clone()).map(PublicKeyValue::NistP384))
+            .or_else(|_| {
+                ed25519_dalek::VerifyingKey::try_from(info.clone()).map(PublicKeyValue::Curve25519)
+            });
+        #[cfg(feature = "brainpool")]
+        let r = r.or_else(|_| {
+            use bp256::pkcs8::DecodePublicKey;
+            use x509::der::Encode;
+            bp256::elliptic_curve::PublicKey::<bp256::BrainpoolP256r1>::from_public_key_der(
+                &info.to_der()?,
+            )
+            .map(PublicKeyValue::BrainpoolP256r1)
+            .map_err(|_| x509::spki::Error::KeyMalformed)
+        });
         match r {
             Err(e) => Err(Error::new(StatusCode::BadCertificateInvalid, e)),
             Ok(v) => Ok(PublicKey { value: v }),
@@ -760,6 +891,14 @@ impl X509 {
         }
     }
 
+    /// Get the application uri of the cert, which is the first subject alt name if it is a uri.
+    pub fn application_uri(&self) -> Option<String> {
+        match self.get_alternate_names()?.first()? {
+            GeneralName::UniformResourceIdentifier(val) => Some(val.to_string()),
+            _ => None,
+        }
+    }
+
     /// Tests if the supplied application uri matches the uri alt subject name entry on the cert
     pub fn is_application_uri_valid(&self, application_uri: &str) -> Result<(), StatusCode> {
         // Expecting the first subject alternative name to be a uri that matches with the supplied
@@ -842,6 +981,232 @@ impl X509 {
             Some(val) => Ok(val),
         }
     }
+
+    /// Produces an issuer name string such as "CN=foo/C=IE"
+    pub fn issuer_name(&self) -> String {
+        let r = self.value.tbs_certificate.issuer.to_string();
+        r.replace(";", "/")
+    }
+
+    /// Get the serial number of the certificate as big endian bytes.
+    pub fn serial_number(&self) -> &[u8] {
+        self.value.tbs_certificate.serial_number.as_bytes()
+    }
+
+    pub(crate) fn raw_serial_number(&self) -> &x509::serial_number::SerialNumber {
+        &self.value.tbs_certificate.serial_number
+    }
+
+    pub(crate) fn subject(&self) -> &x509::name::Name {
+        &self.value.tbs_certificate.subject
+    }
+
+    /// Whether the certificate is self-issued, meaning that the issuer and subject are the same.
+    /// Self-issued certificates are the root of a certificate chain.
+    pub fn is_self_issued(&self) -> bool {
+        self.value.tbs_certificate.issuer == self.value.tbs_certificate.subject
+    }
+
+    /// Whether this is the certificate of a certificate authority, i.e. the basic constraints
+    /// extension is present and has `cA` set.
+    pub fn is_ca(&self) -> bool {
+        use x509::ext::pkix::BasicConstraints;
+
+        let r: Result<Option<(bool, BasicConstraints)>, _> = self.value.tbs_certificate.get();
+        matches!(r, Ok(Some((_, c))) if c.ca)
+    }
+
+    /// Whether this certificate was issued by `issuer`. This checks that the issuer name
+    /// matches the subject of `issuer`, and that the signature on this certificate
+    /// can be verified using the public key of `issuer`.
+    pub fn is_issued_by(&self, issuer: &X509) -> bool {
+        use x509_cert::der::Encode;
+
+        if &self.value.tbs_certificate.issuer != issuer.subject() {
+            return false;
+        }
+        let Ok(tbs) = self.value.tbs_certificate.to_der() else {
+            return false;
+        };
+        let Some(signature) = self.value.signature.as_bytes() else {
+            return false;
+        };
+        issuer.verify_signed_data(&self.value.signature_algorithm.oid, &tbs, signature)
+    }
+
+    /// Whether `pkey` is the private key matching the public key in this certificate.
+    pub fn is_key_pair(&self, pkey: &PrivateKey) -> bool {
+        self.public_key()
+            .is_ok_and(|k| k.value == pkey.to_public_key().value)
+    }
+
+    /// Create a DER encoded PKCS #10 certificate signing request for the public key of `pkey`,
+    /// signed with `pkey`. The subject alternative names are copied from this certificate.
+    /// If `subject_name` is `None`, the subject of this certificate is used as well.
+    ///
+    /// The subject name may be given in either the "CN=foo,O=bar" or the "CN=foo/O=bar" form.
+    pub fn create_signing_request(
+        &self,
+        pkey: &PrivateKey,
+        subject_name: Option<&str>,
+    ) -> Result<Vec<u8>, X509Error> {
+        use std::str::FromStr;
+        use x509::{builder::Builder, der::Encode};
+
+        let subject = match subject_name {
+            Some(name) => x509::name::Name::from_str(&name.replace('/', ","))?,
+            None => self.subject().clone(),
+        };
+        let request = match &pkey.value {
+            PrivateKeyValue::Rsa(k) => {
+                let signing_key = pkcs1v15::SigningKey::<sha2::Sha256>::new(k.clone());
+                self.signing_request_builder(subject, &signing_key)?
+                    .build::<pkcs1v15::Signature>()
+            }
+            PrivateKeyValue::NistP256(k) => {
+                let signing_key = p256::ecdsa::SigningKey::from(k);
+                self.signing_request_builder(subject, &signing_key)?
+                    .build::<p256::ecdsa::DerSignature>()
+            }
+            PrivateKeyValue::NistP384(k) => {
+                let signing_key = p384::ecdsa::SigningKey::from(k);
+                self.signing_request_builder(subject, &signing_key)?
+                    .build::<p384::ecdsa::DerSignature>()
+            }
+            #[cfg(feature = "brainpool")]
+            PrivateKeyValue::BrainpoolP256r1(k) => {
+                let signer = BrainpoolP256r1Signer::new(pkey, k).map_err(|_| X509Error)?;
+                let mut builder = self.signing_request_builder(subject, &signer)?;
+                let blob = builder.finalize()?;
+                let signature = signer.sign(&blob)?;
+                builder.assemble(signature)
+            }
+            PrivateKeyValue::Curve25519(k) => {
+                use ed25519_dalek::Signer;
+                let mut builder = self.signing_request_builder(subject, k)?;
+                let blob = builder.finalize()?;
+                let signature = x509::der::asn1::BitString::from_bytes(&k.sign(&blob).to_bytes())?;
+                builder.assemble(signature)
+            }
+        }
+        .map_err(|_| X509Error)?;
+        Ok(request.to_der()?)
+    }
+
+    fn signing_request_builder<'s, S>(
+        &self,
+        subject: x509::name::Name,
+        signing_key: &'s S,
+    ) -> Result<x509::builder::RequestBuilder<'s, S>, X509Error>
+    where
+        S: x509::spki::DynSignatureAlgorithmIdentifier + rsa::signature::Keypair,
+        S::VerifyingKey: x509::spki::EncodePublicKey,
+    {
+        let mut builder =
+            x509::builder::RequestBuilder::new(subject, signing_key).map_err(|_| X509Error)?;
+        if let Some(names) = self.get_alternate_names() {
+            builder
+                .add_extension(&x509::ext::pkix::SubjectAltName(names))
+                .map_err(|_| X509Error)?;
+        }
+        Ok(builder)
+    }
+
+    /// Verify `signature` over `data` using the public key in this certificate, with the
+    /// X509 signature algorithm given by `algorithm`.
+    pub(crate) fn verify_signed_data(
+        &self,
+        algorithm: &const_oid::ObjectIdentifier,
+        data: &[u8],
+        signature: &[u8],
+    ) -> bool {
+        use const_oid::db::rfc5912;
+
+        use rsa::signature::{hazmat::PrehashVerifier, Verifier};
+        use sha2::Digest;
+
+        fn verify<D>(key: &PublicKey, data: &[u8], signature: &[u8]) -> bool
+        where
+            D: sha2::Digest + const_oid::AssociatedOid,
+        {
+            let PublicKeyValue::Rsa(key) = &key.value else {
+                return false;
+            };
+            let verifying_key = pkcs1v15::VerifyingKey::<D>::new(key.clone());
+            pkcs1v15::Signature::try_from(signature)
+                .is_ok_and(|s| verifying_key.verify(data, &s).is_ok())
+        }
+
+        let Ok(key) = self.public_key() else {
+            return false;
+        };
+        let ecdsa_digest = if algorithm == &rfc5912::ECDSA_WITH_SHA_256 {
+            Some(sha2::Sha256::digest(data).to_vec())
+        } else if algorithm == &rfc5912::ECDSA_WITH_SHA_384 {
+            Some(sha2::Sha384::digest(data).to_vec())
+        } else if algorithm == &rfc5912::ECDSA_WITH_SHA_512 {
+            Some(sha2::Sha512::digest(data).to_vec())
+        } else {
+            None
+        };
+        if let Some(digest) = ecdsa_digest {
+            // The digest is given by the signature algorithm, independent of the curve
+            // of the issuer key.
+            match &key.value {
+                PublicKeyValue::NistP256(k) => p256::ecdsa::Signature::from_der(signature)
+                    .is_ok_and(|s| {
+                        p256::ecdsa::VerifyingKey::from(k)
+                            .verify_prehash(&digest, &s)
+                            .is_ok()
+                    }),
+                PublicKeyValue::NistP384(k) => p384::ecdsa::Signature::from_der(signature)
+                    .is_ok_and(|s| {
+                        p384::ecdsa::VerifyingKey::from(k)
+                            .verify_prehash(&digest, &s)
+                            .is_ok()
+                    }),
+                #[cfg(feature = "brainpool")]
+                PublicKeyValue::BrainpoolP256r1(k) => {
+                    use bp256_ecdsa::signature::hazmat::PrehashVerifier as _;
+                    bp256::r1::ecdsa::Signature::from_der(signature).is_ok_and(|s| {
+                        bp256_ecdsa::VerifyingKey::from(k)
+                            .verify_prehash(&digest, &s)
+                            .is_ok()
+                    })
+                }
+                _ => {
+                    warn!(
+                        "Certificate signature algorithm {} does not match the issuer key",
+                        algorithm
+                    );
+                    false
+                }
+            }
+        } else if algorithm == &const_oid::db::rfc8410::ID_ED_25519 {
+            let PublicKeyValue::Curve25519(k) = &key.value else {
+                return false;
+            };
+            ed25519_dalek::Signature::from_slice(signature)
+                .is_ok_and(|s| k.verify(data, &s).is_ok())
+        } else if algorithm == &rfc5912::SHA_1_WITH_RSA_ENCRYPTION {
+            verify::<sha1::Sha1>(&key, data, signature)
+        } else if algorithm == &rfc5912::SHA_256_WITH_RSA_ENCRYPTION {
+            verify::<sha2::Sha256>(&key, data, signature)
+        } else if algorithm == &rfc5912::SHA_384_WITH_RSA_ENCRYPTION {
+            verify::<sha2::Sha384>(&key, data, signature)
+        } else if algorithm == &rfc5912::SHA_512_WITH_RSA_ENCRYPTION {
+            verify::<sha2::Sha512>(&key, data, signature)
+        } else {
+            warn!("Unsupported certificate signature algorithm {}", algorithm);
+            false
+        }
+    }
+}
+
+impl PartialEq for X509 {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
 }
 
 #[cfg(test)]
@@ -1,17 +1,23 @@
-use std::cmp::Ordering;
+use std::{
+    cmp::Ordering,
+    collections::{HashSet, VecDeque},
+};
 
 use regex::Regex;
 use tracing::error;
 
 use opcua_types::{
-    AttributeId, EventFieldList, FilterOperator, NodeId, NumericRange, QualifiedName, Variant,
-    VariantScalarTypeId, VariantTypeId,
+    AttributeId, BrowseDirection, EventFieldList, FilterOperator, NodeId, NumericRange,
+    ObjectTypeId, QualifiedName, ReferenceTypeId, Variant, VariantScalarTypeId, VariantTypeId,
 };
 
+use crate::{References, TypeTree};
+
 use super::{
     event::Event,
     validation::{
-        ParsedContentFilter, ParsedEventFilter, ParsedOperand, ParsedSimpleAttributeOperand,
+        ParsedAttributeOperand, ParsedContentFilter, ParsedContentFilterElement, ParsedEventFilter,
+        ParsedOperand, ParsedSimpleAttributeOperand,
     },
 };
 
@@ -19,20 +25,45 @@ impl ParsedEventFilter {
     /// Evaluate the event filter, returning `None` if the
     /// filter does not accept the event, and a list of event fields fetched from
     /// the event if it does.
+    ///
+    /// Without a type tree, `OfType` only matches the exact event type, and
+    /// `InView` and `RelatedTo` never match. Use [ParsedEventFilter::evaluate_in_context]
+    /// to evaluate these properly.
     pub fn evaluate(&self, event: &dyn Event, client_handle: u32) -> Option<EventFieldList> {
         if !self.content_filter.evaluate(event) {
             return None;
         }
+        Some(self.select_fields(event, client_handle))
+    }
 
+    /// Evaluate the event filter, using `context` to evaluate the `OfType`,
+    /// `InView` and `RelatedTo` operators.
+    pub fn evaluate_in_context(
+        &self,
+        event: &dyn Event,
+        context: EventFilterContext<'_>,
+        client_handle: u32,
+    ) -> Option<EventFieldList> {
+        let item = EventItem {
+            kind: EventItemKind::Event(event),
+            context,
+        };
+        if !self.content_filter.evaluate(item) {
+            return None;
+        }
+        Some(self.select_fields(event, client_handle))
+    }
+
+    fn select_fields(&self, event: &dyn Event, client_handle: u32) -> EventFieldList {
         let fields: Vec<_> = self
             .select_clauses
             .iter()
             .map(|c| get_field(event, c))
             .collect();
-        Some(EventFieldList {
+        EventFieldList {
             client_handle,
             event_fields: Some(fields),
-        })
+        }
     }
 }
 
@@ -81,6 +112,54 @@ pub trait AttributeQueryable: Copy {
         attribute_id: AttributeId,
         index_range: &NumericRange,
     ) -> Variant;
+
+    /// Get the value of an attribute operand, referencing an attribute of a node
+    /// relative to this item. Attribute operands evaluate to null by default.
+    fn get_attribute_operand(&self, _operand: &ParsedAttributeOperand) -> Variant {
+        Variant::Empty
+    }
+
+    /// Return `true` if this item is an instance of `type_definition_id`, or of one
+    /// of its subtypes if `include_subtypes` is set.
+    ///
+    /// Used by the `OfType` and `RelatedTo` operators.
+    fn is_of_type(&self, _type_definition_id: &NodeId, _include_subtypes: bool) -> bool {
+        false
+    }
+
+    /// Return `true` if this item is part of the view given by `view_id`.
+    ///
+    /// Used by the `InView` operator.
+    fn in_view(&self, _view_id: &NodeId) -> bool {
+        false
+    }
+
+    /// Get the items this item references with forward references of type
+    /// `reference_type_id`, or one of its subtypes if `include_subtypes` is set.
+    ///
+    /// Used by the `RelatedTo` operator.
+    fn related(&self, _reference_type_id: &NodeId, _include_subtypes: bool) -> Vec<Self> {
+        Vec::new()
+    }
+
+    /// Get the ID of the node representing this item in the reference graph, if any.
+    ///
+    /// Used by the `RelatedTo` operator to visit each node only once.
+    fn node_id(&self) -> Option<NodeId> {
+        None
+    }
+}
+
+fn base_event_field(event: &dyn Event, name: &'static str) -> Option<NodeId> {
+    match event.get_field(
+        &ObjectTypeId::BaseEventType.into(),
+        AttributeId::Value,
+        &NumericRange::None,
+        &[QualifiedName::new(0, name)],
+    ) {
+        Variant::NodeId(id) => Some(*id),
+        _ => None,
+    }
 }
 
 impl AttributeQueryable for &dyn Event {
@@ -93,6 +172,138 @@ impl AttributeQueryable for &dyn Event {
     ) -> Variant {
         self.get_field(type_definition_id, attribute_id, index_range, browse_path)
     }
+
+    fn is_of_type(&self, type_definition_id: &NodeId, include_subtypes: bool) -> bool {
+        // Without a type tree, the only subtype relation we know of is that
+        // every event type is a subtype of `BaseEventType`.
+        base_event_field(*self, "EventType").is_some_and(|t| {
+            &t == type_definition_id
+                || include_subtypes && type_definition_id == &ObjectTypeId::BaseEventType
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+/// Context used to evaluate the operators in event filters that need
+/// to know about the type hierarchy or the address space.
+pub struct EventFilterContext<'a> {
+    /// Type tree used to check the type of events, and of nodes related to
+    /// the event source.
+    pub type_tree: &'a dyn TypeTree,
+    /// References of the address space containing the event source node, if available.
+    /// `InView` and `RelatedTo` are evaluated on the source node of the event,
+    /// and never match without references.
+    pub references: Option<&'a References>,
+}
+
+#[derive(Clone, Copy)]
+enum EventItemKind<'a> {
+    Event(&'a dyn Event),
+    Node(&'a NodeId),
+}
+
+/// An event, or a node reached from the source node of an event, evaluated
+/// against an event filter.
+#[derive(Clone, Copy)]
+struct EventItem<'a> {
+    kind: EventItemKind<'a>,
+    context: EventFilterContext<'a>,
+}
+
+impl AttributeQueryable for EventItem<'_> {
+    fn get_attribute(
+        &self,
+        type_definition_id: &NodeId,
+        browse_path: &[QualifiedName],
+        attribute_id: AttributeId,
+        index_range: &NumericRange,
+    ) -> Variant {
+        match self.kind {
+            EventItemKind::Event(e) => {
+                e.get_field(type_definition_id, attribute_id, index_range, browse_path)
+            }
+            // Attributes of related nodes are not available to event filters.
+            EventItemKind::Node(_) => Variant::Empty,
+        }
+    }
+
+    fn is_of_type(&self, type_definition_id: &NodeId, include_subtypes: bool) -> bool {
+        let type_id = match self.kind {
+            EventItemKind::Event(e) => base_event_field(e, "EventType"),
+            EventItemKind::Node(n) => self.context.references.and_then(|r| {
+                r.find_references(
+                    n,
+                    Some((ReferenceTypeId::HasTypeDefinition, false)),
+                    self.context.type_tree,
+                    BrowseDirection::Forward,
+                )
+                .next()
+                .map(|r| r.target_node.clone())
+            }),
+        };
+        let Some(type_id) = type_id else {
+            return false;
+        };
+        if include_subtypes {
+            self.context
+                .type_tree
+                .is_subtype_of(&type_id, type_definition_id)
+        } else {
+            &type_id == type_definition_id
+        }
+    }
+
+    fn in_view(&self, view_id: &NodeId) -> bool {
+        let (Some(references), Some(node_id)) = (self.context.references, self.node_id()) else {
+            return false;
+        };
+        // Search upwards through hierarchical references for the view.
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(node_id);
+        while let Some(node_id) = queue.pop_front() {
+            for rf in references.find_references(
+                &node_id,
+                Some((ReferenceTypeId::HierarchicalReferences, true)),
+                self.context.type_tree,
+                BrowseDirection::Inverse,
+            ) {
+                if rf.target_node == view_id {
+                    return true;
+                }
+                if visited.insert(rf.target_node) {
+                    queue.push_back(rf.target_node.clone());
+                }
+            }
+        }
+        false
+    }
+
+    fn related(&self, reference_type_id: &NodeId, include_subtypes: bool) -> Vec<Self> {
+        let (Some(references), Some(node_id)) = (self.context.references, self.node_id()) else {
+            return Vec::new();
+        };
+        references
+            .find_references(
+                &node_id,
+                Some((reference_type_id.clone(), include_subtypes)),
+                self.context.type_tree,
+                BrowseDirection::Forward,
+            )
+            .map(|rf| EventItem {
+                kind: EventItemKind::Node(rf.target_node),
+                context: self.context,
+            })
+            .collect()
+    }
+
+    /// The node representing this item in the reference graph. For events this is the source node.
+    fn node_id(&self) -> Option<NodeId> {
+        match self.kind {
+            EventItemKind::Event(e) => base_event_field(e, "SourceNode").filter(|n| !n.is_null()),
+            EventItemKind::Node(n) => Some(n.clone()),
+        }
+    }
 }
 
 enum BitOperation {
@@ -170,15 +381,111 @@ impl ParsedContentFilter {
                 self.evaluate_operand(item, &op.operands[1]),
                 BitOperation::Or,
             ),
-            _ => Variant::Empty,
+            FilterOperator::OfType => {
+                let Some(type_id) = Self::node_id_operand(&op.operands[0]) else {
+                    return false.into();
+                };
+                item.is_of_type(type_id, true).into()
+            }
+            FilterOperator::InView => {
+                let Some(view_id) = Self::node_id_operand(&op.operands[0]) else {
+                    return false.into();
+                };
+                item.in_view(view_id).into()
+            }
+            FilterOperator::RelatedTo => self.related_to(item, op).into(),
+        }
+    }
+
+    /// Maximum number of hops followed by the `RelatedTo` operator.
+    const MAX_HOPS: u32 = 10;
+
+    fn node_id_operand(op: &ParsedOperand) -> Option<&NodeId> {
+        match op {
+            ParsedOperand::LiteralOperand(o) => match &o.value {
+                Variant::NodeId(n) => Some(n),
+                Variant::ExpandedNodeId(n) => Some(&n.node_id),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Evaluate a `RelatedTo` element with `item` as the source. The operands are
+    ///
+    ///  0. The source type, or an element operand referencing a nested `RelatedTo`
+    ///     the source must satisfy.
+    ///  1. The target type, or an element operand referencing a nested `RelatedTo`
+    ///     the target must satisfy.
+    ///  2. The reference type to follow.
+    ///  3. The number of hops to follow, values less than 1 are treated as 1.
+    ///  4. Whether to include subtypes of the source and target types.
+    ///  5. Whether to include subtypes of the reference type.
+    fn related_to(&self, item: impl AttributeQueryable, op: &ParsedContentFilterElement) -> bool {
+        let Some(reference_type_id) = Self::node_id_operand(&op.operands[2]) else {
+            return false;
+        };
+        let hops = match self.evaluate_operand(item, &op.operands[3]) {
+            Variant::Empty => 1,
+            v => as_type!(v, UInt32, false).clamp(1, Self::MAX_HOPS),
+        };
+        let include_type_subtypes = !matches!(
+            self.evaluate_operand(item, &op.operands[4]),
+            Variant::Boolean(false)
+        );
+        let include_ref_subtypes = !matches!(
+            self.evaluate_operand(item, &op.operands[5]),
+            Variant::Boolean(false)
+        );
+
+        if !self.related_to_operand(item, &op.operands[0], include_type_subtypes) {
+            return false;
+        }
+
+        // Follow references breadth first, visiting each node at most once, so that
+        // cycles and diamonds in the reference graph do not multiply the work per hop.
+        let mut visited = HashSet::new();
+        let mut current = vec![item];
+        for _ in 0..hops {
+            let next: Vec<_> = current
+                .iter()
+                .flat_map(|i| i.related(reference_type_id, include_ref_subtypes))
+                .filter(|n| n.node_id().is_none_or(|id| visited.insert(id)))
+                .collect();
+            if next.is_empty() {
+                return false;
+            }
+            if next
+                .iter()
+                .any(|n| self.related_to_operand(*n, &op.operands[1], include_type_subtypes))
+            {
+                return true;
+            }
+            current = next;
+        }
+        false
+    }
+
+    fn related_to_operand(
+        &self,
+        item: impl AttributeQueryable,
+        op: &ParsedOperand,
+        include_subtypes: bool,
+    ) -> bool {
+        if let ParsedOperand::ElementOperand(e) = op {
+            return match self.elements.get(e.index as usize) {
+                Some(el) if el.operator == FilterOperator::RelatedTo => self.related_to(item, el),
+                _ => false,
+            };
         }
+        Self::node_id_operand(op).is_some_and(|t| item.is_of_type(t, include_subtypes))
     }
 
     fn evaluate_operand(&self, item: impl AttributeQueryable, op: &ParsedOperand) -> Variant {
         match op {
             ParsedOperand::ElementOperand(o) => self.evulate_element(item, o.index as usize),
             ParsedOperand::LiteralOperand(o) => o.value.clone(),
-            ParsedOperand::AttributeOperand(_) => unreachable!(),
+            ParsedOperand::AttributeOperand(o) => item.get_attribute_operand(o),
             ParsedOperand::SimpleAttributeOperand(o) => item.get_attribute(
                 &o.type_definition_id,
                 &o.browse_path,
@@ -391,11 +698,13 @@ mod tests {
     use regex::Regex;
 
     use crate::{
-        events::evaluate::like_to_regex, BaseEventType, DefaultTypeTree, Event, ParsedContentFilter,
+        events::evaluate::like_to_regex, AttributeQueryable, BaseEventType, DefaultTypeTree, Event,
+        EventFilterContext, ParsedContentFilter, ParsedEventFilter, References,
     };
     use opcua_types::{
-        AttributeId, ByteString, ContentFilter, ContentFilterElement, DateTime, FilterOperator,
-        LocalizedText, NodeClass, NodeId, NumericRange, ObjectTypeId, Operand,
+        AttributeId, ByteString, ContentFilter, ContentFilterElement, DateTime, EventFilter,
+        FilterOperator, LocalizedText, NodeClass, NodeId, NumericRange, ObjectTypeId, Operand,
+        QualifiedName, ReferenceTypeId, Variant,
     };
 
     fn compare_regex(r1: Regex, r2: Regex) {
@@ -959,4 +1268,324 @@ mod tests {
         let evt = event(4);
         assert!(!f.evaluate(&evt as &dyn Event));
     }
+
+    /// A tiny graph of typed nodes, for testing the operators that navigate references.
+    struct Graph {
+        /// Type and forward references (reference type, target index) of each node.
+        nodes: Vec<(NodeId, Vec<(NodeId, usize)>)>,
+        /// Supertype of each type.
+        supertypes: Vec<(NodeId, NodeId)>,
+        /// Number of times references have been followed.
+        related_calls: std::cell::Cell<usize>,
+    }
+
+    #[derive(Clone, Copy)]
+    struct GraphNode<'a>(&'a Graph, usize);
+
+    impl AttributeQueryable for GraphNode<'_> {
+        fn get_attribute(
+            &self,
+            _type_definition_id: &NodeId,
+            _browse_path: &[QualifiedName],
+            _attribute_id: AttributeId,
+            _index_range: &NumericRange,
+        ) -> Variant {
+            Variant::Empty
+        }
+
+        fn is_of_type(&self, type_definition_id: &NodeId, include_subtypes: bool) -> bool {
+            let mut ty = &self.0.nodes[self.1].0;
+            loop {
+                if ty == type_definition_id {
+                    return true;
+                }
+                if !include_subtypes {
+                    return false;
+                }
+                match self.0.supertypes.iter().find(|(t, _)| t == ty) {
+                    Some((_, s)) => ty = s,
+                    None => return false,
+                }
+            }
+        }
+
+        fn in_view(&self, view_id: &NodeId) -> bool {
+            view_id == &NodeId::new(1, "View") && self.1 == 0
+        }
+
+        fn related(&self, reference_type_id: &NodeId, _include_subtypes: bool) -> Vec<Self> {
+            self.0.related_calls.set(self.0.related_calls.get() + 1);
+            self.0.nodes[self.1]
+                .1
+                .iter()
+                .filter(|(r, _)| r == reference_type_id)
+                .map(|(_, t)| GraphNode(self.0, *t))
+                .collect()
+        }
+
+        fn node_id(&self) -> Option<NodeId> {
+            Some(NodeId::new(1, self.1 as u32))
+        }
+    }
+
+    #[test]
+    fn test_reference_operators() {
+        let type_tree = type_tree();
+        let (a, b, sub_b) = (
+            NodeId::new(1, "A"),
+            NodeId::new(1, "B"),
+            NodeId::new(1, "SubB"),
+        );
+        let organizes: NodeId = ReferenceTypeId::Organizes.into();
+        let component: NodeId = ReferenceTypeId::HasComponent.into();
+        // 0 (A) -Organizes-> 1 (B) -HasComponent-> 2 (SubB), 0 -Organizes-> 3 (A)
+        let graph = Graph {
+            nodes: vec![
+                (
+                    a.clone(),
+                    vec![(organizes.clone(), 1), (organizes.clone(), 3)],
+                ),
+                (b.clone(), vec![(component.clone(), 2)]),
+                (sub_b.clone(), vec![]),
+                (a.clone(), vec![]),
+            ],
+            supertypes: vec![(sub_b.clone(), b.clone())],
+            related_calls: Default::default(),
+        };
+        let node = |i| GraphNode(&graph, i);
+        let parse = |elements| {
+            let (_, f) = ParsedContentFilter::parse(
+                ContentFilter {
+                    elements: Some(elements),
+                },
+                &type_tree,
+                false,
+                true,
+            );
+            f.unwrap()
+        };
+        let related_to = |source: Operand, target: Operand, rf: &NodeId, hops: u32| {
+            filter_elem(
+                &[
+                    source,
+                    target,
+                    Operand::literal(rf.clone()),
+                    Operand::literal(hops),
+                    Operand::literal(true),
+                    Operand::literal(true),
+                ],
+                FilterOperator::RelatedTo,
+            )
+        };
+
+        // OfType includes subtypes.
+        let f = parse(vec![filter_elem(
+            &[Operand::literal(b.clone())],
+            FilterOperator::OfType,
+        )]);
+        assert!(!f.evaluate(node(0)));
+        assert!(f.evaluate(node(1)));
+        assert!(f.evaluate(node(2)));
+
+        let f = parse(vec![filter_elem(
+            &[Operand::literal(NodeId::new(1, "View"))],
+            FilterOperator::InView,
+        )]);
+        assert!(f.evaluate(node(0)));
+        assert!(!f.evaluate(node(1)));
+
+        // A organizes a B directly.
+        let f = parse(vec![related_to(
+            Operand::literal(a.clone()),
+            Operand::literal(b.clone()),
+            &organizes,
+            1,
+        )]);
+        assert!(f.evaluate(node(0)));
+        assert!(!f.evaluate(node(3)));
+        assert!(!f.evaluate(node(1)));
+
+        // A reaches a SubB only within two hops over HasComponent... which it does not,
+        // since the first hop is Organizes.
+        let f = parse(vec![related_to(
+            Operand::literal(a.clone()),
+            Operand::literal(sub_b.clone()),
+            &component,
+            2,
+        )]);
+        assert!(!f.evaluate(node(0)));
+
+        // Nested: A organizes something that has a SubB component.
+        let f = parse(vec![
+            related_to(
+                Operand::literal(a.clone()),
+                Operand::element(1),
+                &organizes,
+                1,
+            ),
+            related_to(
+                Operand::literal(b.clone()),
+                Operand::literal(sub_b.clone()),
+                &component,
+                1,
+            ),
+        ]);
+        assert!(f.evaluate(node(0)));
+        assert!(!f.evaluate(node(3)));
+    }
+
+    #[test]
+    fn test_related_to_visits_nodes_once() {
+        let type_tree = type_tree();
+        let (a, b) = (NodeId::new(1, "A"), NodeId::new(1, "B"));
+        let organizes: NodeId = ReferenceTypeId::Organizes.into();
+        // Four A nodes that all organize each other, none of them organize a B.
+        let graph = Graph {
+            nodes: (0..4)
+                .map(|i| {
+                    (
+                        a.clone(),
+                        (0..4)
+                            .filter(|j| *j != i)
+                            .map(|j| (organizes.clone(), j))
+                            .collect(),
+                    )
+                })
+                .collect(),
+            supertypes: Vec::new(),
+            related_calls: Default::default(),
+        };
+        let (_, f) = ParsedContentFilter::parse(
+            ContentFilter {
+                elements: Some(vec![filter_elem(
+                    &[
+                        Operand::literal(a.clone()),
+                        Operand::literal(b.clone()),
+                        Operand::literal(organizes.clone()),
+                        Operand::literal(10u32),
+                        Operand::literal(true),
+                        Operand::literal(true),
+                    ],
+                    FilterOperator::RelatedTo,
+                )]),
+            },
+            &type_tree,
+            false,
+            true,
+        );
+        assert!(!f.unwrap().evaluate(GraphNode(&graph, 0)));
+        // Each node is expanded at most once, instead of 3^hops times. The source
+        // is expanded again when it is first reached through a reference.
+        assert_eq!(graph.related_calls.get(), 5);
+    }
+
+    #[test]
+    fn test_event_reference_operators() {
+        let mut type_tree = type_tree();
+        let machine_type = NodeId::new(1, "MachineType");
+        type_tree.add_type_node(
+            &machine_type,
+            &ObjectTypeId::BaseObjectType.into(),
+            NodeClass::ObjectType,
+        );
+        type_tree.add_type_node(
+            &ReferenceTypeId::HierarchicalReferences.into(),
+            &ReferenceTypeId::References.into(),
+            NodeClass::ReferenceType,
+        );
+        type_tree.add_type_node(
+            &ReferenceTypeId::Organizes.into(),
+            &ReferenceTypeId::HierarchicalReferences.into(),
+            NodeClass::ReferenceType,
+        );
+
+        // View -Organizes-> Folder -Organizes-> Source -Organizes-> Machine
+        let (view, folder, source, machine) = (
+            NodeId::new(1, "View"),
+            NodeId::new(1, "Folder"),
+            NodeId::new(1, "Source"),
+            NodeId::new(1, "Machine"),
+        );
+        let mut references = References::new();
+        references.insert_reference(&view, &folder, ReferenceTypeId::Organizes);
+        references.insert_reference(&folder, &source, ReferenceTypeId::Organizes);
+        references.insert_reference(&source, &machine, ReferenceTypeId::Organizes);
+        references.insert_reference(&machine, &machine_type, ReferenceTypeId::HasTypeDefinition);
+
+        let mut evt = event(1);
+        evt.base.source_node = source.clone();
+        let evt = &evt as &dyn Event;
+        let context = EventFilterContext {
+            type_tree: &type_tree,
+            references: Some(&references),
+        };
+        let parse = |elements| {
+            let (_, f) = ParsedEventFilter::new(
+                EventFilter {
+                    select_clauses: None,
+                    where_clause: ContentFilter {
+                        elements: Some(elements),
+                    },
+                },
+                &type_tree,
+            );
+            f.unwrap()
+        };
+
+        // Without a type tree, OfType only matches the exact event type,
+        // and BaseEventType, which all events are subtypes of.
+        let f = parse(vec![filter_elem(
+            &[Operand::literal(NodeId::from(ObjectTypeId::BaseEventType))],
+            FilterOperator::OfType,
+        )]);
+        assert!(f.evaluate(evt, 0).is_some());
+        assert!(f.evaluate_in_context(evt, context, 0).is_some());
+        let f = parse(vec![filter_elem(
+            &[Operand::literal(NodeId::new(1, 123))],
+            FilterOperator::OfType,
+        )]);
+        assert!(f.evaluate(evt, 0).is_some());
+        assert!(f.evaluate_in_context(evt, context, 0).is_some());
+
+        // The event is in the view if its source node is.
+        let f = parse(vec![filter_elem(
+            &[Operand::literal(view.clone())],
+            FilterOperator::InView,
+        )]);
+        assert!(f.evaluate(evt, 0).is_none());
+        assert!(f.evaluate_in_context(evt, context, 0).is_some());
+        let no_references = EventFilterContext {
+            type_tree: &type_tree,
+            references: None,
+        };
+        assert!(f.evaluate_in_context(evt, no_references, 0).is_none());
+        let f = parse(vec![filter_elem(
+            &[Operand::literal(machine.clone())],
+            FilterOperator::InView,
+        )]);
+        assert!(f.evaluate_in_context(evt, context, 0).is_none());
+
+        // The source of the event organizes a machine.
+        let related_to = |hops: u32| {
+            parse(vec![filter_elem(
+                &[
+                    Operand::literal(NodeId::from(ObjectTypeId::BaseEventType)),
+                    Operand::literal(machine_type.clone()),
+                    Operand::literal(NodeId::from(ReferenceTypeId::HierarchicalReferences)),
+                    Operand::literal(hops),
+                    Operand::literal(true),
+                    Operand::literal(true),
+                ],
+                FilterOperator::RelatedTo,
+            )])
+        };
+        assert!(related_to(1).evaluate_in_context(evt, context, 0).is_some());
+        assert!(related_to(1).evaluate(evt, 0).is_none());
+
+        let mut evt = event(1);
+        evt.base.source_node = folder.clone();
+        let evt = &evt as &dyn Event;
+        assert!(related_to(1).evaluate_in_context(evt, context, 0).is_none());
+        assert!(related_to(2).evaluate_in_context(evt, context, 0).is_some());
+    }
 }
@@ -3,7 +3,7 @@ mod event;
 mod evaluate;
 mod validation;
 
-pub use evaluate::AttributeQueryable;
+pub use evaluate::{AttributeQueryable, EventFilterContext};
 pub use event::{BaseEventType, Event, MethodEventField};
 pub use opcua_types::event_field::EventField;
 pub use validation::{
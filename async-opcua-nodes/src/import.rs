@@ -1,4 +1,6 @@
-use opcua_types::NodeId;
+use std::sync::Arc;
+
+use opcua_types::{custom::DataTypeTree, NodeId};
 
 use super::NodeType;
 
@@ -43,4 +45,25 @@ pub trait NodeSetImport {
         &'a self,
         namespaces: &'a NodeSetNamespaceMapper,
     ) -> Box<dyn Iterator<Item = ImportedItem> + 'a>;
+
+    /// Build a data type tree from the data type definitions in this import,
+    /// so that values of structures and enums defined by the import can be encoded and
+    /// decoded at runtime using a [`DynamicTypeLoader`](opcua_types::custom::DynamicTypeLoader).
+    ///
+    /// `data_types` contains the data type hierarchy and the data types already known
+    /// to the server, and is used to resolve the types of structure fields.
+    /// The data types defined by the import should be added to it.
+    ///
+    /// This is called after `register_namespaces` and before `load`. Imports that
+    /// return a type tree should use it when loading values.
+    /// The default implementation returns `None`, which is appropriate for
+    /// generated imports, as these come with their own type loaders.
+    #[allow(unused_variables)]
+    fn load_data_types(
+        &mut self,
+        namespaces: &NodeSetNamespaceMapper,
+        data_types: DataTypeTree,
+    ) -> Option<Arc<DataTypeTree>> {
+        None
+    }
 }
@@ -13,7 +13,7 @@ mod type_tree;
 #[cfg(feature = "xml")]
 mod xml;
 #[cfg(feature = "xml")]
-pub use xml::NodeSet2Import;
+pub use xml::{NodeSet2Export, NodeSet2Import, NodeSet2Model};
 
 pub use base::Base;
 pub use data_type::{DataType, DataTypeBuilder};
@@ -139,6 +139,24 @@ macro_rules! node_builder_impl {
                 self
             }
 
+            /// Sets the permissions granted to each role on the node.
+            pub fn role_permissions(
+                mut self,
+                role_permissions: Vec<opcua_types::RolePermissionType>,
+            ) -> Self {
+                self.node.set_role_permissions(role_permissions);
+                self
+            }
+
+            /// Sets the access restrictions of the node.
+            pub fn access_restrictions(
+                mut self,
+                access_restrictions: opcua_types::AccessRestrictionType,
+            ) -> Self {
+                self.node.set_access_restrictions(access_restrictions);
+                self
+            }
+
             /// Adds a reference to the node
             pub fn reference<T>(
                 mut self,
@@ -387,6 +405,28 @@ macro_rules! node_base_impl {
             fn set_user_write_mask(&mut self, user_write_mask: WriteMask) {
                 self.base.set_user_write_mask(user_write_mask)
             }
+
+            fn role_permissions(&self) -> Option<&[opcua_types::RolePermissionType]> {
+                self.base.role_permissions()
+            }
+
+            fn set_role_permissions(
+                &mut self,
+                role_permissions: Vec<opcua_types::RolePermissionType>,
+            ) {
+                self.base.set_role_permissions(role_permissions)
+            }
+
+            fn access_restrictions(&self) -> Option<opcua_types::AccessRestrictionType> {
+                self.base.access_restrictions()
+            }
+
+            fn set_access_restrictions(
+                &mut self,
+                access_restrictions: opcua_types::AccessRestrictionType,
+            ) {
+                self.base.set_access_restrictions(access_restrictions)
+            }
         }
     };
 }
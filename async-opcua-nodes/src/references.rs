@@ -264,6 +264,34 @@ impl References {
             .unwrap_or_default()
     }
 
+    /// Return an iterator over all references to and from the given node.
+    pub fn node_references<'a: 'b, 'b>(
+        &'a self,
+        node_id: &'b NodeId,
+    ) -> impl Iterator<Item = ReferenceRef<'a>> + 'b {
+        let forward = self
+            .by_source
+            .get(node_id)
+            .into_iter()
+            .flatten()
+            .map(|r| ReferenceRef {
+                reference_type: &r.reference_type,
+                target_node: &r.target_node,
+                direction: ReferenceDirection::Forward,
+            });
+        let inverse = self
+            .by_target
+            .get(node_id)
+            .into_iter()
+            .flatten()
+            .map(|r| ReferenceRef {
+                reference_type: &r.reference_type,
+                target_node: &r.target_node,
+                direction: ReferenceDirection::Inverse,
+            });
+        forward.chain(inverse)
+    }
+
     /// Return an iterator over references matching the given filters.
     pub fn find_references<'a: 'b, 'b>(
         &'a self,
@@ -5,7 +5,8 @@ use std::{
 
 use crate::NamespaceMap;
 use opcua_types::{
-    DataTypeId, NodeClass, NodeId, ObjectTypeId, QualifiedName, ReferenceTypeId, VariableTypeId,
+    custom::ParentIds, DataTypeId, NodeClass, NodeId, ObjectTypeId, QualifiedName, ReferenceTypeId,
+    VariableTypeId,
 };
 
 #[derive(PartialEq, Eq, Hash, Clone)]
@@ -266,6 +267,19 @@ impl DefaultTypeTree {
         &self.namespaces
     }
 
+    /// Get the parent of each data type in the type tree, used to build a
+    /// [`DataTypeTree`](opcua_types::custom::DataTypeTree) for types that
+    /// are not known at compile time.
+    pub fn data_type_parent_ids(&self) -> ParentIds {
+        let mut parent_ids = ParentIds::new();
+        for (id, parent) in &self.subtypes_by_target {
+            if self.nodes.get(id) == Some(&NodeClass::DataType) {
+                parent_ids.add_type(id.clone(), parent.clone());
+            }
+        }
+        parent_ids
+    }
+
     /// Get a vector of all the descendants of the given root node.
     pub fn get_all_children<'a>(&'a self, root: &'a NodeId) -> Vec<&'a NodeId> {
         let mut res = Vec::new();
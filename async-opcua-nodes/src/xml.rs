@@ -6,14 +6,16 @@ use std::{
 
 use hashbrown::HashMap;
 use opcua_types::{
-    Context, DataTypeDefinition, DataValue, DecodingOptions, EnumDefinition, EnumField, Error,
-    LocalizedText, NodeClass, NodeId, QualifiedName, StructureDefinition, StructureField,
+    custom::{DataTypeTree, DynamicTypeLoader, EncodingIds, TypeInfo},
+    AccessRestrictionType, Context, DataTypeDefinition, DataValue, DecodingOptions, EnumDefinition,
+    EnumField, Error, LocalizedText, NodeClass, NodeId, NodeSetNamespaceMapper, PermissionType,
+    QualifiedName, ReferenceTypeId, RolePermissionType, StructureDefinition, StructureField,
     StructureType, TypeLoader, TypeLoaderCollection, Variant,
 };
 use opcua_xml::{
     load_nodeset2_file,
     schema::ua_node_set::{
-        self, ArrayDimensions, ListOfReferences, UADataType, UAMethod, UANodeSet, UAObject,
+        self, ArrayDimensions, ListOfReferences, UADataType, UAMethod, UANode, UANodeSet, UAObject,
         UAObjectType, UAReferenceType, UAVariable, UAVariableType, UAView,
     },
     XmlError,
@@ -21,15 +23,21 @@ use opcua_xml::{
 use regex::Regex;
 use tracing::warn;
 
+mod export;
+
+pub use export::{NodeSet2Export, NodeSet2Model};
+
 use crate::{
-    Base, DataType, EventNotifier, ImportedItem, ImportedReference, Method, NodeSetImport, Object,
-    ObjectType, ReferenceType, Variable, VariableType, View,
+    Base, DataType, EventNotifier, ImportedItem, ImportedReference, Method, NodeBase,
+    NodeSetImport, Object, ObjectType, ReferenceType, Variable, VariableType, View,
 };
 
 /// [`NodeSetImport`] implementation for dynamically loading NodeSet2 files at
 /// runtime. Note that structures must be loaded with a type loader. By default
 /// the type loader for the base types is registered, but if your NodeSet2 file uses custom types
-/// you will have to add an [`TypeLoader`] using [`NodeSet2Import::add_type_loader`].
+/// you will have to add an [`TypeLoader`] using [`NodeSet2Import::add_type_loader`],
+/// or let the server build one from the data types defined in the file, see
+/// [`NodeSetImport::load_data_types`].
 pub struct NodeSet2Import {
     type_loaders: TypeLoaderCollection,
     dependent_namespaces: Vec<String>,
@@ -136,6 +144,116 @@ impl NodeSet2Import {
         self.type_loaders.add(loader);
     }
 
+    fn make_context<'a>(&'a self, namespaces: &'a NodeSetNamespaceMapper) -> Context<'a> {
+        let mut ctx = Context::new(
+            namespaces.namespaces(),
+            &self.type_loaders,
+            DecodingOptions::default(),
+        );
+        ctx.set_aliases(&self.aliases);
+        ctx.set_index_map(namespaces.index_map());
+        ctx
+    }
+
+    fn make_data_type_tree(&self, ctx: &Context<'_>, mut type_tree: DataTypeTree) -> DataTypeTree {
+        // Collect the type hierarchy and the encodings of each data type, these may be
+        // given as references in either direction.
+        let mut encodings: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        let mut encoding_names = HashMap::new();
+        for node in &self.file.nodes {
+            let base = node.base();
+            let Ok(node_id) = self.make_node_id(&base.node_id, ctx) else {
+                continue;
+            };
+            if let UANode::Object(_) = node {
+                if let Ok(name) = self.make_qualified_name(&base.browse_name, ctx) {
+                    encoding_names.insert(node_id.clone(), name);
+                }
+            }
+            for rf in base.references.iter().flat_map(|r| r.references.iter()) {
+                let (Ok(type_id), Ok(target_id)) = (
+                    self.make_node_id(&rf.reference_type, ctx),
+                    self.make_node_id(&rf.node_id, ctx),
+                ) else {
+                    continue;
+                };
+                match (node, rf.is_forward) {
+                    (UANode::DataType(_), false) if type_id == ReferenceTypeId::HasSubtype => {
+                        type_tree
+                            .parent_ids_mut()
+                            .add_type(node_id.clone(), target_id);
+                    }
+                    (UANode::DataType(_), true) if type_id == ReferenceTypeId::HasSubtype => {
+                        type_tree
+                            .parent_ids_mut()
+                            .add_type(target_id, node_id.clone());
+                    }
+                    (UANode::DataType(_), true) if type_id == ReferenceTypeId::HasEncoding => {
+                        encodings
+                            .entry(node_id.clone())
+                            .or_default()
+                            .push(target_id);
+                    }
+                    (UANode::Object(_), false) if type_id == ReferenceTypeId::HasEncoding => {
+                        encodings
+                            .entry(target_id)
+                            .or_default()
+                            .push(node_id.clone());
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        for node in &self.file.nodes {
+            let UANode::DataType(data_type) = node else {
+                continue;
+            };
+            let Some(definition) = &data_type.definition else {
+                continue;
+            };
+            let base = &data_type.base.base;
+            let (node_id, definition, name) = match (
+                self.make_node_id(&base.node_id, ctx),
+                self.make_data_type_def(definition, ctx),
+                self.make_qualified_name(&base.browse_name, ctx),
+            ) {
+                (Ok(id), Ok(def), Ok(name)) => (id, def, name),
+                (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+                    warn!("Failed to load data type {}: {e}", base.node_id.0);
+                    continue;
+                }
+            };
+
+            let encoding_ids = encodings.get(&node_id).map(|ids| {
+                let mut encoding_ids = EncodingIds::default();
+                for id in ids {
+                    match encoding_names.get(id).map(|n| n.name.as_ref()) {
+                        Some("Default Binary") => encoding_ids.binary_id = id.clone(),
+                        Some("Default XML") => encoding_ids.xml_id = id.clone(),
+                        Some("Default JSON") => encoding_ids.json_id = id.clone(),
+                        _ => (),
+                    }
+                }
+                encoding_ids
+            });
+
+            match TypeInfo::from_type_definition(
+                definition,
+                name.name.as_ref().to_owned(),
+                encoding_ids,
+                data_type.base.is_abstract,
+                &node_id,
+                type_tree.parent_ids(),
+            ) {
+                Ok(info) => type_tree.add_type(node_id, info),
+                Err(e) => warn!("Failed to load data type {node_id}: {e}"),
+            }
+        }
+
+        type_tree
+    }
+
     fn select_localized_text(&self, texts: &[ua_node_set::LocalizedText]) -> Option<LocalizedText> {
         let mut selected_str = None;
         for text in texts {
@@ -219,7 +337,7 @@ impl NodeSet2Import {
         def: &ua_node_set::DataTypeDefinition,
         ctx: &Context<'_>,
     ) -> Result<DataTypeDefinition, Error> {
-        let is_enum = def.fields.first().is_some_and(|f| f.value != -1);
+        let is_enum = def.is_option_set || def.fields.first().is_some_and(|f| f.value != -1);
         if is_enum {
             let fields = def
                 .fields
@@ -240,9 +358,11 @@ impl NodeSet2Import {
             }))
         } else {
             let mut any_optional = false;
+            let mut any_subtyped = false;
             let mut fields = Vec::with_capacity(def.fields.len());
             for field in &def.fields {
                 any_optional |= field.is_optional;
+                any_subtyped |= field.allow_sub_types;
                 fields.push(StructureField {
                     name: field.name.clone().into(),
                     description: self
@@ -252,14 +372,20 @@ impl NodeSet2Import {
                     value_rank: field.value_rank.0,
                     array_dimensions: self.make_array_dimensions(&field.array_dimensions)?,
                     max_string_length: field.max_string_length as u32,
-                    is_optional: field.is_optional,
+                    // For structures with subtyped values, `is_optional` means that
+                    // the field allows subtypes of its data type.
+                    is_optional: field.is_optional || field.allow_sub_types,
                 });
             }
             Ok(DataTypeDefinition::Structure(StructureDefinition {
                 default_encoding_id: NodeId::null(),
                 base_data_type: NodeId::null(),
-                structure_type: if def.is_union {
+                structure_type: if def.is_union && any_subtyped {
+                    StructureType::UnionWithSubtypedValues
+                } else if def.is_union {
                     StructureType::Union
+                } else if any_subtyped {
+                    StructureType::StructureWithSubtypedValues
                 } else if any_optional {
                     StructureType::StructureWithOptionalFields
                 } else {
@@ -276,7 +402,7 @@ impl NodeSet2Import {
         base: &ua_node_set::UANodeBase,
         node_class: NodeClass,
     ) -> Result<Base, Error> {
-        Ok(Base::new_full(
+        let mut res = Base::new_full(
             self.make_node_id(&base.node_id, ctx)?,
             node_class,
             self.make_qualified_name(&base.browse_name, ctx)?,
@@ -285,7 +411,26 @@ impl NodeSet2Import {
             self.select_localized_text(&base.description),
             Some(base.write_mask.0),
             Some(base.user_write_mask.0),
-        ))
+        );
+        if let Some(role_permissions) = &base.role_permissions {
+            let role_permissions = role_permissions
+                .role_permissions
+                .iter()
+                .map(|p| {
+                    Ok(RolePermissionType {
+                        role_id: self.make_node_id(&p.node_id, ctx)?,
+                        permissions: PermissionType::from_bits_truncate(p.permissions as i32),
+                    })
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            res.set_role_permissions(role_permissions);
+        }
+        if base.access_restrictions.0 != 0 {
+            res.set_access_restrictions(AccessRestrictionType::from_bits_truncate(
+                base.access_restrictions.0 as i16,
+            ));
+        }
+        Ok(res)
     }
 
     fn make_references(
@@ -486,12 +631,7 @@ impl NodeSetImport for NodeSet2Import {
         &'a self,
         namespaces: &'a opcua_types::NodeSetNamespaceMapper,
     ) -> Box<dyn Iterator<Item = crate::ImportedItem> + 'a> {
-        let mut ctx = Context::new(
-            namespaces.namespaces(),
-            &self.type_loaders,
-            DecodingOptions::default(),
-        );
-        ctx.set_aliases(&self.aliases);
+        let ctx = self.make_context(namespaces);
         Box::new(self.file.nodes.iter().filter_map(move |raw_node| {
             let r = match raw_node {
                 opcua_xml::schema::ua_node_set::UANode::Object(node) => {
@@ -526,18 +666,46 @@ impl NodeSetImport for NodeSet2Import {
             }
         }))
     }
+
+    fn load_data_types(
+        &mut self,
+        namespaces: &NodeSetNamespaceMapper,
+        data_types: DataTypeTree,
+    ) -> Option<Arc<DataTypeTree>> {
+        if !self.file.nodes.iter().any(|n| match n {
+            UANode::DataType(d) => d.definition.is_some(),
+            _ => false,
+        }) {
+            return None;
+        }
+
+        let type_tree =
+            Arc::new(self.make_data_type_tree(&self.make_context(namespaces), data_types));
+        self.type_loaders
+            .add(Arc::new(DynamicTypeLoader::new(type_tree.clone())));
+        Some(type_tree)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{io::Cursor, sync::Arc};
+
     use opcua_types::{
-        DataTypeId, EUInformation, ExtensionObject, LocalizedText, NamespaceMap,
-        NodeSetNamespaceMapper, QualifiedName, Variant,
+        custom::{DataTypeTree, DynamicStructure, DynamicTypeLoader, ParentIds},
+        BinaryDecodable, BinaryEncodable, Context, DataTypeDefinition, DataTypeId, DecodingOptions,
+        EUInformation, EnumDefinition, EnumField, ExtensionObject, LocalizedText, NamespaceMap,
+        NodeId, NodeSetNamespaceMapper, ObjectTypeId, QualifiedName, ReferenceTypeId,
+        StructureDefinition, StructureField, StructureType, TypeLoaderCollection, UAString,
+        VariableTypeId, Variant, VariantScalarTypeId,
     };
 
-    use crate::{NodeBase, NodeSetImport, NodeType};
+    use crate::{
+        DataTypeBuilder, NodeBase, NodeSetImport, NodeType, ObjectBuilder, ObjectTypeBuilder,
+        ReferenceTypeBuilder, References, VariableBuilder,
+    };
 
-    use super::NodeSet2Import;
+    use super::{NodeSet2Export, NodeSet2Import};
 
     const TEST_NODESET: &str = r#"
 <UANodeSet xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:xsd="http://www.w3.org/2001/XMLSchema" LastModified="2023-12-15T00:00:00Z" xmlns="http://opcfoundation.org/UA/2011/03/UANodeSet.xsd">
@@ -626,4 +794,458 @@ mod tests {
             )))
         );
     }
+
+    const DATA_TYPE_NODESET: &str = r#"
+<UANodeSet xmlns="http://opcfoundation.org/UA/2011/03/UANodeSet.xsd">
+  <NamespaceUris>
+    <Uri>http://test.com</Uri>
+  </NamespaceUris>
+  <Aliases>
+    <Alias Alias="HasSubtype">i=45</Alias>
+    <Alias Alias="HasEncoding">i=38</Alias>
+    <Alias Alias="Duration">i=290</Alias>
+  </Aliases>
+  <UADataType NodeId="ns=1;i=1" BrowseName="1:MyStruct">
+    <DisplayName>MyStruct</DisplayName>
+    <References>
+      <Reference ReferenceType="HasSubtype" IsForward="false">i=22</Reference>
+      <Reference ReferenceType="HasEncoding">ns=1;i=3</Reference>
+      <Reference ReferenceType="HasEncoding">ns=1;i=4</Reference>
+    </References>
+    <Definition Name="1:MyStruct">
+      <Field Name="Interval" DataType="Duration" />
+      <Field Name="Kind" DataType="ns=1;i=2" />
+      <Field Name="Names" DataType="i=12" ValueRank="1" />
+    </Definition>
+  </UADataType>
+  <UADataType NodeId="ns=1;i=2" BrowseName="1:MyEnum">
+    <DisplayName>MyEnum</DisplayName>
+    <References>
+      <Reference ReferenceType="HasSubtype" IsForward="false">i=29</Reference>
+    </References>
+    <Definition Name="1:MyEnum">
+      <Field Name="Off" Value="0" />
+      <Field Name="On" Value="1" />
+    </Definition>
+  </UADataType>
+  <UAObject NodeId="ns=1;i=3" BrowseName="Default Binary">
+    <DisplayName>Default Binary</DisplayName>
+  </UAObject>
+  <UAObject NodeId="ns=1;i=4" BrowseName="Default XML">
+    <DisplayName>Default XML</DisplayName>
+  </UAObject>
+  <UAObject NodeId="ns=1;i=5" BrowseName="Default JSON">
+    <DisplayName>Default JSON</DisplayName>
+    <References>
+      <Reference ReferenceType="HasEncoding" IsForward="false">ns=1;i=1</Reference>
+    </References>
+  </UAObject>
+  <UAVariable NodeId="ns=1;i=6" BrowseName="1:MyVariable" DataType="ns=1;i=1">
+    <DisplayName>MyVariable</DisplayName>
+    <Value>
+      <ExtensionObject xmlns="http://opcfoundation.org/UA/2008/02/Types.xsd">
+        <TypeId><Identifier>ns=1;i=4</Identifier></TypeId>
+        <Body>
+          <MyStruct>
+            <Interval>1.5</Interval>
+            <Kind>1</Kind>
+            <Names><String>a</String><String>b</String></Names>
+          </MyStruct>
+        </Body>
+      </ExtensionObject>
+    </Value>
+  </UAVariable>
+</UANodeSet>"#;
+
+    #[test]
+    fn test_load_xml_nodeset_data_types() {
+        let mut import = NodeSet2Import::new_str("en", DATA_TYPE_NODESET, vec![]).unwrap();
+        let mut ns = NamespaceMap::new();
+        ns.add_namespace("http://other.com");
+        let mut map = NodeSetNamespaceMapper::new(&mut ns);
+        import.register_namespaces(&mut map);
+
+        let mut parent_ids = ParentIds::new();
+        parent_ids.add_type(DataTypeId::Duration.into(), DataTypeId::Double.into());
+        let type_tree = import
+            .load_data_types(&map, DataTypeTree::new(parent_ids))
+            .unwrap();
+
+        let struct_id = NodeId::new(2, 1);
+        let typ = type_tree.get_struct_type(&struct_id).unwrap();
+        assert_eq!(typ.name, "MyStruct");
+        assert_eq!(typ.encoding_ids.binary_id, NodeId::new(2, 3));
+        assert_eq!(typ.encoding_ids.xml_id, NodeId::new(2, 4));
+        assert_eq!(typ.encoding_ids.json_id, NodeId::new(2, 5));
+        assert_eq!(typ.fields[0].scalar_type, VariantScalarTypeId::Double);
+        assert_eq!(typ.fields[1].scalar_type, VariantScalarTypeId::Int32);
+        assert!(type_tree.get_type(&NodeId::new(2, 2)).is_some());
+
+        let nodes: Vec<_> = import.load(&map).collect();
+        assert_eq!(nodes.len(), 6);
+        let NodeType::Variable(v) = &nodes[5].node else {
+            panic!("Unexpected node type");
+        };
+        let Some(Variant::ExtensionObject(obj)) = &v.value.value else {
+            panic!("Unexpected value {:?}", v.value.value);
+        };
+        let value = obj.inner_as::<DynamicStructure>().unwrap();
+        assert_eq!(value.get_field(0), Some(&Variant::from(1.5f64)));
+        assert_eq!(value.get_field(1), Some(&Variant::from(1i32)));
+        assert_eq!(
+            value.get_field(2),
+            Some(&Variant::from(vec![
+                UAString::from("a"),
+                UAString::from("b")
+            ]))
+        );
+
+        // The value can be re-encoded and decoded using the loaded types.
+        let mut loaders = TypeLoaderCollection::new();
+        loaders.add(Arc::new(DynamicTypeLoader::new(type_tree.clone())));
+        let ctx = Context::new(map.namespaces(), &loaders, DecodingOptions::test());
+        let mut buf = Vec::new();
+        BinaryEncodable::encode(obj, &mut buf, &ctx).unwrap();
+        let decoded: ExtensionObject =
+            BinaryDecodable::decode(&mut Cursor::new(&buf), &ctx).unwrap();
+        assert_eq!(&decoded, obj);
+    }
+
+    const DEPENDENT_NODESET: &str = r#"
+<UANodeSet xmlns="http://opcfoundation.org/UA/2011/03/UANodeSet.xsd">
+  <NamespaceUris>
+    <Uri>http://dependent.com</Uri>
+  </NamespaceUris>
+  <Aliases>
+    <Alias Alias="HasSubtype">i=45</Alias>
+    <Alias Alias="HasEncoding">i=38</Alias>
+  </Aliases>
+  <UADataType NodeId="ns=2;i=1" BrowseName="2:Wrapper">
+    <DisplayName>Wrapper</DisplayName>
+    <References>
+      <Reference ReferenceType="HasSubtype" IsForward="false">i=22</Reference>
+      <Reference ReferenceType="HasEncoding">ns=2;i=2</Reference>
+    </References>
+    <Definition Name="2:Wrapper">
+      <Field Name="Inner" DataType="ns=1;i=1" />
+      <Field Name="Count" DataType="i=6" />
+    </Definition>
+  </UADataType>
+  <UAObject NodeId="ns=2;i=2" BrowseName="Default XML">
+    <DisplayName>Default XML</DisplayName>
+  </UAObject>
+  <UAVariable NodeId="ns=2;i=3" BrowseName="2:MyVariable" DataType="ns=2;i=1">
+    <DisplayName>MyVariable</DisplayName>
+    <Value>
+      <ExtensionObject xmlns="http://opcfoundation.org/UA/2008/02/Types.xsd">
+        <TypeId><Identifier>ns=2;i=2</Identifier></TypeId>
+        <Body>
+          <Wrapper>
+            <Inner>
+              <Interval>2.5</Interval>
+              <Kind>0</Kind>
+              <Names><String>c</String></Names>
+            </Inner>
+            <Count>3</Count>
+          </Wrapper>
+        </Body>
+      </ExtensionObject>
+    </Value>
+  </UAVariable>
+</UANodeSet>"#;
+
+    #[test]
+    fn test_load_xml_nodeset_dependent_data_types() {
+        let mut ns = NamespaceMap::new();
+        let mut map = NodeSetNamespaceMapper::new(&mut ns);
+        let mut import = NodeSet2Import::new_str("en", DATA_TYPE_NODESET, vec![]).unwrap();
+        import.register_namespaces(&mut map);
+        let mut parent_ids = ParentIds::new();
+        parent_ids.add_type(DataTypeId::Duration.into(), DataTypeId::Double.into());
+        let base_types = import
+            .load_data_types(&map, DataTypeTree::new(parent_ids))
+            .unwrap();
+
+        // Load a node set using the types of the first one, like the server does.
+        let mut dependent =
+            NodeSet2Import::new_str("en", DEPENDENT_NODESET, vec!["http://test.com".to_owned()])
+                .unwrap();
+        dependent.register_namespaces(&mut map);
+        let mut parent_ids = ParentIds::new();
+        parent_ids.add_type(NodeId::new(1, 1), DataTypeId::Structure.into());
+        let mut known_types = DataTypeTree::new(parent_ids);
+        known_types.add_types_from(&base_types);
+        let type_tree = dependent.load_data_types(&map, known_types).unwrap();
+
+        let typ = type_tree.get_struct_type(&NodeId::new(2, 1)).unwrap();
+        assert_eq!(
+            typ.fields[0].scalar_type,
+            VariantScalarTypeId::ExtensionObject
+        );
+
+        let nodes: Vec<_> = dependent.load(&map).collect();
+        let NodeType::Variable(v) = &nodes[2].node else {
+            panic!("Unexpected node type");
+        };
+        let Some(Variant::ExtensionObject(obj)) = &v.value.value else {
+            panic!("Unexpected value {:?}", v.value.value);
+        };
+        let value = obj.inner_as::<DynamicStructure>().unwrap();
+        assert_eq!(value.get_field(1), Some(&Variant::from(3i32)));
+        let Some(Variant::ExtensionObject(inner)) = value.get_field(0) else {
+            panic!("Unexpected field {:?}", value.get_field(0));
+        };
+        let inner = inner.inner_as::<DynamicStructure>().unwrap();
+        assert_eq!(inner.get_field(0), Some(&Variant::from(2.5f64)));
+        assert_eq!(inner.get_field(1), Some(&Variant::from(0i32)));
+    }
+
+    #[test]
+    fn test_export_xml_nodeset() {
+        let mut namespaces = NamespaceMap::new();
+        namespaces.add_namespace("urn:server");
+        let ns = namespaces.add_namespace("http://test.com");
+        let other_ns = namespaces.add_namespace("http://other.com");
+
+        let type_id = NodeId::new(ns, 1);
+        let object_id = NodeId::new(ns, 2);
+        let variable_id = NodeId::new(ns, 3);
+        let struct_id = NodeId::new(ns, 4);
+        let enum_id = NodeId::new(ns, 5);
+        let ref_type_id = NodeId::new(ns, "MyRef");
+        let unit_id = NodeId::new(ns, 7);
+        let other_id = NodeId::new(other_ns, 1);
+        let subtyped_id = NodeId::new(ns, 8);
+        let option_set_id = NodeId::new(ns, 9);
+
+        let nodes: Vec<NodeType> = vec![
+            ObjectTypeBuilder::new(&type_id, QualifiedName::new(ns, "MyType"), "MyType")
+                .is_abstract(true)
+                .build()
+                .into(),
+            ObjectBuilder::new(&object_id, QualifiedName::new(ns, "MyObject"), "MyObject")
+                .description(LocalizedText::new("en", "My description"))
+                .build()
+                .into(),
+            VariableBuilder::new(&variable_id, "Values", "Values")
+                .data_type(DataTypeId::Int32)
+                .value_rank(1)
+                .array_dimensions(&[3])
+                .value(vec![1, 2, 3])
+                .build()
+                .into(),
+            DataTypeBuilder::new(&struct_id, QualifiedName::new(ns, "MyStruct"), "MyStruct")
+                .data_type_definition(DataTypeDefinition::Structure(StructureDefinition {
+                    default_encoding_id: NodeId::null(),
+                    base_data_type: NodeId::null(),
+                    structure_type: StructureType::Structure,
+                    fields: Some(vec![StructureField {
+                        name: "Field".into(),
+                        data_type: DataTypeId::Double.into(),
+                        value_rank: -1,
+                        ..Default::default()
+                    }]),
+                }))
+                .build()
+                .into(),
+            DataTypeBuilder::new(&enum_id, QualifiedName::new(ns, "MyEnum"), "MyEnum")
+                .data_type_definition(DataTypeDefinition::Enum(EnumDefinition {
+                    fields: Some(vec![EnumField {
+                        value: 1,
+                        display_name: "One".into(),
+                        description: LocalizedText::null(),
+                        name: "One".into(),
+                    }]),
+                }))
+                .build()
+                .into(),
+            VariableBuilder::new(&unit_id, QualifiedName::new(ns, "Unit"), "Unit")
+                .data_type(DataTypeId::EUInformation)
+                .value(ExtensionObject::from_message(EUInformation {
+                    namespace_uri: "http://unit-namespace.namespace".into(),
+                    unit_id: 15,
+                    display_name: LocalizedText::new("en", "Degrees Celsius"),
+                    description: LocalizedText::null(),
+                }))
+                .build()
+                .into(),
+            DataTypeBuilder::new(
+                &subtyped_id,
+                QualifiedName::new(ns, "MySubtypedUnion"),
+                "MySubtypedUnion",
+            )
+            .data_type_definition(DataTypeDefinition::Structure(StructureDefinition {
+                default_encoding_id: NodeId::null(),
+                base_data_type: NodeId::null(),
+                structure_type: StructureType::UnionWithSubtypedValues,
+                fields: Some(vec![StructureField {
+                    name: "Field".into(),
+                    data_type: DataTypeId::Structure.into(),
+                    value_rank: -1,
+                    is_optional: true,
+                    ..Default::default()
+                }]),
+            }))
+            .build()
+            .into(),
+            DataTypeBuilder::new(
+                &option_set_id,
+                QualifiedName::new(ns, "MyOptionSet"),
+                "MyOptionSet",
+            )
+            .data_type_definition(DataTypeDefinition::Enum(EnumDefinition {
+                fields: Some(vec![EnumField {
+                    value: 0,
+                    display_name: "Bit0".into(),
+                    description: LocalizedText::null(),
+                    name: "Bit0".into(),
+                }]),
+            }))
+            .build()
+            .into(),
+            ReferenceTypeBuilder::new(&ref_type_id, QualifiedName::new(ns, "MyRef"), "MyRef")
+                .inverse_name("MyRefInverse")
+                .build()
+                .into(),
+        ];
+        let mut references = References::new();
+        references.insert_reference(
+            &ObjectTypeId::BaseObjectType.into(),
+            &type_id,
+            ReferenceTypeId::HasSubtype,
+        );
+        references.insert_reference(&object_id, &type_id, ReferenceTypeId::HasTypeDefinition);
+        references.insert_reference(&object_id, &variable_id, ReferenceTypeId::HasProperty);
+        references.insert_reference(
+            &variable_id,
+            &VariableTypeId::PropertyType.into(),
+            ReferenceTypeId::HasTypeDefinition,
+        );
+        references.insert_reference(&object_id, &other_id, ref_type_id.clone());
+        references.insert_reference(
+            &DataTypeId::UInt32.into(),
+            &option_set_id,
+            ReferenceTypeId::HasSubtype,
+        );
+
+        let export = NodeSet2Export::new(vec!["http://test.com".to_owned()]);
+        let xml = export
+            .write_to_string(&namespaces, &nodes, &references)
+            .unwrap();
+        assert!(xml.contains(r#"<Alias Alias="HasTypeDefinition">i=40</Alias>"#));
+        assert!(xml.contains(r#"<Field Name="Field" DataType="Double"/>"#));
+        assert!(xml.contains(r#"<RequiredModel ModelUri="http://opcfoundation.org/UA/"/>"#));
+        assert!(xml.contains(r#"<Definition Name="1:MySubtypedUnion" IsUnion="true">"#));
+        assert!(xml.contains(r#"<Field Name="Field" DataType="Structure" AllowSubTypes="true"/>"#));
+        assert!(xml.contains(r#"<Definition Name="1:MyOptionSet" IsOptionSet="true">"#));
+        assert!(xml.contains(r#"<Definition Name="1:MyEnum">"#));
+
+        // Import into a server where the namespaces have different indices.
+        let import = NodeSet2Import::new_str("en", &xml, vec![]).unwrap();
+        assert_eq!(
+            import.get_own_namespaces(),
+            vec!["http://test.com".to_owned(), "http://other.com".to_owned()]
+        );
+        let mut new_namespaces = NamespaceMap::new();
+        let mut map = NodeSetNamespaceMapper::new(&mut new_namespaces);
+        import.register_namespaces(&mut map);
+        let imported: Vec<_> = import.load(&map).collect();
+        assert_eq!(imported.len(), nodes.len());
+
+        let map_id = |id: &NodeId| match id.namespace {
+            n if n == ns => NodeId::new(1, id.identifier.clone()),
+            n if n == other_ns => NodeId::new(2, id.identifier.clone()),
+            _ => id.clone(),
+        };
+        for (original, item) in nodes.iter().zip(&imported) {
+            let (original, node) = (original.as_node(), item.node.as_node());
+            assert_eq!(node.node_id(), &map_id(original.node_id()));
+            assert_eq!(node.node_class(), original.node_class());
+            assert_eq!(node.display_name().text, original.display_name().text);
+            assert_eq!(
+                node.description().map(|d| &d.text),
+                original.description().map(|d| &d.text)
+            );
+            assert_eq!(node.browse_name().name, original.browse_name().name);
+            let mut expected: Vec<_> = references
+                .node_references(original.node_id())
+                .map(|r| {
+                    (
+                        map_id(r.target_node),
+                        map_id(r.reference_type),
+                        r.direction == crate::ReferenceDirection::Forward,
+                    )
+                })
+                .collect();
+            let mut found: Vec<_> = item
+                .references
+                .iter()
+                .map(|r| (r.target_id.clone(), r.type_id.clone(), r.is_forward))
+                .collect();
+            expected.sort_by_key(|r| format!("{r:?}"));
+            found.sort_by_key(|r| format!("{r:?}"));
+            assert_eq!(expected, found);
+        }
+
+        let NodeType::Variable(v) = &imported[2].node else {
+            panic!("Unexpected node type");
+        };
+        assert_eq!(v.value.value, Some(Variant::from(vec![1, 2, 3])));
+        assert_eq!(v.array_dimensions(), Some(vec![3]));
+        assert_eq!(v.browse_name(), &QualifiedName::new(0, "Values"));
+        let NodeType::ObjectType(t) = &imported[0].node else {
+            panic!("Unexpected node type");
+        };
+        assert!(t.is_abstract());
+        let NodeType::DataType(d) = &imported[3].node else {
+            panic!("Unexpected node type");
+        };
+        let Some(DataTypeDefinition::Structure(s)) = d.data_type_definition() else {
+            panic!("Expected structure definition");
+        };
+        assert_eq!(s.fields.as_ref().unwrap()[0].data_type, DataTypeId::Double);
+        let NodeType::DataType(d) = &imported[4].node else {
+            panic!("Unexpected node type");
+        };
+        let Some(DataTypeDefinition::Enum(e)) = d.data_type_definition() else {
+            panic!("Expected enum definition");
+        };
+        assert_eq!(e.fields.as_ref().unwrap()[0].value, 1);
+        let NodeType::Variable(v) = &imported[5].node else {
+            panic!("Unexpected node type");
+        };
+        assert_eq!(
+            v.value.value,
+            Some(Variant::ExtensionObject(ExtensionObject::from_message(
+                EUInformation {
+                    namespace_uri: "http://unit-namespace.namespace".into(),
+                    unit_id: 15,
+                    display_name: LocalizedText::new("en", "Degrees Celsius"),
+                    description: LocalizedText::null()
+                }
+            )))
+        );
+        let NodeType::ReferenceType(r) = &imported[8].node else {
+            panic!("Unexpected node type");
+        };
+        assert_eq!(
+            r.inverse_name(),
+            Some(LocalizedText::new("", "MyRefInverse"))
+        );
+        let NodeType::DataType(d) = &imported[6].node else {
+            panic!("Unexpected node type");
+        };
+        let Some(DataTypeDefinition::Structure(s)) = d.data_type_definition() else {
+            panic!("Expected structure definition");
+        };
+        assert_eq!(s.structure_type, StructureType::UnionWithSubtypedValues);
+        assert!(s.fields.as_ref().unwrap()[0].is_optional);
+        let NodeType::DataType(d) = &imported[7].node else {
+            panic!("Unexpected node type");
+        };
+        let Some(DataTypeDefinition::Enum(e)) = d.data_type_definition() else {
+            panic!("Expected enum definition");
+        };
+        assert_eq!(e.fields.as_ref().unwrap()[0].value, 0);
+    }
 }
@@ -0,0 +1,737 @@
+use std::io::Write;
+
+use hashbrown::{HashMap, HashSet};
+use opcua_types::{
+    xml::XmlEncodable, DataTypeDefinition, DataTypeId, DateTime, DecodingOptions, Error,
+    Identifier, LocalizedText, NamespaceMap, NodeId, QualifiedName, ReferenceTypeId, StructureType,
+    TypeLoaderCollection, Variant,
+};
+use opcua_xml::{
+    events::{BytesDecl, BytesStart, Event},
+    XmlStreamWriter,
+};
+
+use crate::{HasNodeId, NodeType, ReferenceDirection, References};
+
+const BASE_NAMESPACE: &str = "http://opcfoundation.org/UA/";
+const UA_NODE_SET_NAMESPACE: &str = "http://opcfoundation.org/UA/2011/03/UANodeSet.xsd";
+const UA_TYPES_NAMESPACE: &str = "http://opcfoundation.org/UA/2008/02/Types.xsd";
+
+#[derive(Debug, Clone)]
+/// Description of a model written to the `Models` table of a NodeSet2 file.
+pub struct NodeSet2Model {
+    /// Model URI.
+    pub model_uri: String,
+    /// Model version.
+    pub version: Option<String>,
+    /// Model publication date.
+    pub publication_date: Option<DateTime>,
+    /// Models required by this model.
+    pub required_models: Vec<NodeSet2Model>,
+}
+
+impl NodeSet2Model {
+    /// Create a new model description with the given model URI.
+    pub fn new(model_uri: impl Into<String>) -> Self {
+        Self {
+            model_uri: model_uri.into(),
+            version: None,
+            publication_date: None,
+            required_models: Vec::new(),
+        }
+    }
+
+    /// Set the model version.
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Set the model publication date.
+    pub fn publication_date(mut self, publication_date: DateTime) -> Self {
+        self.publication_date = Some(publication_date);
+        self
+    }
+
+    /// Add a model required by this model.
+    pub fn required_model(mut self, model: NodeSet2Model) -> Self {
+        self.required_models.push(model);
+        self
+    }
+}
+
+/// Exporter writing nodes to a `NodeSet2.xml` file, for example the nodes owned by
+/// a node manager. The result can be loaded again with [`super::NodeSet2Import`].
+///
+/// Only nodes in the exported namespaces are written. Any other namespace referenced
+/// by exported nodes, through references, browse names or data types, is added to
+/// the `NamespaceUris` table after the exported namespaces.
+///
+/// # Example
+///
+/// ```ignore
+/// let export = NodeSet2Export::new(vec!["http://my.namespace.uri".to_owned()]);
+/// let address_space = node_manager.address_space().read();
+/// let mut file = std::fs::File::create("MyNodeSet.NodeSet2.xml")?;
+/// export.write(
+///     &mut file,
+///     server.type_tree().read().namespaces(),
+///     address_space.nodes(),
+///     address_space.references(),
+/// )?;
+/// ```
+pub struct NodeSet2Export {
+    namespaces: Vec<String>,
+    models: Vec<NodeSet2Model>,
+    last_modified: Option<DateTime>,
+}
+
+impl NodeSet2Export {
+    /// Create a new NodeSet2 exporter, writing nodes in `namespaces`.
+    pub fn new(namespaces: Vec<String>) -> Self {
+        Self {
+            namespaces,
+            models: Vec::new(),
+            last_modified: None,
+        }
+    }
+
+    /// Add a model to the `Models` table. If no models are added, a model is
+    /// written for each exported namespace, requiring the base namespace and
+    /// any other referenced namespaces.
+    pub fn add_model(&mut self, model: NodeSet2Model) {
+        self.models.push(model);
+    }
+
+    /// Set the `LastModified` timestamp of the node set.
+    pub fn set_last_modified(&mut self, last_modified: DateTime) {
+        self.last_modified = Some(last_modified);
+    }
+
+    /// Write `nodes` to a `NodeSet2.xml` document. `namespaces` is the namespace
+    /// map of the server the nodes were taken from, and `references` contains
+    /// the references of the nodes.
+    pub fn write<'a>(
+        &self,
+        writer: &mut dyn Write,
+        namespaces: &NamespaceMap,
+        nodes: impl IntoIterator<Item = &'a NodeType>,
+        references: &References,
+    ) -> Result<(), Error> {
+        let mut own_indices = HashSet::new();
+        for ns in &self.namespaces {
+            let idx = namespaces.get_index(ns).ok_or_else(|| {
+                Error::encoding(format!("Namespace {ns} is not in the namespace map"))
+            })?;
+            own_indices.insert(idx);
+        }
+
+        let mut nodes: Vec<_> = nodes
+            .into_iter()
+            .filter(|n| own_indices.contains(&n.node_id().namespace))
+            .collect();
+        nodes.sort_by_key(|n| sort_key(n.node_id()));
+
+        let uris: HashMap<_, _> = namespaces
+            .known_namespaces()
+            .iter()
+            .map(|(uri, idx)| (*idx, uri.as_str()))
+            .collect();
+        let mut namespace_uris: Vec<&str> = self.namespaces.iter().map(|s| s.as_str()).collect();
+        let mut used = Vec::new();
+        for node in &nodes {
+            collect_namespaces(node, references, &mut used);
+        }
+        for idx in used {
+            if idx == 0 || own_indices.contains(&idx) {
+                continue;
+            }
+            let uri = uris.get(&idx).ok_or_else(|| {
+                Error::encoding(format!("Namespace index {idx} is not in the namespace map"))
+            })?;
+            if !namespace_uris.contains(uri) {
+                namespace_uris.push(uri);
+            }
+        }
+        // Map from index in the node set to index in the server.
+        let index_map: HashMap<u16, u16> = namespace_uris
+            .iter()
+            .enumerate()
+            .filter_map(|(i, uri)| Some((i as u16 + 1, namespaces.get_index(uri)?)))
+            .collect();
+
+        let aliases = collect_aliases(&nodes, references);
+
+        let loaders = TypeLoaderCollection::new_empty();
+        let mut ctx = opcua_types::Context::new(namespaces, &loaders, DecodingOptions::default());
+        ctx.set_index_map(&index_map);
+
+        let mut writer = NodeSetWriter {
+            writer: XmlStreamWriter::new_with_indent(writer, b' ', 2),
+            ctx,
+            aliases: aliases
+                .iter()
+                .map(|(alias, id)| (id.clone(), alias.as_str()))
+                .collect(),
+        };
+        writer.write_node_set(self, &namespace_uris, &aliases, &nodes, references)
+    }
+
+    /// Write `nodes` to a `NodeSet2.xml` document and return it as a string.
+    /// See [`NodeSet2Export::write`].
+    pub fn write_to_string<'a>(
+        &self,
+        namespaces: &NamespaceMap,
+        nodes: impl IntoIterator<Item = &'a NodeType>,
+        references: &References,
+    ) -> Result<String, Error> {
+        let mut buf = Vec::new();
+        self.write(&mut buf, namespaces, nodes, references)?;
+        String::from_utf8(buf).map_err(Error::encoding)
+    }
+}
+
+fn sort_key(node_id: &NodeId) -> (u16, u8, u32, String) {
+    match &node_id.identifier {
+        Identifier::Numeric(i) => (node_id.namespace, 0, *i, String::new()),
+        r => (node_id.namespace, 1, 0, r.to_string()),
+    }
+}
+
+fn push_namespace(used: &mut Vec<u16>, idx: u16) {
+    if !used.contains(&idx) {
+        used.push(idx);
+    }
+}
+
+/// Collect the namespaces referenced by `node` in the order they are found.
+fn collect_namespaces(node: &NodeType, references: &References, used: &mut Vec<u16>) {
+    push_namespace(used, node.node_id().namespace);
+    push_namespace(used, node.as_node().browse_name().namespace_index);
+    for rf in references.node_references(node.node_id()) {
+        push_namespace(used, rf.reference_type.namespace);
+        push_namespace(used, rf.target_node.namespace);
+    }
+    match node {
+        NodeType::Variable(v) => push_namespace(used, v.data_type.namespace),
+        NodeType::VariableType(v) => push_namespace(used, v.data_type().namespace),
+        NodeType::DataType(d) => {
+            if let Some(DataTypeDefinition::Structure(s)) = d.data_type_definition() {
+                for field in s.fields.iter().flatten() {
+                    push_namespace(used, field.data_type.namespace);
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+fn alias_name(node_id: &NodeId) -> Option<String> {
+    if node_id.namespace != 0 {
+        return None;
+    }
+    let Identifier::Numeric(id) = node_id.identifier else {
+        return None;
+    };
+    if let Ok(r) = ReferenceTypeId::try_from(id) {
+        return Some(format!("{r:?}"));
+    }
+    DataTypeId::try_from(id).ok().map(|d| format!("{d:?}"))
+}
+
+/// Create aliases for the reference types and data types in the base namespace
+/// used by `nodes`, sorted by node ID.
+fn collect_aliases(nodes: &[&NodeType], references: &References) -> Vec<(String, NodeId)> {
+    let mut ids = HashSet::new();
+    for node in nodes {
+        for rf in references.node_references(node.node_id()) {
+            ids.insert(rf.reference_type);
+        }
+        match node {
+            NodeType::Variable(v) => {
+                ids.insert(&v.data_type);
+            }
+            NodeType::VariableType(v) => {
+                ids.insert(v.data_type());
+            }
+            NodeType::DataType(d) => {
+                if let Some(DataTypeDefinition::Structure(s)) = d.data_type_definition() {
+                    ids.extend(s.fields.iter().flatten().map(|f| &f.data_type));
+                }
+            }
+            _ => (),
+        }
+    }
+    let mut ids: Vec<_> = ids.into_iter().collect();
+    ids.sort_by_key(|id| sort_key(id));
+    ids.into_iter()
+        .filter_map(|id| Some((alias_name(id)?, id.clone())))
+        .collect()
+}
+
+struct NodeSetWriter<'a, 'b> {
+    writer: XmlStreamWriter<&'a mut dyn Write>,
+    ctx: opcua_types::Context<'b>,
+    aliases: HashMap<NodeId, &'b str>,
+}
+
+impl NodeSetWriter<'_, '_> {
+    fn write_node_set(
+        &mut self,
+        export: &NodeSet2Export,
+        namespace_uris: &[&str],
+        aliases: &[(String, NodeId)],
+        nodes: &[&NodeType],
+        references: &References,
+    ) -> Result<(), Error> {
+        self.writer
+            .write_event(Event::Decl(BytesDecl::new("1.0", Some("utf-8"), None)))?;
+        let mut attrs = vec![
+            (
+                "xmlns:xsi",
+                "http://www.w3.org/2001/XMLSchema-instance".to_owned(),
+            ),
+            ("xmlns:xsd", "http://www.w3.org/2001/XMLSchema".to_owned()),
+            ("xmlns", UA_NODE_SET_NAMESPACE.to_owned()),
+        ];
+        if let Some(last_modified) = &export.last_modified {
+            attrs.push(("LastModified", last_modified.to_rfc3339()));
+        }
+        self.start("UANodeSet", &attrs)?;
+
+        self.writer.write_start("NamespaceUris")?;
+        for uri in namespace_uris {
+            self.text_element("Uri", &[], uri)?;
+        }
+        self.writer.write_end("NamespaceUris")?;
+
+        self.writer.write_start("Models")?;
+        if export.models.is_empty() {
+            let required: Vec<_> = std::iter::once(BASE_NAMESPACE)
+                .chain(
+                    namespace_uris
+                        .iter()
+                        .filter(|uri| !export.namespaces.iter().any(|ns| ns == *uri))
+                        .copied(),
+                )
+                .map(NodeSet2Model::new)
+                .collect();
+            for ns in &export.namespaces {
+                self.write_model(
+                    "Model",
+                    &NodeSet2Model {
+                        required_models: required.clone(),
+                        ..NodeSet2Model::new(ns.clone())
+                    },
+                )?;
+            }
+        } else {
+            for model in &export.models {
+                self.write_model("Model", model)?;
+            }
+        }
+        self.writer.write_end("Models")?;
+
+        if !aliases.is_empty() {
+            self.writer.write_start("Aliases")?;
+            for (alias, id) in aliases {
+                self.text_element("Alias", &[("Alias", alias.clone())], &id.to_string())?;
+            }
+            self.writer.write_end("Aliases")?;
+        }
+
+        for node in nodes {
+            self.write_node(node, references)?;
+        }
+
+        self.writer.write_end("UANodeSet")?;
+        Ok(())
+    }
+
+    fn start(&mut self, tag: &str, attrs: &[(&str, String)]) -> Result<(), Error> {
+        self.writer.write_event(Event::Start(element(tag, attrs)))?;
+        Ok(())
+    }
+
+    fn text_element(
+        &mut self,
+        tag: &str,
+        attrs: &[(&str, String)],
+        text: &str,
+    ) -> Result<(), Error> {
+        self.start(tag, attrs)?;
+        self.writer.write_text(text)?;
+        self.writer.write_end(tag)?;
+        Ok(())
+    }
+
+    fn write_model(&mut self, tag: &str, model: &NodeSet2Model) -> Result<(), Error> {
+        let mut attrs = vec![("ModelUri", model.model_uri.clone())];
+        if let Some(version) = &model.version {
+            attrs.push(("Version", version.clone()));
+        }
+        if let Some(publication_date) = &model.publication_date {
+            attrs.push(("PublicationDate", publication_date.to_rfc3339()));
+        }
+        if model.required_models.is_empty() {
+            self.writer
+                .write_event(Event::Empty(element(tag, &attrs)))?;
+            return Ok(());
+        }
+        self.start(tag, &attrs)?;
+        for required in &model.required_models {
+            self.write_model("RequiredModel", required)?;
+        }
+        self.writer.write_end(tag)?;
+        Ok(())
+    }
+
+    fn node_id(&self, node_id: &NodeId) -> Result<String, Error> {
+        let namespace = self
+            .ctx
+            .resolve_namespace_index_inverse(node_id.namespace)?;
+        Ok(NodeId::new(namespace, node_id.identifier.clone()).to_string())
+    }
+
+    /// Format a node ID, using an alias if one is defined.
+    fn aliased_node_id(&self, node_id: &NodeId) -> Result<String, Error> {
+        match self.aliases.get(node_id) {
+            Some(alias) => Ok((*alias).to_owned()),
+            None => self.node_id(node_id),
+        }
+    }
+
+    fn qualified_name(&self, name: &QualifiedName) -> Result<String, Error> {
+        let namespace = self
+            .ctx
+            .resolve_namespace_index_inverse(name.namespace_index)?;
+        if namespace == 0 {
+            Ok(name.name.to_string())
+        } else {
+            Ok(format!("{namespace}:{}", name.name))
+        }
+    }
+
+    fn localized_text(&mut self, tag: &str, text: &LocalizedText) -> Result<(), Error> {
+        let attrs = if text.locale.is_null() || text.locale.is_empty() {
+            Vec::new()
+        } else {
+            vec![("Locale", text.locale.to_string())]
+        };
+        self.text_element(tag, &attrs, text.text.as_ref())
+    }
+
+    fn write_node(&mut self, node: &NodeType, references: &References) -> Result<(), Error> {
+        let n = node.as_node();
+        let mut attrs = vec![
+            ("NodeId", self.node_id(n.node_id())?),
+            ("BrowseName", self.qualified_name(n.browse_name())?),
+        ];
+        if let Some(mask) = n.write_mask().filter(|m| !m.is_empty()) {
+            attrs.push(("WriteMask", mask.bits().to_string()));
+        }
+        if let Some(mask) = n.user_write_mask().filter(|m| !m.is_empty()) {
+            attrs.push(("UserWriteMask", mask.bits().to_string()));
+        }
+        if let Some(restrictions) = n.access_restrictions().filter(|r| !r.is_empty()) {
+            attrs.push(("AccessRestrictions", restrictions.bits().to_string()));
+        }
+
+        let tag = match node {
+            NodeType::Object(o) => {
+                if !o.event_notifier().is_empty() {
+                    attrs.push(("EventNotifier", o.event_notifier().bits().to_string()));
+                }
+                "UAObject"
+            }
+            NodeType::Variable(v) => {
+                attrs.push(("DataType", self.aliased_node_id(&v.data_type)?));
+                if v.value_rank != -1 {
+                    attrs.push(("ValueRank", v.value_rank.to_string()));
+                }
+                if let Some(dims) = &v.array_dimensions {
+                    attrs.push(("ArrayDimensions", array_dimensions(dims)));
+                }
+                if v.access_level != 1 {
+                    attrs.push(("AccessLevel", v.access_level.to_string()));
+                }
+                if v.user_access_level != 1 {
+                    attrs.push(("UserAccessLevel", v.user_access_level.to_string()));
+                }
+                if let Some(interval) = v.minimum_sampling_interval.filter(|i| *i != 0.0) {
+                    attrs.push(("MinimumSamplingInterval", interval.to_string()));
+                }
+                if v.historizing {
+                    attrs.push(("Historizing", "true".to_owned()));
+                }
+                "UAVariable"
+            }
+            NodeType::Method(m) => {
+                if m.executable() {
+                    attrs.push(("Executable", "true".to_owned()));
+                }
+                if m.user_executable() {
+                    attrs.push(("UserExecutable", "true".to_owned()));
+                }
+                "UAMethod"
+            }
+            NodeType::View(v) => {
+                if v.contains_no_loops() {
+                    attrs.push(("ContainsNoLoops", "true".to_owned()));
+                }
+                attrs.push(("EventNotifier", v.event_notifier().bits().to_string()));
+                "UAView"
+            }
+            NodeType::ObjectType(o) => {
+                if o.is_abstract() {
+                    attrs.push(("IsAbstract", "true".to_owned()));
+                }
+                "UAObjectType"
+            }
+            NodeType::VariableType(v) => {
+                if v.is_abstract() {
+                    attrs.push(("IsAbstract", "true".to_owned()));
+                }
+                attrs.push(("DataType", self.aliased_node_id(v.data_type())?));
+                if v.value_rank() != -1 {
+                    attrs.push(("ValueRank", v.value_rank().to_string()));
+                }
+                if let Some(dims) = v.array_dimensions() {
+                    attrs.push(("ArrayDimensions", array_dimensions(&dims)));
+                }
+                "UAVariableType"
+            }
+            NodeType::DataType(d) => {
+                if d.is_abstract() {
+                    attrs.push(("IsAbstract", "true".to_owned()));
+                }
+                "UADataType"
+            }
+            NodeType::ReferenceType(r) => {
+                if r.is_abstract() {
+                    attrs.push(("IsAbstract", "true".to_owned()));
+                }
+                if r.symmetric() {
+                    attrs.push(("Symmetric", "true".to_owned()));
+                }
+                "UAReferenceType"
+            }
+        };
+        self.start(tag, &attrs)?;
+
+        self.localized_text("DisplayName", n.display_name())?;
+        if let Some(description) = n.description() {
+            self.localized_text("Description", description)?;
+        }
+        self.write_references(n.node_id(), references)?;
+        if let Some(role_permissions) = n.role_permissions() {
+            self.writer.write_start("RolePermissions")?;
+            for permission in role_permissions {
+                self.text_element(
+                    "RolePermission",
+                    &[("Permissions", permission.permissions.bits().to_string())],
+                    &self.node_id(&permission.role_id)?,
+                )?;
+            }
+            self.writer.write_end("RolePermissions")?;
+        }
+
+        match node {
+            NodeType::Variable(v) => self.write_value(v.value.value.as_ref())?,
+            NodeType::VariableType(v) => {
+                self.write_value(v.value().and_then(|v| v.value.as_ref()))?
+            }
+            NodeType::DataType(d) => {
+                if let Some(definition) = d.data_type_definition() {
+                    let is_option_set = is_option_set(n.node_id(), references);
+                    self.write_definition(n.browse_name(), definition, is_option_set)?;
+                }
+            }
+            NodeType::ReferenceType(r) => {
+                if let Some(inverse_name) = r.inverse_name() {
+                    self.localized_text("InverseName", &inverse_name)?;
+                }
+            }
+            _ => (),
+        }
+
+        self.writer.write_end(tag)?;
+        Ok(())
+    }
+
+    fn write_references(&mut self, node_id: &NodeId, references: &References) -> Result<(), Error> {
+        let mut refs: Vec<_> = references.node_references(node_id).collect();
+        if refs.is_empty() {
+            return Ok(());
+        }
+        refs.sort_by_key(|r| {
+            (
+                sort_key(r.reference_type),
+                r.direction == ReferenceDirection::Forward,
+                sort_key(r.target_node),
+            )
+        });
+        self.writer.write_start("References")?;
+        for rf in refs {
+            let mut attrs = vec![("ReferenceType", self.aliased_node_id(rf.reference_type)?)];
+            if rf.direction == ReferenceDirection::Inverse {
+                attrs.push(("IsForward", "false".to_owned()));
+            }
+            let target = self.node_id(rf.target_node)?;
+            self.text_element("Reference", &attrs, &target)?;
+        }
+        self.writer.write_end("References")?;
+        Ok(())
+    }
+
+    fn write_value(&mut self, value: Option<&Variant>) -> Result<(), Error> {
+        let Some(value) = value.filter(|v| !v.is_empty()) else {
+            return Ok(());
+        };
+        // The value is encoded separately, so that the UA types namespace
+        // can be declared on its root element.
+        let mut buf = Vec::new();
+        {
+            let mut value_writer = XmlStreamWriter::new(&mut buf as &mut dyn Write);
+            value.encode(&mut value_writer, &self.ctx)?;
+        }
+        let Some(name_end) = buf
+            .iter()
+            .skip(1)
+            .position(|c| matches!(c, b' ' | b'>' | b'/'))
+        else {
+            return Ok(());
+        };
+        let xmlns = format!(" xmlns=\"{UA_TYPES_NAMESPACE}\"");
+        buf.splice(name_end + 1..name_end + 1, xmlns.bytes());
+
+        self.writer.write_start("Value")?;
+        self.writer.write_raw(&buf)?;
+        self.writer.write_end("Value")?;
+        Ok(())
+    }
+
+    fn write_definition(
+        &mut self,
+        name: &QualifiedName,
+        definition: &DataTypeDefinition,
+        is_option_set: bool,
+    ) -> Result<(), Error> {
+        let mut attrs = vec![("Name", self.qualified_name(name)?)];
+        match definition {
+            DataTypeDefinition::Structure(s) => {
+                if matches!(
+                    s.structure_type,
+                    StructureType::Union | StructureType::UnionWithSubtypedValues
+                ) {
+                    attrs.push(("IsUnion", "true".to_owned()));
+                }
+                // For structures with subtyped values, `is_optional` means that
+                // the field allows subtypes of its data type.
+                let optional_attr = if matches!(
+                    s.structure_type,
+                    StructureType::StructureWithSubtypedValues
+                        | StructureType::UnionWithSubtypedValues
+                ) {
+                    "AllowSubTypes"
+                } else {
+                    "IsOptional"
+                };
+                self.start("Definition", &attrs)?;
+                for field in s.fields.iter().flatten() {
+                    let mut attrs = vec![
+                        ("Name", field.name.to_string()),
+                        ("DataType", self.aliased_node_id(&field.data_type)?),
+                    ];
+                    if field.value_rank != -1 {
+                        attrs.push(("ValueRank", field.value_rank.to_string()));
+                    }
+                    if let Some(dims) = &field.array_dimensions {
+                        attrs.push(("ArrayDimensions", array_dimensions(dims)));
+                    }
+                    if field.max_string_length != 0 {
+                        attrs.push(("MaxStringLength", field.max_string_length.to_string()));
+                    }
+                    if field.is_optional {
+                        attrs.push((optional_attr, "true".to_owned()));
+                    }
+                    if field.description.text.is_empty() {
+                        self.writer
+                            .write_event(Event::Empty(element("Field", &attrs)))?;
+                    } else {
+                        self.start("Field", &attrs)?;
+                        self.localized_text("Description", &field.description)?;
+                        self.writer.write_end("Field")?;
+                    }
+                }
+            }
+            DataTypeDefinition::Enum(e) => {
+                if is_option_set {
+                    attrs.push(("IsOptionSet", "true".to_owned()));
+                }
+                self.start("Definition", &attrs)?;
+                for field in e.fields.iter().flatten() {
+                    let attrs = [
+                        ("Name", field.name.to_string()),
+                        ("Value", field.value.to_string()),
+                    ];
+                    self.start("Field", &attrs)?;
+                    if !field.display_name.text.is_empty() {
+                        self.localized_text("DisplayName", &field.display_name)?;
+                    }
+                    if !field.description.text.is_empty() {
+                        self.localized_text("Description", &field.description)?;
+                    }
+                    self.writer.write_end("Field")?;
+                }
+            }
+        }
+        self.writer.write_end("Definition")?;
+        Ok(())
+    }
+}
+
+/// Check whether the data type `node_id` is an option set, meaning that it is a
+/// subtype of `OptionSet` or of an unsigned integer type, following the `HasSubtype`
+/// references in `references`.
+fn is_option_set(node_id: &NodeId, references: &References) -> bool {
+    let option_set_bases = [
+        DataTypeId::OptionSet,
+        DataTypeId::UInteger,
+        DataTypeId::Byte,
+        DataTypeId::UInt16,
+        DataTypeId::UInt32,
+        DataTypeId::UInt64,
+    ];
+    let mut visited = HashSet::new();
+    let mut current = node_id;
+    while visited.insert(current) {
+        let Some(parent) = references.node_references(current).find_map(|r| {
+            (r.direction == ReferenceDirection::Inverse
+                && *r.reference_type == ReferenceTypeId::HasSubtype)
+                .then_some(r.target_node)
+        }) else {
+            return false;
+        };
+        if option_set_bases.iter().any(|b| parent == b) {
+            return true;
+        }
+        current = parent;
+    }
+    false
+}
+
+fn element<'a>(tag: &'a str, attrs: &[(&str, String)]) -> BytesStart<'a> {
+    let mut elem = BytesStart::new(tag);
+    for (key, value) in attrs {
+        elem.push_attribute((*key, value.as_str()));
+    }
+    elem
+}
+
+fn array_dimensions(dims: &[u32]) -> String {
+    dims.iter()
+        .map(|d| d.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
@@ -0,0 +1,924 @@
+//! Instantiation of object and variable types, creating the children
+//! declared on the type and its supertypes.
+
+use hashbrown::{HashMap, HashSet};
+use opcua_nodes::{
+    Base, EventNotifier, Method, NodeBase, NodeType, Object, ReferenceRef, TypeTree, Variable,
+};
+use opcua_types::{
+    BrowseDirection, DataEncoding, DataValue, Guid, Identifier, LocalizedText, NodeClass, NodeId,
+    NumericRange, ObjectId, QualifiedName, ReferenceTypeId, StatusCode, TimestampsToReturn,
+};
+
+use super::{AccessLevel, AddressSpace};
+
+/// Function generating the node ID of a new child from the node ID of
+/// its parent and its browse name.
+pub type InstanceNodeIdFn = dyn FnMut(&NodeId, &QualifiedName) -> NodeId + Send;
+
+/// Strategy for generating the node IDs of the children created when
+/// instantiating a type.
+pub enum InstanceNodeIds {
+    /// Create random `Guid` node IDs in the given namespace.
+    Guid(u16),
+    /// Create string node IDs from the node ID of the parent and the browse name
+    /// of the child, separated by `.`, in the namespace of the parent.
+    /// For example, the child `Status` of `ns=2;s=Device1` is given the ID `ns=2;s=Device1.Status`.
+    BrowsePath,
+    /// Create node IDs with a custom function, called with the node ID of the parent
+    /// and the browse name of the new child.
+    Custom(Box<InstanceNodeIdFn>),
+}
+
+impl InstanceNodeIds {
+    fn next(&mut self, parent: &NodeId, browse_name: &QualifiedName) -> NodeId {
+        match self {
+            InstanceNodeIds::Guid(namespace) => NodeId::new(*namespace, Guid::new()),
+            InstanceNodeIds::BrowsePath => {
+                let parent_id = match &parent.identifier {
+                    Identifier::Numeric(v) => v.to_string(),
+                    Identifier::String(v) => v.as_ref().to_owned(),
+                    Identifier::Guid(v) => v.to_string(),
+                    Identifier::ByteString(v) => v.as_base64(),
+                };
+                NodeId::new(
+                    parent.namespace,
+                    format!("{}.{}", parent_id, browse_name.name),
+                )
+            }
+            InstanceNodeIds::Custom(f) => f(parent, browse_name),
+        }
+    }
+}
+
+/// Builder for an instance of an `ObjectType` or `VariableType`.
+///
+/// Inserting the instance creates an object or variable with the given node ID,
+/// along with every child of the type and its supertypes with the `Mandatory`
+/// modelling rule, and optionally those with the `Optional` modelling rule.
+/// Children are created recursively, and references between the children of a type are
+/// copied to the new nodes.
+///
+/// Children with a placeholder modelling rule, or a browse name like `<Name>`, are only
+/// created if they are given a name with [InstanceBuilder::placeholder].
+///
+/// Type definitions and instance declarations are read from the address space the
+/// instance is inserted into. [InMemoryNodeManager::instantiate] also reads them from the
+/// other in-memory node managers on the server, such as the one for the core namespace.
+/// Inserting the instance fails with `BadTypeDefinitionInvalid` if the type or one of
+/// its supertypes cannot be found, or if the type contains an instance of itself.
+///
+/// [InMemoryNodeManager::instantiate]: crate::node_manager::memory::InMemoryNodeManager::instantiate
+pub struct InstanceBuilder {
+    node_id: NodeId,
+    browse_name: QualifiedName,
+    display_name: LocalizedText,
+    type_definition: NodeId,
+    parent: Option<(NodeId, NodeId)>,
+    include_optional: bool,
+    node_ids: InstanceNodeIds,
+    placeholders: Vec<(Vec<QualifiedName>, QualifiedName)>,
+}
+
+impl InstanceBuilder {
+    /// Create a new instance builder for an instance of `type_definition`,
+    /// which must be an `ObjectType` or a `VariableType`.
+    ///
+    /// Child node IDs are generated from their browse path by default,
+    /// see [InstanceNodeIds::BrowsePath].
+    pub fn new(
+        node_id: &NodeId,
+        browse_name: impl Into<QualifiedName>,
+        display_name: impl Into<LocalizedText>,
+        type_definition: impl Into<NodeId>,
+    ) -> Self {
+        Self {
+            node_id: node_id.clone(),
+            browse_name: browse_name.into(),
+            display_name: display_name.into(),
+            type_definition: type_definition.into(),
+            parent: None,
+            include_optional: false,
+            node_ids: InstanceNodeIds::BrowsePath,
+            placeholders: Vec::new(),
+        }
+    }
+
+    /// Add a reference of type `reference_type` from `parent` to the new instance.
+    pub fn parent(mut self, parent: impl Into<NodeId>, reference_type: impl Into<NodeId>) -> Self {
+        self.parent = Some((parent.into(), reference_type.into()));
+        self
+    }
+
+    /// Add the instance as a component of `parent`.
+    pub fn component_of(self, parent: impl Into<NodeId>) -> Self {
+        self.parent(parent, ReferenceTypeId::HasComponent)
+    }
+
+    /// Add the instance to the folder `parent`.
+    pub fn organized_by(self, parent: impl Into<NodeId>) -> Self {
+        self.parent(parent, ReferenceTypeId::Organizes)
+    }
+
+    /// Also create children with the `Optional` modelling rule.
+    pub fn include_optional(mut self, include_optional: bool) -> Self {
+        self.include_optional = include_optional;
+        self
+    }
+
+    /// Set the strategy for generating the node IDs of the children of the instance.
+    pub fn node_ids(mut self, node_ids: InstanceNodeIds) -> Self {
+        self.node_ids = node_ids;
+        self
+    }
+
+    /// Create an instance of the placeholder given by `path` with browse name `browse_name`.
+    /// `path` is the browse path from the new instance to the placeholder, for example
+    /// `["<VendorCapability>"]`. This can be called multiple times for the same
+    /// placeholder to create several instances of it.
+    pub fn placeholder(
+        mut self,
+        path: &[QualifiedName],
+        browse_name: impl Into<QualifiedName>,
+    ) -> Self {
+        self.placeholders.push((path.to_vec(), browse_name.into()));
+        self
+    }
+
+    /// Insert the instance into the address space. The returned list contains
+    /// the IDs of every created node, starting with the instance itself.
+    ///
+    /// No nodes are created if this fails.
+    pub fn insert(
+        self,
+        address_space: &mut AddressSpace,
+        type_tree: &dyn TypeTree,
+    ) -> Result<Vec<NodeId>, StatusCode> {
+        let plan = self.plan(&[&*address_space], type_tree)?;
+        plan.insert(address_space)
+    }
+
+    /// Plan the nodes and references to create without modifying the address space.
+    /// Type definitions and instance declarations are read from `sources`, the first of
+    /// which must be the address space the instance will be inserted into.
+    pub(crate) fn plan(
+        mut self,
+        sources: &[&AddressSpace],
+        type_tree: &dyn TypeTree,
+    ) -> Result<InstancePlan, StatusCode> {
+        let root = self.make_root(sources, type_tree)?;
+        let mut planner = Planner {
+            sources,
+            type_tree,
+            include_optional: self.include_optional,
+            node_ids: &mut self.node_ids,
+            placeholders: &self.placeholders,
+            nodes: vec![root],
+            node_id_set: HashSet::from([self.node_id.clone()]),
+            references: Vec::new(),
+            deferred: Vec::new(),
+            scopes: Vec::new(),
+            visiting: Vec::new(),
+        };
+        if let Some((parent, reference_type)) = &self.parent {
+            planner
+                .references
+                .push((parent.clone(), self.node_id.clone(), reference_type.clone()));
+        }
+        planner.references.push((
+            self.node_id.clone(),
+            self.type_definition.clone(),
+            ReferenceTypeId::HasTypeDefinition.into(),
+        ));
+        planner.instantiate_children(&self.node_id, &[], None, Some(&self.type_definition))?;
+        Ok(planner.finish())
+    }
+
+    fn make_root(
+        &self,
+        sources: &[&AddressSpace],
+        type_tree: &dyn TypeTree,
+    ) -> Result<NodeType, StatusCode> {
+        let address_space = sources[0];
+        if !address_space
+            .namespaces()
+            .contains_key(&self.node_id.namespace)
+        {
+            return Err(StatusCode::BadNodeIdRejected);
+        }
+        if address_space.node_exists(&self.node_id) {
+            return Err(StatusCode::BadNodeIdExists);
+        }
+
+        let type_node = sources
+            .iter()
+            .find_map(|a| a.find_node(&self.type_definition));
+        match (type_tree.get(&self.type_definition), type_node) {
+            (Some(NodeClass::ObjectType), Some(NodeType::ObjectType(t))) if t.is_abstract() => {
+                Err(StatusCode::BadTypeDefinitionInvalid)
+            }
+            (Some(NodeClass::ObjectType), Some(NodeType::ObjectType(_))) => Ok(Object::new(
+                &self.node_id,
+                self.browse_name.clone(),
+                self.display_name.clone(),
+                EventNotifier::empty(),
+            )
+            .into()),
+            (Some(NodeClass::VariableType), Some(NodeType::VariableType(t))) => {
+                if t.is_abstract() {
+                    return Err(StatusCode::BadTypeDefinitionInvalid);
+                }
+                Ok(Variable::new_full(
+                    self.variable_base(),
+                    t.data_type().clone(),
+                    false,
+                    t.value_rank(),
+                    DataValue::value_only(
+                        t.value().and_then(|v| v.value.clone()).unwrap_or_default(),
+                    ),
+                    AccessLevel::CURRENT_READ.bits(),
+                    AccessLevel::CURRENT_READ.bits(),
+                    t.array_dimensions(),
+                    None,
+                )
+                .into())
+            }
+            _ => Err(StatusCode::BadTypeDefinitionInvalid),
+        }
+    }
+
+    fn variable_base(&self) -> Base {
+        Base::new(
+            NodeClass::Variable,
+            &self.node_id,
+            self.browse_name.clone(),
+            self.display_name.clone(),
+        )
+    }
+}
+
+/// A child of a type or instance declaration that may be instantiated.
+struct Declaration {
+    node_id: NodeId,
+    browse_name: QualifiedName,
+    reference_type: NodeId,
+    modelling_rule: NodeId,
+    scope: usize,
+}
+
+/// The nodes and references to create for an instance.
+pub(crate) struct InstancePlan {
+    nodes: Vec<NodeType>,
+    references: Vec<(NodeId, NodeId, NodeId)>,
+}
+
+impl InstancePlan {
+    /// Insert the planned nodes and references into the address space, returning the
+    /// IDs of the created nodes. Fails without creating anything if a node already exists.
+    pub(crate) fn insert(
+        self,
+        address_space: &mut AddressSpace,
+    ) -> Result<Vec<NodeId>, StatusCode> {
+        // The address space may have been modified since the instance was planned.
+        if self
+            .nodes
+            .iter()
+            .any(|n| address_space.node_exists(n.as_node().node_id()))
+        {
+            return Err(StatusCode::BadNodeIdExists);
+        }
+        let ids: Vec<_> = self
+            .nodes
+            .iter()
+            .map(|n| n.as_node().node_id().clone())
+            .collect();
+        for node in self.nodes {
+            address_space.insert::<_, NodeId>(node, None);
+        }
+        for (source, target, reference_type) in self.references {
+            address_space.insert_reference(&source, &target, reference_type);
+        }
+        Ok(ids)
+    }
+}
+
+/// Collects the nodes and references to create before modifying the address space,
+/// so that nothing is created if instantiation fails.
+struct Planner<'a> {
+    /// Address spaces to read types and declarations from, starting with the target.
+    sources: &'a [&'a AddressSpace],
+    type_tree: &'a dyn TypeTree,
+    include_optional: bool,
+    node_ids: &'a mut InstanceNodeIds,
+    placeholders: &'a [(Vec<QualifiedName>, QualifiedName)],
+    nodes: Vec<NodeType>,
+    node_id_set: HashSet<NodeId>,
+    references: Vec<(NodeId, NodeId, NodeId)>,
+    /// References from new nodes to declarations, which are resolved to the
+    /// instance of the declaration in the same scope once all nodes are created.
+    deferred: Vec<(NodeId, NodeId, NodeId, usize)>,
+    /// Map from declaration to instance, one for each instantiated type.
+    scopes: Vec<HashMap<NodeId, NodeId>>,
+    /// Types and declarations currently being instantiated, used to detect
+    /// types that contain an instance of themselves.
+    visiting: Vec<NodeId>,
+}
+
+impl<'a> Planner<'a> {
+    /// The address space the instance is inserted into.
+    fn target(&self) -> &'a AddressSpace {
+        self.sources[0]
+    }
+
+    fn find_node(&self, node_id: &NodeId) -> Option<&'a NodeType> {
+        self.sources.iter().find_map(|a| a.find_node(node_id))
+    }
+
+    /// Find forward references from `node_id` in every source address space.
+    fn find_references<'b>(
+        &self,
+        node_id: &'b NodeId,
+        filter: Option<(NodeId, bool)>,
+    ) -> impl Iterator<Item = ReferenceRef<'a>> + 'b
+    where
+        'a: 'b,
+    {
+        let type_tree = self.type_tree;
+        self.sources.iter().flat_map(move |a| {
+            a.find_references(node_id, filter.clone(), type_tree, BrowseDirection::Forward)
+        })
+    }
+
+    fn modelling_rule(&self, node_id: &NodeId) -> Option<NodeId> {
+        self.find_references(
+            node_id,
+            Some((ReferenceTypeId::HasModellingRule.into(), false)),
+        )
+        .next()
+        .map(|r| r.target_node.clone())
+    }
+
+    /// Add the children of `source` to `declarations`, unless a child with the
+    /// same browse name is already present.
+    fn collect_declarations(
+        &self,
+        source: &NodeId,
+        scope: usize,
+        declarations: &mut Vec<Declaration>,
+    ) {
+        for r in self.find_references(
+            source,
+            Some((ReferenceTypeId::HierarchicalReferences.into(), true)),
+        ) {
+            let Some(modelling_rule) = self.modelling_rule(r.target_node) else {
+                continue;
+            };
+            let Some(node) = self.find_node(r.target_node) else {
+                continue;
+            };
+            let browse_name = node.as_node().browse_name();
+            if declarations.iter().any(|d| &d.browse_name == browse_name) {
+                continue;
+            }
+            declarations.push(Declaration {
+                node_id: r.target_node.clone(),
+                browse_name: browse_name.clone(),
+                reference_type: r.reference_type.clone(),
+                modelling_rule,
+                scope,
+            });
+        }
+    }
+
+    /// Create the children of `parent`, from the instance declaration `declaration` it
+    /// was created from, if any, and from `type_definition` and its supertypes.
+    fn instantiate_children(
+        &mut self,
+        parent: &NodeId,
+        path: &[QualifiedName],
+        declaration: Option<(&NodeId, usize)>,
+        type_definition: Option<&NodeId>,
+    ) -> Result<(), StatusCode> {
+        let visiting = self.visiting.len();
+        for id in declaration
+            .map(|(d, _)| d)
+            .into_iter()
+            .chain(type_definition)
+        {
+            if self.visiting.contains(id) {
+                return Err(StatusCode::BadTypeDefinitionInvalid);
+            }
+            self.visiting.push(id.clone());
+        }
+
+        // Children declared directly on the instance declaration override those on its type.
+        let mut declarations = Vec::new();
+        if let Some((declaration, scope)) = declaration {
+            self.collect_declarations(declaration, scope, &mut declarations);
+        }
+        if let Some(type_definition) = type_definition {
+            let scope = self.scopes.len();
+            self.scopes.push(HashMap::new());
+            let mut current = Some(type_definition);
+            while let Some(type_id) = current {
+                // Without the type node we cannot know which children to create.
+                if self.find_node(type_id).is_none() {
+                    return Err(StatusCode::BadTypeDefinitionInvalid);
+                }
+                self.collect_declarations(type_id, scope, &mut declarations);
+                current = self.type_tree.get_supertype(type_id);
+            }
+        }
+
+        for declaration in declarations {
+            for browse_name in self.instance_names(path, &declaration) {
+                self.instantiate_declaration(parent, path, &declaration, browse_name)?;
+            }
+        }
+        self.visiting.truncate(visiting);
+        Ok(())
+    }
+
+    /// Get the browse names of the instances of `declaration` to create.
+    fn instance_names(
+        &self,
+        path: &[QualifiedName],
+        declaration: &Declaration,
+    ) -> Vec<(QualifiedName, bool)> {
+        let name = declaration.browse_name.name.as_ref();
+        let rule = &declaration.modelling_rule;
+        let is_placeholder = (name.starts_with('<') && name.ends_with('>'))
+            || rule == &ObjectId::ModellingRule_MandatoryPlaceholder
+            || rule == &ObjectId::ModellingRule_OptionalPlaceholder;
+
+        if is_placeholder {
+            self.placeholders
+                .iter()
+                .filter(|(p, _)| {
+                    p.len() == path.len() + 1
+                        && p.starts_with(path)
+                        && p.last() == Some(&declaration.browse_name)
+                })
+                .map(|(_, name)| (name.clone(), true))
+                .collect()
+        } else if rule == &ObjectId::ModellingRule_Mandatory
+            || self.include_optional
+                && (rule == &ObjectId::ModellingRule_Optional
+                    || rule == &ObjectId::ModellingRule_ExposesItsArray)
+        {
+            vec![(declaration.browse_name.clone(), false)]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn instantiate_declaration(
+        &mut self,
+        parent: &NodeId,
+        path: &[QualifiedName],
+        declaration: &Declaration,
+        (browse_name, is_placeholder): (QualifiedName, bool),
+    ) -> Result<(), StatusCode> {
+        let Some(source) = self.find_node(&declaration.node_id) else {
+            return Ok(());
+        };
+        let node_id = self.node_ids.next(parent, &browse_name);
+        if self.target().node_exists(&node_id) || self.node_id_set.contains(&node_id) {
+            return Err(StatusCode::BadNodeIdExists);
+        }
+        if !self.target().namespaces().contains_key(&node_id.namespace) {
+            return Err(StatusCode::BadNodeIdRejected);
+        }
+        let display_name = if is_placeholder {
+            LocalizedText::new("", browse_name.name.as_ref())
+        } else {
+            source.as_node().display_name().clone()
+        };
+        let Some(node) = copy_node(source, &node_id, browse_name.clone(), display_name) else {
+            return Ok(());
+        };
+
+        self.nodes.push(node);
+        self.node_id_set.insert(node_id.clone());
+        self.scopes[declaration.scope].insert(declaration.node_id.clone(), node_id.clone());
+        self.references.push((
+            parent.clone(),
+            node_id.clone(),
+            declaration.reference_type.clone(),
+        ));
+
+        let mut type_definition = None;
+        for r in self.find_references(&declaration.node_id, None) {
+            if r.reference_type == &ReferenceTypeId::HasModellingRule {
+                continue;
+            }
+            if r.reference_type == &ReferenceTypeId::HasTypeDefinition {
+                type_definition = Some(r.target_node.clone());
+                self.references.push((
+                    node_id.clone(),
+                    r.target_node.clone(),
+                    r.reference_type.clone(),
+                ));
+                continue;
+            }
+            // Children are created when instantiating the declaration.
+            if self.type_tree.is_subtype_of(
+                r.reference_type,
+                &ReferenceTypeId::HierarchicalReferences.into(),
+            ) && self.modelling_rule(r.target_node).is_some()
+            {
+                continue;
+            }
+            self.deferred.push((
+                node_id.clone(),
+                r.target_node.clone(),
+                r.reference_type.clone(),
+                declaration.scope,
+            ));
+        }
+
+        let mut path = path.to_vec();
+        path.push(browse_name);
+        self.instantiate_children(
+            &node_id,
+            &path,
+            Some((&declaration.node_id, declaration.scope)),
+            type_definition.as_ref(),
+        )
+    }
+
+    /// Resolve references between declarations, and return the nodes and references to create.
+    fn finish(mut self) -> InstancePlan {
+        for (source, target, reference_type, scope) in std::mem::take(&mut self.deferred) {
+            if let Some(instance) = self.scopes[scope].get(&target) {
+                self.references
+                    .push((source, instance.clone(), reference_type));
+            } else if self.modelling_rule(&target).is_none() {
+                // References to nodes that are not declarations are copied as is,
+                // references to declarations that were not instantiated are dropped.
+                self.references.push((source, target, reference_type));
+            }
+        }
+        InstancePlan {
+            nodes: self.nodes,
+            references: self.references,
+        }
+    }
+}
+
+/// Copy an instance declaration, giving it a new node ID and browse name.
+/// Returns `None` if the node is not an object, variable or method.
+fn copy_node(
+    source: &NodeType,
+    node_id: &NodeId,
+    browse_name: QualifiedName,
+    display_name: LocalizedText,
+) -> Option<NodeType> {
+    let node = source.as_node();
+    let mut base = Base::new_full(
+        node_id.clone(),
+        source.node_class(),
+        browse_name,
+        display_name,
+        node.description().cloned(),
+        node.write_mask().map(|m| m.bits()),
+        node.user_write_mask().map(|m| m.bits()),
+    );
+    if let Some(role_permissions) = node.role_permissions() {
+        base.set_role_permissions(role_permissions.to_vec());
+    }
+    if let Some(access_restrictions) = node.access_restrictions() {
+        base.set_access_restrictions(access_restrictions);
+    }
+
+    Some(match source {
+        NodeType::Object(o) => Object::new_full(base, o.event_notifier()).into(),
+        NodeType::Variable(v) => Variable::new_full(
+            base,
+            v.data_type(),
+            v.historizing(),
+            v.value_rank(),
+            v.value(
+                TimestampsToReturn::Both,
+                &NumericRange::None,
+                &DataEncoding::Binary,
+                0.0,
+            ),
+            v.access_level().bits(),
+            v.user_access_level().bits(),
+            v.array_dimensions(),
+            v.minimum_sampling_interval(),
+        )
+        .into(),
+        NodeType::Method(m) => Method::new_full(base, m.executable(), m.user_executable()).into(),
+        _ => return None,
+    })
+}
+
+impl AddressSpace {
+    /// Insert an instance of an `ObjectType` or `VariableType`, creating its mandatory
+    /// children. See [InstanceBuilder] for details.
+    pub fn instantiate(
+        &mut self,
+        type_tree: &dyn TypeTree,
+        instance: InstanceBuilder,
+    ) -> Result<Vec<NodeId>, StatusCode> {
+        instance.insert(self, type_tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::address_space::{
+        AddressSpace, CoreNamespace, NodeBase, NodeType, ObjectBuilder, ObjectTypeBuilder,
+        VariableBuilder,
+    };
+    use opcua_nodes::{DefaultTypeTree, NamespaceMap};
+    use opcua_types::{
+        BrowseDirection, DataEncoding, DataTypeId, NodeId, NumericRange, ObjectId, ObjectTypeId,
+        QualifiedName, ReferenceTypeId, StatusCode, TimestampsToReturn, VariableTypeId,
+    };
+
+    use super::{InstanceBuilder, InstanceNodeIds};
+
+    fn make_address_space() -> (AddressSpace, DefaultTypeTree) {
+        let mut address_space = AddressSpace::new();
+        address_space.add_namespace("http://opcfoundation.org/UA/", 0);
+        let mut namespaces = NamespaceMap::default();
+        address_space.import_node_set(&CoreNamespace, &mut namespaces);
+        address_space.add_namespace("urn:test", 1);
+        let mut type_tree = DefaultTypeTree::new();
+        address_space.load_into_type_tree(&mut type_tree);
+        (address_space, type_tree)
+    }
+
+    fn children(
+        address_space: &AddressSpace,
+        type_tree: &DefaultTypeTree,
+        node_id: &NodeId,
+    ) -> Vec<String> {
+        address_space
+            .find_references(
+                node_id,
+                Some((ReferenceTypeId::HierarchicalReferences, true)),
+                type_tree,
+                BrowseDirection::Forward,
+            )
+            .filter_map(|r| address_space.find_node(r.target_node))
+            .map(|n| n.as_node().browse_name().name.as_ref().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn instantiate_file_type() {
+        let (mut address_space, type_tree) = make_address_space();
+        let id = NodeId::new(1, "File");
+        let ids = InstanceBuilder::new(&id, "File", "File", ObjectTypeId::FileType)
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut address_space, &type_tree)
+            .unwrap();
+        assert_eq!(ids[0], id);
+
+        let mut names = children(&address_space, &type_tree, &id);
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "Close",
+                "GetPosition",
+                "Open",
+                "OpenCount",
+                "Read",
+                "SetPosition",
+                "Size",
+                "UserWritable",
+                "Writable",
+                "Write"
+            ]
+        );
+        let open = NodeId::new(1, "File.Open");
+        assert!(matches!(
+            address_space.find_node(&open),
+            Some(NodeType::Method(_))
+        ));
+        let args = NodeId::new(1, "File.Open.InputArguments");
+        let Some(NodeType::Variable(v)) = address_space.find_node(&args) else {
+            panic!("Missing input arguments");
+        };
+        assert_eq!(v.data_type(), DataTypeId::Argument);
+        assert!(address_space.has_reference(&open, &args, ReferenceTypeId::HasProperty));
+        assert!(address_space.has_reference(
+            &args,
+            &VariableTypeId::PropertyType.into(),
+            ReferenceTypeId::HasTypeDefinition
+        ));
+        assert!(address_space.has_reference(
+            &id,
+            &ObjectTypeId::FileType.into(),
+            ReferenceTypeId::HasTypeDefinition
+        ));
+        assert!(address_space.has_reference(
+            &ObjectId::ObjectsFolder.into(),
+            &id,
+            ReferenceTypeId::Organizes
+        ));
+        // Instances do not have modelling rules.
+        assert!(address_space
+            .find_references(
+                &args,
+                Some((ReferenceTypeId::HasModellingRule, false)),
+                &type_tree,
+                BrowseDirection::Forward,
+            )
+            .next()
+            .is_none());
+        assert_eq!(ids.len(), names.len() + 1 + 9);
+
+        let id = NodeId::new(1, "File2");
+        InstanceBuilder::new(&id, "File2", "File2", ObjectTypeId::FileType)
+            .include_optional(true)
+            .node_ids(InstanceNodeIds::Guid(1))
+            .insert(&mut address_space, &type_tree)
+            .unwrap();
+        let names = children(&address_space, &type_tree, &id);
+        assert!(names.iter().any(|n| n == "MimeType"));
+        assert!(names.iter().any(|n| n == "MaxByteStringLength"));
+    }
+
+    #[test]
+    fn instantiate_custom_type() {
+        let (mut address_space, type_tree) = make_address_space();
+        let mut type_tree = type_tree;
+
+        // BaseDeviceType has a mandatory Status variable and a placeholder,
+        // DeviceType adds a Mode variable that is referenced from Status,
+        // and overrides the Status variable with a new default value.
+        let base_id = NodeId::new(1, "BaseDeviceType");
+        ObjectTypeBuilder::new(&base_id, "BaseDeviceType", "BaseDeviceType")
+            .subtype_of(ObjectTypeId::BaseObjectType)
+            .insert(&mut address_space);
+        VariableBuilder::new(&NodeId::new(1, "BaseDeviceType.Status"), "Status", "Status")
+            .value(0)
+            .data_type(DataTypeId::Int32)
+            .component_of(base_id.clone())
+            .has_type_definition(VariableTypeId::BaseDataVariableType)
+            .has_modelling_rule(ObjectId::ModellingRule_Mandatory)
+            .insert(&mut address_space);
+        let placeholder_id = NodeId::new(1, "BaseDeviceType.Sensor");
+        ObjectBuilder::new(
+            &placeholder_id,
+            QualifiedName::new(1, "<Sensor>"),
+            "<Sensor>",
+        )
+        .component_of(base_id.clone())
+        .has_type_definition(ObjectTypeId::FolderType)
+        .insert(&mut address_space);
+        address_space.insert_reference(
+            &placeholder_id,
+            &ObjectId::ModellingRule_OptionalPlaceholder.into(),
+            ReferenceTypeId::HasModellingRule,
+        );
+
+        let type_id = NodeId::new(1, "DeviceType");
+        ObjectTypeBuilder::new(&type_id, "DeviceType", "DeviceType")
+            .subtype_of(base_id.clone())
+            .insert(&mut address_space);
+        let status_id = NodeId::new(1, "DeviceType.Status");
+        let mode_id = NodeId::new(1, "DeviceType.Mode");
+        VariableBuilder::new(&status_id, "Status", "Status")
+            .value(5)
+            .data_type(DataTypeId::Int32)
+            .component_of(type_id.clone())
+            .has_type_definition(VariableTypeId::BaseDataVariableType)
+            .has_modelling_rule(ObjectId::ModellingRule_Mandatory)
+            .insert(&mut address_space);
+        VariableBuilder::new(&mode_id, "Mode", "Mode")
+            .value(1)
+            .data_type(DataTypeId::Int32)
+            .property_of(type_id.clone())
+            .has_type_definition(VariableTypeId::PropertyType)
+            .has_modelling_rule(ObjectId::ModellingRule_Mandatory)
+            .insert(&mut address_space);
+        address_space.insert_reference(&status_id, &mode_id, ReferenceTypeId::HasCause);
+        address_space.load_into_type_tree(&mut type_tree);
+
+        let id = NodeId::new(1, "Device");
+        let ids = InstanceBuilder::new(&id, "Device", "Device", type_id.clone())
+            .organized_by(ObjectId::ObjectsFolder)
+            .placeholder(
+                &[QualifiedName::new(1, "<Sensor>")],
+                QualifiedName::new(1, "S1"),
+            )
+            .placeholder(
+                &[QualifiedName::new(1, "<Sensor>")],
+                QualifiedName::new(1, "S2"),
+            )
+            .insert(&mut address_space, &type_tree)
+            .unwrap();
+        assert_eq!(ids.len(), 5);
+
+        let mut names = children(&address_space, &type_tree, &id);
+        names.sort();
+        assert_eq!(names, vec!["Mode", "S1", "S2", "Status"]);
+
+        let new_status = NodeId::new(1, "Device.Status");
+        let Some(NodeType::Variable(v)) = address_space.find_node(&new_status) else {
+            panic!("Missing status");
+        };
+        assert_eq!(
+            v.value(
+                TimestampsToReturn::Neither,
+                &NumericRange::None,
+                &DataEncoding::Binary,
+                0.0
+            )
+            .value,
+            Some(5.into())
+        );
+        assert!(address_space.has_reference(
+            &new_status,
+            &NodeId::new(1, "Device.Mode"),
+            ReferenceTypeId::HasCause
+        ));
+        let Some(NodeType::Object(o)) = address_space.find_node(&NodeId::new(1, "Device.S1"))
+        else {
+            panic!("Missing placeholder instance");
+        };
+        assert_eq!(o.display_name().text.as_ref(), "S1");
+
+        // Failing to create the instance leaves the address space unchanged.
+        let id = NodeId::new(1, "Device2");
+        address_space.add_folder(
+            &NodeId::new(1, "Device2.Status"),
+            "Status",
+            "Status",
+            &ObjectId::ObjectsFolder.into(),
+        );
+        let err = InstanceBuilder::new(&id, "Device2", "Device2", type_id)
+            .insert(&mut address_space, &type_tree)
+            .unwrap_err();
+        assert_eq!(err, StatusCode::BadNodeIdExists);
+        assert!(!address_space.node_exists(&id));
+
+        let err = InstanceBuilder::new(&id, "Device2", "Device2", ObjectTypeId::BaseEventType)
+            .insert(&mut address_space, &type_tree)
+            .unwrap_err();
+        assert_eq!(err, StatusCode::BadTypeDefinitionInvalid);
+    }
+
+    #[test]
+    fn instantiate_from_other_address_space() {
+        let (core, type_tree) = make_address_space();
+        let mut address_space = AddressSpace::new();
+        address_space.add_namespace("urn:test", 1);
+
+        let id = NodeId::new(1, "File");
+        let plan = InstanceBuilder::new(&id, "File", "File", ObjectTypeId::FileType)
+            .plan(&[&address_space, &core], &type_tree)
+            .unwrap();
+        let ids = plan.insert(&mut address_space).unwrap();
+        assert!(ids.contains(&NodeId::new(1, "File.Open.InputArguments")));
+        assert!(address_space.node_exists(&NodeId::new(1, "File.Size")));
+        assert!(!core.node_exists(&id));
+
+        // Without the core namespace the type and its children are unknown.
+        let err = InstanceBuilder::new(
+            &NodeId::new(1, "File2"),
+            "File2",
+            "File2",
+            ObjectTypeId::FileType,
+        )
+        .insert(&mut address_space, &type_tree)
+        .unwrap_err();
+        assert_eq!(err, StatusCode::BadTypeDefinitionInvalid);
+    }
+
+    #[test]
+    fn instantiate_recursive_type() {
+        let (mut address_space, mut type_tree) = make_address_space();
+
+        // LoopType has a mandatory child of type LoopType.
+        let type_id = NodeId::new(1, "LoopType");
+        ObjectTypeBuilder::new(&type_id, "LoopType", "LoopType")
+            .subtype_of(ObjectTypeId::BaseObjectType)
+            .insert(&mut address_space);
+        let child_id = NodeId::new(1, "LoopType.Child");
+        ObjectBuilder::new(&child_id, "Child", "Child")
+            .component_of(type_id.clone())
+            .has_type_definition(type_id.clone())
+            .insert(&mut address_space);
+        address_space.insert_reference(
+            &child_id,
+            &ObjectId::ModellingRule_Mandatory.into(),
+            ReferenceTypeId::HasModellingRule,
+        );
+        address_space.load_into_type_tree(&mut type_tree);
+
+        let id = NodeId::new(1, "Loop");
+        let err = InstanceBuilder::new(&id, "Loop", "Loop", type_id)
+            .insert(&mut address_space, &type_tree)
+            .unwrap_err();
+        assert_eq!(err, StatusCode::BadTypeDefinitionInvalid);
+        assert!(!address_space.node_exists(&id));
+    }
+}
@@ -1,22 +1,28 @@
 //! Implementation of [AddressSpace], and in-memory OPC-UA address space.
 
+mod instantiate;
+mod model_change;
 mod utils;
 
+pub use instantiate::{InstanceBuilder, InstanceNodeIdFn, InstanceNodeIds};
+pub use model_change::ModelChanges;
 pub use opcua_nodes::*;
 pub use utils::*;
 
 #[cfg(feature = "generated-address-space")]
 pub use opcua_core_namespace::CoreNamespace;
 
-use std::collections::VecDeque;
+use std::{collections::VecDeque, sync::Arc};
 
 use hashbrown::{HashMap, HashSet};
 use tracing::{debug, error, info, warn};
 
 use crate::node_manager::{ParsedReadValueId, ParsedWriteValue, RequestContext};
 use opcua_types::{
-    BrowseDirection, DataValue, LocalizedText, NodeClass, NodeId, QualifiedName, ReferenceTypeId,
-    StatusCode, TimestampsToReturn,
+    custom::{DataTypeTree, EncodingIds, TypeInfo},
+    BrowseDirection, DataEncoding, DataTypeDefinition, DataValue, DateTime, LocalizedText,
+    ModelChangeStructureVerbMask, NodeClass, NodeId, NumericRange, QualifiedName, ReferenceTypeId,
+    SemanticChangeStructureDataType, StatusCode, TimestampsToReturn, UAString, Variant,
 };
 
 /// Represents an in-memory address space.
@@ -25,6 +31,7 @@ pub struct AddressSpace {
     node_map: HashMap<NodeId, NodeType>,
     namespaces: HashMap<u16, String>,
     references: References,
+    model_changes: Option<ModelChanges>,
 }
 
 impl AddressSpace {
@@ -34,9 +41,159 @@ impl AddressSpace {
             node_map: HashMap::new(),
             namespaces: HashMap::new(),
             references: References::new(),
+            model_changes: None,
         }
     }
 
+    /// Enable or disable tracking of changes to the structure of the address space.
+    ///
+    /// When enabled, nodes and references added or removed through methods on the address space
+    /// are recorded, and can be retrieved with [AddressSpace::take_model_changes].
+    pub fn track_model_changes(&mut self, enabled: bool) {
+        if !enabled {
+            self.model_changes = None;
+        } else if self.model_changes.is_none() {
+            self.model_changes = Some(ModelChanges::default());
+        }
+    }
+
+    /// Take the changes to the structure of the address space recorded since the last call to
+    /// this method. This is empty unless change tracking is enabled with
+    /// [AddressSpace::track_model_changes].
+    pub fn take_model_changes(&mut self) -> ModelChanges {
+        let Some(changes) = self.model_changes.as_mut() else {
+            return ModelChanges::default();
+        };
+        let mut changes = std::mem::take(changes);
+        // Nodes may have been added before their type definition reference,
+        // so find the type of any nodes that still exist.
+        for change in changes.changes_mut() {
+            if change.affected_type.is_null() {
+                change.affected_type = self.model_change_type(&change.affected);
+            }
+        }
+        changes
+    }
+
+    fn record_model_change(
+        &mut self,
+        affected: &NodeId,
+        affected_type: Option<&NodeId>,
+        verb: ModelChangeStructureVerbMask,
+    ) {
+        if let Some(changes) = self.model_changes.as_mut() {
+            changes.add(affected, affected_type, verb);
+        }
+    }
+
+    /// Get the type reported for the given node in model change events,
+    /// which is the type definition of objects and variables.
+    fn model_change_type(&self, node_id: &NodeId) -> NodeId {
+        self.references
+            .node_references(node_id)
+            .find(|r| {
+                r.direction == ReferenceDirection::Forward
+                    && *r.reference_type == ReferenceTypeId::HasTypeDefinition
+            })
+            .map(|r| r.target_node.clone())
+            .unwrap_or_default()
+    }
+
+    /// If the node with the given ID is a property that defines the semantics of its parent,
+    /// indicated by the `SemanticChange` access level, get the semantic change to report when
+    /// its value changes.
+    pub fn semantic_change(&self, node_id: &NodeId) -> Option<SemanticChangeStructureDataType> {
+        let Some(NodeType::Variable(v)) = self.node_map.get(node_id) else {
+            return None;
+        };
+        if !v.access_level().contains(AccessLevel::SEMANTIC_CHANGE) {
+            return None;
+        }
+        let parent = self
+            .references
+            .node_references(node_id)
+            .find(|r| {
+                r.direction == ReferenceDirection::Inverse
+                    && *r.reference_type == ReferenceTypeId::HasProperty
+            })?
+            .target_node;
+        Some(SemanticChangeStructureDataType {
+            affected: parent.clone(),
+            affected_type: self.model_change_type(parent),
+        })
+    }
+
+    /// Get the source nodes of references pointing to the given node.
+    fn inverse_reference_sources(&self, node_id: &NodeId) -> Vec<NodeId> {
+        if self.model_changes.is_none() {
+            return Vec::new();
+        }
+        self.references
+            .node_references(node_id)
+            .filter(|r| r.direction == ReferenceDirection::Inverse)
+            .map(|r| r.target_node.clone())
+            .collect()
+    }
+
+    /// Update the `NodeVersion` property of each of the given nodes that has one,
+    /// returning the IDs of the updated properties.
+    ///
+    /// The `NodeVersion` property should be changed whenever the references of a node change.
+    pub fn increment_node_versions<'a>(
+        &mut self,
+        node_ids: impl Iterator<Item = &'a NodeId>,
+    ) -> Vec<NodeId> {
+        let mut updated = Vec::new();
+        let now = DateTime::now();
+        let name = QualifiedName::from("NodeVersion");
+        for node_id in node_ids {
+            let Some(property_id) = self
+                .references
+                .node_references(node_id)
+                .filter(|r| {
+                    r.direction == ReferenceDirection::Forward
+                        && *r.reference_type == ReferenceTypeId::HasProperty
+                })
+                .find(|r| {
+                    self.node_map
+                        .get(r.target_node)
+                        .is_some_and(|n| n.as_node().browse_name() == &name)
+                })
+                .map(|r| r.target_node.clone())
+            else {
+                continue;
+            };
+            let Some(NodeType::Variable(property)) = self.node_map.get_mut(&property_id) else {
+                continue;
+            };
+            let version = match property
+                .value(
+                    TimestampsToReturn::Neither,
+                    &NumericRange::None,
+                    &DataEncoding::Binary,
+                    0.0,
+                )
+                .value
+            {
+                Some(Variant::String(s)) => s.as_ref().parse::<u64>().map(|v| v + 1).unwrap_or(1),
+                _ => 1,
+            };
+            if property
+                .set_value_direct(
+                    UAString::from(version.to_string()),
+                    StatusCode::Good,
+                    &now,
+                    &now,
+                )
+                .is_err()
+            {
+                continue;
+            }
+            updated.push(property_id);
+        }
+        updated
+    }
+
     /// Import a node set into this address space.
     /// This will register namespaces from the node set import.
     pub fn import_node_set<T: NodeSetImport + ?Sized>(
@@ -46,6 +203,108 @@ impl AddressSpace {
     ) {
         let mut map = NodeSetNamespaceMapper::new(namespaces);
         import.register_namespaces(&mut map);
+        self.import_items(import, &map);
+    }
+
+    /// Import a node set into this address space, loading any data types
+    /// defined by the import using [`NodeSetImport::load_data_types`].
+    /// This will register namespaces from the node set import.
+    ///
+    /// The data type hierarchy in `type_tree` and the data types in `data_types`
+    /// are used to resolve structure fields. The data types in the imported node set
+    /// are added to both, so that node sets may depend on types defined in previously
+    /// imported node sets, including the core namespace.
+    ///
+    /// If the import returns a data type tree, it should be registered
+    /// with the server as a [`DynamicTypeLoader`](opcua_types::custom::DynamicTypeLoader),
+    /// so that values of these types can be used by clients.
+    pub fn import_node_set_with_data_types<T: NodeSetImport + ?Sized>(
+        &mut self,
+        import: &mut T,
+        type_tree: &mut DefaultTypeTree,
+        data_types: &mut DataTypeTree,
+    ) -> Option<Arc<DataTypeTree>> {
+        let mut known_types = DataTypeTree::new(type_tree.data_type_parent_ids());
+        known_types.add_types_from(data_types);
+        let mut map = NodeSetNamespaceMapper::new(type_tree.namespaces_mut());
+        import.register_namespaces(&mut map);
+        let loaded_types = import.load_data_types(&map, known_types);
+        let imported_data_types = self.import_items(import, &map);
+
+        for node_id in &imported_data_types {
+            let parent = self
+                .references
+                .find_references(
+                    node_id,
+                    Some((ReferenceTypeId::HasSubtype, false)),
+                    &*type_tree,
+                    BrowseDirection::Inverse,
+                )
+                .next()
+                .map(|r| r.target_node.clone());
+            if let Some(parent) = parent {
+                type_tree.add_type_node(node_id, &parent, NodeClass::DataType);
+            }
+        }
+        self.add_data_type_info(&imported_data_types, type_tree, data_types);
+        loaded_types
+    }
+
+    /// Add the data types in `node_ids` that have a data type definition to `data_types`.
+    fn add_data_type_info(
+        &self,
+        node_ids: &[NodeId],
+        type_tree: &DefaultTypeTree,
+        data_types: &mut DataTypeTree,
+    ) {
+        let parent_ids = type_tree.data_type_parent_ids();
+        for node_id in node_ids {
+            let Some(NodeType::DataType(data_type)) = self.node_map.get(node_id) else {
+                continue;
+            };
+            let Some(definition) = data_type.data_type_definition() else {
+                continue;
+            };
+            let encoding_ids = matches!(definition, DataTypeDefinition::Structure(_)).then(|| {
+                let mut encoding_ids = EncodingIds::default();
+                for rf in self.references.find_references(
+                    node_id,
+                    Some((ReferenceTypeId::HasEncoding, false)),
+                    type_tree,
+                    BrowseDirection::Forward,
+                ) {
+                    let Some(encoding) = self.node_map.get(rf.target_node) else {
+                        continue;
+                    };
+                    let id = rf.target_node.clone();
+                    match encoding.as_node().browse_name().name.as_ref() {
+                        "Default Binary" => encoding_ids.binary_id = id,
+                        "Default XML" => encoding_ids.xml_id = id,
+                        "Default JSON" => encoding_ids.json_id = id,
+                        _ => (),
+                    }
+                }
+                encoding_ids
+            });
+            match TypeInfo::from_type_definition(
+                definition.clone(),
+                data_type.browse_name().name.as_ref().to_owned(),
+                encoding_ids,
+                data_type.is_abstract(),
+                node_id,
+                &parent_ids,
+            ) {
+                Ok(info) => data_types.add_type(node_id.clone(), info),
+                Err(e) => debug!("Failed to load data type {node_id}: {e}"),
+            }
+        }
+    }
+
+    fn import_items<T: NodeSetImport + ?Sized>(
+        &mut self,
+        import: &T,
+        map: &NodeSetNamespaceMapper,
+    ) -> Vec<NodeId> {
         let owned_namespaces = import.get_own_namespaces();
         for ns in owned_namespaces {
             let idx = map
@@ -56,11 +315,16 @@ impl AddressSpace {
             self.add_namespace(&ns, *idx);
         }
         let mut count = 0;
-        for item in import.load(&map) {
+        let mut data_types = Vec::new();
+        for item in import.load(map) {
             count += 1;
+            if item.node.node_class() == NodeClass::DataType {
+                data_types.push(item.node.node_id().clone());
+            }
             self.import_node(item);
         }
         info!("Imported {count} nodes");
+        data_types
     }
 
     /// Load types from this address space into the given type tree.
@@ -178,9 +442,19 @@ impl AddressSpace {
             error!("This node {} already exists", node_id);
             false
         } else {
+            self.record_model_change(&node_id, None, ModelChangeStructureVerbMask::NodeAdded);
             // If references are supplied, add them now
             if let Some(references) = references {
                 self.references.insert::<S>(&node_id, references);
+                for (target, _, direction) in references {
+                    if *direction == ReferenceDirection::Inverse {
+                        self.record_model_change(
+                            target,
+                            None,
+                            ModelChangeStructureVerbMask::ReferenceAdded,
+                        );
+                    }
+                }
             }
             self.node_map.insert(node_id, node_type);
 
@@ -198,7 +472,15 @@ impl AddressSpace {
             false
         } else {
             self.node_map.insert(node_id.clone(), node.node);
+            self.record_model_change(&node_id, None, ModelChangeStructureVerbMask::NodeAdded);
             for r in node.references {
+                if !r.is_forward {
+                    self.record_model_change(
+                        &r.target_id,
+                        None,
+                        ModelChangeStructureVerbMask::ReferenceAdded,
+                    );
+                }
                 self.references.import_reference(node_id.clone(), r);
             }
 
@@ -233,6 +515,11 @@ impl AddressSpace {
         target_node: &NodeId,
         reference_type: impl Into<NodeId>,
     ) {
+        self.record_model_change(
+            source_node,
+            None,
+            ModelChangeStructureVerbMask::ReferenceAdded,
+        );
         self.references
             .insert_reference(source_node, target_node, reference_type)
     }
@@ -242,7 +529,9 @@ impl AddressSpace {
         &mut self,
         references: impl Iterator<Item = (&'a NodeId, &'a NodeId, impl Into<NodeId>)>,
     ) {
-        self.references.insert_references(references)
+        for (source, target, typ) in references {
+            self.insert_reference(source, target, typ);
+        }
     }
 
     /// Delete a reference.
@@ -252,8 +541,17 @@ impl AddressSpace {
         target_node: &NodeId,
         reference_type: impl Into<NodeId>,
     ) -> bool {
-        self.references
-            .delete_reference(source_node, target_node, reference_type)
+        let found = self
+            .references
+            .delete_reference(source_node, target_node, reference_type);
+        if found {
+            self.record_model_change(
+                source_node,
+                None,
+                ModelChangeStructureVerbMask::ReferenceDeleted,
+            );
+        }
+        found
     }
 
     /// Delete references starting at or pointing to the given node.
@@ -262,8 +560,29 @@ impl AddressSpace {
         source_node: &NodeId,
         delete_target_references: bool,
     ) -> bool {
-        self.references
-            .delete_node_references(source_node, delete_target_references)
+        let sources = if delete_target_references {
+            self.inverse_reference_sources(source_node)
+        } else {
+            Vec::new()
+        };
+        let found = self
+            .references
+            .delete_node_references(source_node, delete_target_references);
+        if found {
+            self.record_model_change(
+                source_node,
+                None,
+                ModelChangeStructureVerbMask::ReferenceDeleted,
+            );
+            for source in &sources {
+                self.record_model_change(
+                    source,
+                    None,
+                    ModelChangeStructureVerbMask::ReferenceDeleted,
+                );
+            }
+        }
+        found
     }
 
     /// Check if the reference given by `source_node`, `target_node` and
@@ -370,6 +689,16 @@ impl AddressSpace {
         self.node_map.get(node_id)
     }
 
+    /// Get the references between nodes in the address space.
+    pub fn references(&self) -> &References {
+        &self.references
+    }
+
+    /// Iterate over all nodes in the address space, in no particular order.
+    pub fn nodes(&self) -> impl Iterator<Item = &NodeType> {
+        self.node_map.values()
+    }
+
     /// Finds a node by its node id and returns a mutable reference to it.
     pub fn find_node_mut(&mut self, node_id: &NodeId) -> Option<&mut NodeType> {
         self.node_map.get_mut(node_id)
@@ -438,6 +767,23 @@ impl AddressSpace {
 
     /// Remove a node from the address space.
     pub fn delete(&mut self, node_id: &NodeId, delete_target_references: bool) -> Option<NodeType> {
+        if self.model_changes.is_some() && self.node_exists(node_id) {
+            let type_id = self.model_change_type(node_id);
+            self.record_model_change(
+                node_id,
+                Some(&type_id),
+                ModelChangeStructureVerbMask::NodeDeleted,
+            );
+            if delete_target_references {
+                for source in self.inverse_reference_sources(node_id) {
+                    self.record_model_change(
+                        &source,
+                        None,
+                        ModelChangeStructureVerbMask::ReferenceDeleted,
+                    );
+                }
+            }
+        }
         let n = self.node_map.remove(node_id);
         self.references
             .delete_node_references(node_id, delete_target_references);
@@ -488,23 +834,7 @@ impl NodeInsertTarget for AddressSpace {
         node: impl Into<NodeType>,
         references: Option<&'a [(&'a NodeId, &NodeId, opcua_nodes::ReferenceDirection)]>,
     ) -> bool {
-        let node_type = node.into();
-        let node_id = node_type.node_id().clone();
-
-        self.assert_namespace(&node_id);
-
-        if self.node_exists(&node_id) {
-            error!("This node {} already exists", node_id);
-            false
-        } else {
-            // If references are supplied, add them now
-            if let Some(references) = references {
-                self.references.insert(&node_id, references);
-            }
-            self.node_map.insert(node_id, node_type);
-
-            true
-        }
+        AddressSpace::insert(self, node, references)
     }
 }
 
@@ -0,0 +1,84 @@
+use hashbrown::HashMap;
+use opcua_types::{ModelChangeStructureDataType, ModelChangeStructureVerbMask, NodeId};
+
+/// Maximum number of nodes to track changes for. If more nodes than this
+/// are changed before the changes are reported, the individual changes are
+/// dropped, and clients are told that the address space changed in some
+/// unspecified way instead.
+const MAX_TRACKED_CHANGES: usize = 10_000;
+
+#[derive(Debug, Default)]
+/// Changes to the structure of an [AddressSpace](super::AddressSpace), that
+/// is, nodes and references being added or removed. These are reported to
+/// clients using `GeneralModelChangeEventType` events.
+///
+/// Changes to the same node are combined into a single entry with multiple verbs.
+pub struct ModelChanges {
+    changes: Vec<ModelChangeStructureDataType>,
+    by_node: HashMap<NodeId, usize>,
+    overflowed: bool,
+}
+
+impl ModelChanges {
+    pub(super) fn add(
+        &mut self,
+        affected: &NodeId,
+        affected_type: Option<&NodeId>,
+        verb: ModelChangeStructureVerbMask,
+    ) {
+        if self.overflowed {
+            return;
+        }
+        if let Some(idx) = self.by_node.get(affected) {
+            let change = &mut self.changes[*idx];
+            // References added to a new node are part of adding the node.
+            if verb == ModelChangeStructureVerbMask::ReferenceAdded
+                && change.verb & ModelChangeStructureVerbMask::NodeAdded as u8 != 0
+            {
+                return;
+            }
+            change.verb |= verb as u8;
+            if let Some(affected_type) = affected_type {
+                change.affected_type = affected_type.clone();
+            }
+            return;
+        }
+        if self.changes.len() >= MAX_TRACKED_CHANGES {
+            self.overflowed = true;
+            self.changes.clear();
+            self.by_node.clear();
+            return;
+        }
+        self.by_node.insert(affected.clone(), self.changes.len());
+        self.changes.push(ModelChangeStructureDataType {
+            affected: affected.clone(),
+            affected_type: affected_type.cloned().unwrap_or_default(),
+            verb: verb as u8,
+        });
+    }
+
+    pub(super) fn changes_mut(&mut self) -> &mut [ModelChangeStructureDataType] {
+        &mut self.changes
+    }
+
+    /// Return `true` if no changes were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && !self.overflowed
+    }
+
+    /// Return `true` if too many changes were made to report them individually.
+    /// In this case [ModelChanges::changes] is empty.
+    pub fn is_overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// Get the list of recorded changes.
+    pub fn changes(&self) -> &[ModelChangeStructureDataType] {
+        &self.changes
+    }
+
+    /// Consume this and return the list of recorded changes.
+    pub fn into_changes(self) -> Vec<ModelChangeStructureDataType> {
+        self.changes
+    }
+}
@@ -1,7 +1,12 @@
-use crate::node_manager::{ParsedReadValueId, ParsedWriteValue, RequestContext, ServerContext};
+use crate::{
+    node_manager::{ParsedReadValueId, ParsedWriteValue, RequestContext, ServerContext},
+    roles::permissions_for_roles,
+};
+use opcua_core::trace_read_lock;
 use opcua_nodes::TypeTree;
 use opcua_types::{
-    AttributeId, DataEncoding, DataTypeId, DataValue, DateTime, NumericRange, StatusCode,
+    AccessRestrictionType, AttributeId, DataEncoding, DataTypeId, DataValue, DateTime,
+    ExtensionObject, MessageSecurityMode, NumericRange, PermissionType, StatusCode,
     TimestampsToReturn, Variant, WriteMask,
 };
 use tracing::debug;
@@ -25,6 +30,19 @@ pub fn is_writable(
     node: &NodeType,
     attribute_id: AttributeId,
 ) -> Result<(), StatusCode> {
+    if let Some(permissions) = user_permissions(context, node) {
+        let required = match attribute_id {
+            AttributeId::Value if matches!(node, NodeType::Variable(_)) => PermissionType::Write,
+            AttributeId::RolePermissions => PermissionType::WriteRolePermissions,
+            AttributeId::Historizing => PermissionType::WriteHistorizing,
+            _ => PermissionType::WriteAttribute,
+        };
+        if !permissions.contains(required) {
+            return Err(StatusCode::BadUserAccessDenied);
+        }
+    }
+    validate_access_restrictions(context, node, false)?;
+
     if let (NodeType::Variable(_), AttributeId::Value) = (node, attribute_id) {
         if !user_access_level(context, node).contains(AccessLevel::CURRENT_WRITE) {
             return Err(StatusCode::BadUserAccessDenied);
@@ -77,11 +95,112 @@ pub fn user_access_level(context: &RequestContext, node: &NodeType) -> AccessLev
     } else {
         AccessLevel::CURRENT_READ
     };
-    context.authenticator.effective_user_access_level(
+    let user_access_level = context.authenticator.effective_user_access_level(
         &context.token,
         user_access_level,
         node.node_id(),
-    )
+    );
+    restrict_access_level(context, node, user_access_level)
+}
+
+/// Remove the bits from `access_level` that the roles of the session in `context`
+/// do not grant on `node`.
+fn restrict_access_level(
+    context: &RequestContext,
+    node: &NodeType,
+    access_level: AccessLevel,
+) -> AccessLevel {
+    if !matches!(node, NodeType::Variable(_)) {
+        return access_level;
+    }
+    let Some(permissions) = user_permissions(context, node) else {
+        return access_level;
+    };
+    let mut allowed =
+        AccessLevel::SEMANTIC_CHANGE | AccessLevel::STATUS_WRITE | AccessLevel::TIMESTAMP_WRITE;
+    if permissions.contains(PermissionType::Read) {
+        allowed |= AccessLevel::CURRENT_READ;
+    }
+    if permissions.contains(PermissionType::Write) {
+        allowed |= AccessLevel::CURRENT_WRITE;
+    }
+    if permissions.contains(PermissionType::ReadHistory) {
+        allowed |= AccessLevel::HISTORY_READ;
+    }
+    if permissions.intersects(
+        PermissionType::InsertHistory
+            | PermissionType::ModifyHistory
+            | PermissionType::DeleteHistory,
+    ) {
+        allowed |= AccessLevel::HISTORY_WRITE;
+    }
+    access_level & allowed
+}
+
+/// Get the permissions granted on `node` to the session given by `context`.
+///
+/// Returns `None` if the node has no role permissions, in which case access to the node
+/// is not restricted by roles.
+pub fn user_permissions(context: &RequestContext, node: &NodeType) -> Option<PermissionType> {
+    let role_permissions = node.as_node().role_permissions()?;
+    let session = trace_read_lock!(context.session);
+    Some(permissions_for_roles(role_permissions, session.roles()))
+}
+
+/// Validate that the session given by `context` has been granted `permission`
+/// on `node`. Nodes without role permissions are not restricted.
+pub fn validate_permission(
+    context: &RequestContext,
+    node: &NodeType,
+    permission: PermissionType,
+) -> Result<(), StatusCode> {
+    match user_permissions(context, node) {
+        Some(p) if !p.contains(permission) => Err(StatusCode::BadUserAccessDenied),
+        _ => Ok(()),
+    }
+}
+
+/// Return `true` if the session given by `context` may see `node` when browsing.
+pub fn is_browsable(context: &RequestContext, node: &NodeType) -> bool {
+    validate_permission(context, node, PermissionType::Browse).is_ok()
+        && validate_access_restrictions(context, node, true).is_ok()
+}
+
+/// Validate that the secure channel used by the session given by `context` satisfies
+/// the access restrictions on `node`.
+///
+/// If `is_browse` is `true`, the restrictions are only applied if the node has the
+/// `ApplyRestrictionsToBrowse` flag set. This should be used for browsing and
+/// reading attributes other than the value.
+pub fn validate_access_restrictions(
+    context: &RequestContext,
+    node: &NodeType,
+    is_browse: bool,
+) -> Result<(), StatusCode> {
+    let Some(restrictions) = node.as_node().access_restrictions() else {
+        return Ok(());
+    };
+    if is_browse && !restrictions.contains(AccessRestrictionType::ApplyRestrictionsToBrowse) {
+        return Ok(());
+    }
+    let security_mode = trace_read_lock!(context.session).message_security_mode();
+    let satisfied = if restrictions.contains(AccessRestrictionType::EncryptionRequired) {
+        security_mode == MessageSecurityMode::SignAndEncrypt
+    } else if restrictions.contains(AccessRestrictionType::SigningRequired) {
+        matches!(
+            security_mode,
+            MessageSecurityMode::Sign | MessageSecurityMode::SignAndEncrypt
+        )
+    } else {
+        // SessionRequired is always satisfied, since all services that
+        // access nodes require a session.
+        true
+    };
+    if satisfied {
+        Ok(())
+    } else {
+        Err(StatusCode::BadSecurityModeInsufficient)
+    }
 }
 
 /// Validate that the user given by `context` is allowed to read
@@ -91,7 +210,25 @@ pub fn validate_node_read(
     context: &RequestContext,
     node_to_read: &ParsedReadValueId,
 ) -> Result<(), StatusCode> {
-    is_readable(context, node)?;
+    // Reading the value requires the `Read` permission, which is included in the
+    // user access level. Other attributes only require the node to be browsable.
+    match (node_to_read.attribute_id, user_permissions(context, node)) {
+        (AttributeId::Value, _) | (_, None) => is_readable(context, node)?,
+        (AttributeId::RolePermissions, Some(p))
+            if !p.contains(PermissionType::ReadRolePermissions) =>
+        {
+            return Err(StatusCode::BadUserAccessDenied);
+        }
+        (_, Some(p)) if !p.contains(PermissionType::Browse) => {
+            return Err(StatusCode::BadUserAccessDenied);
+        }
+        _ => (),
+    }
+    validate_access_restrictions(
+        context,
+        node,
+        node_to_read.attribute_id != AttributeId::Value,
+    )?;
 
     if node_to_read.attribute_id != AttributeId::Value
         && node_to_read.index_range != NumericRange::None
@@ -224,6 +361,7 @@ pub fn read_node_value(
                     access_level,
                     node.node_id(),
                 );
+                let access_level = restrict_access_level(context, node, access_level);
                 Some(Variant::from(access_level.bits()))
             }
             Some(v) => Some(v),
@@ -238,7 +376,8 @@ pub fn read_node_value(
             Some(Variant::Boolean(val)) => Some(Variant::from(
                 val && context
                     .authenticator
-                    .is_user_executable(&context.token, node.node_id()),
+                    .is_user_executable(&context.token, node.node_id())
+                    && validate_permission(context, node, PermissionType::Call).is_ok(),
             )),
             r => r,
         }
@@ -246,6 +385,22 @@ pub fn read_node_value(
         value
     };
 
+    // Only include the permissions for roles granted to the current session.
+    let value = if node_to_read.attribute_id == AttributeId::UserRolePermissions {
+        let session = trace_read_lock!(context.session);
+        node.as_node().role_permissions().map(|perms| {
+            Variant::from(
+                perms
+                    .iter()
+                    .filter(|p| session.roles().contains(&p.role_id))
+                    .map(|p| ExtensionObject::from_message(p.clone()))
+                    .collect::<Vec<_>>(),
+            )
+        })
+    } else {
+        value
+    };
+
     result_value.value = value;
     result_value.status = attribute.status;
     if matches!(node, NodeType::Variable(_)) && node_to_read.attribute_id == AttributeId::Value {
@@ -10,10 +10,14 @@ use opcua_types::{
 use tracing::{debug, error};
 
 use crate::identity_token::{
-    POLICY_ID_ANONYMOUS, POLICY_ID_ISSUED_TOKEN_NONE, POLICY_ID_ISSUED_TOKEN_RSA_15,
-    POLICY_ID_ISSUED_TOKEN_RSA_OAEP, POLICY_ID_ISSUED_TOKEN_RSA_OAEP_SHA256,
-    POLICY_ID_USER_PASS_NONE, POLICY_ID_USER_PASS_RSA_15, POLICY_ID_USER_PASS_RSA_OAEP,
-    POLICY_ID_USER_PASS_RSA_OAEP_SHA256, POLICY_ID_X509,
+    POLICY_ID_ANONYMOUS, POLICY_ID_ISSUED_TOKEN_ECC_BRAINPOOL_P256R1,
+    POLICY_ID_ISSUED_TOKEN_ECC_CURVE25519, POLICY_ID_ISSUED_TOKEN_ECC_NIST_P256,
+    POLICY_ID_ISSUED_TOKEN_ECC_NIST_P384, POLICY_ID_ISSUED_TOKEN_NONE,
+    POLICY_ID_ISSUED_TOKEN_RSA_15, POLICY_ID_ISSUED_TOKEN_RSA_OAEP,
+    POLICY_ID_ISSUED_TOKEN_RSA_OAEP_SHA256, POLICY_ID_USER_PASS_ECC_BRAINPOOL_P256R1,
+    POLICY_ID_USER_PASS_ECC_CURVE25519, POLICY_ID_USER_PASS_ECC_NIST_P256,
+    POLICY_ID_USER_PASS_ECC_NIST_P384, POLICY_ID_USER_PASS_NONE, POLICY_ID_USER_PASS_RSA_15,
+    POLICY_ID_USER_PASS_RSA_OAEP, POLICY_ID_USER_PASS_RSA_OAEP_SHA256, POLICY_ID_X509,
 };
 
 use super::{
@@ -74,6 +78,9 @@ impl UserToken {
 pub struct CoreServerPermissions {
     /// Whether the user can read the server diagnostics.
     pub read_diagnostics: bool,
+    /// Whether the user can manage the server certificate and trust list
+    /// through the `ServerConfiguration` object.
+    pub manage_certificates: bool,
 }
 
 #[allow(unused)]
@@ -194,6 +201,12 @@ pub trait AuthManager: Send + Sync + 'static {
     fn core_permissions(&self, token: &UserToken) -> CoreServerPermissions {
         CoreServerPermissions::default()
     }
+
+    /// Return the IDs of roles granted to the given user, in addition to the roles
+    /// granted by the identity mapping rules in the server [`RoleSet`](crate::roles::RoleSet).
+    fn user_roles(&self, token: &UserToken) -> Vec<NodeId> {
+        Vec::new()
+    }
 }
 
 /// A simple authenticator that keeps a map of valid users in memory.
@@ -344,6 +357,7 @@ impl AuthManager for DefaultAuthenticator {
             .get(token.0.as_str())
             .map(|r| CoreServerPermissions {
                 read_diagnostics: r.read_diagnostics,
+                manage_certificates: r.manage_certificates,
             })
             .unwrap_or_default()
     }
@@ -358,6 +372,10 @@ pub fn user_pass_security_policy_id(endpoint: &ServerEndpoint) -> UAString {
         | SecurityPolicy::Basic256Sha256
         | SecurityPolicy::Aes128Sha256RsaOaep => POLICY_ID_USER_PASS_RSA_OAEP,
         SecurityPolicy::Aes256Sha256RsaPss => POLICY_ID_USER_PASS_RSA_OAEP_SHA256,
+        SecurityPolicy::EccNistP256 => POLICY_ID_USER_PASS_ECC_NIST_P256,
+        SecurityPolicy::EccNistP384 => POLICY_ID_USER_PASS_ECC_NIST_P384,
+        SecurityPolicy::EccBrainpoolP256r1 => POLICY_ID_USER_PASS_ECC_BRAINPOOL_P256R1,
+        SecurityPolicy::EccCurve25519 => POLICY_ID_USER_PASS_ECC_CURVE25519,
         _ => {
             panic!("Invalid security policy for username and password")
         }
@@ -374,6 +392,10 @@ pub fn issued_token_security_policy(endpoint: &ServerEndpoint) -> UAString {
         | SecurityPolicy::Basic256Sha256
         | SecurityPolicy::Aes128Sha256RsaOaep => POLICY_ID_ISSUED_TOKEN_RSA_OAEP,
         SecurityPolicy::Aes256Sha256RsaPss => POLICY_ID_ISSUED_TOKEN_RSA_OAEP_SHA256,
+        SecurityPolicy::EccNistP256 => POLICY_ID_ISSUED_TOKEN_ECC_NIST_P256,
+        SecurityPolicy::EccNistP384 => POLICY_ID_ISSUED_TOKEN_ECC_NIST_P384,
+        SecurityPolicy::EccBrainpoolP256r1 => POLICY_ID_ISSUED_TOKEN_ECC_BRAINPOOL_P256R1,
+        SecurityPolicy::EccCurve25519 => POLICY_ID_ISSUED_TOKEN_ECC_CURVE25519,
         _ => {
             panic!("Invalid security policy for username and password")
         }
@@ -382,8 +404,13 @@ pub fn issued_token_security_policy(endpoint: &ServerEndpoint) -> UAString {
 }
 
 /// Get the username and password policy URI for the given endpioint.
-pub fn user_pass_security_policy_uri(_endpoint: &ServerEndpoint) -> UAString {
-    // TODO we could force the security policy uri for passwords to be something other than the default
-    //  here to ensure they're secure even when the endpoint's security policy is None.
-    UAString::null()
+///
+/// This is only set if the endpoint has an explicit password security policy, otherwise
+/// secrets are encrypted with the security policy of the endpoint.
+pub fn user_pass_security_policy_uri(endpoint: &ServerEndpoint) -> UAString {
+    if endpoint.password_security_policy.is_some() {
+        UAString::from(endpoint.password_security_policy().to_uri())
+    } else {
+        UAString::null()
+    }
 }
@@ -9,8 +9,9 @@ use opcua_crypto::SecurityPolicy;
 use opcua_types::{BuildInfo, MessageSecurityMode, TypeLoader, TypeLoaderCollection};
 
 use super::{
-    authenticator::AuthManager, node_manager::NodeManagerBuilder, Limits, Server, ServerConfig,
-    ServerEndpoint, ServerHandle, ServerUserToken, ANONYMOUS_USER_TOKEN_ID,
+    authenticator::AuthManager, node_manager::NodeManagerBuilder, DiscoveryServerConfig, Limits,
+    ReverseConnectConfig, Server, ServerConfig, ServerEndpoint, ServerHandle, ServerUserToken,
+    ANONYMOUS_USER_TOKEN_ID,
 };
 
 /// Server builder, used to configure the server programatically,
@@ -343,6 +344,15 @@ impl ServerBuilder {
         self
     }
 
+    /// Reject certificates issued by a certificate authority without a current
+    /// revocation list, instead of accepting them with a warning.
+    pub fn reject_unknown_revocation_status(mut self, reject: bool) -> Self {
+        self.config
+            .certificate_validation
+            .reject_unknown_revocation_status = reject;
+        self
+    }
+
     /// PKI folder, either absolute or relative to executable.
     pub fn pki_dir(mut self, pki_dir: impl Into<PathBuf>) -> Self {
         self.config.pki_dir = pki_dir.into();
@@ -356,6 +366,22 @@ impl ServerBuilder {
         self
     }
 
+    /// Run the server as a local discovery server. Other servers can register
+    /// themselves with it, and clients can find them using `FindServers` and
+    /// `FindServersOnNetwork`.
+    pub fn discovery_server(mut self, config: DiscoveryServerConfig) -> Self {
+        self.config.discovery_server = Some(config);
+        self
+    }
+
+    /// Add a client the server should connect to using reverse connect.
+    /// The server keeps a connection open to each configured client, and
+    /// reconnects with exponential backoff if the connection fails or is closed.
+    pub fn add_reverse_connect(mut self, reverse_connect: ReverseConnectConfig) -> Self {
+        self.config.reverse_connect.push(reverse_connect);
+        self
+    }
+
     /// Timeout for new connections to send a `HELLO` message, in seconds.
     /// After this timeout expires without a valid hello message, the connection
     /// is closed.
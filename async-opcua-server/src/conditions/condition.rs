@@ -0,0 +1,641 @@
+use opcua_nodes::Event;
+use opcua_types::{
+    AttributeId, ByteString, DateTime, Guid, LocalizedText, NodeId, NumericRange, ObjectTypeId,
+    QualifiedName, StatusCode, UAString, Variant,
+};
+
+use crate::EventField;
+
+use super::limit::{LimitAlarm, LimitStates};
+
+/// State of a `TwoStateVariableType` field, such as `EnabledState` or `AckedState`.
+#[derive(Debug, Clone)]
+struct TwoStateVariable {
+    id: bool,
+    transition_time: DateTime,
+    true_state: &'static str,
+    false_state: &'static str,
+}
+
+impl TwoStateVariable {
+    fn new(id: bool, true_state: &'static str, false_state: &'static str) -> Self {
+        Self {
+            id,
+            transition_time: DateTime::now(),
+            true_state,
+            false_state,
+        }
+    }
+
+    /// Set the state, returning `true` if it changed.
+    fn set(&mut self, id: bool) -> bool {
+        if self.id == id {
+            return false;
+        }
+        self.id = id;
+        self.transition_time = DateTime::now();
+        true
+    }
+
+    fn text(&self) -> LocalizedText {
+        if self.id {
+            self.true_state.into()
+        } else {
+            self.false_state.into()
+        }
+    }
+}
+
+impl EventField for TwoStateVariable {
+    fn get_value(
+        &self,
+        attribute_id: AttributeId,
+        index_range: &NumericRange,
+        remaining_path: &[QualifiedName],
+    ) -> Variant {
+        let Some(field) = remaining_path.first() else {
+            return self.text().get_value(attribute_id, index_range, &[]);
+        };
+        if field.namespace_index != 0 || remaining_path.len() != 1 {
+            return Variant::Empty;
+        }
+        match field.name.as_ref() {
+            "Id" => self.id.get_value(attribute_id, index_range, &[]),
+            "TransitionTime" | "EffectiveTransitionTime" => {
+                self.transition_time
+                    .get_value(attribute_id, index_range, &[])
+            }
+            "TrueState" => {
+                LocalizedText::from(self.true_state).get_value(attribute_id, index_range, &[])
+            }
+            "FalseState" => {
+                LocalizedText::from(self.false_state).get_value(attribute_id, index_range, &[])
+            }
+            "EffectiveDisplayName" => self.text().get_value(attribute_id, index_range, &[]),
+            _ => Variant::Empty,
+        }
+    }
+}
+
+/// Value of a `ConditionVariableType` field, such as `Comment` or `Quality`.
+#[derive(Debug, Clone)]
+struct ConditionVariable<T> {
+    value: T,
+    source_timestamp: DateTime,
+}
+
+impl<T> ConditionVariable<T> {
+    fn new(value: T) -> Self {
+        Self {
+            value,
+            source_timestamp: DateTime::now(),
+        }
+    }
+
+    fn set(&mut self, value: T) {
+        self.value = value;
+        self.source_timestamp = DateTime::now();
+    }
+}
+
+impl<T: EventField> EventField for ConditionVariable<T> {
+    fn get_value(
+        &self,
+        attribute_id: AttributeId,
+        index_range: &NumericRange,
+        remaining_path: &[QualifiedName],
+    ) -> Variant {
+        match remaining_path {
+            [] => self.value.get_value(attribute_id, index_range, &[]),
+            [field] if field.namespace_index == 0 && field.name.as_ref() == "SourceTimestamp" => {
+                self.source_timestamp
+                    .get_value(attribute_id, index_range, &[])
+            }
+            _ => Variant::Empty,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AlarmState {
+    active: TwoStateVariable,
+    input_node: NodeId,
+    limits: Option<(LimitAlarm, LimitStates)>,
+    normal_severity: u16,
+}
+
+/// The state of a single condition, or a branch of a condition, as defined in OPC-UA Part 9.
+///
+/// A condition is created as a plain `ConditionType`, and can be extended to an
+/// acknowledgeable condition with [`Condition::with_acknowledge`], to an alarm with
+/// [`Condition::with_alarm`], and to a limit alarm with [`Condition::with_limits`].
+///
+/// Conditions are owned by the [`ConditionManager`](super::ConditionManager), which
+/// reports an event each time a condition changes.
+///
+/// The condition is also the event reported to clients, so this implements [`Event`].
+#[derive(Debug, Clone)]
+pub struct Condition {
+    condition_id: NodeId,
+    branch_id: NodeId,
+    event_id: ByteString,
+    event_type: NodeId,
+    source_node: NodeId,
+    source_name: UAString,
+    time: DateTime,
+    receive_time: DateTime,
+    message: LocalizedText,
+    severity: u16,
+    condition_class_id: NodeId,
+    condition_class_name: LocalizedText,
+    condition_name: UAString,
+    client_user_id: UAString,
+    retain: bool,
+    enabled: TwoStateVariable,
+    quality: ConditionVariable<StatusCode>,
+    last_severity: ConditionVariable<u16>,
+    comment: ConditionVariable<LocalizedText>,
+    acked: Option<TwoStateVariable>,
+    confirmed: Option<TwoStateVariable>,
+    alarm: Option<AlarmState>,
+}
+
+impl Condition {
+    /// Create a new condition.
+    ///
+    /// # Arguments
+    ///
+    ///  * `condition_id` - The node ID of the condition. This is used as `ObjectId` when
+    ///    clients call methods on the condition. A node with this ID is added to the address
+    ///    space when the condition is added to the [`ConditionManager`](super::ConditionManager).
+    ///  * `event_type` - Type of the condition, must be a subtype of `ConditionType`.
+    ///  * `source_node` - Node the condition is reported for.
+    ///  * `source_name` - Name of the source node.
+    ///  * `condition_name` - Name of the condition.
+    pub fn new(
+        condition_id: NodeId,
+        event_type: impl Into<NodeId>,
+        source_node: NodeId,
+        source_name: impl Into<UAString>,
+        condition_name: impl Into<UAString>,
+    ) -> Self {
+        let now = DateTime::now();
+        Self {
+            condition_id,
+            branch_id: NodeId::null(),
+            event_id: ByteString::null(),
+            event_type: event_type.into(),
+            source_node,
+            source_name: source_name.into(),
+            time: now,
+            receive_time: now,
+            message: LocalizedText::null(),
+            severity: 1,
+            condition_class_id: ObjectTypeId::BaseConditionClassType.into(),
+            condition_class_name: "BaseConditionClass".into(),
+            condition_name: condition_name.into(),
+            client_user_id: UAString::null(),
+            retain: false,
+            enabled: TwoStateVariable::new(true, "Enabled", "Disabled"),
+            quality: ConditionVariable::new(StatusCode::Good),
+            last_severity: ConditionVariable::new(1),
+            comment: ConditionVariable::new(LocalizedText::null()),
+            acked: None,
+            confirmed: None,
+            alarm: None,
+        }
+    }
+
+    /// Make this an acknowledgeable condition. If `confirm` is `true`, the
+    /// condition must also be confirmed after it is acknowledged.
+    pub fn with_acknowledge(mut self, confirm: bool) -> Self {
+        self.acked = Some(TwoStateVariable::new(
+            true,
+            "Acknowledged",
+            "Unacknowledged",
+        ));
+        if confirm {
+            self.confirmed = Some(TwoStateVariable::new(true, "Confirmed", "Unconfirmed"));
+        }
+        self.update_retain();
+        self
+    }
+
+    /// Make this an alarm, with `input_node` as the node whose value is used to
+    /// determine whether the alarm is active. Alarms are always acknowledgeable.
+    pub fn with_alarm(mut self, input_node: NodeId) -> Self {
+        if self.acked.is_none() {
+            self = self.with_acknowledge(false);
+        }
+        self.alarm = Some(AlarmState {
+            active: TwoStateVariable::new(false, "Active", "Inactive"),
+            input_node,
+            limits: None,
+            normal_severity: self.severity,
+        });
+        self
+    }
+
+    /// Make this a limit alarm. The alarm becomes active when a value set with
+    /// [`Condition::set_limit_value`] exceeds one of the limits.
+    pub fn with_limits(mut self, input_node: NodeId, limits: LimitAlarm) -> Self {
+        if self.alarm.is_none() {
+            self = self.with_alarm(input_node);
+        }
+        if let Some(alarm) = &mut self.alarm {
+            alarm.limits = Some((limits, LimitStates::default()));
+        }
+        self
+    }
+
+    /// Set the condition class of this condition.
+    pub fn with_condition_class(
+        mut self,
+        class_id: impl Into<NodeId>,
+        class_name: impl Into<LocalizedText>,
+    ) -> Self {
+        self.condition_class_id = class_id.into();
+        self.condition_class_name = class_name.into();
+        self
+    }
+
+    /// Set the initial severity of this condition.
+    pub fn with_severity(mut self, severity: u16) -> Self {
+        self.set_severity(severity);
+        if let Some(alarm) = &mut self.alarm {
+            alarm.normal_severity = severity;
+        }
+        self
+    }
+
+    /// Set the initial message of this condition.
+    pub fn with_message(mut self, message: impl Into<LocalizedText>) -> Self {
+        self.message = message.into();
+        self
+    }
+
+    /// Node ID of the condition.
+    pub fn condition_id(&self) -> &NodeId {
+        &self.condition_id
+    }
+
+    /// Name of the condition.
+    pub fn condition_name(&self) -> &UAString {
+        &self.condition_name
+    }
+
+    /// Branch ID of this condition, null for the main branch.
+    pub fn branch_id(&self) -> &NodeId {
+        &self.branch_id
+    }
+
+    /// Event ID of the last event reported for this condition.
+    pub fn event_id(&self) -> &ByteString {
+        &self.event_id
+    }
+
+    /// Type of this condition.
+    pub fn event_type(&self) -> &NodeId {
+        &self.event_type
+    }
+
+    /// Node this condition is reported for.
+    pub fn source_node(&self) -> &NodeId {
+        &self.source_node
+    }
+
+    /// Whether the condition is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.id
+    }
+
+    /// Whether the condition is in a state that is interesting for clients.
+    /// Disabled conditions are never retained.
+    pub fn retain(&self) -> bool {
+        self.retain && self.enabled.id
+    }
+
+    /// Current severity.
+    pub fn severity(&self) -> u16 {
+        self.severity
+    }
+
+    /// Current message.
+    pub fn message(&self) -> &LocalizedText {
+        &self.message
+    }
+
+    /// Last comment added to the condition.
+    pub fn comment(&self) -> &LocalizedText {
+        &self.comment.value
+    }
+
+    /// Whether the condition is acknowledged, or `None` if the condition is not acknowledgeable.
+    pub fn is_acked(&self) -> Option<bool> {
+        self.acked.as_ref().map(|s| s.id)
+    }
+
+    /// Whether the condition is confirmed, or `None` if the condition does not support confirm.
+    pub fn is_confirmed(&self) -> Option<bool> {
+        self.confirmed.as_ref().map(|s| s.id)
+    }
+
+    /// Whether the alarm is active, or `None` if the condition is not an alarm.
+    pub fn is_active(&self) -> Option<bool> {
+        self.alarm.as_ref().map(|a| a.active.id)
+    }
+
+    /// Current limit states, or `None` if the condition is not a limit alarm.
+    pub fn limit_states(&self) -> Option<LimitStates> {
+        self.alarm
+            .as_ref()
+            .and_then(|a| a.limits.as_ref())
+            .map(|l| l.1)
+    }
+
+    /// Set the severity. `LastSeverity` is set to the previous severity.
+    pub fn set_severity(&mut self, severity: u16) {
+        if severity != self.severity {
+            self.last_severity.set(self.severity);
+            self.severity = severity;
+        }
+    }
+
+    /// Set the message.
+    pub fn set_message(&mut self, message: impl Into<LocalizedText>) {
+        self.message = message.into();
+    }
+
+    /// Set the quality of the data the condition is based on.
+    pub fn set_quality(&mut self, quality: StatusCode) {
+        self.quality.set(quality);
+    }
+
+    /// Set whether the condition should be retained. For acknowledgeable conditions this
+    /// is computed automatically from the condition state, and this has no effect.
+    pub fn set_retain(&mut self, retain: bool) {
+        self.retain = retain;
+        self.update_retain();
+    }
+
+    /// Set whether the alarm is active. When an alarm becomes active it must
+    /// be acknowledged again. Does nothing if the condition is not an alarm.
+    pub fn set_active(&mut self, active: bool) {
+        let Some(alarm) = &mut self.alarm else {
+            return;
+        };
+        if alarm.active.set(active) && active {
+            if let Some(acked) = &mut self.acked {
+                acked.set(false);
+            }
+        }
+        self.update_retain();
+    }
+
+    /// Evaluate `value` against the limits of a limit alarm, updating the limit states,
+    /// active state, and severity. Returns `true` if the limit states changed.
+    pub fn set_limit_value(&mut self, value: f64) -> bool {
+        let Some(alarm) = &mut self.alarm else {
+            return false;
+        };
+        let Some((limits, states)) = &mut alarm.limits else {
+            return false;
+        };
+        let new_states = limits.evaluate(value, *states);
+        if new_states == *states {
+            return false;
+        }
+        *states = new_states;
+        let severity = limits.severity(new_states).unwrap_or(alarm.normal_severity);
+        self.set_severity(severity);
+        self.set_active(new_states.any());
+        true
+    }
+
+    pub(super) fn set_enabled(&mut self, enabled: bool) -> Result<(), StatusCode> {
+        if !self.enabled.set(enabled) {
+            return Err(if enabled {
+                StatusCode::BadConditionAlreadyEnabled
+            } else {
+                StatusCode::BadConditionAlreadyDisabled
+            });
+        }
+        self.update_retain();
+        Ok(())
+    }
+
+    pub(super) fn add_comment(&mut self, comment: LocalizedText, user: UAString) {
+        self.comment.set(comment);
+        self.client_user_id = user;
+    }
+
+    pub(super) fn acknowledge(
+        &mut self,
+        comment: LocalizedText,
+        user: UAString,
+    ) -> Result<(), StatusCode> {
+        let Some(acked) = &mut self.acked else {
+            return Err(StatusCode::BadMethodInvalid);
+        };
+        if !acked.set(true) {
+            return Err(StatusCode::BadConditionBranchAlreadyAcked);
+        }
+        if let Some(confirmed) = &mut self.confirmed {
+            confirmed.set(false);
+        }
+        if !comment.text.is_null() {
+            self.add_comment(comment, user);
+        }
+        self.update_retain();
+        Ok(())
+    }
+
+    pub(super) fn confirm(
+        &mut self,
+        comment: LocalizedText,
+        user: UAString,
+    ) -> Result<(), StatusCode> {
+        let Some(confirmed) = &mut self.confirmed else {
+            return Err(StatusCode::BadMethodInvalid);
+        };
+        if !confirmed.set(true) {
+            return Err(StatusCode::BadConditionBranchAlreadyConfirmed);
+        }
+        if !comment.text.is_null() {
+            self.add_comment(comment, user);
+        }
+        self.update_retain();
+        Ok(())
+    }
+
+    /// Mark the condition as no longer retained, used when the condition is removed.
+    pub(super) fn clear_retain(&mut self) {
+        self.retain = false;
+    }
+
+    /// Create a copy of this condition as a new branch.
+    pub(super) fn new_branch(&self) -> Condition {
+        let mut branch = self.clone();
+        branch.branch_id = NodeId::new(self.condition_id.namespace, Guid::new());
+        branch
+    }
+
+    /// Prepare the condition for a new event, with a new event ID and time.
+    pub(super) fn next_event(&mut self) {
+        self.event_id = Guid::new().into();
+        self.time = DateTime::now();
+        self.receive_time = self.time;
+    }
+
+    fn update_retain(&mut self) {
+        let Some(acked) = &self.acked else {
+            return;
+        };
+        let active = self.alarm.as_ref().is_some_and(|a| a.active.id);
+        let confirmed = self.confirmed.as_ref().map(|c| c.id).unwrap_or(true);
+        self.retain = active || !acked.id || !confirmed;
+    }
+
+    fn get_limit_value(
+        &self,
+        name: &str,
+        attribute_id: AttributeId,
+        index_range: &NumericRange,
+        remaining_path: &[QualifiedName],
+    ) -> Variant {
+        let Some((limits, states)) = self.alarm.as_ref().and_then(|a| a.limits.as_ref()) else {
+            return Variant::Empty;
+        };
+        if let Some(limit) = limits.limit_value(name) {
+            return limit.get_value(attribute_id, index_range, remaining_path);
+        }
+
+        if limits.is_exclusive() {
+            if name != "LimitState" {
+                return Variant::Empty;
+            }
+            let Some((state, id)) = states.exclusive_state() else {
+                return Variant::Empty;
+            };
+            return match remaining_path {
+                [cs] if cs.name.as_ref() == "CurrentState" => {
+                    LocalizedText::from(state).get_value(attribute_id, index_range, &[])
+                }
+                [cs, id_field]
+                    if cs.name.as_ref() == "CurrentState" && id_field.name.as_ref() == "Id" =>
+                {
+                    NodeId::from(id).get_value(attribute_id, index_range, &[])
+                }
+                _ => Variant::Empty,
+            };
+        }
+
+        let (active, text) = match name {
+            "HighHighState" => (states.high_high, "HighHigh"),
+            "HighState" => (states.high, "High"),
+            "LowState" => (states.low, "Low"),
+            "LowLowState" => (states.low_low, "LowLow"),
+            _ => return Variant::Empty,
+        };
+        let (true_state, false_state) = match text {
+            "HighHigh" => ("HighHigh active", "HighHigh inactive"),
+            "High" => ("High active", "High inactive"),
+            "Low" => ("Low active", "Low inactive"),
+            _ => ("LowLow active", "LowLow inactive"),
+        };
+        TwoStateVariable {
+            id: active,
+            transition_time: self.time,
+            true_state,
+            false_state,
+        }
+        .get_value(attribute_id, index_range, remaining_path)
+    }
+}
+
+impl EventField for Condition {
+    fn get_value(
+        &self,
+        attribute_id: AttributeId,
+        index_range: &NumericRange,
+        remaining_path: &[QualifiedName],
+    ) -> Variant {
+        let Some(field) = remaining_path.first() else {
+            return Variant::Empty;
+        };
+        if field.namespace_index != 0 {
+            return Variant::Empty;
+        }
+        let rest = &remaining_path[1..];
+        match field.name.as_ref() {
+            "EventId" => self.event_id.get_value(attribute_id, index_range, rest),
+            "EventType" => self.event_type.get_value(attribute_id, index_range, rest),
+            "SourceNode" => self.source_node.get_value(attribute_id, index_range, rest),
+            "SourceName" => self.source_name.get_value(attribute_id, index_range, rest),
+            "Time" => self.time.get_value(attribute_id, index_range, rest),
+            "ReceiveTime" => self.receive_time.get_value(attribute_id, index_range, rest),
+            "Message" => self.message.get_value(attribute_id, index_range, rest),
+            "Severity" => self.severity.get_value(attribute_id, index_range, rest),
+            "ConditionClassId" => {
+                self.condition_class_id
+                    .get_value(attribute_id, index_range, rest)
+            }
+            "ConditionClassName" => {
+                self.condition_class_name
+                    .get_value(attribute_id, index_range, rest)
+            }
+            "ConditionName" => self
+                .condition_name
+                .get_value(attribute_id, index_range, rest),
+            "BranchId" => self.branch_id.get_value(attribute_id, index_range, rest),
+            "ClientUserId" => self
+                .client_user_id
+                .get_value(attribute_id, index_range, rest),
+            "Retain" => self.retain().get_value(attribute_id, index_range, rest),
+            "EnabledState" => self.enabled.get_value(attribute_id, index_range, rest),
+            "Quality" => self.quality.get_value(attribute_id, index_range, rest),
+            "LastSeverity" => self
+                .last_severity
+                .get_value(attribute_id, index_range, rest),
+            "Comment" => self.comment.get_value(attribute_id, index_range, rest),
+            "AckedState" => self.acked.get_value(attribute_id, index_range, rest),
+            "ConfirmedState" => self.confirmed.get_value(attribute_id, index_range, rest),
+            "ActiveState" | "InputNode" | "SuppressedOrShelved" => {
+                let Some(alarm) = &self.alarm else {
+                    return Variant::Empty;
+                };
+                match field.name.as_ref() {
+                    "ActiveState" => alarm.active.get_value(attribute_id, index_range, rest),
+                    "InputNode" => alarm.input_node.get_value(attribute_id, index_range, rest),
+                    _ => false.get_value(attribute_id, index_range, rest),
+                }
+            }
+            name => self.get_limit_value(name, attribute_id, index_range, rest),
+        }
+    }
+}
+
+impl Event for Condition {
+    fn get_field(
+        &self,
+        _type_definition_id: &NodeId,
+        attribute_id: AttributeId,
+        index_range: &NumericRange,
+        browse_path: &[QualifiedName],
+    ) -> Variant {
+        // The ConditionId is selected as the NodeId of the condition itself.
+        if browse_path.is_empty() {
+            if attribute_id == AttributeId::NodeId {
+                return self
+                    .condition_id
+                    .get_value(AttributeId::Value, index_range, &[]);
+            }
+            return Variant::Empty;
+        }
+        self.get_value(attribute_id, index_range, browse_path)
+    }
+
+    fn time(&self) -> &DateTime {
+        &self.time
+    }
+}
@@ -0,0 +1,173 @@
+use opcua_types::ObjectId;
+
+/// Which limits of a limit alarm are currently exceeded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LimitStates {
+    /// Value is at or above the `HighHigh` limit.
+    pub high_high: bool,
+    /// Value is at or above the `High` limit.
+    pub high: bool,
+    /// Value is at or below the `Low` limit.
+    pub low: bool,
+    /// Value is at or below the `LowLow` limit.
+    pub low_low: bool,
+}
+
+impl LimitStates {
+    /// Whether any limit is exceeded.
+    pub fn any(&self) -> bool {
+        self.high_high || self.high || self.low || self.low_low
+    }
+
+    /// Name and node ID of the state of an exclusive limit alarm, if any limit is exceeded.
+    pub(super) fn exclusive_state(&self) -> Option<(&'static str, ObjectId)> {
+        if self.high_high {
+            Some((
+                "HighHigh",
+                ObjectId::ExclusiveLimitStateMachineType_HighHigh,
+            ))
+        } else if self.high {
+            Some(("High", ObjectId::ExclusiveLimitStateMachineType_High))
+        } else if self.low_low {
+            Some(("LowLow", ObjectId::ExclusiveLimitStateMachineType_LowLow))
+        } else if self.low {
+            Some(("Low", ObjectId::ExclusiveLimitStateMachineType_Low))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Limit {
+    pub(super) value: f64,
+    pub(super) severity: u16,
+}
+
+/// Configuration of a limit alarm, corresponding to `ExclusiveLimitAlarmType` or
+/// `NonExclusiveLimitAlarmType` in OPC-UA Part 9.
+///
+/// A limit alarm is active whenever the input value exceeds one of the configured limits.
+/// An exclusive limit alarm is only ever in one limit state at a time, while a non-exclusive
+/// limit alarm may be both `High` and `HighHigh` at the same time.
+#[derive(Debug, Clone, Default)]
+pub struct LimitAlarm {
+    pub(super) exclusive: bool,
+    pub(super) high_high: Option<Limit>,
+    pub(super) high: Option<Limit>,
+    pub(super) low: Option<Limit>,
+    pub(super) low_low: Option<Limit>,
+    pub(super) deadband: f64,
+}
+
+impl LimitAlarm {
+    /// Create a new exclusive limit alarm with no limits.
+    pub fn exclusive() -> Self {
+        Self {
+            exclusive: true,
+            ..Default::default()
+        }
+    }
+
+    /// Create a new non-exclusive limit alarm with no limits.
+    pub fn non_exclusive() -> Self {
+        Self {
+            exclusive: false,
+            ..Default::default()
+        }
+    }
+
+    /// Set the `HighHigh` limit, and the severity of the alarm when it is exceeded.
+    pub fn high_high(mut self, limit: f64, severity: u16) -> Self {
+        self.high_high = Some(Limit {
+            value: limit,
+            severity,
+        });
+        self
+    }
+
+    /// Set the `High` limit, and the severity of the alarm when it is exceeded.
+    pub fn high(mut self, limit: f64, severity: u16) -> Self {
+        self.high = Some(Limit {
+            value: limit,
+            severity,
+        });
+        self
+    }
+
+    /// Set the `Low` limit, and the severity of the alarm when it is exceeded.
+    pub fn low(mut self, limit: f64, severity: u16) -> Self {
+        self.low = Some(Limit {
+            value: limit,
+            severity,
+        });
+        self
+    }
+
+    /// Set the `LowLow` limit, and the severity of the alarm when it is exceeded.
+    pub fn low_low(mut self, limit: f64, severity: u16) -> Self {
+        self.low_low = Some(Limit {
+            value: limit,
+            severity,
+        });
+        self
+    }
+
+    /// Set the deadband. Once a limit is exceeded, the value must return past the limit
+    /// by more than the deadband before the limit state is cleared.
+    pub fn deadband(mut self, deadband: f64) -> Self {
+        self.deadband = deadband.max(0.0);
+        self
+    }
+
+    /// Whether this is an exclusive limit alarm.
+    pub fn is_exclusive(&self) -> bool {
+        self.exclusive
+    }
+
+    /// Evaluate `value` against the configured limits, given the `current` limit states.
+    pub fn evaluate(&self, value: f64, current: LimitStates) -> LimitStates {
+        let high = |limit: &Option<Limit>, active: bool| {
+            limit.is_some_and(|l| value >= l.value || active && value > l.value - self.deadband)
+        };
+        let low = |limit: &Option<Limit>, active: bool| {
+            limit.is_some_and(|l| value <= l.value || active && value < l.value + self.deadband)
+        };
+        let mut states = LimitStates {
+            high_high: high(&self.high_high, current.high_high),
+            high: high(&self.high, current.high),
+            low: low(&self.low, current.low),
+            low_low: low(&self.low_low, current.low_low),
+        };
+        if self.exclusive {
+            states.high &= !states.high_high;
+            states.low &= !states.low_low;
+        }
+        states
+    }
+
+    /// Get the severity for the given limit states, which is the severity of the most
+    /// severe exceeded limit, if any.
+    pub fn severity(&self, states: LimitStates) -> Option<u16> {
+        [
+            (states.high_high, self.high_high),
+            (states.low_low, self.low_low),
+            (states.high, self.high),
+            (states.low, self.low),
+        ]
+        .into_iter()
+        .filter_map(|(active, limit)| limit.filter(|_| active))
+        .map(|l| l.severity)
+        .max()
+    }
+
+    pub(super) fn limit_value(&self, name: &str) -> Option<f64> {
+        match name {
+            "HighHighLimit" => self.high_high.map(|l| l.value),
+            "HighLimit" => self.high.map(|l| l.value),
+            "LowLimit" => self.low.map(|l| l.value),
+            "LowLowLimit" => self.low_low.map(|l| l.value),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,651 @@
+//! Framework for OPC-UA Alarms & Conditions, defined in OPC-UA Part 9.
+//!
+//! Conditions are registered with the [`ConditionManager`], available from
+//! [`ServerHandle::conditions`](crate::ServerHandle::conditions) or from the
+//! [`ServerInfo`](crate::ServerInfo). The condition manager reports an event each time a
+//! condition changes, implements the `Enable`, `Disable`, `AddComment`, `Acknowledge`,
+//! and `Confirm` methods for registered conditions, and implements `ConditionRefresh`
+//! and `ConditionRefresh2`.
+//!
+//! Each condition is added to the address space of the node manager that owns it, as an
+//! object referenced from its source node with `HasCondition`. Clients call methods on
+//! a condition using the condition ID as `ObjectId`, and the well known method ID,
+//! for example `AcknowledgeableConditionType_Acknowledge`, as `MethodId`. These calls
+//! are routed to the node manager owning the condition, which checks permissions like
+//! for any other method. The in-memory node managers pass calls their implementation
+//! does not handle on to the condition manager.
+
+mod condition;
+mod limit;
+
+use std::sync::Arc;
+
+use hashbrown::HashMap;
+use opcua_core::{sync::RwLock, trace_lock, trace_read_lock, trace_write_lock};
+use opcua_nodes::{BaseEventType, Event, EventFilterContext};
+use opcua_types::{
+    AttributeId, Guid, LocalizedText, MethodId, NodeId, ObjectId, ObjectTypeId, PermissionType,
+    QualifiedName, ReferenceTypeId, StatusCode, UAString, Variant, VariantScalarTypeId,
+    VariantTypeId,
+};
+
+pub use condition::Condition;
+pub use limit::{LimitAlarm, LimitStates};
+
+use crate::{
+    address_space::{AddressSpace, EventNotifier, Object, ReferenceDirection},
+    load_method_args,
+    node_manager::{MethodCall, RequestContext},
+    roles::permissions_for_roles,
+    SubscriptionCache,
+};
+
+struct ConditionEntry {
+    condition: Condition,
+    branches: Vec<Condition>,
+    /// Address space containing the node representing the condition.
+    address_space: Arc<RwLock<AddressSpace>>,
+}
+
+impl ConditionEntry {
+    fn iter(&self) -> impl Iterator<Item = &Condition> {
+        std::iter::once(&self.condition).chain(self.branches.iter())
+    }
+}
+
+/// Manager for the conditions on the server.
+pub struct ConditionManager {
+    subscriptions: Arc<SubscriptionCache>,
+    conditions: RwLock<HashMap<NodeId, ConditionEntry>>,
+}
+
+impl ConditionManager {
+    pub(crate) fn new(subscriptions: Arc<SubscriptionCache>) -> Self {
+        Self {
+            subscriptions,
+            conditions: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Add a condition. An event is reported if the condition is retained.
+    /// Replaces any existing condition with the same condition ID.
+    ///
+    /// An object representing the condition is added to `address_space`, which should
+    /// be the address space of the node manager owning the condition ID, so that method
+    /// calls on the condition are routed to that node manager. Like other direct changes
+    /// to the address space, the new node is reported to clients by
+    /// [`InMemoryNodeManager::notify_model_changes`](crate::node_manager::memory::InMemoryNodeManager::notify_model_changes).
+    ///
+    /// Returns `BadNodeIdExists` if the condition ID is already used by a node that
+    /// does not represent a condition in this manager.
+    pub fn add_condition(
+        &self,
+        mut condition: Condition,
+        address_space: &Arc<RwLock<AddressSpace>>,
+    ) -> Result<(), StatusCode> {
+        let id = condition.condition_id();
+        let previous = trace_read_lock!(self.conditions)
+            .get(id)
+            .map(|e| e.address_space.clone());
+        {
+            let mut space = trace_write_lock!(address_space);
+            // Only nodes created by this manager are replaced.
+            if previous
+                .as_ref()
+                .is_some_and(|p| Arc::ptr_eq(p, address_space))
+            {
+                space.delete(id, true);
+            } else if space.node_exists(id) {
+                return Err(StatusCode::BadNodeIdExists);
+            }
+            Self::insert_node(&condition, &mut space);
+        }
+        if let Some(previous) = previous.filter(|p| !Arc::ptr_eq(p, address_space)) {
+            trace_write_lock!(previous).delete(condition.condition_id(), true);
+        }
+        condition.next_event();
+        let events = if condition.retain() {
+            vec![condition.clone()]
+        } else {
+            Vec::new()
+        };
+        trace_write_lock!(self.conditions).insert(
+            condition.condition_id().clone(),
+            ConditionEntry {
+                condition,
+                branches: Vec::new(),
+                address_space: address_space.clone(),
+            },
+        );
+        self.report(address_space, &events);
+        Ok(())
+    }
+
+    /// Methods that can be called on a condition.
+    fn condition_methods(condition: &Condition) -> Vec<MethodId> {
+        let mut methods = vec![
+            MethodId::ConditionType_Enable,
+            MethodId::ConditionType_Disable,
+            MethodId::ConditionType_AddComment,
+        ];
+        if condition.is_acked().is_some() {
+            methods.push(MethodId::AcknowledgeableConditionType_Acknowledge);
+        }
+        if condition.is_confirmed().is_some() {
+            methods.push(MethodId::AcknowledgeableConditionType_Confirm);
+        }
+        methods
+    }
+
+    fn insert_node(condition: &Condition, address_space: &mut AddressSpace) {
+        let id = condition.condition_id();
+        let name = condition.condition_name().as_ref();
+        let event_type = condition.event_type().clone();
+        let methods: Vec<NodeId> = Self::condition_methods(condition)
+            .into_iter()
+            .map(|m| m.into())
+            .collect();
+        let mut references = vec![
+            (
+                &event_type,
+                NodeId::from(ReferenceTypeId::HasTypeDefinition),
+                ReferenceDirection::Forward,
+            ),
+            (
+                condition.source_node(),
+                ReferenceTypeId::HasCondition.into(),
+                ReferenceDirection::Inverse,
+            ),
+        ];
+        // The condition references the methods of its type, which is how the
+        // node manager knows that these methods can be called on the condition.
+        references.extend(methods.iter().map(|m| {
+            (
+                m,
+                ReferenceTypeId::HasComponent.into(),
+                ReferenceDirection::Forward,
+            )
+        }));
+        let references: Vec<_> = references.iter().map(|(t, r, d)| (*t, r, *d)).collect();
+        address_space.insert(
+            Object::new(
+                id,
+                QualifiedName::new(id.namespace, name),
+                name,
+                EventNotifier::empty(),
+            ),
+            Some(&references),
+        );
+    }
+
+    /// Return `true` if `call` is a call to a method on a condition in this manager.
+    pub(crate) fn is_condition_call(&self, call: &MethodCall) -> bool {
+        let Ok(method) = call.method_id().as_method_id() else {
+            return false;
+        };
+        matches!(
+            method,
+            MethodId::ConditionType_Enable
+                | MethodId::ConditionType_Disable
+                | MethodId::ConditionType_AddComment
+                | MethodId::AcknowledgeableConditionType_Acknowledge
+                | MethodId::AcknowledgeableConditionType_Confirm
+        ) && trace_read_lock!(self.conditions).contains_key(call.object_id())
+    }
+
+    /// Remove a condition and all its branches. An event with `Retain` set to `false`
+    /// is reported for any retained condition or branch.
+    ///
+    /// The object representing the condition is removed from the address space.
+    pub fn remove_condition(&self, condition_id: &NodeId) -> Option<Condition> {
+        let mut entry = trace_write_lock!(self.conditions).remove(condition_id)?;
+        let mut events = Vec::new();
+        for cond in std::iter::once(&mut entry.condition).chain(entry.branches.iter_mut()) {
+            if cond.retain() {
+                cond.clear_retain();
+                cond.next_event();
+                events.push(cond.clone());
+            }
+        }
+        trace_write_lock!(entry.address_space).delete(condition_id, true);
+        self.report(&entry.address_space, &events);
+        Some(entry.condition)
+    }
+
+    /// Get a copy of the current state of a condition.
+    pub fn get_condition(&self, condition_id: &NodeId) -> Option<Condition> {
+        let conditions = trace_read_lock!(self.conditions);
+        conditions.get(condition_id).map(|e| e.condition.clone())
+    }
+
+    /// Get a copy of the current branches of a condition, not including the main branch.
+    pub fn get_branches(&self, condition_id: &NodeId) -> Vec<Condition> {
+        let conditions = trace_read_lock!(self.conditions);
+        conditions
+            .get(condition_id)
+            .map(|e| e.branches.clone())
+            .unwrap_or_default()
+    }
+
+    /// Get the IDs of all conditions in the manager.
+    pub fn condition_ids(&self) -> Vec<NodeId> {
+        let conditions = trace_read_lock!(self.conditions);
+        conditions.keys().cloned().collect()
+    }
+
+    /// Update a condition, then report an event with the new state of the condition
+    /// if it is enabled.
+    pub fn update_condition<R>(
+        &self,
+        condition_id: &NodeId,
+        update: impl FnOnce(&mut Condition) -> R,
+    ) -> Result<R, StatusCode> {
+        self.modify(condition_id, |entry, events| {
+            let res = update(&mut entry.condition);
+            Self::report_change(&mut entry.condition, events);
+            Ok(res)
+        })
+    }
+
+    /// Evaluate `value` against the limits of a limit alarm. An event is reported
+    /// if the limit state changed. Returns `true` if the limit state changed.
+    pub fn set_limit_value(&self, condition_id: &NodeId, value: f64) -> Result<bool, StatusCode> {
+        self.modify(condition_id, |entry, events| {
+            let changed = entry.condition.set_limit_value(value);
+            if changed {
+                Self::report_change(&mut entry.condition, events);
+            }
+            Ok(changed)
+        })
+    }
+
+    /// Create a new branch of a condition, containing a copy of the current state
+    /// of the condition. This is used to preserve a state that still requires action
+    /// from an operator, for example when an alarm becomes inactive before it was acknowledged.
+    ///
+    /// The branch is removed once it is acknowledged and confirmed.
+    /// Returns the ID of the new branch.
+    pub fn create_branch(&self, condition_id: &NodeId) -> Result<NodeId, StatusCode> {
+        self.modify(condition_id, |entry, events| {
+            let mut branch = entry.condition.new_branch();
+            let id = branch.branch_id().clone();
+            Self::report_change(&mut branch, events);
+            entry.branches.push(branch);
+            Ok(id)
+        })
+    }
+
+    /// Modify the entry of a condition, then report the events collected in
+    /// `modify`. Events are reported after the lock on the conditions is released,
+    /// since reporting an event locks the address space and the type tree.
+    fn modify<R>(
+        &self,
+        condition_id: &NodeId,
+        modify: impl FnOnce(&mut ConditionEntry, &mut Vec<Condition>) -> Result<R, StatusCode>,
+    ) -> Result<R, StatusCode> {
+        let mut events = Vec::new();
+        let (res, address_space) = {
+            let mut conditions = trace_write_lock!(self.conditions);
+            let entry = conditions
+                .get_mut(condition_id)
+                .ok_or(StatusCode::BadNodeIdUnknown)?;
+            (modify(entry, &mut events), entry.address_space.clone())
+        };
+        self.report(&address_space, &events);
+        res
+    }
+
+    /// Report events for conditions in `address_space`. The address space is
+    /// used to evaluate event filters, and to check that clients may receive the events.
+    fn report(&self, address_space: &RwLock<AddressSpace>, events: &[Condition]) {
+        if events.is_empty() {
+            return;
+        }
+        let address_space = trace_read_lock!(address_space);
+        self.subscriptions.notify_events_from_address_space(
+            events.iter().map(|c| (c as &dyn Event, c.source_node())),
+            &address_space,
+        );
+    }
+
+    fn report_change(condition: &mut Condition, events: &mut Vec<Condition>) {
+        condition.next_event();
+        if condition.is_enabled() {
+            events.push(condition.clone());
+        }
+    }
+
+    /// Handle a call to a method on a condition managed by this manager, or to
+    /// `ConditionRefresh`. Returns `None` if the call is not for the condition manager.
+    /// The status of the call is set to `Good` on success.
+    ///
+    /// The node manager owning the object is responsible for checking that the
+    /// method exists on the object, and that the user is allowed to call it.
+    pub(crate) fn call(
+        &self,
+        context: &RequestContext,
+        call: &mut MethodCall,
+    ) -> Option<Result<(), StatusCode>> {
+        let method = call.method_id().as_method_id().ok()?;
+        let is_refresh = matches!(
+            method,
+            MethodId::ConditionType_ConditionRefresh | MethodId::ConditionType_ConditionRefresh2
+        );
+        if !is_refresh && !self.is_condition_call(call) {
+            return None;
+        }
+
+        let res = match method {
+            MethodId::ConditionType_ConditionRefresh => load_method_args!(call, UInt32)
+                .and_then(|sub_id| self.refresh(context, sub_id, None)),
+            MethodId::ConditionType_ConditionRefresh2 => load_method_args!(call, UInt32, UInt32)
+                .and_then(|(sub_id, item_id)| self.refresh(context, sub_id, Some(item_id))),
+            MethodId::ConditionType_Enable => self.set_enabled(call.object_id(), true),
+            MethodId::ConditionType_Disable => self.set_enabled(call.object_id(), false),
+            _ => load_method_args!(call, ByteString, LocalizedText).and_then(
+                |(event_id, comment)| {
+                    let user = UAString::from(context.token.0.as_str());
+                    self.update_branch(call.object_id(), &event_id, |c| match method {
+                        MethodId::AcknowledgeableConditionType_Acknowledge => {
+                            c.acknowledge(*comment, user)
+                        }
+                        MethodId::AcknowledgeableConditionType_Confirm => c.confirm(*comment, user),
+                        _ => {
+                            c.add_comment(*comment, user);
+                            Ok(())
+                        }
+                    })
+                },
+            ),
+        };
+        if res.is_ok() {
+            call.set_status(StatusCode::Good);
+        }
+        Some(res)
+    }
+
+    fn set_enabled(&self, condition_id: &NodeId, enabled: bool) -> Result<(), StatusCode> {
+        self.modify(condition_id, |entry, events| {
+            entry.condition.set_enabled(enabled)?;
+            for branch in &mut entry.branches {
+                // Branches cannot be enabled or disabled independently.
+                let _ = branch.set_enabled(enabled);
+            }
+            // Report the change for all branches, this also tells clients that disabled
+            // conditions are no longer retained.
+            for cond in std::iter::once(&mut entry.condition).chain(entry.branches.iter_mut()) {
+                cond.next_event();
+                events.push(cond.clone());
+            }
+            Ok(())
+        })
+    }
+
+    /// Update the branch of a condition identified by the last reported `event_id`.
+    fn update_branch(
+        &self,
+        condition_id: &NodeId,
+        event_id: &opcua_types::ByteString,
+        update: impl FnOnce(&mut Condition) -> Result<(), StatusCode>,
+    ) -> Result<(), StatusCode> {
+        self.modify(condition_id, |entry, events| {
+            if !entry.condition.is_enabled() {
+                return Err(StatusCode::BadConditionDisabled);
+            }
+            if entry.condition.event_id() == event_id {
+                update(&mut entry.condition)?;
+                Self::report_change(&mut entry.condition, events);
+                return Ok(());
+            }
+            let idx = entry
+                .branches
+                .iter()
+                .position(|b| b.event_id() == event_id)
+                .ok_or(StatusCode::BadEventIdUnknown)?;
+            let branch = &mut entry.branches[idx];
+            update(branch)?;
+            Self::report_change(branch, events);
+            // Branches are only kept while they require action.
+            if !branch.retain() {
+                entry.branches.remove(idx);
+            }
+            Ok(())
+        })
+    }
+
+    fn refresh(
+        &self,
+        context: &RequestContext,
+        subscription_id: u32,
+        monitored_item_id: Option<u32>,
+    ) -> Result<(), StatusCode> {
+        let subs = context
+            .subscriptions
+            .get_session_subscriptions(context.session_id)
+            .ok_or(StatusCode::BadSubscriptionIdInvalid)?;
+        let roles = trace_read_lock!(context.session).roles().to_vec();
+
+        // Take a snapshot of the retained conditions, grouped by address space.
+        let mut retained: Vec<(Arc<RwLock<AddressSpace>>, Vec<Condition>)> = Vec::new();
+        for entry in trace_read_lock!(self.conditions).values() {
+            let conds = entry.iter().filter(|c| c.retain()).cloned();
+            match retained
+                .iter_mut()
+                .find(|(a, _)| Arc::ptr_eq(a, &entry.address_space))
+            {
+                Some((_, c)) => c.extend(conds),
+                None => retained.push((entry.address_space.clone(), conds.collect())),
+            }
+        }
+        // Lock the address spaces in a consistent order, and before the type tree
+        // and the subscriptions, like when events are reported.
+        retained.sort_by_key(|(a, _)| Arc::as_ptr(a) as usize);
+        let address_spaces: Vec<_> = retained.iter().map(|(a, _)| trace_read_lock!(a)).collect();
+
+        let type_tree = trace_read_lock!(context.type_tree);
+        let filter_context = EventFilterContext {
+            type_tree: &*type_tree,
+            references: None,
+        };
+        let mut subs = trace_lock!(subs);
+        let sub = subs
+            .get_mut(subscription_id)
+            .ok_or(StatusCode::BadSubscriptionIdInvalid)?;
+
+        let items: Vec<_> = sub
+            .items()
+            .filter(|i| {
+                i.item_to_monitor().attribute_id == AttributeId::EventNotifier
+                    && !matches!(monitored_item_id, Some(id) if id != i.id())
+            })
+            .map(|i| (i.id(), i.item_to_monitor().node_id.clone()))
+            .collect();
+        if monitored_item_id.is_some() && items.is_empty() {
+            return Err(StatusCode::BadMonitoredItemIdInvalid);
+        }
+
+        let server_id: NodeId = ObjectId::Server.into();
+        let refresh_event = |type_id: ObjectTypeId, message: &str| {
+            BaseEventType::new_now(type_id, Guid::new().into(), LocalizedText::from(message))
+                .set_source_node(server_id.clone())
+                .set_source_name("Server".into())
+                .set_severity(1)
+        };
+        let start = refresh_event(ObjectTypeId::RefreshStartEventType, "Refresh started");
+        let end = refresh_event(ObjectTypeId::RefreshEndEventType, "Refresh completed");
+
+        for (item_id, notifier) in items {
+            sub.notify_event(&item_id, &start, filter_context);
+            for ((_, conds), address_space) in retained.iter().zip(&address_spaces) {
+                let filter_context = EventFilterContext {
+                    type_tree: &*type_tree,
+                    references: Some(address_space.references()),
+                };
+                for cond in conds {
+                    if (notifier == server_id || &notifier == cond.source_node())
+                        && Self::can_receive(address_space, cond, &notifier, &roles)
+                    {
+                        sub.notify_event(&item_id, cond, filter_context);
+                    }
+                }
+            }
+            sub.notify_event(&item_id, &end, filter_context);
+        }
+
+        Ok(())
+    }
+
+    /// Check that a session with `roles` has the `ReceiveEvents` permission on the
+    /// source node of the condition and on the notifier, like when events are reported.
+    fn can_receive(
+        address_space: &AddressSpace,
+        condition: &Condition,
+        notifier: &NodeId,
+        roles: &[NodeId],
+    ) -> bool {
+        [notifier, condition.source_node()]
+            .into_iter()
+            .filter_map(|id| address_space.find(id)?.as_node().role_permissions())
+            .all(|rp| permissions_for_roles(rp, roles).contains(PermissionType::ReceiveEvents))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opcua_nodes::Event;
+    use opcua_types::{
+        AttributeId, LocalizedText, NodeId, NumericRange, ObjectTypeId, QualifiedName, Variant,
+    };
+
+    use super::{Condition, LimitAlarm, LimitStates};
+
+    fn field(cond: &Condition, path: &[&str]) -> Variant {
+        let path: Vec<_> = path.iter().map(|p| QualifiedName::new(0, *p)).collect();
+        cond.get_field(
+            &ObjectTypeId::ConditionType.into(),
+            AttributeId::Value,
+            &NumericRange::None,
+            &path,
+        )
+    }
+
+    #[test]
+    fn acknowledge_and_confirm() {
+        let mut cond = Condition::new(
+            NodeId::new(1, "alarm"),
+            ObjectTypeId::AlarmConditionType,
+            NodeId::new(1, "source"),
+            "Source",
+            "Alarm",
+        )
+        .with_acknowledge(true)
+        .with_alarm(NodeId::new(1, "source"));
+
+        assert!(!cond.retain());
+        cond.set_active(true);
+        assert_eq!(cond.is_acked(), Some(false));
+        assert!(cond.retain());
+        assert_eq!(
+            field(&cond, &["AckedState"]),
+            Variant::from(LocalizedText::from("Unacknowledged"))
+        );
+
+        cond.set_active(false);
+        // Still retained, since it is not acknowledged.
+        assert!(cond.retain());
+        cond.acknowledge(LocalizedText::from("Ack"), "user".into())
+            .unwrap();
+        assert_eq!(cond.is_confirmed(), Some(false));
+        assert!(cond.retain());
+        assert_eq!(field(&cond, &["AckedState", "Id"]), Variant::from(true));
+        assert_eq!(
+            field(&cond, &["Comment"]),
+            Variant::from(LocalizedText::from("Ack"))
+        );
+        assert!(cond
+            .acknowledge(LocalizedText::null(), "user".into())
+            .is_err());
+
+        cond.confirm(LocalizedText::null(), "user".into()).unwrap();
+        assert!(!cond.retain());
+        assert_eq!(field(&cond, &["Retain"]), Variant::from(false));
+
+        // Condition ID is the node ID of the condition itself.
+        assert_eq!(
+            cond.get_field(
+                &ObjectTypeId::ConditionType.into(),
+                AttributeId::NodeId,
+                &NumericRange::None,
+                &[]
+            ),
+            Variant::from(NodeId::new(1, "alarm"))
+        );
+    }
+
+    #[test]
+    fn limit_alarm() {
+        let limits = LimitAlarm::exclusive()
+            .high_high(100.0, 900)
+            .high(80.0, 500)
+            .low(20.0, 500)
+            .deadband(5.0);
+
+        assert_eq!(
+            limits.evaluate(101.0, LimitStates::default()),
+            LimitStates {
+                high_high: true,
+                ..Default::default()
+            }
+        );
+        let high = LimitStates {
+            high: true,
+            ..Default::default()
+        };
+        assert_eq!(limits.evaluate(85.0, LimitStates::default()), high);
+        // Within the deadband, so still high.
+        assert_eq!(limits.evaluate(76.0, high), high);
+        assert_eq!(limits.evaluate(74.0, high), LimitStates::default());
+        assert_eq!(
+            limits.evaluate(10.0, LimitStates::default()),
+            LimitStates {
+                low: true,
+                ..Default::default()
+            }
+        );
+
+        let non_exclusive = LimitAlarm::non_exclusive()
+            .high_high(100.0, 900)
+            .high(80.0, 500);
+        let states = non_exclusive.evaluate(101.0, LimitStates::default());
+        assert!(states.high && states.high_high);
+        assert_eq!(non_exclusive.severity(states), Some(900));
+
+        let mut cond = Condition::new(
+            NodeId::new(1, "alarm"),
+            ObjectTypeId::ExclusiveLimitAlarmType,
+            NodeId::new(1, "source"),
+            "Source",
+            "Alarm",
+        )
+        .with_severity(100)
+        .with_limits(NodeId::new(1, "source"), limits);
+        assert!(!cond.set_limit_value(50.0));
+        assert!(cond.set_limit_value(90.0));
+        assert_eq!(cond.is_active(), Some(true));
+        assert_eq!(cond.severity(), 500);
+        assert_eq!(
+            field(&cond, &["LimitState", "CurrentState"]),
+            Variant::from(LocalizedText::from("High"))
+        );
+        assert_eq!(field(&cond, &["HighLimit"]), Variant::from(80.0));
+        assert_eq!(field(&cond, &["LastSeverity"]), Variant::from(100u16));
+
+        assert!(cond.set_limit_value(50.0));
+        assert_eq!(cond.is_active(), Some(false));
+        assert_eq!(cond.severity(), 100);
+        assert_eq!(
+            field(&cond, &["LimitState", "CurrentState"]),
+            Variant::Empty
+        );
+    }
+}
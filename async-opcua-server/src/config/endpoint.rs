@@ -5,8 +5,9 @@ use std::{
 
 use serde::{Deserialize, Serialize};
 
+use opcua_core::comms::url::{OPC_TCP_SCHEME, OPC_WSS_SCHEME, OPC_WS_SCHEME};
 use opcua_crypto::SecurityPolicy;
-use opcua_types::MessageSecurityMode;
+use opcua_types::{profiles, MessageSecurityMode};
 
 use super::server::{ServerUserToken, ANONYMOUS_USER_TOKEN_ID};
 
@@ -25,6 +26,49 @@ pub struct ServerEndpoint {
     pub password_security_policy: Option<String>,
     /// User tokens
     pub user_token_ids: BTreeSet<String>,
+    /// Transport used to reach the endpoint. All transports share the same port.
+    #[serde(default)]
+    pub transport: EndpointTransport,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy, Default, Hash)]
+/// Transport used by a server endpoint.
+pub enum EndpointTransport {
+    /// OPC UA binary over TCP, using `opc.tcp` URLs.
+    #[default]
+    Tcp,
+    /// OPC UA binary over WebSockets, using `opc.ws` URLs.
+    WebSocket,
+    /// OPC UA binary over WebSockets with TLS, using `opc.wss` URLs.
+    /// This requires the `tls` feature.
+    WebSocketSecure,
+}
+
+impl EndpointTransport {
+    /// Get the URL scheme used for this transport.
+    pub fn scheme(&self) -> &'static str {
+        match self {
+            EndpointTransport::Tcp => OPC_TCP_SCHEME,
+            EndpointTransport::WebSocket => OPC_WS_SCHEME,
+            EndpointTransport::WebSocketSecure => OPC_WSS_SCHEME,
+        }
+    }
+
+    /// Return `true` if `url` uses the URL scheme of this transport.
+    pub fn matches_url(&self, url: &str) -> bool {
+        url.split_once("://")
+            .is_some_and(|(scheme, _)| scheme.eq_ignore_ascii_case(self.scheme()))
+    }
+
+    /// Get the transport profile URI for this transport.
+    pub fn profile_uri(&self) -> &'static str {
+        match self {
+            EndpointTransport::Tcp => profiles::TRANSPORT_PROFILE_URI_BINARY,
+            EndpointTransport::WebSocket | EndpointTransport::WebSocketSecure => {
+                profiles::TRANSPORT_PROFILE_URI_WSS_BINARY
+            }
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Hash, Eq)]
@@ -58,6 +102,7 @@ impl<'a> From<(&'a str, SecurityPolicy, MessageSecurityMode, &'a [&'a str])> for
             security_level: Self::security_level(v.1, v.2),
             password_security_policy: None,
             user_token_ids: v.3.iter().map(|id| id.to_string()).collect(),
+            transport: EndpointTransport::Tcp,
         }
     }
 }
@@ -80,9 +125,16 @@ impl ServerEndpoint {
             security_level: Self::security_level(security_policy, security_mode),
             password_security_policy: None,
             user_token_ids: user_token_ids.iter().cloned().collect(),
+            transport: EndpointTransport::Tcp,
         }
     }
 
+    /// Set the transport used by this endpoint.
+    pub fn with_transport(mut self, transport: EndpointTransport) -> Self {
+        self.transport = transport;
+        self
+    }
+
     /// Recommends a security level for the supplied security policy
     fn security_level(security_policy: SecurityPolicy, security_mode: MessageSecurityMode) -> u8 {
         let security_level = match security_policy {
@@ -91,6 +143,10 @@ impl ServerEndpoint {
             SecurityPolicy::Basic256 => 3,
             SecurityPolicy::Basic256Sha256 => 4,
             SecurityPolicy::Aes256Sha256RsaPss => 5,
+            SecurityPolicy::EccNistP256
+            | SecurityPolicy::EccBrainpoolP256r1
+            | SecurityPolicy::EccCurve25519 => 6,
+            SecurityPolicy::EccNistP384 => 7,
             _ => 0,
         };
         if security_mode == MessageSecurityMode::SignAndEncrypt {
@@ -303,7 +359,9 @@ impl ServerEndpoint {
         let security_policy = SecurityPolicy::from_str(&self.security_policy).unwrap();
         let security_mode = MessageSecurityMode::from(self.security_mode.as_ref());
         if security_policy == SecurityPolicy::Unknown {
-            errors.push(format!("Endpoint {} is invalid. Security policy \"{}\" is invalid. Valid values are None, Basic128Rsa15, Basic256, Basic256Sha256, Aes128Sha256RsaOaep, Aes256Sha256RsaPss,", id, self.security_policy));
+            errors.push(format!("Endpoint {} is invalid. Security policy \"{}\" is invalid. Valid values are None, Basic128Rsa15, Basic256, Basic256Sha256, Aes128Sha256RsaOaep, Aes256Sha256RsaPss, ECC_nistP256, ECC_nistP384, ECC_brainpoolP256r1, ECC_curve25519", id, self.security_policy));
+        } else if !security_policy.is_supported() {
+            errors.push(format!("Endpoint {} is invalid. Security policy \"{}\" is not supported, ECC_brainpoolP256r1 requires the brainpool feature", id, self.security_policy));
         } else if security_mode == MessageSecurityMode::Invalid {
             errors.push(format!("Endpoint {} is invalid. Security mode \"{}\" is invalid. Valid values are None, Sign, SignAndEncrypt", id, self.security_mode));
         } else if (security_policy == SecurityPolicy::None
@@ -318,6 +376,13 @@ impl ServerEndpoint {
             errors.push(format!("Endpoint {} is invalid. Security policy and security mode must both contain None or neither of them should (2).", id));
         }
 
+        if self.transport == EndpointTransport::WebSocketSecure && !cfg!(feature = "tls") {
+            errors.push(format!(
+                "Endpoint {} is invalid. The WebSocketSecure transport requires the tls feature",
+                id
+            ));
+        }
+
         if errors.is_empty() {
             Ok(())
         } else {
@@ -336,8 +401,12 @@ impl ServerEndpoint {
     }
 
     /// Get the URL of this endpoint, with `base_endpoint` as root.
+    /// The scheme of `base_endpoint` is replaced by the scheme of the endpoint transport.
     pub fn endpoint_url(&self, base_endpoint: &str) -> String {
-        format!("{}{}", base_endpoint, self.path)
+        match base_endpoint.split_once("://") {
+            Some((_, rest)) => format!("{}://{}{}", self.transport.scheme(), rest, self.path),
+            None => format!("{}{}", base_endpoint, self.path),
+        }
     }
 
     /// Returns the effective password security policy for the endpoint. This is the explicitly set password
@@ -42,6 +42,9 @@ pub struct Limits {
     /// Maximum number of registered sessions before new ones are rejected.
     #[serde(default = "defaults::max_sessions")]
     pub max_sessions: usize,
+    /// Maximum size in bytes of the trust list written through the `ServerConfiguration` object.
+    #[serde(default = "defaults::max_trust_list_size")]
+    pub max_trust_list_size: usize,
 }
 
 impl Default for Limits {
@@ -60,6 +63,7 @@ impl Default for Limits {
             max_query_continuation_points: defaults::max_query_continuation_points(),
             operational: OperationalLimits::default(),
             max_sessions: defaults::max_sessions(),
+            max_trust_list_size: defaults::max_trust_list_size(),
         }
     }
 }
@@ -104,6 +108,13 @@ pub struct SubscriptionLimits {
     /// Maximum number of queued notifications per subscription. 0 for unlimited.
     #[serde(default = "defaults::max_queued_notifications")]
     pub max_queued_notifications: usize,
+    /// Maximum processing interval of aggregate filters on monitored items, in milliseconds.
+    #[serde(default = "defaults::max_aggregate_processing_interval_ms")]
+    pub max_aggregate_processing_interval_ms: f64,
+    /// Maximum number of raw samples kept by a monitored item with an aggregate filter.
+    /// The oldest samples are dropped beyond this.
+    #[serde(default = "defaults::max_aggregate_samples")]
+    pub max_aggregate_samples: usize,
 }
 
 impl Default for SubscriptionLimits {
@@ -122,6 +133,8 @@ impl Default for SubscriptionLimits {
             max_lifetime_count: defaults::max_lifetime_count(),
             max_notifications_per_publish: defaults::max_notifications_per_publish(),
             max_queued_notifications: defaults::max_queued_notifications(),
+            max_aggregate_processing_interval_ms: defaults::max_aggregate_processing_interval_ms(),
+            max_aggregate_samples: defaults::max_aggregate_samples(),
         }
     }
 }
@@ -159,6 +172,10 @@ pub struct OperationalLimits {
     /// Maximum number of nodes per history update call.
     #[serde(default = "defaults::max_nodes_per_history_update")]
     pub max_nodes_per_history_update: usize,
+    /// Maximum number of processing intervals per node in a processed history read,
+    /// 0 for no limit.
+    #[serde(default = "defaults::max_processed_intervals_per_history_read")]
+    pub max_processed_intervals_per_history_read: usize,
     /// Maximum number of references per node during browse.
     #[serde(default = "defaults::max_references_per_browse_node")]
     pub max_references_per_browse_node: usize,
@@ -171,6 +188,11 @@ pub struct OperationalLimits {
     /// Maximum number of references per data set on query calls.
     #[serde(default = "defaults::max_references_query_return")]
     pub max_references_query_return: usize,
+    /// Maximum number of nodes each node manager may visit while evaluating a query,
+    /// including nodes reached through `RelatedTo` operators. Queries exceeding this
+    /// fail with `BadQueryTooComplex`.
+    #[serde(default = "defaults::max_nodes_visited_per_query")]
+    pub max_nodes_visited_per_query: usize,
     /// Maximum number of nodes per add/delete nodes call.
     #[serde(default = "defaults::max_nodes_per_node_management")]
     pub max_nodes_per_node_management: usize,
@@ -196,10 +218,13 @@ impl Default for OperationalLimits {
             max_nodes_per_history_read_data: defaults::max_nodes_per_history_read_data(),
             max_nodes_per_history_read_events: defaults::max_nodes_per_history_read_events(),
             max_nodes_per_history_update: defaults::max_nodes_per_history_update(),
+            max_processed_intervals_per_history_read:
+                defaults::max_processed_intervals_per_history_read(),
             max_references_per_browse_node: defaults::max_references_per_browse_node(),
             max_node_descs_per_query: defaults::max_node_descs_per_query(),
             max_data_sets_query_return: defaults::max_data_sets_query_return(),
             max_references_query_return: defaults::max_references_query_return(),
+            max_nodes_visited_per_query: defaults::max_nodes_visited_per_query(),
             max_nodes_per_node_management: defaults::max_nodes_per_node_management(),
             max_references_per_references_management:
                 defaults::max_references_per_references_management(),
@@ -243,6 +268,9 @@ mod defaults {
     pub(super) fn max_sessions() -> usize {
         constants::MAX_SESSIONS
     }
+    pub(super) fn max_trust_list_size() -> usize {
+        constants::MAX_TRUST_LIST_SIZE
+    }
 
     pub(super) fn max_subscriptions_per_session() -> usize {
         constants::MAX_SUBSCRIPTIONS_PER_SESSION
@@ -280,6 +308,12 @@ mod defaults {
     pub(super) fn max_queued_notifications() -> usize {
         constants::MAX_QUEUED_NOTIFICATIONS
     }
+    pub(super) fn max_aggregate_processing_interval_ms() -> f64 {
+        constants::MAX_AGGREGATE_PROCESSING_INTERVAL_MS
+    }
+    pub(super) fn max_aggregate_samples() -> usize {
+        constants::MAX_AGGREGATE_SAMPLES
+    }
 
     pub(super) fn max_nodes_per_translate_browse_paths_to_node_ids() -> usize {
         constants::MAX_NODES_PER_TRANSLATE_BROWSE_PATHS_TO_NODE_IDS
@@ -311,6 +345,9 @@ mod defaults {
     pub(super) fn max_nodes_per_history_update() -> usize {
         constants::MAX_NODES_PER_HISTORY_UPDATE
     }
+    pub(super) fn max_processed_intervals_per_history_read() -> usize {
+        constants::MAX_PROCESSED_INTERVALS_PER_HISTORY_READ
+    }
     pub(super) fn max_references_per_browse_node() -> usize {
         constants::MAX_REFERENCES_PER_BROWSE_NODE
     }
@@ -323,6 +360,9 @@ mod defaults {
     pub(super) fn max_references_query_return() -> usize {
         constants::MAX_REFERENCES_QUERY_RETURN
     }
+    pub(super) fn max_nodes_visited_per_query() -> usize {
+        constants::MAX_NODES_VISITED_PER_QUERY
+    }
     pub(super) fn max_nodes_per_node_management() -> usize {
         constants::MAX_NODES_PER_NODE_MANAGEMENT
     }
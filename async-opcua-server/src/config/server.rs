@@ -13,7 +13,10 @@ use serde::{Deserialize, Serialize};
 use tracing::{trace, warn};
 
 use crate::constants;
-use opcua_core::{comms::url::url_matches_except_host, config::Config};
+use opcua_core::{
+    comms::url::{is_opc_ua_binary_url, is_websocket_url, url_matches_except_host},
+    config::Config,
+};
 use opcua_crypto::{CertificateStore, SecurityPolicy, Thumbprint};
 use opcua_types::{
     ApplicationDescription, ApplicationType, DecodingOptions, LocalizedText, MessageSecurityMode,
@@ -36,6 +39,60 @@ pub struct TcpConfig {
     pub port: u16,
 }
 
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+/// Configuration for a client the server should connect to using reverse connect.
+/// The server opens a connection to the client and sends a `ReverseHello` message,
+/// after which the client establishes a secure channel as normal.
+pub struct ReverseConnectConfig {
+    /// URL of the client to connect to, for example `opc.tcp://client:4844`.
+    pub client_url: String,
+    /// Endpoint URL sent to the client in the `ReverseHello` message. If this is
+    /// not set, the URL of the default endpoint, or the first endpoint, is used.
+    #[serde(default)]
+    pub endpoint_url: Option<String>,
+    /// Initial delay in milliseconds before reconnecting after a failed connection
+    /// attempt. The delay is doubled for each consecutive failure, and reset once
+    /// the client completes the handshake on a connection.
+    #[serde(default = "defaults::reverse_connect_retry_interval_ms")]
+    pub retry_interval_ms: u64,
+    /// Maximum delay in milliseconds between connection attempts.
+    #[serde(default = "defaults::reverse_connect_max_retry_interval_ms")]
+    pub max_retry_interval_ms: u64,
+}
+
+impl ReverseConnectConfig {
+    /// Create a new reverse connect configuration for the client at `client_url`,
+    /// using default retry intervals.
+    pub fn new(client_url: impl Into<String>) -> Self {
+        Self {
+            client_url: client_url.into(),
+            endpoint_url: None,
+            retry_interval_ms: defaults::reverse_connect_retry_interval_ms(),
+            max_retry_interval_ms: defaults::reverse_connect_max_retry_interval_ms(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+/// Configuration for running the server as a local discovery server (LDS).
+/// Other servers can register themselves using `RegisterServer` and `RegisterServer2`,
+/// and are returned from `FindServers` and `FindServersOnNetwork` until they unregister
+/// or their registration expires.
+pub struct DiscoveryServerConfig {
+    /// Time in seconds before a registration expires if the server does not register again.
+    /// Servers are expected to re-register at most every 10 minutes.
+    #[serde(default = "defaults::registration_timeout_secs")]
+    pub registration_timeout_secs: u64,
+}
+
+impl Default for DiscoveryServerConfig {
+    fn default() -> Self {
+        Self {
+            registration_timeout_secs: defaults::registration_timeout_secs(),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
 /// User token handled by the default authenticator.
 pub struct ServerUserToken {
@@ -53,6 +110,9 @@ pub struct ServerUserToken {
     #[serde(default)]
     /// Access to read diagnostics on the server.
     pub read_diagnostics: bool,
+    #[serde(default)]
+    /// Access to manage the server certificate and trust list.
+    pub manage_certificates: bool,
 }
 
 impl ServerUserToken {
@@ -67,6 +127,7 @@ impl ServerUserToken {
             x509: None,
             thumbprint: None,
             read_diagnostics: false,
+            manage_certificates: false,
         }
     }
 
@@ -81,6 +142,7 @@ impl ServerUserToken {
             x509: Some(cert_path.to_string_lossy().to_string()),
             thumbprint: None,
             read_diagnostics: false,
+            manage_certificates: false,
         }
     }
 
@@ -144,6 +206,12 @@ impl ServerUserToken {
         self.read_diagnostics = read;
         self
     }
+
+    /// Set the ability for the user to manage the server certificate and trust list.
+    pub fn manage_certificates(mut self, manage: bool) -> Self {
+        self.manage_certificates = manage;
+        self
+    }
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
@@ -154,6 +222,10 @@ pub struct CertificateValidation {
     pub trust_client_certs: bool,
     /// Check the valid from/to fields of a certificate
     pub check_time: bool,
+    /// Reject certificates whose revocation status is unknown, because a certificate
+    /// authority in the chain has no current revocation list.
+    #[serde(default)]
+    pub reject_unknown_revocation_status: bool,
 }
 
 impl Default for CertificateValidation {
@@ -161,6 +233,7 @@ impl Default for CertificateValidation {
         Self {
             trust_client_certs: false,
             check_time: true,
+            reject_unknown_revocation_status: false,
         }
     }
 }
@@ -239,6 +312,14 @@ pub struct ServerConfig {
     /// Enable server diagnostics.
     #[serde(default)]
     pub diagnostics: bool,
+    /// Clients the server connects to using reverse connect, in addition
+    /// to listening for incoming connections.
+    #[serde(default)]
+    pub reverse_connect: Vec<ReverseConnectConfig>,
+    /// Run the server as a local discovery server, keeping a registry of
+    /// other servers on the network.
+    #[serde(default)]
+    pub discovery_server: Option<DiscoveryServerConfig>,
 }
 
 mod defaults {
@@ -263,6 +344,18 @@ mod defaults {
     pub(super) fn max_session_timeout_ms() -> u64 {
         constants::MAX_SESSION_TIMEOUT
     }
+
+    pub(super) fn reverse_connect_retry_interval_ms() -> u64 {
+        1_000
+    }
+
+    pub(super) fn reverse_connect_max_retry_interval_ms() -> u64 {
+        30_000
+    }
+
+    pub(super) fn registration_timeout_secs() -> u64 {
+        600
+    }
 }
 
 impl Config for ServerConfig {
@@ -317,6 +410,14 @@ impl Config for ServerConfig {
         if self.discovery_urls.is_empty() {
             errors.push("Server configuration is invalid. Discovery urls not set".to_owned());
         }
+        for reverse in &self.reverse_connect {
+            if !is_opc_ua_binary_url(&reverse.client_url) || is_websocket_url(&reverse.client_url) {
+                errors.push(format!(
+                    "Reverse connect client url {} is not a valid opc.tcp url",
+                    reverse.client_url
+                ));
+            }
+        }
 
         if errors.is_empty() {
             Ok(())
@@ -338,7 +439,11 @@ impl Config for ServerConfig {
     }
 
     fn application_type(&self) -> ApplicationType {
-        ApplicationType::Server
+        if self.discovery_server.is_some() {
+            ApplicationType::DiscoveryServer
+        } else {
+            ApplicationType::Server
+        }
     }
 
     fn discovery_urls(&self) -> Option<Vec<UAString>> {
@@ -392,6 +497,8 @@ impl Default for ServerConfig {
             max_secure_channel_token_lifetime_ms: defaults::max_secure_channel_token_lifetime_ms(),
             max_session_timeout_ms: defaults::max_session_timeout_ms(),
             diagnostics: false,
+            reverse_connect: Vec::new(),
+            discovery_server: None,
         }
     }
 }
@@ -430,6 +537,7 @@ impl ServerConfig {
             certificate_validation: CertificateValidation {
                 trust_client_certs: false,
                 check_time: true,
+                reject_unknown_revocation_status: false,
             },
             pki_dir,
             discovery_server_url,
@@ -20,9 +20,9 @@ use crate::{
 };
 use opcua_types::{
     AccessLevelExType, AccessRestrictionType, AttributeId, BrowseDirection, DataTypeId, DataValue,
-    DateTime, ExpandedNodeId, ExtensionObject, IdType, LocalizedText, NodeClass, NodeId,
-    NumericRange, ObjectId, ObjectTypeId, QualifiedName, ReferenceDescription, ReferenceTypeId,
-    RolePermissionType, StatusCode, TimestampsToReturn, VariableTypeId, Variant,
+    DateTime, ExpandedNodeId, ExtensionObject, IdType, Identifier, LocalizedText, NodeClass,
+    NodeId, NumericRange, ObjectId, ObjectTypeId, QualifiedName, ReferenceDescription,
+    ReferenceTypeId, RolePermissionType, StatusCode, TimestampsToReturn, VariableTypeId, Variant,
 };
 
 /// Node manager handling nodes in the server hierarchy that are not part of the
@@ -551,7 +551,9 @@ impl DiagnosticsNodeManager {
 #[async_trait]
 impl NodeManager for DiagnosticsNodeManager {
     fn owns_node(&self, id: &NodeId) -> bool {
-        id.namespace == self.namespace_index
+        // Roles added at runtime are created in the server namespace with GUID
+        // identifiers, and are owned by the core node manager.
+        id.namespace == self.namespace_index && !matches!(id.identifier, Identifier::Guid(_))
     }
 
     fn name(&self) -> &str {
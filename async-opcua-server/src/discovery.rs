@@ -81,9 +81,11 @@ pub(crate) async fn periodic_discovery_server_registration(
     let mut interval = tokio::time::interval(interval);
     interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
+    // The client uses the certificate of the server, so it must also use the
+    // application URI of the server.
     let client = ClientBuilder::new()
         .application_name("DiscoveryClient")
-        .application_uri("urn:DiscoveryClient")
+        .application_uri(registered_server.server_uri.as_ref())
         .pki_dir(pki_dir)
         .session_retry_limit(1)
         .client();
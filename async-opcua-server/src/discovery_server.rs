@@ -0,0 +1,477 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2024 Adam Lock
+
+//! Registry of servers used when running as a local discovery server.
+
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use opcua_core::sync::Mutex;
+use opcua_types::{
+    ApplicationDescription, ApplicationType, DateTime, ExtensionObject, LocalizedText,
+    MdnsDiscoveryConfiguration, RegisteredServer, ServerOnNetwork, StatusCode, UAString,
+};
+use tracing::{debug, info};
+
+use crate::config::DiscoveryServerConfig;
+
+struct Registration {
+    server: RegisteredServer,
+    mdns_server_name: UAString,
+    server_capabilities: Vec<UAString>,
+    /// Record ID for each discovery URL, in the same order as `server.discovery_urls`.
+    record_ids: Vec<u32>,
+    registered_at: Instant,
+}
+
+impl Registration {
+    fn is_expired(&self, timeout: Duration) -> bool {
+        if self.registered_at.elapsed() > timeout {
+            return true;
+        }
+        // A registered server may point to a semaphore file, once
+        // the file is gone the registration is no longer valid.
+        !self.server.semaphore_file_path.is_empty()
+            && !Path::new(self.server.semaphore_file_path.as_ref()).exists()
+    }
+
+    fn discovery_urls(&self) -> impl Iterator<Item = &UAString> {
+        self.server.discovery_urls.iter().flatten()
+    }
+}
+
+struct RegistryState {
+    next_record_id: u32,
+    servers: BTreeMap<String, Registration>,
+}
+
+/// Registry of servers registered with this server using `RegisterServer` or
+/// `RegisterServer2`.
+pub(crate) struct DiscoveryServerRegistry {
+    registration_timeout: Duration,
+    counter_reset_time: DateTime,
+    state: Mutex<RegistryState>,
+}
+
+impl DiscoveryServerRegistry {
+    pub(crate) fn new(config: &DiscoveryServerConfig) -> Self {
+        Self {
+            registration_timeout: Duration::from_secs(config.registration_timeout_secs),
+            counter_reset_time: DateTime::now(),
+            state: Mutex::new(RegistryState {
+                next_record_id: 1,
+                servers: BTreeMap::new(),
+            }),
+        }
+    }
+
+    fn validate(server: &RegisteredServer) -> Result<(), StatusCode> {
+        if server.server_uri.is_empty() {
+            return Err(StatusCode::BadServerUriInvalid);
+        }
+        let has_name = server
+            .server_names
+            .iter()
+            .flatten()
+            .any(|n| !n.text.is_empty());
+        if !has_name {
+            return Err(StatusCode::BadServerNameMissing);
+        }
+        if server.server_type == ApplicationType::Client {
+            return Err(StatusCode::BadInvalidArgument);
+        }
+        if server.is_online {
+            let has_url = server
+                .discovery_urls
+                .iter()
+                .flatten()
+                .any(|u| !u.is_empty());
+            if !has_url {
+                return Err(StatusCode::BadDiscoveryUrlMissing);
+            }
+            if !server.semaphore_file_path.is_empty()
+                && !Path::new(server.semaphore_file_path.as_ref()).exists()
+            {
+                return Err(StatusCode::BadSempahoreFileMissing);
+            }
+        }
+        Ok(())
+    }
+
+    fn purge_expired(&self, state: &mut RegistryState) {
+        state.servers.retain(|uri, reg| {
+            let expired = reg.is_expired(self.registration_timeout);
+            if expired {
+                info!("Registration of server {uri} has expired");
+            }
+            !expired
+        });
+    }
+
+    /// Register a server, or remove it from the registry if it is not online.
+    ///
+    /// If `mdns` is `None` the mDNS configuration of an existing registration is kept.
+    pub(crate) fn register(
+        &self,
+        server: RegisteredServer,
+        mdns: Option<MdnsDiscoveryConfiguration>,
+    ) -> Result<(), StatusCode> {
+        Self::validate(&server)?;
+
+        let mut state = self.state.lock();
+        let uri = server.server_uri.as_ref().to_owned();
+        if !server.is_online {
+            if state.servers.remove(&uri).is_some() {
+                info!("Server {uri} unregistered");
+            }
+            return Ok(());
+        }
+
+        let previous = state.servers.remove(&uri);
+        // Keep the record IDs of discovery URLs that were registered before.
+        let mut record_ids = Vec::new();
+        for url in server.discovery_urls.iter().flatten() {
+            let existing = previous.as_ref().and_then(|p| {
+                p.discovery_urls()
+                    .zip(p.record_ids.iter())
+                    .find(|(u, _)| *u == url)
+                    .map(|(_, id)| *id)
+            });
+            let id = match existing {
+                Some(id) => id,
+                None => {
+                    let id = state.next_record_id;
+                    state.next_record_id += 1;
+                    id
+                }
+            };
+            record_ids.push(id);
+        }
+
+        let (mdns_server_name, server_capabilities) = match (mdns, previous.as_ref()) {
+            (Some(mdns), _) if !mdns.mdns_server_name.is_empty() => (
+                mdns.mdns_server_name,
+                mdns.server_capabilities.unwrap_or_default(),
+            ),
+            (Some(mdns), _) => (
+                Self::default_mdns_name(&server),
+                mdns.server_capabilities.unwrap_or_default(),
+            ),
+            // A plain RegisterServer, for example to renew a registration made with
+            // RegisterServer2, does not remove the existing discovery configuration.
+            (None, Some(previous)) => (
+                previous.mdns_server_name.clone(),
+                previous.server_capabilities.clone(),
+            ),
+            (None, None) => (Self::default_mdns_name(&server), Vec::new()),
+        };
+
+        if previous.is_none() {
+            info!("Server {uri} registered");
+        } else {
+            debug!("Server {uri} registered again");
+        }
+        state.servers.insert(
+            uri,
+            Registration {
+                server,
+                mdns_server_name,
+                server_capabilities,
+                record_ids,
+                registered_at: Instant::now(),
+            },
+        );
+        Ok(())
+    }
+
+    fn default_mdns_name(server: &RegisteredServer) -> UAString {
+        server
+            .server_names
+            .iter()
+            .flatten()
+            .map(|n| n.text.clone())
+            .find(|n| !n.is_empty())
+            .unwrap_or_default()
+    }
+
+    /// Register a server using `RegisterServer2`, returning a status code for each
+    /// discovery configuration. Only [`MdnsDiscoveryConfiguration`] is supported.
+    pub(crate) fn register2(
+        &self,
+        server: RegisteredServer,
+        discovery_configuration: Option<Vec<ExtensionObject>>,
+    ) -> Result<Vec<StatusCode>, StatusCode> {
+        let mut mdns = None;
+        let mut results = Vec::new();
+        for config in discovery_configuration.into_iter().flatten() {
+            match config.into_inner_as::<MdnsDiscoveryConfiguration>() {
+                Some(c) => {
+                    mdns = Some(*c);
+                    results.push(StatusCode::Good);
+                }
+                None => results.push(StatusCode::BadNotSupported),
+            }
+        }
+        self.register(server, mdns)?;
+        Ok(results)
+    }
+
+    fn server_name(names: &[LocalizedText], locale_ids: &[UAString]) -> LocalizedText {
+        locale_ids
+            .iter()
+            .find_map(|locale| names.iter().find(|n| n.locale == *locale))
+            .or_else(|| names.first())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Get application descriptions of all registered servers, with names
+    /// in the first matching locale in `locale_ids`.
+    pub(crate) fn find_servers(&self, locale_ids: &[UAString]) -> Vec<ApplicationDescription> {
+        let mut state = self.state.lock();
+        self.purge_expired(&mut state);
+        state
+            .servers
+            .values()
+            .map(|reg| {
+                let server = &reg.server;
+                ApplicationDescription {
+                    application_uri: server.server_uri.clone(),
+                    product_uri: server.product_uri.clone(),
+                    application_name: Self::server_name(
+                        server.server_names.as_deref().unwrap_or_default(),
+                        locale_ids,
+                    ),
+                    application_type: server.server_type,
+                    gateway_server_uri: server.gateway_server_uri.clone(),
+                    discovery_profile_uri: UAString::null(),
+                    discovery_urls: server.discovery_urls.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Get registered servers on the network, one record per discovery URL, ordered by
+    /// record ID. Returns the time the record IDs were last reset, and the list of records.
+    pub(crate) fn find_servers_on_network(
+        &self,
+        starting_record_id: u32,
+        max_records_to_return: u32,
+        server_capability_filter: &[UAString],
+    ) -> (DateTime, Vec<ServerOnNetwork>) {
+        let mut state = self.state.lock();
+        self.purge_expired(&mut state);
+        let mut records: Vec<_> = state
+            .servers
+            .values()
+            .filter(|reg| {
+                server_capability_filter
+                    .iter()
+                    .all(|c| reg.server_capabilities.contains(c))
+            })
+            .flat_map(|reg| {
+                reg.discovery_urls()
+                    .zip(reg.record_ids.iter())
+                    .filter(|(_, id)| **id > starting_record_id)
+                    .map(|(url, id)| ServerOnNetwork {
+                        record_id: *id,
+                        server_name: reg.mdns_server_name.clone(),
+                        discovery_url: url.clone(),
+                        server_capabilities: Some(reg.server_capabilities.clone()),
+                    })
+            })
+            .collect();
+        records.sort_by_key(|r| r.record_id);
+        if max_records_to_return > 0 {
+            records.truncate(max_records_to_return as usize);
+        }
+        (self.counter_reset_time, records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use opcua_types::{
+        ApplicationType, Argument, ExtensionObject, LocalizedText, MdnsDiscoveryConfiguration,
+        RegisteredServer, StatusCode, UAString,
+    };
+
+    use super::DiscoveryServerRegistry;
+    use crate::config::DiscoveryServerConfig;
+
+    fn server(uri: &str, urls: &[&str]) -> RegisteredServer {
+        RegisteredServer {
+            server_uri: uri.into(),
+            product_uri: "urn:product".into(),
+            server_names: Some(vec![
+                LocalizedText::new("en", "Server"),
+                LocalizedText::new("de", "Server DE"),
+            ]),
+            server_type: ApplicationType::Server,
+            gateway_server_uri: UAString::null(),
+            discovery_urls: Some(urls.iter().map(|u| (*u).into()).collect()),
+            semaphore_file_path: UAString::null(),
+            is_online: true,
+        }
+    }
+
+    fn registry() -> DiscoveryServerRegistry {
+        DiscoveryServerRegistry::new(&DiscoveryServerConfig::default())
+    }
+
+    #[test]
+    fn register_validation() {
+        let registry = registry();
+        let mut s = server("", &["opc.tcp://localhost:4855"]);
+        assert_eq!(
+            registry.register(s, None),
+            Err(StatusCode::BadServerUriInvalid)
+        );
+
+        s = server("urn:server", &["opc.tcp://localhost:4855"]);
+        s.server_names = None;
+        assert_eq!(
+            registry.register(s, None),
+            Err(StatusCode::BadServerNameMissing)
+        );
+
+        s = server("urn:server", &[]);
+        assert_eq!(
+            registry.register(s, None),
+            Err(StatusCode::BadDiscoveryUrlMissing)
+        );
+
+        s = server("urn:server", &["opc.tcp://localhost:4855"]);
+        s.server_type = ApplicationType::Client;
+        assert_eq!(
+            registry.register(s, None),
+            Err(StatusCode::BadInvalidArgument)
+        );
+
+        s = server("urn:server", &["opc.tcp://localhost:4855"]);
+        s.semaphore_file_path = "/does/not/exist.sem".into();
+        assert_eq!(
+            registry.register(s, None),
+            Err(StatusCode::BadSempahoreFileMissing)
+        );
+
+        assert!(registry.find_servers(&[]).is_empty());
+    }
+
+    #[test]
+    fn register_and_unregister() {
+        let registry = registry();
+        registry
+            .register(server("urn:server", &["opc.tcp://localhost:4855"]), None)
+            .unwrap();
+        let servers = registry.find_servers(&["de".into()]);
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].application_uri.as_ref(), "urn:server");
+        assert_eq!(servers[0].application_name.text.as_ref(), "Server DE");
+        let servers = registry.find_servers(&["fr".into()]);
+        assert_eq!(servers[0].application_name.text.as_ref(), "Server");
+
+        let mut s = server("urn:server", &[]);
+        s.is_online = false;
+        registry.register(s, None).unwrap();
+        assert!(registry.find_servers(&[]).is_empty());
+    }
+
+    #[test]
+    fn registration_expiry() {
+        let registry = DiscoveryServerRegistry::new(&DiscoveryServerConfig {
+            registration_timeout_secs: 0,
+        });
+        registry
+            .register(server("urn:server", &["opc.tcp://localhost:4855"]), None)
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(registry.find_servers(&[]).is_empty());
+    }
+
+    #[test]
+    fn semaphore_file() {
+        let dir = std::env::temp_dir().join(format!("opcua-lds-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("server.sem");
+        std::fs::write(&path, b"").unwrap();
+
+        let registry = registry();
+        let mut s = server("urn:server", &["opc.tcp://localhost:4855"]);
+        s.semaphore_file_path = path.to_str().unwrap().into();
+        registry.register(s, None).unwrap();
+        assert_eq!(registry.find_servers(&[]).len(), 1);
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(registry.find_servers(&[]).is_empty());
+        let _ = std::fs::remove_dir(&dir);
+    }
+
+    #[test]
+    fn register2_and_find_on_network() {
+        let registry = registry();
+        let results = registry
+            .register2(
+                server(
+                    "urn:server1",
+                    &["opc.tcp://localhost:4855", "opc.tcp://localhost:4856"],
+                ),
+                Some(vec![
+                    ExtensionObject::from_message(MdnsDiscoveryConfiguration {
+                        mdns_server_name: "Server1".into(),
+                        server_capabilities: Some(vec!["DA".into(), "HD".into()]),
+                    }),
+                    ExtensionObject::from_message(Argument::default()),
+                ]),
+            )
+            .unwrap();
+        assert_eq!(results, vec![StatusCode::Good, StatusCode::BadNotSupported]);
+        registry
+            .register(server("urn:server2", &["opc.tcp://localhost:4857"]), None)
+            .unwrap();
+
+        let (_, records) = registry.find_servers_on_network(0, 0, &[]);
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records.iter().map(|r| r.record_id).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(records[0].server_name.as_ref(), "Server1");
+        assert_eq!(records[2].server_name.as_ref(), "Server");
+
+        let (_, records) = registry.find_servers_on_network(1, 1, &[]);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].record_id, 2);
+
+        let (_, records) = registry.find_servers_on_network(0, 0, &["DA".into()]);
+        assert_eq!(records.len(), 2);
+        let (_, records) = registry.find_servers_on_network(0, 0, &["DA".into(), "AC".into()]);
+        assert!(records.is_empty());
+
+        // Registering again keeps existing record IDs.
+        registry
+            .register(
+                server(
+                    "urn:server1",
+                    &["opc.tcp://localhost:4856", "opc.tcp://localhost:4858"],
+                ),
+                None,
+            )
+            .unwrap();
+        let (_, records) = registry.find_servers_on_network(0, 0, &[]);
+        assert_eq!(
+            records.iter().map(|r| r.record_id).collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+        // The mDNS configuration from RegisterServer2 is kept.
+        assert_eq!(records[0].server_name.as_ref(), "Server1");
+        let (_, records) = registry.find_servers_on_network(0, 0, &["DA".into()]);
+        assert_eq!(records.len(), 2);
+    }
+}
@@ -12,10 +12,18 @@ pub(crate) const POLICY_ID_USER_PASS_NONE: &str = "userpass_none";
 pub(crate) const POLICY_ID_USER_PASS_RSA_15: &str = "userpass_rsa_15";
 pub(crate) const POLICY_ID_USER_PASS_RSA_OAEP: &str = "userpass_rsa_oaep";
 pub(crate) const POLICY_ID_USER_PASS_RSA_OAEP_SHA256: &str = "userpass_rsa_oaep_sha256";
+pub(crate) const POLICY_ID_USER_PASS_ECC_NIST_P256: &str = "userpass_ecc_nistp256";
+pub(crate) const POLICY_ID_USER_PASS_ECC_NIST_P384: &str = "userpass_ecc_nistp384";
+pub(crate) const POLICY_ID_USER_PASS_ECC_BRAINPOOL_P256R1: &str = "userpass_ecc_brainpoolp256r1";
+pub(crate) const POLICY_ID_USER_PASS_ECC_CURVE25519: &str = "userpass_ecc_curve25519";
 pub(crate) const POLICY_ID_ISSUED_TOKEN_NONE: &str = "userpass_none";
 pub(crate) const POLICY_ID_ISSUED_TOKEN_RSA_15: &str = "userpass_rsa_15";
 pub(crate) const POLICY_ID_ISSUED_TOKEN_RSA_OAEP: &str = "userpass_rsa_oaep";
 pub(crate) const POLICY_ID_ISSUED_TOKEN_RSA_OAEP_SHA256: &str = "userpass_rsa_oaep_sha256";
+pub(crate) const POLICY_ID_ISSUED_TOKEN_ECC_NIST_P256: &str = "userpass_ecc_nistp256";
+pub(crate) const POLICY_ID_ISSUED_TOKEN_ECC_NIST_P384: &str = "userpass_ecc_nistp384";
+pub(crate) const POLICY_ID_ISSUED_TOKEN_ECC_BRAINPOOL_P256R1: &str = "userpass_ecc_brainpoolp256r1";
+pub(crate) const POLICY_ID_ISSUED_TOKEN_ECC_CURVE25519: &str = "userpass_ecc_curve25519";
 pub(crate) const POLICY_ID_X509: &str = "x509";
 
 /// Identity token representation on the server, decoded from the client.
@@ -4,6 +4,7 @@
 
 //! Provides server state information, such as status, configuration, running servers and so on.
 
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicU16, AtomicU8, Ordering};
 use std::sync::Arc;
 
@@ -12,23 +13,29 @@ use opcua_nodes::DefaultTypeTree;
 use tracing::{debug, error, warn};
 
 use crate::authenticator::{user_pass_security_policy_id, Password};
+use crate::conditions::ConditionManager;
 use crate::diagnostics::{ServerDiagnostics, ServerDiagnosticsSummary};
+use crate::discovery_server::DiscoveryServerRegistry;
 use crate::node_manager::TypeTreeForUser;
+use crate::roles::RoleSet;
 use opcua_core::comms::url::{hostname_from_url, url_matches_except_host};
 use opcua_core::handle::AtomicHandle;
 use opcua_core::sync::RwLock;
-use opcua_crypto::{user_identity, PrivateKey, SecurityPolicy, X509};
+use opcua_crypto::{
+    ecc::{EccCurve, EphemeralKey},
+    user_identity, PrivateKey, SecurityPolicy, X509,
+};
+use opcua_types::{
+    custom::DataTypeTree, ByteString, ContextOwned, DateTime, DecodingOptions, Error,
+    ExtensionObject, IssuedIdentityToken, LocalizedText, MessageSecurityMode, NamespaceMap,
+    TypeLoader, TypeLoaderCollection, UAString,
+};
 use opcua_types::{
-    profiles, status_code::StatusCode, ActivateSessionRequest, AnonymousIdentityToken,
+    status_code::StatusCode, ActivateSessionRequest, AnonymousIdentityToken,
     ApplicationDescription, ApplicationType, EndpointDescription, RegisteredServer,
     ServerState as ServerStateType, SignatureData, UserNameIdentityToken, UserTokenType,
     X509IdentityToken,
 };
-use opcua_types::{
-    ByteString, ContextOwned, DateTime, DecodingOptions, Error, ExtensionObject,
-    IssuedIdentityToken, LocalizedText, MessageSecurityMode, NamespaceMap, TypeLoader,
-    TypeLoaderCollection, UAString,
-};
 
 use crate::config::{ServerConfig, ServerEndpoint};
 
@@ -36,6 +43,16 @@ use super::authenticator::{AuthManager, UserToken};
 use super::identity_token::{IdentityToken, POLICY_ID_ANONYMOUS, POLICY_ID_X509};
 use super::{OperationalLimits, ServerCapabilities, ANONYMOUS_USER_TOKEN_ID};
 
+/// The server's application instance certificate and its private key. These are always
+/// replaced together, so the certificate and key in use always match.
+#[derive(Default)]
+pub struct ServerKeyPair {
+    /// Server public certificate read from config location or `None` if there is none.
+    pub certificate: Option<Arc<X509>>,
+    /// Server private key.
+    pub pkey: Option<Arc<PrivateKey>>,
+}
+
 /// Server state is any configuration associated with the server as a whole that individual sessions might
 /// be interested in.
 pub struct ServerInfo {
@@ -51,10 +68,12 @@ pub struct ServerInfo {
     pub servers: Vec<String>,
     /// Server configuration
     pub config: Arc<ServerConfig>,
-    /// Server public certificate read from config location or null if there is none
-    pub server_certificate: Option<X509>,
-    /// Server private key
-    pub server_pkey: Option<PrivateKey>,
+    /// Server certificate and private key. These may be replaced at runtime when the
+    /// certificate is updated. This replaces the `server_certificate` and `server_pkey`
+    /// fields of earlier versions.
+    pub server_keypair: ArcSwap<ServerKeyPair>,
+    /// Server certificates and private keys used with the ECC security policies, by curve.
+    pub ecc_certificates: HashMap<EccCurve, (Arc<X509>, Arc<PrivateKey>)>,
     /// Operational limits
     pub(crate) operational_limits: OperationalLimits,
     /// Current state
@@ -87,8 +106,18 @@ pub struct ServerInfo {
     pub port: AtomicU16,
     /// List of active type loaders
     pub type_loaders: RwLock<TypeLoaderCollection>,
+    /// Data types with a known data type definition, used to resolve structure fields
+    /// of data types defined in node sets imported at runtime.
+    pub data_types: RwLock<DataTypeTree>,
     /// Current server diagnostics.
     pub diagnostics: ServerDiagnostics,
+    /// Alarms and conditions on the server.
+    pub conditions: Arc<ConditionManager>,
+    /// Registry of servers registered with this server, if it is
+    /// running as a local discovery server.
+    pub(crate) discovery_server: Option<DiscoveryServerRegistry>,
+    /// Roles on the server, used for role based access control.
+    pub roles: Arc<RoleSet>,
 }
 
 impl ServerInfo {
@@ -103,21 +132,23 @@ impl ServerInfo {
             "Endpoints requested, transport profile uris {:?}",
             transport_profile_uris
         );
-        if let Some(ref transport_profile_uris) = *transport_profile_uris {
-            // Note - some clients pass an empty array
-            if !transport_profile_uris.is_empty() {
-                // As we only support binary transport, the result is None if the supplied profile_uris does not contain that profile
-                let found_binary_transport = transport_profile_uris.iter().any(|profile_uri| {
-                    profile_uri.as_ref() == profiles::TRANSPORT_PROFILE_URI_BINARY
-                });
-                if !found_binary_transport {
-                    error!(
-                        "Client wants to connect with a non binary transport {:#?}",
-                        transport_profile_uris
-                    );
-                    return None;
-                }
-            }
+        // Note - some clients pass an empty array
+        let transport_profile_uris = transport_profile_uris
+            .as_ref()
+            .filter(|uris| !uris.is_empty());
+        let matches_profile = |e: &ServerEndpoint| match transport_profile_uris {
+            Some(uris) => uris
+                .iter()
+                .any(|uri| uri.as_ref() == e.transport.profile_uri()),
+            None => true,
+        };
+        if transport_profile_uris.is_some() && !self.config.endpoints.values().any(matches_profile)
+        {
+            error!(
+                "Client wants to connect with an unsupported transport {:#?}",
+                transport_profile_uris
+            );
+            return None;
         }
 
         if let Ok(hostname) = hostname_from_url(endpoint_url.as_ref()) {
@@ -128,6 +159,7 @@ impl ServerInfo {
                 .config
                 .endpoints
                 .values()
+                .filter(|e| matches_profile(e))
                 .map(|e| self.new_endpoint_description(e, true))
                 .collect();
             Some(endpoints)
@@ -212,7 +244,9 @@ impl ServerInfo {
                     discovery_profile_uri: UAString::null(),
                     discovery_urls: self.discovery_urls(),
                 },
-                self.server_certificate_as_byte_string(),
+                self.server_certificate_for_policy(endpoint.security_policy())
+                    .map(|cert| cert.as_byte_string())
+                    .unwrap_or_default(),
             )
         } else {
             (
@@ -236,7 +270,7 @@ impl ServerInfo {
             security_mode: endpoint.message_security_mode(),
             security_policy_uri: UAString::from(endpoint.security_policy().to_uri()),
             user_identity_tokens: Some(user_identity_tokens),
-            transport_profile_uri: UAString::from(profiles::TRANSPORT_PROFILE_URI_BINARY),
+            transport_profile_uri: UAString::from(endpoint.transport.profile_uri()),
             security_level: endpoint.security_level,
         }
     }
@@ -256,9 +290,14 @@ impl ServerInfo {
         }
     }
 
-    /// Get the application type, will be `Server`.
+    /// Get the application type, will be `DiscoveryServer` if the server is
+    /// configured as a local discovery server, `Server` otherwise.
     pub fn application_type(&self) -> ApplicationType {
-        ApplicationType::Server
+        if self.discovery_server.is_some() {
+            ApplicationType::DiscoveryServer
+        } else {
+            ApplicationType::Server
+        }
     }
 
     /// Get the gateway server URI.
@@ -285,15 +324,62 @@ impl ServerInfo {
         )
     }
 
+    /// Get the current server certificate, if there is one.
+    ///
+    /// The certificate may be replaced between this call and a call to `current_server_pkey`,
+    /// so use `server_keypair` to get a certificate and private key that match.
+    #[deprecated(note = "use `server_keypair`, which holds a matching certificate and key")]
+    pub fn current_server_certificate(&self) -> Option<Arc<X509>> {
+        self.server_keypair.load().certificate.clone()
+    }
+
+    /// Get the current server private key, if there is one.
+    ///
+    /// The key may be replaced between this call and a call to `current_server_certificate`,
+    /// so use `server_keypair` to get a certificate and private key that match.
+    #[deprecated(note = "use `server_keypair`, which holds a matching certificate and key")]
+    pub fn current_server_pkey(&self) -> Option<Arc<PrivateKey>> {
+        self.server_keypair.load().pkey.clone()
+    }
+
     /// Get the server certificate as a byte string.
     pub fn server_certificate_as_byte_string(&self) -> ByteString {
-        if let Some(ref server_certificate) = self.server_certificate {
+        if let Some(server_certificate) = self.server_keypair.load().certificate.as_deref() {
             server_certificate.as_byte_string()
         } else {
             ByteString::null()
         }
     }
 
+    /// Get the server certificate used with the given security policy. This is the
+    /// certificate on the curve of the policy for the ECC security policies.
+    pub fn server_certificate_for_policy(
+        &self,
+        security_policy: SecurityPolicy,
+    ) -> Option<Arc<X509>> {
+        match security_policy.ecc_curve() {
+            Some(curve) => self
+                .ecc_certificates
+                .get(&curve)
+                .map(|(cert, _)| cert.clone()),
+            None => self.server_keypair.load().certificate.clone(),
+        }
+    }
+
+    /// Get the server private key used with the given security policy.
+    pub fn server_pkey_for_policy(
+        &self,
+        security_policy: SecurityPolicy,
+    ) -> Option<Arc<PrivateKey>> {
+        match security_policy.ecc_curve() {
+            Some(curve) => self
+                .ecc_certificates
+                .get(&curve)
+                .map(|(_, pkey)| pkey.clone()),
+            None => self.server_keypair.load().pkey.clone(),
+        }
+    }
+
     /// Get a representation of this server as a `RegisteredServer` object.
     pub fn registered_server(&self) -> RegisteredServer {
         let server_uri = self.application_uri.clone();
@@ -322,6 +408,10 @@ impl ServerInfo {
     /// It is possible that the endpoint does not exist, or that the token is invalid / unsupported
     /// or that the token cannot be used with the end point. The return codes reflect the responses
     /// that ActivateSession would expect from a service call.
+    ///
+    /// User name tokens with secrets in the `EccEncryptedSecret` format are rejected, since they
+    /// require the keys of the session, use [`ServerInfo::authenticate_endpoint_with_keys`]
+    /// for those.
     pub async fn authenticate_endpoint(
         &self,
         request: &ActivateSessionRequest,
@@ -330,6 +420,35 @@ impl ServerInfo {
         security_mode: MessageSecurityMode,
         user_identity_token: ExtensionObject,
         server_nonce: &ByteString,
+    ) -> Result<UserToken, Error> {
+        self.authenticate_endpoint_with_keys(
+            request,
+            endpoint_url,
+            security_policy,
+            security_mode,
+            user_identity_token,
+            server_nonce,
+            None,
+            None,
+        )
+        .await
+    }
+
+    /// Authenticates access to an endpoint, like [`ServerInfo::authenticate_endpoint`].
+    ///
+    /// The `client_certificate` and the `ecdh_key` last issued to the session are used to
+    /// decrypt secrets in the `EccEncryptedSecret` format.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn authenticate_endpoint_with_keys(
+        &self,
+        request: &ActivateSessionRequest,
+        endpoint_url: &str,
+        security_policy: SecurityPolicy,
+        security_mode: MessageSecurityMode,
+        user_identity_token: ExtensionObject,
+        server_nonce: &ByteString,
+        client_certificate: Option<&X509>,
+        ecdh_key: Option<&EphemeralKey>,
     ) -> Result<UserToken, Error> {
         // Get security from endpoint url
         if let Some(endpoint) = self.config.find_endpoint(
@@ -338,6 +457,12 @@ impl ServerInfo {
             security_policy,
             security_mode,
         ) {
+            let keypair = self.server_keypair.load_full();
+            let server_certificate = match security_policy.ecc_curve() {
+                Some(_) => self.server_certificate_for_policy(security_policy),
+                None => keypair.certificate.clone(),
+            };
+            let server_pkey = keypair.pkey.clone();
             // Now validate the user identity token
             match IdentityToken::new(user_identity_token) {
                 IdentityToken::None => {
@@ -354,8 +479,10 @@ impl ServerInfo {
                     self.authenticate_username_identity_token(
                         endpoint,
                         &token,
-                        &self.server_pkey,
+                        server_pkey.as_deref(),
                         server_nonce,
+                        client_certificate,
+                        ecdh_key,
                     )
                     .await
                 }
@@ -364,7 +491,7 @@ impl ServerInfo {
                         endpoint,
                         &token,
                         &request.user_token_signature,
-                        &self.server_certificate,
+                        server_certificate.as_deref(),
                         server_nonce,
                     )
                     .await
@@ -373,8 +500,10 @@ impl ServerInfo {
                     self.authenticate_issued_identity_token(
                         endpoint,
                         &token,
-                        &self.server_pkey,
+                        server_pkey.as_deref(),
                         server_nonce,
+                        client_certificate,
+                        ecdh_key,
                     )
                     .await
                 }
@@ -423,12 +552,15 @@ impl ServerInfo {
 
     /// Authenticates the username identity token with the supplied endpoint. The function returns the user token identifier
     /// that matches the identity token.
+    #[allow(clippy::too_many_arguments)]
     async fn authenticate_username_identity_token(
         &self,
         endpoint: &ServerEndpoint,
         token: &UserNameIdentityToken,
-        server_key: &Option<PrivateKey>,
+        server_key: Option<&PrivateKey>,
         server_nonce: &ByteString,
+        client_certificate: Option<&X509>,
+        ecdh_key: Option<&EphemeralKey>,
     ) -> Result<UserToken, Error> {
         if !self.authenticator.supports_user_pass(endpoint) {
             Err(Error::new(
@@ -452,7 +584,7 @@ impl ServerInfo {
                 token.encryption_algorithm.as_ref()
             );
             let token_password = if !token.encryption_algorithm.is_null() {
-                if let Some(ref server_key) = server_key {
+                if let Some(server_key) = server_key {
                     let decrypted = user_identity::legacy_decrypt_secret(
                         token,
                         server_nonce.as_ref(),
@@ -471,6 +603,20 @@ impl ServerInfo {
                         "Failed to decrypt identity token password",
                     ));
                 }
+            } else if user_identity::is_encrypted_secret(&token.password) {
+                let decrypted = self.decrypt_encrypted_secret(
+                    endpoint,
+                    &token.password,
+                    server_nonce,
+                    client_certificate,
+                    ecdh_key,
+                )?;
+                String::from_utf8(decrypted.value.unwrap_or_default()).map_err(|e| {
+                    Error::new(
+                        StatusCode::BadIdentityTokenInvalid,
+                        format!("Failed to decode identity token to string: {e}"),
+                    )
+                })?
             } else {
                 token.plaintext_password()?
             };
@@ -492,7 +638,7 @@ impl ServerInfo {
         endpoint: &ServerEndpoint,
         token: &X509IdentityToken,
         user_token_signature: &SignatureData,
-        server_certificate: &Option<X509>,
+        server_certificate: Option<&X509>,
         server_nonce: &ByteString,
     ) -> Result<UserToken, Error> {
         if !self.authenticator.supports_x509(endpoint) {
@@ -509,7 +655,7 @@ impl ServerInfo {
             ))
         } else {
             match server_certificate {
-                Some(ref server_certificate) => {
+                Some(server_certificate) => {
                     // Find the security policy used for verifying tokens
                     let user_identity_tokens = self.authenticator.user_token_policies(endpoint);
                     let security_policy = user_identity_tokens
@@ -552,12 +698,15 @@ impl ServerInfo {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn authenticate_issued_identity_token(
         &self,
         endpoint: &ServerEndpoint,
         token: &IssuedIdentityToken,
-        server_key: &Option<PrivateKey>,
+        server_key: Option<&PrivateKey>,
         server_nonce: &ByteString,
+        client_certificate: Option<&X509>,
+        ecdh_key: Option<&EphemeralKey>,
     ) -> Result<UserToken, Error> {
         if !self.authenticator.supports_issued_token(endpoint) {
             Err(Error::new(
@@ -576,7 +725,7 @@ impl ServerInfo {
                 token.encryption_algorithm.as_ref()
             );
             let decrypted_token = if !token.encryption_algorithm.is_null() {
-                if let Some(ref server_key) = server_key {
+                if let Some(server_key) = server_key {
                     user_identity::legacy_decrypt_secret(token, server_nonce.as_ref(), server_key)?
                 } else {
                     error!("Identity token password is encrypted but no server private key was supplied");
@@ -585,6 +734,14 @@ impl ServerInfo {
                         "Failed to decrypt identity token issued token",
                     ));
                 }
+            } else if user_identity::is_encrypted_secret(&token.token_data) {
+                self.decrypt_encrypted_secret(
+                    endpoint,
+                    &token.token_data,
+                    server_nonce,
+                    client_certificate,
+                    ecdh_key,
+                )?
             } else {
                 token.token_data.clone()
             };
@@ -595,6 +752,39 @@ impl ServerInfo {
         }
     }
 
+    /// Decrypt a secret in the `RsaEncryptedSecret` or `EccEncryptedSecret` format. The secret must
+    /// be encrypted with the security policy of the user token policy of the endpoint.
+    fn decrypt_encrypted_secret(
+        &self,
+        endpoint: &ServerEndpoint,
+        secret: &ByteString,
+        server_nonce: &ByteString,
+        client_certificate: Option<&X509>,
+        ecdh_key: Option<&EphemeralKey>,
+    ) -> Result<ByteString, Error> {
+        let security_policy = endpoint.password_security_policy();
+        let server_key = self.server_pkey_for_policy(security_policy);
+        let decrypted = user_identity::decrypt_encrypted_secret(
+            secret,
+            security_policy,
+            server_nonce.as_ref(),
+            server_key.as_deref(),
+            ecdh_key,
+        )?;
+        // An EccEncryptedSecret is signed with the client application instance certificate,
+        // which has already been validated when the session was created.
+        if let Some(signing_certificate) = &decrypted.signing_certificate {
+            if client_certificate.map(|c| c.thumbprint()) != Some(signing_certificate.thumbprint())
+            {
+                return Err(Error::new(
+                    StatusCode::BadIdentityTokenInvalid,
+                    "Encrypted secret was not signed with the client certificate of the session",
+                ));
+            }
+        }
+        Ok(decrypted.secret)
+    }
+
     pub(crate) fn initial_encoding_context(&self) -> ContextOwned {
         // The namespace map is populated later, once the session is connected.
         ContextOwned::new(
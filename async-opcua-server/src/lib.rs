@@ -13,24 +13,27 @@
 pub mod address_space;
 pub mod authenticator;
 mod builder;
+pub mod conditions;
 mod config;
 pub mod diagnostics;
 #[cfg(feature = "discovery-server-registration")]
 mod discovery;
+mod discovery_server;
 mod identity_token;
 mod info;
 pub mod node_manager;
+pub mod roles;
 mod server;
 mod server_handle;
 mod server_status;
 mod session;
 mod subscriptions;
-mod transport;
+pub mod transport;
 
 pub use builder::ServerBuilder;
 pub use config::*;
 pub use identity_token::IdentityToken;
-pub use info::ServerInfo;
+pub use info::{ServerInfo, ServerKeyPair};
 pub use opcua_types::event_field::EventField;
 pub use server::Server;
 pub use server_handle::ServerHandle;
@@ -103,15 +106,22 @@ pub mod constants {
     /// Maximum number of nodes per history update call. Not separate constants
     /// for data and events because update may mix the two.
     pub const MAX_NODES_PER_HISTORY_UPDATE: usize = 100;
+    /// Maximum number of processing intervals per node in a processed history read.
+    pub const MAX_PROCESSED_INTERVALS_PER_HISTORY_READ: usize = 10000;
     /// Maximum number of node descriptions per query call.
     pub const MAX_NODE_DESCS_PER_QUERY: usize = 100;
     /// Maximum number of references to return per query data set.
     pub const MAX_REFERENCES_QUERY_RETURN: usize = 100;
+    /// Maximum number of nodes each node manager may visit while evaluating a query.
+    pub const MAX_NODES_VISITED_PER_QUERY: usize = 1_000_000;
     /// Maximum number of data sets to return per query.
     pub const MAX_DATA_SETS_QUERY_RETURN: usize = 1000;
     /// Maximum number of subscriptions per subscription management call, where applicable.
     pub const MAX_SUBSCRIPTIONS_PER_CALL: usize = 10;
 
+    /// Maximum size in bytes of the trust list file written through the `ServerConfiguration` object.
+    pub const MAX_TRUST_LIST_SIZE: usize = 4 * 1024 * 1024;
+
     /// Maximum number of sessions active on a server.
     pub const MAX_SESSIONS: usize = 20;
     /// Maximum number of references per node during Browse or BrowseNext.
@@ -131,6 +141,10 @@ pub mod constants {
     pub const MAX_NOTIFICATIONS_PER_PUBLISH: u64 = 0;
     /// Maximum number of queued notifications. Any notifications beyond this are dropped.
     pub const MAX_QUEUED_NOTIFICATIONS: usize = 20;
+    /// Maximum processing interval of an aggregate filter on a monitored item, one day.
+    pub const MAX_AGGREGATE_PROCESSING_INTERVAL_MS: f64 = 86_400_000.0;
+    /// Maximum number of raw samples kept by a monitored item with an aggregate filter.
+    pub const MAX_AGGREGATE_SAMPLES: usize = 10_000;
 
     /// Receive buffer size default.
     pub const RECEIVE_BUFFER_SIZE: usize = u16::MAX as usize;
@@ -16,6 +16,7 @@ pub struct HistoryNode {
     data_encoding: QualifiedName,
     input_continuation_point: Option<ContinuationPoint>,
     next_continuation_point: Option<ContinuationPoint>,
+    aggregate_type: Option<NodeId>,
     result: Option<ExtensionObject>,
     status: StatusCode,
 }
@@ -116,6 +117,7 @@ impl HistoryNode {
             data_encoding: node.data_encoding,
             input_continuation_point: cp,
             next_continuation_point: None,
+            aggregate_type: None,
             result: None,
             status,
         }
@@ -136,6 +138,16 @@ impl HistoryNode {
         &self.data_encoding
     }
 
+    /// Get the aggregate to compute for this node. This is only set
+    /// for `ReadProcessed` history reads.
+    pub fn aggregate_type(&self) -> Option<&NodeId> {
+        self.aggregate_type.as_ref()
+    }
+
+    pub(crate) fn set_aggregate_type(&mut self, aggregate_type: NodeId) {
+        self.aggregate_type = Some(aggregate_type);
+    }
+
     /// Get the current continuation point.
     pub fn continuation_point(&self) -> Option<&ContinuationPoint> {
         self.input_continuation_point.as_ref()
@@ -166,6 +178,16 @@ impl HistoryNode {
         self.status
     }
 
+    #[cfg(test)]
+    pub(crate) fn take_result(&mut self) -> Option<ExtensionObject> {
+        self.result.take()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn take_next_continuation_point(&mut self) -> Option<ContinuationPoint> {
+        self.next_continuation_point.take()
+    }
+
     pub(crate) fn into_result(mut self, session: &mut Session) -> HistoryReadResult {
         let cp = match self.next_continuation_point {
             Some(p) => {
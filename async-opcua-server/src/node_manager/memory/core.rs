@@ -17,19 +17,25 @@ use crate::{
     ServerCapabilities, ServerStatusWrapper,
 };
 use opcua_core::{sync::RwLock, trace_lock};
+use opcua_crypto::CertificateStore;
 use opcua_types::{
     DataValue, DateTime, ExtensionObject, IdType, Identifier, MethodId, MonitoringMode, NodeId,
     NumericRange, ObjectId, ReferenceTypeId, StatusCode, TimeZoneDataType, TimestampsToReturn,
     VariableId, Variant, VariantScalarTypeId, VariantTypeId,
 };
 
-use super::{InMemoryNodeManager, InMemoryNodeManagerImpl, InMemoryNodeManagerImplBuilder};
+use super::{
+    role_set::RoleSetObject, server_configuration::ServerConfiguration, InMemoryNodeManager,
+    InMemoryNodeManagerImpl, InMemoryNodeManagerImplBuilder,
+};
 
 /// Node manager impl for the core namespace.
 pub struct CoreNodeManagerImpl {
     sampler: SyncSampler,
     node_managers: NodeManagersRef,
     status: Arc<ServerStatusWrapper>,
+    server_configuration: ServerConfiguration,
+    server_namespace: u16,
 }
 
 /// Node manager for the core namespace.
@@ -42,12 +48,31 @@ impl InMemoryNodeManagerImplBuilder for CoreNodeManagerBuilder {
     type Impl = CoreNodeManagerImpl;
 
     fn build(self, context: ServerContext, address_space: &mut AddressSpace) -> Self::Impl {
-        {
+        let server_namespace = {
             let mut type_tree = context.type_tree.write();
-            address_space.import_node_set(&CoreNamespace, type_tree.namespaces_mut());
-        }
+            // Also adds the core data types to the type tree, so that node sets imported
+            // by node managers built later can use them in their data type definitions.
+            address_space.import_node_set_with_data_types(
+                &mut CoreNamespace,
+                &mut type_tree,
+                &mut context.info.data_types.write(),
+            );
+            // Roles added at runtime are created in the namespace of the server. The rest
+            // of that namespace is owned by the diagnostics node manager.
+            let server_namespace = type_tree
+                .namespaces_mut()
+                .add_namespace(context.info.application_uri.as_ref());
+            address_space.add_namespace(context.info.application_uri.as_ref(), server_namespace);
+            RoleSetObject::init(address_space, &*type_tree, &context.info.roles);
+            server_namespace
+        };
 
-        CoreNodeManagerImpl::new(context.node_managers.clone(), context.status.clone())
+        CoreNodeManagerImpl::new(
+            context.node_managers.clone(),
+            context.status.clone(),
+            context.certificate_store.clone(),
+            server_namespace,
+        )
     }
 }
 
@@ -79,6 +104,9 @@ impl InMemoryNodeManagerImpl for CoreNodeManagerImpl {
         // Some core methods should be generally executable
         Self::set_method_executable(address_space, MethodId::Server_GetMonitoredItems);
         Self::set_method_executable(address_space, MethodId::Server_ResendData);
+        Self::set_method_executable(address_space, MethodId::ConditionType_ConditionRefresh);
+        Self::set_method_executable(address_space, MethodId::ConditionType_ConditionRefresh2);
+        self.server_configuration.init(address_space);
     }
 
     fn namespaces(&self) -> Vec<NamespaceMetadata> {
@@ -100,6 +128,10 @@ impl InMemoryNodeManagerImpl for CoreNodeManagerImpl {
         "core"
     }
 
+    fn owns_node(&self, id: &NodeId) -> bool {
+        id.namespace != self.server_namespace || matches!(id.identifier, Identifier::Guid(_))
+    }
+
     async fn read_values(
         &self,
         context: &RequestContext,
@@ -121,11 +153,11 @@ impl InMemoryNodeManagerImpl for CoreNodeManagerImpl {
     async fn call(
         &self,
         context: &RequestContext,
-        _address_space: &RwLock<AddressSpace>,
+        address_space: &RwLock<AddressSpace>,
         methods_to_call: &mut [&mut &mut MethodCall],
     ) -> Result<(), StatusCode> {
         for method in methods_to_call {
-            if let Err(e) = self.call_builtin_method(method, context) {
+            if let Err(e) = self.call_builtin_method(method, context, address_space) {
                 method.set_status(e);
             }
         }
@@ -220,11 +252,18 @@ impl InMemoryNodeManagerImpl for CoreNodeManagerImpl {
 }
 
 impl CoreNodeManagerImpl {
-    pub(super) fn new(node_managers: NodeManagersRef, status: Arc<ServerStatusWrapper>) -> Self {
+    pub(super) fn new(
+        node_managers: NodeManagersRef,
+        status: Arc<ServerStatusWrapper>,
+        certificate_store: Arc<RwLock<CertificateStore>>,
+        server_namespace: u16,
+    ) -> Self {
         Self {
             sampler: SyncSampler::new(),
             status,
             node_managers,
+            server_configuration: ServerConfiguration::new(certificate_store),
+            server_namespace,
         }
     }
 
@@ -252,6 +291,10 @@ impl CoreNodeManagerImpl {
         // In this case, the values are largely read from configuration.
         if let Some(v) = self.read_server_value(context, node_to_read) {
             v
+        } else if let Some(v) =
+            RoleSetObject::read_value(context, address_space, &node_to_read.node_id)
+        {
+            Self::variant_to_data_value(context, v, node_to_read)
         } else {
             // If it can't be found, read it from the node hierarchy.
             read_node_value(node, context, node_to_read, max_age, timestamps_to_return)
@@ -501,32 +544,41 @@ impl CoreNodeManagerImpl {
                 }
             }
 
-            _ => return None,
+            r => self.server_configuration.read_value(context, r)?,
 
         };
 
+        Some(Self::variant_to_data_value(context, v, node))
+    }
+
+    /// Create a data value for a value read from the server, applying the index range.
+    fn variant_to_data_value(
+        context: &RequestContext,
+        v: Variant,
+        node: &ParsedReadValueId,
+    ) -> DataValue {
         let v = if !matches!(node.index_range, NumericRange::None) {
             match v.range_of(&node.index_range) {
                 Ok(v) => v,
                 Err(e) => {
-                    return Some(DataValue {
+                    return DataValue {
                         value: None,
                         status: Some(e),
                         ..Default::default()
-                    })
+                    }
                 }
             }
         } else {
             v
         };
 
-        Some(DataValue {
+        DataValue {
             value: Some(v),
             status: Some(StatusCode::Good),
             source_timestamp: Some(**context.info.start_time.load()),
             server_timestamp: Some(**context.info.start_time.load()),
             ..Default::default()
-        })
+        }
     }
 
     fn add_aggregates(&self, address_space: &mut AddressSpace, capabilities: &ServerCapabilities) {
@@ -551,6 +603,7 @@ impl CoreNodeManagerImpl {
         &self,
         call: &mut MethodCall,
         context: &RequestContext,
+        address_space: &RwLock<AddressSpace>,
     ) -> Result<(), StatusCode> {
         let Ok(id) = call.method_id().as_method_id() else {
             return Ok(());
@@ -583,7 +636,14 @@ impl CoreNodeManagerImpl {
                 sub.set_resend_data();
                 call.set_status(StatusCode::Good);
             }
-            _ => return Err(StatusCode::BadNotSupported),
+            id => {
+                return self
+                    .server_configuration
+                    .call(call, context, id)
+                    .or_else(|| RoleSetObject::call(call, context, address_space, id))
+                    .or_else(|| context.info.conditions.call(context, call))
+                    .unwrap_or(Err(StatusCode::BadNotSupported))
+            }
         }
         Ok(())
     }
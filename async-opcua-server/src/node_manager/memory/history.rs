@@ -0,0 +1,804 @@
+use std::collections::VecDeque;
+
+use async_trait::async_trait;
+use hashbrown::HashMap;
+use opcua_core::{sync::RwLock, trace_read_lock, trace_write_lock};
+use opcua_types::{
+    DataValue, DateTime, DeleteAtTimeDetails, DeleteRawModifiedDetails, HistoryData, NodeId,
+    PerformUpdateType, ReadAtTimeDetails, ReadRawModifiedDetails, StatusCode, StatusCodeValueType,
+    TimestampsToReturn, UpdateDataDetails, Variant,
+};
+
+use crate::{
+    node_manager::{
+        HistoryNode, HistoryUpdateDetails, HistoryUpdateNode, RawHistoryProvider, RequestContext,
+    },
+    ContinuationPoint,
+};
+
+/// Simple in-memory store for historical values of variables.
+///
+/// Values are kept in a bounded buffer per node, ordered by source timestamp.
+/// Once a buffer is full, the oldest value is dropped whenever a new value is recorded.
+///
+/// The historian can serve `ReadRawModifiedDetails` and `ReadAtTimeDetails` history reads,
+/// `ReadProcessedDetails` through its [RawHistoryProvider] implementation, and `UpdateDataDetails`, `DeleteRawModifiedDetails` and `DeleteAtTimeDetails` history
+/// updates. It is used by [InMemoryNodeManager](super::InMemoryNodeManager) when history
+/// is enabled with
+/// [InMemoryNodeManagerBuilder::with_history](super::InMemoryNodeManagerBuilder::with_history),
+/// but may also be used directly by custom node managers.
+pub struct InMemoryHistorian {
+    values: RwLock<HashMap<NodeId, VecDeque<DataValue>>>,
+    max_values_per_node: usize,
+}
+
+struct HistorianContinuationPoint {
+    // Timestamp of the next value to return.
+    timestamp: DateTime,
+    // Number of values with `timestamp` that were already returned.
+    skip: usize,
+}
+
+/// Get the timestamp used to order a historical value.
+fn value_time(value: &DataValue) -> DateTime {
+    value
+        .source_timestamp
+        .or(value.server_timestamp)
+        .unwrap_or_else(DateTime::null)
+}
+
+fn bound_not_found(timestamp: DateTime) -> DataValue {
+    DataValue {
+        value: None,
+        status: Some(StatusCode::BadBoundNotFound),
+        source_timestamp: Some(timestamp),
+        server_timestamp: Some(timestamp),
+        ..Default::default()
+    }
+}
+
+fn apply_timestamps(mut value: DataValue, timestamps_to_return: TimestampsToReturn) -> DataValue {
+    match timestamps_to_return {
+        TimestampsToReturn::Source => {
+            value.server_timestamp = None;
+            value.server_picoseconds = None;
+        }
+        TimestampsToReturn::Server => {
+            value.source_timestamp = None;
+            value.source_picoseconds = None;
+        }
+        TimestampsToReturn::Neither => {
+            value.source_timestamp = None;
+            value.source_picoseconds = None;
+            value.server_timestamp = None;
+            value.server_picoseconds = None;
+        }
+        _ => (),
+    }
+    value
+}
+
+/// Interpolate between two values at `time`, linearly if both values are numeric,
+/// otherwise using the value of `before`.
+fn interpolate(before: &DataValue, after: &DataValue, time: DateTime) -> Option<Variant> {
+    let t0 = value_time(before).checked_ticks();
+    let t1 = value_time(after).checked_ticks();
+    let (Some(v0), Some(v1)) = (before.value.as_ref(), after.value.as_ref()) else {
+        return before.value.clone();
+    };
+    match (v0.as_f64(), v1.as_f64()) {
+        (Some(f0), Some(f1)) if t1 > t0 => {
+            let ratio = (time.checked_ticks() - t0) as f64 / (t1 - t0) as f64;
+            let value = Variant::from(f0 + (f1 - f0) * ratio);
+            // Try to return a value of the original type, fall back to double.
+            let Some(type_id) = v0.scalar_type_id() else {
+                return Some(value);
+            };
+            match value.cast(type_id) {
+                Variant::Empty => Some(value),
+                v => Some(v),
+            }
+        }
+        _ => Some(v0.clone()),
+    }
+}
+
+impl InMemoryHistorian {
+    /// Create a new historian, storing at most `max_values_per_node` values for
+    /// each node.
+    pub fn new(max_values_per_node: usize) -> Self {
+        Self {
+            values: Default::default(),
+            max_values_per_node: max_values_per_node.max(1),
+        }
+    }
+
+    /// Get the maximum number of values stored for each node.
+    pub fn max_values_per_node(&self) -> usize {
+        self.max_values_per_node
+    }
+
+    /// Record a new value for the node given by `node_id`. If the value has no source timestamp,
+    /// the server timestamp is used instead, and if neither is set, the current time.
+    pub fn record(&self, node_id: &NodeId, value: DataValue) {
+        let mut values = trace_write_lock!(self.values);
+        let buffer = values.entry(node_id.clone()).or_default();
+        self.insert(buffer, value);
+    }
+
+    /// Record a list of values for the node given by `node_id`.
+    pub fn record_many(&self, node_id: &NodeId, new_values: impl IntoIterator<Item = DataValue>) {
+        let mut values = trace_write_lock!(self.values);
+        let buffer = values.entry(node_id.clone()).or_default();
+        for value in new_values {
+            self.insert(buffer, value);
+        }
+    }
+
+    fn insert(&self, buffer: &mut VecDeque<DataValue>, mut value: DataValue) {
+        if value.source_timestamp.is_none() {
+            value.source_timestamp = Some(value.server_timestamp.unwrap_or_else(DateTime::now));
+        }
+        if value.server_timestamp.is_none() {
+            value.server_timestamp = value.source_timestamp;
+        }
+        let time = value_time(&value);
+        // Insert after any values with the same timestamp, values are usually
+        // recorded in order so this is normally at the end.
+        let idx = buffer.partition_point(|v| value_time(v) <= time);
+        buffer.insert(idx, value);
+        while buffer.len() > self.max_values_per_node {
+            buffer.pop_front();
+        }
+    }
+
+    /// Get all stored values for the node given by `node_id` with source timestamp
+    /// `start <= ts < end`, in chronological order.
+    pub fn values(&self, node_id: &NodeId, start: DateTime, end: DateTime) -> Vec<DataValue> {
+        let values = trace_read_lock!(self.values);
+        let Some(buffer) = values.get(node_id) else {
+            return Vec::new();
+        };
+        let start_idx = buffer.partition_point(|v| value_time(v) < start);
+        let end_idx = buffer.partition_point(|v| value_time(v) < end);
+        buffer
+            .range(start_idx..end_idx.max(start_idx))
+            .cloned()
+            .collect()
+    }
+
+    /// Get all stored values for the node given by `node_id` with source timestamp
+    /// `start <= ts < end`, as well as the closest value before `start` and the closest
+    /// value at or after `end`, if they exist.
+    pub fn values_with_bounds(
+        &self,
+        node_id: &NodeId,
+        start: DateTime,
+        end: DateTime,
+    ) -> Vec<DataValue> {
+        let values = trace_read_lock!(self.values);
+        let Some(buffer) = values.get(node_id) else {
+            return Vec::new();
+        };
+        let start_idx = buffer
+            .partition_point(|v| value_time(v) < start)
+            .saturating_sub(1);
+        let end_idx = (buffer.partition_point(|v| value_time(v) < end) + 1).min(buffer.len());
+        buffer
+            .range(start_idx..end_idx.max(start_idx))
+            .cloned()
+            .collect()
+    }
+
+    /// Remove all stored history for the node given by `node_id`.
+    pub fn clear(&self, node_id: &NodeId) {
+        let mut values = trace_write_lock!(self.values);
+        values.remove(node_id);
+    }
+
+    /// Read raw history for the given list of nodes.
+    ///
+    /// Modified values are not stored, so `is_read_modified` is not supported.
+    pub fn read_raw_modified(
+        &self,
+        details: &ReadRawModifiedDetails,
+        nodes: &mut [&mut &mut HistoryNode],
+        timestamps_to_return: TimestampsToReturn,
+    ) {
+        let status = if details.is_read_modified {
+            Err(StatusCode::BadHistoryOperationUnsupported)
+        } else {
+            match (details.start_time.is_null(), details.end_time.is_null()) {
+                (true, true) => Err(StatusCode::BadInvalidTimestampArgument),
+                (true, false) | (false, true) if details.num_values_per_node == 0 => {
+                    Err(StatusCode::BadInvalidTimestampArgument)
+                }
+                _ => Ok(()),
+            }
+        };
+        if let Err(e) = status {
+            for node in nodes {
+                node.set_status(e);
+            }
+            return;
+        }
+
+        let is_forward = details.end_time.is_null()
+            || !details.start_time.is_null() && details.start_time <= details.end_time;
+
+        let per_node = if details.num_values_per_node == 0 {
+            usize::MAX
+        } else {
+            details.num_values_per_node as usize
+        };
+
+        let values = trace_read_lock!(self.values);
+        for node in nodes {
+            let empty = VecDeque::new();
+            let buffer = values.get(node.node_id()).unwrap_or(&empty);
+            let all = Self::raw_range(buffer, details, is_forward);
+
+            let start_index = if let Some(cp) = node.continuation_point() {
+                let Some(cp) = cp.get::<HistorianContinuationPoint>() else {
+                    node.set_status(StatusCode::BadContinuationPointInvalid);
+                    continue;
+                };
+                let first = all
+                    .iter()
+                    .position(|v| {
+                        let time = value_time(v);
+                        if is_forward {
+                            time >= cp.timestamp
+                        } else {
+                            time <= cp.timestamp
+                        }
+                    })
+                    .unwrap_or(all.len());
+                (first + cp.skip).min(all.len())
+            } else {
+                0
+            };
+
+            let end_index = start_index.saturating_add(per_node).min(all.len());
+            if end_index < all.len() {
+                let timestamp = value_time(&all[end_index]);
+                let skip = all[..end_index]
+                    .iter()
+                    .rev()
+                    .take_while(|v| value_time(v) == timestamp)
+                    .count();
+                node.set_next_continuation_point(Some(ContinuationPoint::new(Box::new(
+                    HistorianContinuationPoint { timestamp, skip },
+                ))));
+            }
+
+            let data_values = all[start_index..end_index]
+                .iter()
+                .map(|v| apply_timestamps(v.clone(), timestamps_to_return))
+                .collect();
+            node.set_result(HistoryData {
+                data_values: Some(data_values),
+            });
+            node.set_status(StatusCode::Good);
+        }
+    }
+
+    fn raw_range(
+        buffer: &VecDeque<DataValue>,
+        details: &ReadRawModifiedDetails,
+        is_forward: bool,
+    ) -> Vec<DataValue> {
+        let start = details.start_time;
+        let end = details.end_time;
+        let before = |idx: usize| idx.checked_sub(1).and_then(|i| buffer.get(i)).cloned();
+
+        if is_forward {
+            // Forward reads include `start <= ts < end`. If the start and end times are equal,
+            // only values at exactly that time are returned.
+            let start_idx = buffer.partition_point(|v| value_time(v) < start);
+            let end_idx = if end.is_null() {
+                buffer.len()
+            } else if start == end {
+                buffer.partition_point(|v| value_time(v) <= end)
+            } else {
+                buffer.partition_point(|v| value_time(v) < end)
+            };
+            let mut res: Vec<_> = buffer.range(start_idx..end_idx).cloned().collect();
+            if details.return_bounds {
+                if !matches!(res.first(), Some(v) if value_time(v) == start) {
+                    res.insert(
+                        0,
+                        before(start_idx).unwrap_or_else(|| bound_not_found(start)),
+                    );
+                }
+                if !end.is_null() {
+                    res.push(
+                        buffer
+                            .get(end_idx)
+                            .cloned()
+                            .unwrap_or_else(|| bound_not_found(end)),
+                    );
+                }
+            }
+            res
+        } else {
+            // Backward reads include `end < ts <= start`. If the start time is not set,
+            // read backwards from the end time, including it.
+            let (upper, lower) = if start.is_null() {
+                (end, None)
+            } else {
+                (start, Some(end))
+            };
+            let start_idx = match lower {
+                Some(lower) => buffer.partition_point(|v| value_time(v) <= lower),
+                None => 0,
+            };
+            let end_idx = buffer.partition_point(|v| value_time(v) <= upper);
+            let mut res: Vec<_> = buffer.range(start_idx..end_idx).cloned().collect();
+            if details.return_bounds {
+                if let Some(lower) = lower {
+                    res.insert(
+                        0,
+                        before(start_idx).unwrap_or_else(|| bound_not_found(lower)),
+                    );
+                }
+                if !matches!(res.last(), Some(v) if value_time(v) == upper) {
+                    res.push(
+                        buffer
+                            .get(end_idx)
+                            .cloned()
+                            .unwrap_or_else(|| bound_not_found(upper)),
+                    );
+                }
+            }
+            res.reverse();
+            res
+        }
+    }
+
+    /// Read history at the given timestamps for the given list of nodes.
+    ///
+    /// Values missing at a requested time are interpolated from the surrounding values,
+    /// linearly for numeric values and stepped otherwise.
+    pub fn read_at_time(
+        &self,
+        details: &ReadAtTimeDetails,
+        nodes: &mut [&mut &mut HistoryNode],
+        timestamps_to_return: TimestampsToReturn,
+    ) {
+        let req_times = details.req_times.as_deref().unwrap_or_default();
+        let values = trace_read_lock!(self.values);
+        for node in nodes {
+            let empty = VecDeque::new();
+            let buffer = values.get(node.node_id()).unwrap_or(&empty);
+            let data_values = req_times
+                .iter()
+                .map(|time| {
+                    apply_timestamps(
+                        Self::value_at_time(buffer, *time, details.use_simple_bounds),
+                        timestamps_to_return,
+                    )
+                })
+                .collect();
+            node.set_result(HistoryData {
+                data_values: Some(data_values),
+            });
+            node.set_status(StatusCode::Good);
+        }
+    }
+
+    fn value_at_time(
+        buffer: &VecDeque<DataValue>,
+        time: DateTime,
+        simple_bounds: bool,
+    ) -> DataValue {
+        let idx = buffer.partition_point(|v| value_time(v) < time);
+        if let Some(v) = buffer.get(idx).filter(|v| value_time(v) == time) {
+            return v.clone();
+        }
+
+        // With simple bounds, the nearest values are used regardless of status,
+        // otherwise we look for the nearest good values.
+        let is_bound = |v: &&DataValue| simple_bounds || v.status().is_good();
+        let before = buffer.range(..idx).rev().find(is_bound);
+        let after = buffer.range(idx..).find(is_bound);
+
+        let Some(before) = before else {
+            return DataValue {
+                value: None,
+                status: Some(StatusCode::BadNoData),
+                source_timestamp: Some(time),
+                server_timestamp: Some(time),
+                ..Default::default()
+            };
+        };
+
+        let (value, status) = match after {
+            Some(after) => {
+                let status = if before.status().is_good() && after.status().is_good() {
+                    StatusCode::Good
+                } else {
+                    StatusCode::UncertainDataSubNormal
+                };
+                (interpolate(before, after, time), status)
+            }
+            // No later value, extrapolate using the last known value.
+            None => (before.value.clone(), StatusCode::UncertainDataSubNormal),
+        };
+
+        DataValue {
+            value,
+            status: Some(status.set_value_type(StatusCodeValueType::Interpolated)),
+            source_timestamp: Some(time),
+            server_timestamp: Some(time),
+            ..Default::default()
+        }
+    }
+
+    /// Returns `true` if this historian is able to handle the given history update details.
+    pub fn supports_update(details: &HistoryUpdateDetails) -> bool {
+        matches!(
+            details,
+            HistoryUpdateDetails::UpdateData(_)
+                | HistoryUpdateDetails::DeleteRawModified(_)
+                | HistoryUpdateDetails::DeleteAtTime(_)
+        )
+    }
+
+    /// Execute a history update on a single node.
+    pub fn update(&self, node: &mut HistoryUpdateNode) {
+        let res = match node.details() {
+            HistoryUpdateDetails::UpdateData(d) => self.update_data(d),
+            HistoryUpdateDetails::DeleteRawModified(d) => self.delete_raw_modified(d),
+            HistoryUpdateDetails::DeleteAtTime(d) => Ok(Some(self.delete_at_time(d))),
+            _ => Err(StatusCode::BadHistoryOperationUnsupported),
+        };
+
+        match res {
+            Ok(results) => {
+                node.set_operation_results(results);
+                node.set_status(StatusCode::Good);
+            }
+            Err(e) => node.set_status(e),
+        }
+    }
+
+    fn update_data(
+        &self,
+        details: &UpdateDataDetails,
+    ) -> Result<Option<Vec<StatusCode>>, StatusCode> {
+        let mode = details.perform_insert_replace;
+        if mode == PerformUpdateType::Remove {
+            return Err(StatusCode::BadHistoryOperationInvalid);
+        }
+
+        let update_values = details.update_values.as_deref().unwrap_or_default();
+        let mut results = Vec::with_capacity(update_values.len());
+        let mut values = trace_write_lock!(self.values);
+        let buffer = values.entry(details.node_id.clone()).or_default();
+
+        for value in update_values {
+            let Some(time) = value.source_timestamp else {
+                results.push(StatusCode::BadInvalidTimestamp);
+                continue;
+            };
+            let idx = buffer.partition_point(|v| value_time(v) < time);
+            let exists = buffer.get(idx).is_some_and(|v| value_time(v) == time);
+            let mut value = value.clone();
+            if value.server_timestamp.is_none() {
+                value.server_timestamp = Some(DateTime::now());
+            }
+
+            results.push(match (mode, exists) {
+                (PerformUpdateType::Insert, true) => StatusCode::BadEntryExists,
+                (PerformUpdateType::Replace, false) => StatusCode::BadNoEntryExists,
+                (_, true) => {
+                    buffer[idx] = value;
+                    StatusCode::GoodEntryReplaced
+                }
+                // The buffer is full and the value is older than everything in it,
+                // so it would be evicted immediately.
+                (_, false) if idx == 0 && buffer.len() >= self.max_values_per_node => {
+                    StatusCode::BadOutOfRange
+                }
+                (_, false) => {
+                    self.insert(buffer, value);
+                    StatusCode::GoodEntryInserted
+                }
+            });
+        }
+
+        Ok(Some(results))
+    }
+
+    fn delete_raw_modified(
+        &self,
+        details: &DeleteRawModifiedDetails,
+    ) -> Result<Option<Vec<StatusCode>>, StatusCode> {
+        if details.is_delete_modified {
+            return Err(StatusCode::BadHistoryOperationUnsupported);
+        }
+        if details.start_time.is_null() && details.end_time.is_null() {
+            return Err(StatusCode::BadInvalidTimestampArgument);
+        }
+
+        let (low, high) = if details.end_time.is_null() || details.start_time <= details.end_time {
+            (details.start_time, details.end_time)
+        } else {
+            (details.end_time, details.start_time)
+        };
+
+        let mut values = trace_write_lock!(self.values);
+        let Some(buffer) = values.get_mut(&details.node_id) else {
+            return Err(StatusCode::BadNoData);
+        };
+        let len = buffer.len();
+        buffer.retain(|v| {
+            let time = value_time(v);
+            time < low || !high.is_null() && time >= high
+        });
+        if buffer.len() == len {
+            return Err(StatusCode::BadNoData);
+        }
+
+        Ok(None)
+    }
+
+    fn delete_at_time(&self, details: &DeleteAtTimeDetails) -> Vec<StatusCode> {
+        let req_times = details.req_times.as_deref().unwrap_or_default();
+        let mut values = trace_write_lock!(self.values);
+        let buffer = values.get_mut(&details.node_id);
+        let Some(buffer) = buffer else {
+            return vec![StatusCode::BadNoEntryExists; req_times.len()];
+        };
+
+        req_times
+            .iter()
+            .map(|time| {
+                let idx = buffer.partition_point(|v| value_time(v) < *time);
+                if buffer.get(idx).is_some_and(|v| value_time(v) == *time) {
+                    buffer.remove(idx);
+                    StatusCode::Good
+                } else {
+                    StatusCode::BadNoEntryExists
+                }
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl RawHistoryProvider for InMemoryHistorian {
+    async fn read_raw_with_bounds(
+        &self,
+        _context: &RequestContext,
+        node_id: &NodeId,
+        start: DateTime,
+        end: DateTime,
+    ) -> Result<Vec<DataValue>, StatusCode> {
+        Ok(self.values_with_bounds(node_id, start, end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+    use opcua_types::{
+        DataValue, DateTime, HistoryData, HistoryReadValueId, NodeId, PerformUpdateType,
+        ReadAtTimeDetails, ReadRawModifiedDetails, StatusCode, StatusCodeValueType,
+        TimestampsToReturn, UpdateDataDetails, Variant,
+    };
+
+    use super::InMemoryHistorian;
+    use crate::{
+        node_manager::{HistoryNode, HistoryUpdateDetails, HistoryUpdateNode},
+        ContinuationPoint,
+    };
+
+    fn value(v: f64, time: DateTime) -> DataValue {
+        DataValue {
+            value: Some(v.into()),
+            status: Some(StatusCode::Good),
+            source_timestamp: Some(time),
+            server_timestamp: Some(time),
+            ..Default::default()
+        }
+    }
+
+    fn history_node(id: &NodeId, cp: Option<ContinuationPoint>) -> HistoryNode {
+        HistoryNode::new(
+            HistoryReadValueId {
+                node_id: id.clone(),
+                ..Default::default()
+            },
+            false,
+            cp,
+        )
+    }
+
+    fn result_values(mut node: HistoryNode) -> Vec<DataValue> {
+        node.take_result()
+            .unwrap()
+            .inner_as::<HistoryData>()
+            .unwrap()
+            .data_values
+            .clone()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn ring_buffer_is_bounded() {
+        let historian = InMemoryHistorian::new(10);
+        let id = NodeId::new(1, 1);
+        let start = DateTime::now();
+        for i in 0..20 {
+            historian.record(&id, value(i as f64, start + TimeDelta::seconds(i)));
+        }
+        let values = historian.values(&id, DateTime::null(), DateTime::endtimes());
+        assert_eq!(values.len(), 10);
+        assert_eq!(values[0].value, Some(Variant::Double(10.0)));
+        assert_eq!(values[9].value, Some(Variant::Double(19.0)));
+    }
+
+    #[test]
+    fn read_raw_with_continuation() {
+        let historian = InMemoryHistorian::new(100);
+        let id = NodeId::new(1, 1);
+        let start = DateTime::now();
+        for i in 0..50 {
+            historian.record(&id, value(i as f64, start + TimeDelta::seconds(i)));
+        }
+        let details = ReadRawModifiedDetails {
+            is_read_modified: false,
+            start_time: start,
+            end_time: start + TimeDelta::seconds(100),
+            num_values_per_node: 20,
+            return_bounds: false,
+        };
+
+        let mut read = Vec::new();
+        let mut cp = None;
+        loop {
+            let mut node = history_node(&id, cp.take());
+            historian.read_raw_modified(&details, &mut [&mut &mut node], TimestampsToReturn::Both);
+            assert_eq!(node.status(), StatusCode::Good);
+            cp = node.take_next_continuation_point();
+            let has_cp = cp.is_some();
+            read.extend(result_values(node));
+            if !has_cp {
+                break;
+            }
+        }
+        assert_eq!(read.len(), 50);
+        for (idx, v) in read.iter().enumerate() {
+            assert_eq!(v.value, Some(Variant::Double(idx as f64)));
+        }
+
+        // Read backwards
+        let details = ReadRawModifiedDetails {
+            is_read_modified: false,
+            start_time: start + TimeDelta::seconds(10),
+            end_time: start,
+            num_values_per_node: 0,
+            return_bounds: false,
+        };
+        let mut node = history_node(&id, None);
+        historian.read_raw_modified(&details, &mut [&mut &mut node], TimestampsToReturn::Both);
+        let read = result_values(node);
+        assert_eq!(read.len(), 10);
+        assert_eq!(read[0].value, Some(Variant::Double(10.0)));
+        assert_eq!(read[9].value, Some(Variant::Double(1.0)));
+    }
+
+    #[test]
+    fn read_at_time_interpolates() {
+        let historian = InMemoryHistorian::new(100);
+        let id = NodeId::new(1, 1);
+        let start = DateTime::now();
+        historian.record(&id, value(0.0, start));
+        historian.record(&id, value(10.0, start + TimeDelta::seconds(10)));
+
+        let details = ReadAtTimeDetails {
+            req_times: Some(vec![
+                start - TimeDelta::seconds(1),
+                start,
+                start + TimeDelta::seconds(4),
+                start + TimeDelta::seconds(12),
+            ]),
+            use_simple_bounds: true,
+        };
+        let mut node = history_node(&id, None);
+        historian.read_at_time(&details, &mut [&mut &mut node], TimestampsToReturn::Both);
+        let read = result_values(node);
+        assert_eq!(read.len(), 4);
+        assert_eq!(read[0].status(), StatusCode::BadNoData);
+        assert_eq!(read[1].value, Some(Variant::Double(0.0)));
+        assert_eq!(read[2].value, Some(Variant::Double(4.0)));
+        assert_eq!(
+            read[2].status().value_type(),
+            StatusCodeValueType::Interpolated
+        );
+        assert!(read[3].status().is_uncertain());
+        assert_eq!(read[3].value, Some(Variant::Double(10.0)));
+    }
+
+    #[test]
+    fn update_and_delete() {
+        let historian = InMemoryHistorian::new(100);
+        let id = NodeId::new(1, 1);
+        let start = DateTime::now();
+        historian.record(&id, value(0.0, start));
+
+        let mut node =
+            HistoryUpdateNode::new(HistoryUpdateDetails::UpdateData(UpdateDataDetails {
+                node_id: id.clone(),
+                perform_insert_replace: PerformUpdateType::Insert,
+                update_values: Some(vec![
+                    value(1.0, start),
+                    value(2.0, start + TimeDelta::seconds(1)),
+                ]),
+            }));
+        historian.update(&mut node);
+        let res = node.into_result();
+        assert_eq!(res.status_code, StatusCode::Good);
+        assert_eq!(
+            res.operation_results,
+            Some(vec![
+                StatusCode::BadEntryExists,
+                StatusCode::GoodEntryInserted
+            ])
+        );
+
+        let mut node = HistoryUpdateNode::new(HistoryUpdateDetails::DeleteRawModified(
+            opcua_types::DeleteRawModifiedDetails {
+                node_id: id.clone(),
+                is_delete_modified: false,
+                start_time: start,
+                end_time: start + TimeDelta::seconds(1),
+            },
+        ));
+        historian.update(&mut node);
+        assert_eq!(node.status(), StatusCode::Good);
+        let values = historian.values(&id, DateTime::null(), DateTime::endtimes());
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[0].value, Some(Variant::Double(2.0)));
+    }
+
+    #[test]
+    fn insert_into_full_buffer() {
+        let historian = InMemoryHistorian::new(2);
+        let id = NodeId::new(1, 1);
+        let start = DateTime::now();
+        historian.record_many(
+            &id,
+            [
+                value(1.0, start + TimeDelta::seconds(1)),
+                value(2.0, start + TimeDelta::seconds(2)),
+            ],
+        );
+
+        let mut node =
+            HistoryUpdateNode::new(HistoryUpdateDetails::UpdateData(UpdateDataDetails {
+                node_id: id.clone(),
+                perform_insert_replace: PerformUpdateType::Insert,
+                update_values: Some(vec![
+                    value(0.0, start),
+                    value(3.0, start + TimeDelta::seconds(3)),
+                ]),
+            }));
+        historian.update(&mut node);
+        let res = node.into_result();
+        assert_eq!(
+            res.operation_results,
+            Some(vec![
+                StatusCode::BadOutOfRange,
+                StatusCode::GoodEntryInserted
+            ])
+        );
+
+        let values = historian.values(&id, DateTime::null(), DateTime::endtimes());
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].value, Some(Variant::Double(2.0)));
+        assert_eq!(values[1].value, Some(Variant::Double(3.0)));
+    }
+}
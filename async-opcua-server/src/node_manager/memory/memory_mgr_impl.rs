@@ -53,6 +53,14 @@ pub trait InMemoryNodeManagerImpl: Send + Sync + 'static {
     /// Return the static list of namespaces this node manager uses.
     fn namespaces(&self) -> Vec<NamespaceMetadata>;
 
+    /// Return `true` if this node manager owns the node with ID `id`. This is only
+    /// called for nodes in one of the namespaces of the address space, node managers
+    /// sharing a namespace with another node manager can use this to only claim
+    /// their own nodes.
+    fn owns_node(&self, id: &NodeId) -> bool {
+        true
+    }
+
     /// Return whether this node should handle requests to create a node
     /// for the given parent ID. This is only called if no new node ID is
     /// requested, otherwise owns_node is called on the requested node ID.
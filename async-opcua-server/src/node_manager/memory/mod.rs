@@ -2,15 +2,23 @@
 //! all its nodes in memory, and delegates implementing
 //! details to a type implementing [InMemoryNodeManagerImpl].
 
+mod history;
 mod memory_mgr_impl;
+mod model_change;
+mod query;
 mod simple;
 
 #[cfg(feature = "generated-address-space")]
 mod core;
+#[cfg(feature = "generated-address-space")]
+mod role_set;
+#[cfg(feature = "generated-address-space")]
+mod server_configuration;
 
 #[cfg(feature = "generated-address-space")]
 pub use core::{CoreNodeManager, CoreNodeManagerBuilder, CoreNodeManagerImpl};
 
+pub use history::InMemoryHistorian;
 pub use memory_mgr_impl::*;
 use opcua_core::{trace_read_lock, trace_write_lock};
 pub use simple::*;
@@ -26,8 +34,9 @@ use hashbrown::HashMap;
 
 use crate::{
     address_space::{
-        read_node_value, user_access_level, AccessLevel, EventNotifier, NodeType,
-        ReferenceDirection,
+        is_browsable, read_node_value, user_access_level, user_permissions,
+        validate_access_restrictions, validate_permission, AccessLevel, EventNotifier,
+        InstanceBuilder, NodeType, ReferenceDirection,
     },
     diagnostics::NamespaceMetadata,
     subscriptions::CreateMonitoredItem,
@@ -37,18 +46,19 @@ use opcua_core::sync::RwLock;
 use opcua_types::{
     argument::Argument, AttributeId, BrowseDescriptionResultMask, BrowseDirection, DataEncoding,
     DataValue, DateTime, ExpandedNodeId, MonitoringMode, NodeClass, NodeId, NumericRange,
-    ReadAnnotationDataDetails, ReadAtTimeDetails, ReadEventDetails, ReadProcessedDetails,
-    ReadRawModifiedDetails, ReferenceDescription, ReferenceTypeId, StatusCode, TimestampsToReturn,
-    Variant,
+    PermissionType, ReadAnnotationDataDetails, ReadAtTimeDetails, ReadEventDetails,
+    ReadProcessedDetails, ReadRawModifiedDetails, ReferenceDescription, ReferenceTypeId,
+    StatusCode, TimestampsToReturn, Variant,
 };
 
 use super::{
     build::NodeManagerBuilder,
+    history_read_processed_from_raw,
     view::{AddReferenceResult, ExternalReference, ExternalReferenceRequest, NodeMetadata},
     AddNodeItem, AddReferenceItem, BrowseNode, BrowsePathItem, DefaultTypeTree, DeleteNodeItem,
     DeleteReferenceItem, DynNodeManager, HistoryNode, HistoryUpdateDetails, HistoryUpdateNode,
-    MethodCall, MonitoredItemRef, MonitoredItemUpdateRef, NodeManager, ReadNode, RegisterNodeItem,
-    RequestContext, ServerContext, WriteNode,
+    MethodCall, MonitoredItemRef, MonitoredItemUpdateRef, NodeManager, NodeManagersRef,
+    QueryRequest, ReadNode, RegisterNodeItem, RequestContext, ServerContext, WriteNode,
 };
 
 use crate::address_space::AddressSpace;
@@ -66,39 +76,72 @@ struct BrowseContinuationPoint {
 pub struct InMemoryNodeManager<TImpl> {
     address_space: Arc<RwLock<AddressSpace>>,
     namespaces: HashMap<u16, String>,
+    historian: Option<Arc<InMemoryHistorian>>,
+    node_managers: NodeManagersRef,
     inner: TImpl,
 }
 
 /// Builder for the in-memory node manager.
 pub struct InMemoryNodeManagerBuilder<T> {
     impl_builder: T,
+    max_history_values_per_node: Option<usize>,
 }
 
 impl<T: InMemoryNodeManagerImplBuilder> InMemoryNodeManagerBuilder<T> {
     /// Create a new in memory node manager builder with the given
     /// builder for the [InMemoryNodeManagerImpl].
     pub fn new(impl_builder: T) -> Self {
-        Self { impl_builder }
+        Self {
+            impl_builder,
+            max_history_values_per_node: None,
+        }
+    }
+
+    /// Enable the built-in [InMemoryHistorian]. Values set with
+    /// [InMemoryNodeManager::set_values] on variables with `Historizing` set to `true`
+    /// are recorded, keeping at most `max_values_per_node` values for each variable.
+    ///
+    /// The historian handles raw, processed and at-time history reads, and data history updates.
+    /// Other history services are still forwarded to the [InMemoryNodeManagerImpl].
+    pub fn with_history(mut self, max_values_per_node: usize) -> Self {
+        self.max_history_values_per_node = Some(max_values_per_node);
+        self
     }
 }
 
 impl<T: InMemoryNodeManagerImplBuilder> NodeManagerBuilder for InMemoryNodeManagerBuilder<T> {
     fn build(self: Box<Self>, context: ServerContext) -> Arc<DynNodeManager> {
         let mut address_space = AddressSpace::new();
+        let node_managers = context.node_managers.clone();
         let inner = self.impl_builder.build(context, &mut address_space);
-        Arc::new(InMemoryNodeManager::new(inner, address_space))
+        let mut manager = InMemoryNodeManager::new(inner, address_space, node_managers);
+        if let Some(max_values) = self.max_history_values_per_node {
+            manager.historian = Some(Arc::new(InMemoryHistorian::new(max_values)));
+        }
+        Arc::new(manager)
     }
 }
 
 impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
-    pub(crate) fn new(inner: TImpl, address_space: AddressSpace) -> Self {
+    pub(crate) fn new(
+        inner: TImpl,
+        address_space: AddressSpace,
+        node_managers: NodeManagersRef,
+    ) -> Self {
         Self {
             namespaces: address_space.namespaces().clone(),
             address_space: Arc::new(RwLock::new(address_space)),
+            historian: None,
+            node_managers,
             inner,
         }
     }
 
+    /// Get the built-in historian, if history is enabled for this node manager.
+    pub fn historian(&self) -> Option<&Arc<InMemoryHistorian>> {
+        self.historian.as_ref()
+    }
+
     /// Return the inner [InMemoryNodeManagerImpl].
     pub fn inner(&self) -> &TImpl {
         &self.inner
@@ -141,6 +184,15 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
             }
         }
 
+        model_change::report_semantic_changes(
+            &address_space,
+            subscriptions,
+            output
+                .iter()
+                .filter(|(_, a)| *a == AttributeId::Value)
+                .map(|(id, _)| *id),
+        );
+
         subscriptions.maybe_notify(
             output.into_iter(),
             |node_id, attribute_id, index_range, data_encoding| {
@@ -173,6 +225,9 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
 
     /// Set variable values with updates given by `values`, notifying any
     /// subscriptions of the changes.
+    ///
+    /// If history is enabled, new values of variables with `Historizing` set
+    /// are also recorded in the historian.
     pub fn set_values<'a>(
         &self,
         subscriptions: &SubscriptionCache,
@@ -203,6 +258,19 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
                     } else {
                         v.set_data_value(value)
                     }
+                    if let Some(historian) = &self.historian {
+                        if v.historizing() {
+                            historian.record(
+                                id,
+                                v.value(
+                                    TimestampsToReturn::Both,
+                                    &NumericRange::None,
+                                    &DataEncoding::Binary,
+                                    0.0,
+                                ),
+                            );
+                        }
+                    }
                 }
                 NodeType::VariableType(v) => v.set_value(value.value.unwrap_or_default()),
                 _ => return Err(StatusCode::BadAttributeIdInvalid),
@@ -211,6 +279,12 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
             output.push((id, AttributeId::Value));
         }
 
+        model_change::report_semantic_changes(
+            &address_space,
+            subscriptions,
+            output.iter().map(|(id, _)| *id),
+        );
+
         subscriptions.maybe_notify(
             output.into_iter(),
             |node_id, attribute_id, index_range, data_encoding| {
@@ -241,6 +315,57 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
         self.set_values(subscriptions, [(id, index_range, value)].into_iter())
     }
 
+    /// Report changes to the structure of the address space made since changes were last
+    /// reported, by emitting a `GeneralModelChangeEventType` event from the `Server` object.
+    /// This also updates the `NodeVersion` property of affected nodes that have one.
+    ///
+    /// Changes made while handling the node management, `Write` and `Call` services
+    /// are reported at the end of each request. Call this after modifying the address
+    /// space directly.
+    pub fn notify_model_changes(&self, subscriptions: &SubscriptionCache) {
+        let mut address_space = trace_write_lock!(self.address_space);
+        model_change::report_model_changes(&mut address_space, subscriptions);
+    }
+
+    /// Insert an instance of an `ObjectType` or `VariableType` into the address space,
+    /// creating its mandatory children. See [InstanceBuilder] for details.
+    ///
+    /// The type may be defined in this node manager or in any other node manager
+    /// with an in-memory address space, such as the core node manager.
+    ///
+    /// Returns the IDs of the created nodes, starting with the instance itself.
+    pub fn instantiate(
+        &self,
+        type_tree: &RwLock<DefaultTypeTree>,
+        instance: InstanceBuilder,
+    ) -> Result<Vec<NodeId>, StatusCode> {
+        let mut sources = vec![self.address_space.clone()];
+        for node_manager in self.node_managers.iter() {
+            if let Some(address_space) = node_manager.in_memory_address_space() {
+                if !Arc::ptr_eq(&address_space, &self.address_space) {
+                    sources.push(address_space);
+                }
+            }
+        }
+
+        let plan = {
+            // Lock the address spaces in a consistent order, and before the type tree.
+            let mut order: Vec<_> = (0..sources.len()).collect();
+            order.sort_by_key(|i| Arc::as_ptr(&sources[*i]) as usize);
+            let mut locks: Vec<_> = order
+                .into_iter()
+                .map(|i| (i, trace_read_lock!(sources[i])))
+                .collect();
+            locks.sort_by_key(|(i, _)| *i);
+            let address_spaces: Vec<&AddressSpace> = locks.iter().map(|(_, a)| &**a).collect();
+            let type_tree = trace_read_lock!(type_tree);
+            instance.plan(&address_spaces, &*type_tree)?
+        };
+
+        let mut address_space = trace_write_lock!(self.address_space);
+        plan.insert(&mut address_space)
+    }
+
     fn get_reference(
         address_space: &AddressSpace,
         type_tree: &DefaultTypeTree,
@@ -289,8 +414,9 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
     fn browse_node(
         address_space: &AddressSpace,
         type_tree: &DefaultTypeTree,
+        context: &RequestContext,
         node: &mut BrowseNode,
-        namespaces: &hashbrown::HashMap<u16, String>,
+        owns_node: &dyn Fn(&NodeId) -> bool,
     ) {
         let reference_type_id = if node.reference_type_id().is_null() {
             None
@@ -320,7 +446,7 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
             }
             let target_node = address_space.find_node(reference.target_node);
             let Some(target_node) = target_node else {
-                if namespaces.contains_key(&reference.target_node.namespace) {
+                if owns_node(reference.target_node) {
                     warn!(
                         "Target node {} in reference from {} of type {} does not exist",
                         reference.target_node,
@@ -338,6 +464,10 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
                 continue;
             };
 
+            if !is_browsable(context, target_node) {
+                continue;
+            }
+
             let r_node =
                 Self::get_reference(address_space, type_tree, target_node, node.result_mask());
 
@@ -365,7 +495,7 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
         address_space: &AddressSpace,
         type_tree: &DefaultTypeTree,
         context: &RequestContext,
-        namespaces: &hashbrown::HashMap<u16, String>,
+        owns_node: &dyn Fn(&NodeId) -> bool,
         item: &mut BrowsePathItem,
     ) {
         if let Some(name) = item.unmatched_browse_name() {
@@ -408,7 +538,7 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
                 ) {
                     if !next_matching_nodes.contains(rf.target_node) {
                         let Some(node) = address_space.find_node(rf.target_node) else {
-                            if !namespaces.contains_key(&rf.target_node.namespace) {
+                            if !owns_node(rf.target_node) {
                                 results.push((
                                     rf.target_node,
                                     depth,
@@ -418,6 +548,10 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
                             continue;
                         };
 
+                        if !is_browsable(context, node) {
+                            continue;
+                        }
+
                         if element.target_name.is_null()
                             || node.as_node().browse_name() == &element.target_name
                         {
@@ -463,6 +597,11 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
                     history_node.set_status(StatusCode::BadHistoryOperationUnsupported);
                     continue;
                 }
+
+                if let Err(e) = validate_permission(context, node, PermissionType::ReadHistory) {
+                    history_node.set_status(e);
+                    continue;
+                }
             } else {
                 let NodeType::Variable(_) = node else {
                     history_node.set_status(StatusCode::BadHistoryOperationUnsupported);
@@ -477,6 +616,11 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
                 }
             }
 
+            if let Err(e) = validate_access_restrictions(context, node, false) {
+                history_node.set_status(e);
+                continue;
+            }
+
             valid.push(history_node);
         }
 
@@ -515,6 +659,15 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
                     history_node.set_status(StatusCode::BadHistoryOperationUnsupported);
                     continue;
                 }
+
+                let required = match history_node.details() {
+                    HistoryUpdateDetails::DeleteEvent(_) => PermissionType::DeleteHistory,
+                    _ => PermissionType::InsertHistory | PermissionType::ModifyHistory,
+                };
+                if user_permissions(context, node).is_some_and(|p| !p.intersects(required)) {
+                    history_node.set_status(StatusCode::BadUserAccessDenied);
+                    continue;
+                }
             } else {
                 let NodeType::Variable(_) = node else {
                     history_node.set_status(StatusCode::BadHistoryOperationUnsupported);
@@ -529,6 +682,11 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
                 }
             }
 
+            if let Err(e) = validate_access_restrictions(context, node, false) {
+                history_node.set_status(e);
+                continue;
+            }
+
             valid.push(history_node);
         }
 
@@ -558,13 +716,19 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
                 continue;
             };
 
-            let Some(NodeType::Method(method_node)) = address_space.find(method_ref.target_node)
-            else {
-                method.set_status(StatusCode::BadMethodInvalid);
-                continue;
+            let method_type = match address_space.find(method_ref.target_node) {
+                Some(n @ NodeType::Method(_)) => Some(n),
+                // The methods of a condition are the methods of its type, which are not in
+                // this address space. They are implemented by the condition manager, which
+                // also validates the arguments.
+                None if context.info.conditions.is_condition_call(method) => None,
+                _ => {
+                    method.set_status(StatusCode::BadMethodInvalid);
+                    continue;
+                }
             };
 
-            if !method_node.user_executable()
+            if method_type.is_some_and(|n| !matches!(n, NodeType::Method(m) if m.user_executable()))
                 || !context
                     .authenticator
                     .is_user_executable(&context.token, method.method_id())
@@ -573,6 +737,25 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
                 continue;
             }
 
+            // The Call permission is required on both the object and the method.
+            let object_node = address_space.find(method.object_id());
+            if let Err(e) = method_type
+                .into_iter()
+                .chain(object_node)
+                .try_for_each(|n| {
+                    validate_permission(context, n, PermissionType::Call)?;
+                    validate_access_restrictions(context, n, false)
+                })
+            {
+                method.set_status(e);
+                continue;
+            }
+
+            if method_type.is_none() {
+                valid.push(method);
+                continue;
+            }
+
             let input_arguments = address_space.find_node_by_browse_name(
                 method.method_id(),
                 Some((ReferenceTypeId::HasProperty, false)),
@@ -652,13 +835,17 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
 #[async_trait]
 impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl> {
     fn owns_node(&self, id: &NodeId) -> bool {
-        self.namespaces.contains_key(&id.namespace)
+        self.namespaces.contains_key(&id.namespace) && self.inner.owns_node(id)
     }
 
     fn name(&self) -> &str {
         self.inner.name()
     }
 
+    fn in_memory_address_space(&self) -> Option<Arc<RwLock<AddressSpace>>> {
+        Some(self.address_space.clone())
+    }
+
     #[allow(clippy::await_holding_lock)]
     async fn init(&self, type_tree: &mut DefaultTypeTree, context: ServerContext) {
         // During init we effectively own the address space, so this should be safe.
@@ -667,6 +854,10 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         self.inner.init(&mut address_space, context).await;
 
         address_space.load_into_type_tree(type_tree);
+        // Nodes created before this point are part of the initial address space,
+        // changes after this are reported to clients.
+        address_space.take_model_changes();
+        address_space.track_model_changes(true);
     }
 
     fn namespaces_for_user(&self, _context: &RequestContext) -> Vec<NamespaceMetadata> {
@@ -714,6 +905,14 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
                 continue;
             }
 
+            if address_space
+                .find(node.node_id())
+                .is_some_and(|n| !is_browsable(context, n))
+            {
+                node.set_status(StatusCode::BadNodeIdUnknown);
+                continue;
+            }
+
             node.set_status(StatusCode::Good);
 
             if let Some(mut point) = node.take_continuation_point::<BrowseContinuationPoint>() {
@@ -731,7 +930,9 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
                     node.set_next_continuation_point(point);
                 }
             } else {
-                Self::browse_node(&address_space, &type_tree, node, &self.namespaces);
+                Self::browse_node(&address_space, &type_tree, context, node, &|id| {
+                    self.owns_node(id)
+                });
             }
         }
 
@@ -796,7 +997,7 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
                 &address_space,
                 &type_tree,
                 context,
-                &self.namespaces,
+                &|id| self.owns_node(id),
                 node,
             );
         }
@@ -868,6 +1069,12 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
                         node.set_status(StatusCode::BadAttributeIdInvalid);
                         continue;
                     }
+                    if let Err(e) = validate_permission(context, n, PermissionType::ReceiveEvents)
+                        .and_then(|_| validate_access_restrictions(context, n, false))
+                    {
+                        node.set_status(e);
+                        continue;
+                    }
 
                     // No further action beyond just validation.
                     node.set_status(StatusCode::Good);
@@ -951,6 +1158,14 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         self.inner.delete_monitored_items(context, &items).await;
     }
 
+    async fn query(
+        &self,
+        context: &RequestContext,
+        request: &mut QueryRequest,
+    ) -> Result<(), StatusCode> {
+        self.query_address_space(context, request).await
+    }
+
     async fn history_read_raw_modified(
         &self,
         context: &RequestContext,
@@ -959,6 +1174,10 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         timestamps_to_return: TimestampsToReturn,
     ) -> Result<(), StatusCode> {
         let mut nodes = self.validate_history_read_nodes(context, nodes, false);
+        if let Some(historian) = &self.historian {
+            historian.read_raw_modified(details, &mut nodes, timestamps_to_return);
+            return Ok(());
+        }
         self.inner
             .history_read_raw_modified(context, details, &mut nodes, timestamps_to_return)
             .await
@@ -972,6 +1191,16 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         timestamps_to_return: TimestampsToReturn,
     ) -> Result<(), StatusCode> {
         let mut nodes = self.validate_history_read_nodes(context, nodes, false);
+        if let Some(historian) = &self.historian {
+            return history_read_processed_from_raw(
+                historian.as_ref(),
+                context,
+                details,
+                &mut nodes,
+                timestamps_to_return,
+            )
+            .await;
+        }
         self.inner
             .history_read_processed(context, details, &mut nodes, timestamps_to_return)
             .await
@@ -985,6 +1214,10 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         timestamps_to_return: TimestampsToReturn,
     ) -> Result<(), StatusCode> {
         let mut nodes = self.validate_history_read_nodes(context, nodes, false);
+        if let Some(historian) = &self.historian {
+            historian.read_at_time(details, &mut nodes, timestamps_to_return);
+            return Ok(());
+        }
         self.inner
             .history_read_at_time(context, details, &mut nodes, timestamps_to_return)
             .await
@@ -1023,7 +1256,23 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
     ) -> Result<(), StatusCode> {
         self.inner
             .write(context, &self.address_space, nodes_to_write)
-            .await
+            .await?;
+
+        {
+            let address_space = trace_read_lock!(self.address_space);
+            model_change::report_semantic_changes(
+                &address_space,
+                &context.subscriptions,
+                nodes_to_write
+                    .iter()
+                    .filter(|n| {
+                        n.status().is_good() && n.value().attribute_id == AttributeId::Value
+                    })
+                    .map(|n| &n.value().node_id),
+            );
+        }
+        self.notify_model_changes(&context.subscriptions);
+        Ok(())
     }
 
     async fn history_update(
@@ -1032,7 +1281,26 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         nodes: &mut [&mut HistoryUpdateNode],
     ) -> Result<(), StatusCode> {
         let mut nodes = self.validate_history_write_nodes(context, nodes);
-        self.inner.history_update(context, &mut nodes).await
+        let Some(historian) = &self.historian else {
+            return self.inner.history_update(context, &mut nodes).await;
+        };
+
+        let mut rest = Vec::with_capacity(nodes.len());
+        for node in nodes {
+            if InMemoryHistorian::supports_update(node.details()) {
+                historian.update(node);
+            } else {
+                rest.push(node);
+            }
+        }
+        if !rest.is_empty() {
+            if let Err(e) = self.inner.history_update(context, &mut rest).await {
+                for node in rest {
+                    node.set_status(e);
+                }
+            }
+        }
+        Ok(())
     }
 
     async fn call(
@@ -1041,9 +1309,28 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         methods_to_call: &mut [&mut MethodCall],
     ) -> Result<(), StatusCode> {
         let mut to_call = self.validate_method_calls(context, methods_to_call);
-        self.inner
+        let res = self
+            .inner
             .call(context, &self.address_space, &mut to_call)
-            .await
+            .await;
+        // Calls to condition methods that were not handled by the implementation
+        // are handled by the condition manager.
+        for call in to_call {
+            if call.status() == StatusCode::BadMethodInvalid {
+                if let Some(r) = context.info.conditions.call(context, call) {
+                    if let Err(e) = r {
+                        call.set_status(e);
+                    }
+                    continue;
+                }
+            }
+            if let Err(e) = res {
+                call.set_status(e);
+            }
+        }
+        // Methods may modify the address space, for example by adding roles.
+        self.notify_model_changes(&context.subscriptions);
+        Ok(())
     }
 
     /// Add a list of nodes.
@@ -1055,9 +1342,12 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         context: &RequestContext,
         nodes_to_add: &mut [&mut AddNodeItem],
     ) -> Result<(), StatusCode> {
-        self.inner
+        let res = self
+            .inner
             .add_nodes(context, &self.address_space, nodes_to_add)
-            .await
+            .await;
+        self.notify_model_changes(&context.subscriptions);
+        res
     }
 
     async fn add_references(
@@ -1065,9 +1355,12 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         context: &RequestContext,
         references_to_add: &mut [&mut AddReferenceItem],
     ) -> Result<(), StatusCode> {
-        self.inner
+        let res = self
+            .inner
             .add_references(context, &self.address_space, references_to_add)
-            .await
+            .await;
+        self.notify_model_changes(&context.subscriptions);
+        res
     }
 
     async fn delete_nodes(
@@ -1075,9 +1368,12 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         context: &RequestContext,
         nodes_to_delete: &mut [&mut DeleteNodeItem],
     ) -> Result<(), StatusCode> {
-        self.inner
+        let res = self
+            .inner
             .delete_nodes(context, &self.address_space, nodes_to_delete)
-            .await
+            .await;
+        self.notify_model_changes(&context.subscriptions);
+        res
     }
 
     async fn delete_node_references(
@@ -1087,7 +1383,8 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
     ) {
         self.inner
             .delete_node_references(context, &self.address_space, to_delete)
-            .await
+            .await;
+        self.notify_model_changes(&context.subscriptions);
     }
 
     async fn delete_references(
@@ -1095,8 +1392,11 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         context: &RequestContext,
         references_to_delete: &mut [&mut DeleteReferenceItem],
     ) -> Result<(), StatusCode> {
-        self.inner
+        let res = self
+            .inner
             .delete_references(context, &self.address_space, references_to_delete)
-            .await
+            .await;
+        self.notify_model_changes(&context.subscriptions);
+        res
     }
 }
@@ -0,0 +1,167 @@
+use opcua_types::{
+    AttributeId, ModelChangeStructureVerbMask, NodeId, SemanticChangeStructureDataType,
+    TimestampsToReturn,
+};
+
+use crate::{address_space::AddressSpace, SubscriptionCache};
+
+#[cfg(feature = "generated-address-space")]
+mod events {
+    use opcua_core_namespace::events::{
+        BaseModelChangeEventType, GeneralModelChangeEventType, SemanticChangeEventType,
+    };
+    use opcua_nodes::{BaseEventType, Event};
+    use opcua_types::{Guid, NodeId, ObjectId, ObjectTypeId, SemanticChangeStructureDataType};
+
+    use crate::{
+        address_space::{AddressSpace, ModelChanges},
+        SubscriptionCache,
+    };
+
+    fn base_event(type_id: ObjectTypeId, message: &str) -> BaseEventType {
+        BaseEventType::new_now(type_id, Guid::new().into(), message)
+            .set_source_node(ObjectId::Server.into())
+            .set_source_name("Server".into())
+    }
+
+    pub(super) fn emit_model_changes(
+        address_space: &AddressSpace,
+        subscriptions: &SubscriptionCache,
+        changes: ModelChanges,
+    ) {
+        let server_id: NodeId = ObjectId::Server.into();
+        if changes.is_overflowed() {
+            // Too many changes to list, just tell clients that something changed.
+            let event = BaseModelChangeEventType {
+                base: base_event(
+                    ObjectTypeId::BaseModelChangeEventType,
+                    "The address space has changed",
+                ),
+            };
+            subscriptions.notify_events_from_address_space(
+                [(&event as &dyn Event, &server_id)].into_iter(),
+                address_space,
+            );
+        } else {
+            let event = GeneralModelChangeEventType {
+                base: BaseModelChangeEventType {
+                    base: base_event(
+                        ObjectTypeId::GeneralModelChangeEventType,
+                        "The address space has changed",
+                    ),
+                },
+                changes: changes.into_changes(),
+            };
+            subscriptions.notify_events_from_address_space(
+                [(&event as &dyn Event, &server_id)].into_iter(),
+                address_space,
+            );
+        }
+    }
+
+    pub(super) fn emit_semantic_changes(
+        address_space: &AddressSpace,
+        subscriptions: &SubscriptionCache,
+        changes: Vec<SemanticChangeStructureDataType>,
+    ) {
+        let server_id: NodeId = ObjectId::Server.into();
+        let event = SemanticChangeEventType {
+            base: base_event(
+                ObjectTypeId::SemanticChangeEventType,
+                "The semantics of a node have changed",
+            ),
+            changes,
+        };
+        subscriptions.notify_events_from_address_space(
+            [(&event as &dyn Event, &server_id)].into_iter(),
+            address_space,
+        );
+    }
+}
+
+#[cfg(not(feature = "generated-address-space"))]
+mod events {
+    // Without the core namespace there is no `Server` object to emit events from.
+    use opcua_types::SemanticChangeStructureDataType;
+
+    use crate::{
+        address_space::{AddressSpace, ModelChanges},
+        SubscriptionCache,
+    };
+
+    pub(super) fn emit_model_changes(
+        _address_space: &AddressSpace,
+        _subscriptions: &SubscriptionCache,
+        _changes: ModelChanges,
+    ) {
+    }
+
+    pub(super) fn emit_semantic_changes(
+        _address_space: &AddressSpace,
+        _subscriptions: &SubscriptionCache,
+        _changes: Vec<SemanticChangeStructureDataType>,
+    ) {
+    }
+}
+
+/// Report changes to the structure of the address space recorded since the last
+/// time this was called, and update the `NodeVersion` of affected nodes.
+pub(super) fn report_model_changes(
+    address_space: &mut AddressSpace,
+    subscriptions: &SubscriptionCache,
+) {
+    let changes = address_space.take_model_changes();
+    if changes.is_empty() {
+        return;
+    }
+
+    let versions = address_space.increment_node_versions(
+        changes
+            .changes()
+            .iter()
+            .filter(|c| c.verb & ModelChangeStructureVerbMask::NodeDeleted as u8 == 0)
+            .map(|c| &c.affected),
+    );
+    notify_values(address_space, subscriptions, &versions);
+
+    events::emit_model_changes(address_space, subscriptions, changes);
+}
+
+/// Report semantic changes caused by changing the values of the given nodes.
+pub(super) fn report_semantic_changes<'a>(
+    address_space: &AddressSpace,
+    subscriptions: &SubscriptionCache,
+    node_ids: impl Iterator<Item = &'a NodeId>,
+) {
+    let mut changes: Vec<SemanticChangeStructureDataType> = Vec::new();
+    for change in node_ids.filter_map(|id| address_space.semantic_change(id)) {
+        if !changes.iter().any(|c| c.affected == change.affected) {
+            changes.push(change);
+        }
+    }
+    if !changes.is_empty() {
+        events::emit_semantic_changes(address_space, subscriptions, changes);
+    }
+}
+
+fn notify_values(
+    address_space: &AddressSpace,
+    subscriptions: &SubscriptionCache,
+    node_ids: &[NodeId],
+) {
+    if node_ids.is_empty() {
+        return;
+    }
+    subscriptions.maybe_notify(
+        node_ids.iter().map(|id| (id, AttributeId::Value)),
+        |node_id, attribute_id, index_range, data_encoding| {
+            let node = address_space.find(node_id)?;
+            node.as_node().get_attribute(
+                TimestampsToReturn::Both,
+                attribute_id,
+                index_range,
+                data_encoding,
+            )
+        },
+    );
+}
@@ -0,0 +1,458 @@
+use std::{
+    cell::Cell,
+    collections::{HashSet, VecDeque},
+};
+
+use opcua_core::trace_read_lock;
+use opcua_nodes::{
+    AttributeQueryable, DefaultTypeTree, HasNodeId, ParsedAttributeOperand, TypeTree,
+};
+use opcua_types::{
+    AttributeId, BrowseDirection, DataEncoding, DataValue, ExpandedNodeId, NodeClass, NodeId,
+    NumericRange, QualifiedName, QueryDataSet, ReferenceTypeId, RelativePath, StatusCode,
+    TimestampsToReturn, Variant,
+};
+
+use crate::{
+    address_space::{is_browsable, AddressSpace, NodeType},
+    node_manager::{ParsedReadValueId, QueryRequest, RequestContext},
+    ContinuationPoint,
+};
+
+use super::{InMemoryNodeManager, InMemoryNodeManagerImpl};
+
+/// Node matching a query, waiting to be turned into a data set.
+struct QueryMatch {
+    node_id: NodeId,
+    type_definition: NodeId,
+    // Index of the matched node type description in the request.
+    node_type: usize,
+}
+
+struct QueryContinuation {
+    matches: VecDeque<QueryMatch>,
+}
+
+/// A node in the address space, evaluated against a query content filter.
+#[derive(Clone, Copy)]
+struct QueryItem<'a> {
+    node: &'a NodeType,
+    address_space: &'a AddressSpace,
+    type_tree: &'a DefaultTypeTree,
+    context: &'a RequestContext,
+    /// Number of nodes that may still be visited by this query.
+    budget: &'a Cell<usize>,
+}
+
+/// Consume `count` nodes from the budget of a query, returning `false` if
+/// the budget is exhausted.
+fn visit_nodes(budget: &Cell<usize>, count: usize) -> bool {
+    match budget.get().checked_sub(count) {
+        Some(remaining) => {
+            budget.set(remaining);
+            true
+        }
+        None => {
+            budget.set(0);
+            false
+        }
+    }
+}
+
+fn type_definition(
+    address_space: &AddressSpace,
+    type_tree: &DefaultTypeTree,
+    node_id: &NodeId,
+) -> Option<NodeId> {
+    address_space
+        .find_references(
+            node_id,
+            Some((ReferenceTypeId::HasTypeDefinition, false)),
+            type_tree,
+            BrowseDirection::Forward,
+        )
+        .next()
+        .map(|r| r.target_node.clone())
+}
+
+/// Find the nodes at the end of `path`, starting from `start`, returning at most `max` nodes.
+/// An empty path refers to the starting node itself.
+fn resolve_relative_path<'a>(
+    address_space: &'a AddressSpace,
+    type_tree: &DefaultTypeTree,
+    context: &RequestContext,
+    start: &'a NodeId,
+    path: &RelativePath,
+    max: usize,
+) -> Vec<&'a NodeId> {
+    let mut matching_nodes = vec![start];
+    for element in path.elements.iter().flatten() {
+        let reference_filter = if element.reference_type_id.is_null() {
+            None
+        } else {
+            Some((element.reference_type_id.clone(), element.include_subtypes))
+        };
+        let mut next_matching_nodes = Vec::new();
+        let mut seen = HashSet::new();
+        for node_id in matching_nodes {
+            for rf in address_space.find_references(
+                node_id,
+                reference_filter.clone(),
+                type_tree,
+                if element.is_inverse {
+                    BrowseDirection::Inverse
+                } else {
+                    BrowseDirection::Forward
+                },
+            ) {
+                let Some(node) = address_space.find_node(rf.target_node) else {
+                    continue;
+                };
+                if !is_browsable(context, node) {
+                    continue;
+                }
+                if (element.target_name.is_null()
+                    || node.as_node().browse_name() == &element.target_name)
+                    && seen.insert(rf.target_node)
+                {
+                    next_matching_nodes.push(rf.target_node);
+                }
+            }
+        }
+        matching_nodes = next_matching_nodes;
+    }
+    matching_nodes.truncate(max);
+    matching_nodes
+}
+
+impl QueryItem<'_> {
+    fn read_attribute(
+        &self,
+        node: &NodeType,
+        attribute_id: AttributeId,
+        index_range: &NumericRange,
+    ) -> Variant {
+        if !is_browsable(self.context, node) {
+            return Variant::Empty;
+        }
+        self.address_space
+            .read(
+                self.context,
+                &ParsedReadValueId {
+                    node_id: node.node_id().clone(),
+                    attribute_id,
+                    index_range: index_range.clone(),
+                    data_encoding: DataEncoding::Binary,
+                },
+                0.0,
+                TimestampsToReturn::Neither,
+            )
+            .value
+            .unwrap_or_default()
+    }
+}
+
+impl AttributeQueryable for QueryItem<'_> {
+    fn get_attribute(
+        &self,
+        type_definition_id: &NodeId,
+        browse_path: &[QualifiedName],
+        attribute_id: AttributeId,
+        index_range: &NumericRange,
+    ) -> Variant {
+        if !type_definition_id.is_null() && !self.is_of_type(type_definition_id, true) {
+            return Variant::Empty;
+        }
+        let Some(node) = self.address_space.find_node_by_browse_path(
+            self.node.node_id(),
+            Some((ReferenceTypeId::HierarchicalReferences, true)),
+            self.type_tree,
+            BrowseDirection::Forward,
+            browse_path,
+        ) else {
+            return Variant::Empty;
+        };
+        self.read_attribute(node, attribute_id, index_range)
+    }
+
+    fn get_attribute_operand(&self, operand: &ParsedAttributeOperand) -> Variant {
+        if !self.is_of_type(&operand.node_id, true) {
+            return Variant::Empty;
+        }
+        let Some(node) = resolve_relative_path(
+            self.address_space,
+            self.type_tree,
+            self.context,
+            self.node.node_id(),
+            &operand.browse_path,
+            1,
+        )
+        .into_iter()
+        .next()
+        .and_then(|id| self.address_space.find_node(id)) else {
+            return Variant::Empty;
+        };
+        self.read_attribute(node, operand.attribute_id, &operand.index_range)
+    }
+
+    fn is_of_type(&self, type_definition_id: &NodeId, include_subtypes: bool) -> bool {
+        let Some(type_definition) =
+            type_definition(self.address_space, self.type_tree, self.node.node_id())
+        else {
+            return false;
+        };
+        if include_subtypes {
+            self.type_tree
+                .is_subtype_of(&type_definition, type_definition_id)
+        } else {
+            &type_definition == type_definition_id
+        }
+    }
+
+    fn in_view(&self, view_id: &NodeId) -> bool {
+        if !self
+            .address_space
+            .find_node(view_id)
+            .is_some_and(|n| n.node_class() == NodeClass::View)
+        {
+            return false;
+        }
+        // A node is in a view if it is reachable from the view through
+        // hierarchical references. Search upwards from the node, since nodes
+        // usually have far fewer parents than children.
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(self.node.node_id());
+        while let Some(node_id) = queue.pop_front() {
+            for rf in self.address_space.find_references(
+                node_id,
+                Some((ReferenceTypeId::HierarchicalReferences, true)),
+                self.type_tree,
+                BrowseDirection::Inverse,
+            ) {
+                if rf.target_node == view_id {
+                    return true;
+                }
+                if visited.insert(rf.target_node) {
+                    queue.push_back(rf.target_node);
+                }
+            }
+        }
+        false
+    }
+
+    fn related(&self, reference_type_id: &NodeId, include_subtypes: bool) -> Vec<Self> {
+        let related: Vec<_> = self
+            .address_space
+            .find_references(
+                self.node.node_id(),
+                Some((reference_type_id.clone(), include_subtypes)),
+                self.type_tree,
+                BrowseDirection::Forward,
+            )
+            .filter_map(|rf| self.address_space.find_node(rf.target_node))
+            .filter(|n| is_browsable(self.context, n))
+            .map(|node| QueryItem { node, ..*self })
+            .collect();
+        // Once the budget is exhausted the query fails, so stop following references.
+        if !visit_nodes(self.budget, related.len()) {
+            return Vec::new();
+        }
+        related
+    }
+
+    fn node_id(&self) -> Option<NodeId> {
+        Some(self.node.node_id().clone())
+    }
+}
+
+fn find_matches(
+    address_space: &AddressSpace,
+    type_tree: &DefaultTypeTree,
+    context: &RequestContext,
+    request: &QueryRequest,
+) -> Result<VecDeque<QueryMatch>, StatusCode> {
+    let node_types: Vec<_> = request
+        .node_types()
+        .iter()
+        .map(|t| {
+            t.type_definition_node
+                .try_resolve(type_tree.namespaces())
+                .map(|id| id.into_owned())
+        })
+        .collect();
+
+    let budget = Cell::new(request.max_nodes_visited());
+    let mut matches = VecDeque::new();
+    for node in address_space.nodes() {
+        if !visit_nodes(&budget, 1) {
+            return Err(StatusCode::BadQueryTooComplex);
+        }
+        if !matches!(node.node_class(), NodeClass::Object | NodeClass::Variable)
+            || !is_browsable(context, node)
+        {
+            continue;
+        }
+        let Some(type_definition) = type_definition(address_space, type_tree, node.node_id())
+        else {
+            continue;
+        };
+        let Some(node_type) = node_types
+            .iter()
+            .zip(request.node_types())
+            .position(|(id, desc)| {
+                id.as_ref().is_some_and(|id| {
+                    if desc.include_sub_types {
+                        type_tree.is_subtype_of(&type_definition, id)
+                    } else {
+                        &type_definition == id
+                    }
+                })
+            })
+        else {
+            continue;
+        };
+        let item = QueryItem {
+            node,
+            address_space,
+            type_tree,
+            context,
+            budget: &budget,
+        };
+        let matched = request.filter().evaluate(item);
+        if budget.get() == 0 {
+            return Err(StatusCode::BadQueryTooComplex);
+        }
+        if !matched {
+            continue;
+        }
+        matches.push_back(QueryMatch {
+            node_id: node.node_id().clone(),
+            type_definition,
+            node_type,
+        });
+    }
+    Ok(matches)
+}
+
+fn data_value_to_variant(value: DataValue) -> Variant {
+    match value.status {
+        Some(s) if s.is_bad() => Variant::StatusCode(s),
+        _ => value.value.unwrap_or_default(),
+    }
+}
+
+impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
+    pub(super) async fn query_address_space(
+        &self,
+        context: &RequestContext,
+        request: &mut QueryRequest,
+    ) -> Result<(), StatusCode> {
+        let mut values = Vec::new();
+        let mut value_reads = Vec::new();
+        let mut page = Vec::new();
+        {
+            let address_space = trace_read_lock!(self.address_space);
+            let type_tree = trace_read_lock!(context.type_tree);
+
+            // A continuation point from a different node manager means we start from the beginning.
+            let mut matches = match request.take_continuation_point::<QueryContinuation>() {
+                Some(c) => c.matches,
+                None => find_matches(&address_space, &type_tree, context, request)?,
+            };
+
+            while page.len() < request.remaining_data_sets() {
+                let Some(m) = matches.pop_front() else {
+                    break;
+                };
+                // Values for each data description, for each node found by its relative path.
+                let mut data_set_values = Vec::new();
+                for desc in &request.node_types()[m.node_type].data_to_return {
+                    let targets = resolve_relative_path(
+                        &address_space,
+                        &type_tree,
+                        context,
+                        &m.node_id,
+                        &desc.relative_path,
+                        request.max_references_to_return(),
+                    );
+                    let mut desc_values = Vec::with_capacity(targets.len());
+                    for target in targets {
+                        let read = ParsedReadValueId {
+                            node_id: target.clone(),
+                            attribute_id: desc.attribute_id,
+                            index_range: desc.index_range.clone(),
+                            data_encoding: DataEncoding::Binary,
+                        };
+                        // Values are read after releasing the lock, since they may be dynamic.
+                        if desc.attribute_id == AttributeId::Value {
+                            value_reads.push((
+                                (page.len(), data_set_values.len(), desc_values.len()),
+                                read,
+                            ));
+                            desc_values.push(DataValue::default());
+                        } else {
+                            desc_values.push(address_space.read(
+                                context,
+                                &read,
+                                0.0,
+                                TimestampsToReturn::Neither,
+                            ));
+                        }
+                    }
+                    data_set_values.push(desc_values);
+                }
+                values.push(data_set_values);
+                page.push(m);
+            }
+
+            if !matches.is_empty() {
+                request.set_next_continuation_point(Some(ContinuationPoint::new(Box::new(
+                    QueryContinuation { matches },
+                ))));
+            }
+        }
+
+        if !value_reads.is_empty() {
+            let ids: Vec<_> = value_reads.iter().map(|(_, r)| r).collect();
+            let read_values = self
+                .inner
+                .read_values(
+                    context,
+                    &self.address_space,
+                    &ids,
+                    0.0,
+                    TimestampsToReturn::Neither,
+                )
+                .await;
+            for (((data_set, desc, index), _), value) in value_reads.iter().zip(read_values) {
+                values[*data_set][*desc][*index] = value;
+            }
+        }
+
+        for (m, data_set_values) in page.into_iter().zip(values) {
+            let values = data_set_values
+                .into_iter()
+                .map(|mut v| {
+                    if v.len() == 1 {
+                        data_value_to_variant(v.remove(0))
+                    } else if v.is_empty() {
+                        Variant::Empty
+                    } else {
+                        v.into_iter()
+                            .map(data_value_to_variant)
+                            .collect::<Vec<_>>()
+                            .into()
+                    }
+                })
+                .collect();
+            request.add_data_set(QueryDataSet {
+                node_id: ExpandedNodeId::new(m.node_id),
+                type_definition_node: ExpandedNodeId::new(m.type_definition),
+                values: Some(values),
+            });
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,317 @@
+//! Implementation of the `RoleSet` object from OPC UA Part 18, exposing the
+//! roles in the server [`RoleSet`](crate::roles::RoleSet) in the address space.
+//!
+//! The role properties are read from the role set on each read, and the methods
+//! for managing roles require the `SecurityAdmin` role and an encrypted channel.
+
+use opcua_core::{trace_read_lock, trace_write_lock};
+use opcua_nodes::{AccessLevel, NodeType, ObjectBuilder, TypeTree, VariableBuilder};
+use opcua_types::{
+    BrowseDirection, DataTypeId, EndpointType, ExtensionObject, Guid, IdentityMappingRuleType,
+    MessageSecurityMode, MethodId, NodeClass, NodeId, ObjectId, ObjectTypeId, QualifiedName,
+    ReferenceTypeId, StatusCode, TryFromVariant, UAString, VariableTypeId, Variant,
+};
+
+use crate::{
+    address_space::AddressSpace,
+    node_manager::{MethodCall, RequestContext},
+    roles::{Role, RoleSet},
+};
+use opcua_core::sync::RwLock;
+
+/// Methods defined on `RoleType`, used by roles added with `AddRole`.
+const ROLE_TYPE_METHODS: [MethodId; 6] = [
+    MethodId::RoleType_AddIdentity,
+    MethodId::RoleType_RemoveIdentity,
+    MethodId::RoleType_AddApplication,
+    MethodId::RoleType_RemoveApplication,
+    MethodId::RoleType_AddEndpoint,
+    MethodId::RoleType_RemoveEndpoint,
+];
+
+/// Handles the methods and dynamic variables on the `RoleSet` object and its roles.
+pub(super) struct RoleSetObject;
+
+impl RoleSetObject {
+    /// Make the role management methods callable.
+    pub(super) fn init(
+        address_space: &mut AddressSpace,
+        type_tree: &dyn TypeTree,
+        roles: &RoleSet,
+    ) {
+        let mut methods = vec![
+            NodeId::from(MethodId::Server_ServerCapabilities_RoleSet_AddRole),
+            NodeId::from(MethodId::Server_ServerCapabilities_RoleSet_RemoveRole),
+        ];
+        methods.extend(ROLE_TYPE_METHODS.iter().map(|m| NodeId::from(*m)));
+        for role in roles.roles() {
+            methods.extend(
+                address_space
+                    .find_references(
+                        &role.node_id,
+                        Some((ReferenceTypeId::HasComponent, false)),
+                        type_tree,
+                        BrowseDirection::Forward,
+                    )
+                    .map(|r| r.target_node.clone()),
+            );
+        }
+
+        for method in methods {
+            let Some(NodeType::Method(m)) = address_space.find_mut(&method) else {
+                continue;
+            };
+            m.set_executable(true);
+            m.set_user_executable(true);
+        }
+    }
+
+    /// Read the value of one of the properties of a role, or `None` if `node_id`
+    /// is not a role property.
+    pub(super) fn read_value(
+        context: &RequestContext,
+        address_space: &AddressSpace,
+        node_id: &NodeId,
+    ) -> Option<Variant> {
+        let node = address_space.find(node_id)?;
+        if node.node_class() != NodeClass::Variable
+            || node.as_node().browse_name().namespace_index != 0
+        {
+            return None;
+        }
+        let name = node.as_node().browse_name().name.as_ref();
+        if !matches!(
+            name,
+            "Identities"
+                | "Applications"
+                | "ApplicationsExclude"
+                | "Endpoints"
+                | "EndpointsExclude"
+        ) {
+            return None;
+        }
+        let role = {
+            let type_tree = trace_read_lock!(context.type_tree);
+            let parent = address_space
+                .find_references(
+                    node_id,
+                    Some((ReferenceTypeId::HasProperty, false)),
+                    &*type_tree,
+                    BrowseDirection::Inverse,
+                )
+                .next()?
+                .target_node
+                .clone();
+            context.info.roles.get(&parent)?
+        };
+
+        Some(match name {
+            "Identities" => role
+                .identities
+                .into_iter()
+                .map(ExtensionObject::from_message)
+                .collect::<Vec<_>>()
+                .into(),
+            "Applications" => role.applications.into(),
+            "ApplicationsExclude" => role.applications_exclude.into(),
+            "Endpoints" => role
+                .endpoints
+                .into_iter()
+                .map(ExtensionObject::from_message)
+                .collect::<Vec<_>>()
+                .into(),
+            "EndpointsExclude" => role.endpoints_exclude.into(),
+            _ => return None,
+        })
+    }
+
+    /// Call one of the role management methods. Returns `None` if `id` is not
+    /// a role management method.
+    pub(super) fn call(
+        call: &mut MethodCall,
+        context: &RequestContext,
+        address_space: &RwLock<AddressSpace>,
+        id: MethodId,
+    ) -> Option<Result<(), StatusCode>> {
+        let res = match id {
+            MethodId::Server_ServerCapabilities_RoleSet_AddRole => {
+                Self::add_role(call, context, address_space)
+            }
+            MethodId::Server_ServerCapabilities_RoleSet_RemoveRole => {
+                Self::remove_role(call, context, address_space)
+            }
+            _ => {
+                // Each role has its own instance of the methods, so look up the method
+                // by its browse name, and the role by the object ID.
+                let role = context.info.roles.get(call.object_id())?;
+                let name = {
+                    let address_space = trace_read_lock!(address_space);
+                    let node = address_space.find(call.method_id())?;
+                    node.as_node().browse_name().name.as_ref().to_owned()
+                };
+                Self::update_role(call, context, &role, &name)?
+            }
+        };
+        Some(res)
+    }
+
+    /// Managing roles requires the `SecurityAdmin` role and an encrypted channel.
+    fn check_access(context: &RequestContext) -> Result<(), StatusCode> {
+        let session = trace_read_lock!(context.session);
+        if !session
+            .roles()
+            .contains(&ObjectId::WellKnownRole_SecurityAdmin.into())
+        {
+            return Err(StatusCode::BadUserAccessDenied);
+        }
+        if session.message_security_mode() != MessageSecurityMode::SignAndEncrypt {
+            return Err(StatusCode::BadSecurityModeInsufficient);
+        }
+        Ok(())
+    }
+
+    fn argument<T: TryFromVariant>(call: &MethodCall, index: usize) -> Result<T, StatusCode> {
+        call.arguments()
+            .get(index)
+            .cloned()
+            .unwrap_or_default()
+            .try_cast_to()
+            .map_err(|_| StatusCode::BadInvalidArgument)
+    }
+
+    fn add_role(
+        call: &mut MethodCall,
+        context: &RequestContext,
+        address_space: &RwLock<AddressSpace>,
+    ) -> Result<(), StatusCode> {
+        Self::check_access(context)?;
+        let name: UAString = Self::argument(call, 0)?;
+        let namespace_uri: UAString = Self::argument(call, 1)?;
+        if name.is_empty() {
+            return Err(StatusCode::BadInvalidArgument);
+        }
+        // New nodes are created in the namespace of the server, and role names without
+        // a namespace are qualified by it as well.
+        let (server_namespace, namespace_index) = {
+            let type_tree = trace_read_lock!(context.type_tree);
+            let server_namespace = type_tree
+                .namespaces()
+                .get_index(context.info.application_uri.as_ref())
+                .ok_or(StatusCode::BadInternalError)?;
+            let namespace_index = if namespace_uri.is_empty() {
+                server_namespace
+            } else {
+                type_tree
+                    .namespaces()
+                    .get_index(namespace_uri.as_ref())
+                    .ok_or(StatusCode::BadInvalidArgument)?
+            };
+            (server_namespace, namespace_index)
+        };
+        let browse_name = QualifiedName::new(namespace_index, name.as_ref());
+
+        let role_id = context
+            .info
+            .roles
+            .add_role(browse_name.clone(), server_namespace)?;
+        let mut address_space = trace_write_lock!(address_space);
+        ObjectBuilder::new(&role_id, browse_name, name.as_ref())
+            .component_of(ObjectId::Server_ServerCapabilities_RoleSet)
+            .has_type_definition(ObjectTypeId::RoleType)
+            .insert(&mut *address_space);
+        for (property, data_type, value_rank) in [
+            ("Identities", DataTypeId::IdentityMappingRuleType, 1),
+            ("Applications", DataTypeId::String, 1),
+            ("ApplicationsExclude", DataTypeId::Boolean, -1),
+            ("Endpoints", DataTypeId::EndpointType, 1),
+            ("EndpointsExclude", DataTypeId::Boolean, -1),
+        ] {
+            VariableBuilder::new(
+                &NodeId::new(server_namespace, Guid::new()),
+                property,
+                property,
+            )
+            .property_of(role_id.clone())
+            .has_type_definition(VariableTypeId::PropertyType)
+            .data_type(data_type)
+            .value_rank(value_rank)
+            .access_level(AccessLevel::CURRENT_READ)
+            .user_access_level(AccessLevel::CURRENT_READ)
+            .insert(&mut *address_space);
+        }
+        for method in ROLE_TYPE_METHODS {
+            address_space.insert_reference(&role_id, &method.into(), ReferenceTypeId::HasComponent);
+        }
+
+        call.set_outputs(vec![role_id.into()]);
+        call.set_status(StatusCode::Good);
+        Ok(())
+    }
+
+    fn remove_role(
+        call: &mut MethodCall,
+        context: &RequestContext,
+        address_space: &RwLock<AddressSpace>,
+    ) -> Result<(), StatusCode> {
+        Self::check_access(context)?;
+        let role_id: NodeId = Self::argument(call, 0)?;
+        context.info.roles.remove_role(&role_id)?;
+
+        let mut address_space = trace_write_lock!(address_space);
+        let type_tree = trace_read_lock!(context.type_tree);
+        let properties: Vec<_> = address_space
+            .find_references(
+                &role_id,
+                Some((ReferenceTypeId::HasProperty, false)),
+                &*type_tree,
+                BrowseDirection::Forward,
+            )
+            .map(|r| r.target_node.clone())
+            .collect();
+        for property in properties {
+            address_space.delete(&property, true);
+        }
+        address_space.delete(&role_id, true);
+
+        call.set_status(StatusCode::Good);
+        Ok(())
+    }
+
+    fn update_role(
+        call: &mut MethodCall,
+        context: &RequestContext,
+        role: &Role,
+        method_name: &str,
+    ) -> Option<Result<(), StatusCode>> {
+        let roles = &context.info.roles;
+        let id = &role.node_id;
+        if let Err(e) = Self::check_access(context) {
+            return Some(Err(e));
+        }
+        let res = match method_name {
+            "AddIdentity" => Self::argument::<IdentityMappingRuleType>(call, 0)
+                .and_then(|rule| roles.add_identity(id, rule)),
+            "RemoveIdentity" => Self::argument::<IdentityMappingRuleType>(call, 0)
+                .and_then(|rule| roles.remove_identity(id, &rule)),
+            "AddApplication" => {
+                Self::argument::<UAString>(call, 0).and_then(|uri| roles.add_application(id, uri))
+            }
+            "RemoveApplication" => Self::argument::<UAString>(call, 0)
+                .and_then(|uri| roles.remove_application(id, &uri)),
+            "AddEndpoint" => Self::argument::<EndpointType>(call, 0)
+                .and_then(|endpoint| roles.add_endpoint(id, endpoint)),
+            "RemoveEndpoint" => Self::argument::<EndpointType>(call, 0)
+                .and_then(|endpoint| roles.remove_endpoint(id, &endpoint)),
+            _ => return None,
+        };
+        Some(res.and_then(|changed| {
+            // Adding an existing entry is allowed, but removing a missing one is not.
+            if !changed && method_name.starts_with("Remove") {
+                Err(StatusCode::BadNotFound)
+            } else {
+                call.set_status(StatusCode::Good);
+                Ok(())
+            }
+        }))
+    }
+}
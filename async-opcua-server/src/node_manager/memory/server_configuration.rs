@@ -0,0 +1,707 @@
+//! Implementation of the push certificate management model from OPC UA Part 12,
+//! exposed through the `ServerConfiguration` object and the `TrustList` of the
+//! `DefaultApplicationGroup`.
+//!
+//! Changes are applied immediately, so `ApplyChanges` never needs to be called.
+//! A new certificate is used for every secure channel opened after it has been applied.
+
+use std::{
+    io::Cursor,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use hashbrown::HashMap;
+use opcua_core::{
+    sync::{Mutex, RwLock},
+    trace_lock, trace_read_lock, trace_write_lock,
+};
+use opcua_crypto::{CertificateStore, KeySize, PrivateKey, X509};
+use opcua_nodes::NodeType;
+use opcua_types::{
+    BinaryDecodable, BinaryEncodable, ByteString, MessageSecurityMode, MethodId, NodeId, ObjectId,
+    ObjectTypeId, ReferenceTypeId, StatusCode, TrustListDataType, TrustListMasks, TryFromVariant,
+    UAString, VariableId, Variant, VariantScalarTypeId, VariantTypeId,
+};
+use tracing::{error, info};
+
+use crate::{
+    address_space::AddressSpace,
+    load_method_args,
+    node_manager::{MethodCall, RequestContext},
+    ServerKeyPair,
+};
+
+/// Open handles to the trust list that have not been used for this long are closed
+/// the next time the trust list is opened.
+const TRUST_LIST_ACTIVITY_TIMEOUT: Duration = Duration::from_secs(300);
+/// Key size used when a new private key is generated by `CreateSigningRequest`,
+/// if the server has no key already.
+const DEFAULT_KEY_SIZE: u32 = 2048;
+/// Certificate types of the ECC security policies. Only the RSA application certificate
+/// is managed through the server configuration, so these are rejected.
+const ECC_CERTIFICATE_TYPES: [ObjectTypeId; 7] = [
+    ObjectTypeId::EccApplicationCertificateType,
+    ObjectTypeId::EccNistP256ApplicationCertificateType,
+    ObjectTypeId::EccNistP384ApplicationCertificateType,
+    ObjectTypeId::EccBrainpoolP256r1ApplicationCertificateType,
+    ObjectTypeId::EccBrainpoolP384r1ApplicationCertificateType,
+    ObjectTypeId::EccCurve25519ApplicationCertificateType,
+    ObjectTypeId::EccCurve448ApplicationCertificateType,
+];
+
+/// Mode bits for the `Open` method on `FileType`.
+mod open_mode {
+    pub(super) const READ: u8 = 1;
+    pub(super) const WRITE: u8 = 2;
+    pub(super) const ERASE_EXISTING: u8 = 4;
+    pub(super) const APPEND: u8 = 8;
+}
+
+/// An open handle to the trust list file.
+struct TrustListFile {
+    session_id: u32,
+    mode: u8,
+    data: Vec<u8>,
+    position: usize,
+    last_used: Instant,
+}
+
+#[derive(Default)]
+struct ServerConfigurationState {
+    /// Private key generated by `CreateSigningRequest`, used once the signed
+    /// certificate is passed to `UpdateCertificate`.
+    pending_pkey: Option<PrivateKey>,
+    files: HashMap<u32, TrustListFile>,
+    next_file_handle: u32,
+}
+
+impl ServerConfigurationState {
+    fn remove_expired_files(&mut self) {
+        self.files
+            .retain(|_, f| f.last_used.elapsed() < TRUST_LIST_ACTIVITY_TIMEOUT);
+    }
+}
+
+/// Handles the methods and dynamic variables on the `ServerConfiguration` object.
+pub(super) struct ServerConfiguration {
+    certificate_store: Arc<RwLock<CertificateStore>>,
+    state: Mutex<ServerConfigurationState>,
+}
+
+impl ServerConfiguration {
+    pub(super) fn new(certificate_store: Arc<RwLock<CertificateStore>>) -> Self {
+        Self {
+            certificate_store,
+            state: Mutex::new(ServerConfigurationState::default()),
+        }
+    }
+
+    /// Make the methods callable, and add the optional `TrustListType` methods to the
+    /// trust list instance, since they are not part of the core namespace.
+    pub(super) fn init(&self, address_space: &mut AddressSpace) {
+        let trust_list: NodeId =
+            ObjectId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList
+                .into();
+        for method in [
+            MethodId::TrustListType_OpenWithMasks,
+            MethodId::TrustListType_CloseAndUpdate,
+            MethodId::TrustListType_AddCertificate,
+            MethodId::TrustListType_RemoveCertificate,
+        ] {
+            address_space.insert_reference(
+                &trust_list,
+                &method.into(),
+                ReferenceTypeId::HasComponent,
+            );
+        }
+
+        for method in [
+            MethodId::ServerConfiguration_UpdateCertificate,
+            MethodId::ServerConfiguration_CreateSigningRequest,
+            MethodId::ServerConfiguration_GetRejectedList,
+            MethodId::ServerConfiguration_ApplyChanges,
+            MethodId::ServerConfiguration_CancelChanges,
+            MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_GetRejectedList,
+            MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_Open,
+            MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_Close,
+            MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_Read,
+            MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_Write,
+            MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_GetPosition,
+            MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_SetPosition,
+            MethodId::TrustListType_OpenWithMasks,
+            MethodId::TrustListType_CloseAndUpdate,
+            MethodId::TrustListType_AddCertificate,
+            MethodId::TrustListType_RemoveCertificate,
+        ] {
+            let Some(NodeType::Method(m)) = address_space.find_mut(method) else {
+                continue;
+            };
+            m.set_executable(true);
+            m.set_user_executable(true);
+        }
+    }
+
+    /// Read the value of one of the variables managed by the server configuration,
+    /// or `None` if `var_id` is not one of them.
+    pub(super) fn read_value(
+        &self,
+        context: &RequestContext,
+        var_id: VariableId,
+    ) -> Option<Variant> {
+        Some(match var_id {
+            VariableId::ServerConfiguration_SupportedPrivateKeyFormats => {
+                vec![UAString::from("PEM")].into()
+            }
+            VariableId::ServerConfiguration_MaxTrustListSize => {
+                (context.info.config.limits.max_trust_list_size.min(u32::MAX as usize) as u32)
+                    .into()
+            }
+            VariableId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_CertificateTypes => {
+                vec![NodeId::from(ObjectTypeId::RsaSha256ApplicationCertificateType)].into()
+            }
+            VariableId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_Size => {
+                (self.encode_trust_list(context, TrustListMasks::All as u32).len() as u64).into()
+            }
+            VariableId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_OpenCount => {
+                (trace_lock!(self.state).files.len() as u16).into()
+            }
+            VariableId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_Writable => {
+                true.into()
+            }
+            VariableId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_UserWritable => {
+                Self::check_access(context).is_ok().into()
+            }
+            _ => return None,
+        })
+    }
+
+    /// Call one of the server configuration methods. Returns `None` if `id` is not
+    /// a server configuration method.
+    pub(super) fn call(
+        &self,
+        call: &mut MethodCall,
+        context: &RequestContext,
+        id: MethodId,
+    ) -> Option<Result<(), StatusCode>> {
+        let res = match id {
+            MethodId::ServerConfiguration_UpdateCertificate => {
+                self.update_certificate(call, context)
+            }
+            MethodId::ServerConfiguration_CreateSigningRequest => {
+                self.create_signing_request(call, context)
+            }
+            MethodId::ServerConfiguration_GetRejectedList
+            | MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_GetRejectedList => {
+                self.get_rejected_list(call, context)
+            }
+            MethodId::ServerConfiguration_ApplyChanges => {
+                Self::check_access(context).map(|_| call.set_status(StatusCode::Good))
+            }
+            MethodId::ServerConfiguration_CancelChanges => {
+                Self::check_access(context).map(|_| {
+                    trace_lock!(self.state).pending_pkey = None;
+                    call.set_status(StatusCode::Good);
+                })
+            }
+            MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_Open => {
+                self.open(call, context)
+            }
+            MethodId::TrustListType_OpenWithMasks => self.open_with_masks(call, context),
+            MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_Read => {
+                self.read(call, context)
+            }
+            MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_Write => {
+                self.write(call, context)
+            }
+            MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_GetPosition => {
+                self.get_position(call, context)
+            }
+            MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_SetPosition => {
+                self.set_position(call, context)
+            }
+            MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_Close => {
+                self.close(call, context)
+            }
+            MethodId::TrustListType_CloseAndUpdate => self.close_and_update(call, context),
+            MethodId::TrustListType_AddCertificate => self.add_certificate(call, context),
+            MethodId::TrustListType_RemoveCertificate => self.remove_certificate(call, context),
+            _ => return None,
+        };
+        Some(res)
+    }
+
+    /// Certificate management requires the `manage_certificates` permission and
+    /// an encrypted channel.
+    fn check_access(context: &RequestContext) -> Result<(), StatusCode> {
+        if !context
+            .info
+            .authenticator
+            .core_permissions(&context.token)
+            .manage_certificates
+        {
+            return Err(StatusCode::BadUserAccessDenied);
+        }
+        let security_mode = trace_read_lock!(context.session).message_security_mode();
+        if security_mode != MessageSecurityMode::SignAndEncrypt {
+            return Err(StatusCode::BadSecurityModeInsufficient);
+        }
+        Ok(())
+    }
+
+    /// Only the default application group is supported.
+    fn check_certificate_group(group_id: &NodeId) -> Result<(), StatusCode> {
+        if group_id.is_null()
+            || group_id == &ObjectId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup
+        {
+            Ok(())
+        } else {
+            Err(StatusCode::BadInvalidArgument)
+        }
+    }
+
+    /// Only the RSA application certificate can be managed. The certificates for the ECC
+    /// security policies are not part of the certificate group, so the ECC certificate
+    /// types are rejected.
+    fn check_certificate_type(type_id: &NodeId) -> Result<(), StatusCode> {
+        if type_id.is_null()
+            || type_id == &ObjectTypeId::ApplicationCertificateType
+            || type_id == &ObjectTypeId::RsaMinApplicationCertificateType
+            || type_id == &ObjectTypeId::RsaSha256ApplicationCertificateType
+        {
+            Ok(())
+        } else if ECC_CERTIFICATE_TYPES.iter().any(|t| type_id == t) {
+            error!(
+                "ECC application certificates cannot be managed through the server configuration"
+            );
+            Err(StatusCode::BadNotSupported)
+        } else {
+            Err(StatusCode::BadNotSupported)
+        }
+    }
+
+    fn argument<T: TryFromVariant>(call: &MethodCall, index: usize) -> Result<T, StatusCode> {
+        call.arguments()
+            .get(index)
+            .cloned()
+            .unwrap_or_default()
+            .try_cast_to()
+            .map_err(|_| StatusCode::BadInvalidArgument)
+    }
+
+    fn update_certificate(
+        &self,
+        call: &mut MethodCall,
+        context: &RequestContext,
+    ) -> Result<(), StatusCode> {
+        Self::check_access(context)?;
+        let group_id: NodeId = Self::argument(call, 0)?;
+        let type_id: NodeId = Self::argument(call, 1)?;
+        let certificate: ByteString = Self::argument(call, 2)?;
+        let issuer_certificates: Option<Vec<ByteString>> = Self::argument(call, 3)?;
+        let private_key_format: Option<UAString> = Self::argument(call, 4)?;
+        let private_key: Option<ByteString> = Self::argument(call, 5)?;
+        Self::check_certificate_group(&group_id)?;
+        Self::check_certificate_type(&type_id)?;
+
+        let certificate =
+            X509::from_byte_string(&certificate).map_err(|_| StatusCode::BadCertificateInvalid)?;
+        let issuer_certificates = issuer_certificates
+            .iter()
+            .flatten()
+            .map(|c| X509::from_byte_string(c).map_err(|_| StatusCode::BadCertificateInvalid))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut state = trace_lock!(self.state);
+        // The new certificate must match either the key supplied with it, the key
+        // generated by CreateSigningRequest, or the current key.
+        let (pkey, new_pkey) = match private_key.filter(|k| !k.is_null()) {
+            Some(key) => {
+                let format = private_key_format.unwrap_or_default();
+                if format.as_ref() != "PEM" {
+                    return Err(StatusCode::BadNotSupported);
+                }
+                let pkey = PrivateKey::from_pem(key.as_ref())
+                    .map_err(|_| StatusCode::BadSecurityChecksFailed)?;
+                (Arc::new(pkey), true)
+            }
+            None => match state.pending_pkey.as_ref() {
+                Some(pkey) if certificate.is_key_pair(pkey) => (Arc::new(pkey.clone()), true),
+                _ => (
+                    context
+                        .info
+                        .server_keypair
+                        .load()
+                        .pkey
+                        .clone()
+                        .ok_or(StatusCode::BadSecurityChecksFailed)?,
+                    false,
+                ),
+            },
+        };
+        // Check the new certificate fully before anything is stored or replaced.
+        if !certificate.is_key_pair(&pkey) {
+            error!("New server certificate does not match the private key");
+            return Err(StatusCode::BadSecurityChecksFailed);
+        }
+        if let Err(e) = certificate.is_application_uri_valid(context.info.application_uri.as_ref())
+        {
+            error!("New server certificate does not match the application URI of the server");
+            return Err(e);
+        }
+
+        {
+            let store = trace_write_lock!(self.certificate_store);
+            if let Err(e) = store.validate_certificate_chain(&certificate, &issuer_certificates) {
+                error!("New server certificate chain is invalid: {e}");
+                return Err(e);
+            }
+            for issuer in &issuer_certificates {
+                store.add_certificate(issuer, false).map_err(|e| {
+                    error!("Failed to store issuer certificate: {e}");
+                    StatusCode::BadInternalError
+                })?;
+            }
+            store
+                .store_own_cert_and_pkey(&certificate, new_pkey.then_some(&*pkey))
+                .map_err(|e| {
+                    error!("Failed to store server certificate: {e}");
+                    StatusCode::BadInternalError
+                })?;
+        }
+        info!(
+            "Server certificate updated to {}",
+            certificate.thumbprint().as_hex_string()
+        );
+        // Replace the certificate and key together, so that no secure channel is created
+        // with a certificate that does not match the key.
+        context.info.server_keypair.store(Arc::new(ServerKeyPair {
+            certificate: Some(Arc::new(certificate)),
+            pkey: Some(pkey),
+        }));
+        state.pending_pkey = None;
+
+        // The new certificate is applied immediately.
+        call.set_outputs(vec![false.into()]);
+        call.set_status(StatusCode::Good);
+        Ok(())
+    }
+
+    fn create_signing_request(
+        &self,
+        call: &mut MethodCall,
+        context: &RequestContext,
+    ) -> Result<(), StatusCode> {
+        Self::check_access(context)?;
+        let group_id: NodeId = Self::argument(call, 0)?;
+        let type_id: NodeId = Self::argument(call, 1)?;
+        let subject_name: Option<UAString> = Self::argument(call, 2)?;
+        let regenerate_private_key: bool = Self::argument(call, 3)?;
+        Self::check_certificate_group(&group_id)?;
+        Self::check_certificate_type(&type_id)?;
+
+        // The current certificate provides the subject and alternative names.
+        let keypair = context.info.server_keypair.load_full();
+        let certificate = keypair
+            .certificate
+            .clone()
+            .ok_or(StatusCode::BadInvalidState)?;
+        let current_pkey = keypair.pkey.clone();
+        let pkey = if regenerate_private_key {
+            let key_size = current_pkey
+                .as_ref()
+                .map(|k| k.bit_length() as u32)
+                .unwrap_or(DEFAULT_KEY_SIZE);
+            PrivateKey::new(key_size).map_err(|e| {
+                error!("Failed to generate private key: {e}");
+                StatusCode::BadInternalError
+            })?
+        } else {
+            current_pkey
+                .as_deref()
+                .cloned()
+                .ok_or(StatusCode::BadInvalidState)?
+        };
+
+        let subject_name = subject_name.filter(|s| !s.is_empty());
+        let request = certificate
+            .create_signing_request(&pkey, subject_name.as_ref().map(|s| s.as_ref()))
+            .map_err(|_| StatusCode::BadInvalidArgument)?;
+        if regenerate_private_key {
+            trace_lock!(self.state).pending_pkey = Some(pkey);
+        }
+
+        call.set_outputs(vec![ByteString::from(request).into()]);
+        call.set_status(StatusCode::Good);
+        Ok(())
+    }
+
+    fn get_rejected_list(
+        &self,
+        call: &mut MethodCall,
+        context: &RequestContext,
+    ) -> Result<(), StatusCode> {
+        Self::check_access(context)?;
+        let certificates: Vec<_> = trace_read_lock!(self.certificate_store)
+            .read_rejected_certs()
+            .iter()
+            .map(|c| c.as_byte_string())
+            .collect();
+        call.set_outputs(vec![certificates.into()]);
+        call.set_status(StatusCode::Good);
+        Ok(())
+    }
+
+    fn encode_trust_list(&self, context: &RequestContext, masks: u32) -> Vec<u8> {
+        let trust_list = trace_read_lock!(self.certificate_store).read_trust_list(masks);
+        let ctx = context.info.initial_encoding_context();
+        trust_list.encode_to_vec(&ctx.context())
+    }
+
+    fn open_file(
+        &self,
+        context: &RequestContext,
+        mode: u8,
+        data: Vec<u8>,
+    ) -> Result<u32, StatusCode> {
+        let mut state = trace_lock!(self.state);
+        state.remove_expired_files();
+        // The trust list can be read by several clients at once,
+        // but only written by one client at a time.
+        let writing = state.files.values().any(|f| f.mode & open_mode::WRITE != 0);
+        if writing || mode & open_mode::WRITE != 0 && !state.files.is_empty() {
+            return Err(StatusCode::BadInvalidState);
+        }
+        state.next_file_handle = state.next_file_handle.wrapping_add(1).max(1);
+        let handle = state.next_file_handle;
+        state.files.insert(
+            handle,
+            TrustListFile {
+                session_id: context.session_id,
+                mode,
+                data,
+                position: 0,
+                last_used: Instant::now(),
+            },
+        );
+        Ok(handle)
+    }
+
+    fn open(&self, call: &mut MethodCall, context: &RequestContext) -> Result<(), StatusCode> {
+        Self::check_access(context)?;
+        let mode = load_method_args!(call, Byte)?;
+        let data = match mode {
+            open_mode::READ => self.encode_trust_list(context, TrustListMasks::All as u32),
+            // The trust list is always replaced as a whole.
+            m if m == open_mode::WRITE | open_mode::ERASE_EXISTING => Vec::new(),
+            m if m & open_mode::APPEND != 0 => return Err(StatusCode::BadNotSupported),
+            _ => return Err(StatusCode::BadInvalidArgument),
+        };
+        let handle = self.open_file(context, mode, data)?;
+        call.set_outputs(vec![handle.into()]);
+        call.set_status(StatusCode::Good);
+        Ok(())
+    }
+
+    fn open_with_masks(
+        &self,
+        call: &mut MethodCall,
+        context: &RequestContext,
+    ) -> Result<(), StatusCode> {
+        Self::check_access(context)?;
+        let masks = load_method_args!(call, UInt32)?;
+        let data = self.encode_trust_list(context, masks);
+        let handle = self.open_file(context, open_mode::READ, data)?;
+        call.set_outputs(vec![handle.into()]);
+        call.set_status(StatusCode::Good);
+        Ok(())
+    }
+
+    /// Run `f` on the open file with the given handle. Files can only be used
+    /// by the session that opened them.
+    fn with_file<T>(
+        &self,
+        context: &RequestContext,
+        handle: u32,
+        f: impl FnOnce(&mut TrustListFile) -> Result<T, StatusCode>,
+    ) -> Result<T, StatusCode> {
+        let mut state = trace_lock!(self.state);
+        let file = state
+            .files
+            .get_mut(&handle)
+            .filter(|f| f.session_id == context.session_id)
+            .ok_or(StatusCode::BadInvalidArgument)?;
+        file.last_used = Instant::now();
+        f(file)
+    }
+
+    fn read(&self, call: &mut MethodCall, context: &RequestContext) -> Result<(), StatusCode> {
+        Self::check_access(context)?;
+        let (handle, length) = load_method_args!(call, UInt32, Int32)?;
+        let length = usize::try_from(length).map_err(|_| StatusCode::BadInvalidArgument)?;
+        let data = self.with_file(context, handle, |file| {
+            if file.mode & open_mode::READ == 0 {
+                return Err(StatusCode::BadInvalidState);
+            }
+            let end = file.data.len().min(file.position.saturating_add(length));
+            let data = file.data[file.position.min(end)..end].to_vec();
+            file.position = end;
+            Ok(data)
+        })?;
+        call.set_outputs(vec![ByteString::from(data).into()]);
+        call.set_status(StatusCode::Good);
+        Ok(())
+    }
+
+    fn write(&self, call: &mut MethodCall, context: &RequestContext) -> Result<(), StatusCode> {
+        Self::check_access(context)?;
+        let (handle, data) = load_method_args!(call, UInt32, ByteString)?;
+        let max_size = context.info.config.limits.max_trust_list_size;
+        self.with_file(context, handle, |file| {
+            if file.mode & open_mode::WRITE == 0 {
+                return Err(StatusCode::BadInvalidState);
+            }
+            let data = data.as_ref();
+            let end = file.position.saturating_add(data.len());
+            if end > max_size {
+                return Err(StatusCode::BadEncodingLimitsExceeded);
+            }
+            if file.data.len() < end {
+                file.data.resize(end, 0);
+            }
+            file.data[file.position..end].copy_from_slice(data);
+            file.position = end;
+            Ok(())
+        })?;
+        call.set_status(StatusCode::Good);
+        Ok(())
+    }
+
+    fn get_position(
+        &self,
+        call: &mut MethodCall,
+        context: &RequestContext,
+    ) -> Result<(), StatusCode> {
+        Self::check_access(context)?;
+        let handle = load_method_args!(call, UInt32)?;
+        let position = self.with_file(context, handle, |file| Ok(file.position as u64))?;
+        call.set_outputs(vec![position.into()]);
+        call.set_status(StatusCode::Good);
+        Ok(())
+    }
+
+    fn set_position(
+        &self,
+        call: &mut MethodCall,
+        context: &RequestContext,
+    ) -> Result<(), StatusCode> {
+        Self::check_access(context)?;
+        let (handle, position) = load_method_args!(call, UInt32, UInt64)?;
+        self.with_file(context, handle, |file| {
+            // Setting the position past the end of the file moves it to the end.
+            file.position = file.data.len().min(position as usize);
+            Ok(())
+        })?;
+        call.set_status(StatusCode::Good);
+        Ok(())
+    }
+
+    /// Remove the open file with the given handle, discarding any changes.
+    fn take_file(
+        &self,
+        context: &RequestContext,
+        handle: u32,
+    ) -> Result<TrustListFile, StatusCode> {
+        let mut state = trace_lock!(self.state);
+        match state.files.get(&handle) {
+            Some(f) if f.session_id == context.session_id => {}
+            _ => return Err(StatusCode::BadInvalidArgument),
+        }
+        Ok(state.files.remove(&handle).unwrap())
+    }
+
+    fn close(&self, call: &mut MethodCall, context: &RequestContext) -> Result<(), StatusCode> {
+        Self::check_access(context)?;
+        let handle = load_method_args!(call, UInt32)?;
+        self.take_file(context, handle)?;
+        call.set_status(StatusCode::Good);
+        Ok(())
+    }
+
+    fn close_and_update(
+        &self,
+        call: &mut MethodCall,
+        context: &RequestContext,
+    ) -> Result<(), StatusCode> {
+        Self::check_access(context)?;
+        let handle = load_method_args!(call, UInt32)?;
+        let file = self.take_file(context, handle)?;
+        if file.mode & open_mode::WRITE == 0 {
+            return Err(StatusCode::BadInvalidState);
+        }
+        let ctx = context.info.initial_encoding_context();
+        let trust_list = TrustListDataType::decode(&mut Cursor::new(&file.data), &ctx.context())
+            .map_err(|e| {
+                error!("Failed to decode trust list: {e}");
+                StatusCode::BadInvalidArgument
+            })?;
+        trace_write_lock!(self.certificate_store).write_trust_list(&trust_list)?;
+        info!("Trust list updated");
+
+        // The trust list is read from disk whenever a certificate is validated,
+        // so the changes are applied immediately.
+        call.set_outputs(vec![false.into()]);
+        call.set_status(StatusCode::Good);
+        Ok(())
+    }
+
+    /// Adding and removing certificates is not allowed while the trust list is
+    /// open for writing, since the changes would be overwritten.
+    fn check_not_writing(&self) -> Result<(), StatusCode> {
+        let mut state = trace_lock!(self.state);
+        state.remove_expired_files();
+        if state.files.values().any(|f| f.mode & open_mode::WRITE != 0) {
+            Err(StatusCode::BadInvalidState)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn add_certificate(
+        &self,
+        call: &mut MethodCall,
+        context: &RequestContext,
+    ) -> Result<(), StatusCode> {
+        Self::check_access(context)?;
+        let (certificate, is_trusted) = load_method_args!(call, ByteString, Boolean)?;
+        self.check_not_writing()?;
+        let certificate =
+            X509::from_byte_string(&certificate).map_err(|_| StatusCode::BadCertificateInvalid)?;
+        // Issuer certificates must be CA certificates.
+        if !is_trusted && !certificate.is_ca() {
+            return Err(StatusCode::BadCertificateInvalid);
+        }
+        trace_write_lock!(self.certificate_store)
+            .add_certificate(&certificate, is_trusted)
+            .map_err(|e| {
+                error!("Failed to add certificate: {e}");
+                StatusCode::BadInternalError
+            })?;
+        call.set_status(StatusCode::Good);
+        Ok(())
+    }
+
+    fn remove_certificate(
+        &self,
+        call: &mut MethodCall,
+        context: &RequestContext,
+    ) -> Result<(), StatusCode> {
+        Self::check_access(context)?;
+        let (thumbprint, is_trusted) = load_method_args!(call, String, Boolean)?;
+        self.check_not_writing()?;
+        trace_write_lock!(self.certificate_store)
+            .remove_certificate(thumbprint.as_ref(), is_trusted)?;
+        call.set_status(StatusCode::Good);
+        Ok(())
+    }
+}
@@ -1,21 +1,23 @@
 use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
+use futures::{future::BoxFuture, FutureExt};
 use opcua_core::{trace_read_lock, trace_write_lock};
-use opcua_nodes::{HasNodeId, NodeSetImport};
+use opcua_nodes::{HasNodeId, MethodBuilder, NodeSetImport};
 
 use crate::{
     address_space::{read_node_value, write_node_value, AddressSpace},
     node_manager::{
-        DefaultTypeTree, MethodCall, MonitoredItemRef, MonitoredItemUpdateRef, NodeManagerBuilder,
-        NodeManagersRef, ParsedReadValueId, RequestContext, ServerContext, SyncSampler, WriteNode,
+        method_arguments, DefaultTypeTree, MethodCall, MethodHandler, MethodInputs, MethodOutputs,
+        MonitoredItemRef, MonitoredItemUpdateRef, NodeManagerBuilder, NodeManagersRef,
+        ParsedReadValueId, RequestContext, ServerContext, SyncSampler, WriteNode,
     },
     CreateMonitoredItem,
 };
 use opcua_core::sync::RwLock;
 use opcua_types::{
-    AttributeId, DataValue, MonitoringMode, NodeClass, NodeId, NumericRange, StatusCode,
-    TimestampsToReturn, Variant,
+    custom::DynamicTypeLoader, AttributeId, DataValue, Guid, MonitoringMode, NamespaceMap,
+    NodeClass, NodeId, NumericRange, StatusCode, TimestampsToReturn, Variant,
 };
 
 use super::{
@@ -35,6 +37,16 @@ type ReadCB = Arc<
         + 'static,
 >;
 type MethodCB = Arc<dyn Fn(&[Variant]) -> Result<Vec<Variant>, StatusCode> + Send + Sync + 'static>;
+type TypedMethodFuture = BoxFuture<'static, Result<Vec<Variant>, StatusCode>>;
+type TypedMethodCB =
+    Arc<dyn Fn(&mut MethodCall) -> Option<TypedMethodFuture> + Send + Sync + 'static>;
+
+/// A callback for `Call`. Each method has at most one, so a typed handler and a
+/// plain callback never compete for the same method.
+enum MethodCallback {
+    Plain(MethodCB),
+    Typed(TypedMethodCB),
+}
 
 /// Builder for the [SimpleNodeManager].
 pub struct SimpleNodeManagerBuilder {
@@ -71,8 +83,16 @@ impl InMemoryNodeManagerImplBuilder for SimpleNodeManagerBuilder {
     fn build(mut self, context: ServerContext, address_space: &mut AddressSpace) -> Self::Impl {
         {
             let mut type_tree = context.type_tree.write();
-            for import in self.imports {
-                address_space.import_node_set(&*import, type_tree.namespaces_mut());
+            for mut import in self.imports {
+                if let Some(data_types) = address_space.import_node_set_with_data_types(
+                    &mut *import,
+                    &mut type_tree,
+                    &mut context.info.data_types.write(),
+                ) {
+                    context
+                        .info
+                        .add_type_loader(Arc::new(DynamicTypeLoader::new(data_types)));
+                }
                 let nss = import.get_own_namespaces();
                 for ns in nss {
                     if !self.namespaces.iter().any(|n| n.namespace_uri == ns) {
@@ -119,7 +139,7 @@ pub fn simple_node_manager_imports(
 pub struct SimpleNodeManagerImpl {
     write_cbs: RwLock<HashMap<NodeId, WriteCB>>,
     read_cbs: RwLock<HashMap<NodeId, ReadCB>>,
-    method_cbs: RwLock<HashMap<NodeId, MethodCB>>,
+    method_cbs: RwLock<HashMap<NodeId, MethodCallback>>,
     namespaces: Vec<NamespaceMetadata>,
     #[allow(unused)]
     node_managers: NodeManagersRef,
@@ -284,16 +304,38 @@ impl InMemoryNodeManagerImpl for SimpleNodeManagerImpl {
         _address_space: &RwLock<AddressSpace>,
         methods_to_call: &mut [&mut &mut MethodCall],
     ) -> Result<(), StatusCode> {
-        let cbs = trace_read_lock!(self.method_cbs);
-        for method in methods_to_call {
-            if let Some(cb) = cbs.get(method.method_id()) {
-                match cb(method.arguments()) {
-                    Ok(r) => {
-                        method.set_outputs(r);
-                        method.set_status(StatusCode::Good);
+        let mut pending = Vec::new();
+        {
+            let cbs = trace_read_lock!(self.method_cbs);
+            for (idx, method) in methods_to_call.iter_mut().enumerate() {
+                match cbs.get(method.method_id()) {
+                    Some(MethodCallback::Plain(cb)) => match cb(method.arguments()) {
+                        Ok(r) => {
+                            method.set_outputs(r);
+                            method.set_status(StatusCode::Good);
+                        }
+                        Err(e) => method.set_status(e),
+                    },
+                    Some(MethodCallback::Typed(cb)) => {
+                        // Typed methods are async, so collect the futures and await them
+                        // once the lock is released.
+                        if let Some(fut) = cb(method) {
+                            pending.push(fut.map(move |r| (idx, r)));
+                        }
                     }
-                    Err(e) => method.set_status(e),
+                    None => (),
+                }
+            }
+        }
+
+        for (idx, res) in futures::future::join_all(pending).await {
+            let method = &mut methods_to_call[idx];
+            match res {
+                Ok(r) => {
+                    method.set_outputs(r);
+                    method.set_status(StatusCode::Good);
                 }
+                Err(e) => method.set_status(e),
             }
         }
 
@@ -422,12 +464,89 @@ impl SimpleNodeManagerImpl {
     }
 
     /// Add a callback for `Call` on the method given by `id`.
+    ///
+    /// This replaces any callback previously added for the method, including a typed
+    /// handler added with [SimpleNodeManagerImpl::add_typed_method_callback].
     pub fn add_method_callback(
         &self,
         id: NodeId,
         cb: impl Fn(&[Variant]) -> Result<Vec<Variant>, StatusCode> + Send + Sync + 'static,
     ) {
         let mut cbs = trace_write_lock!(self.method_cbs);
-        cbs.insert(id, Arc::new(cb));
+        cbs.insert(id, MethodCallback::Plain(Arc::new(cb)));
+    }
+
+    /// Add a typed handler for `Call` on the method given by `id`.
+    ///
+    /// The handler is an async function whose arguments are decoded from the
+    /// method input arguments, returning a tuple of outputs. If the arguments
+    /// cannot be decoded, the call fails with `BadArgumentsMissing`,
+    /// `BadTooManyArguments`, or `BadInvalidArgument` with `BadTypeMismatch`
+    /// for each invalid argument. See [MethodCall::decode_arguments].
+    ///
+    /// This replaces any callback previously added for the method, including one
+    /// added with [SimpleNodeManagerImpl::add_method_callback].
+    pub fn add_typed_method_callback<Args: MethodInputs, H: MethodHandler<Args>>(
+        &self,
+        id: NodeId,
+        handler: H,
+    ) {
+        let cb = move |call: &mut MethodCall| {
+            let args = call.decode_arguments::<Args>()?;
+            Some(
+                handler
+                    .call(args)
+                    .map(|r| r.map(MethodOutputs::into_variants))
+                    .boxed(),
+            )
+        };
+        let mut cbs = trace_write_lock!(self.method_cbs);
+        cbs.insert(id, MethodCallback::Typed(Arc::new(cb)));
+    }
+
+    /// Insert the method given by `builder` with a typed handler.
+    ///
+    /// The `InputArguments` and `OutputArguments` properties of the method are
+    /// generated from the signature of `handler`, using `input_names` and `output_names`
+    /// as argument names. See [SimpleNodeManagerImpl::add_typed_method_callback].
+    ///
+    /// Returns `false` if the method could not be inserted.
+    pub fn add_typed_method<Args: MethodInputs, H: MethodHandler<Args>>(
+        &self,
+        address_space: &mut AddressSpace,
+        builder: MethodBuilder,
+        input_names: &[&str],
+        output_names: &[&str],
+        handler: H,
+    ) -> bool {
+        let id = builder.get_node_id().clone();
+        let namespaces = NamespaceMap::new_full(
+            address_space
+                .namespaces()
+                .iter()
+                .map(|(k, v)| (v.clone(), *k))
+                .collect(),
+        );
+        let (inputs, outputs) = method_arguments(&handler, input_names, output_names, &namespaces);
+        let mut builder = builder;
+        if !inputs.is_empty() {
+            builder = builder.input_args(
+                address_space,
+                &NodeId::new(id.namespace, Guid::new()),
+                &inputs,
+            );
+        }
+        if !outputs.is_empty() {
+            builder = builder.output_args(
+                address_space,
+                &NodeId::new(id.namespace, Guid::new()),
+                &outputs,
+            );
+        }
+        if !builder.insert(address_space) {
+            return false;
+        }
+        self.add_typed_method_callback(id, handler);
+        true
     }
 }
@@ -3,7 +3,7 @@ use opcua_types::{
     Variant,
 };
 
-use super::IntoResult;
+use super::{IntoResult, MethodInputs};
 
 #[derive(Debug)]
 /// Container for a single method call in a `Call` service call.
@@ -42,6 +42,30 @@ impl MethodCall {
         self.status = StatusCode::BadInvalidArgument;
     }
 
+    /// Decode the arguments of this method call into `T`, typically a tuple of
+    /// argument types. On failure, this sets the status of the method call to
+    /// `BadArgumentsMissing` or `BadTooManyArguments` if the number of arguments
+    /// is wrong, or sets an argument error with `BadTypeMismatch` for each
+    /// argument that could not be converted, then returns `None`.
+    pub fn decode_arguments<T: MethodInputs>(&mut self) -> Option<T> {
+        let count = T::count();
+        if self.arguments.len() < count {
+            self.status = StatusCode::BadArgumentsMissing;
+            return None;
+        }
+        if self.arguments.len() > count {
+            self.status = StatusCode::BadTooManyArguments;
+            return None;
+        }
+        match T::decode(&self.arguments) {
+            Ok(args) => Some(args),
+            Err(results) => {
+                self.set_argument_error(results);
+                None
+            }
+        }
+    }
+
     /// Set the result of this method call.
     pub fn set_status(&mut self, status: StatusCode) {
         self.status = status;
@@ -11,6 +11,7 @@ use std::{
 
 use async_trait::async_trait;
 use opcua_core::sync::RwLock;
+use opcua_crypto::CertificateStore;
 use opcua_nodes::DefaultTypeTree;
 use opcua_types::{
     ExpandedNodeId, MonitoringMode, NodeId, ReadAnnotationDataDetails, ReadAtTimeDetails,
@@ -27,10 +28,11 @@ mod method;
 mod monitored_items;
 mod node_management;
 mod query;
+mod typed_method;
 mod utils;
 mod view;
 
-use crate::{diagnostics::NamespaceMetadata, ServerStatusWrapper};
+use crate::{address_space::AddressSpace, diagnostics::NamespaceMetadata, ServerStatusWrapper};
 
 use super::{
     authenticator::AuthManager, info::ServerInfo, subscriptions::CreateMonitoredItem,
@@ -46,6 +48,7 @@ pub use {
     monitored_items::{MonitoredItemRef, MonitoredItemUpdateRef},
     node_management::{AddNodeItem, AddReferenceItem, DeleteNodeItem, DeleteReferenceItem},
     query::{ParsedNodeTypeDescription, ParsedQueryDataDescription, QueryRequest},
+    typed_method::{method_arguments, MethodHandler, MethodInputs, MethodOutputs},
     utils::*,
     view::{
         impl_translate_browse_paths_using_browse, AddReferenceResult, BrowseNode, BrowsePathItem,
@@ -248,6 +251,9 @@ pub struct ServerContext {
     pub type_tree_getter: Arc<dyn TypeTreeForUser>,
     /// Wrapper managing the `ServerStatus` server variable.
     pub status: Arc<ServerStatusWrapper>,
+    /// The server certificate store, containing the server's own certificate
+    /// and the trust list.
+    pub certificate_store: Arc<RwLock<CertificateStore>>,
 }
 
 /// This trait is a workaround for the lack of
@@ -297,6 +303,13 @@ pub trait NodeManager: IntoAnyArc + Any {
         false
     }
 
+    /// Return the in-memory address space of this node manager, if it has one.
+    /// Other node managers read type definitions and instance declarations from
+    /// this when instantiating types.
+    fn in_memory_address_space(&self) -> Option<Arc<RwLock<AddressSpace>>> {
+        None
+    }
+
     /// Return whether this node should handle requests to create a node
     /// for the given parent ID. This is only called if no new node ID is
     /// requested, otherwise owns_node is called on the requested node ID.
@@ -524,8 +537,9 @@ pub trait NodeManager: IntoAnyArc + Any {
 
     /// Perform a query on the address space.
     ///
-    /// All node managers must be able to query in order for the
-    /// server to support querying.
+    /// The default implementation returns no results, so node managers that do
+    /// not support querying do not prevent other node managers from answering
+    /// queries. An error returned here fails the entire query.
     ///
     /// The node manager should set a continuation point if it reaches
     /// limits, but is responsible for not exceeding max_data_sets_to_return
@@ -535,7 +549,7 @@ pub trait NodeManager: IntoAnyArc + Any {
         context: &RequestContext,
         request: &mut QueryRequest,
     ) -> Result<(), StatusCode> {
-        Err(StatusCode::BadServiceUnsupported)
+        Ok(())
     }
 
     /// Call a list of methods.
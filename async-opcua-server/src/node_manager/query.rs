@@ -18,6 +18,7 @@ pub(crate) struct QueryContinuationPoint {
     filter: ParsedContentFilter,
     max_data_sets_to_return: usize,
     max_references_to_return: usize,
+    max_nodes_visited: usize,
 }
 
 #[derive(Debug)]
@@ -106,6 +107,7 @@ pub struct QueryRequest {
     filter: ParsedContentFilter,
     max_data_sets_to_return: usize,
     max_references_to_return: usize,
+    max_nodes_visited: usize,
     continuation_point: Option<ContinuationPoint>,
     next_continuation_point: Option<ContinuationPoint>,
     status: StatusCode,
@@ -120,12 +122,14 @@ impl QueryRequest {
         filter: ParsedContentFilter,
         max_data_sets_to_return: usize,
         max_references_to_return: usize,
+        max_nodes_visited: usize,
     ) -> Self {
         Self {
             node_types,
             filter,
             max_data_sets_to_return,
             max_references_to_return,
+            max_nodes_visited,
             continuation_point: None,
             next_continuation_point: None,
             data_sets: Vec::new(),
@@ -140,6 +144,7 @@ impl QueryRequest {
             filter: point.filter,
             max_data_sets_to_return: point.max_data_sets_to_return,
             max_references_to_return: point.max_references_to_return,
+            max_nodes_visited: point.max_nodes_visited,
             continuation_point: Some(point.continuation_point),
             next_continuation_point: None,
             status: StatusCode::Good,
@@ -153,11 +158,32 @@ impl QueryRequest {
         &self.data_sets
     }
 
+    /// Add a data set to the query result. The node manager is responsible
+    /// for not exceeding [QueryRequest::remaining_data_sets].
+    pub fn add_data_set(&mut self, data_set: QueryDataSet) {
+        self.data_sets.push(data_set);
+    }
+
     /// Continuation point, if present.
     pub fn continuation_point(&self) -> Option<&ContinuationPoint> {
         self.continuation_point.as_ref()
     }
 
+    /// Take the continuation point, if it is of type `T`.
+    ///
+    /// Continuation points passed on from a different node manager are not of
+    /// the type used by this node manager, so this returns `None`, and the
+    /// node manager should start the query from the beginning.
+    pub fn take_continuation_point<T: Send + Sync + 'static>(&mut self) -> Option<Box<T>> {
+        self.continuation_point.take().and_then(|c| c.take())
+    }
+
+    /// Clear the input continuation point before moving on to the next node manager,
+    /// which should start its query from the beginning.
+    pub(crate) fn clear_continuation_point(&mut self) {
+        self.continuation_point = None;
+    }
+
     /// Maximum number of references to return.
     pub fn max_references_to_return(&self) -> usize {
         self.max_references_to_return
@@ -168,6 +194,13 @@ impl QueryRequest {
         self.max_data_sets_to_return
     }
 
+    /// Maximum number of nodes a node manager should visit while evaluating
+    /// this query, including nodes reached when evaluating the filter.
+    /// Node managers should fail with `BadQueryTooComplex` if this is exceeded.
+    pub fn max_nodes_visited(&self) -> usize {
+        self.max_nodes_visited
+    }
+
     /// Content filter that the results must match.
     pub fn filter(&self) -> &ParsedContentFilter {
         &self.filter
@@ -229,6 +262,7 @@ impl QueryRequest {
             filter: self.filter,
             max_data_sets_to_return: self.max_data_sets_to_return,
             max_references_to_return: self.max_references_to_return,
+            max_nodes_visited: self.max_nodes_visited,
         });
 
         let mut status = self.status;
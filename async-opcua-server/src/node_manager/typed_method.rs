@@ -0,0 +1,217 @@
+//! Typed method handlers, where method arguments are decoded from and
+//! outputs encoded to variants automatically.
+//!
+//! A method handler is an async function taking any number of arguments implementing
+//! [TryFromVariant] and [ArgumentType], returning a `Result` of a tuple of outputs
+//! implementing `Into<Variant>` and [ArgumentType]. The `InputArguments` and
+//! `OutputArguments` properties of the method can be generated from the same signature
+//! with [method_arguments].
+//!
+//! # Example
+//!
+//! ```ignore
+//! manager.inner().add_typed_method_callback(method_id, |a: i32, b: i32| async move {
+//!     Ok((a + b,))
+//! });
+//! ```
+
+use std::future::Future;
+
+use futures::{future::BoxFuture, FutureExt};
+use opcua_types::{
+    Argument, ArgumentType, NamespaceMap, StatusCode, TryFromVariant, UAString, Variant,
+};
+
+/// Trait for lists of method input arguments. This is implemented for tuples of
+/// up to 8 types implementing [TryFromVariant] and [ArgumentType].
+pub trait MethodInputs: Sized + Send + 'static {
+    /// The number of input arguments.
+    fn count() -> usize;
+
+    /// Describe the input arguments, using `names` as argument names.
+    fn arguments(names: &[&str], namespaces: &NamespaceMap) -> Vec<Argument>;
+
+    /// Decode the input arguments. `arguments` must have the length given by
+    /// [MethodInputs::count]. On failure, the result for each argument is returned.
+    fn decode(arguments: &[Variant]) -> Result<Self, Vec<StatusCode>>;
+}
+
+/// Trait for lists of method outputs. This is implemented for tuples of up to
+/// 8 types implementing `Into<Variant>` and [ArgumentType].
+pub trait MethodOutputs: Send + 'static {
+    /// Describe the output arguments, using `names` as argument names.
+    fn arguments(names: &[&str], namespaces: &NamespaceMap) -> Vec<Argument>;
+
+    /// Convert the outputs into a list of variants.
+    fn into_variants(self) -> Vec<Variant>;
+}
+
+/// Trait for typed method handlers, implemented for async functions with
+/// arguments `Args`, returning `Result<O, StatusCode>` where `O` implements [MethodOutputs].
+pub trait MethodHandler<Args>: Send + Sync + 'static {
+    /// The output type of the method.
+    type Outputs: MethodOutputs;
+
+    /// Call the method with decoded arguments.
+    fn call(&self, args: Args) -> BoxFuture<'static, Result<Self::Outputs, StatusCode>>;
+}
+
+fn argument_name(names: &[&str], index: usize, default: &str) -> UAString {
+    match names.get(index) {
+        Some(name) => (*name).into(),
+        None => format!("{default}{index}").into(),
+    }
+}
+
+macro_rules! impl_typed_method {
+    ($($t:ident $v:ident),*) => {
+        impl<$($t),*> MethodInputs for ($($t,)*)
+        where
+            $($t: TryFromVariant + ArgumentType + Send + 'static),*
+        {
+            fn count() -> usize {
+                let names: &[&str] = &[$(stringify!($t)),*];
+                names.len()
+            }
+
+            #[allow(unused)]
+            fn arguments(names: &[&str], namespaces: &NamespaceMap) -> Vec<Argument> {
+                let mut index = 0;
+                vec![$({
+                    let arg = Argument::of_type::<$t>(argument_name(names, index, "Input"), namespaces);
+                    index += 1;
+                    arg
+                }),*]
+            }
+
+            #[allow(unused)]
+            fn decode(arguments: &[Variant]) -> Result<Self, Vec<StatusCode>> {
+                let mut arguments = arguments.iter();
+                let mut results = Vec::new();
+                $(
+                    let $v = $t::try_from_variant(arguments.next().cloned().unwrap_or_default()).ok();
+                    results.push(if $v.is_some() {
+                        StatusCode::Good
+                    } else {
+                        StatusCode::BadTypeMismatch
+                    });
+                )*
+                match ($($v,)*) {
+                    ($(Some($v),)*) => Ok(($($v,)*)),
+                    #[allow(unreachable_patterns)]
+                    _ => Err(results),
+                }
+            }
+        }
+
+        impl<$($t),*> MethodOutputs for ($($t,)*)
+        where
+            $($t: Into<Variant> + ArgumentType + Send + 'static),*
+        {
+            #[allow(unused)]
+            fn arguments(names: &[&str], namespaces: &NamespaceMap) -> Vec<Argument> {
+                let mut index = 0;
+                vec![$({
+                    let arg = Argument::of_type::<$t>(argument_name(names, index, "Output"), namespaces);
+                    index += 1;
+                    arg
+                }),*]
+            }
+
+            fn into_variants(self) -> Vec<Variant> {
+                let ($($v,)*) = self;
+                vec![$($v.into()),*]
+            }
+        }
+
+        impl<F, Fut, O, $($t),*> MethodHandler<($($t,)*)> for F
+        where
+            F: Fn($($t),*) -> Fut + Send + Sync + 'static,
+            Fut: Future<Output = Result<O, StatusCode>> + Send + 'static,
+            O: MethodOutputs,
+        {
+            type Outputs = O;
+
+            fn call(&self, ($($v,)*): ($($t,)*)) -> BoxFuture<'static, Result<O, StatusCode>> {
+                (self)($($v),*).boxed()
+            }
+        }
+    };
+}
+
+impl_typed_method!();
+impl_typed_method!(T1 v1);
+impl_typed_method!(T1 v1, T2 v2);
+impl_typed_method!(T1 v1, T2 v2, T3 v3);
+impl_typed_method!(T1 v1, T2 v2, T3 v3, T4 v4);
+impl_typed_method!(T1 v1, T2 v2, T3 v3, T4 v4, T5 v5);
+impl_typed_method!(T1 v1, T2 v2, T3 v3, T4 v4, T5 v5, T6 v6);
+impl_typed_method!(T1 v1, T2 v2, T3 v3, T4 v4, T5 v5, T6 v6, T7 v7);
+impl_typed_method!(T1 v1, T2 v2, T3 v3, T4 v4, T5 v5, T6 v6, T7 v7, T8 v8);
+
+/// Get the input and output argument descriptions of the typed method `handler`,
+/// for the `InputArguments` and `OutputArguments` properties of the method.
+///
+/// Arguments without a name in `input_names` or `output_names` are given
+/// names like `Input0` and `Output0`. Data types are resolved using `namespaces`.
+pub fn method_arguments<Args: MethodInputs, H: MethodHandler<Args>>(
+    _handler: &H,
+    input_names: &[&str],
+    output_names: &[&str],
+    namespaces: &NamespaceMap,
+) -> (Vec<Argument>, Vec<Argument>) {
+    (
+        Args::arguments(input_names, namespaces),
+        H::Outputs::arguments(output_names, namespaces),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use opcua_types::{DataTypeId, NamespaceMap, StatusCode, UAString, Variant};
+
+    use super::{method_arguments, MethodHandler, MethodInputs, MethodOutputs};
+
+    #[test]
+    fn decode_inputs() {
+        let args = <(i32, UAString)>::decode(&[5.into(), "hello".into()]).unwrap();
+        assert_eq!(args, (5, "hello".into()));
+
+        // Implicit conversions are allowed.
+        let args = <(i64,)>::decode(&[5u8.into()]).unwrap();
+        assert_eq!(args, (5,));
+
+        let err = <(i32, bool)>::decode(&["hello".into(), true.into()]).unwrap_err();
+        assert_eq!(err, vec![StatusCode::BadTypeMismatch, StatusCode::Good]);
+    }
+
+    #[test]
+    fn encode_outputs() {
+        let outputs = (1u32, "test".to_owned(), vec![1.0f64, 2.0]).into_variants();
+        assert_eq!(outputs[0], Variant::UInt32(1));
+        assert_eq!(outputs[1], Variant::from("test"));
+        assert_eq!(outputs[2], Variant::from(vec![1.0f64, 2.0]));
+        assert!(().into_variants().is_empty());
+    }
+
+    #[tokio::test]
+    async fn typed_handler() {
+        let handler =
+            |a: i32, b: Vec<i32>| async move { Ok((a + b.iter().sum::<i32>(), b.len() as u32)) };
+        let (inputs, outputs) =
+            method_arguments(&handler, &["A"], &["Sum", "Count"], &NamespaceMap::new());
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(inputs[0].name.as_ref(), "A");
+        assert_eq!(inputs[0].data_type, DataTypeId::Int32);
+        assert_eq!(inputs[0].value_rank, -1);
+        assert_eq!(inputs[1].name.as_ref(), "Input1");
+        assert_eq!(inputs[1].value_rank, 1);
+        assert_eq!(outputs[1].name.as_ref(), "Count");
+        assert_eq!(outputs[1].data_type, DataTypeId::UInt32);
+
+        let res = MethodHandler::call(&handler, (1, vec![2, 3]))
+            .await
+            .unwrap();
+        assert_eq!(res, (6, 2));
+    }
+}
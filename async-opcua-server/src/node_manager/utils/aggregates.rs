@@ -0,0 +1,959 @@
+use async_trait::async_trait;
+use opcua_types::{
+    AggregateConfiguration, DataValue, DateTime, HistoryData, NodeId, ObjectId,
+    ReadProcessedDetails, StatusCode, StatusCodeValueType, TimestampsToReturn, Variant,
+};
+
+use crate::node_manager::{HistoryNode, RequestContext};
+
+/// Aggregate functions, defined in OPC-UA Part 13, that can be computed
+/// using [calculate_aggregate].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateType {
+    /// Value interpolated at the start of each interval.
+    Interpolative,
+    /// Arithmetic average of the raw values in each interval.
+    Average,
+    /// Time weighted average of each interval, using sloped interpolation.
+    TimeAverage,
+    /// Time integral of each interval, in value-seconds.
+    Total,
+    /// Smallest raw value in each interval, with the timestamp of the interval.
+    Minimum,
+    /// Largest raw value in each interval, with the timestamp of the interval.
+    Maximum,
+    /// Smallest raw value in each interval, with the timestamp of the value.
+    MinimumActualTime,
+    /// Largest raw value in each interval, with the timestamp of the value.
+    MaximumActualTime,
+    /// Difference between the largest and smallest value in each interval.
+    Range,
+    /// Number of raw values in each interval.
+    Count,
+    /// First raw value in each interval.
+    Start,
+    /// Last raw value in each interval.
+    End,
+    /// Difference between the last and first raw value in each interval.
+    Delta,
+}
+
+impl AggregateType {
+    /// Get the aggregate type from the node ID of its AggregateFunction object.
+    pub fn from_node_id(id: &NodeId) -> Option<Self> {
+        Some(match id.as_object_id().ok()? {
+            ObjectId::AggregateFunction_Interpolative => Self::Interpolative,
+            ObjectId::AggregateFunction_Average => Self::Average,
+            ObjectId::AggregateFunction_TimeAverage => Self::TimeAverage,
+            ObjectId::AggregateFunction_Total => Self::Total,
+            ObjectId::AggregateFunction_Minimum => Self::Minimum,
+            ObjectId::AggregateFunction_Maximum => Self::Maximum,
+            ObjectId::AggregateFunction_MinimumActualTime => Self::MinimumActualTime,
+            ObjectId::AggregateFunction_MaximumActualTime => Self::MaximumActualTime,
+            ObjectId::AggregateFunction_Range => Self::Range,
+            ObjectId::AggregateFunction_Count => Self::Count,
+            ObjectId::AggregateFunction_Start => Self::Start,
+            ObjectId::AggregateFunction_End => Self::End,
+            ObjectId::AggregateFunction_Delta => Self::Delta,
+            _ => return None,
+        })
+    }
+
+    /// Get the node ID of the AggregateFunction object for this aggregate.
+    pub fn node_id(&self) -> NodeId {
+        match self {
+            Self::Interpolative => ObjectId::AggregateFunction_Interpolative,
+            Self::Average => ObjectId::AggregateFunction_Average,
+            Self::TimeAverage => ObjectId::AggregateFunction_TimeAverage,
+            Self::Total => ObjectId::AggregateFunction_Total,
+            Self::Minimum => ObjectId::AggregateFunction_Minimum,
+            Self::Maximum => ObjectId::AggregateFunction_Maximum,
+            Self::MinimumActualTime => ObjectId::AggregateFunction_MinimumActualTime,
+            Self::MaximumActualTime => ObjectId::AggregateFunction_MaximumActualTime,
+            Self::Range => ObjectId::AggregateFunction_Range,
+            Self::Count => ObjectId::AggregateFunction_Count,
+            Self::Start => ObjectId::AggregateFunction_Start,
+            Self::End => ObjectId::AggregateFunction_End,
+            Self::Delta => ObjectId::AggregateFunction_Delta,
+        }
+        .into()
+    }
+}
+
+/// Get the effective aggregate configuration. If `use_server_capabilities_defaults`
+/// is set, this returns the defaults from Part 13.
+pub fn effective_aggregate_configuration(
+    config: &AggregateConfiguration,
+) -> AggregateConfiguration {
+    if config.use_server_capabilities_defaults {
+        AggregateConfiguration {
+            use_server_capabilities_defaults: true,
+            treat_uncertain_as_bad: true,
+            percent_data_bad: 100,
+            percent_data_good: 100,
+            use_sloped_extrapolation: false,
+        }
+    } else {
+        config.clone()
+    }
+}
+
+fn value_time(value: &DataValue) -> DateTime {
+    value
+        .source_timestamp
+        .or(value.server_timestamp)
+        .unwrap_or_else(DateTime::null)
+}
+
+/// A raw value with a numeric value, used for calculations.
+#[derive(Clone, Copy)]
+struct Point {
+    ticks: i64,
+    value: f64,
+    good: bool,
+}
+
+struct Interval<'a> {
+    start: i64,
+    end: i64,
+    /// Raw values with `start <= ts < end`.
+    values: &'a [DataValue],
+    /// Last value before the interval, regardless of status.
+    last_before: Option<&'a DataValue>,
+    /// Last usable value before the interval.
+    prior: Option<&'a DataValue>,
+    /// First usable value after the interval.
+    next: Option<&'a DataValue>,
+    partial: bool,
+}
+
+struct Calculator<'a> {
+    config: AggregateConfiguration,
+    raw: &'a [DataValue],
+}
+
+impl Calculator<'_> {
+    fn is_usable(&self, value: &DataValue) -> bool {
+        let status = value.status();
+        status.is_good() || status.is_uncertain() && !self.config.treat_uncertain_as_bad
+    }
+
+    fn point(&self, value: &DataValue) -> Option<Point> {
+        Some(Point {
+            ticks: value_time(value).checked_ticks(),
+            value: value.value.as_ref()?.as_f64()?,
+            good: value.status().is_good(),
+        })
+    }
+
+    fn usable_points(&self, values: &[DataValue]) -> Vec<Point> {
+        values
+            .iter()
+            .filter(|v| self.is_usable(v))
+            .filter_map(|v| self.point(v))
+            .collect()
+    }
+
+    fn interval(&self, start: i64, end: i64, partial: bool) -> Interval<'_> {
+        let start_idx = self
+            .raw
+            .partition_point(|v| value_time(v).checked_ticks() < start);
+        let end_idx = self
+            .raw
+            .partition_point(|v| value_time(v).checked_ticks() < end);
+        Interval {
+            start,
+            end,
+            values: &self.raw[start_idx..end_idx],
+            last_before: start_idx.checked_sub(1).map(|i| &self.raw[i]),
+            prior: self.raw[..start_idx]
+                .iter()
+                .rev()
+                .find(|v| self.is_usable(v)),
+            next: self.raw[end_idx..].iter().find(|v| self.is_usable(v)),
+            partial,
+        }
+    }
+
+    /// Compute the status of an interval based on the duration of good and bad data,
+    /// as described in Part 13, 5.4.3.
+    fn interval_status(&self, interval: &Interval<'_>) -> StatusCode {
+        let total = (interval.end - interval.start).max(1) as f64;
+        let mut good = 0i64;
+        let mut bad = 0i64;
+        // Before the first value in the interval, the state is given by the prior value,
+        // if there is no prior value, the data is missing, which counts as bad.
+        let mut state = interval
+            .last_before
+            .map(|v| self.is_usable(v))
+            .unwrap_or(false);
+        let mut time = interval.start;
+        for value in interval.values {
+            let ticks = value_time(value).checked_ticks();
+            if state {
+                good += ticks - time;
+            } else {
+                bad += ticks - time;
+            }
+            time = ticks;
+            state = self.is_usable(value);
+        }
+        if state {
+            good += interval.end - time;
+        } else {
+            bad += interval.end - time;
+        }
+
+        let percent_good = good as f64 / total * 100.0;
+        let percent_bad = bad as f64 / total * 100.0;
+        if bad > 0 && percent_bad >= self.config.percent_data_bad as f64 {
+            StatusCode::Bad
+        } else if percent_good >= self.config.percent_data_good as f64 {
+            StatusCode::Good
+        } else {
+            StatusCode::UncertainDataSubNormal
+        }
+    }
+
+    /// Interpolate a value at `ticks` between two points, using sloped interpolation.
+    fn interpolate(before: Point, after: Point, ticks: i64) -> f64 {
+        if after.ticks <= before.ticks {
+            return before.value;
+        }
+        let ratio = (ticks - before.ticks) as f64 / (after.ticks - before.ticks) as f64;
+        before.value + (after.value - before.value) * ratio
+    }
+
+    /// Find the value at the given time, either a raw value, or interpolated
+    /// from the surrounding usable values. Returns the value, and whether it
+    /// is calculated from good data.
+    fn value_at(&self, ticks: i64) -> Option<(f64, bool)> {
+        let idx = self
+            .raw
+            .partition_point(|v| value_time(v).checked_ticks() < ticks);
+        if let Some(point) = self
+            .raw
+            .get(idx)
+            .filter(|v| value_time(v).checked_ticks() == ticks && self.is_usable(v))
+            .and_then(|v| self.point(v))
+        {
+            return Some((point.value, point.good));
+        }
+        let before = self.raw[..idx]
+            .iter()
+            .rev()
+            .filter(|v| self.is_usable(v))
+            .find_map(|v| self.point(v))?;
+        let after = self.raw[idx..]
+            .iter()
+            .filter(|v| self.is_usable(v))
+            .find_map(|v| self.point(v));
+        match after {
+            Some(after) => Some((
+                Self::interpolate(before, after, ticks),
+                before.good && after.good,
+            )),
+            // Extrapolation always results in uncertain data.
+            None if self.config.use_sloped_extrapolation => {
+                let prev = self.raw[..idx]
+                    .iter()
+                    .rev()
+                    .filter(|v| self.is_usable(v))
+                    .filter_map(|v| self.point(v))
+                    .nth(1);
+                match prev {
+                    Some(prev) => Some((Self::interpolate(prev, before, ticks), false)),
+                    None => Some((before.value, false)),
+                }
+            }
+            None => Some((before.value, false)),
+        }
+    }
+
+    /// Compute the time integral of the interval, in value-ticks, and the number of ticks
+    /// covered by data.
+    fn integral(&self, interval: &Interval<'_>) -> Option<(f64, i64)> {
+        let mut points = Vec::with_capacity(interval.values.len() + 2);
+        if interval.prior.is_some() {
+            if let Some((value, good)) = self.value_at(interval.start) {
+                points.push(Point {
+                    ticks: interval.start,
+                    value,
+                    good,
+                });
+            }
+        }
+        for point in self.usable_points(interval.values) {
+            if points.last().is_some_and(|p| p.ticks == point.ticks) {
+                continue;
+            }
+            points.push(point);
+        }
+        let last = *points.last()?;
+        if last.ticks < interval.end {
+            let value = if interval.next.is_some() {
+                self.value_at(interval.end).map(|v| v.0)
+            } else {
+                None
+            };
+            points.push(Point {
+                ticks: interval.end,
+                value: value.unwrap_or(last.value),
+                good: last.good,
+            });
+        }
+        let first = points.first()?.ticks;
+        let integral = points
+            .windows(2)
+            .map(|w| (w[0].value + w[1].value) / 2.0 * (w[1].ticks - w[0].ticks) as f64)
+            .sum();
+        Some((integral, interval.end - first))
+    }
+
+    fn compute(&self, aggregate: AggregateType, interval: &Interval<'_>) -> DataValue {
+        let mut timestamp = DateTime::from(interval.start);
+        let mut value_type = StatusCodeValueType::Calculated;
+        let status = self.interval_status(interval);
+
+        let value: Result<Variant, StatusCode> = match aggregate {
+            AggregateType::Interpolative => {
+                value_type = StatusCodeValueType::Interpolated;
+                match self.value_at(interval.start) {
+                    Some((value, true)) => Ok(value.into()),
+                    Some((value, false)) => {
+                        return self.result(
+                            value.into(),
+                            StatusCode::UncertainDataSubNormal,
+                            value_type,
+                            timestamp,
+                            interval.partial,
+                        );
+                    }
+                    None => Err(StatusCode::BadNoData),
+                }
+            }
+            AggregateType::Average => {
+                let points = self.usable_points(interval.values);
+                if points.is_empty() {
+                    Err(StatusCode::BadNoData)
+                } else {
+                    Ok((points.iter().map(|p| p.value).sum::<f64>() / points.len() as f64).into())
+                }
+            }
+            AggregateType::TimeAverage | AggregateType::Total => match self.integral(interval) {
+                Some((integral, duration)) if duration > 0 => {
+                    if aggregate == AggregateType::Total {
+                        // Ticks are 100 nanoseconds.
+                        Ok((integral / 10_000_000.0).into())
+                    } else {
+                        Ok((integral / duration as f64).into())
+                    }
+                }
+                _ => Err(StatusCode::BadNoData),
+            },
+            AggregateType::Minimum
+            | AggregateType::Maximum
+            | AggregateType::MinimumActualTime
+            | AggregateType::MaximumActualTime => {
+                let is_min = matches!(
+                    aggregate,
+                    AggregateType::Minimum | AggregateType::MinimumActualTime
+                );
+                let mut best: Option<(&DataValue, Point)> = None;
+                for value in interval.values.iter().filter(|v| self.is_usable(v)) {
+                    let Some(point) = self.point(value) else {
+                        continue;
+                    };
+                    let replace = match best {
+                        None => true,
+                        Some((_, b)) if is_min => point.value < b.value,
+                        Some((_, b)) => point.value > b.value,
+                    };
+                    if replace {
+                        best = Some((value, point));
+                    }
+                }
+                match best {
+                    Some((value, _)) => {
+                        if matches!(
+                            aggregate,
+                            AggregateType::MinimumActualTime | AggregateType::MaximumActualTime
+                        ) {
+                            timestamp = value_time(value);
+                        }
+                        value_type = StatusCodeValueType::Raw;
+                        Ok(value.value.clone().unwrap_or_default())
+                    }
+                    None => Err(StatusCode::BadNoData),
+                }
+            }
+            AggregateType::Range => {
+                let points = self.usable_points(interval.values);
+                let min = points.iter().map(|p| p.value).reduce(f64::min);
+                let max = points.iter().map(|p| p.value).reduce(f64::max);
+                match (min, max) {
+                    (Some(min), Some(max)) => Ok((max - min).into()),
+                    _ => Err(StatusCode::BadNoData),
+                }
+            }
+            AggregateType::Count => {
+                Ok((interval.values.iter().filter(|v| self.is_usable(v)).count() as i32).into())
+            }
+            AggregateType::Start | AggregateType::End => {
+                let value = if aggregate == AggregateType::Start {
+                    interval.values.first()
+                } else {
+                    interval.values.last()
+                };
+                match value {
+                    Some(value) => {
+                        // Start and End return the raw value, with its own status.
+                        return self.result(
+                            value.value.clone().unwrap_or_default(),
+                            value.status(),
+                            StatusCodeValueType::Raw,
+                            value_time(value),
+                            interval.partial,
+                        );
+                    }
+                    None => Err(StatusCode::BadNoData),
+                }
+            }
+            AggregateType::Delta => {
+                let points = self.usable_points(interval.values);
+                match (points.first(), points.last()) {
+                    (Some(first), Some(last)) if points.len() > 1 => {
+                        Ok((last.value - first.value).into())
+                    }
+                    _ => Err(StatusCode::BadNoData),
+                }
+            }
+        };
+
+        match value {
+            Ok(value) => self.result(value, status, value_type, timestamp, interval.partial),
+            Err(e) => DataValue {
+                value: None,
+                status: Some(e),
+                source_timestamp: Some(timestamp),
+                server_timestamp: Some(timestamp),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn result(
+        &self,
+        value: Variant,
+        status: StatusCode,
+        value_type: StatusCodeValueType,
+        timestamp: DateTime,
+        partial: bool,
+    ) -> DataValue {
+        let status = if status.is_bad() {
+            status
+        } else {
+            status.set_value_type(value_type).set_partial(partial)
+        };
+        DataValue {
+            value: (!status.is_bad()).then_some(value),
+            status: Some(status),
+            source_timestamp: Some(timestamp),
+            server_timestamp: Some(timestamp),
+            ..Default::default()
+        }
+    }
+}
+
+/// Calculate an aggregate over a series of raw values.
+///
+/// `raw` must be sorted by source timestamp, and should contain the values in the
+/// range given by `start_time` and `end_time`, as well as the closest value before and
+/// after this range, if they exist, as these are needed for interpolation.
+///
+/// If `start_time` is after `end_time` the result is returned in reverse order.
+/// A `processing_interval` of zero means that a single interval covering the
+/// entire range is used.
+///
+/// Returns `BadInvalidArgument` if `processing_interval` is negative or not finite, and
+/// `BadTooManyOperations` if the range would be split into more than `max_intervals`
+/// intervals. A `max_intervals` of zero means no limit.
+pub fn calculate_aggregate(
+    raw: &[DataValue],
+    aggregate: AggregateType,
+    start_time: DateTime,
+    end_time: DateTime,
+    processing_interval: f64,
+    config: &AggregateConfiguration,
+    max_intervals: usize,
+) -> Result<Vec<DataValue>, StatusCode> {
+    let count = processed_interval_count(start_time, end_time, processing_interval)?;
+    if max_intervals > 0 && count > max_intervals as u64 {
+        return Err(StatusCode::BadTooManyOperations);
+    }
+
+    let calc = Calculator {
+        config: effective_aggregate_configuration(config),
+        raw,
+    };
+
+    let start = start_time.checked_ticks().min(end_time.checked_ticks());
+    let end = start_time.checked_ticks().max(end_time.checked_ticks());
+    let step = processing_interval_ticks(processing_interval);
+
+    let mut intervals = Vec::with_capacity(count as usize);
+    if step <= 0 {
+        intervals.push(calc.interval(start, end, false));
+    } else if start_time > end_time {
+        // Intervals of a reverse read are aligned to the start time, which is the
+        // end of the range, so the partial interval is the oldest one.
+        let mut time = end;
+        while time > start {
+            let interval_start = time.saturating_sub(step).max(start);
+            intervals.push(calc.interval(interval_start, time, time - interval_start < step));
+            time = interval_start;
+        }
+    } else {
+        let mut time = start;
+        while time < end {
+            let interval_end = time.saturating_add(step).min(end);
+            intervals.push(calc.interval(time, interval_end, interval_end - time < step));
+            time = interval_end;
+        }
+    }
+
+    Ok(intervals
+        .iter()
+        .map(|i| calc.compute(aggregate, i))
+        .collect())
+}
+
+/// Convert a processing interval in milliseconds to ticks of 100 nanoseconds.
+fn processing_interval_ticks(processing_interval: f64) -> i64 {
+    (processing_interval * 10_000.0) as i64
+}
+
+/// Get the number of intervals the range between `start_time` and `end_time` is split
+/// into for the given `processing_interval`, without allocating them.
+///
+/// Returns `BadInvalidArgument` if `processing_interval` is negative or not finite.
+pub fn processed_interval_count(
+    start_time: DateTime,
+    end_time: DateTime,
+    processing_interval: f64,
+) -> Result<u64, StatusCode> {
+    if !processing_interval.is_finite() || processing_interval < 0.0 {
+        return Err(StatusCode::BadInvalidArgument);
+    }
+    let step = processing_interval_ticks(processing_interval);
+    if step <= 0 {
+        return Ok(1);
+    }
+    let span = start_time
+        .checked_ticks()
+        .abs_diff(end_time.checked_ticks());
+    Ok(span.div_ceil(step as u64))
+}
+
+/// Trait for types that can supply raw history, used to compute aggregates
+/// with [history_read_processed_from_raw].
+#[async_trait]
+pub trait RawHistoryProvider: Send + Sync {
+    /// Read raw values for the node given by `node_id` with source timestamp `start <= ts < end`,
+    /// as well as the closest value before `start`, and the closest value at or after `end`,
+    /// if they exist. The values must be sorted by source timestamp.
+    async fn read_raw_with_bounds(
+        &self,
+        context: &RequestContext,
+        node_id: &NodeId,
+        start: DateTime,
+        end: DateTime,
+    ) -> Result<Vec<DataValue>, StatusCode>;
+}
+
+/// Default implementation of `history_read_processed` for node managers that are able to
+/// provide raw history. This reads raw history from `provider`, and computes the
+/// aggregate requested for each node.
+pub async fn history_read_processed_from_raw(
+    provider: &(impl RawHistoryProvider + ?Sized),
+    context: &RequestContext,
+    details: &ReadProcessedDetails,
+    nodes: &mut [&mut &mut HistoryNode],
+    timestamps_to_return: TimestampsToReturn,
+) -> Result<(), StatusCode> {
+    if details.start_time.is_null() || details.end_time.is_null() {
+        return Err(StatusCode::BadInvalidTimestampArgument);
+    }
+    let max_intervals = context
+        .info
+        .config
+        .limits
+        .operational
+        .max_processed_intervals_per_history_read;
+    let count = processed_interval_count(
+        details.start_time,
+        details.end_time,
+        details.processing_interval,
+    )?;
+    if max_intervals > 0 && count > max_intervals as u64 {
+        return Err(StatusCode::BadTooManyOperations);
+    }
+    let config = &details.aggregate_configuration;
+    if !config.use_server_capabilities_defaults
+        && (config.percent_data_bad > 100 || config.percent_data_good > 100)
+    {
+        return Err(StatusCode::BadAggregateConfigurationRejected);
+    }
+
+    let start = details.start_time.min(details.end_time);
+    let end = details.start_time.max(details.end_time);
+
+    for node in nodes {
+        let Some(aggregate) = node.aggregate_type().and_then(AggregateType::from_node_id) else {
+            node.set_status(StatusCode::BadAggregateNotSupported);
+            continue;
+        };
+        let raw = match provider
+            .read_raw_with_bounds(context, node.node_id(), start, end)
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                node.set_status(e);
+                continue;
+            }
+        };
+
+        let data_values = match calculate_aggregate(
+            &raw,
+            aggregate,
+            details.start_time,
+            details.end_time,
+            details.processing_interval,
+            config,
+            max_intervals,
+        ) {
+            Ok(r) => r,
+            Err(e) => {
+                node.set_status(e);
+                continue;
+            }
+        };
+        let data_values = data_values
+            .into_iter()
+            .map(|mut v| {
+                match timestamps_to_return {
+                    TimestampsToReturn::Source => v.server_timestamp = None,
+                    TimestampsToReturn::Server => v.source_timestamp = None,
+                    TimestampsToReturn::Neither => {
+                        v.source_timestamp = None;
+                        v.server_timestamp = None;
+                    }
+                    _ => (),
+                }
+                v
+            })
+            .collect();
+
+        node.set_result(HistoryData {
+            data_values: Some(data_values),
+        });
+        node.set_status(StatusCode::Good);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+    use opcua_types::{
+        AggregateConfiguration, DataValue, DateTime, StatusCode, StatusCodeValueType, Variant,
+    };
+
+    use super::{calculate_aggregate, AggregateType};
+
+    fn series(start: DateTime, values: &[(i64, f64, StatusCode)]) -> Vec<DataValue> {
+        values
+            .iter()
+            .map(|(offset, value, status)| DataValue {
+                value: Some((*value).into()),
+                status: Some(*status),
+                source_timestamp: Some(start + TimeDelta::seconds(*offset)),
+                server_timestamp: Some(start + TimeDelta::seconds(*offset)),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn aggregate(
+        raw: &[DataValue],
+        aggregate: AggregateType,
+        start: DateTime,
+        end: DateTime,
+        processing_interval: f64,
+        config: &AggregateConfiguration,
+    ) -> Vec<DataValue> {
+        calculate_aggregate(raw, aggregate, start, end, processing_interval, config, 0).unwrap()
+    }
+
+    fn config() -> AggregateConfiguration {
+        AggregateConfiguration {
+            use_server_capabilities_defaults: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn average_min_max_count() {
+        let start = DateTime::ymd_hms(2024, 1, 1, 0, 0, 0);
+        let raw = series(
+            start,
+            &[
+                (0, 1.0, StatusCode::Good),
+                (3, 3.0, StatusCode::Good),
+                (6, 5.0, StatusCode::Good),
+                (10, 7.0, StatusCode::Good),
+                (15, 9.0, StatusCode::Good),
+            ],
+        );
+        let end = start + TimeDelta::seconds(20);
+
+        let avg = aggregate(
+            &raw,
+            AggregateType::Average,
+            start,
+            end,
+            10_000.0,
+            &config(),
+        );
+        assert_eq!(avg.len(), 2);
+        assert_eq!(avg[0].value, Some(Variant::Double(3.0)));
+        assert_eq!(
+            avg[0].status().value_type(),
+            StatusCodeValueType::Calculated
+        );
+        assert!(avg[0].status().is_good());
+        assert_eq!(avg[1].value, Some(Variant::Double(8.0)));
+        assert_eq!(
+            avg[1].source_timestamp,
+            Some(start + TimeDelta::seconds(10))
+        );
+
+        let min = aggregate(
+            &raw,
+            AggregateType::Minimum,
+            start,
+            end,
+            10_000.0,
+            &config(),
+        );
+        assert_eq!(min[0].value, Some(Variant::Double(1.0)));
+        assert_eq!(min[1].value, Some(Variant::Double(7.0)));
+
+        let max = aggregate(
+            &raw,
+            AggregateType::MaximumActualTime,
+            start,
+            end,
+            10_000.0,
+            &config(),
+        );
+        assert_eq!(max[0].value, Some(Variant::Double(5.0)));
+        assert_eq!(max[0].source_timestamp, Some(start + TimeDelta::seconds(6)));
+
+        let count = aggregate(&raw, AggregateType::Count, start, end, 10_000.0, &config());
+        assert_eq!(count[0].value, Some(Variant::Int32(3)));
+        assert_eq!(count[1].value, Some(Variant::Int32(2)));
+
+        // Reversed time gives intervals in reverse order.
+        let count = aggregate(&raw, AggregateType::Count, end, start, 10_000.0, &config());
+        assert_eq!(count[0].value, Some(Variant::Int32(2)));
+        assert_eq!(count[1].value, Some(Variant::Int32(3)));
+    }
+
+    #[test]
+    fn reverse_uneven_intervals() {
+        let start = DateTime::ymd_hms(2024, 1, 1, 0, 0, 0);
+        let raw = series(
+            start,
+            &[
+                (0, 1.0, StatusCode::Good),
+                (3, 3.0, StatusCode::Good),
+                (6, 5.0, StatusCode::Good),
+                (10, 7.0, StatusCode::Good),
+                (15, 9.0, StatusCode::Good),
+                (20, 11.0, StatusCode::Good),
+            ],
+        );
+        let end = start + TimeDelta::seconds(25);
+
+        // Forward reads are aligned to the start time, so the last interval is partial.
+        let count = aggregate(&raw, AggregateType::Count, start, end, 10_000.0, &config());
+        assert_eq!(count.len(), 3);
+        assert_eq!(count[0].value, Some(Variant::Int32(3)));
+        assert_eq!(count[1].value, Some(Variant::Int32(2)));
+        assert_eq!(count[2].value, Some(Variant::Int32(1)));
+        assert!(!count[0].status().partial());
+        assert!(count[2].status().partial());
+
+        // Reverse reads step backwards from the start time, so the oldest interval is partial.
+        let count = aggregate(&raw, AggregateType::Count, end, start, 10_000.0, &config());
+        assert_eq!(count.len(), 3);
+        assert_eq!(count[0].value, Some(Variant::Int32(2)));
+        assert_eq!(
+            count[0].source_timestamp,
+            Some(start + TimeDelta::seconds(15))
+        );
+        assert_eq!(count[1].value, Some(Variant::Int32(2)));
+        assert_eq!(
+            count[1].source_timestamp,
+            Some(start + TimeDelta::seconds(5))
+        );
+        assert_eq!(count[2].value, Some(Variant::Int32(2)));
+        assert_eq!(count[2].source_timestamp, Some(start));
+        assert!(!count[0].status().partial());
+        assert!(!count[1].status().partial());
+        assert!(count[2].status().partial());
+    }
+
+    #[test]
+    fn time_average_and_interpolative() {
+        let start = DateTime::ymd_hms(2024, 1, 1, 0, 0, 0);
+        let raw = series(
+            start,
+            &[
+                (0, 0.0, StatusCode::Good),
+                (10, 10.0, StatusCode::Good),
+                (20, 10.0, StatusCode::Good),
+            ],
+        );
+        let end = start + TimeDelta::seconds(20);
+
+        let avg = aggregate(
+            &raw,
+            AggregateType::TimeAverage,
+            start,
+            end,
+            10_000.0,
+            &config(),
+        );
+        assert_eq!(avg.len(), 2);
+        assert_eq!(avg[0].value, Some(Variant::Double(5.0)));
+        assert_eq!(avg[1].value, Some(Variant::Double(10.0)));
+
+        let total = aggregate(&raw, AggregateType::Total, start, end, 0.0, &config());
+        assert_eq!(total.len(), 1);
+        assert_eq!(total[0].value, Some(Variant::Double(150.0)));
+
+        let interp = aggregate(
+            &raw,
+            AggregateType::Interpolative,
+            start + TimeDelta::seconds(5),
+            end,
+            5_000.0,
+            &config(),
+        );
+        assert_eq!(interp.len(), 3);
+        assert_eq!(interp[0].value, Some(Variant::Double(5.0)));
+        assert_eq!(
+            interp[0].status().value_type(),
+            StatusCodeValueType::Interpolated
+        );
+        assert_eq!(interp[1].value, Some(Variant::Double(10.0)));
+    }
+
+    #[test]
+    fn quality_from_bad_data() {
+        let start = DateTime::ymd_hms(2024, 1, 1, 0, 0, 0);
+        let raw = series(
+            start,
+            &[
+                (0, 1.0, StatusCode::Good),
+                (5, 100.0, StatusCode::Bad),
+                (10, 3.0, StatusCode::Good),
+            ],
+        );
+        let end = start + TimeDelta::seconds(20);
+
+        // Half of the first interval is bad, which is less than the default 100% bad,
+        // but not enough for the default 100% good.
+        let avg = aggregate(
+            &raw,
+            AggregateType::Average,
+            start,
+            end,
+            10_000.0,
+            &config(),
+        );
+        assert_eq!(avg[0].value, Some(Variant::Double(1.0)));
+        assert_eq!(
+            avg[0].status().sub_code(),
+            StatusCode::UncertainDataSubNormal.sub_code()
+        );
+        assert!(avg[1].status().is_good());
+
+        // With a lower threshold, the interval is bad.
+        let avg = aggregate(
+            &raw,
+            AggregateType::Average,
+            start,
+            end,
+            10_000.0,
+            &AggregateConfiguration {
+                use_server_capabilities_defaults: false,
+                treat_uncertain_as_bad: true,
+                percent_data_bad: 50,
+                percent_data_good: 50,
+                use_sloped_extrapolation: false,
+            },
+        );
+        assert!(avg[0].status().is_bad());
+        assert!(avg[0].value.is_none());
+
+        // An interval with no data at all is bad.
+        let avg = aggregate(
+            &raw,
+            AggregateType::Average,
+            start - TimeDelta::seconds(10),
+            start,
+            10_000.0,
+            &config(),
+        );
+        assert_eq!(avg[0].status(), StatusCode::BadNoData);
+    }
+
+    #[test]
+    fn interval_limits() {
+        let start = DateTime::ymd_hms(2020, 1, 1, 0, 0, 0);
+        let end = DateTime::ymd_hms(2024, 1, 1, 0, 0, 0);
+
+        let res = calculate_aggregate(&[], AggregateType::Count, start, end, 1.0, &config(), 1000);
+        assert_eq!(res, Err(StatusCode::BadTooManyOperations));
+
+        for interval in [f64::NAN, f64::INFINITY, -1.0] {
+            let res = calculate_aggregate(
+                &[],
+                AggregateType::Count,
+                start,
+                end,
+                interval,
+                &config(),
+                1000,
+            );
+            assert_eq!(res, Err(StatusCode::BadInvalidArgument));
+        }
+
+        let res = calculate_aggregate(
+            &[],
+            AggregateType::Count,
+            start,
+            start + TimeDelta::seconds(25),
+            10_000.0,
+            &config(),
+            3,
+        )
+        .unwrap();
+        assert_eq!(res.len(), 3);
+    }
+}
@@ -1,8 +1,13 @@
+mod aggregates;
 mod opaque_node_id;
 mod operations;
 mod result;
 mod sync_sampler;
 
+pub use aggregates::{
+    calculate_aggregate, effective_aggregate_configuration, history_read_processed_from_raw,
+    processed_interval_count, AggregateType, RawHistoryProvider,
+};
 pub use opaque_node_id::*;
 pub use operations::{get_namespaces_for_user, get_node_metadata};
 pub(crate) use result::{consume_results, IntoResult};
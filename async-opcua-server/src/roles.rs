@@ -0,0 +1,587 @@
+//! Role based access control, as defined in OPC-UA Part 18.
+//!
+//! The [`RoleSet`] contains the roles known by the server, available from
+//! [`ServerHandle::roles`](crate::ServerHandle::roles) or from the
+//! [`ServerInfo`](crate::ServerInfo). Each role has a set of identity mapping rules,
+//! and optional lists of applications and endpoints that restrict which sessions
+//! may be granted the role. Roles are granted to a session when it is activated.
+//!
+//! Nodes with the `RolePermissions` attribute set are only accessible to sessions
+//! that have been granted a role with the required permissions. Nodes without
+//! role permissions are not restricted by roles at all.
+//!
+//! The roles are exposed in the address space under `Server/ServerCapabilities/RoleSet`,
+//! and can be managed by clients with the `SecurityAdmin` role over an encrypted channel.
+
+use hashbrown::HashMap;
+use opcua_core::{sync::RwLock, trace_read_lock, trace_write_lock};
+use opcua_crypto::X509;
+use opcua_types::{
+    EndpointType, Guid, IdentityCriteriaType, IdentityMappingRuleType, MessageSecurityMode, NodeId,
+    ObjectId, PermissionType, QualifiedName, RolePermissionType, StatusCode, UAString,
+};
+
+use crate::IdentityToken;
+
+/// A role on the server, see OPC-UA Part 18, 4.4.
+#[derive(Debug, Clone)]
+pub struct Role {
+    /// Node ID of the role object.
+    pub node_id: NodeId,
+    /// Name of the role, this is the browse name of the role object.
+    pub name: QualifiedName,
+    /// Rules used to decide which user identities are granted this role.
+    pub identities: Vec<IdentityMappingRuleType>,
+    /// Application URIs used to restrict which client applications may be granted this role.
+    pub applications: Vec<UAString>,
+    /// If `true`, `applications` lists the applications that may _not_ be granted this role,
+    /// otherwise it lists the only applications that may be granted this role.
+    pub applications_exclude: bool,
+    /// Endpoints used to restrict which sessions may be granted this role.
+    pub endpoints: Vec<EndpointType>,
+    /// If `true`, `endpoints` lists the endpoints that may _not_ be granted this role,
+    /// otherwise it lists the only endpoints that may be granted this role.
+    pub endpoints_exclude: bool,
+}
+
+impl Role {
+    /// Create a new role with no identity mapping rules, and no restrictions on
+    /// applications or endpoints.
+    pub fn new(node_id: NodeId, name: QualifiedName) -> Self {
+        Self {
+            node_id,
+            name,
+            identities: Vec::new(),
+            applications: Vec::new(),
+            applications_exclude: true,
+            endpoints: Vec::new(),
+            endpoints_exclude: true,
+        }
+    }
+
+    fn with_identity(mut self, criteria_type: IdentityCriteriaType) -> Self {
+        self.identities.push(IdentityMappingRuleType {
+            criteria_type,
+            criteria: UAString::null(),
+        });
+        self
+    }
+
+    fn matches(&self, session: &SessionRoleInfo<'_>) -> bool {
+        let application_listed = self
+            .applications
+            .iter()
+            .any(|a| Some(a.as_ref()) == session.application_uri);
+        if application_listed == self.applications_exclude {
+            return false;
+        }
+
+        let endpoint_listed = self.endpoints.iter().any(|e| {
+            (e.endpoint_url.is_null() || e.endpoint_url.as_ref() == session.endpoint_url)
+                && (e.security_mode == MessageSecurityMode::Invalid
+                    || e.security_mode == session.security_mode)
+                && (e.security_policy_uri.is_null()
+                    || e.security_policy_uri.as_ref() == session.security_policy_uri)
+        });
+        if endpoint_listed == self.endpoints_exclude {
+            return false;
+        }
+
+        self.identities
+            .iter()
+            .any(|rule| session.matches_rule(rule))
+    }
+}
+
+/// Information about a session used to decide which roles it is granted.
+pub(crate) struct SessionRoleInfo<'a> {
+    pub identity: &'a IdentityToken,
+    /// The application URI from the client certificate, if the session has one. The
+    /// URI in the client's application description is not used, since the client
+    /// can put anything in there.
+    pub application_uri: Option<&'a str>,
+    pub endpoint_url: &'a str,
+    pub security_mode: MessageSecurityMode,
+    pub security_policy_uri: &'a str,
+}
+
+impl SessionRoleInfo<'_> {
+    fn is_anonymous(&self) -> bool {
+        matches!(
+            self.identity,
+            IdentityToken::None | IdentityToken::Anonymous(_)
+        )
+    }
+
+    /// The application instance certificate is validated when the secure channel
+    /// is opened, so any application on a secure channel is trusted.
+    fn is_trusted_application(&self) -> bool {
+        !matches!(
+            self.security_mode,
+            MessageSecurityMode::None | MessageSecurityMode::Invalid
+        )
+    }
+
+    fn user_certificate(&self) -> Option<X509> {
+        let IdentityToken::X509(token) = self.identity else {
+            return None;
+        };
+        X509::from_byte_string(&token.certificate_data).ok()
+    }
+
+    fn matches_rule(&self, rule: &IdentityMappingRuleType) -> bool {
+        match rule.criteria_type {
+            IdentityCriteriaType::UserName => match self.identity {
+                IdentityToken::UserName(token) => token.user_name == rule.criteria,
+                _ => false,
+            },
+            IdentityCriteriaType::Thumbprint => self.user_certificate().is_some_and(|c| {
+                c.thumbprint()
+                    .as_hex_string()
+                    .eq_ignore_ascii_case(rule.criteria.as_ref())
+            }),
+            IdentityCriteriaType::X509Subject => self
+                .user_certificate()
+                .is_some_and(|c| c.subject_name() == rule.criteria.as_ref()),
+            IdentityCriteriaType::Anonymous => self.is_anonymous(),
+            IdentityCriteriaType::AuthenticatedUser => {
+                !self.is_anonymous() && !matches!(self.identity, IdentityToken::Invalid(_))
+            }
+            IdentityCriteriaType::Application => {
+                self.is_trusted_application()
+                    && Some(rule.criteria.as_ref()) == self.application_uri
+            }
+            IdentityCriteriaType::TrustedApplication => self.is_trusted_application(),
+            // Roles and groups from access tokens must be mapped by the `AuthManager`.
+            IdentityCriteriaType::Role | IdentityCriteriaType::GroupId => false,
+        }
+    }
+}
+
+/// The set of roles known by the server.
+pub struct RoleSet {
+    roles: RwLock<HashMap<NodeId, Role>>,
+}
+
+impl Default for RoleSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RoleSet {
+    /// Create a new role set containing the well known roles defined in OPC-UA Part 3.
+    ///
+    /// The `Anonymous`, `AuthenticatedUser` and `TrustedApplication` roles are granted to
+    /// matching sessions by default, the other roles have no identity mapping rules.
+    pub fn new() -> Self {
+        let roles = [
+            Role::new(ObjectId::WellKnownRole_Anonymous.into(), "Anonymous".into())
+                .with_identity(IdentityCriteriaType::Anonymous),
+            Role::new(
+                ObjectId::WellKnownRole_AuthenticatedUser.into(),
+                "AuthenticatedUser".into(),
+            )
+            .with_identity(IdentityCriteriaType::AuthenticatedUser),
+            Role::new(
+                ObjectId::WellKnownRole_TrustedApplication.into(),
+                "TrustedApplication".into(),
+            )
+            .with_identity(IdentityCriteriaType::TrustedApplication),
+            Role::new(ObjectId::WellKnownRole_Observer.into(), "Observer".into()),
+            Role::new(ObjectId::WellKnownRole_Operator.into(), "Operator".into()),
+            Role::new(ObjectId::WellKnownRole_Engineer.into(), "Engineer".into()),
+            Role::new(
+                ObjectId::WellKnownRole_Supervisor.into(),
+                "Supervisor".into(),
+            ),
+            Role::new(
+                ObjectId::WellKnownRole_ConfigureAdmin.into(),
+                "ConfigureAdmin".into(),
+            ),
+            Role::new(
+                ObjectId::WellKnownRole_SecurityAdmin.into(),
+                "SecurityAdmin".into(),
+            ),
+        ];
+        Self {
+            roles: RwLock::new(roles.into_iter().map(|r| (r.node_id.clone(), r)).collect()),
+        }
+    }
+
+    /// `true` if `role_id` is one of the well known roles, which cannot be removed.
+    pub fn is_well_known(role_id: &NodeId) -> bool {
+        let Ok(id) = role_id.as_object_id() else {
+            return false;
+        };
+        matches!(
+            id,
+            ObjectId::WellKnownRole_Anonymous
+                | ObjectId::WellKnownRole_AuthenticatedUser
+                | ObjectId::WellKnownRole_TrustedApplication
+                | ObjectId::WellKnownRole_Observer
+                | ObjectId::WellKnownRole_Operator
+                | ObjectId::WellKnownRole_Engineer
+                | ObjectId::WellKnownRole_Supervisor
+                | ObjectId::WellKnownRole_ConfigureAdmin
+                | ObjectId::WellKnownRole_SecurityAdmin
+        )
+    }
+
+    /// Get a copy of the role with the given ID.
+    pub fn get(&self, role_id: &NodeId) -> Option<Role> {
+        trace_read_lock!(self.roles).get(role_id).cloned()
+    }
+
+    /// Get a copy of all the roles on the server.
+    pub fn roles(&self) -> Vec<Role> {
+        trace_read_lock!(self.roles).values().cloned().collect()
+    }
+
+    /// Find the role with the given name.
+    pub fn find_by_name(&self, name: &QualifiedName) -> Option<Role> {
+        trace_read_lock!(self.roles)
+            .values()
+            .find(|r| &r.name == name)
+            .cloned()
+    }
+
+    /// Add a new role with the given name, returning the ID of the new role, which is
+    /// allocated in the namespace given by `namespace`.
+    ///
+    /// This only adds the role to the role set, the `AddRole` method on the `RoleSet`
+    /// object also creates the role object in the address space.
+    pub(crate) fn add_role(
+        &self,
+        name: QualifiedName,
+        namespace: u16,
+    ) -> Result<NodeId, StatusCode> {
+        let mut roles = trace_write_lock!(self.roles);
+        if roles.values().any(|r| r.name == name) {
+            return Err(StatusCode::BadBrowseNameDuplicated);
+        }
+        let node_id = NodeId::new(namespace, Guid::new());
+        roles.insert(node_id.clone(), Role::new(node_id.clone(), name));
+        Ok(node_id)
+    }
+
+    /// Remove a role that was added with `add_role`.
+    pub(crate) fn remove_role(&self, role_id: &NodeId) -> Result<Role, StatusCode> {
+        if Self::is_well_known(role_id) {
+            return Err(StatusCode::BadRequestNotAllowed);
+        }
+        trace_write_lock!(self.roles)
+            .remove(role_id)
+            .ok_or(StatusCode::BadNodeIdUnknown)
+    }
+
+    /// Update the role with the given ID.
+    pub fn update_role<R>(
+        &self,
+        role_id: &NodeId,
+        f: impl FnOnce(&mut Role) -> R,
+    ) -> Result<R, StatusCode> {
+        let mut roles = trace_write_lock!(self.roles);
+        let role = roles.get_mut(role_id).ok_or(StatusCode::BadNodeIdUnknown)?;
+        Ok(f(role))
+    }
+
+    /// Add an identity mapping rule to a role. Returns `false` if the rule already exists.
+    pub fn add_identity(
+        &self,
+        role_id: &NodeId,
+        rule: IdentityMappingRuleType,
+    ) -> Result<bool, StatusCode> {
+        self.update_role(role_id, |r| {
+            if r.identities.contains(&rule) {
+                false
+            } else {
+                r.identities.push(rule);
+                true
+            }
+        })
+    }
+
+    /// Remove an identity mapping rule from a role. Returns `false` if the rule did not exist.
+    pub fn remove_identity(
+        &self,
+        role_id: &NodeId,
+        rule: &IdentityMappingRuleType,
+    ) -> Result<bool, StatusCode> {
+        self.update_role(role_id, |r| {
+            let len = r.identities.len();
+            r.identities.retain(|i| i != rule);
+            r.identities.len() != len
+        })
+    }
+
+    /// Add an application URI to a role. Returns `false` if the application is already listed.
+    pub fn add_application(
+        &self,
+        role_id: &NodeId,
+        application_uri: UAString,
+    ) -> Result<bool, StatusCode> {
+        self.update_role(role_id, |r| {
+            if r.applications.contains(&application_uri) {
+                false
+            } else {
+                r.applications.push(application_uri);
+                true
+            }
+        })
+    }
+
+    /// Remove an application URI from a role. Returns `false` if the application was not listed.
+    pub fn remove_application(
+        &self,
+        role_id: &NodeId,
+        application_uri: &UAString,
+    ) -> Result<bool, StatusCode> {
+        self.update_role(role_id, |r| {
+            let len = r.applications.len();
+            r.applications.retain(|a| a != application_uri);
+            r.applications.len() != len
+        })
+    }
+
+    /// Add an endpoint to a role. Returns `false` if the endpoint is already listed.
+    pub fn add_endpoint(
+        &self,
+        role_id: &NodeId,
+        endpoint: EndpointType,
+    ) -> Result<bool, StatusCode> {
+        self.update_role(role_id, |r| {
+            if r.endpoints.contains(&endpoint) {
+                false
+            } else {
+                r.endpoints.push(endpoint);
+                true
+            }
+        })
+    }
+
+    /// Remove an endpoint from a role. Returns `false` if the endpoint was not listed.
+    pub fn remove_endpoint(
+        &self,
+        role_id: &NodeId,
+        endpoint: &EndpointType,
+    ) -> Result<bool, StatusCode> {
+        self.update_role(role_id, |r| {
+            let len = r.endpoints.len();
+            r.endpoints.retain(|e| e != endpoint);
+            r.endpoints.len() != len
+        })
+    }
+
+    /// Get the IDs of the roles granted to a session.
+    pub(crate) fn roles_for_session(&self, session: &SessionRoleInfo<'_>) -> Vec<NodeId> {
+        trace_read_lock!(self.roles)
+            .values()
+            .filter(|r| r.matches(session))
+            .map(|r| r.node_id.clone())
+            .collect()
+    }
+}
+
+/// Get the combined permissions granted to a set of roles by a list of role permissions.
+pub fn permissions_for_roles(
+    role_permissions: &[RolePermissionType],
+    roles: &[NodeId],
+) -> PermissionType {
+    role_permissions
+        .iter()
+        .filter(|p| roles.contains(&p.role_id))
+        .fold(PermissionType::empty(), |acc, p| acc | p.permissions)
+}
+
+#[cfg(test)]
+mod tests {
+    use opcua_types::{AnonymousIdentityToken, UserNameIdentityToken};
+
+    use super::*;
+
+    fn user(name: &str) -> IdentityToken {
+        IdentityToken::UserName(UserNameIdentityToken {
+            user_name: name.into(),
+            ..Default::default()
+        })
+    }
+
+    fn info<'a>(identity: &'a IdentityToken, mode: MessageSecurityMode) -> SessionRoleInfo<'a> {
+        SessionRoleInfo {
+            identity,
+            application_uri: Some("urn:client"),
+            endpoint_url: "opc.tcp://localhost:4855/",
+            security_mode: mode,
+            security_policy_uri: "",
+        }
+    }
+
+    fn rule(criteria_type: IdentityCriteriaType, criteria: &str) -> IdentityMappingRuleType {
+        IdentityMappingRuleType {
+            criteria_type,
+            criteria: criteria.into(),
+        }
+    }
+
+    #[test]
+    fn default_roles() {
+        let roles = RoleSet::new();
+        let anon = IdentityToken::Anonymous(AnonymousIdentityToken::default());
+        let granted = roles.roles_for_session(&info(&anon, MessageSecurityMode::None));
+        assert_eq!(
+            granted,
+            vec![NodeId::from(ObjectId::WellKnownRole_Anonymous)]
+        );
+
+        let user = user("user");
+        let granted = roles.roles_for_session(&info(&user, MessageSecurityMode::Sign));
+        assert_eq!(granted.len(), 2);
+        assert!(granted.contains(&ObjectId::WellKnownRole_AuthenticatedUser.into()));
+        assert!(granted.contains(&ObjectId::WellKnownRole_TrustedApplication.into()));
+    }
+
+    #[test]
+    fn user_name_mapping() {
+        let roles = RoleSet::new();
+        let operator = NodeId::from(ObjectId::WellKnownRole_Operator);
+        assert!(roles
+            .add_identity(&operator, rule(IdentityCriteriaType::UserName, "op"))
+            .unwrap());
+        assert!(!roles
+            .add_identity(&operator, rule(IdentityCriteriaType::UserName, "op"))
+            .unwrap());
+
+        let op = user("op");
+        assert!(roles
+            .roles_for_session(&info(&op, MessageSecurityMode::None))
+            .contains(&operator));
+        let other = user("other");
+        assert!(!roles
+            .roles_for_session(&info(&other, MessageSecurityMode::None))
+            .contains(&operator));
+
+        assert!(roles
+            .remove_identity(&operator, &rule(IdentityCriteriaType::UserName, "op"))
+            .unwrap());
+        assert!(!roles
+            .roles_for_session(&info(&op, MessageSecurityMode::None))
+            .contains(&operator));
+    }
+
+    #[test]
+    fn application_and_endpoint_filters() {
+        let roles = RoleSet::new();
+        let operator = NodeId::from(ObjectId::WellKnownRole_Operator);
+        roles
+            .add_identity(&operator, rule(IdentityCriteriaType::UserName, "op"))
+            .unwrap();
+        let op = user("op");
+
+        // Exclude the client application.
+        roles
+            .add_application(&operator, "urn:client".into())
+            .unwrap();
+        assert!(!roles
+            .roles_for_session(&info(&op, MessageSecurityMode::None))
+            .contains(&operator));
+        // Turn the list into an include list.
+        roles
+            .update_role(&operator, |r| r.applications_exclude = false)
+            .unwrap();
+        assert!(roles
+            .roles_for_session(&info(&op, MessageSecurityMode::None))
+            .contains(&operator));
+
+        // Only allow encrypted endpoints.
+        roles
+            .add_endpoint(
+                &operator,
+                EndpointType {
+                    security_mode: MessageSecurityMode::SignAndEncrypt,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        roles
+            .update_role(&operator, |r| r.endpoints_exclude = false)
+            .unwrap();
+        assert!(!roles
+            .roles_for_session(&info(&op, MessageSecurityMode::None))
+            .contains(&operator));
+        assert!(roles
+            .roles_for_session(&info(&op, MessageSecurityMode::SignAndEncrypt))
+            .contains(&operator));
+    }
+
+    #[test]
+    fn application_mapping_uses_certificate_uri() {
+        let roles = RoleSet::new();
+        let engineer = NodeId::from(ObjectId::WellKnownRole_Engineer);
+        roles
+            .add_identity(
+                &engineer,
+                rule(IdentityCriteriaType::Application, "urn:client"),
+            )
+            .unwrap();
+        let anon = IdentityToken::Anonymous(AnonymousIdentityToken::default());
+
+        assert!(roles
+            .roles_for_session(&info(&anon, MessageSecurityMode::Sign))
+            .contains(&engineer));
+        // The rule does not depend on the user identity.
+        let op = user("op");
+        assert!(roles
+            .roles_for_session(&info(&op, MessageSecurityMode::Sign))
+            .contains(&engineer));
+
+        // Without a certificate, the application URI is unknown.
+        let mut session = info(&anon, MessageSecurityMode::Sign);
+        session.application_uri = None;
+        assert!(!roles.roles_for_session(&session).contains(&engineer));
+    }
+
+    #[test]
+    fn add_remove_role() {
+        let roles = RoleSet::new();
+        let id = roles.add_role("Custom".into(), 1).unwrap();
+        assert_eq!(id.namespace, 1);
+        assert_eq!(
+            roles.add_role("Custom".into(), 1).unwrap_err(),
+            StatusCode::BadBrowseNameDuplicated
+        );
+        assert_eq!(roles.find_by_name(&"Custom".into()).unwrap().node_id, id);
+        assert_eq!(
+            roles
+                .remove_role(&ObjectId::WellKnownRole_Anonymous.into())
+                .unwrap_err(),
+            StatusCode::BadRequestNotAllowed
+        );
+        roles.remove_role(&id).unwrap();
+        assert!(roles.get(&id).is_none());
+    }
+
+    #[test]
+    fn combined_permissions() {
+        let operator = NodeId::from(ObjectId::WellKnownRole_Operator);
+        let observer = NodeId::from(ObjectId::WellKnownRole_Observer);
+        let permissions = vec![
+            RolePermissionType {
+                role_id: operator.clone(),
+                permissions: PermissionType::Read | PermissionType::Write,
+            },
+            RolePermissionType {
+                role_id: observer.clone(),
+                permissions: PermissionType::Browse | PermissionType::Read,
+            },
+        ];
+        assert_eq!(
+            permissions_for_roles(&permissions, &[operator.clone(), observer]),
+            PermissionType::Browse | PermissionType::Read | PermissionType::Write
+        );
+        assert_eq!(
+            permissions_for_roles(&permissions, &[]),
+            PermissionType::empty()
+        );
+    }
+}
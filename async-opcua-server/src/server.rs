@@ -9,11 +9,16 @@ use std::{
 };
 
 use arc_swap::ArcSwap;
-use futures::{future::Either, never::Never, stream::FuturesUnordered, FutureExt, StreamExt};
-use opcua_core::{sync::RwLock, trace_read_lock, trace_write_lock};
+use futures::{
+    future::{BoxFuture, Either},
+    never::Never,
+    stream::FuturesUnordered,
+    FutureExt, StreamExt,
+};
+use opcua_core::{comms::stream::BoxedStream, sync::RwLock, trace_read_lock, trace_write_lock};
 use opcua_nodes::DefaultTypeTree;
 use tokio::{
-    net::TcpListener,
+    net::{TcpListener, TcpStream},
     pin,
     sync::Notify,
     task::{JoinError, JoinHandle},
@@ -21,23 +26,37 @@ use tokio::{
 use tokio_util::sync::CancellationToken;
 use tracing::{error, info, warn};
 
-use opcua_core::{config::Config, handle::AtomicHandle};
-use opcua_crypto::CertificateStore;
+use opcua_core::{
+    comms::{tcp_types::ReverseHelloMessage, url::hostname_port_from_url},
+    config::Config,
+    constants::DEFAULT_OPC_UA_SERVER_PORT,
+    handle::AtomicHandle,
+};
+use opcua_crypto::{ecc::EccCurve, CertificateStore};
 
 use crate::{
+    conditions::ConditionManager,
     diagnostics::ServerDiagnostics,
+    discovery_server::DiscoveryServerRegistry,
     node_manager::{DefaultTypeTreeGetter, ServerContext},
+    roles::RoleSet,
     session::controller::{ControllerCommand, SessionStarter},
-    transport::tcp::{TcpConnector, TransportConfig},
+    transport::{
+        tcp::{TcpConnector, TransportConfig},
+        Listener,
+    },
     ServerStatusWrapper,
 };
-use opcua_types::{DateTime, LocalizedText, ServerState, UAString};
+use opcua_types::{
+    custom::{DataTypeTree, ParentIds},
+    DateTime, LocalizedText, ServerState, StatusCode, UAString,
+};
 
 use super::{
     authenticator::DefaultAuthenticator,
     builder::ServerBuilder,
-    config::ServerConfig,
-    info::ServerInfo,
+    config::{EndpointTransport, ServerConfig},
+    info::{ServerInfo, ServerKeyPair},
     node_manager::{NodeManagers, NodeManagersRef},
     server_handle::ServerHandle,
     session::manager::SessionManager,
@@ -49,6 +68,14 @@ struct ConnectionInfo {
     command_send: tokio::sync::mpsc::Sender<ControllerCommand>,
 }
 
+/// Outgoing reverse connection attempt, yielding the index of the
+/// reverse connect configuration and the connected socket.
+type ReverseConnectFuture = BoxFuture<'static, (usize, Result<BoxedStream, StatusCode>)>;
+
+/// Reverse connection waiting to be used by the client, yielding the index of the
+/// reverse connect configuration and whether the client completed the handshake.
+type ReverseConnectionFuture = BoxFuture<'static, (usize, bool)>;
+
 /// The server struct. This is consumed when run, so you will typically not hold onto this for longer
 /// periods of time.
 pub struct Server {
@@ -119,6 +146,23 @@ impl Server {
             warn!("Server is missing its application instance certificate and/or its private key. Encrypted endpoints will not function correctly.");
         }
 
+        let ecc_certificates: HashMap<_, _> = EccCurve::ALL
+            .iter()
+            .copied()
+            .filter_map(|curve| {
+                let cert = certificate_store.read_own_ecc_cert(curve).ok()?;
+                let pkey = certificate_store.read_own_ecc_pkey(curve).ok()?;
+                Some((curve, (Arc::new(cert), Arc::new(pkey))))
+            })
+            .collect();
+        for endpoint in config.endpoints.values() {
+            if let Some(curve) = endpoint.security_policy().ecc_curve() {
+                if !ecc_certificates.contains_key(&curve) {
+                    warn!("Server is missing its application instance certificate for curve {curve}. Endpoints with security policy {} will not function correctly.", endpoint.security_policy());
+                }
+            }
+        }
+
         config.read_x509_thumbprints();
 
         if config.certificate_validation.trust_client_certs {
@@ -126,6 +170,11 @@ impl Server {
             certificate_store.set_trust_unknown_certs(true);
         }
         certificate_store.set_check_time(config.certificate_validation.check_time);
+        certificate_store.set_reject_unknown_revocation_status(
+            config
+                .certificate_validation
+                .reject_unknown_revocation_status,
+        );
 
         let config = Arc::new(config);
 
@@ -133,6 +182,11 @@ impl Server {
 
         let type_tree = Arc::new(RwLock::new(DefaultTypeTree::new()));
 
+        let subscriptions = Arc::new(SubscriptionCache::new(
+            config.limits.subscriptions,
+            type_tree.clone(),
+        ));
+
         let info = ServerInfo {
             authenticator: builder
                 .authenticator
@@ -146,8 +200,11 @@ impl Server {
             start_time: ArcSwap::new(Arc::new(opcua_types::DateTime::now())),
             servers,
             config: config.clone(),
-            server_certificate,
-            server_pkey,
+            server_keypair: ArcSwap::new(Arc::new(ServerKeyPair {
+                certificate: server_certificate.map(Arc::new),
+                pkey: server_pkey.map(Arc::new),
+            })),
+            ecc_certificates,
             operational_limits: config.limits.operational.clone(),
             state: ArcSwap::new(Arc::new(ServerState::Shutdown)),
             send_buffer_size,
@@ -163,16 +220,22 @@ impl Server {
                 .type_tree_getter
                 .unwrap_or_else(|| Arc::new(DefaultTypeTreeGetter)),
             type_loaders: RwLock::new(builder.type_loaders),
+            data_types: RwLock::new(DataTypeTree::new(ParentIds::new())),
             diagnostics: ServerDiagnostics {
                 enabled: config.diagnostics,
                 ..Default::default()
             },
+            conditions: Arc::new(ConditionManager::new(subscriptions.clone())),
+            discovery_server: config
+                .discovery_server
+                .as_ref()
+                .map(DiscoveryServerRegistry::new),
+            roles: Arc::new(RoleSet::new()),
         };
 
         let certificate_store = Arc::new(RwLock::new(certificate_store));
 
         let info = Arc::new(info);
-        let subscriptions = Arc::new(SubscriptionCache::new(config.limits.subscriptions));
 
         let node_managers_ref = NodeManagersRef::new_empty();
         let status_wrapper = Arc::new(ServerStatusWrapper::new(
@@ -187,6 +250,7 @@ impl Server {
             type_tree: type_tree.clone(),
             type_tree_getter: info.type_tree_getter.clone(),
             status: status_wrapper.clone(),
+            certificate_store: certificate_store.clone(),
         };
 
         let mut final_node_managers = Vec::new();
@@ -259,7 +323,12 @@ impl Server {
     #[cfg(feature = "discovery-server-registration")]
     async fn run_discovery_server_registration(info: Arc<ServerInfo>) -> Never {
         let registered_server = info.registered_server();
-        let Some(discovery_server_url) = info.config.discovery_server_url.as_ref() else {
+        // A discovery server does not register itself with another discovery server.
+        let discovery_server_url = match info.discovery_server {
+            Some(_) => None,
+            None => info.config.discovery_server_url.as_ref(),
+        };
+        let Some(discovery_server_url) = discovery_server_url else {
             loop {
                 futures::future::pending::<()>().await;
             }
@@ -273,13 +342,14 @@ impl Server {
         .await
     }
 
-    /// Run the server using a given TCP listener.
+    /// Run the server using a given listener, typically a `TcpListener`.
     /// Note that the configured TCP endpoint is still used to create the endpoint
     /// descriptions, you must properly set `host` and `port` even when using this.
     ///
     /// This is useful for testing, as you can bind a `TcpListener` to port `0` auto-assign
-    /// a port.
-    pub async fn run_with(mut self, listener: TcpListener) -> Result<(), String> {
+    /// a port. See [`Listener`] for other transports, such as Unix domain sockets or
+    /// in-memory connections.
+    pub async fn run_with(mut self, mut listener: impl Listener) -> Result<(), String> {
         let context = ServerContext {
             node_managers: self.node_managers.as_weak(),
             subscriptions: self.subscriptions.clone(),
@@ -288,21 +358,21 @@ impl Server {
             type_tree: self.info.type_tree.clone(),
             type_tree_getter: self.info.type_tree_getter.clone(),
             status: self.status.clone(),
+            certificate_store: self.certificate_store.clone(),
         };
 
         self.initialize_node_managers(&context).await?;
 
         self.status.set_server_started();
         self.info.start_time.store(Arc::new(DateTime::now()));
+        self.info.state.store(Arc::new(ServerState::Running));
 
-        let addr = listener
-            .local_addr()
-            .map_err(|e| format!("Failed to bind socket: {e:?}"))?;
-        info!("Now listening for connections on {addr}");
+        info!("Now listening for connections on {}", listener.describe());
 
+        let port = listener.port().unwrap_or(self.config.tcp_config.port);
         self.info
             .port
-            .store(addr.port(), std::sync::atomic::Ordering::Relaxed);
+            .store(port, std::sync::atomic::Ordering::Relaxed);
 
         self.log_endpoint_info();
 
@@ -324,6 +394,21 @@ impl Server {
             Self::run_session_expiry(&self.session_manager, &self.session_notify);
         pin!(session_expiry_fut);
 
+        let reverse_connect = self.config.reverse_connect.clone();
+        let mut reverse_connect_delays: Vec<_> = reverse_connect
+            .iter()
+            .map(|r| Duration::from_millis(r.retry_interval_ms))
+            .collect();
+        let mut reverse_connects = FuturesUnordered::<ReverseConnectFuture>::new();
+        let mut unclaimed_reverse_connections = FuturesUnordered::<ReverseConnectionFuture>::new();
+        for (idx, r) in reverse_connect.iter().enumerate() {
+            reverse_connects.push(Self::reverse_connect(
+                idx,
+                r.client_url.clone(),
+                Duration::ZERO,
+            ));
+        }
+
         loop {
             let conn_fut = if self.connections.is_empty() {
                 if self.token.is_cancelled() {
@@ -344,34 +429,56 @@ impl Server {
                         Err(e) => error!("Connection panic! {e}")
                     }
                 }
+                Some((idx, rs)) = reverse_connects.next(), if !self.token.is_cancelled() => {
+                    let config = &reverse_connect[idx];
+                    match rs {
+                        Ok(socket) => {
+                            info!("Opened reverse connection to {} ({connection_counter})", config.client_url);
+                            let reverse_hello = ReverseHelloMessage::new(
+                                self.info.application_uri.as_ref(),
+                                &self.reverse_connect_endpoint_url(config.endpoint_url.as_deref()),
+                            );
+                            let (established_send, established_recv) = tokio::sync::oneshot::channel();
+                            let (handle, conn) = self.start_connection(socket, Some((reverse_hello, established_send)), connection_counter);
+                            self.connections.push(handle);
+                            self.connection_map.insert(connection_counter, conn);
+                            connection_counter += 1;
+                            unclaimed_reverse_connections.push(established_recv.map(move |r| (idx, r.is_ok())).boxed());
+                        }
+                        Err(e) => {
+                            let delay = reverse_connect_delays[idx];
+                            warn!("Failed to open reverse connection to {}: {e}, retrying in {}ms", config.client_url, delay.as_millis());
+                            reverse_connects.push(Self::reverse_connect(idx, config.client_url.clone(), delay));
+                            reverse_connect_delays[idx] = (delay * 2)
+                                .min(Duration::from_millis(config.max_retry_interval_ms));
+                        }
+                    }
+                }
+                Some((idx, established)) = unclaimed_reverse_connections.next(), if !self.token.is_cancelled() => {
+                    let config = &reverse_connect[idx];
+                    if established {
+                        // The client is using the connection, so open a new one right away,
+                        // keeping one connection open for the client to claim.
+                        reverse_connect_delays[idx] = Duration::from_millis(config.retry_interval_ms);
+                        reverse_connects.push(Self::reverse_connect(idx, config.client_url.clone(), Duration::ZERO));
+                    } else {
+                        let delay = reverse_connect_delays[idx];
+                        warn!("Reverse connection to {} closed before the handshake completed, retrying in {}ms", config.client_url, delay.as_millis());
+                        reverse_connects.push(Self::reverse_connect(idx, config.client_url.clone(), delay));
+                        reverse_connect_delays[idx] = (delay * 2)
+                            .min(Duration::from_millis(config.max_retry_interval_ms));
+                    }
+                }
                 _ = &mut subscription_fut => {}
                 _ = &mut discovery_fut => {}
                 _ = &mut session_expiry_fut => {}
                 rs = listener.accept() => {
                     match rs {
-                        Ok((socket, addr)) => {
-                            info!("Accept new connection from {addr} ({connection_counter})");
-                            let conn = SessionStarter::new(
-                                TcpConnector::new(socket, TransportConfig {
-                                    send_buffer_size: self.info.config.limits.send_buffer_size,
-                                    max_message_size: self.info.config.limits.max_message_size,
-                                    max_chunk_count: self.info.config.limits.max_chunk_count,
-                                    receive_buffer_size: self.info.config.limits.receive_buffer_size,
-                                    hello_timeout: Duration::from_secs(self.info.config.tcp_config.hello_timeout as u64),
-                                }, self.info.decoding_options()),
-                                self.info.clone(),
-                                self.session_manager.clone(),
-                                self.certificate_store.clone(),
-                                self.node_managers.clone(),
-                                self.subscriptions.clone()
-                            );
-
-                            let (send, recv) = tokio::sync::mpsc::channel(5);
-                            let handle = tokio::spawn(conn.run(recv).map(move |_| connection_counter));
+                        Ok((socket, peer)) => {
+                            info!("Accept new connection from {peer} ({connection_counter})");
+                            let (handle, conn) = self.start_connection(socket, None, connection_counter);
                             self.connections.push(handle);
-                            self.connection_map.insert(connection_counter, ConnectionInfo {
-                                command_send: send
-                            });
+                            self.connection_map.insert(connection_counter, conn);
                             connection_counter += 1;
                         }
                         Err(e) => {
@@ -390,6 +497,89 @@ impl Server {
         Ok(())
     }
 
+    fn start_connection(
+        &self,
+        socket: BoxedStream,
+        reverse_connect: Option<(ReverseHelloMessage, tokio::sync::oneshot::Sender<()>)>,
+        connection_counter: u32,
+    ) -> (JoinHandle<u32>, ConnectionInfo) {
+        let mut connector = TcpConnector::new(
+            socket,
+            TransportConfig {
+                send_buffer_size: self.info.config.limits.send_buffer_size,
+                max_message_size: self.info.config.limits.max_message_size,
+                max_chunk_count: self.info.config.limits.max_chunk_count,
+                receive_buffer_size: self.info.config.limits.receive_buffer_size,
+                hello_timeout: Duration::from_secs(
+                    self.info.config.tcp_config.hello_timeout as u64,
+                ),
+            },
+            self.info.decoding_options(),
+        );
+        let established = match reverse_connect {
+            Some((reverse_hello, established)) => {
+                connector = connector.with_reverse_hello(reverse_hello);
+                Some(established)
+            }
+            None => None,
+        };
+        let mut conn = SessionStarter::new(
+            connector,
+            self.info.clone(),
+            self.session_manager.clone(),
+            self.certificate_store.clone(),
+            self.node_managers.clone(),
+            self.subscriptions.clone(),
+        );
+        if let Some(established) = established {
+            conn = conn.with_established_notify(established);
+        }
+
+        let (send, recv) = tokio::sync::mpsc::channel(5);
+        let handle = tokio::spawn(conn.run(recv).map(move |_| connection_counter));
+        (handle, ConnectionInfo { command_send: send })
+    }
+
+    /// Get the endpoint URL to send to clients in `ReverseHello` messages.
+    /// Reverse connections always use OPC UA TCP, so only `opc.tcp` endpoints are used.
+    fn reverse_connect_endpoint_url(&self, configured: Option<&str>) -> String {
+        if let Some(url) = configured {
+            return url.to_owned();
+        }
+        let base_endpoint = self.info.base_endpoint();
+        self.config
+            .default_endpoint()
+            .filter(|e| e.transport == EndpointTransport::Tcp)
+            .or_else(|| {
+                self.config
+                    .endpoints
+                    .values()
+                    .find(|e| e.transport == EndpointTransport::Tcp)
+            })
+            .map(|e| e.endpoint_url(&base_endpoint))
+            .unwrap_or(base_endpoint)
+    }
+
+    /// Open a connection to a client for reverse connect, after waiting for `delay`.
+    fn reverse_connect(idx: usize, client_url: String, delay: Duration) -> ReverseConnectFuture {
+        async move {
+            tokio::time::sleep(delay).await;
+            let res = async {
+                let (host, port) = hostname_port_from_url(&client_url, DEFAULT_OPC_UA_SERVER_PORT)?;
+                TcpStream::connect((host.as_str(), port))
+                    .await
+                    .map(|s| Box::new(s) as BoxedStream)
+                    .map_err(|e| {
+                        warn!("Could not connect to client {client_url}: {e}");
+                        StatusCode::BadCommunicationError
+                    })
+            }
+            .await;
+            (idx, res)
+        }
+        .boxed()
+    }
+
     /// Run the server. The provided `token` can be used to stop the server gracefully.
     pub async fn run(self) -> Result<(), String> {
         let addr = self.get_socket_address();
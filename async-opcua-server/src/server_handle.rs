@@ -10,7 +10,7 @@ use tracing::info;
 use opcua_core::sync::RwLock;
 use opcua_types::{AttributeId, DataValue, LocalizedText, ServerState, VariableId};
 
-use crate::ServerStatusWrapper;
+use crate::{conditions::ConditionManager, roles::RoleSet, ServerStatusWrapper};
 
 use super::{
     info::ServerInfo, node_manager::NodeManagers, session::manager::SessionManager,
@@ -65,6 +65,17 @@ impl ServerHandle {
         &self.subscriptions
     }
 
+    /// Get a reference to the condition manager, used to manage alarms and conditions.
+    pub fn conditions(&self) -> &Arc<ConditionManager> {
+        &self.info.conditions
+    }
+
+    /// Get a reference to the role set, used to manage roles and the identities
+    /// mapped to them.
+    pub fn roles(&self) -> &Arc<RoleSet> {
+        &self.info.roles
+    }
+
     /// Set the service level, properly notifying subscribed clients of the change.
     pub fn set_service_level(&self, sl: u8) {
         self.service_level
@@ -18,15 +18,17 @@ use opcua_core::{
 };
 use opcua_crypto::{CertificateStore, SecurityPolicy};
 use opcua_types::{
-    ChannelSecurityToken, DateTime, FindServersResponse, GetEndpointsResponse, MessageSecurityMode,
-    OpenSecureChannelRequest, OpenSecureChannelResponse, ResponseHeader, SecurityTokenRequestType,
-    ServiceFault, StatusCode,
+    ChannelSecurityToken, DateTime, FindServersOnNetworkResponse, FindServersResponse,
+    GetEndpointsResponse, MessageSecurityMode, OpenSecureChannelRequest, OpenSecureChannelResponse,
+    RegisterServer2Response, RegisterServerResponse, RegisteredServer, ResponseHeader,
+    SecurityTokenRequestType, ServiceFault, StatusCode,
 };
 use tokio_util::sync::CancellationToken;
 use tracing_futures::Instrument;
 
 use crate::{
     authenticator::UserToken,
+    discovery_server::DiscoveryServerRegistry,
     info::ServerInfo,
     node_manager::NodeManagers,
     subscriptions::SubscriptionCache,
@@ -95,6 +97,7 @@ pub(crate) struct SessionStarter<T> {
     certificate_store: Arc<RwLock<CertificateStore>>,
     node_managers: NodeManagers,
     subscriptions: Arc<SubscriptionCache>,
+    established: Option<tokio::sync::oneshot::Sender<()>>,
 }
 
 impl<T: Connector> SessionStarter<T> {
@@ -113,9 +116,20 @@ impl<T: Connector> SessionStarter<T> {
             certificate_store,
             node_managers,
             subscriptions,
+            established: None,
         }
     }
 
+    /// Notify `established` once the client has completed the transport handshake.
+    /// If the handshake fails, `established` is dropped instead.
+    pub(crate) fn with_established_notify(
+        mut self,
+        established: tokio::sync::oneshot::Sender<()>,
+    ) -> Self {
+        self.established = Some(established);
+        self
+    }
+
     pub(crate) async fn run(self, mut command: tokio::sync::mpsc::Receiver<ControllerCommand>) {
         let token = CancellationToken::new();
         let span = tracing::info_span!("Establish TCP channel");
@@ -147,6 +161,10 @@ impl<T: Connector> SessionStarter<T> {
             }
         };
 
+        if let Some(established) = self.established {
+            let _ = established.send(());
+        }
+
         let controller = SessionController::new(
             transport,
             self.session_manager,
@@ -331,7 +349,12 @@ impl SessionController {
             RequestMessage::CreateSession(request) => {
                 let _h = span.enter();
                 let mut mgr = trace_write_lock!(self.session_manager);
-                let res = mgr.create_session(&mut self.channel, &self.certificate_store, &request);
+                let res = mgr.create_session(
+                    &mut self.channel,
+                    self.transport.endpoint_transport(),
+                    &self.certificate_store,
+                    &request,
+                );
                 drop(mgr);
                 self.process_service_result(res, request.request_header.request_handle, id)
             }
@@ -340,6 +363,7 @@ impl SessionController {
                 let res = activate_session(
                     &self.session_manager,
                     &mut self.channel,
+                    self.transport.endpoint_transport(),
                     &request,
                     &mut self.message_handler,
                 )
@@ -387,7 +411,13 @@ impl SessionController {
 
                 // TODO endpoint URL
 
-                // TODO localeids, filter out servers that do not support locale ids
+                // Registered servers, if this is a discovery server.
+                if let Some(discovery_server) = &self.info.discovery_server {
+                    servers.extend(
+                        discovery_server
+                            .find_servers(request.locale_ids.as_deref().unwrap_or_default()),
+                    );
+                }
 
                 // Filter servers that do not have a matching application uri
                 if let Some(ref server_uris) = request.server_uris {
@@ -412,45 +442,55 @@ impl SessionController {
             }
             RequestMessage::FindServersOnNetwork(request) => {
                 let _h = span.enter();
-                if let Err(e) = self.transport.enqueue_message_for_send(
-                    &mut self.channel,
-                    ServiceFault::new(&request.request_header, StatusCode::BadServiceUnsupported)
-                        .into(),
-                    id,
-                ) {
-                    error!("Failed to send request response: {e}");
-                    RequestProcessResult::Close
-                } else {
-                    RequestProcessResult::Ok
-                }
+                let res = match &self.info.discovery_server {
+                    Some(discovery_server) => {
+                        let (last_counter_reset_time, servers) = discovery_server
+                            .find_servers_on_network(
+                                request.starting_record_id,
+                                request.max_records_to_return,
+                                request
+                                    .server_capability_filter
+                                    .as_deref()
+                                    .unwrap_or_default(),
+                            );
+                        Ok(FindServersOnNetworkResponse {
+                            response_header: ResponseHeader::new_good(&request.request_header),
+                            last_counter_reset_time,
+                            servers: Some(servers),
+                        })
+                    }
+                    None => Err(StatusCode::BadServiceUnsupported),
+                };
+                self.process_service_result(res, request.request_header.request_handle, id)
             }
             RequestMessage::RegisterServer(request) => {
                 let _h = span.enter();
-                if let Err(e) = self.transport.enqueue_message_for_send(
-                    &mut self.channel,
-                    ServiceFault::new(&request.request_header, StatusCode::BadServiceUnsupported)
-                        .into(),
-                    id,
-                ) {
-                    error!("Failed to send request response: {e}");
-                    RequestProcessResult::Close
-                } else {
-                    RequestProcessResult::Ok
-                }
+                let request_handle = request.request_header.request_handle;
+                let res = self
+                    .discovery_server_for_registration(&request.server)
+                    .and_then(|d| {
+                        d.register(request.server, None)?;
+                        Ok(RegisterServerResponse {
+                            response_header: ResponseHeader::new_good(&request.request_header),
+                        })
+                    });
+                self.process_service_result(res, request_handle, id)
             }
             RequestMessage::RegisterServer2(request) => {
                 let _h = span.enter();
-                if let Err(e) = self.transport.enqueue_message_for_send(
-                    &mut self.channel,
-                    ServiceFault::new(&request.request_header, StatusCode::BadServiceUnsupported)
-                        .into(),
-                    id,
-                ) {
-                    error!("Failed to send request response: {e}");
-                    RequestProcessResult::Close
-                } else {
-                    RequestProcessResult::Ok
-                }
+                let request_handle = request.request_header.request_handle;
+                let res = self
+                    .discovery_server_for_registration(&request.server)
+                    .and_then(|d| {
+                        let results =
+                            d.register2(request.server, request.discovery_configuration)?;
+                        Ok(RegisterServer2Response {
+                            response_header: ResponseHeader::new_good(&request.request_header),
+                            configuration_results: Some(results),
+                            diagnostic_infos: None,
+                        })
+                    });
+                self.process_service_result(res, request_handle, id)
             }
 
             message => {
@@ -559,6 +599,33 @@ impl SessionController {
         }
     }
 
+    /// Get the discovery server registry, if registration is allowed on this channel.
+    /// Servers may only register over a secure channel.
+    fn discovery_server_for_registration(
+        &self,
+        server: &RegisteredServer,
+    ) -> Result<&DiscoveryServerRegistry, StatusCode> {
+        let Some(discovery_server) = &self.info.discovery_server else {
+            return Err(StatusCode::BadServiceUnsupported);
+        };
+        if self.channel.security_mode() == MessageSecurityMode::None {
+            return Err(StatusCode::BadSecurityChecksFailed);
+        }
+        // A server may only register itself, so the server URI must be the application
+        // URI in the certificate used to create the secure channel.
+        let Some(cert) = self.channel.remote_cert() else {
+            return Err(StatusCode::BadSecurityChecksFailed);
+        };
+        if cert.application_uri().as_deref() != Some(server.server_uri.as_ref()) {
+            error!(
+                "Server URI {} does not match the application URI of the certificate of the secure channel",
+                server.server_uri
+            );
+            return Err(StatusCode::BadServerUriInvalid);
+        }
+        Ok(discovery_server)
+    }
+
     fn process_service_result(
         &mut self,
         res: Result<impl Into<ResponseMessage>, StatusCode>,
@@ -732,7 +799,11 @@ impl SessionController {
             && (security_mode == MessageSecurityMode::Sign
                 || security_mode == MessageSecurityMode::SignAndEncrypt)
         {
-            self.channel.derive_keys();
+            if let Err(err) = self.channel.derive_keys() {
+                // Without keys the channel cannot send anything, so it must be closed.
+                error!("Failed to derive secure channel keys: {err}");
+                return Err(err.status());
+            }
         }
 
         let response = OpenSecureChannelResponse {
@@ -11,7 +11,7 @@ use crate::authenticator::UserToken;
 use crate::identity_token::IdentityToken;
 use crate::info::ServerInfo;
 use crate::node_manager::{BrowseContinuationPoint, QueryContinuationPoint};
-use opcua_crypto::X509;
+use opcua_crypto::{EphemeralKey, X509};
 use opcua_types::{
     ApplicationDescription, ByteString, MessageSecurityMode, NodeId, StatusCode, UAString,
 };
@@ -32,6 +32,8 @@ pub struct Session {
     pub(super) authentication_token: NodeId,
     /// Session nonce
     session_nonce: ByteString,
+    /// Ephemeral key last issued to the client for encrypting user identity secrets.
+    ecdh_key: Option<Arc<EphemeralKey>>,
     /// Session name (supplied by client)
     session_name: UAString,
     /// Session timeout
@@ -66,6 +68,8 @@ pub struct Session {
     query_continuation_points: HashMap<ByteString, QueryContinuationPoint>,
     /// User token.
     user_token: Option<UserToken>,
+    /// Roles granted to the session when it was activated.
+    roles: Vec<NodeId>,
     /// Whether the session has been closed.
     is_closed: bool,
 }
@@ -98,6 +102,7 @@ impl Session {
             client_certificate,
             authentication_token,
             session_nonce,
+            ecdh_key: None,
             session_name,
             session_timeout: if session_timeout == 0 {
                 Duration::from_millis(info.config.max_session_timeout_ms)
@@ -117,6 +122,7 @@ impl Session {
             history_continuation_points: Default::default(),
             query_continuation_points: Default::default(),
             user_token: None,
+            roles: Vec::new(),
             application_description,
             message_security_mode,
             is_closed: false,
@@ -179,8 +185,10 @@ impl Session {
         identity: IdentityToken,
         locale_ids: Option<Vec<UAString>>,
         user_token: UserToken,
+        roles: Vec<NodeId>,
     ) {
         self.user_token = Some(user_token);
+        self.roles = roles;
         self.secure_channel_id = secure_channel_id;
         self.session_nonce = server_nonce;
         self.user_identity = identity;
@@ -215,6 +223,17 @@ impl Session {
         &self.session_nonce
     }
 
+    /// Get the ephemeral key last issued to the client for encrypting user identity secrets
+    /// with an ECC security policy.
+    pub(crate) fn ecdh_key(&self) -> Option<Arc<EphemeralKey>> {
+        self.ecdh_key.clone()
+    }
+
+    /// Set the ephemeral key issued to the client, replacing any previous key.
+    pub(crate) fn set_ecdh_key(&mut self, ecdh_key: Option<Arc<EphemeralKey>>) {
+        self.ecdh_key = ecdh_key;
+    }
+
     /// Whether this session is activated.
     pub fn is_activated(&self) -> bool {
         self.user_token.is_some() && !self.is_closed
@@ -301,6 +320,12 @@ impl Session {
         self.user_token.as_ref()
     }
 
+    /// Get the IDs of the roles granted to this session. This is empty
+    /// until the session is activated.
+    pub fn roles(&self) -> &[NodeId] {
+        &self.roles
+    }
+
     /// Get the message security mode used by this session.
     pub fn message_security_mode(&self) -> MessageSecurityMode {
         self.message_security_mode
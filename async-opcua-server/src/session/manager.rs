@@ -8,22 +8,82 @@ use std::{
 };
 
 use opcua_core::{comms::secure_channel::SecureChannel, trace_read_lock, trace_write_lock};
-use opcua_crypto::{random, security_policy::SecurityPolicy, CertificateStore};
+use opcua_crypto::{
+    random, security_policy::SecurityPolicy, user_identity, CertificateStore, EphemeralKey,
+};
 use parking_lot::RwLock;
 use tokio::sync::Notify;
 use tracing::{error, info};
 
-use crate::{identity_token::IdentityToken, info::ServerInfo};
+use crate::{
+    config::EndpointTransport, identity_token::IdentityToken, info::ServerInfo,
+    roles::SessionRoleInfo,
+};
 use opcua_types::{
-    ActivateSessionRequest, ActivateSessionResponse, CloseSessionRequest, CloseSessionResponse,
-    CreateSessionRequest, CreateSessionResponse, Error, NodeId, ResponseHeader, SignatureData,
-    StatusCode,
+    ActivateSessionRequest, ActivateSessionResponse, AdditionalParametersType, CloseSessionRequest,
+    CloseSessionResponse, CreateSessionRequest, CreateSessionResponse, Error, ExtensionObject,
+    NodeId, RequestHeader, ResponseHeader, SignatureData, StatusCode, UAString, Variant,
 };
 
 use super::{instance::Session, message_handler::MessageHandler};
 
 static NEXT_SESSION_ID: AtomicU32 = AtomicU32::new(1);
 
+/// Issue an ephemeral key for encrypting user identity secrets if the client requested one
+/// in the additional header of the request, as described in part 4, 7.41.2.5.
+///
+/// Returns the key, if one was issued, and the additional header for the response.
+fn issue_ecdh_key(
+    info: &ServerInfo,
+    request_header: &RequestHeader,
+) -> (Option<Arc<EphemeralKey>>, ExtensionObject) {
+    let Some(policy_uri) = request_header
+        .additional_header
+        .inner_as::<AdditionalParametersType>()
+        .and_then(|p| p.get(user_identity::ECDH_POLICY_URI_PARAMETER))
+    else {
+        return (None, ExtensionObject::null());
+    };
+
+    let security_policy = match policy_uri {
+        Variant::String(uri) => SecurityPolicy::from_uri(uri.as_ref()),
+        _ => SecurityPolicy::Unknown,
+    };
+    let key = security_policy.ecc_curve().and_then(|curve| {
+        let signing_key = info.server_pkey_for_policy(security_policy)?;
+        let key = EphemeralKey::new(curve);
+        match user_identity::create_ephemeral_key_type(&key, security_policy, &signing_key) {
+            Ok(key_type) => Some((key, key_type)),
+            Err(e) => {
+                error!("Failed to sign ephemeral key: {e}");
+                None
+            }
+        }
+    });
+
+    let (key, value) = match key {
+        Some((key, key_type)) => (
+            Some(Arc::new(key)),
+            Variant::from(ExtensionObject::from_message(key_type)),
+        ),
+        None => {
+            let uri = match policy_uri {
+                Variant::String(uri) => uri.clone(),
+                _ => UAString::null(),
+            };
+            error!("Client requested an ephemeral key for unsupported security policy {uri}");
+            (None, Variant::from(StatusCode::BadSecurityPolicyRejected))
+        }
+    };
+    (
+        key,
+        ExtensionObject::from_message(AdditionalParametersType::new([(
+            user_identity::ECDH_KEY_PARAMETER,
+            value,
+        )])),
+    )
+}
+
 pub(super) fn next_session_id() -> (NodeId, u32) {
     // Session id will be a string identifier
     let session_id = NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed);
@@ -64,6 +124,7 @@ impl SessionManager {
     pub(crate) fn create_session(
         &mut self,
         channel: &mut SecureChannel,
+        transport: EndpointTransport,
         certificate_store: &RwLock<CertificateStore>,
         request: &CreateSessionRequest,
     ) -> Result<CreateSessionResponse, StatusCode> {
@@ -81,6 +142,14 @@ impl SessionManager {
             error!("Create session was passed an null endpoint url");
             return Err(StatusCode::BadTcpEndpointUrlInvalid);
         }
+        // Only endpoints using the transport the client connected with may be used.
+        if !transport.matches_url(request.endpoint_url.as_ref()) {
+            error!(
+                "Create session was passed endpoint url {} on a {:?} connection",
+                request.endpoint_url, transport
+            );
+            return Err(StatusCode::BadTcpEndpointUrlInvalid);
+        }
 
         let Some(endpoints) = endpoints else {
             return Err(StatusCode::BadTcpEndpointUrlInvalid);
@@ -97,6 +166,14 @@ impl SessionManager {
                 None,
                 None,
             )?;
+            // The application URI is used to grant roles, so the client must not be able
+            // to claim a different URI than the one in its certificate.
+            if let Err(e) =
+                cert.is_application_uri_valid(request.client_description.application_uri.as_ref())
+            {
+                error!("Create session was passed an application URI that does not match the client certificate");
+                return Err(e);
+            }
             Some(cert)
         } else {
             None
@@ -109,9 +186,10 @@ impl SessionManager {
             .min(request.requested_session_timeout.floor() as u64);
         let max_request_message_size = self.info.config.limits.max_message_size as u32;
 
-        let server_signature = if let Some(ref pkey) = self.info.server_pkey {
+        let server_signature = if let Some(pkey) = self.info.server_pkey_for_policy(security_policy)
+        {
             opcua_crypto::create_signature_data(
-                pkey,
+                &pkey,
                 security_policy,
                 &request.client_certificate,
                 &request.client_nonce,
@@ -129,10 +207,16 @@ impl SessionManager {
 
         let authentication_token = NodeId::new(0, random::byte_string(32));
         let server_nonce = security_policy.random_nonce();
-        let server_certificate = self.info.server_certificate_as_byte_string();
+        let server_certificate = self
+            .info
+            .server_certificate_for_policy(security_policy)
+            .map(|cert| cert.as_byte_string())
+            .unwrap_or_default();
         let server_endpoints = Some(endpoints);
 
-        let session = Session::create(
+        let (ecdh_key, additional_header) = issue_ecdh_key(&self.info, &request.request_header);
+
+        let mut session = Session::create(
             &self.info,
             authentication_token.clone(),
             channel.secure_channel_id(),
@@ -148,6 +232,7 @@ impl SessionManager {
             request.client_description.clone(),
             channel.security_mode(),
         );
+        session.set_ecdh_key(ecdh_key);
         info!("Created new session with ID {}", session.session_id());
 
         let session_id = session.session_id().clone();
@@ -163,7 +248,10 @@ impl SessionManager {
         self.notify.notify_waiters();
 
         Ok(CreateSessionResponse {
-            response_header: ResponseHeader::new_good(&request.request_header),
+            response_header: ResponseHeader {
+                additional_header,
+                ..ResponseHeader::new_good(&request.request_header)
+            },
             session_id,
             authentication_token,
             revised_session_timeout: session_timeout as f64,
@@ -183,12 +271,12 @@ impl SessionManager {
         client_signature: &SignatureData,
     ) -> Result<(), Error> {
         if let Some(client_certificate) = session.client_certificate() {
-            if let Some(ref server_certificate) = info.server_certificate {
+            if let Some(server_certificate) = info.server_certificate_for_policy(security_policy) {
                 opcua_crypto::verify_signature_data(
                     client_signature,
                     security_policy,
                     client_certificate,
-                    server_certificate,
+                    &server_certificate,
                     session.session_nonce().as_ref(),
                 )?;
                 Ok(())
@@ -294,6 +382,7 @@ pub(crate) async fn close_session(
 pub(crate) async fn activate_session(
     mgr_lck: &RwLock<SessionManager>,
     channel: &mut SecureChannel,
+    transport: EndpointTransport,
     request: &ActivateSessionRequest,
     handler: &mut MessageHandler,
 ) -> Result<ActivateSessionResponse, StatusCode> {
@@ -301,19 +390,29 @@ pub(crate) async fn activate_session(
     let security_mode = channel.security_mode();
     let secure_channel_id = channel.secure_channel_id();
     let server_nonce = security_policy.random_nonce();
-    let (endpoint_url, session_nonce, session_lck, info) = {
+    let (endpoint_url, session_nonce, client_certificate, ecdh_key, session_lck, info) = {
         let mgr = trace_read_lock!(mgr_lck);
         let Some(session_lck) = mgr.find_by_token(&request.request_header.authentication_token)
         else {
             return Err(StatusCode::BadSessionIdInvalid);
         };
 
-        let (endpoint_url, session_nonce) = {
+        let (endpoint_url, session_nonce, client_certificate, ecdh_key) = {
             let session = trace_read_lock!(session_lck);
             session.validate_timed_out()?;
 
             let endpoint_url = session.endpoint_url().to_string();
 
+            // The session may be activated on a different connection than the one it
+            // was created on, which must use the same transport.
+            if !transport.matches_url(&endpoint_url) {
+                error!(
+                    "activate_session, endpoint url {} cannot be used on a {:?} connection",
+                    endpoint_url, transport
+                );
+                return Err(StatusCode::BadTcpEndpointUrlInvalid);
+            }
+
             if !mgr
                 .info
                 .endpoint_exists(&endpoint_url, security_policy, security_mode)
@@ -331,22 +430,39 @@ pub(crate) async fn activate_session(
                     &request.client_signature,
                 )?;
             }
-            (endpoint_url, session.session_nonce().clone())
+            (
+                endpoint_url,
+                session.session_nonce().clone(),
+                session.client_certificate().cloned(),
+                session.ecdh_key(),
+            )
         };
-        (endpoint_url, session_nonce, session_lck, mgr.info.clone())
+        (
+            endpoint_url,
+            session_nonce,
+            client_certificate,
+            ecdh_key,
+            session_lck,
+            mgr.info.clone(),
+        )
     };
 
     let user_token = info
-        .authenticate_endpoint(
+        .authenticate_endpoint_with_keys(
             request,
             &endpoint_url,
             security_policy,
             security_mode,
             request.user_identity_token.clone(),
             &session_nonce,
+            client_certificate.as_ref(),
+            ecdh_key.as_deref(),
         )
         .await?;
 
+    // Each ephemeral key is only used once, so a new one is issued if the client asks for it.
+    let (ecdh_key, additional_header) = issue_ecdh_key(&info, &request.request_header);
+
     let (server_nonce, session_id) = {
         let mut session = trace_write_lock!(session_lck);
 
@@ -363,13 +479,31 @@ pub(crate) async fn activate_session(
         // The standard also mentions that a server may need to
         // "Tear down connections to an underlying system and re-establish them using the new credentials". We need some way to
         // handle this eventuality, perhaps a dedicated node-manager endpoint that can be called here.
+        let identity = IdentityToken::new(request.user_identity_token.clone());
+        let application_uri = session
+            .client_certificate()
+            .and_then(|c| c.application_uri());
+        let mut roles = info.roles.roles_for_session(&SessionRoleInfo {
+            identity: &identity,
+            application_uri: application_uri.as_deref(),
+            endpoint_url: &endpoint_url,
+            security_mode,
+            security_policy_uri: session.security_policy_uri(),
+        });
+        for role in info.authenticator.user_roles(&user_token) {
+            if !roles.contains(&role) {
+                roles.push(role);
+            }
+        }
         session.activate(
             secure_channel_id,
             server_nonce,
-            IdentityToken::new(request.user_identity_token.clone()),
+            identity,
             request.locale_ids.clone(),
             user_token.clone(),
+            roles,
         );
+        session.set_ecdh_key(ecdh_key);
         (
             session.session_nonce().clone(),
             session.session_id_numeric(),
@@ -384,7 +518,10 @@ pub(crate) async fn activate_session(
     // TODO: Audit
 
     Ok(ActivateSessionResponse {
-        response_header: ResponseHeader::new_good(&request.request_header),
+        response_header: ResponseHeader {
+            additional_header,
+            ..ResponseHeader::new_good(&request.request_header)
+        },
         server_nonce,
         results: None,
         diagnostic_infos: None,
@@ -164,6 +164,17 @@ pub(crate) async fn history_read(
     {
         return service_fault!(request, StatusCode::BadTooManyOperations);
     }
+    let aggregate_types = match &details {
+        HistoryReadDetails::Processed(d) => {
+            let aggregate_types = d.aggregate_type.clone().unwrap_or_default();
+            if aggregate_types.len() != items.len() {
+                return service_fault!(request, StatusCode::BadAggregateListMismatch);
+            }
+            Some(aggregate_types)
+        }
+        _ => None,
+    };
+
     let mut nodes: Vec<_> = {
         let mut session = trace_write_lock!(request.session);
         items
@@ -190,6 +201,12 @@ pub(crate) async fn history_read(
             .collect()
     };
 
+    if let Some(aggregate_types) = aggregate_types {
+        for (node, aggregate_type) in nodes.iter_mut().zip(aggregate_types) {
+            node.set_aggregate_type(aggregate_type);
+        }
+    }
+
     // If we are releasing continuation points we should not return any data.
     if request.request.release_continuation_points {
         return Response {
@@ -24,7 +24,14 @@ pub(crate) async fn call(node_managers: NodeManagers, request: Request<CallReque
         let mut owned: Vec<_> = calls
             .iter_mut()
             .filter(|c| {
-                node_manager.owns_node(c.method_id()) && c.status() == StatusCode::BadMethodInvalid
+                // Methods on conditions are called using the method ID from the
+                // condition type, so they are handled by the node manager owning the condition.
+                let target = if request.info.conditions.is_condition_call(c) {
+                    c.object_id()
+                } else {
+                    c.method_id()
+                };
+                node_manager.owns_node(target) && c.status() == StatusCode::BadMethodInvalid
             })
             .collect();
 
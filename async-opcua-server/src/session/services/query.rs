@@ -55,7 +55,7 @@ pub(crate) async fn query_first(
 
     let (filter_result, filter) = {
         let type_tree = context.get_type_tree_for_user();
-        ParsedContentFilter::parse(request.request.filter, type_tree.get(), false, false)
+        ParsedContentFilter::parse(request.request.filter, type_tree.get(), true, true)
     };
 
     let content_filter = match filter {
@@ -89,14 +89,13 @@ pub(crate) async fn query_first(
         content_filter,
         max_data_sets_to_return,
         max_references_to_return,
+        request.info.operational_limits.max_nodes_visited_per_query,
     );
 
     for (index, node_manager) in node_managers.iter().enumerate() {
         context.current_node_manager_index = index;
         // All node managers must succeed. Partial success is really
         // hard to quantify for query...
-        // TODO: This is pretty much impossible to implement
-        // until we actually implement this in the core node manager.
         if let Err(e) = node_manager
             .query(&context, &mut query_request)
             .instrument(debug_span!("Query", node_manager = %node_manager.name()))
@@ -119,6 +118,7 @@ pub(crate) async fn query_first(
         if query_request.is_completed() {
             break;
         }
+        query_request.clear_continuation_point();
     }
     let (result, continuation_point, status) = {
         let mut session = trace_write_lock!(request.session);
@@ -186,6 +186,7 @@ pub(crate) async fn query_next(
         if query_request.is_completed() {
             break;
         }
+        query_request.clear_continuation_point();
     }
 
     let (result, continuation_point, status) = {
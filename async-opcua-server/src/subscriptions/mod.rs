@@ -8,7 +8,7 @@ use chrono::Utc;
 use hashbrown::{Equivalent, HashMap};
 pub use monitored_item::{CreateMonitoredItem, MonitoredItem};
 use opcua_core::{trace_read_lock, trace_write_lock, ResponseMessage};
-use opcua_nodes::{Event, TypeTree};
+use opcua_nodes::{DefaultTypeTree, Event, EventFilterContext, References, TypeTree};
 pub use session_subscriptions::SessionSubscriptions;
 use subscription::TickReason;
 pub use subscription::{MonitoredItemHandle, Subscription, SubscriptionState};
@@ -20,16 +20,18 @@ use opcua_types::{
     AttributeId, CreateSubscriptionRequest, CreateSubscriptionResponse, DataEncoding, DataValue,
     DateTimeUtc, MessageSecurityMode, ModifySubscriptionRequest, ModifySubscriptionResponse,
     MonitoredItemCreateResult, MonitoredItemModifyRequest, MonitoringMode, NodeId,
-    NotificationMessage, NumericRange, ObjectId, PublishRequest, RepublishRequest,
-    RepublishResponse, ResponseHeader, SetPublishingModeRequest, SetPublishingModeResponse,
-    StatusCode, TimestampsToReturn, TransferResult, TransferSubscriptionsRequest,
-    TransferSubscriptionsResponse,
+    NotificationMessage, NumericRange, ObjectId, ObjectTypeId, PermissionType, PublishRequest,
+    QualifiedName, RepublishRequest, RepublishResponse, ResponseHeader, RolePermissionType,
+    SetPublishingModeRequest, SetPublishingModeResponse, StatusCode, TimestampsToReturn,
+    TransferResult, TransferSubscriptionsRequest, TransferSubscriptionsResponse, Variant,
 };
 
 use super::{
+    address_space::AddressSpace,
     authenticator::UserToken,
     info::ServerInfo,
     node_manager::{MonitoredItemRef, MonitoredItemUpdateRef, RequestContext, ServerContext},
+    roles::permissions_for_roles,
     session::instance::Session,
     SubscriptionLimits,
 };
@@ -58,6 +60,50 @@ struct MonitoredItemEntry {
     index_range: NumericRange,
 }
 
+/// Where the source nodes of reported events can be found.
+#[derive(Clone, Copy)]
+enum EventSource<'a> {
+    None,
+    References(&'a References),
+    AddressSpace(&'a AddressSpace),
+}
+
+impl<'a> EventSource<'a> {
+    fn references(&self) -> Option<&'a References> {
+        match self {
+            EventSource::None => None,
+            EventSource::References(r) => Some(r),
+            EventSource::AddressSpace(a) => Some(a.references()),
+        }
+    }
+
+    /// Get the role permissions that must all grant `ReceiveEvents` to a session
+    /// for it to receive `event`.
+    fn event_permissions(
+        &self,
+        event: &dyn Event,
+        notifier: &NodeId,
+    ) -> Vec<&'a [RolePermissionType]> {
+        let EventSource::AddressSpace(address_space) = self else {
+            return Vec::new();
+        };
+        let source = match event.get_field(
+            &ObjectTypeId::BaseEventType.into(),
+            AttributeId::Value,
+            &NumericRange::None,
+            &[QualifiedName::new(0, "SourceNode")],
+        ) {
+            Variant::NodeId(id) => Some(*id),
+            _ => None,
+        };
+        [Some(notifier), source.as_ref()]
+            .into_iter()
+            .flatten()
+            .filter_map(|id| address_space.find(id)?.as_node().role_permissions())
+            .collect()
+    }
+}
+
 struct SubscriptionCacheInner {
     /// Map from session ID to subscription cache
     session_subscriptions: HashMap<u32, Arc<Mutex<SessionSubscriptions>>>,
@@ -77,10 +123,12 @@ pub struct SubscriptionCache {
     inner: RwLock<SubscriptionCacheInner>,
     /// Configured limits on subscriptions.
     limits: SubscriptionLimits,
+    /// Global type tree, used when evaluating event filters.
+    type_tree: Arc<RwLock<DefaultTypeTree>>,
 }
 
 impl SubscriptionCache {
-    pub(crate) fn new(limits: SubscriptionLimits) -> Self {
+    pub(crate) fn new(limits: SubscriptionLimits, type_tree: Arc<RwLock<DefaultTypeTree>>) -> Self {
         Self {
             inner: RwLock::new(SubscriptionCacheInner {
                 session_subscriptions: HashMap::new(),
@@ -88,6 +136,7 @@ impl SubscriptionCache {
                 monitored_items: HashMap::new(),
             }),
             limits,
+            type_tree,
         }
     }
 
@@ -394,10 +443,54 @@ impl SubscriptionCache {
 
     /// Notify listening clients to events. Without a custom node manager implementing
     /// event history, this is the only way to report events in the server.
+    ///
+    /// The `InView` and `RelatedTo` operators in event filters never match events reported
+    /// this way, use [SubscriptionCache::notify_events_with_references] to support them.
     pub fn notify_events<'a>(&self, items: impl Iterator<Item = (&'a dyn Event, &'a NodeId)>) {
+        self.notify_events_inner(items, EventSource::None);
+    }
+
+    /// Notify listening clients to events, like [SubscriptionCache::notify_events].
+    ///
+    /// `references` should be the references of the address space containing the
+    /// source nodes of the events, and are used to evaluate the `InView` and `RelatedTo`
+    /// operators in event filters.
+    pub fn notify_events_with_references<'a>(
+        &self,
+        items: impl Iterator<Item = (&'a dyn Event, &'a NodeId)>,
+        references: &References,
+    ) {
+        self.notify_events_inner(items, EventSource::References(references));
+    }
+
+    /// Notify listening clients to events raised by nodes in `address_space`, like
+    /// [SubscriptionCache::notify_events_with_references].
+    ///
+    /// Events are only delivered to sessions that have the `ReceiveEvents` permission
+    /// on both the source node of the event and the notifier, if these are found in
+    /// `address_space` and have role permissions.
+    pub fn notify_events_from_address_space<'a>(
+        &self,
+        items: impl Iterator<Item = (&'a dyn Event, &'a NodeId)>,
+        address_space: &AddressSpace,
+    ) {
+        self.notify_events_inner(items, EventSource::AddressSpace(address_space));
+    }
+
+    fn notify_events_inner<'a>(
+        &self,
+        items: impl Iterator<Item = (&'a dyn Event, &'a NodeId)>,
+        source: EventSource<'_>,
+    ) {
+        let type_tree = trace_read_lock!(self.type_tree);
+        let context = EventFilterContext {
+            type_tree: &*type_tree,
+            references: source.references(),
+        };
         let lck = trace_read_lock!(self.inner);
         let mut by_subscription = HashMap::<u32, Vec<_>>::new();
         for (evt, notifier) in items {
+            let permissions = Arc::new(source.event_permissions(evt, notifier));
             let notifier_key = MonitoredItemKeyRef {
                 id: notifier,
                 attribute_id: AttributeId::EventNotifier,
@@ -410,7 +503,7 @@ impl SubscriptionCache {
                     by_subscription
                         .entry(handle.subscription_id)
                         .or_default()
-                        .push((*handle, evt));
+                        .push((*handle, evt, permissions.clone()));
                 }
             }
             // The server gets all notifications.
@@ -430,7 +523,7 @@ impl SubscriptionCache {
                     by_subscription
                         .entry(handle.subscription_id)
                         .or_default()
-                        .push((*handle, evt));
+                        .push((*handle, evt, permissions.clone()));
                 }
             }
         }
@@ -442,8 +535,29 @@ impl SubscriptionCache {
             let Some(cache) = lck.session_subscriptions.get(session_id) else {
                 continue;
             };
+            let items = if items.iter().any(|(_, _, p)| !p.is_empty()) {
+                // Read the roles without holding the lock on the subscriptions, the
+                // session is locked while calling into the subscription cache elsewhere.
+                let session = cache.lock().session().clone();
+                let session = trace_read_lock!(session);
+                items
+                    .into_iter()
+                    .filter(|(_, _, p)| {
+                        p.iter().all(|rp| {
+                            permissions_for_roles(rp, session.roles())
+                                .contains(PermissionType::ReceiveEvents)
+                        })
+                    })
+                    .map(|(handle, evt, _)| (handle, evt))
+                    .collect()
+            } else {
+                items
+                    .into_iter()
+                    .map(|(handle, evt, _)| (handle, evt))
+                    .collect()
+            };
             let mut cache_lck = cache.lock();
-            cache_lck.notify_events(items);
+            cache_lck.notify_events(items, context);
         }
     }
 
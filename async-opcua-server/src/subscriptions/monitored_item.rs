@@ -1,15 +1,22 @@
 use std::collections::{BTreeSet, VecDeque};
 
-use opcua_nodes::{Event, ParsedEventFilter, TypeTree};
+use opcua_nodes::{Event, EventFilterContext, ParsedEventFilter, TypeTree};
 use tracing::error;
 
 use super::MonitoredItemHandle;
-use crate::{info::ServerInfo, node_manager::ParsedReadValueId};
+use crate::{
+    config::SubscriptionLimits,
+    info::ServerInfo,
+    node_manager::{
+        calculate_aggregate, effective_aggregate_configuration, AggregateType, ParsedReadValueId,
+    },
+};
 use opcua_types::{
-    match_extension_object_owned, DataChangeFilter, DataValue, DateTime, EventFieldList,
-    EventFilter, EventFilterResult, ExtensionObject, MonitoredItemCreateRequest,
-    MonitoredItemModifyRequest, MonitoredItemNotification, MonitoringMode, NumericRange,
-    ParsedDataChangeFilter, StatusCode, TimestampsToReturn, Variant,
+    match_extension_object_owned, AggregateConfiguration, AggregateFilter, AggregateFilterResult,
+    DataChangeFilter, DataValue, DateTime, EventFieldList, EventFilter, EventFilterResult,
+    ExtensionObject, MonitoredItemCreateRequest, MonitoredItemModifyRequest,
+    MonitoredItemNotification, MonitoringMode, NumericRange, ParsedDataChangeFilter, StatusCode,
+    TimestampsToReturn, Variant,
 };
 
 #[derive(Debug, Clone, PartialEq)]
@@ -30,22 +37,128 @@ impl From<EventFieldList> for Notification {
     }
 }
 
+#[derive(Debug, Clone)]
+/// Parsed aggregate filter for a monitored item.
+pub struct ParsedAggregateFilter {
+    /// Aggregate to compute for each processing interval.
+    pub aggregate: AggregateType,
+    /// Start of the first processing interval. Intervals are aligned to this time.
+    pub start_time: DateTime,
+    /// Length of each processing interval in milliseconds.
+    pub processing_interval: f64,
+    /// Effective aggregate configuration.
+    pub config: AggregateConfiguration,
+    /// Maximum number of raw samples kept while computing the aggregate.
+    pub max_samples: usize,
+}
+
+impl ParsedAggregateFilter {
+    /// Parse an aggregate filter, returning the revised filter parameters
+    /// along with the parsed filter. The processing interval is revised to be no
+    /// smaller than `sampling_interval` and no larger than the limit in `limits`.
+    /// The start time is revised to the start of the current processing interval if
+    /// it is in the past, and to at most one processing interval from now.
+    pub fn parse(
+        filter: AggregateFilter,
+        sampling_interval: f64,
+        limits: &SubscriptionLimits,
+    ) -> (AggregateFilterResult, Result<Self, StatusCode>) {
+        let processing_interval = filter
+            .processing_interval
+            .max(sampling_interval)
+            .min(limits.max_aggregate_processing_interval_ms);
+        let config = effective_aggregate_configuration(&filter.aggregate_configuration);
+
+        let now = DateTime::now();
+        // Ticks are 100 nanoseconds, the processing interval is in milliseconds.
+        let step = (processing_interval * 10_000.0) as i64;
+        let start_time = if filter.start_time.is_null() {
+            now
+        } else if step > 0 && filter.start_time < now {
+            let start = filter.start_time.checked_ticks();
+            let elapsed = now.checked_ticks() - start;
+            DateTime::from(start + elapsed / step * step)
+        } else {
+            let latest = DateTime::from(now.checked_ticks() + step.max(0));
+            filter.start_time.min(latest)
+        };
+
+        let res = AggregateFilterResult {
+            revised_start_time: start_time,
+            revised_processing_interval: processing_interval,
+            revised_aggregate_configuration: config.clone(),
+        };
+
+        let Some(aggregate) = AggregateType::from_node_id(&filter.aggregate_type) else {
+            return (res, Err(StatusCode::BadAggregateNotSupported));
+        };
+        if step <= 0 {
+            return (res, Err(StatusCode::BadMonitoredItemFilterInvalid));
+        }
+        if config.percent_data_bad > 100 || config.percent_data_good > 100 {
+            return (res, Err(StatusCode::BadAggregateConfigurationRejected));
+        }
+
+        (
+            res,
+            Ok(Self {
+                aggregate,
+                start_time,
+                processing_interval,
+                config,
+                max_samples: limits.max_aggregate_samples,
+            }),
+        )
+    }
+}
+
 #[derive(Debug, Clone)]
 /// Parsed filter type for a monitored item.
 pub enum FilterType {
     None,
     DataChangeFilter(ParsedDataChangeFilter),
     EventFilter(ParsedEventFilter),
+    AggregateFilter(ParsedAggregateFilter),
 }
 
 impl FilterType {
     /// Try to create a filter from an extension object, returning
     /// an `EventFilterResult` if the filter is for events.
+    ///
+    /// Aggregate filters are parsed without a sampling interval and with the default
+    /// subscription limits, use [FilterType::from_filter_with_sampling_interval] to get
+    /// the `AggregateFilterResult`.
     pub fn from_filter(
         filter: ExtensionObject,
         eu_range: Option<(f64, f64)>,
         type_tree: &dyn TypeTree,
     ) -> (Option<EventFilterResult>, Result<FilterType, StatusCode>) {
+        let (res, filter) = Self::from_filter_with_sampling_interval(
+            filter,
+            eu_range,
+            0.0,
+            &SubscriptionLimits::default(),
+            type_tree,
+        );
+        (
+            res.and_then(|r| r.into_inner_as::<EventFilterResult>())
+                .map(|r| *r),
+            filter,
+        )
+    }
+
+    /// Try to create a filter from an extension object, returning
+    /// a filter result if the filter is for events or aggregates.
+    ///
+    /// `sampling_interval` is the revised sampling interval of the monitored item, and
+    /// `limits` are the subscription limits of the server.
+    pub fn from_filter_with_sampling_interval(
+        filter: ExtensionObject,
+        eu_range: Option<(f64, f64)>,
+        sampling_interval: f64,
+        limits: &SubscriptionLimits,
+        type_tree: &dyn TypeTree,
+    ) -> (Option<ExtensionObject>, Result<FilterType, StatusCode>) {
         // Check if the filter is a supported filter type
         if filter.is_null() {
             return (None, Ok(FilterType::None));
@@ -58,7 +171,11 @@ impl FilterType {
             },
             v: EventFilter => {
                 let (res, filter_res) = ParsedEventFilter::new(v, type_tree);
-                (Some(res), filter_res.map(FilterType::EventFilter))
+                (Some(ExtensionObject::from_message(res)), filter_res.map(FilterType::EventFilter))
+            },
+            v: AggregateFilter => {
+                let (res, filter_res) = ParsedAggregateFilter::parse(v, sampling_interval, limits);
+                (Some(ExtensionObject::from_message(res)), filter_res.map(FilterType::AggregateFilter))
             },
             _ => {
                 error!(
@@ -89,7 +206,7 @@ pub struct CreateMonitoredItem {
     initial_value: Option<DataValue>,
     status_code: StatusCode,
     filter: FilterType,
-    filter_res: Option<EventFilterResult>,
+    filter_res: Option<ExtensionObject>,
     timestamps_to_return: TimestampsToReturn,
     eu_range: Option<(f64, f64)>,
 }
@@ -145,10 +262,15 @@ impl CreateMonitoredItem {
         type_tree: &dyn TypeTree,
         eu_range: Option<(f64, f64)>,
     ) -> Self {
-        let (filter_res, filter) =
-            FilterType::from_filter(req.requested_parameters.filter, eu_range, type_tree);
         let sampling_interval =
             sanitize_sampling_interval(info, req.requested_parameters.sampling_interval);
+        let (filter_res, filter) = FilterType::from_filter_with_sampling_interval(
+            req.requested_parameters.filter,
+            eu_range,
+            sampling_interval,
+            &info.config.limits.subscriptions,
+            type_tree,
+        );
         let queue_size = sanitize_queue_size(info, req.requested_parameters.queue_size as usize);
 
         let (filter, mut status) = match filter {
@@ -258,11 +380,97 @@ impl CreateMonitoredItem {
         self.status_code
     }
 
-    pub(crate) fn filter_res(&self) -> Option<&EventFilterResult> {
+    pub(crate) fn filter_res(&self) -> Option<&ExtensionObject> {
         self.filter_res.as_ref()
     }
 }
 
+#[derive(Debug)]
+/// Raw samples collected for a monitored item with an aggregate filter.
+struct AggregateState {
+    // Start of the processing interval currently being collected.
+    interval_start: DateTime,
+    // Samples sorted by timestamp. This includes the last sample before
+    // `interval_start`, which is needed for interpolation.
+    samples: VecDeque<DataValue>,
+    // Maximum number of samples, the oldest samples are dropped beyond this.
+    max_samples: usize,
+}
+
+impl AggregateState {
+    fn new(filter: &FilterType, mut samples: VecDeque<DataValue>) -> Option<Self> {
+        let FilterType::AggregateFilter(filter) = filter else {
+            return None;
+        };
+        let excess = samples.len().saturating_sub(filter.max_samples);
+        samples.drain(..excess);
+        Some(Self {
+            interval_start: filter.start_time,
+            samples,
+            max_samples: filter.max_samples,
+        })
+    }
+
+    fn insert(&mut self, mut value: DataValue) {
+        if value.source_timestamp.is_none() {
+            value.source_timestamp = Some(value.server_timestamp.unwrap_or_else(DateTime::now));
+        }
+        let ts = value.source_timestamp;
+        let idx = self.samples.partition_point(|v| v.source_timestamp <= ts);
+        self.samples.insert(idx, value);
+        if self.samples.len() > self.max_samples {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Compute the aggregate for each processing interval that ended at or before `now`.
+    fn close_intervals(
+        &mut self,
+        filter: &ParsedAggregateFilter,
+        now: DateTime,
+        max_intervals: usize,
+    ) -> Vec<DataValue> {
+        let step = (filter.processing_interval * 10_000.0) as i64;
+        let start = self.interval_start.checked_ticks();
+        let count = (now.checked_ticks() - start) / step;
+        if count <= 0 {
+            return Vec::new();
+        }
+        // Intervals that would be dropped from the queue anyway are skipped.
+        let skip = (count - (max_intervals as i64).min(count)).max(0);
+        let mut result = Vec::with_capacity((count - skip) as usize);
+        let samples = self.samples.make_contiguous();
+        for i in skip..count {
+            let interval_start = DateTime::from(start + i * step);
+            let interval_end = DateTime::from(start + (i + 1) * step);
+            result.extend(
+                calculate_aggregate(
+                    samples,
+                    filter.aggregate,
+                    interval_start,
+                    interval_end,
+                    0.0,
+                    &filter.config,
+                    1,
+                )
+                .unwrap_or_default(),
+            );
+        }
+        self.interval_start = DateTime::from(start + count * step);
+
+        // Drop samples that are no longer needed, keeping the last one before the new interval.
+        let interval_start = Some(self.interval_start);
+        let before = self
+            .samples
+            .partition_point(|v| v.source_timestamp < interval_start);
+        if before > 1 {
+            self.samples.drain(..before - 1);
+        }
+
+        result
+    }
+}
+
 #[derive(Debug)]
 /// State of an active monitored item on the server.
 pub struct MonitoredItem {
@@ -283,6 +491,7 @@ pub struct MonitoredItem {
     last_data_value: Option<DataValue>,
     any_new_notification: bool,
     eu_range: Option<(f64, f64)>,
+    aggregate_state: Option<AggregateState>,
 }
 
 impl MonitoredItem {
@@ -303,6 +512,7 @@ impl MonitoredItem {
             queue_overflow: false,
             any_new_notification: false,
             eu_range: request.eu_range,
+            aggregate_state: AggregateState::new(&request.filter, VecDeque::new()),
         };
         if let Some(val) = request.initial_value.as_ref() {
             v.notify_data_value(val.clone());
@@ -328,19 +538,29 @@ impl MonitoredItem {
         timestamps_to_return: TimestampsToReturn,
         request: &MonitoredItemModifyRequest,
         type_tree: &dyn TypeTree,
-    ) -> (Option<EventFilterResult>, StatusCode) {
+    ) -> (Option<ExtensionObject>, StatusCode) {
         self.timestamps_to_return = timestamps_to_return;
-        let (filter_res, filter) = FilterType::from_filter(
+        let sampling_interval =
+            sanitize_sampling_interval(info, request.requested_parameters.sampling_interval);
+        let (filter_res, filter) = FilterType::from_filter_with_sampling_interval(
             request.requested_parameters.filter.clone(),
             self.eu_range,
+            sampling_interval,
+            &info.config.limits.subscriptions,
             type_tree,
         );
         self.filter = match filter {
             Ok(f) => f,
             Err(e) => return (filter_res, e),
         };
-        self.sampling_interval =
-            sanitize_sampling_interval(info, request.requested_parameters.sampling_interval);
+        // Keep any samples collected so far, they may still be needed for interpolation.
+        let samples = self
+            .aggregate_state
+            .take()
+            .map(|s| s.samples)
+            .unwrap_or_default();
+        self.aggregate_state = AggregateState::new(&self.filter, samples);
+        self.sampling_interval = sampling_interval;
         self.queue_size =
             sanitize_queue_size(info, request.requested_parameters.queue_size as usize);
         self.client_handle = request.requested_parameters.client_handle;
@@ -406,6 +626,19 @@ impl MonitoredItem {
             }
         }
 
+        if let Some(state) = &mut self.aggregate_state {
+            // Values are only reported once the processing interval is complete.
+            if value.status() == StatusCode::BadWaitingForInitialData {
+                return false;
+            }
+            let now = value
+                .source_timestamp
+                .or(value.server_timestamp)
+                .unwrap_or_else(DateTime::now);
+            state.insert(value);
+            return self.tick_aggregate(now);
+        }
+
         let data_change = match (&self.last_data_value, &self.filter) {
             (Some(last_dv), FilterType::DataChangeFilter(filter)) => {
                 filter.is_changed(&value, last_dv)
@@ -423,7 +656,49 @@ impl MonitoredItem {
         }
 
         self.last_data_value = Some(value.clone());
+        self.filter_timestamps(&mut value);
+
+        let client_handle = self.client_handle;
+        self.enqueue_notification(MonitoredItemNotification {
+            client_handle,
+            value,
+        });
+
+        true
+    }
+
+    /// Close any processing intervals of an aggregate filter that ended at or
+    /// before `now`, and enqueue the aggregated values. Returns `true` if any
+    /// values were enqueued.
+    pub(super) fn tick_aggregate(&mut self, now: DateTime) -> bool {
+        if self.monitoring_mode == MonitoringMode::Disabled {
+            return false;
+        }
+        let (FilterType::AggregateFilter(filter), Some(state)) =
+            (&self.filter, &mut self.aggregate_state)
+        else {
+            return false;
+        };
 
+        let values = state.close_intervals(filter, now, self.queue_size);
+        if values.is_empty() {
+            return false;
+        }
+
+        for mut value in values {
+            self.last_data_value = Some(value.clone());
+            self.filter_timestamps(&mut value);
+            let client_handle = self.client_handle;
+            self.enqueue_notification(MonitoredItemNotification {
+                client_handle,
+                value,
+            });
+        }
+
+        true
+    }
+
+    fn filter_timestamps(&self, value: &mut DataValue) {
         match self.timestamps_to_return {
             TimestampsToReturn::Neither | TimestampsToReturn::Invalid => {
                 value.source_timestamp = None;
@@ -443,17 +718,13 @@ impl MonitoredItem {
                 // DO NOTHING
             }
         }
-
-        let client_handle = self.client_handle;
-        self.enqueue_notification(MonitoredItemNotification {
-            client_handle,
-            value,
-        });
-
-        true
     }
 
-    pub(super) fn notify_event(&mut self, event: &dyn Event) -> bool {
+    pub(super) fn notify_event(
+        &mut self,
+        event: &dyn Event,
+        context: EventFilterContext<'_>,
+    ) -> bool {
         if self.monitoring_mode == MonitoringMode::Disabled {
             return false;
         }
@@ -462,7 +733,7 @@ impl MonitoredItem {
             return false;
         };
 
-        let Some(notif) = filter.evaluate(event, self.client_handle) else {
+        let Some(notif) = filter.evaluate_in_context(event, context, self.client_handle) else {
             return false;
         };
 
@@ -615,14 +886,18 @@ impl MonitoredItem {
 pub(super) mod tests {
     use chrono::{Duration, Utc};
 
-    use crate::{node_manager::ParsedReadValueId, subscriptions::monitored_item::Notification};
+    use crate::{
+        config::SubscriptionLimits,
+        node_manager::{AggregateType, ParsedReadValueId},
+        subscriptions::monitored_item::Notification,
+    };
     use opcua_types::{
-        AttributeId, DataChangeFilter, DataChangeTrigger, DataValue, DateTime, Deadband,
-        DeadbandType, MonitoringMode, NodeId, ParsedDataChangeFilter, ReadValueId, StatusCode,
-        Variant,
+        AggregateConfiguration, AggregateFilter, AttributeId, DataChangeFilter, DataChangeTrigger,
+        DataValue, DateTime, Deadband, DeadbandType, MonitoringMode, NodeId, ObjectId,
+        ParsedDataChangeFilter, ReadValueId, StatusCode, Variant,
     };
 
-    use super::{FilterType, MonitoredItem};
+    use super::{AggregateState, FilterType, MonitoredItem, ParsedAggregateFilter};
 
     pub(crate) fn new_monitored_item(
         id: u32,
@@ -640,7 +915,6 @@ pub(super) mod tests {
             triggered_items: Default::default(),
             client_handle: Default::default(),
             sampling_interval,
-            filter,
             discard_oldest,
             queue_size: 10,
             notification_queue: Default::default(),
@@ -649,6 +923,8 @@ pub(super) mod tests {
             last_data_value: None,
             any_new_notification: false,
             eu_range: None,
+            aggregate_state: AggregateState::new(&filter, Default::default()),
+            filter,
         };
 
         if let Some(val) = initial_value {
@@ -876,4 +1152,174 @@ pub(super) mod tests {
             }
         }
     }
+
+    #[test]
+    fn aggregate_filter_parse() {
+        let start = DateTime::now() - Duration::try_milliseconds(2500).unwrap();
+        let (res, filter) = ParsedAggregateFilter::parse(
+            AggregateFilter {
+                start_time: start,
+                aggregate_type: ObjectId::AggregateFunction_Average.into(),
+                processing_interval: 1000.0,
+                aggregate_configuration: AggregateConfiguration {
+                    use_server_capabilities_defaults: true,
+                    ..Default::default()
+                },
+            },
+            100.0,
+            &SubscriptionLimits::default(),
+        );
+        let filter = filter.unwrap();
+        // Start time is moved to the start of the current interval.
+        assert_eq!(
+            res.revised_start_time,
+            start + Duration::try_seconds(2).unwrap()
+        );
+        assert_eq!(filter.start_time, res.revised_start_time);
+        assert_eq!(res.revised_processing_interval, 1000.0);
+        assert_eq!(res.revised_aggregate_configuration.percent_data_good, 100);
+
+        // Processing interval is revised to the sampling interval.
+        let (res, _) = ParsedAggregateFilter::parse(
+            AggregateFilter {
+                start_time: start,
+                aggregate_type: ObjectId::AggregateFunction_Average.into(),
+                processing_interval: 10.0,
+                aggregate_configuration: Default::default(),
+            },
+            100.0,
+            &SubscriptionLimits::default(),
+        );
+        assert_eq!(res.revised_processing_interval, 100.0);
+
+        // Start time in the future is revised to at most one interval from now,
+        // and the processing interval is limited by the server.
+        let limits = SubscriptionLimits {
+            max_aggregate_processing_interval_ms: 5000.0,
+            ..Default::default()
+        };
+        let (res, _) = ParsedAggregateFilter::parse(
+            AggregateFilter {
+                start_time: DateTime::now() + Duration::try_days(365).unwrap(),
+                aggregate_type: ObjectId::AggregateFunction_Average.into(),
+                processing_interval: 1e12,
+                aggregate_configuration: Default::default(),
+            },
+            100.0,
+            &limits,
+        );
+        assert_eq!(res.revised_processing_interval, 5000.0);
+        assert!(res.revised_start_time <= DateTime::now() + Duration::try_seconds(5).unwrap());
+
+        let (_, filter) = ParsedAggregateFilter::parse(
+            AggregateFilter {
+                start_time: start,
+                aggregate_type: ObjectId::AggregateFunction_StandardDeviationPopulation.into(),
+                processing_interval: 1000.0,
+                aggregate_configuration: Default::default(),
+            },
+            100.0,
+            &SubscriptionLimits::default(),
+        );
+        assert_eq!(filter.unwrap_err(), StatusCode::BadAggregateNotSupported);
+
+        let (_, filter) = ParsedAggregateFilter::parse(
+            AggregateFilter {
+                start_time: start,
+                aggregate_type: ObjectId::AggregateFunction_Average.into(),
+                processing_interval: 1000.0,
+                aggregate_configuration: AggregateConfiguration {
+                    percent_data_good: 150,
+                    ..Default::default()
+                },
+            },
+            100.0,
+            &SubscriptionLimits::default(),
+        );
+        assert_eq!(
+            filter.unwrap_err(),
+            StatusCode::BadAggregateConfigurationRejected
+        );
+    }
+
+    #[test]
+    fn monitored_item_aggregate() {
+        let start = DateTime::from(Utc::now());
+        let at = |ms: i64| start + Duration::try_milliseconds(ms).unwrap();
+        let mut item = new_monitored_item(
+            1,
+            ReadValueId {
+                node_id: NodeId::null(),
+                attribute_id: AttributeId::Value as u32,
+                ..Default::default()
+            },
+            MonitoringMode::Reporting,
+            FilterType::AggregateFilter(ParsedAggregateFilter {
+                aggregate: AggregateType::Average,
+                start_time: start,
+                processing_interval: 1000.0,
+                config: AggregateConfiguration {
+                    use_server_capabilities_defaults: true,
+                    ..Default::default()
+                },
+                max_samples: 1000,
+            }),
+            100.0,
+            true,
+            Some(DataValue::new_at(1.0, start)),
+        );
+        // Nothing is reported until the first interval is complete.
+        assert!(!item.has_notifications());
+        assert!(!item.notify_data_value(DataValue::new_at(3.0, at(500))));
+        assert!(!item.has_notifications());
+
+        // A value in the next interval closes the first one.
+        assert!(item.notify_data_value(DataValue::new_at(5.0, at(1000))));
+        assert_eq!(item.notification_queue.len(), 1);
+
+        // Ticking closes intervals without new values.
+        assert!(!item.tick_aggregate(at(1500)));
+        assert!(item.tick_aggregate(at(3000)));
+        assert_eq!(item.notification_queue.len(), 3);
+
+        let values: Vec<_> = item
+            .notification_queue
+            .drain(..)
+            .map(|n| {
+                let Notification::MonitoredItemNotification(n) = n else {
+                    panic!("Wrong notification type");
+                };
+                n.value
+            })
+            .collect();
+        assert_eq!(values[0].value, Some(Variant::Double(2.0)));
+        assert_eq!(values[0].source_timestamp, Some(start));
+        assert_eq!(values[1].value, Some(Variant::Double(5.0)));
+        assert_eq!(values[1].source_timestamp, Some(at(1000)));
+        // No raw values in the last interval.
+        assert_eq!(values[2].status(), StatusCode::BadNoData);
+        assert_eq!(values[2].source_timestamp, Some(at(2000)));
+    }
+
+    #[test]
+    fn aggregate_state_max_samples() {
+        let start = DateTime::from(Utc::now());
+        let filter = FilterType::AggregateFilter(ParsedAggregateFilter {
+            aggregate: AggregateType::Average,
+            start_time: start + Duration::try_days(1).unwrap(),
+            processing_interval: 1000.0,
+            config: AggregateConfiguration::default(),
+            max_samples: 10,
+        });
+        let mut state = AggregateState::new(&filter, Default::default()).unwrap();
+        for i in 0..100 {
+            state.insert(DataValue::new_at(
+                i as f64,
+                start + Duration::try_milliseconds(i).unwrap(),
+            ));
+        }
+        // Only the newest samples are kept.
+        assert_eq!(state.samples.len(), 10);
+        assert_eq!(state.samples[0].value, Some(Variant::Double(90.0)));
+    }
 }
@@ -4,7 +4,7 @@ use std::{
 };
 
 use opcua_core::handle::Handle;
-use opcua_nodes::Event;
+use opcua_nodes::{Event, EventFilterContext};
 use opcua_types::{DataValue, DateTime, DateTimeUtc, NotificationMessage, StatusCode};
 use tracing::{debug, trace, warn};
 
@@ -228,10 +228,11 @@ impl Subscription {
         }
     }
 
-    /// Notify the given monitored item of a new event.
-    pub fn notify_event(&mut self, id: &u32, event: &dyn Event) {
+    /// Notify the given monitored item of a new event. `context` is used
+    /// to evaluate the event filter of the monitored item.
+    pub fn notify_event(&mut self, id: &u32, event: &dyn Event, context: EventFilterContext<'_>) {
         if let Some(item) = self.monitored_items.get_mut(id) {
-            if item.notify_event(event) {
+            if item.notify_event(event, context) {
                 self.notified_monitored_items.insert(*id);
             }
         }
@@ -485,6 +486,15 @@ impl Subscription {
         if matches!(tick_reason, TickReason::TickTimerFired) && !publishing_interval_elapsed {
             return TickResult::None;
         }
+
+        // Close any finished processing intervals for items with aggregate filters.
+        let now_dt = DateTime::from(*now);
+        for (id, item) in self.monitored_items.iter_mut() {
+            if item.tick_aggregate(now_dt) {
+                self.notified_monitored_items.insert(*id);
+            }
+        }
+
         // First, get the actual state transition we're in.
         let transition = self.get_state_transition(
             tick_reason,
@@ -0,0 +1,153 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2024 Adam Lock
+
+use std::future::Future;
+
+use opcua_core::comms::stream::BoxedStream;
+use tokio::{
+    io::DuplexStream,
+    net::TcpListener,
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+};
+
+/// Trait for types accepting incoming connections to the server.
+///
+/// Each accepted stream is handed to the same secure channel and session machinery
+/// as TCP connections, so any bidirectional byte stream carrying OPC UA binary messages
+/// can be used, such as a Unix domain socket, an in-memory pipe, or a TLS tunnel
+/// terminated by the listener.
+///
+/// Notes for implementors:
+///
+///  - This deals with accepting the connection only, the server performs the
+///    `HELLO`/`ACKNOWLEDGE` handshake on the returned stream.
+///  - [`Listener::accept`] _must_ be cancellation safe, since it is polled in a loop
+///    together with the rest of the server.
+///  - If any endpoint uses `opc.ws` or `opc.wss`, the server detects WebSocket and TLS
+///    connections from the first bytes of the stream, as it does for TCP.
+pub trait Listener: Send + 'static {
+    /// Wait for the next incoming connection, returning the stream and
+    /// a description of the remote peer, used for logging.
+    fn accept(&mut self) -> impl Future<Output = std::io::Result<(BoxedStream, String)>> + Send;
+
+    /// Description of where the listener accepts connections, used for logging.
+    fn describe(&self) -> String;
+
+    /// The port the listener is bound to, if it is a network listener.
+    /// This is used in endpoint URLs. If this is `None`, the configured port is used instead.
+    fn port(&self) -> Option<u16> {
+        None
+    }
+}
+
+impl Listener for TcpListener {
+    async fn accept(&mut self) -> std::io::Result<(BoxedStream, String)> {
+        let (socket, addr) = TcpListener::accept(self).await?;
+        Ok((Box::new(socket), addr.to_string()))
+    }
+
+    fn describe(&self) -> String {
+        match self.local_addr() {
+            Ok(addr) => addr.to_string(),
+            Err(e) => format!("unknown address ({e})"),
+        }
+    }
+
+    fn port(&self) -> Option<u16> {
+        self.local_addr().ok().map(|a| a.port())
+    }
+}
+
+#[cfg(unix)]
+impl Listener for tokio::net::UnixListener {
+    async fn accept(&mut self) -> std::io::Result<(BoxedStream, String)> {
+        let (socket, addr) = tokio::net::UnixListener::accept(self).await?;
+        let peer = match addr.as_pathname() {
+            Some(path) => path.display().to_string(),
+            None => "unnamed unix socket".to_owned(),
+        };
+        Ok((Box::new(socket), peer))
+    }
+
+    fn describe(&self) -> String {
+        match self.local_addr() {
+            Ok(addr) => match addr.as_pathname() {
+                Some(path) => format!("unix:{}", path.display()),
+                None => "unnamed unix socket".to_owned(),
+            },
+            Err(e) => format!("unknown unix socket ({e})"),
+        }
+    }
+}
+
+/// Listener accepting in-memory connections opened through a [`DuplexListenerHandle`].
+///
+/// This lets a client and server in the same process communicate without
+/// opening any sockets, which is mostly useful for testing.
+pub struct DuplexListener {
+    incoming: UnboundedReceiver<DuplexStream>,
+    count: u64,
+}
+
+/// Handle used to open connections to a [`DuplexListener`].
+#[derive(Clone)]
+pub struct DuplexListenerHandle {
+    incoming: UnboundedSender<DuplexStream>,
+    max_buf_size: usize,
+}
+
+impl DuplexListener {
+    /// Create a new in-memory listener, and a handle used to connect to it.
+    /// `max_buf_size` is the number of bytes each direction of a connection can
+    /// buffer before writes wait for the other side to read.
+    pub fn new(max_buf_size: usize) -> (Self, DuplexListenerHandle) {
+        let (send, recv) = unbounded_channel();
+        (
+            Self {
+                incoming: recv,
+                count: 0,
+            },
+            DuplexListenerHandle {
+                incoming: send,
+                max_buf_size,
+            },
+        )
+    }
+}
+
+impl DuplexListenerHandle {
+    /// Open a new connection to the listener, returning the client end of the stream.
+    /// This fails if the listener has been dropped.
+    pub fn connect(&self) -> std::io::Result<DuplexStream> {
+        let (client, server) = tokio::io::duplex(self.max_buf_size);
+        self.incoming.send(server).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::ConnectionRefused,
+                "In-memory listener is closed",
+            )
+        })?;
+        Ok(client)
+    }
+}
+
+impl Listener for DuplexListener {
+    async fn accept(&mut self) -> std::io::Result<(BoxedStream, String)> {
+        match self.incoming.recv().await {
+            Some(stream) => {
+                self.count += 1;
+                Ok((
+                    Box::new(stream),
+                    format!("in-memory connection {}", self.count),
+                ))
+            }
+            // Once every handle is dropped there will be no more connections,
+            // but the server should keep running for the existing ones.
+            None => futures::future::pending().await,
+        }
+    }
+
+    fn describe(&self) -> String {
+        "in-memory listener".to_owned()
+    }
+}
@@ -1,3 +1,7 @@
+//! Types for accepting connections to the server over different transports.
+
 mod connect;
+mod listener;
 pub(crate) mod tcp;
 pub(crate) use connect::Connector;
+pub use listener::{DuplexListener, DuplexListenerHandle, Listener};
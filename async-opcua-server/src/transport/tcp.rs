@@ -11,30 +11,30 @@ use opcua_core::{
         message_chunk_info::ChunkInfo,
         secure_channel::SecureChannel,
         sequence_number::SequenceNumberHandle,
+        stream::BoxedStream,
         tcp_codec::{Message, TcpCodec},
-        tcp_types::{AcknowledgeMessage, ErrorMessage},
+        tcp_types::{AcknowledgeMessage, ErrorMessage, ReverseHelloMessage},
+        websocket,
     },
     RequestMessage, ResponseMessage,
 };
 use tracing::error;
 use tracing_futures::Instrument;
 
-use crate::info::ServerInfo;
+use crate::{config::EndpointTransport, info::ServerInfo};
 use opcua_types::{DecodingOptions, Error, ResponseHeader, ServiceFault, StatusCode};
 
-use futures::StreamExt;
-use tokio::{
-    io::{AsyncWriteExt, ReadHalf, WriteHalf},
-    net::TcpStream,
-};
+use futures::{future::Either, StreamExt};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, ReadHalf, WriteHalf};
 use tokio_util::{codec::FramedRead, sync::CancellationToken};
 
 use super::connect::Connector;
 
-/// Transport implementation for opc.tcp.
+/// Transport implementation for opc.tcp, also used for opc.ws and opc.wss,
+/// which carry the same messages over WebSockets.
 pub(crate) struct TcpTransport {
-    read: FramedRead<ReadHalf<TcpStream>, TcpCodec>,
-    write: WriteHalf<TcpStream>,
+    read: FramedRead<ReadHalf<BoxedStream>, TcpCodec>,
+    write: WriteHalf<BoxedStream>,
     send_buffer: SendBuffer,
     state: TransportState,
     pending_chunks: Vec<MessageChunk>,
@@ -42,6 +42,8 @@ pub(crate) struct TcpTransport {
     pub(crate) client_protocol_version: u32,
     /// Last decoded sequence number
     sequence_numbers: SequenceNumberHandle,
+    /// Transport the client connected with.
+    transport: EndpointTransport,
 }
 
 enum TransportState {
@@ -87,31 +89,135 @@ fn min_zero_infinite(server: u32, client: u32) -> u32 {
 }
 
 pub(crate) struct TcpConnector {
-    read: FramedRead<ReadHalf<TcpStream>, TcpCodec>,
-    write: WriteHalf<TcpStream>,
+    stream: BoxedStream,
     deadline: Instant,
     config: TransportConfig,
     decoding_options: DecodingOptions,
+    reverse_hello: Option<ReverseHelloMessage>,
+}
+
+/// State of the `HELLO` handshake, once the transport used by the client is known.
+struct Handshake {
+    read: FramedRead<ReadHalf<BoxedStream>, TcpCodec>,
+    write: WriteHalf<BoxedStream>,
+    transport: EndpointTransport,
+    config: TransportConfig,
+    decoding_options: DecodingOptions,
+    reverse_hello: Option<ReverseHelloMessage>,
 }
 
 impl TcpConnector {
     pub(crate) fn new(
-        stream: TcpStream,
+        stream: BoxedStream,
         config: TransportConfig,
         decoding_options: DecodingOptions,
     ) -> Self {
-        let (read, write) = tokio::io::split(stream);
-        let read = FramedRead::new(read, TcpCodec::new(decoding_options.clone()));
         TcpConnector {
-            read,
-            write,
+            stream,
             deadline: Instant::now() + config.hello_timeout,
             config,
             decoding_options,
+            reverse_hello: None,
         }
     }
 
+    /// Send the given `ReverseHello` message before waiting for `HELLO`. This is
+    /// used for connections opened by the server to a client.
+    ///
+    /// The client may keep such a connection around until it needs it, so there is no
+    /// timeout waiting for `HELLO`.
+    pub(crate) fn with_reverse_hello(mut self, reverse_hello: ReverseHelloMessage) -> Self {
+        self.reverse_hello = Some(reverse_hello);
+        self
+    }
+
+    /// Detect the transport used by the client from the first byte it sends,
+    /// and perform the TLS and WebSocket handshakes if necessary.
+    /// All transports are served on the same port.
+    async fn accept_transport(
+        stream: BoxedStream,
+        info: &ServerInfo,
+    ) -> Result<(BoxedStream, EndpointTransport), StatusCode> {
+        // Peek at the first byte without consuming it, the buffered reader
+        // is kept as the stream so nothing is lost.
+        let mut stream = BufReader::new(stream);
+        let first = match stream.fill_buf().await {
+            Ok([first, ..]) => *first,
+            Ok([]) => {
+                error!("Connection closed before the client sent any data");
+                return Err(StatusCode::BadCommunicationError);
+            }
+            Err(e) => {
+                error!("Failed to read from socket: {e}");
+                return Err(StatusCode::BadCommunicationError);
+            }
+        };
+
+        match first {
+            // TLS handshake record
+            0x16 => Self::accept_tls(stream, info).await,
+            // HTTP GET request, to upgrade to WebSockets
+            b'G' => {
+                let (stream, _) = websocket::server_handshake(stream, info.receive_buffer_size)
+                    .await
+                    .map_err(|e| {
+                        error!("WebSocket handshake failed: {e}");
+                        StatusCode::BadCommunicationError
+                    })?;
+                Ok((Box::new(stream), EndpointTransport::WebSocket))
+            }
+            _ => Ok((Box::new(stream), EndpointTransport::Tcp)),
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    async fn accept_tls(
+        stream: BufReader<BoxedStream>,
+        info: &ServerInfo,
+    ) -> Result<(BoxedStream, EndpointTransport), StatusCode> {
+        let keypair = info.server_keypair.load_full();
+        let (Some(cert), Some(key)) = (&keypair.certificate, &keypair.pkey) else {
+            error!("Cannot accept TLS connection, the server has no certificate");
+            return Err(StatusCode::BadCertificateInvalid);
+        };
+        let stream = opcua_core::comms::tls::acceptor(cert, key)?
+            .accept(stream)
+            .await
+            .map_err(|e| {
+                error!("TLS handshake failed: {e}");
+                StatusCode::BadSecurityChecksFailed
+            })?;
+        let (stream, _) = websocket::server_handshake(stream, info.receive_buffer_size)
+            .await
+            .map_err(|e| {
+                error!("WebSocket handshake failed: {e}");
+                StatusCode::BadCommunicationError
+            })?;
+        Ok((Box::new(stream), EndpointTransport::WebSocketSecure))
+    }
+
+    #[cfg(not(feature = "tls"))]
+    async fn accept_tls(
+        _stream: BufReader<BoxedStream>,
+        _info: &ServerInfo,
+    ) -> Result<(BoxedStream, EndpointTransport), StatusCode> {
+        error!("Cannot accept TLS connection, the tls feature is not enabled");
+        Err(StatusCode::BadCommunicationError)
+    }
+}
+
+impl Handshake {
     async fn connect_inner(&mut self, info: Arc<ServerInfo>) -> Result<SendBuffer, ErrorMessage> {
+        if let Some(reverse_hello) = self.reverse_hello.take() {
+            let buf = opcua_types::SimpleBinaryEncodable::encode_to_vec(&reverse_hello);
+            self.write.write_all(&buf).await.map_err(|e| {
+                ErrorMessage::new(
+                    StatusCode::BadCommunicationError,
+                    &format!("Failed to send reverse hello: {e}"),
+                )
+            })?;
+        }
+
         let hello = match self.read.next().await {
             Some(Ok(Message::Hello(hello))) => Ok(hello),
             Some(Ok(bad_msg)) => Err(ErrorMessage::new(
@@ -146,6 +252,12 @@ impl TcpConnector {
                 "HELLO endpoint url is invalid",
             ));
         }
+        if !self.transport.matches_url(hello.endpoint_url.as_ref()) {
+            return Err(ErrorMessage::new(
+                StatusCode::BadTcpEndpointUrlInvalid,
+                "HELLO endpoint url does not match the transport",
+            ));
+        }
         if !hello.is_valid_buffer_sizes() {
             return Err(ErrorMessage::new(
                 StatusCode::BadCommunicationError,
@@ -202,20 +314,57 @@ impl TcpConnector {
 
 impl Connector for TcpConnector {
     async fn connect(
-        mut self,
+        self,
         info: Arc<ServerInfo>,
         token: CancellationToken,
     ) -> Result<TcpTransport, StatusCode> {
+        let deadline = match self.reverse_hello {
+            Some(_) => Either::Left(futures::future::pending::<()>()),
+            None => Either::Right(tokio::time::sleep_until(self.deadline.into())),
+        };
+        tokio::pin!(deadline);
+
+        // Connections opened by the server for reverse connect always use opc.tcp,
+        // and there is no need to detect the transport if only opc.tcp is configured.
+        let detect = self.reverse_hello.is_none()
+            && info
+                .config
+                .endpoints
+                .values()
+                .any(|e| e.transport != EndpointTransport::Tcp);
+        let (stream, transport) = if detect {
+            tokio::select! {
+                _ = &mut deadline => {
+                    error!("Timeout waiting for the transport handshake");
+                    return Err(StatusCode::BadTimeout);
+                }
+                _ = token.cancelled() => return Err(StatusCode::BadServerHalted),
+                r = Self::accept_transport(self.stream, &info) => r?,
+            }
+        } else {
+            (self.stream, EndpointTransport::Tcp)
+        };
+
+        let (read, write) = tokio::io::split(stream);
+        let mut handshake = Handshake {
+            read: FramedRead::new(read, TcpCodec::new(self.decoding_options.clone())),
+            write,
+            transport,
+            config: self.config,
+            decoding_options: self.decoding_options,
+            reverse_hello: self.reverse_hello,
+        };
+
         let err = tokio::select! {
-            _ = tokio::time::sleep_until(self.deadline.into()) => {
+            _ = &mut deadline => {
                 ErrorMessage::new(StatusCode::BadTimeout, "Timeout waiting for HELLO")
             }
             _ = token.cancelled() => {
                 ErrorMessage::new(StatusCode::BadServerHalted, "Server closed")
             }
-            r = self.connect_inner(info).instrument(tracing::info_span!("OPC-UA TCP handshake")) => {
+            r = handshake.connect_inner(info).instrument(tracing::info_span!("OPC-UA TCP handshake")) => {
                 match r {
-                    Ok(r) => return Ok(TcpTransport::new(self.read, self.write, r)),
+                    Ok(r) => return Ok(TcpTransport::new(handshake.read, handshake.write, r, handshake.transport)),
                     Err(e) => e,
                 }
             }
@@ -225,7 +374,7 @@ impl Connector for TcpConnector {
         // there's a good chance the channel is closed, so just ignore any errors.
         let mut buf = Vec::with_capacity(opcua_types::SimpleBinaryEncodable::byte_len(&err));
         if opcua_types::SimpleBinaryEncodable::encode(&err, &mut buf).is_ok() {
-            let _ = self.write.write_all(&buf).await;
+            let _ = handshake.write.write_all(&buf).await;
         }
 
         Err(err.error)
@@ -234,13 +383,15 @@ impl Connector for TcpConnector {
 
 impl TcpTransport {
     fn new(
-        read: FramedRead<ReadHalf<TcpStream>, TcpCodec>,
-        write: WriteHalf<TcpStream>,
+        read: FramedRead<ReadHalf<BoxedStream>, TcpCodec>,
+        write: WriteHalf<BoxedStream>,
         send_buffer: SendBuffer,
+        transport: EndpointTransport,
     ) -> Self {
         Self {
             read,
             write,
+            transport,
             state: TransportState::Running,
             pending_chunks: Vec::new(),
             sequence_numbers: SequenceNumberHandle::new(true),
@@ -249,6 +400,11 @@ impl TcpTransport {
         }
     }
 
+    /// Get the transport the client connected with.
+    pub(crate) fn endpoint_transport(&self) -> EndpointTransport {
+        self.transport
+    }
+
     /// Set the transport state to closing, once the final message is sent
     /// the connection will be closed.
     pub(crate) fn set_closing(&mut self) {
@@ -397,6 +553,18 @@ impl TcpTransport {
                         &self.pending_chunks,
                     )?);
 
+                    if chunk_info
+                        .message_header
+                        .message_type
+                        .is_open_secure_channel()
+                    {
+                        // The security policy decides whether sequence numbers start at 0 or 1,
+                        // the first request is accepted either way.
+                        let legacy = channel.security_policy().legacy_sequence_numbers();
+                        self.sequence_numbers.set_is_legacy(legacy);
+                        self.send_buffer.set_sequence_number_legacy(legacy);
+                    }
+
                     let request = Chunker::decode(&self.pending_chunks, channel, None)
                         .map_err(|e| e.with_request_id(chunk_info.sequence_header.request_id))?;
                     Ok(Some(Request {
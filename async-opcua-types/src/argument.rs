@@ -17,7 +17,9 @@ use crate::{
     localized_text::LocalizedText,
     node_id::NodeId,
     string::UAString,
-    write_u32, Context, DataTypeId, Error, MessageInfo, ObjectId,
+    write_u32, ByteString, Context, DataTypeId, DataValue, DateTime, DateTimeUtc, DiagnosticInfo,
+    DynEncodable, Error, ExpandedNodeId, ExtensionObject, Guid, MessageInfo, NamespaceMap,
+    ObjectId, QualifiedName, StatusCode, Variant, XmlElement,
 };
 
 // From OPC UA Part 3 - Address Space Model 1.03 Specification
@@ -123,3 +125,110 @@ impl BinaryDecodable for Argument {
         })
     }
 }
+
+impl Argument {
+    /// Create an argument description for a method argument of type `T`.
+    ///
+    /// The data type is resolved using `namespaces`, if it cannot be resolved
+    /// the argument is given the data type `BaseDataType`.
+    pub fn of_type<T: ArgumentType>(name: impl Into<UAString>, namespaces: &NamespaceMap) -> Self {
+        Argument {
+            name: name.into(),
+            data_type: T::data_type()
+                .try_resolve(namespaces)
+                .map(|id| id.into_owned())
+                .unwrap_or_else(|| DataTypeId::BaseDataType.into()),
+            value_rank: T::value_rank(),
+            array_dimensions: None,
+            description: LocalizedText::null(),
+        }
+    }
+}
+
+/// Trait for types that can be used as method arguments, giving the data type
+/// and value rank used to describe the argument in the `InputArguments` or
+/// `OutputArguments` property of a method.
+pub trait ArgumentType {
+    /// The data type of the argument.
+    fn data_type() -> ExpandedNodeId;
+
+    /// The value rank of the argument, `-1` for scalars.
+    fn value_rank() -> i32 {
+        -1
+    }
+}
+
+macro_rules! impl_argument_type {
+    ($tp:ty, $dt:ident) => {
+        impl ArgumentType for $tp {
+            fn data_type() -> ExpandedNodeId {
+                DataTypeId::$dt.into()
+            }
+        }
+    };
+}
+
+impl_argument_type!(bool, Boolean);
+impl_argument_type!(i8, SByte);
+impl_argument_type!(u8, Byte);
+impl_argument_type!(i16, Int16);
+impl_argument_type!(u16, UInt16);
+impl_argument_type!(i32, Int32);
+impl_argument_type!(u32, UInt32);
+impl_argument_type!(i64, Int64);
+impl_argument_type!(u64, UInt64);
+impl_argument_type!(f32, Float);
+impl_argument_type!(f64, Double);
+impl_argument_type!(UAString, String);
+impl_argument_type!(String, String);
+impl_argument_type!(DateTime, DateTime);
+impl_argument_type!(DateTimeUtc, DateTime);
+impl_argument_type!(Guid, Guid);
+impl_argument_type!(uuid::Uuid, Guid);
+impl_argument_type!(ByteString, ByteString);
+impl_argument_type!(XmlElement, XmlElement);
+impl_argument_type!(NodeId, NodeId);
+impl_argument_type!(ExpandedNodeId, ExpandedNodeId);
+impl_argument_type!(StatusCode, StatusCode);
+impl_argument_type!(QualifiedName, QualifiedName);
+impl_argument_type!(LocalizedText, LocalizedText);
+impl_argument_type!(ExtensionObject, Structure);
+impl_argument_type!(DataValue, DataValue);
+impl_argument_type!(Variant, BaseDataType);
+impl_argument_type!(DiagnosticInfo, DiagnosticInfo);
+
+// Structures use the data type of their default value.
+impl<T> ArgumentType for T
+where
+    T: DynEncodable + Default,
+{
+    fn data_type() -> ExpandedNodeId {
+        T::default().data_type_id()
+    }
+}
+
+impl<T> ArgumentType for Option<T>
+where
+    T: ArgumentType,
+{
+    fn data_type() -> ExpandedNodeId {
+        T::data_type()
+    }
+
+    fn value_rank() -> i32 {
+        T::value_rank()
+    }
+}
+
+impl<T> ArgumentType for Vec<T>
+where
+    T: ArgumentType,
+{
+    fn data_type() -> ExpandedNodeId {
+        T::data_type()
+    }
+
+    fn value_rank() -> i32 {
+        1
+    }
+}
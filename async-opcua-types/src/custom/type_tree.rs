@@ -415,6 +415,34 @@ impl DataTypeTree {
         }
     }
 
+    /// Add all the types in `other` to this tree, so that they can be used as
+    /// fields of types in this tree. This does not include the parent IDs of `other`.
+    pub fn add_types_from(&mut self, other: &DataTypeTree) {
+        self.struct_types.extend(
+            other
+                .struct_types
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+        self.enum_types
+            .extend(other.enum_types.iter().map(|(k, v)| (k.clone(), v.clone())));
+        self.other_types.extend(
+            other
+                .other_types
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+        // Types without encodings, such as abstract types, are registered with
+        // a null encoding ID, which should not be mapped to types from `other`.
+        self.encoding_to_data_type.extend(
+            other
+                .encoding_to_data_type
+                .iter()
+                .filter(|(k, _)| !k.is_null())
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+    }
+
     /// Get a type from the tree.
     pub fn get_type<'a>(&'a self, id: &NodeId) -> Option<TypeInfoRef<'a>> {
         if let Some(d) = self.struct_types.get(id) {
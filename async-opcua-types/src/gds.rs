@@ -0,0 +1,72 @@
+//! Types and node IDs from the OPC UA Global Discovery Server namespace, defined in
+//! OPC UA Part 12.
+//!
+//! Only the subset needed to act as a client of a GDS certificate manager is included here.
+//! The data types are generated from `schemas/1.05/Opc.Ua.Gds.Subset.NodeSet2.xml`.
+//! Since the namespace index of the GDS namespace depends on the server, node IDs are
+//! produced from the numeric identifiers with [`GdsObjectId::node_id`] and [`GdsMethodId::node_id`].
+
+use crate::NodeId;
+
+pub use crate::generated::gds::ApplicationRecordDataType;
+/// Type loader for the GDS types in this module. This must be added to
+/// servers that need to decode them, such as a GDS.
+pub use crate::generated::gds::GeneratedTypeLoader as GdsTypeLoader;
+
+/// The namespace URI of the OPC UA GDS namespace.
+pub const GDS_NAMESPACE_URI: &str = "http://opcfoundation.org/UA/GDS/";
+
+/// Private key format for PEM encoded keys, used with `StartNewKeyPairRequest`.
+pub const PRIVATE_KEY_FORMAT_PEM: &str = "PEM";
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[repr(u32)]
+/// Object IDs in the GDS namespace.
+pub enum GdsObjectId {
+    /// The `Directory` object of a GDS.
+    Directory = 141,
+    /// The default application certificate group of the GDS directory.
+    Directory_CertificateGroups_DefaultApplicationGroup = 614,
+}
+
+impl GdsObjectId {
+    /// Get the node ID of this object, given the index of the GDS namespace on the server.
+    pub fn node_id(self, namespace: u16) -> NodeId {
+        NodeId::new(namespace, self as u32)
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[repr(u32)]
+/// Method IDs in the GDS namespace.
+pub enum GdsMethodId {
+    /// `Directory.FindApplications`
+    Directory_FindApplications = 143,
+    /// `Directory.RegisterApplication`
+    Directory_RegisterApplication = 146,
+    /// `Directory.UnregisterApplication`
+    Directory_UnregisterApplication = 149,
+    /// `Directory.StartNewKeyPairRequest`
+    Directory_StartNewKeyPairRequest = 154,
+    /// `Directory.StartSigningRequest`
+    Directory_StartSigningRequest = 157,
+    /// `Directory.FinishRequest`
+    Directory_FinishRequest = 163,
+    /// `Directory.UpdateApplication`
+    Directory_UpdateApplication = 200,
+    /// `Directory.GetTrustList`
+    Directory_GetTrustList = 204,
+    /// `Directory.GetCertificateStatus`
+    Directory_GetCertificateStatus = 222,
+    /// `Directory.GetCertificateGroups`
+    Directory_GetCertificateGroups = 508,
+}
+
+impl GdsMethodId {
+    /// Get the node ID of this method, given the index of the GDS namespace on the server.
+    pub fn node_id(self, namespace: u16) -> NodeId {
+        NodeId::new(namespace, self as u32)
+    }
+}
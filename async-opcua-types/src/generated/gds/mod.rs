@@ -0,0 +1,107 @@
+// This file was autogenerated from schemas/1.05/Opc.Ua.Gds.Subset.NodeSet2.xml by async-opcua-codegen
+//
+// DO NOT EDIT THIS FILE
+
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2024 Adam Lock, Einar Omang
+#[allow(unused)]
+mod opcua {
+    pub(super) use crate as types;
+}
+pub mod structs;
+pub use structs::*;
+static TYPES: std::sync::LazyLock<opcua::types::TypeLoaderInstance> =
+    std::sync::LazyLock::new(|| {
+        let mut inst = opcua::types::TypeLoaderInstance::new();
+        {
+            inst.add_binary_type(
+                1u32,
+                134u32,
+                opcua::types::binary_decode_to_enc::<ApplicationRecordDataType>,
+            );
+        }
+        #[cfg(feature = "xml")]
+        {
+            inst.add_xml_type(
+                1u32,
+                127u32,
+                opcua::types::xml_decode_to_enc::<ApplicationRecordDataType>,
+            );
+        }
+        #[cfg(feature = "json")]
+        {
+            inst.add_json_type(
+                1u32,
+                8001u32,
+                opcua::types::json_decode_to_enc::<ApplicationRecordDataType>,
+            );
+        }
+        inst
+    });
+#[derive(Debug, Clone, Copy)]
+pub struct GeneratedTypeLoader;
+impl opcua::types::TypeLoader for GeneratedTypeLoader {
+    fn load_from_binary(
+        &self,
+        node_id: &opcua::types::NodeId,
+        stream: &mut dyn std::io::Read,
+        ctx: &opcua::types::Context<'_>,
+    ) -> Option<opcua::types::EncodingResult<Box<dyn opcua::types::DynEncodable>>> {
+        let idx = ctx
+            .namespaces()
+            .get_index("http://opcfoundation.org/UA/GDS/")?;
+        if idx != node_id.namespace {
+            return None;
+        }
+        let Some(num_id) = node_id.as_u32() else {
+            return Some(Err(opcua::types::Error::decoding(
+                "Unsupported encoding ID. Only numeric encoding IDs are currently supported",
+            )));
+        };
+        TYPES.decode_binary(num_id, stream, ctx)
+    }
+    #[cfg(feature = "xml")]
+    fn load_from_xml(
+        &self,
+        node_id: &opcua::types::NodeId,
+        stream: &mut opcua::types::xml::XmlStreamReader<&mut dyn std::io::Read>,
+        ctx: &opcua::types::Context<'_>,
+    ) -> Option<opcua::types::EncodingResult<Box<dyn opcua::types::DynEncodable>>> {
+        let idx = ctx
+            .namespaces()
+            .get_index("http://opcfoundation.org/UA/GDS/")?;
+        if idx != node_id.namespace {
+            return None;
+        }
+        let Some(num_id) = node_id.as_u32() else {
+            return Some(Err(opcua::types::Error::decoding(
+                "Unsupported encoding ID. Only numeric encoding IDs are currently supported",
+            )));
+        };
+        TYPES.decode_xml(num_id, stream, ctx)
+    }
+    #[cfg(feature = "json")]
+    fn load_from_json(
+        &self,
+        node_id: &opcua::types::NodeId,
+        stream: &mut opcua::types::json::JsonStreamReader<&mut dyn std::io::Read>,
+        ctx: &opcua::types::Context<'_>,
+    ) -> Option<opcua::types::EncodingResult<Box<dyn opcua::types::DynEncodable>>> {
+        let idx = ctx
+            .namespaces()
+            .get_index("http://opcfoundation.org/UA/GDS/")?;
+        if idx != node_id.namespace {
+            return None;
+        }
+        let Some(num_id) = node_id.as_u32() else {
+            return Some(Err(opcua::types::Error::decoding(
+                "Unsupported encoding ID. Only numeric encoding IDs are currently supported",
+            )));
+        };
+        TYPES.decode_json(num_id, stream, ctx)
+    }
+    fn priority(&self) -> opcua::types::TypeLoaderPriority {
+        opcua::types::TypeLoaderPriority::Generated
+    }
+}
@@ -0,0 +1,37 @@
+// This file was autogenerated from schemas/1.05/Opc.Ua.Gds.Subset.NodeSet2.xml by async-opcua-codegen
+//
+// DO NOT EDIT THIS FILE
+
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2024 Adam Lock, Einar Omang
+#[allow(unused)]
+mod opcua {
+    pub(super) use crate as types;
+}
+#[opcua::types::ua_encodable]
+///https://reference.opcfoundation.org/v105/GDS/docs/7.9.3
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ApplicationRecordDataType {
+    pub application_id: opcua::types::node_id::NodeId,
+    pub application_uri: opcua::types::string::UAString,
+    pub application_type: opcua::types::ApplicationType,
+    pub application_names: Option<Vec<opcua::types::localized_text::LocalizedText>>,
+    pub product_uri: opcua::types::string::UAString,
+    pub discovery_urls: Option<Vec<opcua::types::string::UAString>>,
+    pub server_capabilities: Option<Vec<opcua::types::string::UAString>>,
+}
+impl opcua::types::ExpandedMessageInfo for ApplicationRecordDataType {
+    fn full_type_id(&self) -> opcua::types::ExpandedNodeId {
+        opcua::types::ExpandedNodeId::from((134u32, "http://opcfoundation.org/UA/GDS/"))
+    }
+    fn full_json_type_id(&self) -> opcua::types::ExpandedNodeId {
+        opcua::types::ExpandedNodeId::from((8001u32, "http://opcfoundation.org/UA/GDS/"))
+    }
+    fn full_xml_type_id(&self) -> opcua::types::ExpandedNodeId {
+        opcua::types::ExpandedNodeId::from((127u32, "http://opcfoundation.org/UA/GDS/"))
+    }
+    fn full_data_type_id(&self) -> opcua::types::ExpandedNodeId {
+        opcua::types::ExpandedNodeId::from((1u32, "http://opcfoundation.org/UA/GDS/"))
+    }
+}
@@ -1,2 +1,3 @@
+pub mod gds;
 pub mod node_ids;
 pub mod types;
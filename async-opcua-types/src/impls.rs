@@ -15,11 +15,12 @@ use crate::{
     status_code::StatusCode,
     string::UAString,
     variant::Variant,
-    AnonymousIdentityToken, ApplicationDescription, CallMethodRequest, DataTypeId, DataValue,
-    EndpointDescription, Error, ExpandedNodeId, HistoryUpdateType, IdentityCriteriaType,
-    MessageSecurityMode, MonitoredItemCreateRequest, MonitoringMode, MonitoringParameters,
-    NumericRange, ObjectId, ReadValueId, ServiceCounterDataType, ServiceFault, SignatureData,
-    UserNameIdentityToken, UserTokenPolicy, UserTokenType, WriteValue,
+    AdditionalParametersType, AnonymousIdentityToken, ApplicationDescription, CallMethodRequest,
+    DataTypeId, DataValue, EndpointDescription, Error, ExpandedNodeId, HistoryUpdateType,
+    IdentityCriteriaType, KeyValuePair, MessageSecurityMode, MonitoredItemCreateRequest,
+    MonitoringMode, MonitoringParameters, NumericRange, ObjectId, ReadValueId,
+    ServiceCounterDataType, ServiceFault, SignatureData, UserNameIdentityToken, UserTokenPolicy,
+    UserTokenType, WriteValue,
 };
 
 use super::PerformUpdateType;
@@ -404,3 +405,29 @@ impl WriteValue {
         }
     }
 }
+
+impl AdditionalParametersType {
+    /// Create a new set of additional parameters from a list of parameter names and values.
+    pub fn new<'a>(parameters: impl IntoIterator<Item = (&'a str, Variant)>) -> Self {
+        Self {
+            parameters: Some(
+                parameters
+                    .into_iter()
+                    .map(|(key, value)| KeyValuePair {
+                        key: key.into(),
+                        value,
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Get the value of the parameter with the given name, if it is present.
+    pub fn get(&self, name: &str) -> Option<&Variant> {
+        self.parameters
+            .iter()
+            .flatten()
+            .find(|p| p.key.namespace_index == 0 && p.key.name.as_ref() == name)
+            .map(|p| &p.value)
+    }
+}
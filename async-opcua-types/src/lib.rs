@@ -19,6 +19,9 @@ pub mod profiles {
     /// Transport profile for OPC UA Binary
     pub const TRANSPORT_PROFILE_URI_BINARY: &str =
         "http://opcfoundation.org/UA-Profile/Transport/uatcp-uasc-uabinary";
+    /// Transport profile for OPC UA Binary over WebSockets, with or without TLS.
+    pub const TRANSPORT_PROFILE_URI_WSS_BINARY: &str =
+        "http://opcfoundation.org/UA-Profile/Transport/wss-uasc-uabinary";
     /// Security policy for anonymous tokens.
     pub const SECURITY_USER_TOKEN_POLICY_ANONYMOUS: &str =
         "http://opcfoundation.org/UA-Profile/Security/UserToken/Anonymous";
@@ -238,7 +241,7 @@ bitflags! {
     }
 }
 
-// These 2 modules are autogenerated
+// These modules are autogenerated
 #[doc(hidden)]
 pub mod generated;
 
@@ -260,6 +263,7 @@ pub mod errors;
 pub mod event_field;
 pub mod expanded_node_id;
 pub mod extension_object;
+pub mod gds;
 pub mod guid;
 mod impls;
 #[cfg(feature = "json")]
@@ -10,9 +10,9 @@ use std::convert::TryFrom;
 
 use crate::{
     attribute::AttributeId, match_extension_object_owned, status_code::StatusCode,
-    AttributeOperand, ContentFilter, ContentFilterElement, ElementOperand, ExtensionObject,
-    FilterOperator, LiteralOperand, NodeId, NumericRange, QualifiedName, SimpleAttributeOperand,
-    Variant,
+    AttributeOperand, ContentFilter, ContentFilterElement, ElementOperand, ExpandedNodeId,
+    ExtensionObject, FilterOperator, LiteralOperand, NodeId, NodeTypeDescription, NumericRange,
+    QualifiedName, QueryDataDescription, RelativePath, SimpleAttributeOperand, Variant,
 };
 
 #[derive(PartialEq)]
@@ -101,6 +101,12 @@ impl From<&str> for LiteralOperand {
     }
 }
 
+impl From<NodeId> for LiteralOperand {
+    fn from(v: NodeId) -> Self {
+        Self::from(Variant::from(v))
+    }
+}
+
 impl From<()> for LiteralOperand {
     fn from(_v: ()) -> Self {
         Self::from(Variant::from(()))
@@ -409,6 +415,60 @@ impl ContentFilterBuilder {
         self.add_element(FilterOperator::BitwiseOr, vec![o1.into(), o2.into()])
     }
 
+    /// Add an "of type" operand, matching nodes with the given type definition, or a subtype of it.
+    pub fn of_type<T>(self, type_definition_id: T) -> Self
+    where
+        T: Into<NodeId>,
+    {
+        self.add_element(
+            FilterOperator::OfType,
+            vec![Operand::literal(type_definition_id.into())],
+        )
+    }
+
+    /// Add an "in view" operand, matching nodes contained in the given view.
+    pub fn in_view<T>(self, view_id: T) -> Self
+    where
+        T: Into<NodeId>,
+    {
+        self.add_element(
+            FilterOperator::InView,
+            vec![Operand::literal(view_id.into())],
+        )
+    }
+
+    /// Add a "related to" operand, matching nodes of the `source` type with a reference of
+    /// type `reference_type_id` to a node of the `target` type, within `hops` references.
+    ///
+    /// `source` and `target` are either literal type definition IDs, or element operands
+    /// referencing another "related to" element.
+    pub fn related_to<T, S, R>(
+        self,
+        source: T,
+        target: S,
+        reference_type_id: R,
+        hops: u32,
+        include_type_subtypes: bool,
+        include_reference_subtypes: bool,
+    ) -> Self
+    where
+        T: Into<Operand>,
+        S: Into<Operand>,
+        R: Into<NodeId>,
+    {
+        self.add_element(
+            FilterOperator::RelatedTo,
+            vec![
+                source.into(),
+                target.into(),
+                Operand::literal(reference_type_id.into()),
+                Operand::literal(hops),
+                Operand::literal(include_type_subtypes),
+                Operand::literal(include_reference_subtypes),
+            ],
+        )
+    }
+
     /// Build a content filter.
     pub fn build(self) -> ContentFilter {
         ContentFilter {
@@ -417,6 +477,60 @@ impl ContentFilterBuilder {
     }
 }
 
+impl From<ContentFilterBuilder> for ContentFilter {
+    fn from(value: ContentFilterBuilder) -> Self {
+        value.build()
+    }
+}
+
+impl NodeTypeDescription {
+    /// Create a new node type description for the `Query` service, matching nodes with
+    /// the given type definition, and optionally its subtypes.
+    ///
+    /// Use [`NodeTypeDescription::data`] to select values to return for each matching node.
+    pub fn new<T>(type_definition_node: T, include_sub_types: bool) -> Self
+    where
+        T: Into<ExpandedNodeId>,
+    {
+        Self {
+            type_definition_node: type_definition_node.into(),
+            include_sub_types,
+            data_to_return: None,
+        }
+    }
+
+    /// Add a value to return for each matching node.
+    pub fn data(mut self, data: QueryDataDescription) -> Self {
+        self.data_to_return.get_or_insert_with(Vec::new).push(data);
+        self
+    }
+}
+
+impl QueryDataDescription {
+    /// Create a new query data description, returning the attribute of the node
+    /// found by following `relative_path` from each matching node.
+    pub fn new(
+        relative_path: RelativePath,
+        attribute_id: AttributeId,
+        index_range: NumericRange,
+    ) -> Self {
+        Self {
+            relative_path,
+            attribute_id: attribute_id as u32,
+            index_range,
+        }
+    }
+
+    /// Create a new query data description, returning the attribute of each matching node.
+    pub fn attribute(attribute_id: AttributeId) -> Self {
+        Self::new(
+            RelativePath { elements: None },
+            attribute_id,
+            NumericRange::None,
+        )
+    }
+}
+
 impl SimpleAttributeOperand {
     /// Create a new simple attribute operand.
     pub fn new<T>(
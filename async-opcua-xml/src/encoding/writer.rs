@@ -30,6 +30,14 @@ impl<T: Write> XmlStreamWriter<T> {
         }
     }
 
+    /// Create a new writer with the given inner Write implementation, indenting
+    /// nested elements by `indent_size` repetitions of `indent_char`.
+    pub fn new_with_indent(writer: T, indent_char: u8, indent_size: usize) -> Self {
+        Self {
+            writer: quick_xml::Writer::new_with_indent(writer, indent_char, indent_size),
+        }
+    }
+
     /// Write an event to the stream.
     pub fn write_event(&mut self, element: Event<'_>) -> Result<(), XmlWriteError> {
         self.writer.write_event(element)?;
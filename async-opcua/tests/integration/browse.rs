@@ -8,7 +8,7 @@ use opcua::{
         RelativePathElement, StatusCode, VariableTypeId,
     },
 };
-use opcua_client::browser::BrowseFilter;
+use opcua_client::{browser::BrowseFilter, OperationLimits};
 use opcua_nodes::DefaultTypeTree;
 use opcua_types::{AttributeId, ReadValueId, TimestampsToReturn, VariableId, Variant};
 
@@ -365,6 +365,9 @@ async fn browse_limits() {
     let r = session.browse(&[], 1000, None).await.unwrap_err();
     assert_eq!(r, StatusCode::BadNothingToDo);
 
+    // Disable request splitting in the client, so that the request reaches the server.
+    session.set_operation_limits(OperationLimits::default());
+
     // Too many operations
     let ops: Vec<_> = (0..(browse_limit + 1))
         .map(|r| hierarchical_desc(NodeId::new(2, r as i32)))
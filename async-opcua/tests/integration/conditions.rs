@@ -0,0 +1,442 @@
+use std::time::Duration;
+
+use crate::utils::ChannelNotifications;
+
+use super::utils::setup;
+use opcua::{
+    nodes::{BaseEventType, Event},
+    server::{address_space::ObjectBuilder, conditions::Condition},
+    types::{
+        AttributeId, CallMethodRequest, ContentFilter, ContentFilterElement, EventFilter,
+        ExtensionObject, FilterOperator, LocalizedText, MonitoredItemCreateRequest, MonitoringMode,
+        MonitoringParameters, NodeId, ObjectId, ObjectTypeId, Operand, QualifiedName, ReadValueId,
+        ReferenceTypeId, SimpleAttributeOperand, StatusCode, TimestampsToReturn, Variant,
+    },
+};
+use opcua_types::{MethodId, NumericRange};
+use tokio::{sync::mpsc::UnboundedReceiver, time::timeout};
+
+async fn next_event(
+    events: &mut UnboundedReceiver<(ReadValueId, Option<Vec<Variant>>)>,
+) -> Vec<Variant> {
+    timeout(Duration::from_millis(1000), events.recv())
+        .await
+        .unwrap()
+        .unwrap()
+        .1
+        .unwrap()
+}
+
+#[tokio::test]
+async fn acknowledge_and_refresh() {
+    let (tester, nm, session) = setup().await;
+
+    let condition_id = nm.inner().next_node_id();
+    let source_id = nm.inner().next_node_id();
+    tester
+        .handle
+        .conditions()
+        .add_condition(
+            Condition::new(
+                condition_id.clone(),
+                ObjectTypeId::AlarmConditionType,
+                source_id.clone(),
+                "Source",
+                "Alarm",
+            )
+            .with_acknowledge(false)
+            .with_alarm(source_id.clone())
+            .with_severity(500),
+            nm.address_space(),
+        )
+        .unwrap();
+    // Report the new condition node before subscribing, so the model change
+    // event isn't mixed with the condition events.
+    nm.notify_model_changes(tester.handle.subscriptions());
+
+    let (notifs, _, mut events) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+
+    let select = |path: &[&str]| SimpleAttributeOperand {
+        type_definition_id: ObjectTypeId::BaseEventType.into(),
+        browse_path: Some(path.iter().map(|p| QualifiedName::from(*p)).collect()),
+        attribute_id: AttributeId::Value as u32,
+        index_range: NumericRange::None,
+    };
+    let filter = EventFilter {
+        select_clauses: Some(vec![
+            select(&["EventId"]),
+            select(&["EventType"]),
+            select(&["Retain"]),
+            select(&["AckedState", "Id"]),
+            select(&["ActiveState", "Id"]),
+        ]),
+        where_clause: Default::default(),
+    };
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: ObjectId::Server.into(),
+                    attribute_id: AttributeId::EventNotifier as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    filter: ExtensionObject::from_message(filter),
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].result.status_code, StatusCode::Good);
+
+    // Activate the alarm.
+    tester
+        .handle
+        .conditions()
+        .update_condition(&condition_id, |c| c.set_active(true))
+        .unwrap();
+    let evt = next_event(&mut events).await;
+    assert_eq!(
+        evt[1],
+        Variant::from(NodeId::from(ObjectTypeId::AlarmConditionType))
+    );
+    assert_eq!(evt[2], Variant::from(true));
+    assert_eq!(evt[3], Variant::from(false));
+    assert_eq!(evt[4], Variant::from(true));
+
+    // Acknowledging with an unknown event ID fails.
+    let r = session
+        .call_one(CallMethodRequest {
+            object_id: condition_id.clone(),
+            method_id: MethodId::AcknowledgeableConditionType_Acknowledge.into(),
+            input_arguments: Some(vec![
+                Variant::from(opcua::types::ByteString::from(vec![1u8, 2, 3])),
+                Variant::from(LocalizedText::from("Ack")),
+            ]),
+        })
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::BadEventIdUnknown);
+
+    let r = session
+        .call_one(CallMethodRequest {
+            object_id: condition_id.clone(),
+            method_id: MethodId::AcknowledgeableConditionType_Acknowledge.into(),
+            input_arguments: Some(vec![
+                evt[0].clone(),
+                Variant::from(LocalizedText::from("Ack")),
+            ]),
+        })
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::Good);
+    let evt = next_event(&mut events).await;
+    assert_eq!(evt[3], Variant::from(true));
+    // Still active, so still retained.
+    assert_eq!(evt[2], Variant::from(true));
+    let cond = tester
+        .handle
+        .conditions()
+        .get_condition(&condition_id)
+        .unwrap();
+    assert_eq!(cond.is_acked(), Some(true));
+    assert_eq!(cond.comment(), &LocalizedText::from("Ack"));
+
+    // Refresh sends the retained condition between a start and end event.
+    let r = session
+        .call_one(CallMethodRequest {
+            object_id: ObjectTypeId::ConditionType.into(),
+            method_id: MethodId::ConditionType_ConditionRefresh.into(),
+            input_arguments: Some(vec![Variant::from(sub_id)]),
+        })
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::Good);
+    let evt = next_event(&mut events).await;
+    assert_eq!(
+        evt[1],
+        Variant::from(NodeId::from(ObjectTypeId::RefreshStartEventType))
+    );
+    let evt = next_event(&mut events).await;
+    assert_eq!(
+        evt[1],
+        Variant::from(NodeId::from(ObjectTypeId::AlarmConditionType))
+    );
+    assert_eq!(evt[3], Variant::from(true));
+    let evt = next_event(&mut events).await;
+    assert_eq!(
+        evt[1],
+        Variant::from(NodeId::from(ObjectTypeId::RefreshEndEventType))
+    );
+
+    // Deactivating the acknowledged alarm means it is no longer retained.
+    tester
+        .handle
+        .conditions()
+        .update_condition(&condition_id, |c| c.set_active(false))
+        .unwrap();
+    let evt = next_event(&mut events).await;
+    assert_eq!(evt[2], Variant::from(false));
+    assert_eq!(evt[4], Variant::from(false));
+}
+
+#[tokio::test]
+async fn filter_alarms_by_type() {
+    let (tester, nm, session) = setup().await;
+
+    let (notifs, _, mut events) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+
+    // Only accept alarms, including subtypes of AlarmConditionType.
+    let filter = EventFilter {
+        select_clauses: Some(vec![SimpleAttributeOperand {
+            type_definition_id: ObjectTypeId::BaseEventType.into(),
+            browse_path: Some(vec!["EventType".into()]),
+            attribute_id: AttributeId::Value as u32,
+            index_range: NumericRange::None,
+        }]),
+        where_clause: ContentFilter {
+            elements: Some(vec![ContentFilterElement::from((
+                FilterOperator::OfType,
+                vec![Operand::literal(NodeId::from(
+                    ObjectTypeId::AlarmConditionType,
+                ))],
+            ))]),
+        },
+    };
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: ObjectId::Server.into(),
+                    attribute_id: AttributeId::EventNotifier as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    filter: ExtensionObject::from_message(filter),
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].result.status_code, StatusCode::Good);
+
+    // A plain event is filtered out.
+    let event = BaseEventType::new_now(
+        ObjectTypeId::BaseEventType,
+        opcua::types::ByteString::from(vec![1u8]),
+        "Plain event",
+    )
+    .set_source_node(ObjectId::Server.into());
+    tester
+        .handle
+        .subscriptions()
+        .notify_events([(&event as &dyn Event, &ObjectId::Server.into())].into_iter());
+
+    // An alarm of a subtype of AlarmConditionType is reported.
+    let condition_id = nm.inner().next_node_id();
+    let source_id = nm.inner().next_node_id();
+    tester
+        .handle
+        .conditions()
+        .add_condition(
+            Condition::new(
+                condition_id.clone(),
+                ObjectTypeId::OffNormalAlarmType,
+                source_id.clone(),
+                "Source",
+                "Alarm",
+            )
+            .with_alarm(source_id.clone()),
+            nm.address_space(),
+        )
+        .unwrap();
+    tester
+        .handle
+        .conditions()
+        .update_condition(&condition_id, |c| c.set_active(true))
+        .unwrap();
+
+    let evt = next_event(&mut events).await;
+    assert_eq!(
+        evt[0],
+        Variant::from(NodeId::from(ObjectTypeId::OffNormalAlarmType))
+    );
+    assert!(timeout(Duration::from_millis(300), events.recv())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn condition_in_address_space() {
+    let (tester, nm, session) = setup().await;
+
+    let condition_id = nm.inner().next_node_id();
+    let source_id = nm.inner().next_node_id();
+    tester
+        .handle
+        .conditions()
+        .add_condition(
+            Condition::new(
+                condition_id.clone(),
+                ObjectTypeId::AlarmConditionType,
+                source_id.clone(),
+                "Source",
+                "Alarm",
+            )
+            .with_acknowledge(false)
+            .with_alarm(source_id.clone()),
+            nm.address_space(),
+        )
+        .unwrap();
+
+    let read_name = || ReadValueId {
+        node_id: condition_id.clone(),
+        attribute_id: AttributeId::BrowseName as u32,
+        ..Default::default()
+    };
+    let r = session
+        .read(&[read_name()], TimestampsToReturn::Neither, 0.0)
+        .await
+        .unwrap();
+    assert_eq!(
+        r[0].value,
+        Some(Variant::from(QualifiedName::new(
+            condition_id.namespace,
+            "Alarm"
+        )))
+    );
+
+    // The condition has no confirmed state, so it has no Confirm method.
+    let r = session
+        .call_one(CallMethodRequest {
+            object_id: condition_id.clone(),
+            method_id: MethodId::AcknowledgeableConditionType_Confirm.into(),
+            input_arguments: Some(vec![
+                Variant::from(opcua::types::ByteString::from(vec![1u8])),
+                Variant::from(LocalizedText::from("Confirm")),
+            ]),
+        })
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::BadMethodInvalid);
+
+    let r = session
+        .call_one(CallMethodRequest {
+            object_id: condition_id.clone(),
+            method_id: MethodId::ConditionType_Disable.into(),
+            input_arguments: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::Good);
+    let cond = tester
+        .handle
+        .conditions()
+        .get_condition(&condition_id)
+        .unwrap();
+    assert!(!cond.is_enabled());
+
+    // Removing the condition removes the node.
+    tester.handle.conditions().remove_condition(&condition_id);
+    let r = session
+        .read(&[read_name()], TimestampsToReturn::Neither, 0.0)
+        .await
+        .unwrap();
+    assert_eq!(r[0].status, Some(StatusCode::BadNodeIdUnknown));
+}
+
+#[tokio::test]
+async fn condition_id_already_in_use() {
+    let (tester, nm, session) = setup().await;
+
+    let folder_id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        ObjectBuilder::new(&folder_id, "Folder", "Folder")
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&ObjectTypeId::FolderType.into()),
+        Vec::new(),
+    );
+    let source_id = nm.inner().next_node_id();
+    let alarm = |id: &NodeId| {
+        Condition::new(
+            id.clone(),
+            ObjectTypeId::AlarmConditionType,
+            source_id.clone(),
+            "Source",
+            "Alarm",
+        )
+        .with_alarm(source_id.clone())
+    };
+
+    // A node that isn't a condition is never replaced.
+    assert_eq!(
+        tester
+            .handle
+            .conditions()
+            .add_condition(alarm(&folder_id), nm.address_space()),
+        Err(StatusCode::BadNodeIdExists)
+    );
+    assert!(tester
+        .handle
+        .conditions()
+        .get_condition(&folder_id)
+        .is_none());
+    let r = session
+        .read(
+            &[ReadValueId {
+                node_id: folder_id.clone(),
+                attribute_id: AttributeId::BrowseName as u32,
+                ..Default::default()
+            }],
+            TimestampsToReturn::Neither,
+            0.0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        r[0].value,
+        Some(Variant::from(QualifiedName::from("Folder")))
+    );
+
+    // Adding a condition again replaces the node created for it.
+    let condition_id = nm.inner().next_node_id();
+    for _ in 0..2 {
+        tester
+            .handle
+            .conditions()
+            .add_condition(alarm(&condition_id), nm.address_space())
+            .unwrap();
+    }
+    assert!(tester
+        .handle
+        .conditions()
+        .get_condition(&condition_id)
+        .is_some());
+}
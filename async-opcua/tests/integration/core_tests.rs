@@ -219,6 +219,139 @@ async fn connect_aes256sha256rsapss_sign_and_encrypt() {
     .await;
 }
 
+#[tokio::test]
+async fn connect_ecc_nistp256_sign() {
+    conn_test(
+        SecurityPolicy::EccNistP256,
+        MessageSecurityMode::Sign,
+        IdentityToken::Anonymous,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn connect_ecc_nistp256_sign_and_encrypt() {
+    conn_test(
+        SecurityPolicy::EccNistP256,
+        MessageSecurityMode::SignAndEncrypt,
+        IdentityToken::Anonymous,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn connect_ecc_nistp384_sign() {
+    conn_test(
+        SecurityPolicy::EccNistP384,
+        MessageSecurityMode::Sign,
+        IdentityToken::Anonymous,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn connect_ecc_nistp384_sign_and_encrypt() {
+    conn_test(
+        SecurityPolicy::EccNistP384,
+        MessageSecurityMode::SignAndEncrypt,
+        IdentityToken::Anonymous,
+    )
+    .await;
+}
+
+#[cfg(feature = "brainpool")]
+#[tokio::test]
+async fn connect_ecc_brainpoolp256r1_sign() {
+    conn_test(
+        SecurityPolicy::EccBrainpoolP256r1,
+        MessageSecurityMode::Sign,
+        IdentityToken::Anonymous,
+    )
+    .await;
+}
+
+#[cfg(feature = "brainpool")]
+#[tokio::test]
+async fn connect_ecc_brainpoolp256r1_sign_and_encrypt() {
+    conn_test(
+        SecurityPolicy::EccBrainpoolP256r1,
+        MessageSecurityMode::SignAndEncrypt,
+        IdentityToken::Anonymous,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn connect_ecc_curve25519_sign() {
+    conn_test(
+        SecurityPolicy::EccCurve25519,
+        MessageSecurityMode::Sign,
+        IdentityToken::Anonymous,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn connect_ecc_curve25519_sign_and_encrypt() {
+    conn_test(
+        SecurityPolicy::EccCurve25519,
+        MessageSecurityMode::SignAndEncrypt,
+        IdentityToken::Anonymous,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn connect_ecc_nistp256_with_x509_token() {
+    conn_test(
+        SecurityPolicy::EccNistP256,
+        MessageSecurityMode::SignAndEncrypt,
+        client_x509_token().unwrap(),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn connect_ecc_nistp256_with_username_password() {
+    conn_test(
+        SecurityPolicy::EccNistP256,
+        MessageSecurityMode::SignAndEncrypt,
+        client_user_token(),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn connect_ecc_nistp384_with_username_password() {
+    conn_test(
+        SecurityPolicy::EccNistP384,
+        MessageSecurityMode::Sign,
+        client_user_token(),
+    )
+    .await;
+}
+
+#[cfg(feature = "brainpool")]
+#[tokio::test]
+async fn connect_ecc_brainpoolp256r1_with_username_password() {
+    conn_test(
+        SecurityPolicy::EccBrainpoolP256r1,
+        MessageSecurityMode::SignAndEncrypt,
+        client_user_token(),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn connect_ecc_curve25519_with_username_password() {
+    conn_test(
+        SecurityPolicy::EccCurve25519,
+        MessageSecurityMode::SignAndEncrypt,
+        client_user_token(),
+    )
+    .await;
+}
+
 #[tokio::test]
 async fn connect_basic128rsa15_with_username_password() {
     conn_test(
@@ -282,7 +415,7 @@ async fn discovery_test() {
         .get_endpoints(tester.endpoint(), &[], &[])
         .await
         .unwrap();
-    assert_eq!(endpoints.len(), 11);
+    assert_eq!(endpoints.len(), 19);
 
     // Get with wrong profile URIs
     let endpoints = tester
@@ -302,7 +435,7 @@ async fn discovery_test() {
         )
         .await
         .unwrap();
-    assert_eq!(endpoints.len(), 11);
+    assert_eq!(endpoints.len(), 19);
 }
 
 #[tokio::test]
@@ -1,17 +1,19 @@
 use std::sync::Arc;
 
-use opcua_client::custom_types::DataTypeTreeBuilder;
-use opcua_nodes::{AccessLevel, DataTypeBuilder, VariableBuilder};
+use opcua::{crypto::SecurityPolicy, server::node_manager::memory::simple_node_manager_imports};
+use opcua_client::{custom_types::DataTypeTreeBuilder, IdentityToken};
+use opcua_nodes::{AccessLevel, DataTypeBuilder, NodeSet2Import, VariableBuilder};
 use opcua_types::{
     custom::{DynamicStructure, DynamicTypeLoader},
-    DataTypeDefinition, DataTypeId, EUInformation, IntoVariant, LocalizedText, NodeId, ObjectId,
-    ReadValueId, ReferenceTypeId, StructureDefinition, StructureField, StructureType,
-    TimestampsToReturn, TypeLoader, VariableTypeId, Variant,
+    AttributeId, DataTypeDefinition, DataTypeId, DataValue, EUInformation, ExtensionObject,
+    IntoVariant, LocalizedText, MessageSecurityMode, NodeId, ObjectId, Range, ReadValueId,
+    ReferenceTypeId, StatusCode, StructureDefinition, StructureField, StructureType,
+    TimestampsToReturn, TypeLoader, VariableTypeId, Variant, WriteValue,
 };
 
 use crate::utils::setup;
 
-use super::utils::{TestNodeManager, Tester};
+use super::utils::{test_server, TestNodeManager, Tester};
 
 fn struct_type_def() -> DataTypeDefinition {
     DataTypeDefinition::Structure(StructureDefinition {
@@ -154,3 +156,159 @@ async fn test_data_type_tree_builder() {
     );
     assert!(v.get_field(2).is_none());
 }
+
+const DYNAMIC_NODESET: &str = r#"
+<UANodeSet xmlns="http://opcfoundation.org/UA/2011/03/UANodeSet.xsd">
+  <NamespaceUris>
+    <Uri>urn:dynamic</Uri>
+  </NamespaceUris>
+  <Aliases>
+    <Alias Alias="HasSubtype">i=45</Alias>
+    <Alias Alias="HasEncoding">i=38</Alias>
+    <Alias Alias="HasTypeDefinition">i=40</Alias>
+    <Alias Alias="Organizes">i=35</Alias>
+  </Aliases>
+  <UADataType NodeId="ns=1;i=1" BrowseName="1:MyStruct">
+    <DisplayName>MyStruct</DisplayName>
+    <References>
+      <Reference ReferenceType="HasSubtype" IsForward="false">i=22</Reference>
+      <Reference ReferenceType="HasEncoding">ns=1;i=2</Reference>
+      <Reference ReferenceType="HasEncoding">ns=1;i=3</Reference>
+      <Reference ReferenceType="HasEncoding">ns=1;i=4</Reference>
+    </References>
+    <Definition Name="1:MyStruct">
+      <Field Name="Interval" DataType="i=290" />
+      <Field Name="Name" DataType="i=12" />
+      <Field Name="Limits" DataType="i=884" />
+    </Definition>
+  </UADataType>
+  <UAObject NodeId="ns=1;i=2" BrowseName="Default Binary">
+    <DisplayName>Default Binary</DisplayName>
+    <References>
+      <Reference ReferenceType="HasTypeDefinition">i=76</Reference>
+    </References>
+  </UAObject>
+  <UAObject NodeId="ns=1;i=3" BrowseName="Default XML">
+    <DisplayName>Default XML</DisplayName>
+    <References>
+      <Reference ReferenceType="HasTypeDefinition">i=76</Reference>
+    </References>
+  </UAObject>
+  <UAObject NodeId="ns=1;i=4" BrowseName="Default JSON">
+    <DisplayName>Default JSON</DisplayName>
+    <References>
+      <Reference ReferenceType="HasTypeDefinition">i=76</Reference>
+    </References>
+  </UAObject>
+  <UAVariable NodeId="ns=1;i=5" BrowseName="1:MyVariable" DataType="ns=1;i=1" AccessLevel="3" UserAccessLevel="3">
+    <DisplayName>MyVariable</DisplayName>
+    <References>
+      <Reference ReferenceType="Organizes" IsForward="false">i=85</Reference>
+      <Reference ReferenceType="HasTypeDefinition">i=63</Reference>
+    </References>
+    <Value>
+      <ExtensionObject xmlns="http://opcfoundation.org/UA/2008/02/Types.xsd">
+        <TypeId><Identifier>ns=1;i=3</Identifier></TypeId>
+        <Body>
+          <MyStruct>
+            <Interval>1.5</Interval>
+            <Name>Hello</Name>
+            <Limits><Low>0</Low><High>10</High></Limits>
+          </MyStruct>
+        </Body>
+      </ExtensionObject>
+    </Value>
+  </UAVariable>
+</UANodeSet>"#;
+
+#[tokio::test]
+async fn test_runtime_nodeset_data_types() {
+    let import = NodeSet2Import::new_str("en", DYNAMIC_NODESET, vec![]).unwrap();
+    let server = test_server().with_node_manager(simple_node_manager_imports(
+        vec![Box::new(import)],
+        "dynamic",
+    ));
+    let mut tester = Tester::new(server, false).await;
+    let ns = tester.handle.get_namespace_index("urn:dynamic").unwrap();
+    let session = tester
+        .connect_and_wait(
+            SecurityPolicy::None,
+            MessageSecurityMode::None,
+            IdentityToken::Anonymous,
+        )
+        .await
+        .unwrap();
+
+    // Load the types from the server on the client side as well.
+    let type_tree = DataTypeTreeBuilder::new(|f| f.namespace == 0 || f.namespace == ns)
+        .build(&session)
+        .await
+        .unwrap();
+    let type_id = NodeId::new(ns, 1);
+    let typ = type_tree.get_struct_type(&type_id).unwrap().clone();
+    let type_tree = Arc::new(type_tree);
+    session.add_type_loader(Arc::new(DynamicTypeLoader::new(type_tree.clone())));
+
+    // The value loaded from the node set can be read.
+    let var_id = NodeId::new(ns, 5);
+    let r = session
+        .read(
+            &[ReadValueId::new_value(var_id.clone())],
+            TimestampsToReturn::Neither,
+            0.0,
+        )
+        .await
+        .unwrap();
+    let Some(Variant::ExtensionObject(v)) = &r[0].value else {
+        panic!("Unexpected value {:?}", r[0]);
+    };
+    let v = v.inner_as::<DynamicStructure>().unwrap();
+    assert_eq!(v.get_field(0), Some(&Variant::from(1.5f64)));
+    assert_eq!(v.get_field(1), Some(&Variant::from("Hello")));
+    // Range is defined in the core namespace.
+    assert_eq!(
+        v.get_field(2),
+        Some(&Variant::from(ExtensionObject::from_message(Range {
+            low: 0.0,
+            high: 10.0
+        })))
+    );
+
+    // Write a new value, which the server must decode using the types from the node set.
+    let new_value = DynamicStructure::new_struct(
+        typ,
+        type_tree.clone(),
+        vec![
+            Variant::from(2.5f64),
+            Variant::from("World"),
+            Variant::from(ExtensionObject::from_message(Range {
+                low: -1.0,
+                high: 1.0,
+            })),
+        ],
+    )
+    .unwrap();
+    let r = session
+        .write(&[WriteValue {
+            node_id: var_id.clone(),
+            attribute_id: AttributeId::Value as u32,
+            index_range: Default::default(),
+            value: DataValue::new_now(new_value.clone().into_variant()),
+        }])
+        .await
+        .unwrap();
+    assert_eq!(r[0], StatusCode::Good);
+
+    let r = session
+        .read(
+            &[ReadValueId::new_value(var_id.clone())],
+            TimestampsToReturn::Neither,
+            0.0,
+        )
+        .await
+        .unwrap();
+    let Some(Variant::ExtensionObject(v)) = &r[0].value else {
+        panic!("Unexpected value {:?}", r[0]);
+    };
+    assert_eq!(v.inner_as::<DynamicStructure>().unwrap(), &new_value);
+}
@@ -0,0 +1,142 @@
+use super::utils::{hostname, test_server, Tester, CLIENT_APPLICATION_URI};
+use opcua::{
+    server::DiscoveryServerConfig,
+    types::{ApplicationType, LocalizedText, RegisteredServer, StatusCode, UAString},
+};
+
+fn registered_server(uri: &str, is_online: bool) -> RegisteredServer {
+    RegisteredServer {
+        server_uri: uri.into(),
+        product_uri: "urn:registered_product".into(),
+        server_names: Some(vec![
+            LocalizedText::new("en", "Registered server"),
+            LocalizedText::new("de", "Registrierter Server"),
+        ]),
+        server_type: ApplicationType::Server,
+        gateway_server_uri: UAString::null(),
+        discovery_urls: Some(vec![format!("opc.tcp://{}:4855/", hostname()).into()]),
+        semaphore_file_path: UAString::null(),
+        is_online,
+    }
+}
+
+async fn discovery_tester() -> Tester {
+    Tester::new(
+        test_server().discovery_server(DiscoveryServerConfig::default()),
+        false,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn register_and_find_servers() {
+    let mut tester = discovery_tester().await;
+    let url = tester.endpoint();
+
+    let servers = tester.client.find_servers(&url, None, None).await.unwrap();
+    assert_eq!(servers.len(), 1);
+    assert_eq!(
+        servers[0].application_type,
+        ApplicationType::DiscoveryServer
+    );
+
+    // This is the same call a server uses to register itself with a discovery server.
+    // The server URI must match the certificate of the secure channel.
+    tester
+        .client
+        .register_server(&url, registered_server(CLIENT_APPLICATION_URI, true))
+        .await
+        .unwrap();
+
+    let servers = tester.client.find_servers(&url, None, None).await.unwrap();
+    assert_eq!(servers.len(), 2);
+
+    let servers = tester
+        .client
+        .find_servers(
+            &url,
+            Some(vec!["de".into()]),
+            Some(vec![CLIENT_APPLICATION_URI.into()]),
+        )
+        .await
+        .unwrap();
+    assert_eq!(servers.len(), 1);
+    assert_eq!(servers[0].application_uri.as_ref(), CLIENT_APPLICATION_URI);
+    assert_eq!(
+        servers[0].application_name.text.as_ref(),
+        "Registrierter Server"
+    );
+
+    let res = tester
+        .client
+        .find_servers_on_network(&url, 0, 0, None)
+        .await
+        .unwrap();
+    let records = res.servers.unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].record_id, 1);
+    assert_eq!(records[0].server_name.as_ref(), "Registered server");
+
+    // Unregister the server again.
+    tester
+        .client
+        .register_server(&url, registered_server(CLIENT_APPLICATION_URI, false))
+        .await
+        .unwrap();
+    let servers = tester.client.find_servers(&url, None, None).await.unwrap();
+    assert_eq!(servers.len(), 1);
+    let res = tester
+        .client
+        .find_servers_on_network(&url, 0, 0, None)
+        .await
+        .unwrap();
+    assert!(res.servers.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn register_invalid_server() {
+    let mut tester = discovery_tester().await;
+    let url = tester.endpoint();
+
+    let mut server = registered_server(CLIENT_APPLICATION_URI, true);
+    server.discovery_urls = None;
+    let err = tester
+        .client
+        .register_server(&url, server)
+        .await
+        .unwrap_err();
+    assert_eq!(err, StatusCode::BadDiscoveryUrlMissing);
+
+    // A server cannot register a different server.
+    let err = tester
+        .client
+        .register_server(&url, registered_server("urn:registered", true))
+        .await
+        .unwrap_err();
+    assert_eq!(err, StatusCode::BadServerUriInvalid);
+    let servers = tester.client.find_servers(&url, None, None).await.unwrap();
+    assert_eq!(servers.len(), 1);
+}
+
+#[tokio::test]
+async fn discovery_not_enabled() {
+    let mut tester = Tester::new(test_server(), false).await;
+    let url = tester.endpoint();
+
+    let servers = tester.client.find_servers(&url, None, None).await.unwrap();
+    assert_eq!(servers.len(), 1);
+    assert_eq!(servers[0].application_type, ApplicationType::Server);
+
+    let err = tester
+        .client
+        .register_server(&url, registered_server("urn:registered", true))
+        .await
+        .unwrap_err();
+    assert_eq!(err, StatusCode::BadServiceUnsupported);
+    let err = tester
+        .client
+        .find_servers_on_network(&url, 0, 0, None)
+        .await
+        .unwrap_err();
+    assert_eq!(err, StatusCode::BadServiceUnsupported);
+}
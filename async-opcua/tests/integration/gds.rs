@@ -0,0 +1,422 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use super::utils::{hostname, test_server, Tester};
+use opcua::{
+    client::gds::GdsClient,
+    crypto::{CertificateStore, SecurityPolicy, X509Data, X509},
+    server::{
+        address_space::{MethodBuilder, ObjectBuilder},
+        diagnostics::NamespaceMetadata,
+        node_manager::memory::{simple_node_manager, SimpleNodeManager},
+    },
+    types::{
+        gds::{
+            ApplicationRecordDataType, GdsMethodId, GdsObjectId, GdsTypeLoader, GDS_NAMESPACE_URI,
+        },
+        ApplicationDescription, ApplicationType, Argument, BinaryEncodable, ByteString,
+        ContextOwned, DataTypeId, LocalizedText, MessageSecurityMode, NodeId, ObjectId, StatusCode,
+        TrustListDataType, TrustListMasks, UAString, Variant,
+    },
+};
+use opcua_client::IdentityToken;
+
+const APPLICATION_URI: &str = "x";
+
+fn client_description() -> ApplicationDescription {
+    ApplicationDescription {
+        application_uri: APPLICATION_URI.into(),
+        application_name: "integration_client".into(),
+        application_type: ApplicationType::Client,
+        ..Default::default()
+    }
+}
+
+fn add_method(
+    nm: &SimpleNodeManager,
+    parent: &NodeId,
+    id: NodeId,
+    name: &str,
+    num_inputs: usize,
+    cb: impl Fn(&[Variant]) -> Result<Vec<Variant>, StatusCode> + Send + Sync + 'static,
+) {
+    let ns = id.namespace;
+    let args: Vec<Argument> = (0..num_inputs)
+        .map(|i| (format!("Arg{i}").as_str(), DataTypeId::BaseDataType).into())
+        .collect();
+    {
+        let mut sp = nm.address_space().write();
+        MethodBuilder::new(&id, name, name)
+            .executable(true)
+            .user_executable(true)
+            .component_of(parent.clone())
+            .input_args(&mut *sp, &NodeId::new(ns, format!("{name}_In")), &args)
+            .insert(&mut *sp);
+    }
+    nm.inner().add_method_callback(id, cb);
+}
+
+/// Set up a minimal stand-in for a GDS certificate manager on the test server.
+///
+/// A real GDS would sign the certificate signing request with its CA. The stand-in instead
+/// issues a certificate for the client key directly, which is enough to exercise the client.
+fn setup_gds(tester: &Tester, client_store: &CertificateStore) -> Arc<ByteString> {
+    let nm = tester
+        .handle
+        .node_managers()
+        .get_of_type::<SimpleNodeManager>()
+        .unwrap();
+    let ns = tester
+        .handle
+        .get_namespace_index(GDS_NAMESPACE_URI)
+        .unwrap();
+    let directory = GdsObjectId::Directory.node_id(ns);
+    let trust_list_id = NodeId::new(ns, "TrustList");
+    {
+        let mut sp = nm.address_space().write();
+        ObjectBuilder::new(&directory, "Directory", "Directory")
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut *sp);
+        ObjectBuilder::new(&trust_list_id, "TrustList", "TrustList")
+            .organized_by(&directory)
+            .insert(&mut *sp);
+    }
+
+    let application_id = NodeId::new(ns, "app");
+    let app_id = application_id.clone();
+    add_method(
+        &nm,
+        &directory,
+        GdsMethodId::Directory_RegisterApplication.node_id(ns),
+        "RegisterApplication",
+        1,
+        move |args| {
+            let Some(Variant::ExtensionObject(obj)) = args.first() else {
+                return Err(StatusCode::BadInvalidArgument);
+            };
+            let Some(record) = obj.inner_as::<ApplicationRecordDataType>() else {
+                return Err(StatusCode::BadInvalidArgument);
+            };
+            if record.application_uri.as_ref() != APPLICATION_URI {
+                return Err(StatusCode::BadInvalidArgument);
+            }
+            Ok(vec![app_id.clone().into()])
+        },
+    );
+
+    let client_pkey = client_store.read_own_pkey().unwrap();
+    let (ca, _) = {
+        let mut data = X509Data::sample_cert();
+        data.common_name = "Stand-in CA".to_owned();
+        data.key_size = 1024;
+        X509::cert_and_pkey(&data).unwrap()
+    };
+    let mut data: X509Data = client_description().into();
+    data.alt_host_names.add_dns(hostname());
+    data.certificate_duration_days = 30;
+
+    // Each request is approved on the second call to FinishRequest.
+    let pending = AtomicBool::new(true);
+    add_method(
+        &nm,
+        &directory,
+        GdsMethodId::Directory_StartSigningRequest.node_id(ns),
+        "StartSigningRequest",
+        4,
+        move |args| match &args[3] {
+            Variant::ByteString(csr) if !csr.is_null_or_empty() => {
+                Ok(vec![NodeId::new(ns, "signing").into()])
+            }
+            _ => Err(StatusCode::BadInvalidArgument),
+        },
+    );
+    add_method(
+        &nm,
+        &directory,
+        GdsMethodId::Directory_StartNewKeyPairRequest.node_id(ns),
+        "StartNewKeyPairRequest",
+        7,
+        move |args| match &args[5] {
+            Variant::String(s) if s.as_ref() == "PEM" => {
+                Ok(vec![NodeId::new(ns, "new_key").into()])
+            }
+            _ => Err(StatusCode::BadInvalidArgument),
+        },
+    );
+    let pki_dir = format!("./pki-client/{}/gds", tester.test_id);
+    let app_id = application_id.clone();
+    add_method(
+        &nm,
+        &directory,
+        GdsMethodId::Directory_FinishRequest.node_id(ns),
+        "FinishRequest",
+        2,
+        move |args| {
+            if args[0] != Variant::from(app_id.clone()) {
+                return Err(StatusCode::BadInvalidArgument);
+            }
+            if pending.fetch_xor(true, Ordering::Relaxed) {
+                return Err(StatusCode::BadNothingToDo);
+            }
+            let (cert, pkey) = match &args[1] {
+                Variant::NodeId(id) if **id == NodeId::new(ns, "signing") => (
+                    X509::from_pkey(&client_pkey, &data).unwrap(),
+                    ByteString::null(),
+                ),
+                Variant::NodeId(id) if **id == NodeId::new(ns, "new_key") => {
+                    let cert_path = format!("{pki_dir}/cert.der");
+                    let pkey_path = format!("{pki_dir}/private.pem");
+                    CertificateStore::create_certificate_and_key(
+                        &data,
+                        true,
+                        cert_path.as_ref(),
+                        pkey_path.as_ref(),
+                    )
+                    .unwrap();
+                    (
+                        CertificateStore::read_cert(cert_path.as_ref()).unwrap(),
+                        std::fs::read(&pkey_path).unwrap().into(),
+                    )
+                }
+                _ => return Err(StatusCode::BadInvalidArgument),
+            };
+            Ok(vec![
+                cert.as_byte_string().into(),
+                pkey.into(),
+                vec![ca.as_byte_string()].into(),
+            ])
+        },
+    );
+
+    let id = trust_list_id.clone();
+    add_method(
+        &nm,
+        &directory,
+        GdsMethodId::Directory_GetTrustList.node_id(ns),
+        "GetTrustList",
+        2,
+        move |_| Ok(vec![id.clone().into()]),
+    );
+
+    // The trust list contains the server certificate, and is read in small
+    // chunks to make sure the client reads the whole file.
+    let server_cert = tester
+        .handle
+        .info()
+        .server_keypair
+        .load()
+        .certificate
+        .clone()
+        .unwrap();
+    let trust_list = TrustListDataType {
+        specified_lists: TrustListMasks::All as u32,
+        trusted_certificates: Some(vec![server_cert.as_byte_string()]),
+        trusted_crls: Some(Vec::new()),
+        issuer_certificates: Some(Vec::new()),
+        issuer_crls: Some(Vec::new()),
+    };
+    let data = Arc::new(ByteString::from(
+        trust_list.encode_to_vec(&ContextOwned::default().context()),
+    ));
+    let position = Arc::new(AtomicU32::new(0));
+    let pos = position.clone();
+    add_method(
+        &nm,
+        &trust_list_id,
+        NodeId::new(ns, "TrustList_Open"),
+        "Open",
+        1,
+        move |args| {
+            if args[0] != Variant::from(1u8) {
+                return Err(StatusCode::BadInvalidArgument);
+            }
+            pos.store(0, Ordering::Relaxed);
+            Ok(vec![1u32.into()])
+        },
+    );
+    let file = data.clone();
+    add_method(
+        &nm,
+        &trust_list_id,
+        NodeId::new(ns, "TrustList_Read"),
+        "Read",
+        2,
+        move |args| {
+            let Variant::Int32(len) = args[1] else {
+                return Err(StatusCode::BadInvalidArgument);
+            };
+            let bytes = file.as_ref().as_ref();
+            let start = position.load(Ordering::Relaxed) as usize;
+            let end = bytes.len().min(start + (len as usize).min(100));
+            position.store(end as u32, Ordering::Relaxed);
+            Ok(vec![ByteString::from(bytes[start..end].to_vec()).into()])
+        },
+    );
+    add_method(
+        &nm,
+        &trust_list_id,
+        NodeId::new(ns, "TrustList_Close"),
+        "Close",
+        1,
+        |_| Ok(vec![]),
+    );
+
+    data
+}
+
+fn gds_server() -> opcua::server::ServerBuilder {
+    test_server()
+        .with_node_manager(simple_node_manager(
+            NamespaceMetadata {
+                namespace_uri: GDS_NAMESPACE_URI.to_owned(),
+                ..Default::default()
+            },
+            "gds",
+        ))
+        .with_type_loader(Arc::new(GdsTypeLoader))
+}
+
+async fn gds_client(tester: &mut Tester) -> GdsClient {
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    session.wait_for_connection().await;
+    GdsClient::new(session)
+        .await
+        .unwrap()
+        .poll_interval(Duration::from_millis(50))
+        .request_timeout(Duration::from_secs(5))
+}
+
+async fn register(gds: &GdsClient) -> NodeId {
+    gds.register_application(ApplicationRecordDataType {
+        application_uri: APPLICATION_URI.into(),
+        application_type: ApplicationType::Client,
+        application_names: Some(vec![LocalizedText::from("integration_client")]),
+        ..Default::default()
+    })
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn gds_signing_request() {
+    let mut tester = Tester::new(gds_server(), false).await;
+    let store = tester.client.certificate_store().clone();
+    setup_gds(&tester, &store.read());
+    let gds = gds_client(&mut tester).await;
+
+    let application_id = register(&gds).await;
+    assert_eq!(application_id, NodeId::new(gds.namespace(), "app"));
+
+    let old_cert = store.read().read_own_cert().unwrap();
+    let issued = gds
+        .request_certificate(&store, &application_id)
+        .await
+        .unwrap();
+    assert!(issued.private_key.is_none());
+    assert_eq!(issued.issuer_certificates.len(), 1);
+
+    {
+        let store_ref = store.read();
+        let own_cert = store_ref.read_own_cert().unwrap();
+        assert_eq!(own_cert, issued.certificate);
+        assert_ne!(own_cert, old_cert);
+        assert!(own_cert.is_key_pair(&store_ref.read_own_pkey().unwrap()));
+        assert!(store_ref
+            .read_trust_list(TrustListMasks::IssuerCertificates as u32)
+            .issuer_certificates
+            .unwrap()
+            .contains(&issued.issuer_certificates[0].as_byte_string()));
+    }
+
+    // New secure channels use the issued certificate.
+    let session = tester
+        .connect_and_wait(
+            SecurityPolicy::Basic256Sha256,
+            MessageSecurityMode::SignAndEncrypt,
+            IdentityToken::Anonymous,
+        )
+        .await
+        .unwrap();
+    session.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn gds_new_key_pair() {
+    let mut tester = Tester::new(gds_server(), false).await;
+    let store = tester.client.certificate_store().clone();
+    setup_gds(&tester, &store.read());
+    let gds = gds_client(&mut tester).await;
+    let application_id = register(&gds).await;
+
+    let old_pkey = store.read().read_own_pkey().unwrap();
+    let issued = gds
+        .request_new_key_pair(
+            &store,
+            &application_id,
+            "CN=integration_client",
+            vec![UAString::from(hostname())],
+        )
+        .await
+        .unwrap();
+    let pkey = issued.private_key.unwrap();
+    assert!(!issued.certificate.is_key_pair(&old_pkey));
+
+    let store_ref = store.read();
+    assert_eq!(store_ref.read_own_cert().unwrap(), issued.certificate);
+    assert!(store_ref
+        .read_own_cert()
+        .unwrap()
+        .is_key_pair(&store_ref.read_own_pkey().unwrap()));
+    assert!(issued.certificate.is_key_pair(&pkey));
+}
+
+#[tokio::test]
+async fn gds_trust_list() {
+    let mut tester = Tester::new(gds_server(), false).await;
+    let store = tester.client.certificate_store().clone();
+    let data = setup_gds(&tester, &store.read());
+    let gds = gds_client(&mut tester).await;
+    let application_id = register(&gds).await;
+
+    let trust_list = gds
+        .update_trust_list(&store, &application_id)
+        .await
+        .unwrap();
+    // The file is larger than a single read from the stand-in.
+    assert!(data.as_ref().as_ref().len() > 100);
+    let server_cert = tester
+        .handle
+        .info()
+        .server_keypair
+        .load()
+        .certificate
+        .as_ref()
+        .unwrap()
+        .as_byte_string();
+    assert_eq!(
+        trust_list.trusted_certificates,
+        Some(vec![server_cert.clone()])
+    );
+    assert_eq!(
+        store
+            .read()
+            .read_trust_list(TrustListMasks::All as u32)
+            .trusted_certificates,
+        Some(vec![server_cert])
+    );
+
+    // Registering an application with the wrong URI fails.
+    let r = gds
+        .register_application(ApplicationRecordDataType {
+            application_uri: "other".into(),
+            ..Default::default()
+        })
+        .await;
+    assert_eq!(r, Err(StatusCode::BadInvalidArgument));
+}
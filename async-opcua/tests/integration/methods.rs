@@ -5,14 +5,22 @@ use std::{
 
 use crate::utils::ChannelNotifications;
 
-use super::utils::setup;
+use super::utils::{setup, test_server, Tester};
 use opcua::{
-    server::address_space::MethodBuilder,
+    crypto::SecurityPolicy,
+    nodes::NodeType,
+    server::{
+        address_space::MethodBuilder,
+        diagnostics::NamespaceMetadata,
+        node_manager::memory::{simple_node_manager, SimpleNodeManager},
+    },
     types::{
-        AttributeId, CallMethodRequest, DataTypeId, NodeId, ObjectId, StatusCode, Variant,
-        VariantTypeId,
+        Argument, AttributeId, BrowseDirection, CallMethodRequest, DataEncoding, DataTypeId, Guid,
+        MessageSecurityMode, NodeId, NumericRange, ObjectId, QualifiedName, StatusCode, UAString,
+        Variant, VariantTypeId,
     },
 };
+use opcua_client::{CallError, IdentityToken, OperationLimits, Session};
 use opcua_types::{
     MonitoredItemCreateRequest, MonitoringParameters, ReadValueId, TimestampsToReturn, VariableId,
     VariantScalarTypeId,
@@ -246,6 +254,9 @@ async fn call_limits() {
     let e = session.call(Vec::new()).await.unwrap_err();
     assert_eq!(e, StatusCode::BadNothingToDo);
 
+    // Disable request splitting in the client, so that the request reaches the server.
+    session.set_operation_limits(OperationLimits::default());
+
     // Call too many
     let e = session
         .call(
@@ -304,3 +315,239 @@ async fn call_get_monitored_items() {
     assert_eq!(handles.len(), 1);
     assert_eq!(15, handles[0]);
 }
+
+/// Set up a server with a typed `Concat` method, returning the tester,
+/// the node manager, the method ID, and a connected session.
+async fn setup_typed() -> (Tester, Arc<SimpleNodeManager>, NodeId, Arc<Session>) {
+    let server = test_server().with_node_manager(simple_node_manager(
+        NamespaceMetadata {
+            namespace_uri: "urn:typed".to_owned(),
+            ..Default::default()
+        },
+        "typed",
+    ));
+    let mut tester = Tester::new(server, false).await;
+    let nm = tester
+        .handle
+        .node_managers()
+        .get_of_type::<SimpleNodeManager>()
+        .unwrap();
+    let ns = tester.handle.get_namespace_index("urn:typed").unwrap();
+    let id = NodeId::new(ns, "Concat");
+    {
+        let mut sp = nm.address_space().write();
+        assert!(nm.inner().add_typed_method(
+            &mut sp,
+            MethodBuilder::new(&id, "Concat", "Concat")
+                .executable(true)
+                .user_executable(true)
+                .component_of(ObjectId::ObjectsFolder),
+            &["Text", "Count"],
+            &["Result", "Length"],
+            |text: UAString, count: u32| async move {
+                if count > 10 {
+                    return Err(StatusCode::BadOutOfRange);
+                }
+                let res = text.as_ref().repeat(count as usize);
+                let len = res.len() as u32;
+                Ok((res, len))
+            },
+        ));
+    }
+
+    let session = tester
+        .connect_and_wait(
+            SecurityPolicy::None,
+            MessageSecurityMode::None,
+            IdentityToken::Anonymous,
+        )
+        .await
+        .unwrap();
+    (tester, nm, id, session)
+}
+
+#[tokio::test]
+async fn call_typed() {
+    let (tester, nm, id, session) = setup_typed().await;
+
+    let call = |args: Vec<Variant>| CallMethodRequest {
+        object_id: ObjectId::ObjectsFolder.into(),
+        method_id: id.clone(),
+        input_arguments: Some(args),
+    };
+
+    let r = session
+        .call_one(call(vec!["ab".into(), 3u32.into()]))
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::Good);
+    let outputs = r.output_arguments.unwrap();
+    assert_eq!(outputs, vec![Variant::from("ababab"), Variant::UInt32(6)]);
+
+    // Arguments are cast to the expected type where possible.
+    let r = session
+        .call_one(call(vec!["ab".into(), 2u8.into()]))
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::Good);
+
+    let r = session
+        .call_one(call(vec!["ab".into(), 11u32.into()]))
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::BadOutOfRange);
+
+    let r = session.call_one(call(vec!["ab".into()])).await.unwrap();
+    assert_eq!(r.status_code, StatusCode::BadArgumentsMissing);
+
+    let r = session
+        .call_one(call(vec!["ab".into(), 1u32.into(), 1u32.into()]))
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::BadTooManyArguments);
+
+    let r = session
+        .call_one(call(vec!["ab".into(), Variant::from(NodeId::null())]))
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::BadInvalidArgument);
+    assert_eq!(
+        r.input_argument_results.unwrap(),
+        vec![StatusCode::Good, StatusCode::BadTypeMismatch]
+    );
+
+    // A plain callback replaces the typed handler, and the other way around.
+    nm.inner()
+        .add_method_callback(id.clone(), |_| Ok(vec!["plain".into()]));
+    let r = session
+        .call_one(call(vec!["ab".into(), 3u32.into()]))
+        .await
+        .unwrap();
+    assert_eq!(r.output_arguments.unwrap(), vec![Variant::from("plain")]);
+    nm.inner()
+        .add_typed_method_callback(id.clone(), |text: UAString| async move { Ok((text,)) });
+    let r = session.call_one(call(vec!["ab".into()])).await.unwrap();
+    assert_eq!(r.output_arguments.unwrap(), vec![Variant::from("ab")]);
+
+    // The argument properties are generated from the method signature.
+    let sp = nm.address_space().read();
+    let type_tree = tester.handle.type_tree().read();
+    let args = |name: &str| -> Vec<Argument> {
+        let prop = sp
+            .find_node_by_browse_path(
+                &id,
+                None::<(NodeId, bool)>,
+                &*type_tree,
+                BrowseDirection::Forward,
+                &[QualifiedName::new(0, name)],
+            )
+            .unwrap();
+        let NodeType::Variable(v) = prop else {
+            panic!("Expected variable");
+        };
+        v.value(
+            TimestampsToReturn::Neither,
+            &NumericRange::None,
+            &DataEncoding::Binary,
+            0.0,
+        )
+        .value
+        .unwrap()
+        .try_cast_to()
+        .unwrap()
+    };
+    let inputs = args("InputArguments");
+    assert_eq!(inputs.len(), 2);
+    assert_eq!(inputs[0].name.as_ref(), "Text");
+    assert_eq!(inputs[0].data_type, DataTypeId::String);
+    assert_eq!(inputs[1].name.as_ref(), "Count");
+    assert_eq!(inputs[1].data_type, DataTypeId::UInt32);
+    let outputs = args("OutputArguments");
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(outputs[0].name.as_ref(), "Result");
+    assert_eq!(outputs[1].data_type, DataTypeId::UInt32);
+}
+
+#[tokio::test]
+async fn call_typed_client() {
+    let (_tester, _nm, id, session) = setup_typed().await;
+
+    let (res, len): (String, u32) = session
+        .call_typed(ObjectId::ObjectsFolder, id.clone(), ("ab", 2u32))
+        .await
+        .unwrap();
+    assert_eq!(res, "abab");
+    assert_eq!(len, 4);
+
+    // Method errors are returned as status codes.
+    let err = session
+        .call_typed::<_, (String, u32)>(ObjectId::ObjectsFolder, id.clone(), ("ab", 11u32))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, CallError::Status(StatusCode::BadOutOfRange)));
+
+    // Argument errors from the server are returned per argument.
+    let err = session
+        .call_typed::<_, (String, u32)>(ObjectId::ObjectsFolder, id.clone(), ("ab", Guid::new()))
+        .await
+        .unwrap_err();
+    let CallError::InputArguments(errs) = err else {
+        panic!("Expected argument errors, got {err}");
+    };
+    assert_eq!(errs.len(), 1);
+    assert_eq!(errs[0].index, 1);
+    assert_eq!(errs[0].status, StatusCode::BadTypeMismatch);
+
+    // Outputs that cannot be decoded are reported.
+    let err = session
+        .call_typed::<_, (String, Guid)>(ObjectId::ObjectsFolder, id.clone(), ("ab", 1u32))
+        .await
+        .unwrap_err();
+    let CallError::OutputArguments(errs) = err else {
+        panic!("Expected output errors, got {err}");
+    };
+    assert_eq!(errs[0].index, 1);
+
+    // With validation, the arguments are read from the server and checked before calling.
+    let arguments = session.read_method_arguments(&id).await.unwrap();
+    assert_eq!(arguments.inputs.len(), 2);
+    assert_eq!(arguments.outputs[0].name.as_ref(), "Result");
+
+    // Errors reading the arguments are returned, not treated as a method without arguments.
+    let err = session
+        .read_method_arguments(&NodeId::new(id.namespace, "NoSuchMethod"))
+        .await
+        .unwrap_err();
+    assert_eq!(err, StatusCode::BadNodeIdUnknown);
+
+    let (res, _): (String, u32) = session
+        .call_typed_validated(ObjectId::ObjectsFolder, id.clone(), ("ab", 1u32))
+        .await
+        .unwrap();
+    assert_eq!(res, "ab");
+
+    let err = session
+        .call_typed_validated::<_, (String, u32)>(ObjectId::ObjectsFolder, id.clone(), ("ab", 1i32))
+        .await
+        .unwrap_err();
+    let CallError::InputArguments(errs) = err else {
+        panic!("Expected argument errors, got {err}");
+    };
+    assert_eq!(errs[0].index, 1);
+    assert_eq!(errs[0].name.as_ref().unwrap().as_ref(), "Count");
+    assert_eq!(errs[0].status, StatusCode::BadTypeMismatch);
+
+    let err = session
+        .call_typed_with_arguments::<_, (String, u32)>(
+            ObjectId::ObjectsFolder,
+            id.clone(),
+            ("ab",),
+            &arguments,
+        )
+        .await
+        .unwrap_err();
+    let CallError::InputArguments(errs) = err else {
+        panic!("Expected argument errors, got {err}");
+    };
+    assert_eq!(errs[0].status, StatusCode::BadArgumentsMissing);
+}
@@ -1,10 +1,21 @@
 mod browse;
+mod conditions;
 mod core_tests;
 mod custom_types;
+mod discovery;
+mod gds;
 mod methods;
+mod node_cache;
 mod node_management;
+mod operation_limits;
+mod query;
 mod read;
+mod reverse_connect;
+mod roles;
+mod server_configuration;
 mod subscriptions;
+mod transport;
+mod websocket;
 mod write;
 
 pub use super::utils;
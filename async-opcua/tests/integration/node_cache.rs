@@ -0,0 +1,279 @@
+use std::time::Duration;
+
+use super::utils::setup;
+use opcua::{
+    client::node_cache::NodeCache,
+    core_namespace::events::{BaseModelChangeEventType, GeneralModelChangeEventType},
+    nodes::{BaseEventType, Event},
+    server::address_space::{ObjectBuilder, VariableBuilder},
+    types::{
+        AttributeId, BrowseDirection, ByteString, DataTypeId, LocalizedText,
+        ModelChangeStructureDataType, NodeId, ObjectId, ObjectTypeId, QualifiedName,
+        ReferenceTypeId, RelativePath, StatusCode, VariableTypeId, Variant,
+    },
+};
+
+fn set_display_name(nm: &super::utils::TestNodeManager, id: &NodeId, name: &str) {
+    nm.address_space()
+        .write()
+        .find_mut(id)
+        .unwrap()
+        .as_mut_node()
+        .set_attribute(AttributeId::DisplayName, LocalizedText::from(name).into())
+        .unwrap();
+}
+
+fn display_name(value: Option<Variant>) -> String {
+    match value {
+        Some(Variant::LocalizedText(t)) => t.text.as_ref().to_owned(),
+        v => panic!("Expected localized text, got {v:?}"),
+    }
+}
+
+#[tokio::test]
+async fn node_cache_read_and_invalidate() {
+    let (tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "CachedVar", "CachedVar")
+            .value(1)
+            .data_type(DataTypeId::Int32)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    let cache = NodeCache::new(session.clone());
+    let r = cache
+        .read_attributes(
+            &id,
+            &[
+                AttributeId::DisplayName,
+                AttributeId::Value,
+                AttributeId::EventNotifier,
+            ],
+        )
+        .await
+        .unwrap();
+    assert_eq!(display_name(r[0].value.clone()), "CachedVar");
+    assert_eq!(r[1].value, Some(Variant::Int32(1)));
+    assert_eq!(r[2].status(), StatusCode::BadAttributeIdInvalid);
+
+    // Change the node on the server. The display name is cached, the value is not.
+    set_display_name(&nm, &id, "Renamed");
+    nm.address_space()
+        .write()
+        .find_mut(&id)
+        .unwrap()
+        .as_mut_node()
+        .set_attribute(AttributeId::Value, Variant::Int32(2))
+        .unwrap();
+
+    let r = cache
+        .read_attributes(&id, &[AttributeId::DisplayName, AttributeId::Value])
+        .await
+        .unwrap();
+    assert_eq!(display_name(r[0].value.clone()), "CachedVar");
+    assert_eq!(r[1].value, Some(Variant::Int32(2)));
+
+    cache.invalidate(&id);
+    let r = cache
+        .read_attribute(&id, AttributeId::DisplayName)
+        .await
+        .unwrap();
+    assert_eq!(display_name(r.value), "Renamed");
+
+    // References are cached as well.
+    let refs = cache
+        .find_references(
+            &id,
+            BrowseDirection::Inverse,
+            ReferenceTypeId::HierarchicalReferences,
+            true,
+        )
+        .await
+        .unwrap();
+    assert_eq!(refs.len(), 1);
+    assert_eq!(refs[0].node_id.node_id, ObjectId::ObjectsFolder);
+    assert!(cache
+        .is_subtype_of(
+            &ReferenceTypeId::Organizes.into(),
+            &ReferenceTypeId::HierarchicalReferences.into()
+        )
+        .await
+        .unwrap());
+    assert!(!cache
+        .is_subtype_of(
+            &ReferenceTypeId::Organizes.into(),
+            &ReferenceTypeId::NonHierarchicalReferences.into()
+        )
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn node_cache_browse_path() {
+    let (tester, nm, session) = setup().await;
+
+    let obj_id = nm.inner().next_node_id();
+    let var_id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        ObjectBuilder::new(&obj_id, "CacheObject", "CacheObject")
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&ObjectTypeId::BaseObjectType.into()),
+        Vec::new(),
+    );
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&var_id, "CacheVariable", "CacheVariable")
+            .value(1)
+            .data_type(DataTypeId::Int32)
+            .build()
+            .into(),
+        &obj_id,
+        &ReferenceTypeId::HasComponent.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    let cache = NodeCache::new(session.clone());
+    let path = RelativePath::from(
+        &[
+            QualifiedName::from("CacheObject"),
+            QualifiedName::from("CacheVariable"),
+        ][..],
+    );
+    let r = cache
+        .resolve_browse_path(&ObjectId::ObjectsFolder.into(), &path)
+        .await
+        .unwrap();
+    assert_eq!(r, vec![var_id.clone()]);
+
+    let path = RelativePath::from(&[QualifiedName::from("Missing")][..]);
+    let r = cache.resolve_browse_path(&obj_id, &path).await.unwrap();
+    assert!(r.is_empty());
+}
+
+#[tokio::test]
+async fn node_cache_model_change_event() {
+    let (tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        ObjectBuilder::new(&id, "EventObject", "EventObject")
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&ObjectTypeId::BaseObjectType.into()),
+        Vec::new(),
+    );
+
+    let cache = NodeCache::new(session.clone());
+    cache
+        .subscribe_to_changes(Duration::from_millis(100))
+        .await
+        .unwrap();
+
+    let r = cache
+        .read_attribute(&id, AttributeId::DisplayName)
+        .await
+        .unwrap();
+    assert_eq!(display_name(r.value), "EventObject");
+
+    set_display_name(&nm, &id, "ChangedObject");
+    let event = GeneralModelChangeEventType {
+        base: BaseModelChangeEventType {
+            base: BaseEventType::new_now(
+                ObjectTypeId::GeneralModelChangeEventType,
+                ByteString::from(vec![1u8]),
+                "Model changed",
+            )
+            .set_source_node(ObjectId::Server.into()),
+        },
+        changes: vec![ModelChangeStructureDataType {
+            affected: id.clone(),
+            affected_type: ObjectTypeId::BaseObjectType.into(),
+            verb: 16,
+        }],
+    };
+    tester
+        .handle
+        .subscriptions()
+        .notify_events([(&event as &dyn Event, &ObjectId::Server.into())].into_iter());
+
+    let mut name = String::new();
+    for _ in 0..50 {
+        let r = cache
+            .read_attribute(&id, AttributeId::DisplayName)
+            .await
+            .unwrap();
+        name = display_name(r.value);
+        if name == "ChangedObject" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(name, "ChangedObject");
+
+    cache.unsubscribe().await.unwrap();
+}
+
+#[tokio::test]
+async fn node_cache_save_load() {
+    let (tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        ObjectBuilder::new(&id, "SavedObject", "SavedObject")
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&ObjectTypeId::BaseObjectType.into()),
+        Vec::new(),
+    );
+
+    let cache = NodeCache::new(session.clone());
+    let refs = cache.references(&id).await.unwrap();
+    let r = cache
+        .read_attribute(&id, AttributeId::DisplayName)
+        .await
+        .unwrap();
+    assert_eq!(display_name(r.value), "SavedObject");
+
+    let dir = tempdir::TempDir::new("node_cache").unwrap();
+    let path = dir.path().join("cache.bin");
+    cache.save(&path).await.unwrap();
+
+    // Load into a new cache, then change the server to check that we get the cached values.
+    let cache = NodeCache::new(session.clone());
+    assert!(cache.load(&path).await.unwrap());
+    set_display_name(&nm, &id, "Changed");
+    let r = cache
+        .read_attributes(&id, &[AttributeId::DisplayName, AttributeId::BrowseName])
+        .await
+        .unwrap();
+    assert_eq!(display_name(r[0].value.clone()), "SavedObject");
+    assert_eq!(
+        r[1].value,
+        Some(Variant::from(QualifiedName::from("SavedObject")))
+    );
+    assert_eq!(refs, cache.references(&id).await.unwrap());
+}
@@ -1,12 +1,25 @@
-use super::utils::setup;
+use std::time::Duration;
+
+use super::utils::{read_value_id, setup, ChannelNotifications};
 use opcua::{
-    server::address_space::{EventNotifier, NodeBase, NodeType, ObjectBuilder},
+    client::Session,
+    server::address_space::{
+        AccessLevel, EventNotifier, InstanceBuilder, MethodBuilder, NodeBase, NodeType,
+        ObjectBuilder, ObjectTypeBuilder, ReferenceDirection, VariableBuilder,
+    },
     types::{
-        AddNodeAttributes, AddNodesItem, AddReferencesItem, DeleteNodesItem, DeleteReferencesItem,
-        ExpandedNodeId, NodeClass, NodeId, ObjectAttributes, ObjectId, ObjectTypeId,
-        ReferenceTypeId, StatusCode,
+        AddNodeAttributes, AddNodesItem, AddReferencesItem, AttributeId, BrowseDescription,
+        BrowseDirection, BrowseResultMask, CallMethodRequest, ContentFilter, ContentFilterElement,
+        DataTypeId, DataValue, DeleteNodesItem, DeleteReferencesItem, EventFilter, ExpandedNodeId,
+        ExtensionObject, FilterOperator, ModelChangeStructureDataType,
+        ModelChangeStructureVerbMask, MonitoredItemCreateRequest, MonitoringMode,
+        MonitoringParameters, NodeClass, NodeClassMask, NodeId, ObjectAttributes, ObjectId,
+        ObjectTypeId, Operand, QualifiedName, Range, ReadValueId, ReferenceTypeId,
+        SemanticChangeStructureDataType, SimpleAttributeOperand, StatusCode, TimestampsToReturn,
+        VariableTypeId, Variant,
     },
 };
+use tokio::{sync::mpsc::UnboundedReceiver, time::timeout};
 
 #[tokio::test]
 async fn add_delete_node() {
@@ -212,3 +225,420 @@ async fn add_delete_reference_limits() {
         .unwrap_err();
     assert_eq!(e, StatusCode::BadTooManyOperations);
 }
+
+#[tokio::test]
+async fn instantiate_type() {
+    let (tester, nm, session) = setup().await;
+
+    let type_id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        ObjectTypeBuilder::new(&type_id, "DeviceType", "DeviceType")
+            .build()
+            .into(),
+        &ObjectTypeId::BaseObjectType.into(),
+        &ReferenceTypeId::HasSubtype.into(),
+        None,
+        Vec::new(),
+    );
+    let mandatory = ObjectId::ModellingRule_Mandatory.into();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&nm.inner().next_node_id(), "Status", "Status")
+            .value(3)
+            .data_type(DataTypeId::Int32)
+            .build()
+            .into(),
+        &type_id,
+        &ReferenceTypeId::HasComponent.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        vec![(
+            &mandatory,
+            ReferenceTypeId::HasModellingRule.into(),
+            ReferenceDirection::Forward,
+        )],
+    );
+
+    let id = NodeId::new(type_id.namespace, "Device1");
+    let ids = nm
+        .instantiate(
+            tester.handle.type_tree(),
+            InstanceBuilder::new(&id, "Device1", "Device1", type_id.clone())
+                .organized_by(ObjectId::ObjectsFolder),
+        )
+        .unwrap();
+    let status_id = NodeId::new(type_id.namespace, "Device1.Status");
+    assert_eq!(ids, vec![id.clone(), status_id.clone()]);
+
+    let r = session
+        .browse(
+            &[BrowseDescription {
+                node_id: id.clone(),
+                browse_direction: BrowseDirection::Forward,
+                reference_type_id: ReferenceTypeId::References.into(),
+                include_subtypes: true,
+                node_class_mask: NodeClassMask::all().bits(),
+                result_mask: BrowseResultMask::All as u32,
+            }],
+            1000,
+            None,
+        )
+        .await
+        .unwrap();
+    let refs = r[0].references.clone().unwrap_or_default();
+    assert_eq!(refs.len(), 2);
+    assert!(refs
+        .iter()
+        .any(|r| r.node_id.node_id == status_id && r.browse_name == "Status".into()));
+    assert!(refs.iter().any(|r| r.node_id.node_id == type_id
+        && r.reference_type_id == ReferenceTypeId::HasTypeDefinition));
+
+    let r = session
+        .read(
+            &[read_value_id(AttributeId::Value, &status_id)],
+            TimestampsToReturn::Neither,
+            0.0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(r[0].value, Some(Variant::Int32(3)));
+
+    // Instantiating again with the same node ID fails.
+    let err = nm
+        .instantiate(
+            tester.handle.type_tree(),
+            InstanceBuilder::new(&id, "Device1", "Device1", type_id),
+        )
+        .unwrap_err();
+    assert_eq!(err, StatusCode::BadNodeIdExists);
+
+    // Types in the core namespace are read from the core node manager.
+    let file_id = NodeId::new(id.namespace, "File1");
+    let ids = nm
+        .instantiate(
+            tester.handle.type_tree(),
+            InstanceBuilder::new(&file_id, "File1", "File1", ObjectTypeId::FileType)
+                .organized_by(ObjectId::ObjectsFolder),
+        )
+        .unwrap();
+    assert!(ids.contains(&NodeId::new(id.namespace, "File1.Open")));
+    assert!(ids.contains(&NodeId::new(id.namespace, "File1.Open.InputArguments")));
+
+    // Types that do not exist on the server cannot be instantiated.
+    let err = nm
+        .instantiate(
+            tester.handle.type_tree(),
+            InstanceBuilder::new(
+                &NodeId::new(id.namespace, "Missing"),
+                "Missing",
+                "Missing",
+                NodeId::new(id.namespace, "MissingType"),
+            ),
+        )
+        .unwrap_err();
+    assert_eq!(err, StatusCode::BadTypeDefinitionInvalid);
+}
+
+/// Subscribe to events of the given type on the server object, selecting the `Changes` field.
+async fn subscribe_changes(
+    session: &Session,
+    event_type: ObjectTypeId,
+) -> UnboundedReceiver<(ReadValueId, Option<Vec<Variant>>)> {
+    let (notifs, _, events) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+
+    let filter = EventFilter {
+        select_clauses: Some(vec![SimpleAttributeOperand {
+            type_definition_id: event_type.into(),
+            browse_path: Some(vec![QualifiedName::from("Changes")]),
+            attribute_id: AttributeId::Value as u32,
+            index_range: Default::default(),
+        }]),
+        where_clause: ContentFilter {
+            elements: Some(vec![ContentFilterElement::from((
+                FilterOperator::OfType,
+                vec![Operand::literal(NodeId::from(event_type))],
+            ))]),
+        },
+    };
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: ObjectId::Server.into(),
+                    attribute_id: AttributeId::EventNotifier as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    filter: ExtensionObject::from_message(filter),
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].result.status_code, StatusCode::Good);
+    events
+}
+
+async fn next_changes<T: Clone + Send + Sync + 'static>(
+    events: &mut UnboundedReceiver<(ReadValueId, Option<Vec<Variant>>)>,
+) -> Vec<T> {
+    let evt = timeout(Duration::from_millis(1000), events.recv())
+        .await
+        .unwrap()
+        .unwrap()
+        .1
+        .unwrap();
+    let Variant::Array(arr) = &evt[0] else {
+        panic!("Expected array of changes, got {:?}", evt[0]);
+    };
+    arr.values
+        .iter()
+        .map(|v| match v {
+            Variant::ExtensionObject(o) => o.inner_as::<T>().unwrap().clone(),
+            v => panic!("Expected extension object, got {v:?}"),
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn model_change_events() {
+    let (_tester, _nm, session) = setup().await;
+    let mut events = subscribe_changes(&session, ObjectTypeId::GeneralModelChangeEventType).await;
+
+    let r = session
+        .add_nodes(&[AddNodesItem {
+            parent_node_id: ObjectId::ObjectsFolder.into(),
+            reference_type_id: ReferenceTypeId::Organizes.into(),
+            requested_new_node_id: ExpandedNodeId::null(),
+            browse_name: "ChangedNode".into(),
+            node_class: NodeClass::Object,
+            node_attributes: AddNodeAttributes::Object(ObjectAttributes {
+                specified_attributes: 1 << 6,
+                display_name: "ChangedNode".into(),
+                ..Default::default()
+            })
+            .as_extension_object(),
+            type_definition: ExpandedNodeId::new(ObjectTypeId::FolderType),
+        }])
+        .await
+        .unwrap();
+    assert_eq!(r[0].status_code, StatusCode::Good);
+    let id = r[0].added_node_id.clone();
+
+    let changes: Vec<ModelChangeStructureDataType> = next_changes(&mut events).await;
+    assert_eq!(changes.len(), 2);
+    let added = changes.iter().find(|c| c.affected == id).unwrap();
+    assert_eq!(added.verb, ModelChangeStructureVerbMask::NodeAdded as u8);
+    assert_eq!(added.affected_type, ObjectTypeId::FolderType);
+    let parent = changes
+        .iter()
+        .find(|c| c.affected == ObjectId::ObjectsFolder)
+        .unwrap();
+    assert_eq!(
+        parent.verb,
+        ModelChangeStructureVerbMask::ReferenceAdded as u8
+    );
+
+    let r = session
+        .delete_nodes(&[DeleteNodesItem {
+            node_id: id.clone(),
+            delete_target_references: true,
+        }])
+        .await
+        .unwrap();
+    assert_eq!(r[0], StatusCode::Good);
+
+    let changes: Vec<ModelChangeStructureDataType> = next_changes(&mut events).await;
+    let deleted = changes.iter().find(|c| c.affected == id).unwrap();
+    assert_eq!(
+        deleted.verb,
+        ModelChangeStructureVerbMask::NodeDeleted as u8
+    );
+    assert_eq!(deleted.affected_type, ObjectTypeId::FolderType);
+}
+
+#[tokio::test]
+async fn model_change_events_from_call() {
+    let (_tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    {
+        let mut sp = nm.address_space().write();
+        MethodBuilder::new(&id, "AddNode", "AddNode")
+            .executable(true)
+            .user_executable(true)
+            .component_of(ObjectId::ObjectsFolder)
+            .insert(&mut *sp);
+    }
+    let mut events = subscribe_changes(&session, ObjectTypeId::GeneralModelChangeEventType).await;
+
+    // A method that modifies the address space directly.
+    let added_id = nm.inner().next_node_id();
+    let address_space = nm.address_space().clone();
+    let added_ref = added_id.clone();
+    nm.inner().add_method_cb(id.clone(), move |_| {
+        ObjectBuilder::new(&added_ref, "MethodNode", "MethodNode")
+            .organized_by(ObjectId::ObjectsFolder)
+            .has_type_definition(ObjectTypeId::BaseObjectType)
+            .insert(&mut *address_space.write());
+        Ok(vec![])
+    });
+
+    let r = session
+        .call_one(CallMethodRequest {
+            object_id: ObjectId::ObjectsFolder.into(),
+            method_id: id.clone(),
+            input_arguments: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::Good);
+
+    let changes: Vec<ModelChangeStructureDataType> = next_changes(&mut events).await;
+    let added = changes.iter().find(|c| c.affected == added_id).unwrap();
+    assert_eq!(added.verb, ModelChangeStructureVerbMask::NodeAdded as u8);
+}
+
+#[tokio::test]
+async fn node_version_updated() {
+    let (tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    let version_id = nm.inner().next_node_id();
+    let target_id = nm.inner().next_node_id();
+    for (node_id, name) in [(&id, "Versioned"), (&target_id, "Target")] {
+        nm.inner().add_node(
+            nm.address_space(),
+            tester.handle.type_tree(),
+            ObjectBuilder::new(node_id, name, name).build().into(),
+            &ObjectId::ObjectsFolder.into(),
+            &ReferenceTypeId::Organizes.into(),
+            Some(&ObjectTypeId::BaseObjectType.into()),
+            Vec::new(),
+        );
+    }
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&version_id, "NodeVersion", "NodeVersion")
+            .data_type(DataTypeId::String)
+            .value("0")
+            .build()
+            .into(),
+        &id,
+        &ReferenceTypeId::HasProperty.into(),
+        Some(&VariableTypeId::PropertyType.into()),
+        Vec::new(),
+    );
+    // Report the direct changes above, so they don't affect the version.
+    nm.notify_model_changes(tester.handle.subscriptions());
+    nm.address_space()
+        .write()
+        .find_mut(&version_id)
+        .unwrap()
+        .as_mut_node()
+        .set_attribute(AttributeId::Value, Variant::from("0"))
+        .unwrap();
+
+    let r = session
+        .add_references(&[AddReferencesItem {
+            source_node_id: id.clone(),
+            reference_type_id: ReferenceTypeId::HasCondition.into(),
+            is_forward: true,
+            target_server_uri: Default::default(),
+            target_node_id: target_id.clone().into(),
+            target_node_class: NodeClass::Object,
+        }])
+        .await
+        .unwrap();
+    assert_eq!(r[0], StatusCode::Good);
+
+    let r = session
+        .read(
+            &[read_value_id(AttributeId::Value, &version_id)],
+            TimestampsToReturn::Neither,
+            0.0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(r[0].value, Some(Variant::from("1")));
+}
+
+#[tokio::test]
+async fn semantic_change_events() {
+    let (tester, nm, session) = setup().await;
+    let mut events = subscribe_changes(&session, ObjectTypeId::SemanticChangeEventType).await;
+
+    let id = nm.inner().next_node_id();
+    let range_id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "Analog", "Analog")
+            .data_type(DataTypeId::Double)
+            .value(1.0)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::AnalogItemType.into()),
+        Vec::new(),
+    );
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&range_id, "EURange", "EURange")
+            .data_type(DataTypeId::Range)
+            .value(ExtensionObject::from_message(Range {
+                low: 0.0,
+                high: 10.0,
+            }))
+            .access_level(AccessLevel::CURRENT_READ | AccessLevel::SEMANTIC_CHANGE)
+            .build()
+            .into(),
+        &id,
+        &ReferenceTypeId::HasProperty.into(),
+        Some(&VariableTypeId::PropertyType.into()),
+        Vec::new(),
+    );
+
+    // Changing the value of the variable itself is not a semantic change.
+    nm.set_value(
+        tester.handle.subscriptions(),
+        &id,
+        None,
+        DataValue::new_now(2.0),
+    )
+    .unwrap();
+    nm.set_value(
+        tester.handle.subscriptions(),
+        &range_id,
+        None,
+        DataValue::new_now(ExtensionObject::from_message(Range {
+            low: 0.0,
+            high: 100.0,
+        })),
+    )
+    .unwrap();
+
+    let changes: Vec<SemanticChangeStructureDataType> = next_changes(&mut events).await;
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].affected, id);
+    assert_eq!(changes[0].affected_type, VariableTypeId::AnalogItemType);
+    assert!(timeout(Duration::from_millis(300), events.recv())
+        .await
+        .is_err());
+}
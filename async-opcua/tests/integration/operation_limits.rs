@@ -0,0 +1,325 @@
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use super::utils::{default_client, read_value_id, test_server, ChannelNotifications, Tester};
+use opcua::{
+    client::{OperationLimits, Session},
+    server::ServerBuilder,
+    types::{
+        AttributeId, BrowseDescription, BrowseDirection, DataValue, MonitoredItemCreateRequest,
+        MonitoringMode, MonitoringParameters, NodeId, ObjectId, ReadValueId, ReferenceTypeId,
+        StatusCode, TimestampsToReturn, VariableId, Variant, WriteValue,
+    },
+};
+
+fn limited_server() -> ServerBuilder {
+    let mut server = test_server();
+    let limits = &mut server.limits_mut().operational;
+    limits.max_nodes_per_read = 10;
+    limits.max_nodes_per_write = 10;
+    limits.max_nodes_per_browse = 10;
+    limits.max_monitored_items_per_call = 10;
+    server
+}
+
+async fn connect(tester: &mut Tester) -> Arc<Session> {
+    let (session, event_loop) = tester.connect_default().await.unwrap();
+    event_loop.spawn();
+    tokio::time::timeout(Duration::from_secs(20), session.wait_for_connection())
+        .await
+        .unwrap();
+    session
+}
+
+/// Alternate between two nodes, so that the order of the results can be checked.
+fn alternating_reads(count: usize) -> Vec<ReadValueId> {
+    (0..count)
+        .map(|i| {
+            if i & 1 == 0 {
+                read_value_id(AttributeId::Value, VariableId::Server_ServiceLevel)
+            } else {
+                read_value_id(AttributeId::Value, NodeId::new(2, "doesnotexist"))
+            }
+        })
+        .collect()
+}
+
+fn assert_alternating_values(results: &[DataValue]) {
+    for (i, value) in results.iter().enumerate() {
+        if i & 1 == 0 {
+            assert_eq!(value.value, Some(Variant::Byte(123)), "result {i}");
+        } else {
+            assert_eq!(
+                value.status,
+                Some(StatusCode::BadNodeIdUnknown),
+                "result {i}"
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn read_operation_limits() {
+    let mut tester = Tester::new(limited_server(), false).await;
+    let session = connect(&mut tester).await;
+
+    let limits = session.operation_limits();
+    assert_eq!(limits.max_nodes_per_read, 10);
+    assert_eq!(limits.max_nodes_per_write, 10);
+    assert_eq!(limits.max_nodes_per_browse, 10);
+    assert_eq!(limits.max_monitored_items_per_call, 10);
+}
+
+#[tokio::test]
+async fn split_read() {
+    let mut tester = Tester::new(limited_server(), false).await;
+    let session = connect(&mut tester).await;
+    tester.handle.set_service_level(123);
+
+    let results = session
+        .read(&alternating_reads(105), TimestampsToReturn::Neither, 0.0)
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 105);
+    assert_alternating_values(&results);
+}
+
+#[tokio::test]
+async fn read_without_splitting() {
+    let mut tester = Tester::new_custom_client(
+        limited_server(),
+        default_client(0, false).read_operation_limits(false),
+    )
+    .await;
+    let session = connect(&mut tester).await;
+    assert_eq!(session.operation_limits().max_nodes_per_read, 0);
+
+    let err = session
+        .read(&alternating_reads(11), TimestampsToReturn::Neither, 0.0)
+        .await
+        .unwrap_err();
+    assert_eq!(err, StatusCode::BadTooManyOperations);
+}
+
+#[tokio::test]
+async fn split_write() {
+    let mut tester = Tester::new(limited_server(), false).await;
+    let session = connect(&mut tester).await;
+
+    let nodes_to_write: Vec<_> = alternating_reads(25)
+        .into_iter()
+        .map(|r| WriteValue {
+            node_id: r.node_id,
+            attribute_id: AttributeId::Value as u32,
+            value: DataValue::new_now(1u8),
+            ..Default::default()
+        })
+        .collect();
+    let results = session.write(&nodes_to_write).await.unwrap();
+    assert_eq!(results.len(), 25);
+    for (i, status) in results.iter().enumerate() {
+        if i & 1 == 0 {
+            assert_ne!(*status, StatusCode::BadNodeIdUnknown, "result {i}");
+        } else {
+            assert_eq!(*status, StatusCode::BadNodeIdUnknown, "result {i}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn split_browse() {
+    let mut tester = Tester::new(limited_server(), false).await;
+    let session = connect(&mut tester).await;
+
+    // The objects folder references the server object,
+    // which references its server capabilities.
+    let nodes_to_browse: Vec<_> = (0..25)
+        .map(|i| BrowseDescription {
+            node_id: if i & 1 == 0 {
+                ObjectId::ObjectsFolder.into()
+            } else {
+                ObjectId::Server.into()
+            },
+            browse_direction: BrowseDirection::Forward,
+            reference_type_id: ReferenceTypeId::HierarchicalReferences.into(),
+            include_subtypes: true,
+            node_class_mask: 0,
+            result_mask: 0x3f,
+        })
+        .collect();
+    let results = session.browse(&nodes_to_browse, 1000, None).await.unwrap();
+    assert_eq!(results.len(), 25);
+    for (i, result) in results.iter().enumerate() {
+        assert!(result.status_code.is_good(), "result {i}");
+        let expected: NodeId = if i & 1 == 0 {
+            ObjectId::Server.into()
+        } else {
+            ObjectId::Server_ServerCapabilities.into()
+        };
+        assert!(
+            result
+                .references
+                .as_ref()
+                .unwrap()
+                .iter()
+                .any(|r| r.node_id.node_id == expected),
+            "result {i}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn split_browse_partial_failure() {
+    let mut server = limited_server();
+    server.limits_mut().max_browse_continuation_points = 10;
+    let mut tester = Tester::new(server, false).await;
+    let session = connect(&mut tester).await;
+
+    let nodes_to_browse: Vec<_> = (0..25)
+        .map(|_| BrowseDescription {
+            node_id: ObjectId::Server.into(),
+            browse_direction: BrowseDirection::Forward,
+            reference_type_id: ReferenceTypeId::HierarchicalReferences.into(),
+            include_subtypes: true,
+            node_class_mask: 0,
+            result_mask: 0x3f,
+        })
+        .collect();
+    // The first request has more nodes than the server allows, the second creates
+    // a continuation point for each node.
+    session.set_operation_limits(OperationLimits {
+        max_nodes_per_browse: 15,
+        ..session.operation_limits()
+    });
+    let err = session.browse(&nodes_to_browse, 1, None).await.unwrap_err();
+    assert_eq!(err, StatusCode::BadTooManyOperations);
+
+    // The continuation points of the second request were released, so new ones can be created.
+    let results = session
+        .browse(&nodes_to_browse[..10], 1, None)
+        .await
+        .unwrap();
+    for (i, result) in results.iter().enumerate() {
+        assert!(result.status_code.is_good(), "result {i}");
+        assert!(!result.continuation_point.is_null(), "result {i}");
+    }
+}
+
+#[tokio::test]
+async fn split_create_monitored_items() {
+    let mut tester = Tester::new(limited_server(), false).await;
+    let session = connect(&mut tester).await;
+
+    let (notifs, _data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+
+    let node_id = |i: usize| -> NodeId {
+        if i & 1 == 0 {
+            VariableId::Server_ServiceLevel.into()
+        } else {
+            VariableId::Server_ServerStatus_State.into()
+        }
+    };
+    let items: Vec<_> = (0..25)
+        .map(|i| MonitoredItemCreateRequest {
+            item_to_monitor: read_value_id(AttributeId::Value, node_id(i)),
+            monitoring_mode: MonitoringMode::Reporting,
+            requested_parameters: MonitoringParameters {
+                sampling_interval: 0.0,
+                queue_size: 10,
+                discard_oldest: true,
+                ..Default::default()
+            },
+        })
+        .collect();
+    let results = session
+        .create_monitored_items(sub_id, TimestampsToReturn::Both, items)
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 25);
+    for (i, item) in results.iter().enumerate() {
+        assert!(item.result.status_code.is_good(), "result {i}");
+        assert_eq!(item.item_to_monitor.node_id, node_id(i), "result {i}");
+    }
+    let ids: HashSet<_> = results.iter().map(|r| r.result.monitored_item_id).collect();
+    assert_eq!(ids.len(), 25);
+}
+
+#[tokio::test]
+async fn split_create_monitored_items_partial_failure() {
+    let mut server = limited_server();
+    server
+        .limits_mut()
+        .subscriptions
+        .max_monitored_items_per_sub = 20;
+    // Send one request at a time, so that the last one exceeds the limit.
+    let mut tester =
+        Tester::new_custom_client(server, default_client(0, false).max_concurrent_chunks(1)).await;
+    let session = connect(&mut tester).await;
+
+    let (notifs, _data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+
+    let items: Vec<_> = (0..25)
+        .map(|_| MonitoredItemCreateRequest {
+            item_to_monitor: read_value_id(AttributeId::Value, VariableId::Server_ServiceLevel),
+            monitoring_mode: MonitoringMode::Reporting,
+            requested_parameters: MonitoringParameters {
+                sampling_interval: 0.0,
+                queue_size: 10,
+                discard_oldest: true,
+                ..Default::default()
+            },
+        })
+        .collect();
+    // The items created by the first two requests are returned, even though the last one failed.
+    let results = session
+        .create_monitored_items(sub_id, TimestampsToReturn::Both, items)
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 25);
+    for (i, item) in results.iter().enumerate() {
+        if i < 20 {
+            assert!(item.result.status_code.is_good(), "result {i}");
+        } else {
+            assert_eq!(
+                item.result.status_code,
+                StatusCode::BadTooManyMonitoredItems,
+                "result {i}"
+            );
+        }
+        assert_eq!(
+            item.item_to_monitor.node_id,
+            VariableId::Server_ServiceLevel,
+            "result {i}"
+        );
+    }
+    let ids: HashSet<_> = results[..20]
+        .iter()
+        .map(|r| r.result.monitored_item_id)
+        .collect();
+    assert_eq!(ids.len(), 20);
+
+    // If every request fails, the call fails.
+    let err = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            results[..15]
+                .iter()
+                .map(|r| MonitoredItemCreateRequest {
+                    item_to_monitor: r.item_to_monitor.clone(),
+                    monitoring_mode: r.monitoring_mode,
+                    requested_parameters: r.requested_parameters.clone(),
+                })
+                .collect(),
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(err, StatusCode::BadTooManyMonitoredItems);
+}
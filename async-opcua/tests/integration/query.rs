@@ -0,0 +1,481 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    time::Duration,
+};
+
+use super::utils::{
+    default_server, setup, test_node_manager, test_server, TestNodeManager, Tester,
+};
+use async_trait::async_trait;
+use opcua::{
+    client::{services::QueryFirst, QueryError, Session, UARequest},
+    core::ResponseMessage,
+    server::{
+        address_space::{ObjectBuilder, ObjectTypeBuilder, VariableBuilder},
+        diagnostics::NamespaceMetadata,
+        node_manager::{NodeManager, RequestContext, ServerContext},
+    },
+    types::{
+        operand::ContentFilterBuilder, AttributeId, ByteString, ContentFilter,
+        ContentFilterElement, DataTypeId, ExtensionObject, FilterOperator, NodeClass, NodeId,
+        NodeTypeDescription, NumericRange, ObjectId, ObjectTypeId, Operand, QualifiedName,
+        QueryDataDescription, QueryDataSet, QueryFirstRequest, QueryFirstResponse,
+        QueryNextRequest, QueryNextResponse, RelativePath, RequestHeader, StatusCode,
+        VariableTypeId, Variant, ViewDescription,
+    },
+};
+use opcua_client::services::Read;
+use opcua_nodes::DefaultTypeTree;
+
+// These tests send the raw requests, to check the server side of the service.
+
+fn request_header(session: &Session) -> RequestHeader {
+    Read::new(session).header().clone()
+}
+
+fn folder_query(session: &Session, max_data_sets_to_return: u32) -> QueryFirstRequest {
+    QueryFirstRequest {
+        request_header: request_header(session),
+        view: ViewDescription::default(),
+        node_types: Some(vec![NodeTypeDescription {
+            type_definition_node: ObjectTypeId::FolderType.into(),
+            include_sub_types: true,
+            data_to_return: Some(vec![QueryDataDescription {
+                relative_path: Default::default(),
+                attribute_id: AttributeId::NodeId as u32,
+                index_range: Default::default(),
+            }]),
+        }]),
+        filter: ContentFilter {
+            elements: Some(vec![ContentFilterElement {
+                filter_operator: FilterOperator::OfType,
+                filter_operands: Some(vec![ExtensionObject::from(&Operand::literal(
+                    NodeId::from(ObjectTypeId::FolderType),
+                ))]),
+            }]),
+        },
+        max_data_sets_to_return,
+        max_references_to_return: 0,
+    }
+}
+
+async fn query_first(session: &Session, request: QueryFirstRequest) -> QueryFirstResponse {
+    match session
+        .channel()
+        .send(request, Duration::from_secs(5))
+        .await
+        .unwrap()
+    {
+        ResponseMessage::QueryFirst(r) => *r,
+        r => panic!("Unexpected response {r:?}"),
+    }
+}
+
+async fn query_next(
+    session: &Session,
+    release_continuation_point: bool,
+    continuation_point: ByteString,
+) -> Result<QueryNextResponse, StatusCode> {
+    let request = QueryNextRequest {
+        request_header: request_header(session),
+        release_continuation_point,
+        continuation_point,
+    };
+    match session
+        .channel()
+        .send(request, Duration::from_secs(5))
+        .await
+        .unwrap()
+    {
+        ResponseMessage::QueryNext(r) => Ok(*r),
+        ResponseMessage::ServiceFault(f) => Err(f.response_header.service_result),
+        r => panic!("Unexpected response {r:?}"),
+    }
+}
+
+fn node_ids(data_sets: &[QueryDataSet]) -> Vec<NodeId> {
+    data_sets
+        .iter()
+        .map(|d| {
+            let values = d.values.as_ref().unwrap();
+            assert_eq!(values.len(), 1);
+            let Variant::NodeId(id) = &values[0] else {
+                panic!("Unexpected value {:?}", values[0]);
+            };
+            assert_eq!(**id, d.node_id.node_id);
+            (**id).clone()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn query_continuation_across_node_managers() {
+    let (_tester, nm, session) = setup().await;
+
+    let mut folders = Vec::new();
+    {
+        let mut sp = nm.address_space().write();
+        for i in 0..3 {
+            let id = nm.inner().next_node_id();
+            ObjectBuilder::new(&id, format!("QueryFolder{i}"), format!("QueryFolder{i}"))
+                .has_type_definition(ObjectTypeId::FolderType)
+                .organized_by(ObjectId::ObjectsFolder)
+                .insert(&mut *sp);
+            folders.push(id);
+        }
+    }
+
+    let response = query_first(&session, folder_query(&session, 5)).await;
+    assert!(response.response_header.service_result.is_good());
+    assert!(response.parsing_results.is_none());
+    let mut ids = node_ids(&response.query_data_sets.unwrap());
+    assert_eq!(ids.len(), 5);
+
+    // Follow the continuation points through the core node manager, past the diagnostics
+    // node manager, and into the test node manager.
+    let mut continuation_point = response.continuation_point;
+    let mut pages = 1;
+    while !continuation_point.is_null() {
+        let response = query_next(&session, false, continuation_point)
+            .await
+            .unwrap();
+        assert!(response.response_header.service_result.is_good());
+        let page = node_ids(&response.query_data_sets.unwrap());
+        assert!(page.len() <= 5);
+        ids.extend(page);
+        continuation_point = response.revised_continuation_point;
+        pages += 1;
+    }
+    assert!(pages > 2);
+
+    let unique: HashSet<_> = ids.iter().collect();
+    assert_eq!(unique.len(), ids.len());
+    assert!(unique.contains(&NodeId::from(ObjectId::ObjectsFolder)));
+    assert!(unique.contains(&NodeId::from(ObjectId::TypesFolder)));
+    for folder in &folders {
+        assert!(unique.contains(folder));
+    }
+
+    // Results are returned in node manager order, so the core namespace comes first.
+    let first_test = ids.iter().position(|id| id.namespace != 0).unwrap();
+    assert!(first_test > 5);
+    assert!(ids[first_test..].iter().all(|id| id.namespace != 0));
+    assert_eq!(ids.len() - first_test, folders.len());
+}
+
+#[tokio::test]
+async fn query_release_continuation_point() {
+    let (_tester, _nm, session) = setup().await;
+
+    let response = query_first(&session, folder_query(&session, 1)).await;
+    assert_eq!(response.query_data_sets.unwrap().len(), 1);
+    assert!(!response.continuation_point.is_null());
+
+    let released = query_next(&session, true, response.continuation_point.clone())
+        .await
+        .unwrap();
+    assert!(released.response_header.service_result.is_good());
+    assert!(released.revised_continuation_point.is_null());
+
+    // The continuation point is no longer valid once released.
+    let err = query_next(&session, false, response.continuation_point)
+        .await
+        .unwrap_err();
+    assert_eq!(err, StatusCode::BadContinuationPointInvalid);
+}
+
+#[tokio::test]
+async fn query_invalid_data_description() {
+    let (_tester, _nm, session) = setup().await;
+
+    let mut request = folder_query(&session, 0);
+    request.node_types.as_mut().unwrap()[0]
+        .data_to_return
+        .as_mut()
+        .unwrap()[0]
+        .attribute_id = 1000;
+    let response = query_first(&session, request).await;
+    assert_eq!(
+        response.response_header.service_result,
+        StatusCode::BadInvalidArgument
+    );
+    let parsing_results = response.parsing_results.unwrap();
+    assert_eq!(parsing_results.len(), 1);
+    assert_eq!(
+        parsing_results[0].status_code,
+        StatusCode::BadInvalidArgument
+    );
+    assert_eq!(
+        parsing_results[0].data_status_codes,
+        Some(vec![StatusCode::BadAttributeIdInvalid])
+    );
+}
+
+/// Create a type and a subtype of it, and five objects alternating between them,
+/// each with a property `Val` holding the index of the object.
+fn add_query_nodes(tester: &Tester, nm: &TestNodeManager) -> (NodeId, NodeId) {
+    let type_id = nm.inner().next_node_id();
+    let sub_type_id = nm.inner().next_node_id();
+    let ns = type_id.namespace;
+    {
+        let mut sp = nm.address_space().write();
+        ObjectTypeBuilder::new(&type_id, "QueryType", "QueryType")
+            .subtype_of(ObjectTypeId::BaseObjectType)
+            .insert(&mut *sp);
+        ObjectTypeBuilder::new(&sub_type_id, "QuerySubType", "QuerySubType")
+            .subtype_of(type_id.clone())
+            .insert(&mut *sp);
+        for i in 0..5 {
+            let id = nm.inner().next_node_id();
+            let type_definition = if i & 1 == 0 { &type_id } else { &sub_type_id };
+            ObjectBuilder::new(&id, format!("Object{i}"), format!("Object{i}"))
+                .has_type_definition(type_definition.clone())
+                .organized_by(ObjectId::ObjectsFolder)
+                .insert(&mut *sp);
+            VariableBuilder::new(
+                &nm.inner().next_node_id(),
+                QualifiedName::new(ns, "Val"),
+                "Val",
+            )
+            .value(i)
+            .data_type(DataTypeId::Int32)
+            .property_of(id)
+            .has_type_definition(VariableTypeId::PropertyType)
+            .insert(&mut *sp);
+        }
+    }
+    let mut type_tree = tester.handle.type_tree().write();
+    type_tree.add_type_node(
+        &type_id,
+        &ObjectTypeId::BaseObjectType.into(),
+        NodeClass::ObjectType,
+    );
+    type_tree.add_type_node(&sub_type_id, &type_id, NodeClass::ObjectType);
+    (type_id, sub_type_id)
+}
+
+fn node_type(type_id: &NodeId) -> NodeTypeDescription {
+    let path = [QualifiedName::new(type_id.namespace, "Val")];
+    NodeTypeDescription::new(type_id.clone(), true)
+        .data(QueryDataDescription::new(
+            RelativePath::from(&path[..]),
+            AttributeId::Value,
+            NumericRange::None,
+        ))
+        .data(QueryDataDescription::attribute(AttributeId::BrowseName))
+}
+
+fn values(data_sets: &[QueryDataSet]) -> BTreeSet<i32> {
+    data_sets
+        .iter()
+        .map(|d| {
+            let values = d.values.as_ref().unwrap();
+            assert_eq!(values.len(), 2);
+            let Variant::Int32(v) = values[0] else {
+                panic!("Unexpected value {:?}", values[0]);
+            };
+            assert_eq!(
+                values[1],
+                Variant::from(QualifiedName::new(0, format!("Object{v}")))
+            );
+            v
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn query_all_pages() {
+    let (tester, nm, session) = setup().await;
+    let (type_id, _) = add_query_nodes(&tester, &nm);
+
+    // Only two data sets per call, so this needs to follow continuation points.
+    let data_sets = session
+        .query(
+            &[node_type(&type_id)],
+            ContentFilterBuilder::new().of_type(type_id.clone()),
+            None,
+            2,
+        )
+        .await
+        .unwrap();
+    assert_eq!(data_sets.len(), 5);
+    assert_eq!(values(&data_sets), (0..5).collect());
+}
+
+#[tokio::test]
+async fn query_subtype() {
+    let (tester, nm, session) = setup().await;
+    let (type_id, sub_type_id) = add_query_nodes(&tester, &nm);
+
+    let data_sets = session
+        .query(
+            &[node_type(&type_id)],
+            ContentFilterBuilder::new().of_type(sub_type_id.clone()),
+            None,
+            0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(values(&data_sets), [1, 3].into());
+    for data_set in &data_sets {
+        assert_eq!(data_set.type_definition_node.node_id, sub_type_id);
+    }
+}
+
+#[tokio::test]
+async fn query_invalid_filter() {
+    let (tester, nm, session) = setup().await;
+    let (type_id, _) = add_query_nodes(&tester, &nm);
+
+    // Equals needs two operands.
+    let filter = ContentFilter {
+        elements: Some(vec![ContentFilterElement {
+            filter_operator: FilterOperator::Equals,
+            filter_operands: Some(vec![ExtensionObject::from(&Operand::literal(1))]),
+        }]),
+    };
+    let err = session
+        .query(&[node_type(&type_id)], filter, None, 0)
+        .await
+        .unwrap_err();
+    let QueryError::Invalid {
+        status,
+        parsing_results,
+        filter_result,
+    } = err
+    else {
+        panic!("Expected invalid query, got {err:?}");
+    };
+    assert_eq!(status, StatusCode::BadContentFilterInvalid);
+    assert_eq!(parsing_results.len(), 1);
+    assert!(parsing_results[0].status_code.is_good());
+    let element_results = filter_result.element_results.unwrap();
+    assert_eq!(
+        element_results[0].status_code,
+        StatusCode::BadFilterOperandCountMismatch
+    );
+}
+
+#[tokio::test]
+async fn client_query_release_continuation_point() {
+    let (tester, nm, session) = setup().await;
+    let (type_id, _) = add_query_nodes(&tester, &nm);
+
+    let response = session
+        .query_first(
+            &[node_type(&type_id)],
+            ContentFilterBuilder::new().of_type(type_id.clone()),
+            None,
+            1,
+            0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.query_data_sets.unwrap().len(), 1);
+    assert!(!response.continuation_point.is_null());
+
+    let (data_sets, continuation_point) = session
+        .query_next(true, response.continuation_point.clone())
+        .await
+        .unwrap();
+    assert!(data_sets.is_empty());
+    assert!(continuation_point.is_null());
+
+    // The continuation point is no longer valid once released.
+    let err = session
+        .query_next(false, response.continuation_point)
+        .await
+        .unwrap_err();
+    assert_eq!(err, StatusCode::BadContinuationPointInvalid);
+}
+
+#[tokio::test]
+async fn query_no_node_types() {
+    let (_tester, _nm, session) = setup().await;
+
+    let err = QueryFirst::new(&session)
+        .filter(ContentFilterBuilder::new().of_type(ObjectTypeId::BaseObjectType))
+        .send(session.channel())
+        .await
+        .unwrap_err();
+    assert_eq!(err, StatusCode::BadNothingToDo);
+}
+
+#[tokio::test]
+async fn query_too_complex() {
+    let mut server = test_server();
+    server.limits_mut().operational.max_nodes_visited_per_query = 10;
+    let mut tester = Tester::new(server, false).await;
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    // The core namespace alone has far more than 10 nodes.
+    let response = query_first(&session, folder_query(&session, 0)).await;
+    assert_eq!(
+        response.response_header.service_result,
+        StatusCode::BadQueryTooComplex
+    );
+}
+
+/// Node manager that does not implement query.
+struct NoQueryNodeManager;
+
+#[async_trait]
+impl NodeManager for NoQueryNodeManager {
+    fn owns_node(&self, _id: &NodeId) -> bool {
+        false
+    }
+
+    fn name(&self) -> &str {
+        "no-query"
+    }
+
+    fn namespaces_for_user(&self, _context: &RequestContext) -> Vec<NamespaceMetadata> {
+        Vec::new()
+    }
+
+    async fn init(&self, _type_tree: &mut DefaultTypeTree, _context: ServerContext) {}
+}
+
+#[tokio::test]
+async fn query_node_manager_without_query() {
+    let server = default_server()
+        .with_node_manager(|_| NoQueryNodeManager)
+        .with_node_manager(test_node_manager());
+    let mut tester = Tester::new(server, false).await;
+    let nm = tester
+        .handle
+        .node_managers()
+        .get_of_type::<TestNodeManager>()
+        .unwrap();
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    let folder = nm.inner().next_node_id();
+    ObjectBuilder::new(&folder, "QueryFolder", "QueryFolder")
+        .has_type_definition(ObjectTypeId::FolderType)
+        .organized_by(ObjectId::ObjectsFolder)
+        .insert(&mut *nm.address_space().write());
+
+    // Paging through the results passes the node manager without query support.
+    let response = query_first(&session, folder_query(&session, 5)).await;
+    assert!(response.response_header.service_result.is_good());
+    let mut ids = node_ids(&response.query_data_sets.unwrap());
+    let mut continuation_point = response.continuation_point;
+    while !continuation_point.is_null() {
+        let response = query_next(&session, false, continuation_point)
+            .await
+            .unwrap();
+        assert!(response.response_header.service_result.is_good());
+        ids.extend(node_ids(&response.query_data_sets.unwrap()));
+        continuation_point = response.revised_continuation_point;
+    }
+    assert!(ids.contains(&NodeId::from(ObjectId::ObjectsFolder)));
+    assert!(ids.contains(&folder));
+}
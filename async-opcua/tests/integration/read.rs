@@ -17,7 +17,7 @@ use opcua::{
         WriteMask,
     },
 };
-use opcua_client::{services::Read, DefaultRetryPolicy, ExponentialBackoff};
+use opcua_client::{services::Read, DefaultRetryPolicy, ExponentialBackoff, OperationLimits};
 
 #[tokio::test]
 async fn read() {
@@ -724,6 +724,9 @@ async fn read_limits() {
         .unwrap_err();
     assert_eq!(r, StatusCode::BadTimestampsToReturnInvalid);
 
+    // Disable request splitting in the client, so that the request reaches the server.
+    session.set_operation_limits(OperationLimits::default());
+
     // Too many operations
     let ops: Vec<_> = (0..(read_limit + 1))
         .map(|r| read_value_id(AttributeId::Value, NodeId::new(2, r as i32)))
@@ -1021,6 +1024,9 @@ async fn history_read_fail() {
         .operational
         .max_nodes_per_history_read_data;
 
+    // Disable request splitting in the client, so that the request reaches the server.
+    session.set_operation_limits(OperationLimits::default());
+
     // Read too many
     let r = session
         .history_read(
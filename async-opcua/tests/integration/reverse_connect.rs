@@ -0,0 +1,199 @@
+use std::{sync::Arc, time::Duration};
+
+use super::utils::{hostname, test_server, Tester};
+use opcua::{
+    client::{transport::ReverseTcpConnector, Session},
+    core::comms::tcp_types::ReverseHelloMessage,
+    crypto::SecurityPolicy,
+    server::ReverseConnectConfig,
+    types::{
+        AttributeId, DataValue, EndpointDescription, MessageSecurityMode, ReadValueId,
+        SimpleBinaryEncodable, TimestampsToReturn, UserTokenPolicy, VariableId, Variant,
+    },
+};
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpListener, TcpStream},
+};
+
+async fn connect(tester: &Tester, connector: ReverseTcpConnector) -> Arc<Session> {
+    let (session, event_loop) = tester
+        .client
+        .session_builder()
+        .connector(connector)
+        // The client cannot call GetEndpoints before the server connects, so the
+        // endpoint must be fully described up front.
+        .connect_to_endpoint_directly(EndpointDescription {
+            user_identity_tokens: Some(vec![UserTokenPolicy::anonymous()]),
+            ..(
+                tester.endpoint().as_str(),
+                SecurityPolicy::None.to_str(),
+                MessageSecurityMode::None,
+            )
+                .into()
+        })
+        .unwrap()
+        .build(tester.client.certificate_store().clone());
+    event_loop.spawn();
+    tokio::time::timeout(Duration::from_secs(20), session.wait_for_connection())
+        .await
+        .unwrap();
+    session
+}
+
+async fn read_namespace_array(session: &Session) -> DataValue {
+    session
+        .read(
+            &[ReadValueId {
+                node_id: VariableId::Server_NamespaceArray.into(),
+                attribute_id: AttributeId::Value as u32,
+                ..Default::default()
+            }],
+            TimestampsToReturn::Neither,
+            0.0,
+        )
+        .await
+        .unwrap()
+        .remove(0)
+}
+
+#[tokio::test]
+async fn reverse_connect() {
+    let listener = Arc::new(
+        TcpListener::bind(format!("{}:0", hostname()))
+            .await
+            .unwrap(),
+    );
+    let client_url = format!(
+        "opc.tcp://{}:{}",
+        hostname(),
+        listener.local_addr().unwrap().port()
+    );
+    let tester = Tester::new(
+        test_server().add_reverse_connect(ReverseConnectConfig::new(client_url)),
+        false,
+    )
+    .await;
+
+    let session = connect(
+        &tester,
+        ReverseTcpConnector::new(listener.clone()).server_uri("urn:integration_server"),
+    )
+    .await;
+    let value = read_namespace_array(&session).await;
+    assert!(matches!(value.value, Some(Variant::Array(_))));
+    session.disconnect().await.unwrap();
+
+    // The server keeps a connection open for the client to use next.
+    let session = connect(&tester, ReverseTcpConnector::new(listener)).await;
+    let value = read_namespace_array(&session).await;
+    assert!(matches!(value.value, Some(Variant::Array(_))));
+    session.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn reverse_connect_concurrent_sessions() {
+    let listener = Arc::new(
+        TcpListener::bind(format!("{}:0", hostname()))
+            .await
+            .unwrap(),
+    );
+    let client_url = format!(
+        "opc.tcp://{}:{}",
+        hostname(),
+        listener.local_addr().unwrap().port()
+    );
+    let tester = Tester::new(
+        test_server().add_reverse_connect(ReverseConnectConfig::new(client_url)),
+        false,
+    )
+    .await;
+
+    // Once the first connection is in use, the server opens another one,
+    // so a second session can connect while the first is still open.
+    let first = connect(&tester, ReverseTcpConnector::new(listener.clone())).await;
+    let second = connect(&tester, ReverseTcpConnector::new(listener)).await;
+    for session in [&first, &second] {
+        let value = read_namespace_array(session).await;
+        assert!(matches!(value.value, Some(Variant::Array(_))));
+    }
+    first.disconnect().await.unwrap();
+    second.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn reverse_connect_retry() {
+    // Pick a free port, and only start listening on it after the server has
+    // failed to connect a few times.
+    let addr = std::net::TcpListener::bind(format!("{}:0", hostname()))
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let mut config = ReverseConnectConfig::new(format!("opc.tcp://{}:{}", hostname(), addr.port()));
+    config.retry_interval_ms = 50;
+    config.max_retry_interval_ms = 200;
+    let tester = Tester::new(test_server().add_reverse_connect(config), false).await;
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let connector = ReverseTcpConnector::bind(addr).await.unwrap();
+    let session = connect(&tester, connector).await;
+    let value = read_namespace_array(&session).await;
+    assert!(matches!(value.value, Some(Variant::Array(_))));
+    session.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn reverse_connect_failed_hello() {
+    let listener = Arc::new(
+        TcpListener::bind(format!("{}:0", hostname()))
+            .await
+            .unwrap(),
+    );
+    let client_url = format!(
+        "opc.tcp://{}:{}",
+        hostname(),
+        listener.local_addr().unwrap().port()
+    );
+
+    // The first connection sends ReverseHello, then closes before the handshake
+    // is complete. The client keeps listening for the next connection.
+    let mut stream = TcpStream::connect(listener.local_addr().unwrap())
+        .await
+        .unwrap();
+    let mut buf = Vec::new();
+    ReverseHelloMessage::new("urn:integration_server", &client_url)
+        .encode(&mut buf)
+        .unwrap();
+    stream.write_all(&buf).await.unwrap();
+    drop(stream);
+
+    let tester = Tester::new(
+        test_server().add_reverse_connect(ReverseConnectConfig::new(client_url)),
+        false,
+    )
+    .await;
+
+    // Connect without retrying, so that this only succeeds if the connector itself
+    // moves on to the next connection.
+    let channel = tester
+        .client
+        .session_builder()
+        .connector(ReverseTcpConnector::new(listener))
+        .connect_to_endpoint_directly(EndpointDescription {
+            user_identity_tokens: Some(vec![UserTokenPolicy::anonymous()]),
+            ..(
+                tester.endpoint().as_str(),
+                SecurityPolicy::None.to_str(),
+                MessageSecurityMode::None,
+            )
+                .into()
+        })
+        .unwrap()
+        .build_channel(tester.client.certificate_store().clone());
+    let event_loop = tokio::time::timeout(Duration::from_secs(20), channel.connect_no_retry())
+        .await
+        .unwrap()
+        .unwrap();
+    drop(event_loop);
+    channel.close_channel().await;
+}
@@ -0,0 +1,509 @@
+use std::{sync::Arc, time::Duration};
+
+use super::utils::{
+    client_user_token, read_value_id, setup, test_server, ChannelNotifications, Tester,
+    CLIENT_USERPASS_ID,
+};
+use opcua::{
+    client::{IdentityToken, Session},
+    crypto::SecurityPolicy,
+    nodes::{BaseEventType, Event},
+    server::address_space::{AccessLevel, ObjectBuilder, VariableBuilder},
+    types::{
+        AccessRestrictionType, AttributeId, BrowseDescription, BrowseDirection, BrowseResultMask,
+        ByteString, CallMethodRequest, DataTypeId, DataValue, EventFilter, ExtensionObject,
+        IdentityCriteriaType, IdentityMappingRuleType, MessageSecurityMode, MethodId,
+        MonitoredItemCreateRequest, MonitoringMode, MonitoringParameters, NodeClassMask, NodeId,
+        NumericRange, ObjectId, ObjectTypeId, PermissionType, ReadValueId, ReferenceTypeId,
+        RolePermissionType, SimpleAttributeOperand, StatusCode, TimestampsToReturn, VariableId,
+        Variant, WriteValue,
+    },
+};
+
+fn user_rule() -> IdentityMappingRuleType {
+    IdentityMappingRuleType {
+        criteria_type: IdentityCriteriaType::UserName,
+        criteria: CLIENT_USERPASS_ID.into(),
+    }
+}
+
+async fn read(session: &Session, attribute: AttributeId, id: impl Into<NodeId>) -> DataValue {
+    session
+        .read(
+            &[read_value_id(attribute, id)],
+            TimestampsToReturn::Neither,
+            0.0,
+        )
+        .await
+        .unwrap()
+        .remove(0)
+}
+
+async fn browse(session: &Session, id: impl Into<NodeId>) -> Vec<(NodeId, String)> {
+    let r = session
+        .browse(
+            &[BrowseDescription {
+                node_id: id.into(),
+                browse_direction: BrowseDirection::Forward,
+                reference_type_id: ReferenceTypeId::HierarchicalReferences.into(),
+                include_subtypes: true,
+                node_class_mask: NodeClassMask::all().bits(),
+                result_mask: BrowseResultMask::All as u32,
+            }],
+            1000,
+            None,
+        )
+        .await
+        .unwrap();
+    r[0].references
+        .iter()
+        .flatten()
+        .map(|r| (r.node_id.node_id.clone(), r.browse_name.name.to_string()))
+        .collect()
+}
+
+async fn browse_ids(session: &Session, id: impl Into<NodeId>) -> Vec<NodeId> {
+    browse(session, id)
+        .await
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect()
+}
+
+async fn call(
+    session: &Session,
+    object_id: impl Into<NodeId>,
+    method_id: impl Into<NodeId>,
+    args: Vec<Variant>,
+) -> Result<Vec<Variant>, StatusCode> {
+    let r = session
+        .call_one(CallMethodRequest {
+            object_id: object_id.into(),
+            method_id: method_id.into(),
+            input_arguments: Some(args),
+        })
+        .await
+        .unwrap();
+    if r.status_code.is_good() {
+        Ok(r.output_arguments.unwrap_or_default())
+    } else {
+        Err(r.status_code)
+    }
+}
+
+fn identities(value: &DataValue) -> Vec<IdentityMappingRuleType> {
+    let Some(Variant::Array(arr)) = &value.value else {
+        panic!("Expected array, got {value:?}");
+    };
+    arr.values
+        .iter()
+        .map(|v| match v {
+            Variant::ExtensionObject(o) => o.inner_as::<IdentityMappingRuleType>().unwrap().clone(),
+            v => panic!("Expected extension object, got {v:?}"),
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn read_role_set() {
+    let (_tester, _nm, session) = setup().await;
+
+    let roles = browse_ids(&session, ObjectId::Server_ServerCapabilities_RoleSet).await;
+    assert!(roles.contains(&ObjectId::WellKnownRole_Anonymous.into()));
+    assert!(roles.contains(&ObjectId::WellKnownRole_SecurityAdmin.into()));
+
+    let value = read(
+        &session,
+        AttributeId::Value,
+        VariableId::WellKnownRole_Anonymous_Identities,
+    )
+    .await;
+    assert_eq!(
+        identities(&value),
+        vec![IdentityMappingRuleType {
+            criteria_type: IdentityCriteriaType::Anonymous,
+            criteria: Default::default(),
+        }]
+    );
+}
+
+#[tokio::test]
+async fn role_permissions() {
+    let (mut tester, nm, anonymous) = setup().await;
+    let operator: NodeId = ObjectId::WellKnownRole_Operator.into();
+    tester
+        .handle
+        .roles()
+        .add_identity(&operator, user_rule())
+        .unwrap();
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "Restricted", "Restricted")
+            .value(1)
+            .data_type(DataTypeId::Int32)
+            .access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE)
+            .user_access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE)
+            .role_permissions(vec![
+                RolePermissionType {
+                    role_id: operator.clone(),
+                    permissions: PermissionType::Browse | PermissionType::Read,
+                },
+                RolePermissionType {
+                    role_id: ObjectId::WellKnownRole_SecurityAdmin.into(),
+                    permissions: PermissionType::all(),
+                },
+            ])
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        None,
+        Vec::new(),
+    );
+
+    // Anonymous users have no permissions on the node.
+    assert!(!browse_ids(&anonymous, ObjectId::ObjectsFolder)
+        .await
+        .contains(&id));
+    let value = read(&anonymous, AttributeId::Value, &id).await;
+    assert_eq!(value.status, Some(StatusCode::BadUserAccessDenied));
+    let value = read(&anonymous, AttributeId::DisplayName, &id).await;
+    assert_eq!(value.status, Some(StatusCode::BadUserAccessDenied));
+
+    // The user is an operator, and may browse and read the node, but not write it.
+    let user = tester
+        .connect_and_wait(
+            SecurityPolicy::None,
+            MessageSecurityMode::None,
+            client_user_token(),
+        )
+        .await
+        .unwrap();
+    assert!(browse_ids(&user, ObjectId::ObjectsFolder)
+        .await
+        .contains(&id));
+    let value = read(&user, AttributeId::Value, &id).await;
+    assert_eq!(value.value, Some(Variant::Int32(1)));
+    let value = read(&user, AttributeId::UserAccessLevel, &id).await;
+    assert_eq!(
+        value.value,
+        Some(Variant::Byte(AccessLevel::CURRENT_READ.bits()))
+    );
+    let value = read(&user, AttributeId::RolePermissions, &id).await;
+    assert_eq!(value.status, Some(StatusCode::BadUserAccessDenied));
+    let value = read(&user, AttributeId::UserRolePermissions, &id).await;
+    let Some(Variant::Array(arr)) = value.value else {
+        panic!("Expected array, got {value:?}");
+    };
+    assert_eq!(
+        arr.values,
+        vec![Variant::from(ExtensionObject::from_message(
+            RolePermissionType {
+                role_id: operator.clone(),
+                permissions: PermissionType::Browse | PermissionType::Read,
+            }
+        ))]
+    );
+
+    let r = user
+        .write(&[WriteValue {
+            node_id: id.clone(),
+            attribute_id: AttributeId::Value as u32,
+            index_range: Default::default(),
+            value: DataValue::new_now(2),
+        }])
+        .await
+        .unwrap();
+    assert_eq!(r[0], StatusCode::BadUserAccessDenied);
+}
+
+#[tokio::test]
+async fn access_restrictions() {
+    let (mut tester, nm, anonymous) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "Encrypted", "Encrypted")
+            .value(1)
+            .data_type(DataTypeId::Int32)
+            .access_level(AccessLevel::CURRENT_READ)
+            .user_access_level(AccessLevel::CURRENT_READ)
+            .access_restrictions(AccessRestrictionType::EncryptionRequired)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        None,
+        Vec::new(),
+    );
+
+    // The node is still visible, since restrictions are not applied to browse.
+    assert!(browse_ids(&anonymous, ObjectId::ObjectsFolder)
+        .await
+        .contains(&id));
+    let value = read(&anonymous, AttributeId::Value, &id).await;
+    assert_eq!(value.status, Some(StatusCode::BadSecurityModeInsufficient));
+
+    let encrypted = tester
+        .connect_and_wait(
+            SecurityPolicy::Basic256Sha256,
+            MessageSecurityMode::SignAndEncrypt,
+            IdentityToken::Anonymous,
+        )
+        .await
+        .unwrap();
+    let value = read(&encrypted, AttributeId::Value, &id).await;
+    assert_eq!(value.value, Some(Variant::Int32(1)));
+}
+
+async fn admin_session(tester: &mut Tester) -> Arc<Session> {
+    tester
+        .handle
+        .roles()
+        .add_identity(&ObjectId::WellKnownRole_SecurityAdmin.into(), user_rule())
+        .unwrap();
+    tester
+        .connect_and_wait(
+            SecurityPolicy::Basic256Sha256,
+            MessageSecurityMode::SignAndEncrypt,
+            client_user_token(),
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn add_remove_role() {
+    let mut tester = Tester::new(test_server(), false).await;
+    let session = admin_session(&mut tester).await;
+
+    let res = call(
+        &session,
+        ObjectId::Server_ServerCapabilities_RoleSet,
+        MethodId::Server_ServerCapabilities_RoleSet_AddRole,
+        vec!["Custom".into(), "".into()],
+    )
+    .await
+    .unwrap();
+    let Variant::NodeId(role_id) = &res[0] else {
+        panic!("Expected node ID, got {:?}", res[0]);
+    };
+    let role_id = (**role_id).clone();
+    // New roles are created in the namespace of the server.
+    let server_namespace = session
+        .get_namespace_index(tester.handle.info().application_uri.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(role_id.namespace, server_namespace);
+    assert!(
+        browse_ids(&session, ObjectId::Server_ServerCapabilities_RoleSet)
+            .await
+            .contains(&role_id)
+    );
+
+    let err = call(
+        &session,
+        ObjectId::Server_ServerCapabilities_RoleSet,
+        MethodId::Server_ServerCapabilities_RoleSet_AddRole,
+        vec!["Custom".into(), "".into()],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err, StatusCode::BadBrowseNameDuplicated);
+
+    // Map an identity to the new role through the address space.
+    call(
+        &session,
+        &role_id,
+        MethodId::RoleType_AddIdentity,
+        vec![ExtensionObject::from_message(user_rule()).into()],
+    )
+    .await
+    .unwrap();
+    let (identities_id, _) = browse(&session, &role_id)
+        .await
+        .into_iter()
+        .find(|(_, name)| name == "Identities")
+        .unwrap();
+    assert_eq!(identities_id.namespace, server_namespace);
+    // The rest of the server namespace is still handled by the diagnostics node manager.
+    let namespaces = browse(&session, ObjectId::Server_Namespaces).await;
+    let (namespace_id, _) = namespaces
+        .iter()
+        .find(|(id, _)| id.namespace == server_namespace)
+        .unwrap();
+    assert!(browse(&session, namespace_id)
+        .await
+        .iter()
+        .any(|(_, name)| name == "NamespaceUri"));
+    let value = read(&session, AttributeId::Value, &identities_id).await;
+    assert_eq!(identities(&value), vec![user_rule()]);
+    assert_eq!(
+        tester.handle.roles().get(&role_id).unwrap().identities,
+        vec![user_rule()]
+    );
+
+    // Well known roles cannot be removed.
+    let err = call(
+        &session,
+        ObjectId::Server_ServerCapabilities_RoleSet,
+        MethodId::Server_ServerCapabilities_RoleSet_RemoveRole,
+        vec![NodeId::from(ObjectId::WellKnownRole_Operator).into()],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err, StatusCode::BadRequestNotAllowed);
+
+    call(
+        &session,
+        ObjectId::Server_ServerCapabilities_RoleSet,
+        MethodId::Server_ServerCapabilities_RoleSet_RemoveRole,
+        vec![role_id.clone().into()],
+    )
+    .await
+    .unwrap();
+    assert!(
+        !browse_ids(&session, ObjectId::Server_ServerCapabilities_RoleSet)
+            .await
+            .contains(&role_id)
+    );
+    assert!(tester.handle.roles().get(&role_id).is_none());
+}
+
+#[tokio::test]
+async fn manage_roles_access_denied() {
+    let mut tester = Tester::new(test_server(), false).await;
+    // The user is not a security admin.
+    let session = tester
+        .connect_and_wait(
+            SecurityPolicy::Basic256Sha256,
+            MessageSecurityMode::SignAndEncrypt,
+            client_user_token(),
+        )
+        .await
+        .unwrap();
+
+    let err = call(
+        &session,
+        ObjectId::Server_ServerCapabilities_RoleSet,
+        MethodId::Server_ServerCapabilities_RoleSet_AddRole,
+        vec!["Custom".into(), "".into()],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err, StatusCode::BadUserAccessDenied);
+
+    let err = call(
+        &session,
+        ObjectId::WellKnownRole_Operator,
+        MethodId::WellKnownRole_Operator_AddIdentity,
+        vec![ExtensionObject::from_message(user_rule()).into()],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err, StatusCode::BadUserAccessDenied);
+}
+
+#[tokio::test]
+async fn receive_events_permission() {
+    let (tester, nm, session) = setup().await;
+    let operator: NodeId = ObjectId::WellKnownRole_Operator.into();
+
+    let restricted = nm.inner().next_node_id();
+    let open = nm.inner().next_node_id();
+    for (id, permissions) in [
+        (&restricted, Some(PermissionType::ReceiveEvents)),
+        (&open, None),
+    ] {
+        let mut builder = ObjectBuilder::new(id, "Source", "Source");
+        if let Some(permissions) = permissions {
+            builder = builder.role_permissions(vec![RolePermissionType {
+                role_id: operator.clone(),
+                permissions,
+            }]);
+        }
+        nm.inner().add_node(
+            nm.address_space(),
+            tester.handle.type_tree(),
+            builder.build().into(),
+            &ObjectId::ObjectsFolder.into(),
+            &ReferenceTypeId::Organizes.into(),
+            None,
+            Vec::new(),
+        );
+    }
+
+    let (notifs, _, mut events) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+    let filter = EventFilter {
+        select_clauses: Some(vec![SimpleAttributeOperand {
+            type_definition_id: ObjectTypeId::BaseEventType.into(),
+            browse_path: Some(vec!["SourceNode".into()]),
+            attribute_id: AttributeId::Value as u32,
+            index_range: NumericRange::None,
+        }]),
+        where_clause: Default::default(),
+    };
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: ObjectId::Server.into(),
+                    attribute_id: AttributeId::EventNotifier as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    filter: ExtensionObject::from_message(filter),
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].result.status_code, StatusCode::Good);
+
+    // The anonymous session may not receive events from the restricted node.
+    for source in [&restricted, &open] {
+        let event = BaseEventType::new_now(
+            ObjectTypeId::BaseEventType,
+            ByteString::from(vec![1u8]),
+            "Event",
+        )
+        .set_source_node(source.clone());
+        let address_space = nm.address_space().read();
+        tester
+            .handle
+            .subscriptions()
+            .notify_events_from_address_space(
+                [(&event as &dyn Event, &ObjectId::Server.into())].into_iter(),
+                &address_space,
+            );
+    }
+
+    let evt = tokio::time::timeout(Duration::from_millis(1000), events.recv())
+        .await
+        .unwrap()
+        .unwrap()
+        .1
+        .unwrap();
+    assert_eq!(evt[0], Variant::from(open));
+    assert!(
+        tokio::time::timeout(Duration::from_millis(300), events.recv())
+            .await
+            .is_err()
+    );
+}
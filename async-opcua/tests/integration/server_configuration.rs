@@ -0,0 +1,443 @@
+use std::{io::Cursor, sync::Arc};
+
+use super::utils::{client_user_token, test_server, Tester};
+use opcua::{
+    client::Session,
+    crypto::{CertificateStore, SecurityPolicy, X509Data, X509},
+    types::{
+        BinaryDecodable, BinaryEncodable, ByteString, CallMethodRequest, ContextOwned,
+        MessageSecurityMode, MethodId, NodeId, ObjectId, ObjectTypeId, ReadValueId, StatusCode,
+        TimestampsToReturn, TrustListDataType, TrustListMasks, UAString, VariableId, Variant,
+    },
+};
+use opcua_core::config::Config;
+
+async fn admin_session(tester: &mut Tester) -> Arc<Session> {
+    tester
+        .connect_and_wait(
+            SecurityPolicy::Basic256Sha256,
+            MessageSecurityMode::SignAndEncrypt,
+            client_user_token(),
+        )
+        .await
+        .unwrap()
+}
+
+async fn call(
+    session: &Session,
+    object_id: impl Into<NodeId>,
+    method_id: MethodId,
+    args: Vec<Variant>,
+) -> Result<Vec<Variant>, StatusCode> {
+    let r = session
+        .call_one(CallMethodRequest {
+            object_id: object_id.into(),
+            method_id: method_id.into(),
+            input_arguments: Some(args),
+        })
+        .await
+        .unwrap();
+    if r.status_code.is_good() {
+        Ok(r.output_arguments.unwrap_or_default())
+    } else {
+        Err(r.status_code)
+    }
+}
+
+const TRUST_LIST: ObjectId =
+    ObjectId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList;
+
+async fn read_trust_list(session: &Session) -> TrustListDataType {
+    let handle = call(
+        session,
+        TRUST_LIST,
+        MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_Open,
+        vec![1u8.into()],
+    )
+    .await
+    .unwrap()[0]
+        .clone();
+    let data = call(
+        session,
+        TRUST_LIST,
+        MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_Read,
+        vec![handle.clone(), 1_000_000i32.into()],
+    )
+    .await
+    .unwrap();
+    call(
+        session,
+        TRUST_LIST,
+        MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_Close,
+        vec![handle],
+    )
+    .await
+    .unwrap();
+    let Variant::ByteString(data) = &data[0] else {
+        panic!("Expected byte string, got {:?}", data[0]);
+    };
+    let ctx = ContextOwned::default();
+    TrustListDataType::decode(&mut Cursor::new(data.as_ref()), &ctx.context()).unwrap()
+}
+
+fn make_cert() -> X509 {
+    let mut data = X509Data::sample_cert();
+    data.key_size = 1024;
+    X509::cert_and_pkey(&data).unwrap().0
+}
+
+#[tokio::test]
+async fn requires_encrypted_admin_session() {
+    let mut tester = Tester::new(test_server(), false).await;
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    session.wait_for_connection().await;
+
+    let r = call(
+        &session,
+        ObjectId::ServerConfiguration,
+        MethodId::ServerConfiguration_GetRejectedList,
+        vec![],
+    )
+    .await;
+    assert_eq!(r, Err(StatusCode::BadUserAccessDenied));
+
+    let session = tester
+        .connect_and_wait(
+            SecurityPolicy::None,
+            MessageSecurityMode::None,
+            client_user_token(),
+        )
+        .await
+        .unwrap();
+    let r = call(
+        &session,
+        ObjectId::ServerConfiguration,
+        MethodId::ServerConfiguration_GetRejectedList,
+        vec![],
+    )
+    .await;
+    assert_eq!(r, Err(StatusCode::BadSecurityModeInsufficient));
+
+    let session = admin_session(&mut tester).await;
+    let r = call(
+        &session,
+        ObjectId::ServerConfiguration,
+        MethodId::ServerConfiguration_GetRejectedList,
+        vec![],
+    )
+    .await
+    .unwrap();
+    assert_eq!(r.len(), 1);
+}
+
+#[tokio::test]
+async fn update_trust_list() {
+    let mut tester = Tester::new(test_server(), false).await;
+    let session = admin_session(&mut tester).await;
+    let cert = make_cert();
+
+    call(
+        &session,
+        TRUST_LIST,
+        MethodId::TrustListType_AddCertificate,
+        vec![cert.as_byte_string().into(), true.into()],
+    )
+    .await
+    .unwrap();
+    let trust_list = read_trust_list(&session).await;
+    assert_eq!(trust_list.specified_lists, TrustListMasks::All as u32);
+    assert!(trust_list
+        .trusted_certificates
+        .unwrap()
+        .contains(&cert.as_byte_string()));
+
+    // Non-CA certificates cannot be added as issuers.
+    let r = call(
+        &session,
+        TRUST_LIST,
+        MethodId::TrustListType_AddCertificate,
+        vec![cert.as_byte_string().into(), false.into()],
+    )
+    .await;
+    assert_eq!(r, Err(StatusCode::BadCertificateInvalid));
+
+    call(
+        &session,
+        TRUST_LIST,
+        MethodId::TrustListType_RemoveCertificate,
+        vec![
+            UAString::from(cert.thumbprint().as_hex_string()).into(),
+            true.into(),
+        ],
+    )
+    .await
+    .unwrap();
+    let trust_list = read_trust_list(&session).await;
+    assert!(!trust_list
+        .trusted_certificates
+        .unwrap()
+        .contains(&cert.as_byte_string()));
+
+    // Replace the trusted certificates by writing the trust list file.
+    let handle = call(
+        &session,
+        TRUST_LIST,
+        MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_Open,
+        vec![6u8.into()],
+    )
+    .await
+    .unwrap()[0]
+        .clone();
+    // The trust list cannot be opened again while it is being written.
+    let r = call(
+        &session,
+        TRUST_LIST,
+        MethodId::TrustListType_OpenWithMasks,
+        vec![1u32.into()],
+    )
+    .await;
+    assert_eq!(r, Err(StatusCode::BadInvalidState));
+
+    let new_list = TrustListDataType {
+        specified_lists: TrustListMasks::TrustedCertificates as u32,
+        trusted_certificates: Some(vec![cert.as_byte_string()]),
+        ..Default::default()
+    };
+    let data = new_list.encode_to_vec(&ContextOwned::default().context());
+    call(
+        &session,
+        TRUST_LIST,
+        MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_Write,
+        vec![handle.clone(), ByteString::from(data).into()],
+    )
+    .await
+    .unwrap();
+    let r = call(
+        &session,
+        TRUST_LIST,
+        MethodId::TrustListType_CloseAndUpdate,
+        vec![handle],
+    )
+    .await
+    .unwrap();
+    assert_eq!(r, vec![Variant::from(false)]);
+
+    let trust_list = read_trust_list(&session).await;
+    assert_eq!(
+        trust_list.trusted_certificates,
+        Some(vec![cert.as_byte_string()])
+    );
+    let store = CertificateStore::new(&tester.handle.info().config.pki_dir);
+    assert_eq!(
+        std::fs::read_dir(store.trusted_certs_dir())
+            .unwrap()
+            .count(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn trust_list_size_limit() {
+    let mut server = test_server();
+    server.limits_mut().max_trust_list_size = 1000;
+    let mut tester = Tester::new(server, false).await;
+    let session = admin_session(&mut tester).await;
+
+    let max_size = session
+        .read(
+            &[ReadValueId::from(NodeId::from(
+                VariableId::ServerConfiguration_MaxTrustListSize,
+            ))],
+            TimestampsToReturn::Neither,
+            0.0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(max_size[0].value, Some(Variant::UInt32(1000)));
+
+    let handle = call(
+        &session,
+        TRUST_LIST,
+        MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_Open,
+        vec![6u8.into()],
+    )
+    .await
+    .unwrap()[0]
+        .clone();
+    call(
+        &session,
+        TRUST_LIST,
+        MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_Write,
+        vec![handle.clone(), ByteString::from(vec![0u8; 600]).into()],
+    )
+    .await
+    .unwrap();
+    // The second write would make the file larger than the limit.
+    let r = call(
+        &session,
+        TRUST_LIST,
+        MethodId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup_TrustList_Write,
+        vec![handle.clone(), ByteString::from(vec![0u8; 600]).into()],
+    )
+    .await;
+    assert_eq!(r, Err(StatusCode::BadEncodingLimitsExceeded));
+}
+
+#[tokio::test]
+async fn update_certificate() {
+    let mut tester = Tester::new(test_server(), false).await;
+    let session = admin_session(&mut tester).await;
+
+    let r = call(
+        &session,
+        ObjectId::ServerConfiguration,
+        MethodId::ServerConfiguration_CreateSigningRequest,
+        vec![
+            NodeId::null().into(),
+            NodeId::null().into(),
+            UAString::null().into(),
+            false.into(),
+            ByteString::null().into(),
+        ],
+    )
+    .await
+    .unwrap();
+    let Variant::ByteString(request) = &r[0] else {
+        panic!("Expected byte string, got {:?}", r[0]);
+    };
+    assert!(!request.is_empty());
+
+    // Sign a new certificate with the existing key, as a CA would.
+    let info = tester.handle.info().clone();
+    let pkey = info.server_keypair.load().pkey.clone().unwrap();
+    let mut data: X509Data = info.config.application_description().into();
+    data.certificate_duration_days = 30;
+    let cert = X509::from_pkey(&pkey, &data).unwrap();
+    assert_ne!(
+        info.server_keypair.load().certificate.as_deref(),
+        Some(&cert)
+    );
+
+    let r = call(
+        &session,
+        ObjectId::ServerConfiguration,
+        MethodId::ServerConfiguration_UpdateCertificate,
+        vec![
+            NodeId::from(ObjectId::ServerConfiguration_CertificateGroups_DefaultApplicationGroup)
+                .into(),
+            NodeId::null().into(),
+            cert.as_byte_string().into(),
+            Variant::from(Vec::<ByteString>::new()),
+            UAString::null().into(),
+            ByteString::null().into(),
+        ],
+    )
+    .await
+    .unwrap();
+    assert_eq!(r, vec![Variant::from(false)]);
+    call(
+        &session,
+        ObjectId::ServerConfiguration,
+        MethodId::ServerConfiguration_ApplyChanges,
+        vec![],
+    )
+    .await
+    .unwrap();
+
+    // The new certificate is used immediately, and stored for restarts.
+    let keypair = info.server_keypair.load();
+    assert_eq!(keypair.certificate.as_deref(), Some(&cert));
+    assert!(keypair.pkey.as_ref().is_some_and(|k| Arc::ptr_eq(k, &pkey)));
+    let store = CertificateStore::new(&info.config.pki_dir);
+    assert_eq!(
+        CertificateStore::read_cert(&store.own_certificate_path()).unwrap(),
+        cert
+    );
+
+    // New connections use the new certificate. The ECC endpoints have their own certificates.
+    let session = admin_session(&mut tester).await;
+    let endpoints = tester
+        .client
+        .get_server_endpoints_from_url(tester.endpoint())
+        .await
+        .unwrap();
+    assert!(endpoints
+        .iter()
+        .filter(|e| !SecurityPolicy::from_uri(e.security_policy_uri.as_ref()).is_ecc())
+        .all(|e| e.server_certificate == cert.as_byte_string()));
+
+    // A certificate for a different key is rejected.
+    let update = |cert: &X509, issuers: Vec<ByteString>| {
+        call(
+            &session,
+            ObjectId::ServerConfiguration,
+            MethodId::ServerConfiguration_UpdateCertificate,
+            vec![
+                NodeId::null().into(),
+                NodeId::null().into(),
+                cert.as_byte_string().into(),
+                Variant::from(issuers),
+                UAString::null().into(),
+                ByteString::null().into(),
+            ],
+        )
+    };
+    let other = make_cert();
+    let r = update(&other, Vec::new()).await;
+    assert_eq!(r, Err(StatusCode::BadSecurityChecksFailed));
+
+    // So is a certificate for a different application URI.
+    let mut description = info.config.application_description();
+    description.application_uri = "urn:OtherApplication".into();
+    let other = X509::from_pkey(&pkey, &description.into()).unwrap();
+    let r = update(&other, Vec::new()).await;
+    assert_eq!(r, Err(StatusCode::BadCertificateUriInvalid));
+
+    // And a certificate that does not chain to a valid root, here because the
+    // self-signed signature is broken.
+    let mut der = X509::from_pkey(&pkey, &data).unwrap().to_der().unwrap();
+    *der.last_mut().unwrap() ^= 1;
+    let other = X509::from_der(&der).unwrap();
+    let r = update(&other, Vec::new()).await;
+    assert_eq!(r, Err(StatusCode::BadCertificateInvalid));
+
+    // Issuer certificates must be certificate authorities.
+    let r = update(&cert, vec![make_cert().as_byte_string()]).await;
+    assert_eq!(r, Err(StatusCode::BadCertificateInvalid));
+
+    // None of the rejected certificates replaced the current one.
+    assert_eq!(
+        info.server_keypair.load().certificate.as_deref(),
+        Some(&cert)
+    );
+
+    // The ECC certificates cannot be managed through the server configuration.
+    for (method, args) in [
+        (
+            MethodId::ServerConfiguration_UpdateCertificate,
+            vec![
+                cert.as_byte_string().into(),
+                Variant::from(Vec::<ByteString>::new()),
+                UAString::null().into(),
+                ByteString::null().into(),
+            ],
+        ),
+        (
+            MethodId::ServerConfiguration_CreateSigningRequest,
+            vec![
+                UAString::null().into(),
+                false.into(),
+                ByteString::null().into(),
+            ],
+        ),
+    ] {
+        let mut inputs = vec![
+            NodeId::null().into(),
+            NodeId::from(ObjectTypeId::EccNistP256ApplicationCertificateType).into(),
+        ];
+        inputs.extend(args);
+        let r = call(&session, ObjectId::ServerConfiguration, method, inputs).await;
+        assert_eq!(r, Err(StatusCode::BadNotSupported));
+    }
+}
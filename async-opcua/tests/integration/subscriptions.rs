@@ -15,11 +15,12 @@ use opcua_client::{
     services::{
         CreateMonitoredItems, CreateSubscription, Publish, Republish, TransferSubscriptions,
     },
-    IdentityToken, Subscription, UARequest,
+    IdentityToken, OperationLimits, Subscription, UARequest,
 };
 use opcua_crypto::SecurityPolicy;
 use opcua_types::{
-    DataChangeFilter, DataChangeTrigger, DeadbandType, ExtensionObject, MessageSecurityMode, Range,
+    AggregateConfiguration, AggregateFilter, AggregateFilterResult, DataChangeFilter,
+    DataChangeTrigger, DateTime, DeadbandType, ExtensionObject, MessageSecurityMode, Range,
 };
 use tokio::{sync::mpsc::UnboundedReceiver, time::timeout};
 
@@ -419,6 +420,9 @@ async fn subscription_limits() {
         .unwrap_err();
     assert_eq!(StatusCode::BadNothingToDo, e);
 
+    // Disable request splitting in the client, so that the request reaches the server.
+    session.set_operation_limits(OperationLimits::default());
+
     // Create too many
     let e = session
         .create_monitored_items(
@@ -796,6 +800,106 @@ async fn test_data_change_filters() {
     assert_eq!(v.value.unwrap(), Variant::Double(9.0));
 }
 
+#[tokio::test]
+async fn test_aggregate_filter() {
+    let (tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .value(1.0f64)
+            .data_type(DataTypeId::Double)
+            .access_level(AccessLevel::CURRENT_READ)
+            .user_access_level(AccessLevel::CURRENT_READ)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    let (notifs, mut data, _) = ChannelNotifications::new();
+
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+
+    let make_request = |aggregate: ObjectId| MonitoredItemCreateRequest {
+        item_to_monitor: ReadValueId {
+            node_id: id.clone(),
+            attribute_id: AttributeId::Value as u32,
+            ..Default::default()
+        },
+        monitoring_mode: opcua::types::MonitoringMode::Reporting,
+        requested_parameters: MonitoringParameters {
+            sampling_interval: 0.0,
+            queue_size: 10,
+            discard_oldest: true,
+            filter: ExtensionObject::from_message(AggregateFilter {
+                start_time: DateTime::now(),
+                aggregate_type: aggregate.into(),
+                processing_interval: 200.0,
+                aggregate_configuration: AggregateConfiguration {
+                    use_server_capabilities_defaults: true,
+                    ..Default::default()
+                },
+            }),
+            ..Default::default()
+        },
+    };
+
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![
+                make_request(ObjectId::AggregateFunction_Maximum),
+                make_request(ObjectId::AggregateFunction_StandardDeviationSample),
+            ],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.len(), 2);
+    assert_eq!(res[0].result.status_code, StatusCode::Good);
+    let filter_res = res[0]
+        .result
+        .filter_result
+        .inner_as::<AggregateFilterResult>()
+        .unwrap();
+    assert_eq!(filter_res.revised_processing_interval, 200.0);
+    assert_eq!(
+        res[1].result.status_code,
+        StatusCode::BadAggregateNotSupported
+    );
+
+    nm.set_value(
+        tester.handle.subscriptions(),
+        &id,
+        None,
+        DataValue::new_now(5.0),
+    )
+    .unwrap();
+
+    // Once the processing interval is complete we get the aggregated value.
+    // If the value was set just after an interval ended, that interval has no data.
+    let v = timeout(Duration::from_millis(1000), async {
+        loop {
+            let (r, v) = data.recv().await.unwrap();
+            assert_eq!(r.node_id, id);
+            if v.status() != StatusCode::BadNoData {
+                break v;
+            }
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(v.value.unwrap(), Variant::Double(5.0));
+}
+
 #[tokio::test]
 async fn test_manual_republish() {
     let (tester, nm, session) = setup().await;
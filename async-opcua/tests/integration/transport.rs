@@ -0,0 +1,115 @@
+use std::{sync::Arc, time::Duration};
+
+use super::utils::{client_user_token, test_server, Tester};
+use opcua::{
+    client::{transport::StreamConnector, IdentityToken, Session},
+    crypto::SecurityPolicy,
+    server::transport::DuplexListener,
+    types::{
+        AttributeId, MessageSecurityMode, ReadValueId, TimestampsToReturn, VariableId, Variant,
+    },
+};
+
+async fn connect(
+    tester: &Tester,
+    connector: StreamConnector,
+    security_policy: SecurityPolicy,
+    security_mode: MessageSecurityMode,
+    user_identity: IdentityToken,
+) -> Arc<Session> {
+    // The client cannot fetch endpoints over the custom transport,
+    // so get them from the server directly.
+    let url = tester.endpoint();
+    let endpoints = tester
+        .handle
+        .info()
+        .endpoints(&url.as_str().into(), &None)
+        .unwrap();
+    let (session, event_loop) = tester
+        .client
+        .session_builder()
+        .with_endpoints(endpoints)
+        .connector(connector)
+        .user_identity_token(user_identity)
+        .connect_to_matching_endpoint((url.as_str(), security_policy.to_str(), security_mode))
+        .unwrap()
+        .build(tester.client.certificate_store().clone());
+    event_loop.spawn();
+    tokio::time::timeout(Duration::from_secs(20), session.wait_for_connection())
+        .await
+        .unwrap();
+    session
+}
+
+async fn read_namespace_array(session: &Session) {
+    let value = session
+        .read(
+            &[ReadValueId {
+                node_id: VariableId::Server_NamespaceArray.into(),
+                attribute_id: AttributeId::Value as u32,
+                ..Default::default()
+            }],
+            TimestampsToReturn::Neither,
+            0.0,
+        )
+        .await
+        .unwrap()
+        .remove(0);
+    assert!(matches!(value.value, Some(Variant::Array(_))));
+}
+
+#[tokio::test]
+async fn duplex_connect() {
+    let (listener, handle) = DuplexListener::new(64 * 1024);
+    let tester = Tester::new_with_listener(test_server(), listener).await;
+
+    let connector = || {
+        let handle = handle.clone();
+        StreamConnector::new(move || std::future::ready(handle.connect()))
+    };
+
+    let session = connect(
+        &tester,
+        connector(),
+        SecurityPolicy::None,
+        MessageSecurityMode::None,
+        IdentityToken::Anonymous,
+    )
+    .await;
+    read_namespace_array(&session).await;
+    session.disconnect().await.unwrap();
+
+    let session = connect(
+        &tester,
+        connector(),
+        SecurityPolicy::Basic256Sha256,
+        MessageSecurityMode::SignAndEncrypt,
+        client_user_token(),
+    )
+    .await;
+    read_namespace_array(&session).await;
+    session.disconnect().await.unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn unix_socket_connect() {
+    let path = std::env::temp_dir().join(format!("opcua-test-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = tokio::net::UnixListener::bind(&path).unwrap();
+    let tester = Tester::new_with_listener(test_server(), listener).await;
+
+    let session = connect(
+        &tester,
+        StreamConnector::unix(&path),
+        SecurityPolicy::Basic256Sha256,
+        MessageSecurityMode::Sign,
+        IdentityToken::Anonymous,
+    )
+    .await;
+    read_namespace_array(&session).await;
+    session.disconnect().await.unwrap();
+
+    drop(tester);
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use super::utils::{hostname, test_server, Tester};
+use opcua::{
+    client::IdentityToken,
+    crypto::SecurityPolicy,
+    server::{EndpointTransport, ServerEndpoint, ANONYMOUS_USER_TOKEN_ID},
+    types::{
+        profiles, AttributeId, MessageSecurityMode, ReadValueId, TimestampsToReturn, VariableId,
+        Variant,
+    },
+};
+
+async fn websocket_tester() -> Tester {
+    let user_token_ids = [ANONYMOUS_USER_TOKEN_ID.to_owned()];
+    let server = test_server()
+        .add_endpoint(
+            "ws_none",
+            ServerEndpoint::new_none("/", &user_token_ids)
+                .with_transport(EndpointTransport::WebSocket),
+        )
+        .add_endpoint(
+            "wss_basic256sha256_sign_encrypt",
+            ServerEndpoint::new_basic256sha256_sign_encrypt("/", &user_token_ids)
+                .with_transport(EndpointTransport::WebSocketSecure),
+        );
+    Tester::new(server, false).await
+}
+
+async fn connect_and_read(
+    tester: &mut Tester,
+    url: &str,
+    security_policy: SecurityPolicy,
+    security_mode: MessageSecurityMode,
+) {
+    let (session, event_loop) = tester
+        .client
+        .connect_to_matching_endpoint(
+            (url, security_policy.to_str(), security_mode),
+            IdentityToken::Anonymous,
+        )
+        .await
+        .unwrap();
+    event_loop.spawn();
+    tokio::time::timeout(Duration::from_secs(20), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    let value = session
+        .read(
+            &[ReadValueId {
+                node_id: VariableId::Server_NamespaceArray.into(),
+                attribute_id: AttributeId::Value as u32,
+                ..Default::default()
+            }],
+            TimestampsToReturn::Neither,
+            0.0,
+        )
+        .await
+        .unwrap()
+        .remove(0);
+    assert!(matches!(value.value, Some(Variant::Array(_))));
+    session.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn websocket_connect() {
+    let mut tester = websocket_tester().await;
+    let url = format!("opc.ws://{}:{}/", hostname(), tester.addr.port());
+    connect_and_read(
+        &mut tester,
+        &url,
+        SecurityPolicy::None,
+        MessageSecurityMode::None,
+    )
+    .await;
+
+    // opc.tcp still works on the same port.
+    let url = tester.endpoint();
+    connect_and_read(
+        &mut tester,
+        &url,
+        SecurityPolicy::None,
+        MessageSecurityMode::None,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn websocket_secure_connect() {
+    let mut tester = websocket_tester().await;
+    let url = format!("opc.wss://{}:{}/", hostname(), tester.addr.port());
+    connect_and_read(
+        &mut tester,
+        &url,
+        SecurityPolicy::Basic256Sha256,
+        MessageSecurityMode::SignAndEncrypt,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn websocket_endpoints() {
+    let tester = websocket_tester().await;
+    let url = format!("opc.ws://{}:{}/", hostname(), tester.addr.port());
+
+    let endpoints = tester
+        .client
+        .get_endpoints(&url, &[], &[profiles::TRANSPORT_PROFILE_URI_WSS_BINARY])
+        .await
+        .unwrap();
+    assert_eq!(endpoints.len(), 2);
+    for endpoint in &endpoints {
+        assert_eq!(
+            endpoint.transport_profile_uri.as_ref(),
+            profiles::TRANSPORT_PROFILE_URI_WSS_BINARY
+        );
+    }
+    assert!(endpoints
+        .iter()
+        .any(|e| e.endpoint_url.as_ref().starts_with("opc.wss://")));
+
+    let endpoints = tester
+        .client
+        .get_endpoints(&url, &[], &[profiles::TRANSPORT_PROFILE_URI_BINARY])
+        .await
+        .unwrap();
+    assert!(endpoints
+        .iter()
+        .all(|e| e.endpoint_url.as_ref().starts_with("opc.tcp://")));
+}
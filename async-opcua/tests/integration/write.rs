@@ -1,6 +1,6 @@
 use chrono::TimeDelta;
 use opcua::{
-    client::{HistoryReadAction, HistoryUpdateAction, Session},
+    client::{HistoryReadAction, HistoryUpdateAction, OperationLimits, Session},
     server::address_space::{
         AccessLevel, DataTypeBuilder, EventNotifier, MethodBuilder, NodeType, ObjectBuilder,
         ObjectTypeBuilder, ReferenceTypeBuilder, VariableBuilder, VariableTypeBuilder, ViewBuilder,
@@ -554,6 +554,9 @@ async fn write_limits() {
     let r = session.write(&[]).await.unwrap_err();
     assert_eq!(r, StatusCode::BadNothingToDo);
 
+    // Disable request splitting in the client, so that the request reaches the server.
+    session.set_operation_limits(OperationLimits::default());
+
     // Too many operations
     let ops: Vec<_> = (0..(write_limit + 1))
         .map(|r| write_value(AttributeId::Value, 123, NodeId::new(2, r as i32)))
@@ -3,6 +3,7 @@ mod tester;
 
 pub const CLIENT_USERPASS_ID: &str = "sample1";
 pub const CLIENT_X509_ID: &str = "x509";
+pub const CLIENT_APPLICATION_URI: &str = "x";
 
 pub use node_manager::*;
 use opcua::types::{AttributeId, DataValue, NodeId, ReadValueId, Variant};
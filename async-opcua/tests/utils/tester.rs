@@ -12,7 +12,9 @@ use std::{
 use opcua::{
     client::{Client, ClientBuilder, IdentityToken, Session, SessionEventLoop},
     crypto::SecurityPolicy,
-    server::{ServerBuilder, ServerHandle, ServerUserToken, ANONYMOUS_USER_TOKEN_ID},
+    server::{
+        transport::Listener, ServerBuilder, ServerHandle, ServerUserToken, ANONYMOUS_USER_TOKEN_ID,
+    },
     types::{MessageSecurityMode, StatusCode},
 };
 use opcua_core::config::Config;
@@ -21,7 +23,9 @@ use opcua_types::ApplicationDescription;
 use tokio::net::TcpListener;
 use tokio_util::sync::{CancellationToken, DropGuard};
 
-use super::{test_node_manager, TestNodeManager, CLIENT_USERPASS_ID, CLIENT_X509_ID};
+use super::{
+    test_node_manager, TestNodeManager, CLIENT_APPLICATION_URI, CLIENT_USERPASS_ID, CLIENT_X509_ID,
+};
 
 pub struct Tester {
     pub handle: ServerHandle,
@@ -95,7 +99,8 @@ pub fn default_server() -> ServerBuilder {
                 CLIENT_USERPASS_ID,
                 &format!("{CLIENT_USERPASS_ID}_password"),
             )
-            .read_diagnostics(true),
+            .read_diagnostics(true)
+            .manage_certificates(true),
         )
         .add_user_token(
             CLIENT_X509_ID,
@@ -201,6 +206,34 @@ pub fn default_server() -> ServerBuilder {
             ),
         );
 
+    for (name, policy) in [
+        ("ecc_nistp256", SecurityPolicy::EccNistP256),
+        ("ecc_nistp384", SecurityPolicy::EccNistP384),
+        #[cfg(feature = "brainpool")]
+        ("ecc_brainpoolp256r1", SecurityPolicy::EccBrainpoolP256r1),
+        ("ecc_curve25519", SecurityPolicy::EccCurve25519),
+    ] {
+        builder = builder
+            .add_endpoint(
+                format!("{name}_sign"),
+                (
+                    endpoint_path,
+                    policy,
+                    MessageSecurityMode::Sign,
+                    &user_token_ids as &[&str],
+                ),
+            )
+            .add_endpoint(
+                format!("{name}_sign_encrypt"),
+                (
+                    endpoint_path,
+                    policy,
+                    MessageSecurityMode::SignAndEncrypt,
+                    &user_token_ids as &[&str],
+                ),
+            );
+    }
+
     let limits = builder.limits_mut();
     limits.max_message_size = 1024 * 1024 * 64;
     limits.max_array_length = 100_000;
@@ -213,7 +246,7 @@ pub fn default_server() -> ServerBuilder {
 pub fn default_client(test_id: u16, quick_timeout: bool) -> ClientBuilder {
     let client = ClientBuilder::new()
         .application_name("integration_client")
-        .application_uri("x")
+        .application_uri(CLIENT_APPLICATION_URI)
         .pki_dir(format!("./pki-client/{test_id}"))
         .create_sample_keypair(true)
         .trust_server_certs(true)
@@ -248,8 +281,15 @@ pub fn copy_shared_certs(test_id: u16, desc: &ApplicationDescription) {
             Path::new("certs/server/private.pem"),
         )
         .unwrap();
+        // The server checks that the client certificate matches the application URI
+        // of the client, so it needs its own description.
+        let client_desc = ApplicationDescription {
+            application_uri: CLIENT_APPLICATION_URI.into(),
+            application_name: "integration_client".into(),
+            ..desc.clone()
+        };
         CertificateStore::create_certificate_and_key(
-            &desc.clone().into(),
+            &client_desc.into(),
             true,
             Path::new("certs/client/cert.der"),
             Path::new("certs/client/private.pem"),
@@ -350,6 +390,36 @@ impl Tester {
         }
     }
 
+    /// Start the server on a custom listener. The configured port is used in
+    /// endpoint URLs, since the listener may not have one.
+    #[allow(unused)]
+    pub async fn new_with_listener(server: ServerBuilder, listener: impl Listener) -> Self {
+        let _ = env_logger::try_init();
+
+        let test_id = TEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let port = server.config().tcp_config.port;
+        let addr = SocketAddr::from(([127, 0, 0, 1], port));
+        let server = server
+            .pki_dir(format!("./pki-server/{test_id}"))
+            .discovery_urls(vec![format!("opc.tcp://{}:{}", hostname(), port)]);
+
+        copy_shared_certs(test_id, &server.config().application_description());
+
+        let (server, handle) = server.build().unwrap();
+
+        tokio::task::spawn(server.run_with(listener));
+
+        let client = default_client(test_id, false).client().unwrap();
+
+        Self {
+            _guard: handle.token().clone().drop_guard(),
+            handle,
+            client,
+            addr,
+            test_id,
+        }
+    }
+
     #[allow(unused)]
     pub async fn new_custom_client(server: ServerBuilder, client: ClientBuilder) -> Self {
         let _ = env_logger::try_init();
@@ -221,10 +221,13 @@ fn raise_machine_cycled_event(
     let now = DateTime::now();
     let event = MachineCycledEventType::new(&machine_name, ns, source_machine_id, now);
 
-    // create an event object in a folder with the
-
-    subscriptions.notify_events(
+    // Report the event from the address space containing the machine, so that clients can
+    // filter on the machine with `InView` and `RelatedTo`, and role permissions on the
+    // machine are respected.
+    let address_space = manager.address_space().read();
+    subscriptions.notify_events_from_address_space(
         [(&event as &dyn opcua::nodes::Event, &ObjectId::Server.into())].into_iter(),
+        &address_space,
     );
 }
 
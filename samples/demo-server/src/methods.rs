@@ -101,4 +101,20 @@ pub fn add_methods(manager: Arc<SimpleNodeManager>, ns: u16) {
         };
         Ok(Vec::new())
     });
+
+    // Add has 2 inputs and 1 output, with arguments decoded automatically
+    manager.inner().add_typed_method(
+        &mut address_space,
+        MethodBuilder::new(&NodeId::new(ns, "Add"), "Add", "Add")
+            .component_of(object_id.clone())
+            .executable(true)
+            .user_executable(true),
+        &["A", "B"],
+        &["Sum"],
+        |a: i32, b: i32| async move {
+            a.checked_add(b)
+                .map(|s| (s,))
+                .ok_or(StatusCode::BadOutOfRange)
+        },
+    );
 }